@@ -7,6 +7,8 @@ use ringhopper::primitives::engine::Engine;
 use ringhopper::primitives::tag::ParseStrictness;
 use ringhopper::tag::tree::VirtualTagsDirectory;
 use ringhopper_engines::ALL_SUPPORTED_ENGINES;
+use toml::Value;
+use super::project::{Project, VerbProject};
 use super::util::get_tty_metadata;
 
 pub struct CommandLineParser {
@@ -17,7 +19,8 @@ pub struct CommandLineParser {
     extra_parameters: Vec<String>,
     required_extra_parameters: usize,
     on_help: fn(&CommandLineParser) -> Result<(), String>,
-    strictness: ParseStrictness
+    strictness: ParseStrictness,
+    project: Option<VerbProject>
 }
 
 #[derive(Clone)]
@@ -54,7 +57,8 @@ impl CommandLineParser {
             standard_parameters: HashMap::new(),
             custom_parameters: HashMap::new(),
             extra_parameters: Vec::new(),
-            strictness: ParseStrictness::Strict
+            strictness: ParseStrictness::Strict,
+            project: None
         }
     }
 
@@ -164,6 +168,12 @@ impl CommandLineParser {
                 },
             }
 
+            // Errors are reported when parsing instead, since this is just a hint.
+            if let Some(Ok(Some(project))) = parser.project.as_ref().map(VerbProject::load) {
+                println!();
+                println!("Parameters that are not passed are read from {} if set there.", project.path.display());
+            }
+
             println!();
             std::process::exit(0);
         })
@@ -350,6 +360,39 @@ impl CommandLineParser {
         self
     }
 
+    pub fn add_diagnostics_output(mut self) -> Self {
+        let format = Parameter {
+            values: None,
+            name: "format",
+            short: Some('F'),
+            description: "Set the output format for issues found: `text`, `json`, or `sarif`. Default: `text`",
+            default_values: Some(vec![CommandLineValue::String("text".to_owned())]),
+            value_type: Some(CommandLineValueType::String),
+            required: false,
+            value_count: 1,
+            usage: "<format>",
+            multiple: false,
+        };
+        let suppress = Parameter {
+            values: None,
+            name: "suppress",
+            short: Some('S'),
+            description: "Suppress issues with the given code(s), separated by commas (e.g. `BMP003,IDX001`). This can be used multiple times.",
+            default_values: Some(vec![]),
+            value_type: Some(CommandLineValueType::String),
+            required: false,
+            value_count: 1,
+            usage: "<codes>",
+            multiple: true,
+        };
+
+        assert!(self.standard_parameters.get(&StandardParameterType::OutputFormat).is_none());
+        assert!(self.standard_parameters.get(&StandardParameterType::Suppress).is_none());
+        self.standard_parameters.insert(StandardParameterType::OutputFormat, format);
+        self.standard_parameters.insert(StandardParameterType::Suppress, suppress);
+        self
+    }
+
     pub fn add_custom_parameter(mut self, parameter: Parameter) -> Self {
         assert!(parameter.name != "help" && parameter.short != Some('h'));
         assert!(
@@ -368,6 +411,12 @@ impl CommandLineParser {
         self
     }
 
+    /// Use a project file for any parameters not passed.
+    pub fn set_project(mut self, project: Option<VerbProject>) -> Self {
+        self.project = project;
+        self
+    }
+
     /// Testing only!
     fn parse_strs(self, args: &'static [&'static str]) -> Result<CommandLineArgs, String> {
         self.parse(args.iter().map(<&str>::to_string))
@@ -392,23 +441,7 @@ impl CommandLineParser {
                         Some(n) => n,
                         None => return Err(format!("Argument parse error: Not enough arguments for --{} passed; need {} more", p.name, p.value_count - i))
                     };
-                    let parsed_argument = match p.value_type.expect("value type not set for something that takes arguments") {
-                        CommandLineValueType::Path => CommandLineValue::Path(next_argument.into()),
-                        CommandLineValueType::String => CommandLineValue::String(next_argument),
-                        CommandLineValueType::Float => CommandLineValue::Float(next_argument.parse().map_err(|e| format!("Argument parse error: Cannot convert `{next_argument}` into float: {e}"))?),
-                        CommandLineValueType::Short => CommandLineValue::Short(next_argument.parse().map_err(|e| format!("Argument parse error: Cannot convert `{next_argument}` into short: {e}"))?),
-                        CommandLineValueType::UShort => CommandLineValue::UShort(next_argument.parse().map_err(|e| format!("Argument parse error: Cannot convert `{next_argument}` into ushort: {e}"))?),
-                        CommandLineValueType::Integer => CommandLineValue::Integer(next_argument.parse().map_err(|e| format!("Argument parse error: Cannot convert `{next_argument}` into int: {e}"))?),
-                        CommandLineValueType::UInteger => CommandLineValue::UInteger(next_argument.parse().map_err(|e| format!("Argument parse error: Cannot convert `{next_argument}` into uint: {e}"))?),
-                        CommandLineValueType::Engine => CommandLineValue::Engine({
-                            let engine = &ALL_SUPPORTED_ENGINES[ALL_SUPPORTED_ENGINES.binary_search_by(|engine| engine.name.cmp(&next_argument)).map_err(|_| format!("Argument parse error: `{next_argument}` is not a valid engine. Use the list-engines verb for valid engines."))?];
-                            if !engine.build_target {
-                                return Err(format!("Argument parse error: `{}` is not an engine target and cannot be used with this argument. Use the list-engines verb for valid engines.", engine.name))
-                            }
-                            engine
-                        })
-                    };
-                    values.push(parsed_argument);
+                    values.push(parse_value(p, next_argument)?);
                 }
                 if p.multiple {
                     if let Some(n) = &mut p.values {
@@ -466,6 +499,12 @@ impl CommandLineParser {
             return Err(format!("Argument parse error: Expected {} extra argument(s), got {} instead", self.required_extra_parameters, self.extra_parameters.len()))
         }
 
+        if let Some(project) = self.project.take() {
+            if let Some(loaded) = project.load()? {
+                self.apply_project(project.verb, &loaded)?;
+            }
+        }
+
         for i in all_args_mut!(self) {
             if i.default_values.is_some() && i.values.is_none() {
                 i.values = std::mem::take(&mut i.default_values);
@@ -494,6 +533,11 @@ impl CommandLineParser {
         require_exists(StandardParameterType::Maps)?;
         require_exists(StandardParameterType::CowTags)?;
 
+        if let Some(n) = self.standard_parameters.get(&StandardParameterType::OutputFormat) {
+            let format = n.values.as_ref().unwrap()[0].string();
+            OutputFormat::from_name(format).ok_or_else(|| format!("Argument parse error: `{format}` is not a valid format for --{}", n.name))?;
+        }
+
         Ok(CommandLineArgs {
             custom_parameters: self.custom_parameters,
             standard_parameters: self.standard_parameters,
@@ -501,6 +545,63 @@ impl CommandLineParser {
             strictness: self.strictness
         })
     }
+
+    /// Use the project file for any parameters that were not passed.
+    fn apply_project(&mut self, verb: &str, project: &Project) -> Result<(), String> {
+        let file = project.path.display();
+
+        for name in project.verb_parameters(verb) {
+            if name == "help" || !all_args!(self).any(|p| p.name == name) {
+                return Err(format!("{file}: `{verb}` does not have a --{name} parameter"))
+            }
+        }
+
+        for p in all_args_mut!(self) {
+            if p.values.is_some() || p.name == "help" {
+                continue
+            }
+            let Some(value) = project.get(verb, p.name) else {
+                continue
+            };
+
+            if p.value_count == 0 {
+                match value {
+                    Value::Boolean(true) => p.values = Some(Vec::new()),
+                    Value::Boolean(false) => (),
+                    _ => return Err(format!("{file}: --{} must be set to `true` or `false`", p.name))
+                }
+                continue
+            }
+
+            let items = match value {
+                Value::Array(items) => items.as_slice(),
+                single => std::slice::from_ref(single)
+            };
+            if items.is_empty() {
+                continue
+            }
+            if items.len() % p.value_count != 0 || (!p.multiple && items.len() != p.value_count) {
+                return Err(format!("{file}: Wrong number of values for --{}; expected {}{}", p.name, p.value_count, if p.multiple { " per use" } else { "" }))
+            }
+
+            let mut values = Vec::with_capacity(items.len());
+            for item in items {
+                let text = match item {
+                    Value::String(s) => s.to_owned(),
+                    Value::Integer(i) => i.to_string(),
+                    Value::Float(f) => f.to_string(),
+                    _ => return Err(format!("{file}: Values for --{} must be strings or numbers", p.name))
+                };
+                values.push(match p.value_type {
+                    Some(CommandLineValueType::Path) => CommandLineValue::Path(project.root().join(text)),
+                    _ => parse_value(p, text).map_err(|e| format!("{file}: {e}"))?
+                });
+            }
+            p.values = Some(values);
+        }
+
+        Ok(())
+    }
 }
 
 impl CommandLineArgs {
@@ -612,6 +713,37 @@ impl CommandLineArgs {
             })
     }
 
+    /// Get the output format for diagnostics.
+    ///
+    /// Panics if diagnostics output was not added.
+    pub fn get_output_format(&self) -> OutputFormat {
+        let format = self.standard_parameters
+            .get(&StandardParameterType::OutputFormat)
+            .expect("format not added as standard parameter")
+            .values
+            .as_ref()
+            .expect("format should be present even if it's a default")[0]
+            .string();
+        OutputFormat::from_name(format).expect("format should have been validated when parsing")
+    }
+
+    /// Get all diagnostic codes to suppress.
+    ///
+    /// Panics if diagnostics output was not added.
+    pub fn get_suppressed(&self) -> Vec<&str> {
+        self.standard_parameters
+            .get(&StandardParameterType::Suppress)
+            .expect("suppress not added as standard parameter")
+            .values
+            .as_ref()
+            .expect("suppress should be present even if it's a default")
+            .iter()
+            .flat_map(|v| v.string().split(','))
+            .map(str::trim)
+            .filter(|c| !c.is_empty())
+            .collect()
+    }
+
     /// Get the custom parameters.
     ///
     /// Panics if not added.
@@ -625,6 +757,27 @@ impl CommandLineArgs {
     }
 }
 
+/// Parse a value for a parameter.
+fn parse_value(parameter: &Parameter, value_text: String) -> Result<CommandLineValue, String> {
+    let value = match parameter.value_type.expect("value type not set for something that takes arguments") {
+        CommandLineValueType::Path => CommandLineValue::Path(value_text.into()),
+        CommandLineValueType::String => CommandLineValue::String(value_text),
+        CommandLineValueType::Float => CommandLineValue::Float(value_text.parse().map_err(|e| format!("Argument parse error: Cannot convert `{value_text}` into float: {e}"))?),
+        CommandLineValueType::Short => CommandLineValue::Short(value_text.parse().map_err(|e| format!("Argument parse error: Cannot convert `{value_text}` into short: {e}"))?),
+        CommandLineValueType::UShort => CommandLineValue::UShort(value_text.parse().map_err(|e| format!("Argument parse error: Cannot convert `{value_text}` into ushort: {e}"))?),
+        CommandLineValueType::Integer => CommandLineValue::Integer(value_text.parse().map_err(|e| format!("Argument parse error: Cannot convert `{value_text}` into int: {e}"))?),
+        CommandLineValueType::UInteger => CommandLineValue::UInteger(value_text.parse().map_err(|e| format!("Argument parse error: Cannot convert `{value_text}` into uint: {e}"))?),
+        CommandLineValueType::Engine => CommandLineValue::Engine({
+            let engine = &ALL_SUPPORTED_ENGINES[ALL_SUPPORTED_ENGINES.binary_search_by(|engine| engine.name.cmp(&value_text)).map_err(|_| format!("Argument parse error: `{value_text}` is not a valid engine. Use the list-engines verb for valid engines."))?];
+            if !engine.build_target {
+                return Err(format!("Argument parse error: `{}` is not an engine target and cannot be used with this argument. Use the list-engines verb for valid engines.", engine.name))
+            }
+            engine
+        })
+    };
+    Ok(value)
+}
+
 #[derive(Clone, Default)]
 pub struct Parameter {
     name: &'static str,
@@ -684,6 +837,32 @@ enum StandardParameterType {
     Jobs,
     Engine,
     NoSafeguards,
+    OutputFormat,
+    Suppress,
+}
+
+/// Output format for diagnostics found when processing tags.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum OutputFormat {
+    /// Colored, human-readable text
+    Text,
+
+    /// JSON array of diagnostics
+    Json,
+
+    /// SARIF 2.1.0 log, for code scanning tools
+    Sarif
+}
+
+impl OutputFormat {
+    fn from_name(what: &str) -> Option<Self> {
+        match what {
+            "text" => Some(Self::Text),
+            "json" => Some(Self::Json),
+            "sarif" => Some(Self::Sarif),
+            _ => None
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
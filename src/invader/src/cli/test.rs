@@ -1,4 +1,5 @@
 use super::*;
+use crate::project::PROJECT_FILE_NAME;
 
 #[test]
 fn test_argument_parser_help() {
@@ -54,3 +55,100 @@ fn test_argument_custom() {
     assert!(parser_data_set2.get_custom("test-take-string").is_some_and(|t| t[0].string() == "hello world"));
     assert!(parser_data_set2.get_custom("test-take-path").is_some_and(|t| t[0].path().to_str().unwrap() == "something.txt"));
 }
+
+#[test]
+fn test_argument_diagnostics_output() {
+    let defaults = CommandLineParser::new("Test", "Test")
+        .add_diagnostics_output()
+        .parse_strs(&[]).unwrap();
+
+    assert_eq!(defaults.get_output_format(), OutputFormat::Text);
+    assert!(defaults.get_suppressed().is_empty());
+
+    let set = CommandLineParser::new("Test", "Test")
+        .add_diagnostics_output()
+        .parse_strs(&["--format", "sarif", "-S", "BMP003,IDX001", "--suppress", "RNG002"]).unwrap();
+
+    assert_eq!(set.get_output_format(), OutputFormat::Sarif);
+    assert_eq!(set.get_suppressed(), ["BMP003", "IDX001", "RNG002"]);
+
+    assert!(CommandLineParser::new("Test", "Test")
+        .add_diagnostics_output()
+        .parse_strs(&["-F", "xml"])
+        .is_err());
+}
+
+#[test]
+fn test_argument_project() {
+    let project = Project::parse(r#"
+        tags = ["src", "src/verb"]
+        jobs = 3
+
+        [verbs.test]
+        format = "json"
+        overwrite = true
+        "#, Path::new(env!("CARGO_MANIFEST_DIR")).join("invader.toml")).unwrap();
+    let for_verb = |verb| Some(VerbProject::new(verb, project.clone()));
+
+    let parser = || CommandLineParser::new("Test", "Test")
+        .add_tags(true)
+        .add_jobs()
+        .add_overwrite()
+        .add_diagnostics_output()
+        .set_project(for_verb("test"));
+
+    let from_project = parser().parse_strs(&["-j", "5"]).unwrap();
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    assert_eq!(from_project.get_tags(), [root.join("src"), root.join("src/verb")]);
+    assert_eq!(from_project.get_jobs(), 5);
+    assert!(from_project.get_overwrite());
+    assert_eq!(from_project.get_output_format(), OutputFormat::Json);
+
+    // Passed parameters are used instead, even for parameters that can be passed multiple times
+    let overridden = parser().parse_strs(&["-t", "src", "-F", "text"]).unwrap();
+    assert_eq!(overridden.get_tags(), [Path::new("src")]);
+    assert_eq!(overridden.get_jobs(), 3);
+    assert_eq!(overridden.get_output_format(), OutputFormat::Text);
+
+    // Other verbs only get top-level values
+    let other_verb = CommandLineParser::new("Test", "Test")
+        .add_jobs()
+        .add_overwrite()
+        .set_project(for_verb("other"))
+        .parse_strs(&[]).unwrap();
+    assert_eq!(other_verb.get_jobs(), 3);
+    assert!(!other_verb.get_overwrite());
+
+    // Parameters for a verb it doesn't have are rejected
+    assert!(CommandLineParser::new("Test", "Test")
+        .add_jobs()
+        .set_project(for_verb("test"))
+        .parse_strs(&[])
+        .is_err());
+}
+
+#[test]
+fn test_argument_malformed_project() {
+    let base = std::env::temp_dir().join(format!("invader-cli-test-{}", std::process::id()));
+    std::fs::create_dir_all(&base).unwrap();
+    std::fs::write(base.join(PROJECT_FILE_NAME), "jobs =").unwrap();
+    let project = || Some(VerbProject::find_from("test", base.clone()));
+
+    // Help does not need the project file
+    let help = CommandLineParser::new("Test", "Test")
+        .add_help_with_callback(|_| Err("All good!".to_owned()))
+        .set_project(project())
+        .parse_strs(&["--help"]);
+
+    // The error is only reported once parameters are read from it
+    let parsed = CommandLineParser::new("Test", "Test")
+        .add_jobs()
+        .set_project(project())
+        .parse_strs(&[]);
+
+    std::fs::remove_dir_all(&base).unwrap();
+
+    assert_eq!(help.err().unwrap(), "All good!");
+    let error = parsed.err().unwrap();
+    assert!(error.contains(PROJECT_FILE_NAME), "{error}");
+}
@@ -7,9 +7,11 @@ extern crate windows;
 extern crate libc;
 
 use std::process::ExitCode;
+use project::VerbProject;
 use util::make_stdout_logger;
 
 mod cli;
+mod project;
 mod verb;
 mod util;
 mod threading;
@@ -30,7 +32,7 @@ fn main() -> ExitCode {
             }
             found_verb
         })
-        .map(|v| match (v.function)(args, v.description) {
+        .map(|v| match (v.function)(args, v.description, Some(VerbProject::find(v.name))) {
             Ok(_) => ExitCode::SUCCESS,
             Err(e) => {
                 make_stdout_logger().error_fmt_ln(format_args!("Error executing {}: {e}", v.name));
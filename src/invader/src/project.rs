@@ -0,0 +1,147 @@
+use std::path::{Path, PathBuf};
+use toml::{Table, Value};
+
+#[cfg(test)]
+mod test;
+
+/// Name of the project file searched for in the working directory and its parents.
+pub const PROJECT_FILE_NAME: &str = "invader.toml";
+
+/// Top-level keys, which apply to every verb that takes the parameter of the same name.
+const PROJECT_KEYS: &[&str] = &["tags", "cow", "data", "maps", "engine", "jobs"];
+
+/// Table containing a table of defaults for each verb, e.g. `[verbs.verify-scenario]`.
+const VERBS_KEY: &str = "verbs";
+
+/// Project file defining default parameters for verbs.
+///
+/// ```toml
+/// tags = ["tags", "../shared/tags"]
+/// engine = "pc-custom"
+///
+/// [verbs.verify-scenario]
+/// suppress = ["BMP003"]
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Project {
+    /// Path to the project file
+    pub path: PathBuf,
+
+    /// Top-level values
+    values: Table,
+
+    /// Values for each verb
+    verbs: Table
+}
+
+impl Project {
+    /// Find and load the closest project file, starting at `directory` and then going through its parents.
+    pub fn find(directory: &Path) -> Result<Option<Project>, String> {
+        for dir in directory.ancestors() {
+            let path = dir.join(PROJECT_FILE_NAME);
+            if path.is_file() {
+                let text = std::fs::read_to_string(&path).map_err(|e| format!("Cannot read {}: {e}", path.display()))?;
+                return Project::parse(&text, path).map(Some)
+            }
+        }
+        Ok(None)
+    }
+
+    /// Parse a project file.
+    ///
+    /// `path` is where the project file is located, and relative paths in it are relative to its directory.
+    pub fn parse(text: &str, path: PathBuf) -> Result<Project, String> {
+        let name = path.display().to_string();
+        let mut values: Table = toml::from_str(text).map_err(|e| format!("{name}: {e}"))?;
+
+        let verbs = match values.remove(VERBS_KEY) {
+            Some(Value::Table(verbs)) => verbs,
+            Some(_) => return Err(format!("{name}: `{VERBS_KEY}` must be a table")),
+            None => Table::new()
+        };
+        if let Some((verb, _)) = verbs.iter().find(|v| !v.1.is_table()) {
+            return Err(format!("{name}: `{VERBS_KEY}.{verb}` must be a table"))
+        }
+        if let Some(key) = values.keys().find(|k| !PROJECT_KEYS.contains(&k.as_str())) {
+            return Err(format!("{name}: Unknown key `{key}`; per-verb parameters go in a `[{VERBS_KEY}.<verb>]` section"))
+        }
+
+        Ok(Project { path, values, verbs })
+    }
+
+    /// Get the directory relative paths are resolved from.
+    pub fn root(&self) -> &Path {
+        self.path.parent().unwrap_or(Path::new("."))
+    }
+
+    /// Get the value for a parameter of a verb.
+    ///
+    /// Values set for the verb take precedence over top-level values.
+    pub fn get(&self, verb: &str, parameter: &str) -> Option<&Value> {
+        self.verb_values(verb)
+            .and_then(|v| v.get(parameter))
+            .or_else(|| self.values.get(parameter))
+    }
+
+    /// Get all parameter names set for a verb in its section.
+    pub fn verb_parameters(&self, verb: &str) -> impl Iterator<Item = &str> + '_ {
+        self.verb_values(verb)
+            .into_iter()
+            .flat_map(|v| v.keys().map(String::as_str))
+    }
+
+    fn verb_values(&self, verb: &str) -> Option<&Table> {
+        self.verbs.get(verb).and_then(Value::as_table)
+    }
+}
+
+/// Project file to read the parameters of a verb from.
+///
+/// The project file is only loaded once the verb parses its parameters, so verbs that do not take any (e.g. `version`)
+/// still work if it is malformed.
+#[derive(Clone, Debug)]
+pub struct VerbProject {
+    /// Name of the verb being run
+    pub verb: &'static str,
+
+    /// Where the project file comes from
+    source: ProjectSource
+}
+
+#[derive(Clone, Debug)]
+enum ProjectSource {
+    /// Search from the directory, or from the working directory if `None`
+    Search(Option<PathBuf>),
+
+    /// Already loaded
+    Loaded(Project)
+}
+
+impl VerbProject {
+    /// Use the project file for the current working directory, if any.
+    pub fn find(verb: &'static str) -> VerbProject {
+        VerbProject { verb, source: ProjectSource::Search(None) }
+    }
+
+    /// Use the closest project file to `directory`, if any.
+    pub fn find_from(verb: &'static str, directory: PathBuf) -> VerbProject {
+        VerbProject { verb, source: ProjectSource::Search(Some(directory)) }
+    }
+
+    /// Use an already loaded project file.
+    pub fn new(verb: &'static str, project: Project) -> VerbProject {
+        VerbProject { verb, source: ProjectSource::Loaded(project) }
+    }
+
+    /// Load the project file, returning `None` if there is none.
+    pub fn load(&self) -> Result<Option<Project>, String> {
+        match &self.source {
+            ProjectSource::Loaded(project) => Ok(Some(project.clone())),
+            ProjectSource::Search(Some(directory)) => Project::find(directory),
+            ProjectSource::Search(None) => {
+                let cwd = std::env::current_dir().map_err(|e| format!("Cannot get the current directory: {e}"))?;
+                Project::find(&cwd)
+            }
+        }
+    }
+}
@@ -0,0 +1,84 @@
+use super::*;
+
+const TEST_PROJECT: &str = r#"
+# Shared settings
+tags = [
+    "tags",
+    '../shared tags', # literal strings are fine too
+]
+cow = "tags-out"
+engine = "pc-custom"
+jobs = 4
+
+[verbs.verify-scenario]
+engine = "mcc-cea"
+suppress = ["BMP003", "IDX001"]
+
+[ verbs . "build" ]
+"overwrite" = true
+scale = -1.5
+message = "say \"hi\"\n"
+"#;
+
+fn test_project() -> Project {
+    Project::parse(TEST_PROJECT, Path::new("project").join(PROJECT_FILE_NAME)).unwrap()
+}
+
+#[test]
+fn parse_project() {
+    let project = test_project();
+    let string = |s: &str| Value::String(s.to_owned());
+
+    assert_eq!(project.root(), Path::new("project"));
+    assert_eq!(project.get("bludgeon", "tags"), Some(&Value::Array(vec![string("tags"), string("../shared tags")])));
+    assert_eq!(project.get("bludgeon", "cow"), Some(&string("tags-out")));
+    assert_eq!(project.get("bludgeon", "jobs"), Some(&Value::Integer(4)));
+    assert_eq!(project.get("bludgeon", "suppress"), None);
+
+    assert_eq!(project.get("build", "overwrite"), Some(&Value::Boolean(true)));
+    assert_eq!(project.get("build", "scale"), Some(&Value::Float(-1.5)));
+    assert_eq!(project.get("build", "message"), Some(&string("say \"hi\"\n")));
+    assert_eq!(project.verb_parameters("build").collect::<Vec<_>>(), ["overwrite", "scale", "message"]);
+}
+
+#[test]
+fn verb_values_take_precedence() {
+    let project = test_project();
+    assert_eq!(project.get("verify-scenario", "engine"), Some(&Value::String("mcc-cea".to_owned())));
+    assert_eq!(project.get("build", "engine"), Some(&Value::String("pc-custom".to_owned())));
+}
+
+#[test]
+fn reject_invalid_projects() {
+    let parse = |text: &str| Project::parse(text, PathBuf::from(PROJECT_FILE_NAME));
+
+    assert!(parse("").is_ok());
+    assert!(parse("format = \"json\"").is_err()); // only allowed in a verb section
+    assert!(parse("[build]\noverwrite = true").is_err());
+    assert!(parse("tags = \"tags\"\ntags = \"other\"").is_err());
+    assert!(parse("[verbs.build]\n[verbs.build]").is_err());
+    assert!(parse("verbs = [\"build\"]").is_err());
+    assert!(parse("[verbs]\nbuild = true").is_err());
+    assert!(parse("engine = \"pc-custom").is_err());
+    assert!(parse("tags = [\"tags\"").is_err());
+    assert!(parse("jobs = 4 5").is_err());
+    assert!(parse("jobs = four").is_err());
+
+    let error = parse("\n\njobs =").unwrap_err();
+    assert!(error.starts_with("invader.toml: ") && error.contains("line 3"), "{error}");
+}
+
+#[test]
+fn find_project_in_parent() {
+    let base = std::env::temp_dir().join(format!("invader-project-test-{}", std::process::id()));
+    let nested = base.join("maps").join("nested");
+    std::fs::create_dir_all(&nested).unwrap();
+    std::fs::write(base.join(PROJECT_FILE_NAME), "jobs = 2").unwrap();
+
+    let project = Project::find(&nested);
+    std::fs::remove_dir_all(&base).unwrap();
+
+    let project = project.unwrap().expect("should find the project in a parent directory");
+    assert_eq!(project.path, base.join(PROJECT_FILE_NAME));
+    assert_eq!(project.get("build", "jobs"), Some(&Value::Integer(2)));
+}
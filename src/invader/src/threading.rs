@@ -56,6 +56,33 @@ pub fn do_with_threads<T: TagTree + Send + 'static + Clone, U: Clone + Send + 's
     args: CommandLineArgs,
     user_filter: &str,
     group: Option<TagGroup>,
+    user_data: U,
+    display_mode: DisplayMode,
+    logger: Arc<StdoutLogger>,
+    function: ProcessFunction<T, U>,
+) -> Result<(), String> {
+    let tags = if !TagFilter::is_filter(user_filter) {
+        let tag_path = match group {
+            Some(group) => TagPath::new(TagFilter::cleanup_extension(user_filter, group), group),
+            None => TagPath::from_path(user_filter)
+        }.map_err(|_| format!("Invalid tag path `{user_filter}`"))?;
+        vec![tag_path]
+    }
+    else {
+        let filter = TagFilter::new(user_filter, group);
+        tags_directory.get_all_tags_with_filter(Some(&filter))
+    };
+    do_with_threads_for_tags(tags_directory, args, user_filter, tags, user_data, display_mode, logger, function)
+}
+
+/// Process the given tags rather than the tags in the tags directory that match `user_filter`.
+///
+/// This is useful for verbs that can create tags, where the tags to process are found somewhere else (e.g. data).
+pub fn do_with_threads_for_tags<T: TagTree + Send + 'static + Clone, U: Clone + Send + 'static>(
+    tags_directory: T,
+    args: CommandLineArgs,
+    user_filter: &str,
+    tags: Vec<TagPath>,
     mut user_data: U,
     display_mode: DisplayMode,
     logger: Arc<StdoutLogger>,
@@ -73,47 +100,40 @@ pub fn do_with_threads<T: TagTree + Send + 'static + Clone, U: Clone + Send + 's
     let total = Arc::new(AtomicU64::new(0));
     let failure = Arc::new(AtomicU64::new(0));
 
-    if !TagFilter::is_filter(user_filter) {
-        let tag_path = match group {
-            Some(group) => TagPath::new(TagFilter::cleanup_extension(user_filter, group), group),
-            None => TagPath::from_path(user_filter)
-        }.map_err(|_| format!("Invalid tag path `{user_filter}`"))?;
-        process_tags(&mut context, &success, &failure, &ignored, &total, &tag_path, &mut user_data, display_mode, &logger, function);
-    }
-    else {
-        let filter = TagFilter::new(user_filter, group);
-        let tags: VecDeque<TagPath> = context.tags_directory.get_all_tags_with_filter(Some(&filter)).into();
-        match context.args.get_jobs() {
-            1 => {
-                for path in tags {
-                    process_tags(&mut context, &success, &failure, &ignored, &total, &path, &mut user_data, display_mode, &logger, function);
-                }
-            },
-            n => {
-                let mut threads = Vec::with_capacity(n);
-                let tags = Arc::new(Mutex::new(tags));
-                for _ in 0..n {
-                    let tags = tags.clone();
-                    let mut context = context.clone();
-                    let success = success.clone();
-                    let total = total.clone();
-                    let failure = failure.clone();
-                    let ignored = ignored.clone();
-                    let mut user_data = user_data.clone();
-                    let logger = logger.clone();
-                    threads.push(std::thread::spawn(move || {
-                        loop {
-                            let next = match tags.lock().unwrap().pop_front() {
-                                Some(n) => n,
-                                None => break
-                            };
-                            process_tags(&mut context, &success, &failure, &ignored, &total, &next, &mut user_data, display_mode, &logger, function);
-                        }
-                    }))
-                }
-                for t in threads {
-                    t.join().unwrap();
-                }
+    // Verbs that only take a single tag may not have a jobs parameter.
+    let tags: VecDeque<TagPath> = tags.into();
+    let jobs = if tags.len() > 1 { context.args.get_jobs().min(tags.len()) } else { tags.len() };
+    match jobs {
+        0 => (),
+        1 => {
+            for path in tags {
+                process_tags(&mut context, &success, &failure, &ignored, &total, &path, &mut user_data, display_mode, &logger, function);
+            }
+        },
+        n => {
+            let mut threads = Vec::with_capacity(n);
+            let tags = Arc::new(Mutex::new(tags));
+            for _ in 0..n {
+                let tags = tags.clone();
+                let mut context = context.clone();
+                let success = success.clone();
+                let total = total.clone();
+                let failure = failure.clone();
+                let ignored = ignored.clone();
+                let mut user_data = user_data.clone();
+                let logger = logger.clone();
+                threads.push(std::thread::spawn(move || {
+                    loop {
+                        let next = match tags.lock().unwrap().pop_front() {
+                            Some(n) => n,
+                            None => break
+                        };
+                        process_tags(&mut context, &success, &failure, &ignored, &total, &next, &mut user_data, display_mode, &logger, function);
+                    }
+                }))
+            }
+            for t in threads {
+                t.join().unwrap();
             }
         }
     }
@@ -5,6 +5,7 @@ use std::io::{BufWriter, Stdout, stdout, Write};
 use std::path::Path;
 use std::sync::{Arc, Mutex, MutexGuard};
 use ringhopper::error::{Error, RinghopperResult};
+use ringhopper::primitives::dynamic::DynamicEnumImpl;
 
 pub fn read_file<P: AsRef<Path>>(path: P) -> RinghopperResult<Vec<u8>> {
     let path = path.as_ref();
@@ -311,3 +312,13 @@ impl<'a> LockedStdoutLogger<'a> {
 pub fn bytes_to_mib(bytes: usize) -> String {
     format!("{:.02} MiB", ((bytes / 1024) as f64) / 1024.0)
 }
+
+/// Parse an enum from a command line value, if present.
+pub fn parse_enum<E: DynamicEnumImpl>(value: Option<&str>, name: &str) -> Result<Option<E>, String> {
+    let Some(value) = value else {
+        return Ok(None)
+    };
+    E::from_str(value)
+        .map(Some)
+        .ok_or_else(|| format!("Invalid {name} `{value}`; valid options are: {}", E::str_vals().join(", ")))
+}
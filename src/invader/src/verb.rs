@@ -2,7 +2,9 @@ use std::collections::HashMap;
 use std::env::Args;
 use std::fmt::Arguments;
 use ringhopper::primitives::primitive::TagPath;
-use ringhopper::tag::result::TagResult;
+use ringhopper::tag::result::{results_to_json, results_to_sarif, TagResult};
+use super::cli::{CommandLineArgs, OutputFormat};
+use super::project::VerbProject;
 use super::util::LockedStdoutLogger;
 
 macro_rules! str_unwrap {
@@ -32,17 +34,31 @@ mod recompress_vertices;
 mod dependency_tree;
 mod refactor_paths;
 mod info;
+mod build;
+mod compress;
+mod bitmap;
+mod export_bitmap;
+mod sound;
+mod compile_scripts;
+mod map_diff;
+mod model;
+mod animations;
+mod structure;
+mod collision;
+mod json;
+mod edit_tag;
+mod resource;
 
 #[derive(Copy, Clone)]
 pub struct Verb {
     pub name: &'static str,
     pub description: &'static str,
-    pub function: fn(Args, &'static str) -> Result<(), String>,
+    pub function: fn(Args, &'static str, Option<VerbProject>) -> Result<(), String>,
     aliases: &'static [&'static str]
 }
 
 impl Verb {
-    const fn new(name: &'static str, description: &'static str, function: fn(Args, &'static str) -> Result<(), String>) -> Self {
+    const fn new(name: &'static str, description: &'static str, function: fn(Args, &'static str, Option<VerbProject>) -> Result<(), String>) -> Self {
         Self {
             name, description, function, aliases: &[]
         }
@@ -54,24 +70,40 @@ impl Verb {
 }
 
 pub const ALL_VERBS: &'static [Verb] = &[
+    Verb::new("animations", "Generate model_animations tags from JMA files", animations::animations),
     Verb::new("archive-scenario", "Create a .7z of a map's tag structure", archive::archive_scenario),
     Verb::new("archive-tag", "Create a .7z of a tag and its dependencies", archive::archive_tag),
+    Verb::new("bitmap", "Generate bitmap tags from color plates", bitmap::bitmap),
     Verb::new("bludgeon", "Automatically repair common issues with tags", bludgeon::bludgeon),
+    Verb::new("build", "Build a cache file from a scenario tag", build::build),
+    Verb::new("collision", "Generate model_collision_geometry tags from JMS files", collision::collision),
     Verb::new("compare", "Compare tags between two tag sources", compare::compare).with_aliases(&["cmp"]),
+    Verb::new("compile-scripts", "Compile HaloScript source files into a scenario tag", compile_scripts::compile_scripts),
+    Verb::new("compress", "Compress a cache file for engines that use compression", compress::compress),
     Verb::new("convert", "Convert tags to another tag group", convert::convert),
+    Verb::new("decompress", "Decompress a cache file for engines that use compression", compress::decompress),
     Verb::new("dependency-list", "View dependencies of tags", dependency_list::dependency_list),
     Verb::new("dependency-tree", "View dependencies of a tag in a recursive tree", dependency_tree::dependency_tree),
+    Verb::new("edit-tag", "Get, set, insert, and delete fields in tags", edit_tag::edit_tag),
+    Verb::new("export-bitmap", "Export bitmap data in a bitmap tag as images", export_bitmap::export_bitmap),
+    Verb::new("export-json", "Export tags as JSON files that can be edited and imported back", json::export_json),
     Verb::new("extract", "Extract tags from a map", extract::extract),
+    Verb::new("import-json", "Generate tags from JSON files made with export-json", json::import_json),
     Verb::new("info", "Output info about a map", info::info),
     Verb::new("list-engines", "List all available engine targets", list_engines::list_engines),
     Verb::new("list-scenario-tags", "View all tags needed to build a scenario into a map", dependency_list::list_scenario_tags),
+    Verb::new("map-diff", "Compare two cache files, including data that only exists in cache files", map_diff::map_diff),
+    Verb::new("model", "Generate model tags from JMS files", model::model),
     Verb::new("nudge", "Fix floating point precision errors from tag extraction", nudge::nudge),
     Verb::new("plate", "Generate color plates for bitmaps", plate::plate),
     Verb::new("recompress-vertices", "Recompress model vertices", recompress_vertices::recompress_vertices),
     Verb::new("recover", "Recover data from tags", recover::recover),
     Verb::new("refactor-groups", "Batch refactor dependencies by tag group if the new dependency exists", refactor_groups::refactor_groups),
     Verb::new("refactor-paths", "Batch refactor dependencies by tag path (file extensions cannot be changed)", refactor_paths::refactor_paths),
+    Verb::new("resource", "Build a resource map from the tags used by scenarios", resource::resource),
+    Verb::new("sound", "Generate sound tags from audio files", sound::sound),
     Verb::new("strip", "Clean tags", strip::strip),
+    Verb::new("structure", "Generate scenario_structure_bsp tags from JMS files", structure::structure),
     Verb::new("tag-collection", "Generate tag_collection tags from data", tag_collection::tag_collection),
     Verb::new("ui-widget-collection", "Generate ui_widget_collection tags from data", tag_collection::ui_widget_collection),
     Verb::new("undefault", "Strip default values from tags", undefault::undefault),
@@ -93,6 +125,27 @@ pub fn get_verb(what: &str) -> Option<&'static Verb> {
     None
 }
 
+/// Remove all diagnostics suppressed with --suppress.
+fn suppress_tag_results(results: &mut HashMap<TagPath, TagResult>, args: &CommandLineArgs) {
+    let suppressed = args.get_suppressed();
+    if !suppressed.is_empty() {
+        for result in results.values_mut() {
+            result.suppress(&suppressed);
+        }
+    }
+}
+
+/// Print all diagnostics as JSON or SARIF.
+///
+/// Text output is done with [`print_tag_results`] instead, since it is printed as tags are processed.
+fn print_structured_tag_results(logger: &LockedStdoutLogger, results: &HashMap<TagPath, TagResult>, format: OutputFormat) {
+    match format {
+        OutputFormat::Json => logger.neutral_ln(&results_to_json(results)),
+        OutputFormat::Sarif => logger.neutral_ln(&results_to_sarif(results, "invader", env!("CARGO_PKG_VERSION"))),
+        OutputFormat::Text => unreachable!("text output is printed with print_tag_results")
+    }
+}
+
 fn print_tag_results(logger: &LockedStdoutLogger, results: &HashMap<TagPath, TagResult>, action: Arguments) {
     let total_issues = results
         .iter()
@@ -109,21 +162,21 @@ fn print_tag_results(logger: &LockedStdoutLogger, results: &HashMap<TagPath, Tag
     // First pass: pedantic warnings
     for (path, vr) in results {
         for i in &vr.pedantic_warnings {
-            logger.minor_warning_fmt_ln(format_args!("WARNING (minor) {path}: {i}"))
+            logger.minor_warning_fmt_ln(format_args!("WARNING (minor) [{code}] {path}: {i}", code = i.code))
         }
     }
 
     // Second pass: warnings
     for (path, vr) in results {
         for i in &vr.warnings {
-            logger.warning_fmt_ln(format_args!("WARNING {path}: {i}"))
+            logger.warning_fmt_ln(format_args!("WARNING [{code}] {path}: {i}", code = i.code))
         }
     }
 
     // Final pass: errors
     for (path, vr) in results {
         for i in &vr.errors {
-            logger.error_fmt_ln(format_args!("ERROR {path}: {i}"))
+            logger.error_fmt_ln(format_args!("ERROR [{code}] {path}: {i}", code = i.code))
         }
     }
 }
@@ -0,0 +1,43 @@
+use std::env::Args;
+use std::path::Path;
+use crate::cli::CommandLineParser;
+use crate::project::VerbProject;
+use ringhopper::data::jma::load_animation_sources;
+use ringhopper::definitions::ModelAnimations;
+use ringhopper::primitives::primitive::TagGroup;
+use ringhopper::tag::model_animations::compile_animations;
+use ringhopper::tag::tree::TagTree;
+use crate::threading::{DisplayMode, do_with_threads, ProcessSuccessType};
+use crate::util::make_stdout_logger;
+
+pub fn animations(args: Args, description: &'static str, project: Option<VerbProject>) -> Result<(), String> {
+    let parser = CommandLineParser::new(description, "<model_animations*> [args]")
+        .set_project(project)
+        .add_tags(false)
+        .add_data()
+        .add_help()
+        .add_cow_tags()
+        .add_jobs()
+        .set_required_extra_parameters(1)
+        .parse(args)?;
+
+    let tag = parser.get_extra()[0].clone();
+    do_with_threads(parser.get_virtual_tags_directory(), parser, &tag, Some(TagGroup::ModelAnimations), (), DisplayMode::ShowAll, make_stdout_logger(), |context, path, _, _| {
+        let tag_directory = Path::new(&path.to_native_path()).parent().map(|p| p.to_path_buf()).unwrap_or_default();
+        let data_path = context.args.get_data().join(tag_directory).join("animations");
+        if !data_path.is_dir() {
+            return Ok(ProcessSuccessType::Skipped("no animations directory to import in data"))
+        }
+
+        let sources = load_animation_sources(&data_path)?;
+        let mut tag = if context.tags_directory.contains(path) {
+            context.tags_directory.open_tag_copy(path)?.as_any().downcast_ref::<ModelAnimations>().unwrap().to_owned()
+        }
+        else {
+            ModelAnimations::default()
+        };
+
+        compile_animations(&mut tag, &sources)?;
+        ProcessSuccessType::wrap_write_result(context.tags_directory.write_tag(path, &tag))
+    })
+}
@@ -2,15 +2,17 @@ use std::env::Args;
 use std::path::PathBuf;
 use std::sync::Arc;
 use crate::cli::{CommandLineParser, CommandLineValue, CommandLineValueType, Parameter};
+use crate::project::VerbProject;
 use ringhopper::primitives::primitive::{TagGroup, TagPath};
 use ringhopper::tag::archive::*;
 use ringhopper::tag::tree::{CachingTagTree, CachingTagTreeWriteStrategy};
 use crate::util::make_stdout_logger;
 
-fn archive_command(args: Args, description: &'static str, full_scenario: bool) -> Result<(), String> {
+fn archive_command(args: Args, description: &'static str, project: Option<VerbProject>, full_scenario: bool) -> Result<(), String> {
     let usage = if full_scenario { "<tag> [args]" } else { "<tag.group> [args]" };
 
     let parser = CommandLineParser::new(description, usage)
+        .set_project(project)
         .add_tags(true)
         .add_help()
         .add_overwrite()
@@ -58,10 +60,10 @@ fn archive_command(args: Args, description: &'static str, full_scenario: bool) -
     Ok(())
 }
 
-pub fn archive_tag(args: Args, description: &'static str) -> Result<(), String> {
-    archive_command(args, description, false)
+pub fn archive_tag(args: Args, description: &'static str, project: Option<VerbProject>) -> Result<(), String> {
+    archive_command(args, description, project, false)
 }
 
-pub fn archive_scenario(args: Args, description: &'static str) -> Result<(), String> {
-    archive_command(args, description, true)
+pub fn archive_scenario(args: Args, description: &'static str, project: Option<VerbProject>) -> Result<(), String> {
+    archive_command(args, description, project, true)
 }
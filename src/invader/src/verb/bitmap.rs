@@ -0,0 +1,81 @@
+use std::env::Args;
+use crate::cli::{CommandLineParser, CommandLineValueType, Parameter};
+use crate::project::VerbProject;
+use ringhopper::data::bitmap::{autodetect_image_extension, load_image_from_path};
+use ringhopper::data::bitmap::plate::make_color_plate_from_loose;
+use ringhopper::definitions::{Bitmap, BitmapFormat, BitmapType};
+use ringhopper::primitives::primitive::TagGroup;
+use ringhopper::tag::bitmap::{BlockCompressionQuality, compile_bitmap};
+use ringhopper::tag::tree::TagTree;
+use crate::threading::{DisplayMode, do_with_threads, ProcessSuccessType};
+use crate::util::{make_stdout_logger, parse_enum};
+
+#[derive(Clone)]
+struct UserData {
+    bitmap_type: Option<BitmapType>,
+    format: Option<BitmapFormat>,
+    quality: BlockCompressionQuality
+}
+
+pub fn bitmap(args: Args, description: &'static str, project: Option<VerbProject>) -> Result<(), String> {
+    let parser = CommandLineParser::new(description, "<bitmap*> [args]")
+        .set_project(project)
+        .add_tags(false)
+        .add_data()
+        .add_help()
+        .add_cow_tags()
+        .add_jobs()
+        .add_custom_parameter(Parameter::single("type", 'T', "Set the bitmap type. Default: existing tag's type, or 2d_textures", "<type>", Some(CommandLineValueType::String)))
+        .add_custom_parameter(Parameter::single("format", 'F', "Set the bitmap format. Default: existing tag's format, or 32_bit", "<format>", Some(CommandLineValueType::String)))
+        .add_custom_parameter(Parameter::single("quality", 'Q', "Set the block compression quality (fast, normal, or best). Default: normal", "<quality>", Some(CommandLineValueType::String)))
+        .set_required_extra_parameters(1)
+        .parse(args)?;
+
+    let user_data = UserData {
+        bitmap_type: parse_enum(parser.get_custom("type").map(|t| t[0].string()), "type")?,
+        format: parse_enum(parser.get_custom("format").map(|f| f[0].string()), "format")?,
+        quality: match parser.get_custom("quality").map(|q| q[0].string()) {
+            None | Some("normal") => BlockCompressionQuality::Normal,
+            Some("fast") => BlockCompressionQuality::Fast,
+            Some("best") => BlockCompressionQuality::Best,
+            Some(n) => return Err(format!("Invalid quality `{n}`; valid options are: fast, normal, best"))
+        }
+    };
+
+    let tag = parser.get_extra()[0].clone();
+    do_with_threads(parser.get_virtual_tags_directory(), parser, &tag, Some(TagGroup::Bitmap), user_data, DisplayMode::ShowAll, make_stdout_logger(), |context, path, user_data, _| {
+        let data_path = context
+            .args
+            .get_data()
+            .join(path.to_native_path())
+            .with_extension("");
+
+        let color_plate = if let Some(image_path) = autodetect_image_extension(&data_path) {
+            load_image_from_path(image_path)?
+        }
+        else if data_path.is_dir() {
+            make_color_plate_from_loose(&data_path)?
+        }
+        else {
+            return Ok(ProcessSuccessType::Skipped("no image or directory to import in data"))
+        };
+
+        let mut tag = if context.tags_directory.contains(path) {
+            let mut tag = context.tags_directory.open_tag_copy(path)?;
+            tag.as_any_mut().downcast_mut::<Bitmap>().unwrap().to_owned()
+        }
+        else {
+            Bitmap::default()
+        };
+
+        if let Some(bitmap_type) = user_data.bitmap_type {
+            tag._type = bitmap_type;
+        }
+        if let Some(format) = user_data.format {
+            tag.encoding_format = format;
+        }
+
+        compile_bitmap(&mut tag, &color_plate, user_data.quality)?;
+        ProcessSuccessType::wrap_write_result(context.tags_directory.write_tag(path, &tag))
+    })
+}
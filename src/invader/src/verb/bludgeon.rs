@@ -1,28 +1,86 @@
+use std::collections::HashMap;
 use std::env::Args;
-use crate::cli::CommandLineParser;
-use ringhopper::{primitives::tag::ParseStrictness, tag::{bludgeon::{self, BludgeonResult}, tree::TagTree}};
+use std::sync::{Arc, Mutex};
+use crate::cli::{CommandLineParser, OutputFormat, Parameter};
+use crate::project::VerbProject;
+use ringhopper::{primitives::tag::ParseStrictness, tag::{bludgeon::{self, BludgeonOptions, BludgeonResult}, tree::TagTree}};
+use ringhopper::primitives::primitive::TagPath;
+use ringhopper::tag::compare::compare_tags;
+use ringhopper::tag::result::{Diagnostic, TagResult};
 use crate::threading::{DisplayMode, do_with_threads, ProcessSuccessType};
 use crate::util::make_stdout_logger;
+use crate::verb::print_structured_tag_results;
 
-pub fn bludgeon(args: Args, description: &'static str) -> Result<(), String> {
+#[derive(Clone)]
+struct UserData {
+    format: OutputFormat,
+    options: BludgeonOptions,
+
+    /// Everything that was repaired (or could not be), if not outputting text
+    results: Arc<Mutex<HashMap<TagPath, TagResult>>>
+}
+
+pub fn bludgeon(args: Args, description: &'static str, project: Option<VerbProject>) -> Result<(), String> {
     let parser = CommandLineParser::new(description, "<tag.group*> [args]")
+        .set_project(project)
         .add_tags(false)
         .add_help()
         .add_cow_tags()
         .add_jobs()
+        .add_diagnostics_output()
+        .add_custom_parameter(Parameter::single("decompress-animations", 'a', "Decompress compressed animations. Animations that only have compressed data lose some precision.", "", None))
         .set_required_extra_parameters(1)
         .parse(args)?;
 
     let tag = parser.get_extra()[0].clone();
+    let options = BludgeonOptions {
+        decompress_animations: parser.get_custom("decompress-animations").is_some()
+    };
     let mut directory = parser.get_virtual_tags_directory();
     directory.set_strictness(ParseStrictness::Relaxed);
 
-    do_with_threads(parser.get_virtual_tags_directory(), parser, &tag, None, (), DisplayMode::ShowAll, make_stdout_logger(), |context, path, _, _| {
+    let user_data = UserData {
+        format: parser.get_output_format(),
+        options,
+        results: Arc::new(Mutex::new(HashMap::new()))
+    };
+    let display_mode = match user_data.format {
+        OutputFormat::Text => DisplayMode::ShowAll,
+        _ => DisplayMode::Silent
+    };
+
+    let logger = make_stdout_logger();
+    do_with_threads(parser.get_virtual_tags_directory(), parser, &tag, None, user_data.clone(), display_mode, logger.clone(), |context, path, user_data, _| {
         let mut tag = context.tags_directory.open_tag_copy(&path)?;
+        let structured = user_data.format != OutputFormat::Text;
+        let original = structured.then(|| tag.clone_inner());
+        let mut result = TagResult::default();
 
-        match bludgeon::bludgeon_tag(tag.as_mut(), path) {
-            BludgeonResult::CannotRepair => Ok(ProcessSuccessType::Skipped("cannot repair; tag is FUBAR")),
-            BludgeonResult::Done => ProcessSuccessType::wrap_write_result(context.tags_directory.write_tag(path, tag.as_ref()))
+        let outcome = match bludgeon::bludgeon_tag(tag.as_mut(), path, &user_data.options) {
+            BludgeonResult::CannotRepair => {
+                result.errors.push(Diagnostic::new("BLD001", "Tag cannot be repaired"));
+                Ok(ProcessSuccessType::Skipped("cannot repair; tag is FUBAR"))
+            },
+            BludgeonResult::Done => {
+                if let Some(original) = original {
+                    for difference in compare_tags(original.as_ref(), tag.as_ref(), true, false) {
+                        result.warnings.push(Diagnostic::new("BLD002", format!("{} was repaired: {}", difference.path, difference.difference)).with_field(difference.path));
+                    }
+                }
+                ProcessSuccessType::wrap_write_result(context.tags_directory.write_tag(path, tag.as_ref()))
+            }
+        };
+
+        if structured {
+            result.suppress(&context.args.get_suppressed());
+            user_data.results.lock().unwrap().insert(path.to_owned(), result);
         }
-    })
+        outcome
+    })?;
+
+    if user_data.format != OutputFormat::Text {
+        print_structured_tag_results(&logger.lock(), &user_data.results.lock().unwrap(), user_data.format);
+    }
+
+    Ok(())
 }
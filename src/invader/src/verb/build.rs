@@ -0,0 +1,53 @@
+use std::env::Args;
+use crate::cli::{CommandLineParser, CommandLineValueType, Parameter};
+use crate::project::VerbProject;
+use ringhopper::map::build::{build_cache_file, CacheFileBuildOptions};
+use ringhopper::primitives::primitive::{TagGroup, TagPath};
+use ringhopper::tag::tree::{CachingTagTree, CachingTagTreeWriteStrategy};
+use crate::util::{bytes_to_mib, make_stdout_logger};
+
+pub fn build(args: Args, description: &'static str, project: Option<VerbProject>) -> Result<(), String> {
+    let parser = CommandLineParser::new(description, "<scenario> [args]")
+        .set_project(project)
+        .add_tags(true)
+        .add_maps()
+        .add_engine()
+        .add_overwrite()
+        .add_help()
+        .add_custom_parameter(Parameter::single("output", 'O', "Output filename. Default: <maps>/<scenario_basename>.map", "<file>", Some(CommandLineValueType::Path)))
+        .add_custom_parameter(Parameter::single("rename", 'N', "Set the name in the header. Default: <scenario_basename>", "<name>", Some(CommandLineValueType::String)))
+        .add_custom_parameter(Parameter::single("build-string", 'B', "Set the build string in the header if the engine does not enforce one.", "<str>", Some(CommandLineValueType::String)))
+        .set_required_extra_parameters(1)
+        .parse(args)?;
+
+    let logger = make_stdout_logger();
+    let scenario = str_unwrap!(TagPath::new(&parser.get_extra()[0], TagGroup::Scenario), "Invalid scenario path: {error}");
+    let engine = parser.get_engine();
+
+    let output = parser.get_custom("output").map_or_else(
+        || parser.get_maps().join(format!("{}.map", scenario.base_name())),
+        |o| o[0].path().to_owned()
+    );
+    if !parser.get_overwrite() && output.exists() {
+        logger.warning_fmt_ln(format_args!("{output:?} already exists; skipping"));
+        return Ok(())
+    }
+
+    let options = CacheFileBuildOptions {
+        name: parser.get_custom("rename").map(|n| n[0].string().to_owned()),
+        build: parser.get_custom("build-string").map(|n| n[0].string().to_owned())
+    };
+
+    let cache = CachingTagTree::new(parser.get_virtual_tags_directory(), CachingTagTreeWriteStrategy::Manual);
+    let map = str_unwrap!(build_cache_file(&cache, &scenario, engine, &options), "Failed to build {scenario}: {error}");
+    str_unwrap!(std::fs::write(&output, &map.data), "Failed to write {output:?}: {error}");
+
+    let l = logger.lock();
+    l.neutral_fmt_ln(format_args!("Tags:           {}", map.tag_count));
+    l.neutral_fmt_ln(format_args!("Tag space:      {} / {}", bytes_to_mib(map.used_tag_space), bytes_to_mib(engine.max_tag_space as usize)));
+    l.neutral_fmt_ln(format_args!("File size:      {}", bytes_to_mib(map.data.len())));
+    l.neutral_fmt_ln(format_args!("CRC32:          0x{:08X}", map.crc32));
+    l.success_fmt_ln(format_args!("Wrote {output:?}"));
+
+    Ok(())
+}
@@ -0,0 +1,56 @@
+use std::collections::HashMap;
+use std::env::Args;
+use std::path::Path;
+use crate::cli::CommandLineParser;
+use crate::project::VerbProject;
+use ringhopper::data::jms::load_model_sources;
+use ringhopper::definitions::ModelCollisionGeometry;
+use ringhopper::error::Error;
+use ringhopper::primitives::primitive::TagGroup;
+use ringhopper::tag::model_collision_geometry::compile_model_collision_geometry;
+use ringhopper::tag::tree::TagTree;
+use crate::threading::{DisplayMode, do_with_threads, ProcessSuccessType};
+use crate::util::make_stdout_logger;
+use crate::verb::print_tag_results;
+
+pub fn collision(args: Args, description: &'static str, project: Option<VerbProject>) -> Result<(), String> {
+    let parser = CommandLineParser::new(description, "<model_collision_geometry*> [args]")
+        .set_project(project)
+        .add_tags(false)
+        .add_data()
+        .add_help()
+        .add_cow_tags()
+        .add_jobs()
+        .set_required_extra_parameters(1)
+        .parse(args)?;
+
+    let tag = parser.get_extra()[0].clone();
+    do_with_threads(parser.get_virtual_tags_directory(), parser, &tag, Some(TagGroup::ModelCollisionGeometry), (), DisplayMode::ShowAll, make_stdout_logger(), |context, path, _, logger| {
+        let tag_directory = Path::new(&path.to_native_path()).parent().map(|p| p.to_path_buf()).unwrap_or_default();
+        let data_path = context.args.get_data().join(tag_directory).join("physics");
+        if !data_path.is_dir() {
+            return Ok(ProcessSuccessType::Skipped("no physics directory to import in data"))
+        }
+
+        let sources = load_model_sources(&data_path)?;
+        let mut tag = if context.tags_directory.contains(path) {
+            context.tags_directory.open_tag_copy(path)?.as_any().downcast_ref::<ModelCollisionGeometry>().unwrap().to_owned()
+        }
+        else {
+            ModelCollisionGeometry::default()
+        };
+
+        let result = compile_model_collision_geometry(&mut tag, &sources)?;
+
+        let has_errors = !result.is_ok();
+        if has_errors || !result.warnings.is_empty() {
+            let results = HashMap::from([(path.clone(), result)]);
+            print_tag_results(&logger.lock(), &results, format_args!("Compiled {path}"));
+        }
+        if has_errors {
+            return Err(Error::Other(format!("{} has errors in its collision geometry", data_path.display())))
+        }
+
+        ProcessSuccessType::wrap_write_result(context.tags_directory.write_tag(path, &tag))
+    })
+}
@@ -2,16 +2,19 @@ use std::collections::{HashMap, VecDeque};
 use std::env::Args;
 use std::path::Path;
 use std::sync::{Arc, Mutex};
-use crate::cli::{CommandLineParser, CommandLineValue, CommandLineValueType, Parameter};
+use crate::cli::{CommandLineParser, CommandLineValue, CommandLineValueType, OutputFormat, Parameter};
+use crate::project::VerbProject;
 use ringhopper::error::Error;
 use ringhopper::map::load_map_from_filesystem_as_tag_tree;
 use ringhopper::tag::default::set_all_defaults_for_tag;
 use ringhopper::primitives::primitive::TagPath;
 use ringhopper::primitives::tag::ParseStrictness;
 use ringhopper::tag::compare::{compare_tags, TagComparisonDifference};
+use ringhopper::tag::result::{Diagnostic, TagResult};
 use ringhopper::tag::tree::{TagTree, VirtualTagsDirectory};
 use crate::threading::{DisplayMode, do_with_threads, ProcessSuccessType};
 use crate::util::make_stdout_logger;
+use crate::verb::print_structured_tag_results;
 
 use crate::util::StdoutLogger;
 
@@ -30,8 +33,9 @@ struct UserData {
     abbreviated: bool
 }
 
-pub fn compare(args: Args, description: &'static str) -> Result<(), String> {
+pub fn compare(args: Args, description: &'static str, project: Option<VerbProject>) -> Result<(), String> {
     let parser = CommandLineParser::new(description, "<source1> <source2> [args]")
+        .set_project(project)
         .add_help()
         .add_custom_parameter(Parameter::new(
             "verbose",
@@ -79,6 +83,7 @@ pub fn compare(args: Args, description: &'static str) -> Result<(), String> {
         ))
         .add_jobs()
         .add_custom_parameter(Parameter::single("raw", 'r', "Also compare cache-only fields, and disable defaulting when comparing.", "", None))
+        .add_diagnostics_output()
         .set_required_extra_parameters(2)
         .parse(args)?;
 
@@ -92,6 +97,8 @@ pub fn compare(args: Args, description: &'static str) -> Result<(), String> {
     let verbose = parser.get_custom("verbose").is_some();
     let raw = parser.get_custom("raw").is_some();
     let abbreviated = parser.get_custom("abbreviated").is_some();
+    let format = parser.get_output_format();
+    let suppressed: Vec<String> = parser.get_suppressed().into_iter().map(str::to_owned).collect();
 
     let mut source: VecDeque<Arc<dyn TagTree + Send + Sync>> = VecDeque::new();
     for i in parser.get_extra() {
@@ -150,11 +157,35 @@ pub fn compare(args: Args, description: &'static str) -> Result<(), String> {
         Ok(ProcessSuccessType::Success)
     })?;
 
-    display_result(display_mode, verbose, user_data, &logger);
+    if format == OutputFormat::Text {
+        display_result(display_mode, verbose, user_data, &logger);
+    }
+    else {
+        let mut results = differences_to_results(user_data);
+        for result in results.values_mut() {
+            result.suppress(&suppressed);
+        }
+        print_structured_tag_results(&logger.lock(), &results, format);
+    }
 
     Ok(())
 }
 
+/// Convert each difference into a warning on the field it was found in.
+fn differences_to_results(user_data: UserData) -> HashMap<TagPath, TagResult> {
+    let all_differences = Arc::into_inner(user_data.differences).unwrap().into_inner().unwrap();
+    all_differences
+        .into_iter()
+        .map(|(path, differences)| {
+            let mut result = TagResult::default();
+            for difference in differences {
+                result.warnings.push(Diagnostic::new("CMP001", format!("{}: {}", difference.path, difference.difference)).with_field(difference.path));
+            }
+            (path, result)
+        })
+        .collect()
+}
+
 fn display_result(display_mode: Show, verbose: bool, user_data: UserData, logger: &Arc<StdoutLogger>) {
     let mut matched = 0usize;
     let all_differences = Arc::into_inner(user_data.differences).unwrap().into_inner().unwrap();
@@ -240,7 +271,7 @@ fn display_item(what: &DifferenceMap, depth: usize, io: &Arc<StdoutLogger>) {
     }
 }
 
-fn display_diff(diff: &Vec<TagComparisonDifference>, io: &Arc<StdoutLogger>) {
+pub(super) fn display_diff(diff: &Vec<TagComparisonDifference>, io: &Arc<StdoutLogger>) {
     let mut map = DifferenceMap::default();
     for i in diff {
         map.access_mut(&i.path).difference = Some(i.clone());
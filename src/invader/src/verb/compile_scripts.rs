@@ -0,0 +1,69 @@
+use std::env::Args;
+use std::path::Path;
+use crate::cli::CommandLineParser;
+use crate::project::VerbProject;
+use ringhopper::definitions::{Scenario, ScenarioSourceFile};
+use ringhopper::error::{Error, RinghopperResult};
+use ringhopper::primitives::primitive::{Data, String32, TagGroup};
+use ringhopper::tag::scenario::compile_scripts as compile_scenario_scripts;
+use ringhopper::tag::tree::TagTree;
+use crate::threading::{DisplayMode, do_with_threads, ProcessSuccessType};
+use crate::util::{make_stdout_logger, read_file};
+
+pub fn compile_scripts(args: Args, description: &'static str, project: Option<VerbProject>) -> Result<(), String> {
+    let parser = CommandLineParser::new(description, "<scenario*> [args]")
+        .set_project(project)
+        .add_tags(false)
+        .add_data()
+        .add_engine()
+        .add_help()
+        .add_cow_tags()
+        .add_jobs()
+        .set_required_extra_parameters(1)
+        .parse(args)?;
+
+    let tag = parser.get_extra()[0].clone();
+    do_with_threads(parser.get_virtual_tags_directory(), parser, &tag, Some(TagGroup::Scenario), (), DisplayMode::ShowAll, make_stdout_logger(), |context, path, _, _| {
+        let mut tag = context.tags_directory.open_tag_copy(path)?;
+        let scenario = tag.as_any_mut().downcast_mut::<Scenario>().unwrap();
+
+        // Scripts are in a scripts directory next to where the scenario would be in data. If there is none, recompile
+        // the source files already in the scenario.
+        let scripts_directory = context
+            .args
+            .get_data()
+            .join(path.to_native_path())
+            .parent()
+            .map(|p| p.join("scripts"));
+
+        if let Some(scripts_directory) = scripts_directory.filter(|d| d.is_dir()) {
+            scenario.source_files.items = read_source_files(&scripts_directory)?;
+        }
+
+        if scenario.source_files.items.is_empty() {
+            return Ok(ProcessSuccessType::Skipped("no scripts to compile"))
+        }
+
+        compile_scenario_scripts(scenario, context.args.get_engine())?;
+        ProcessSuccessType::wrap_write_result(context.tags_directory.write_tag(path, tag.as_ref()))
+    })
+}
+
+fn read_source_files(directory: &Path) -> RinghopperResult<Vec<ScenarioSourceFile>> {
+    let mut paths = Vec::new();
+    let entries = std::fs::read_dir(directory).map_err(|e| Error::FailedToReadFile(directory.to_path_buf(), e))?;
+    for entry in entries {
+        let path = entry.map_err(|e| Error::FailedToReadFile(directory.to_path_buf(), e))?.path();
+        if path.is_file() && path.extension().is_some_and(|e| e.eq_ignore_ascii_case("hsc")) {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+
+    paths.into_iter().map(|path| {
+        let name = path.file_stem().unwrap_or_default().to_string_lossy();
+        let name = String32::from_str(&name)
+            .map_err(|_| Error::Other(format!("script file name `{name}` is longer than 31 characters")))?;
+        Ok(ScenarioSourceFile { name, source: Data::new(read_file(&path)?) })
+    }).collect()
+}
@@ -0,0 +1,64 @@
+use std::env::Args;
+use std::path::Path;
+use crate::cli::{CommandLineParser, CommandLineValueType, Parameter};
+use crate::project::VerbProject;
+use ringhopper::map::{compress_map_data, decompress_map_data, is_map_data_compressed};
+use ringhopper::map::header::get_map_details;
+use ringhopper::primitives::engine::EngineCompressionType;
+use crate::util::{bytes_to_mib, make_stdout_logger};
+
+fn compress_command(args: Args, description: &'static str, project: Option<VerbProject>, compress: bool) -> Result<(), String> {
+    let parser = CommandLineParser::new(description, "<map> [args]")
+        .set_project(project)
+        .add_help()
+        .add_overwrite()
+        .add_custom_parameter(Parameter::single("output", 'O', "Output filename. Required unless overwriting the input map with --overwrite", "<file>", Some(CommandLineValueType::Path)))
+        .set_required_extra_parameters(1)
+        .parse(args)?;
+
+    let logger = make_stdout_logger();
+    let input = Path::new(&parser.get_extra()[0]);
+    let overwrite = parser.get_overwrite();
+    let output = match parser.get_custom("output") {
+        Some(o) => o[0].path(),
+        None if overwrite => input,
+        None => return Err("No output file given; use --output, or use --overwrite to overwrite the input map".to_owned())
+    };
+    if !overwrite && output.exists() {
+        return Err(format!("{output:?} already exists; use --overwrite to overwrite it"))
+    }
+
+    let data = str_unwrap!(std::fs::read(input), "Failed to read {input:?}: {error}");
+    let input_size = data.len();
+    let (header, engine) = str_unwrap!(get_map_details(&data), "Failed to read {input:?}: {error}");
+
+    if engine.compression_type == EngineCompressionType::Uncompressed {
+        return Err(format!("{input:?} is a {} map which does not use compression", engine.display_name))
+    }
+
+    match (compress, is_map_data_compressed(&data, &header, engine)) {
+        (true, true) => return Err(format!("{input:?} is already compressed")),
+        (false, false) => return Err(format!("{input:?} is not compressed")),
+        _ => ()
+    }
+
+    let data = if compress {
+        str_unwrap!(compress_map_data(data, engine), "Failed to compress {input:?}: {error}")
+    }
+    else {
+        str_unwrap!(decompress_map_data(data, &header, engine), "Failed to decompress {input:?}: {error}")
+    };
+
+    str_unwrap!(std::fs::write(output, &data), "Failed to write {output:?}: {error}");
+    logger.success_fmt_ln(format_args!("Wrote {output:?} ({} -> {})", bytes_to_mib(input_size), bytes_to_mib(data.len())));
+
+    Ok(())
+}
+
+pub fn compress(args: Args, description: &'static str, project: Option<VerbProject>) -> Result<(), String> {
+    compress_command(args, description, project, true)
+}
+
+pub fn decompress(args: Args, description: &'static str, project: Option<VerbProject>) -> Result<(), String> {
+    compress_command(args, description, project, false)
+}
@@ -1,5 +1,6 @@
 use std::env::Args;
 use crate::cli::CommandLineParser;
+use crate::project::VerbProject;
 use ringhopper::error::Error;
 use ringhopper::primitives::primitive::TagGroup;
 use ringhopper::tag::convert::get_tag_conversion_fn;
@@ -7,8 +8,9 @@ use ringhopper::tag::tree::TagTree;
 use crate::threading::{DisplayMode, do_with_threads, ProcessSuccessType};
 use crate::util::make_stdout_logger;
 
-pub fn convert(args: Args, description: &'static str) -> Result<(), String> {
+pub fn convert(args: Args, description: &'static str, project: Option<VerbProject>) -> Result<(), String> {
     let parser = CommandLineParser::new(description, "<tag.group*> <group> [args]")
+        .set_project(project)
         .add_tags(false)
         .add_overwrite()
         .add_help()
@@ -1,13 +1,15 @@
 use std::env::Args;
 use crate::cli::{CommandLineParser, Parameter};
+use crate::project::VerbProject;
 use ringhopper::primitives::primitive::{TagGroup, TagPath};
 use ringhopper::tag::dependency::*;
 use ringhopper::tag::tree::TagTree;
 
 use crate::util::make_stdout_logger;
 
-pub fn dependency_list(args: Args, description: &'static str) -> Result<(), String> {
+pub fn dependency_list(args: Args, description: &'static str, project: Option<VerbProject>) -> Result<(), String> {
     let parser = CommandLineParser::new(description, "<tag> [args]")
+        .set_project(project)
         .add_tags(true)
         .add_custom_parameter(Parameter::single(
             "recursive",
@@ -63,8 +65,9 @@ pub fn dependency_list(args: Args, description: &'static str) -> Result<(), Stri
 }
 
 
-pub fn list_scenario_tags(args: Args, description: &'static str) -> Result<(), String> {
+pub fn list_scenario_tags(args: Args, description: &'static str, project: Option<VerbProject>) -> Result<(), String> {
     let parser = CommandLineParser::new(description, "<tag> <args>")
+        .set_project(project)
         .add_tags(true)
         .add_engine()
         .add_help()
@@ -1,13 +1,15 @@
 use std::collections::{HashMap, HashSet};
 use std::env::Args;
 use crate::cli::{CommandLineParser, CommandLineValue, CommandLineValueType, Parameter};
+use crate::project::VerbProject;
 use ringhopper::primitives::primitive::TagPath;
 use ringhopper::tag::dependency::*;
 use ringhopper::tag::tree::{CachingTagTree, CachingTagTreeWriteStrategy, TagFilter, TagTree};
 use crate::util::{make_stdout_logger, StdoutLogger};
 
-pub fn dependency_tree(args: Args, description: &'static str) -> Result<(), String> {
+pub fn dependency_tree(args: Args, description: &'static str, project: Option<VerbProject>) -> Result<(), String> {
     let parser = CommandLineParser::new(description, "<tag*> [args]")
+        .set_project(project)
         .add_tags(true)
         .add_help()
         .add_custom_parameter(Parameter::new("broken", 'b', "Only show broken dependencies (and their dependents)", "", None, 0, None, false, false))
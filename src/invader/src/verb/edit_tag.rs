@@ -0,0 +1,164 @@
+use std::env::Args;
+use std::sync::Arc;
+use crate::cli::{CommandLineParser, CommandLineValueType, Parameter};
+use crate::project::VerbProject;
+use ringhopper::tag::edit::{count_elements, delete_elements, get_values, insert_elements, set_values};
+use ringhopper::tag::tree::{TagFilter, TagTree};
+use crate::threading::{DisplayMode, do_with_threads, ProcessSuccessType};
+use crate::util::make_stdout_logger;
+
+#[derive(Default)]
+struct Operations {
+    delete: Vec<String>,
+    insert: Vec<(String, usize)>,
+    set: Vec<(String, String)>,
+    get: Vec<String>,
+    count: Vec<String>,
+    show_path: bool
+}
+
+impl Operations {
+    fn modifies_tag(&self) -> bool {
+        !self.delete.is_empty() || !self.insert.is_empty() || !self.set.is_empty()
+    }
+}
+
+pub fn edit_tag(args: Args, description: &'static str, project: Option<VerbProject>) -> Result<(), String> {
+    let parser = CommandLineParser::new(description, "<tag.group*> [args]")
+        .set_project(project)
+        .add_tags(false)
+        .add_custom_parameter(Parameter::new(
+            "get",
+            'g',
+            "Output the value of all fields matched by the matcher (e.g. `nodes[0-2].name`).",
+            "<matcher>",
+            Some(CommandLineValueType::String),
+            1,
+            None,
+            true,
+            false
+        ))
+        .add_custom_parameter(Parameter::new(
+            "count",
+            'C',
+            "Output the number of elements in all arrays and reflexives matched by the matcher.",
+            "<matcher>",
+            Some(CommandLineValueType::String),
+            1,
+            None,
+            true,
+            false
+        ))
+        .add_custom_parameter(Parameter::new(
+            "set",
+            's',
+            "Set all fields matched by the matcher. Components are comma-separated, and angles are in degrees.",
+            "<matcher> <value>",
+            Some(CommandLineValueType::String),
+            2,
+            None,
+            true,
+            false
+        ))
+        .add_custom_parameter(Parameter::new(
+            "insert",
+            'i',
+            "Insert elements into all reflexives matched by the matcher, at the end unless an index is given (e.g. `nodes[2]`).",
+            "<matcher> <count>",
+            Some(CommandLineValueType::String),
+            2,
+            None,
+            true,
+            false
+        ))
+        .add_custom_parameter(Parameter::new(
+            "delete",
+            'D',
+            "Delete elements from all reflexives matched by the matcher (e.g. `nodes[1-3]`).",
+            "<matcher>",
+            Some(CommandLineValueType::String),
+            1,
+            None,
+            true,
+            false
+        ))
+        .add_cow_tags()
+        .add_jobs()
+        .add_help()
+        .set_required_extra_parameters(1)
+        .parse(args)?;
+
+    let strings = |name: &'static str| -> Vec<String> {
+        parser.get_custom(name).unwrap_or_default().iter().map(|v| v.string().to_owned()).collect()
+    };
+    let pairs = |name: &'static str| -> Vec<(String, String)> {
+        parser.get_custom(name).unwrap_or_default().chunks(2).map(|v| (v[0].string().to_owned(), v[1].string().to_owned())).collect()
+    };
+
+    let tag = parser.get_extra()[0].clone();
+    let mut operations = Operations {
+        delete: strings("delete"),
+        set: pairs("set"),
+        get: strings("get"),
+        count: strings("count"),
+        show_path: TagFilter::is_filter(&tag),
+        ..Default::default()
+    };
+    for (matcher, count) in pairs("insert") {
+        let count = count.parse().map_err(|_| format!("Invalid element count `{count}` for --insert {matcher}"))?;
+        operations.insert.push((matcher, count));
+    }
+
+    let display_mode = if operations.modifies_tag() {
+        DisplayMode::ShowAll
+    }
+    else if !operations.get.is_empty() || !operations.count.is_empty() {
+        DisplayMode::Silent
+    }
+    else {
+        return Err("No operations given; use --get, --count, --set, --insert, or --delete".to_owned())
+    };
+
+    let logger = make_stdout_logger();
+    do_with_threads(parser.get_virtual_tags_directory(), parser, &tag, None, Arc::new(operations), display_mode, logger, |context, path, operations, logger| {
+        let mut tag = context.tags_directory.open_tag_copy(path)?;
+        let data = tag.as_mut().as_mut_dynamic();
+
+        // Operations are applied in a fixed order regardless of how they were passed: deletions first, then
+        // insertions, then values are set, so that --set can target newly inserted elements.
+        for matcher in &operations.delete {
+            delete_elements(data, matcher)?;
+        }
+        for (matcher, count) in &operations.insert {
+            insert_elements(data, matcher, *count)?;
+        }
+        for (matcher, value) in &operations.set {
+            set_values(data, matcher, value)?;
+        }
+
+        let mut output = Vec::new();
+        for matcher in &operations.get {
+            output.extend(get_values(data, matcher)?);
+        }
+        for matcher in &operations.count {
+            output.extend(count_elements(data, matcher)?.into_iter().map(|c| c.to_string()));
+        }
+
+        if !output.is_empty() {
+            let l = logger.lock();
+            for line in output {
+                if operations.show_path {
+                    l.neutral_fmt_ln(format_args!("{path}: {line}"));
+                }
+                else {
+                    l.neutral_fmt_ln(format_args!("{line}"));
+                }
+            }
+        }
+
+        if !operations.modifies_tag() {
+            return Ok(ProcessSuccessType::Success)
+        }
+        ProcessSuccessType::wrap_write_result(context.tags_directory.write_tag(path, tag.as_ref()))
+    })
+}
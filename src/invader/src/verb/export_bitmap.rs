@@ -0,0 +1,96 @@
+use std::env::Args;
+use crate::cli::{CommandLineParser, CommandLineValueType, Parameter};
+use crate::project::VerbProject;
+use ringhopper::data::bitmap::{Image, load_image_from_path};
+use ringhopper::definitions::{Bitmap, BitmapDataType};
+use ringhopper::error::{Error, RinghopperResult};
+use ringhopper::primitives::primitive::TagGroup;
+use ringhopper::tag::bitmap::{decode_bitmap_data, P8Palette};
+use ringhopper::tag::tree::TagTree;
+use crate::threading::{DisplayMode, do_with_threads, ProcessSuccessType};
+use crate::util::make_stdout_logger;
+
+#[derive(Clone)]
+struct UserData {
+    extension: &'static str,
+    encoder: fn(&Image) -> RinghopperResult<Vec<u8>>,
+    palette: Option<P8Palette>
+}
+
+pub fn export_bitmap(args: Args, description: &'static str, project: Option<VerbProject>) -> Result<(), String> {
+    let parser = CommandLineParser::new(description, "<bitmap*> [args]")
+        .set_project(project)
+        .add_tags(false)
+        .add_data()
+        .add_overwrite()
+        .add_help()
+        .add_jobs()
+        .add_custom_parameter(Parameter::single("image-format", 'F', "Set the image format to export (tif or png). Default: tif", "<format>", Some(CommandLineValueType::String)))
+        .add_custom_parameter(Parameter::single("p8-palette", 'p', "Image with the 256 colors of the palette used for P8 bitmaps, in order. Required to export P8 bitmaps", "<image>", Some(CommandLineValueType::Path)))
+        .set_required_extra_parameters(1)
+        .parse(args)?;
+
+    let (extension, encoder): (&'static str, fn(&Image) -> RinghopperResult<Vec<u8>>) = match parser.get_custom("image-format").map(|f| f[0].string()) {
+        None | Some("tif") | Some("tiff") => ("tif", |image| Ok(image.to_tiff())),
+        Some("png") => ("png", Image::to_png),
+        Some(n) => return Err(format!("Invalid image format `{n}`; valid options are: tif, png"))
+    };
+
+    let palette = match parser.get_custom("p8-palette") {
+        Some(p) => {
+            let path = p[0].path();
+            let image = str_unwrap!(load_image_from_path(path), "Failed to read {path:?}: {error}");
+            let palette: P8Palette = image.data.try_into().map_err(|d: Vec<_>| format!("{path:?} has {} colors, but a P8 palette has 256", d.len()))?;
+            Some(palette)
+        },
+        None => None
+    };
+
+    let user_data = UserData { extension, encoder, palette };
+
+    let tag = parser.get_extra()[0].clone();
+    do_with_threads(parser.get_virtual_tags_directory(), parser, &tag, Some(TagGroup::Bitmap), user_data, DisplayMode::ShowAll, make_stdout_logger(), |context, path, user_data, _| {
+        let tag = context.tags_directory.open_tag_copy(path)?;
+        let bitmap = tag.as_any().downcast_ref::<Bitmap>().unwrap();
+        if bitmap.bitmap_data.items.is_empty() {
+            return Ok(ProcessSuccessType::Skipped("no bitmap data in tag"))
+        }
+
+        let output_dir = context
+            .args
+            .get_data()
+            .join(path.to_native_path())
+            .with_file_name(format!("{}_bitmap_data", path.base_name()));
+
+        let mut anything_saved = false;
+        for (index, data) in bitmap.bitmap_data.items.iter().enumerate() {
+            let multiple_faces = data._type != BitmapDataType::_2dTexture;
+
+            for face in decode_bitmap_data(bitmap, index, user_data.palette.as_ref())? {
+                let mut name = format!("{index}");
+                if multiple_faces {
+                    name += &format!("_face{}", face.face_index);
+                }
+                if face.mipmap_index > 0 {
+                    name += &format!("_mip{}", face.mipmap_index);
+                }
+
+                let file = output_dir.join(name).with_extension(user_data.extension);
+                if !context.args.get_overwrite() && file.exists() {
+                    continue;
+                }
+
+                std::fs::create_dir_all(&output_dir).map_err(|e| Error::FailedToWriteFile(output_dir.clone(), e))?;
+                std::fs::write(&file, (user_data.encoder)(&face.image)?).map_err(|e| Error::FailedToWriteFile(file, e))?;
+                anything_saved = true;
+            }
+        }
+
+        Ok(if anything_saved {
+            ProcessSuccessType::Success
+        }
+        else {
+            ProcessSuccessType::Skipped("all images already exist")
+        })
+    })
+}
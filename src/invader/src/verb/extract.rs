@@ -2,6 +2,7 @@ use std::env::Args;
 use std::path::Path;
 use std::sync::Arc;
 use crate::cli::{CommandLineParser, CommandLineValue, CommandLineValueType, Parameter};
+use crate::project::VerbProject;
 use ringhopper::definitions::ScenarioType;
 use ringhopper::map::load_map_from_filesystem;
 use ringhopper::primitives::primitive::TagGroup;
@@ -11,8 +12,9 @@ use ringhopper::tag::tree::{TagTree, VirtualTagsDirectory};
 use crate::threading::{DisplayMode, do_with_threads, ProcessSuccessType};
 use crate::util::make_stdout_logger;
 
-pub fn extract(args: Args, description: &'static str) -> Result<(), String> {
+pub fn extract(args: Args, description: &'static str, project: Option<VerbProject>) -> Result<(), String> {
     let parser = CommandLineParser::new(description, "<map>")
+        .set_project(project)
         .add_tags(false)
         .add_overwrite()
         .add_help()
@@ -5,10 +5,12 @@ use ringhopper::map::{load_map_from_filesystem, MapTagTree};
 use ringhopper::primitives::dynamic::DynamicTagDataArray;
 use ringhopper::primitives::tag::ParseStrictness;
 use crate::cli::{CommandLineParser, CommandLineValue, CommandLineValueType, Parameter};
+use crate::project::VerbProject;
 use crate::util::bytes_to_mib;
 
-pub fn info(args: Args, description: &'static str) -> Result<(), String> {
+pub fn info(args: Args, description: &'static str, project: Option<VerbProject>) -> Result<(), String> {
     let parser = CommandLineParser::new(description, "<map> [args]")
+        .set_project(project)
         .add_help()
         .add_custom_parameter(
             Parameter::new(
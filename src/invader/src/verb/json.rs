@@ -0,0 +1,113 @@
+use std::env::Args;
+use std::path::{Path, PathBuf};
+use crate::cli::{CommandLineArgs, CommandLineParser};
+use crate::project::VerbProject;
+use ringhopper::error::Error;
+use ringhopper::primitives::primitive::TagPath;
+use ringhopper::tag::json::{tag_from_json, tag_to_json};
+use ringhopper::tag::tree::{TagFilter, TagTree};
+use crate::threading::{DisplayMode, do_with_threads, do_with_threads_for_tags, ProcessSuccessType};
+use crate::util::{make_stdout_logger, read_file};
+
+/// Get the path to the JSON file for a tag (e.g. `data/weapons/pistol/pistol.weapon.json`).
+fn json_path(args: &CommandLineArgs, path: &TagPath) -> PathBuf {
+    let mut file = args.get_data().join(path.to_native_path()).into_os_string();
+    file.push(".json");
+    file.into()
+}
+
+/// Find all tags with a JSON file in `directory` (e.g. `weapons/pistol/pistol.weapon.json`) that pass `filter`.
+fn find_json_tags(data: &Path, directory: &Path, filter: &TagFilter, tags: &mut Vec<TagPath>) -> Result<(), String> {
+    let entries = str_unwrap!(std::fs::read_dir(directory), "Cannot read {}: {error}", directory.display());
+    for entry in entries {
+        let path = str_unwrap!(entry, "Cannot read {}: {error}", directory.display()).path();
+        if path.is_dir() {
+            find_json_tags(data, &path, filter, tags)?;
+            continue
+        }
+
+        if !path.extension().is_some_and(|e| e.eq_ignore_ascii_case("json")) {
+            continue
+        }
+
+        // Files that are not named after a tag (e.g. unknown groups) are not tags to import.
+        let Some(tag_path) = path
+            .strip_prefix(data)
+            .ok()
+            .and_then(|p| p.with_extension("").to_str().map(str::to_owned))
+            .and_then(|p| TagPath::from_path(&p).ok()) else {
+            continue
+        };
+        if filter.passes(&tag_path) {
+            tags.push(tag_path);
+        }
+    }
+    Ok(())
+}
+
+pub fn export_json(args: Args, description: &'static str, project: Option<VerbProject>) -> Result<(), String> {
+    let parser = CommandLineParser::new(description, "<tag.group*> [args]")
+        .set_project(project)
+        .add_tags(false)
+        .add_data()
+        .add_overwrite()
+        .add_help()
+        .add_jobs()
+        .set_required_extra_parameters(1)
+        .parse(args)?;
+
+    let tag = parser.get_extra()[0].clone();
+    do_with_threads(parser.get_virtual_tags_directory(), parser, &tag, None, (), DisplayMode::ShowAll, make_stdout_logger(), |context, path, _, _| {
+        let file = json_path(&context.args, path);
+        if !context.args.get_overwrite() && file.exists() {
+            return Ok(ProcessSuccessType::Skipped("JSON file already exists"))
+        }
+
+        let tag = context.tags_directory.open_tag_copy(path)?;
+        let json = serde_json::to_string_pretty(&tag_to_json(tag.as_ref())).unwrap();
+
+        if let Some(parent) = file.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| Error::FailedToWriteFile(parent.to_owned(), e))?;
+        }
+        std::fs::write(&file, json).map_err(|e| Error::FailedToWriteFile(file, e))?;
+        Ok(ProcessSuccessType::Success)
+    })
+}
+
+pub fn import_json(args: Args, description: &'static str, project: Option<VerbProject>) -> Result<(), String> {
+    let parser = CommandLineParser::new(description, "<tag.group*> [args]")
+        .set_project(project)
+        .add_tags(false)
+        .add_data()
+        .add_help()
+        .add_cow_tags()
+        .add_jobs()
+        .set_required_extra_parameters(1)
+        .parse(args)?;
+
+    // Look in data rather than the tags directory, since tags that do not exist yet can be imported, too.
+    let tag = parser.get_extra()[0].clone();
+    let tags = if TagFilter::is_filter(&tag) {
+        let mut tags = Vec::new();
+        find_json_tags(parser.get_data(), parser.get_data(), &TagFilter::new(&tag, None), &mut tags)?;
+        tags.sort();
+        tags
+    }
+    else {
+        vec![str_unwrap!(TagPath::from_path(&tag), "Invalid tag path `{tag}`: {error}")]
+    };
+
+    do_with_threads_for_tags(parser.get_virtual_tags_directory(), parser, &tag, tags, (), DisplayMode::ShowAll, make_stdout_logger(), |context, path, _, _| {
+        let file = json_path(&context.args, path);
+        let text = read_file(&file)?;
+        let text = std::str::from_utf8(&text).map_err(|_| Error::Other(format!("{} is not valid UTF-8", file.display())))?;
+
+        let json = serde_json::from_str(text).map_err(|e| Error::Other(format!("{} is not valid JSON: {e}", file.display())))?;
+        let tag = tag_from_json(&json)?;
+        if tag.group() != path.group() {
+            return Err(Error::Other(format!("{} contains a {} tag, not a {} tag", file.display(), tag.group(), path.group())))
+        }
+
+        ProcessSuccessType::wrap_write_result(context.tags_directory.write_tag(path, tag.as_ref()))
+    })
+}
@@ -1,8 +1,9 @@
 use std::env::Args;
 use ringhopper::primitives::engine::Engine;
 use ringhopper_engines::{EngineCompressionType, ALL_SUPPORTED_ENGINES};
+use crate::project::VerbProject;
 
-pub fn list_engines(mut args: Args, _description: &'static str) -> Result<(), String> {
+pub fn list_engines(mut args: Args, _description: &'static str, _project: Option<VerbProject>) -> Result<(), String> {
     if let Some(n) = args.next() {
         match ALL_SUPPORTED_ENGINES.binary_search_by(|e| e.name.cmp(n.as_str())) {
             Ok(n) => {
@@ -0,0 +1,105 @@
+use std::env::Args;
+use std::path::Path;
+use ringhopper::map::load_map_from_filesystem;
+use ringhopper::map::map_diff::{diff_maps, MapDiffOptions, MapTagLocation};
+use ringhopper::primitives::map::DomainType;
+use ringhopper::primitives::tag::ParseStrictness;
+use crate::cli::{CommandLineParser, Parameter};
+use crate::project::VerbProject;
+use crate::util::make_stdout_logger;
+use super::compare::display_diff;
+
+pub fn map_diff(args: Args, description: &'static str, project: Option<VerbProject>) -> Result<(), String> {
+    let parser = CommandLineParser::new(description, "<map1> <map2> [args]")
+        .set_project(project)
+        .add_help()
+        .add_custom_parameter(Parameter::single("verbose", 'v', "Display each difference in changed tags.", "", None))
+        .add_custom_parameter(Parameter::single("no-contents", 'C', "Do not extract and compare tag contents; only compare cache data.", "", None))
+        .set_required_extra_parameters(2)
+        .parse(args)?;
+
+    let load = |path: &str| {
+        let path = Path::new(path);
+        load_map_from_filesystem(path, ParseStrictness::Relaxed).map_err(|e| format!("Cannot load {path:?} as a cache file: {e}"))
+    };
+
+    let first = load(&parser.get_extra()[0])?;
+    let second = load(&parser.get_extra()[1])?;
+    let verbose = parser.get_custom("verbose").is_some();
+
+    let options = MapDiffOptions {
+        compare_tag_contents: parser.get_custom("no-contents").is_none(),
+        ..Default::default()
+    };
+    let diff = diff_maps(first.as_ref(), second.as_ref(), &options);
+
+    let logger = make_stdout_logger();
+    if diff.is_empty() {
+        logger.success_fmt_ln(format_args!("No differences found"));
+        return Ok(())
+    }
+
+    for i in &diff.header {
+        logger.warning_fmt_ln(format_args!("Header {}: {} -> {}", i.field, i.first, i.second));
+    }
+
+    let layout = |layout: Option<(usize, usize)>| match layout {
+        Some((size, base)) => format!("{size} bytes @ 0x{base:08X}"),
+        None => "none".to_owned()
+    };
+    for i in &diff.domains {
+        let changed = if i.data_changed { " (data changed)" } else { "" };
+        logger.warning_fmt_ln(format_args!("Domain {}: {} -> {}{changed}", format_domain(&i.domain), layout(i.first), layout(i.second)));
+    }
+
+    for i in &diff.removed_tags {
+        logger.error_fmt_ln(format_args!("Removed: {i}"));
+    }
+    for i in &diff.added_tags {
+        logger.success_fmt_ln(format_args!("Added: {i}"));
+    }
+
+    for i in &diff.changed_tags {
+        logger.warning_fmt_ln(format_args!("Changed: {}", i.path));
+        if i.id_changed() {
+            logger.neutral_fmt_ln(format_args!("    ID: #{} -> #{}", i.first.index, i.second.index));
+        }
+        if i.location_changed() {
+            logger.neutral_fmt_ln(format_args!("    Location: {} -> {}", format_location(&i.first), format_location(&i.second)));
+        }
+        if i.content_changed() {
+            logger.neutral_fmt_ln(format_args!("    Contents: {} difference(s)", i.differences.len()));
+            if verbose {
+                display_diff(&i.differences, &logger);
+            }
+        }
+    }
+
+    logger.neutral_fmt_ln(format_args!(
+        "{} header field(s), {} domain(s), {} added, {} removed, {} changed",
+        diff.header.len(),
+        diff.domains.len(),
+        diff.added_tags.len(),
+        diff.removed_tags.len(),
+        diff.changed_tags.len()
+    ));
+
+    Ok(())
+}
+
+fn format_domain(domain: &DomainType) -> String {
+    match domain {
+        DomainType::MapData => "map data".to_owned(),
+        DomainType::TagData => "tag data".to_owned(),
+        DomainType::BSP(i) => format!("BSP #{i}"),
+        DomainType::BSPVertices(i) => format!("BSP #{i} vertices"),
+        DomainType::ResourceMapFile(r) => format!("{r:?} resource map"),
+        DomainType::ResourceMapEntry(r, path) => format!("{r:?} resource {path}"),
+        DomainType::ModelVertexData => "model vertices".to_owned(),
+        DomainType::ModelTriangleData => "model triangles".to_owned()
+    }
+}
+
+fn format_location(location: &MapTagLocation) -> String {
+    format!("0x{:08X} in {}", location.address, format_domain(&location.domain))
+}
@@ -0,0 +1,107 @@
+use std::env::Args;
+use std::path::Path;
+use crate::cli::{CommandLineParser, CommandLineValueType, Parameter};
+use crate::project::VerbProject;
+use ringhopper::data::jms::load_model_sources;
+use ringhopper::definitions::{GBXModel, Model};
+use ringhopper::error::{Error, RinghopperResult};
+use ringhopper::primitives::primitive::{TagGroup, TagPath, TagReference};
+use ringhopper::primitives::tag::PrimaryTagStructDyn;
+use ringhopper::tag::model::{compile_gbxmodel, compile_model, ModelCompileOptions};
+use ringhopper::tag::tree::{TagTree, VirtualTagsDirectory};
+use crate::threading::{DisplayMode, do_with_threads, ProcessSuccessType};
+use crate::util::make_stdout_logger;
+
+pub(crate) const SHADER_GROUPS: &[TagGroup] = &[
+    TagGroup::ShaderEnvironment,
+    TagGroup::ShaderModel,
+    TagGroup::ShaderTransparentChicago,
+    TagGroup::ShaderTransparentChicagoExtended,
+    TagGroup::ShaderTransparentGeneric,
+    TagGroup::ShaderTransparentGlass,
+    TagGroup::ShaderTransparentMeter,
+    TagGroup::ShaderTransparentPlasma,
+    TagGroup::ShaderTransparentWater
+];
+
+pub fn model(args: Args, description: &'static str, project: Option<VerbProject>) -> Result<(), String> {
+    let parser = CommandLineParser::new(description, "<model*> [args]")
+        .set_project(project)
+        .add_tags(false)
+        .add_data()
+        .add_engine()
+        .add_help()
+        .add_cow_tags()
+        .add_jobs()
+        .add_custom_parameter(Parameter::new("lod-cutoffs", 'L', "Set the LoD cutoffs in pixels, from super low to super high. Default: existing tag's cutoffs", "<sl> <l> <m> <h> <sh>", Some(CommandLineValueType::Float), 5, None, false, false))
+        .set_required_extra_parameters(1)
+        .parse(args)?;
+
+    let options = ModelCompileOptions {
+        lod_cutoffs: parser.get_custom("lod-cutoffs").map(|c| [c[0].float(), c[1].float(), c[2].float(), c[3].float(), c[4].float()])
+    };
+
+    // Xbox uses model tags; everything else uses gbxmodel tags.
+    let group = if parser.get_engine().compressed_models { TagGroup::Model } else { TagGroup::GBXModel };
+
+    let tag = parser.get_extra()[0].clone();
+    do_with_threads(parser.get_virtual_tags_directory(), parser, &tag, Some(group), options, DisplayMode::ShowAll, make_stdout_logger(), |context, path, options, _| {
+        let tag_directory = Path::new(&path.to_native_path()).parent().map(|p| p.to_path_buf()).unwrap_or_default();
+        let data_path = context.args.get_data().join(tag_directory).join("models");
+        if !data_path.is_dir() {
+            return Ok(ProcessSuccessType::Skipped("no models directory to import in data"))
+        }
+
+        let sources = load_model_sources(&data_path)?;
+        let exists = context.tags_directory.contains(path);
+        let shader_lookup = |name: &str| find_shader(&context.tags_directory, path, name, SHADER_GROUPS);
+
+        let tag: Box<dyn PrimaryTagStructDyn> = match path.group() {
+            TagGroup::Model => {
+                let mut tag = if exists { context.tags_directory.open_tag_copy(path)?.as_any().downcast_ref::<Model>().unwrap().to_owned() } else { Model::default() };
+                compile_model(&mut tag, &sources, options, shader_lookup)?;
+                Box::new(tag)
+            },
+            TagGroup::GBXModel => {
+                let mut tag = if exists { context.tags_directory.open_tag_copy(path)?.as_any().downcast_ref::<GBXModel>().unwrap().to_owned() } else { GBXModel::default() };
+                compile_gbxmodel(&mut tag, &sources, options, shader_lookup)?;
+                Box::new(tag)
+            },
+            _ => unreachable!()
+        };
+
+        ProcessSuccessType::wrap_write_result(context.tags_directory.write_tag(path, tag.as_ref()))
+    })
+}
+
+/// Find a shader for a material.
+///
+/// Shaders in the `shaders` directory next to the tag are preferred. Otherwise, every tag in `groups` is searched.
+pub(crate) fn find_shader(tags_directory: &VirtualTagsDirectory, tag_path: &TagPath, name: &str, groups: &[TagGroup]) -> RinghopperResult<TagReference> {
+    let name = name.to_ascii_lowercase();
+    let shaders_directory = match tag_path.path().rsplit_once('\\') {
+        Some((directory, _)) => format!("{directory}\\shaders\\{name}"),
+        None => format!("shaders\\{name}")
+    };
+
+    for group in groups {
+        if let Ok(path) = TagPath::new(&shaders_directory, *group) {
+            if tags_directory.contains(&path) {
+                return Ok(TagReference::Set(path))
+            }
+        }
+    }
+
+    let mut matches: Vec<TagPath> = tags_directory
+        .get_all_tags_with_filter(None)
+        .into_iter()
+        .filter(|p| groups.contains(&p.group()) && p.base_name() == name)
+        .collect();
+    matches.sort();
+
+    matches
+        .into_iter()
+        .next()
+        .map(TagReference::Set)
+        .ok_or_else(|| Error::Other(format!("no shader tag found for material `{name}`")))
+}
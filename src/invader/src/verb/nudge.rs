@@ -1,12 +1,14 @@
 use std::env::Args;
 use crate::cli::CommandLineParser;
+use crate::project::VerbProject;
 use ringhopper::tag::nudge::{is_nudgeable, nudge_tag};
 use ringhopper::tag::tree::TagTree;
 use crate::threading::{DisplayMode, do_with_threads, ProcessSuccessType};
 use crate::util::make_stdout_logger;
 
-pub fn nudge(args: Args, description: &'static str) -> Result<(), String> {
+pub fn nudge(args: Args, description: &'static str, project: Option<VerbProject>) -> Result<(), String> {
     let parser = CommandLineParser::new(description, "<tag.group*> [args]")
+        .set_project(project)
         .add_tags(false)
         .add_help()
         .add_cow_tags()
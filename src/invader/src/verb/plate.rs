@@ -1,13 +1,15 @@
 use std::env::Args;
 use crate::cli::CommandLineParser;
+use crate::project::VerbProject;
 use ringhopper::data::bitmap::plate::make_color_plate_from_loose;
 use ringhopper::error::Error;
 use ringhopper::primitives::primitive::TagGroup;
 use crate::threading::{DisplayMode, do_with_threads, ProcessSuccessType};
 use crate::util::make_stdout_logger;
 
-pub fn plate(args: Args, description: &'static str) -> Result<(), String> {
+pub fn plate(args: Args, description: &'static str, project: Option<VerbProject>) -> Result<(), String> {
     let parser = CommandLineParser::new(description, "<bitmap*> [args]")
+        .set_project(project)
         .add_tags(false)
         .add_data()
         .add_help()
@@ -1,5 +1,6 @@
 use std::env::Args;
 use crate::cli::CommandLineParser;
+use crate::project::VerbProject;
 use ringhopper::definitions::ScenarioStructureBSP;
 use ringhopper::error::RinghopperResult;
 use ringhopper::primitives::primitive::{TagGroup, TagPath};
@@ -9,8 +10,9 @@ use ringhopper::tag::tree::{TagTree, VirtualTagsDirectory};
 use crate::threading::{DisplayMode, do_with_threads, ProcessSuccessType};
 use crate::util::make_stdout_logger;
 
-pub fn recompress_vertices(args: Args, description: &'static str) -> Result<(), String> {
+pub fn recompress_vertices(args: Args, description: &'static str, project: Option<VerbProject>) -> Result<(), String> {
     let parser = CommandLineParser::new(description, "<tag.group*> [args]")
+        .set_project(project)
         .add_tags(false)
         .add_help()
         .add_cow_tags()
@@ -3,6 +3,7 @@ use std::path::PathBuf;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use crate::cli::CommandLineParser;
+use crate::project::VerbProject;
 use ringhopper::error::{Error, RinghopperResult};
 use ringhopper::primitives::primitive::TagPath;
 use ringhopper::tag::recover::get_recover_function;
@@ -21,8 +22,9 @@ struct UserData {
     error: AtomicUsize
 }
 
-pub fn recover(args: Args, description: &'static str) -> Result<(), String> {
+pub fn recover(args: Args, description: &'static str, project: Option<VerbProject>) -> Result<(), String> {
     let parser = CommandLineParser::new(description, "<tag.group*> [args]")
+        .set_project(project)
         .add_tags(false)
         .add_data()
         .add_help()
@@ -1,13 +1,15 @@
 use std::env::Args;
 use crate::cli::CommandLineParser;
+use crate::project::VerbProject;
 use ringhopper::{definitions::get_all_referenceable_tag_groups_for_group, primitives::primitive::TagGroup, tag::{dependency::refactor_groups_for_block, tree::TagTree}};
 use crate::threading::{DisplayMode, do_with_threads, ProcessSuccessType};
 use crate::util::make_stdout_logger;
 
 use crate::cli::*;
 
-pub fn refactor_groups(args: Args, description: &'static str) -> Result<(), String> {
+pub fn refactor_groups(args: Args, description: &'static str, project: Option<VerbProject>) -> Result<(), String> {
     let parser = CommandLineParser::new(description, "<group-from> <group-to> [args]")
+        .set_project(project)
         .add_tags(false)
         .add_help()
         .add_cow_tags()
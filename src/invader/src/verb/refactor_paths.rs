@@ -2,6 +2,7 @@ use std::env::Args;
 use std::num::NonZeroUsize;
 use std::time::Instant;
 use crate::cli::CommandLineParser;
+use crate::project::VerbProject;
 use ringhopper::tag::tree::TagFilter;
 use ringhopper::tag::dependency::{refactor_paths_for_tag_tree, ReplaceType};
 use crate::util::make_stdout_logger;
@@ -9,8 +10,9 @@ use crate::verb::print_tag_results;
 
 use crate::cli::*;
 
-pub fn refactor_paths(args: Args, description: &'static str) -> Result<(), String> {
+pub fn refactor_paths(args: Args, description: &'static str, project: Option<VerbProject>) -> Result<(), String> {
     let parser = CommandLineParser::new(description, "<find> <replace> [args]")
+        .set_project(project)
         .add_tags(true)
         .add_help()
         .add_jobs()
@@ -0,0 +1,77 @@
+use std::collections::BTreeSet;
+use std::env::Args;
+use crate::cli::{CommandLineParser, CommandLineValueType, Parameter};
+use crate::project::VerbProject;
+use ringhopper::map::resource::ResourceMapBuilder;
+use ringhopper::primitives::map::ResourceMapType;
+use ringhopper::primitives::primitive::{TagGroup, TagPath};
+use ringhopper::tag::dependency::recursively_get_dependencies_for_map;
+use ringhopper::tag::tree::{CachingTagTree, CachingTagTreeWriteStrategy, TagTree};
+use crate::util::{bytes_to_mib, make_stdout_logger};
+
+pub fn resource(args: Args, description: &'static str, project: Option<VerbProject>) -> Result<(), String> {
+    let parser = CommandLineParser::new(description, "<bitmaps|sounds|loc> [args]")
+        .set_project(project)
+        .add_tags(true)
+        .add_maps()
+        .add_engine()
+        .add_overwrite()
+        .add_help()
+        .add_custom_parameter(Parameter::new("scenario", 's', "Add the tags used by a scenario. This can be used multiple times.", "<scenario>", Some(CommandLineValueType::String), 1, None, true, true))
+        .add_custom_parameter(Parameter::single("output", 'O', "Output filename. Default: <maps>/<type>.map", "<file>", Some(CommandLineValueType::Path)))
+        .set_required_extra_parameters(1)
+        .parse(args)?;
+
+    let logger = make_stdout_logger();
+    let engine = parser.get_engine();
+
+    let type_name = parser.get_extra()[0].as_str();
+    let (resource_type, groups): (ResourceMapType, &[TagGroup]) = match type_name {
+        "bitmaps" => (ResourceMapType::Bitmaps, &[TagGroup::Bitmap]),
+        "sounds" => (ResourceMapType::Sounds, &[TagGroup::Sound]),
+        "loc" => (ResourceMapType::Loc, &[TagGroup::Font, TagGroup::HUDMessageText, TagGroup::UnicodeStringList]),
+        n => return Err(format!("Unknown resource map type `{n}`; expected bitmaps, sounds, or loc"))
+    };
+
+    let Some(resource_maps) = engine.resource_maps else {
+        return Err(format!("`{}` does not use resource maps", engine.name))
+    };
+    if resource_type == ResourceMapType::Loc && !resource_maps.externally_indexed_tags {
+        return Err(format!("`{}` does not use loc.map", engine.name))
+    }
+
+    let output = parser.get_custom("output").map_or_else(
+        || parser.get_maps().join(format!("{type_name}.map")),
+        |o| o[0].path().to_owned()
+    );
+    if !parser.get_overwrite() && output.exists() {
+        logger.warning_fmt_ln(format_args!("{output:?} already exists; skipping"));
+        return Ok(())
+    }
+
+    let cache = CachingTagTree::new(parser.get_virtual_tags_directory(), CachingTagTreeWriteStrategy::Manual);
+
+    let mut tags = BTreeSet::new();
+    for scenario in parser.get_custom("scenario").unwrap() {
+        let scenario = str_unwrap!(TagPath::new(scenario.string(), TagGroup::Scenario), "Invalid scenario path: {error}");
+        let dependencies = str_unwrap!(recursively_get_dependencies_for_map(&scenario, &cache, engine), "Failed to get the dependencies of {scenario}: {error}");
+        tags.extend(dependencies.into_iter().filter(|t| groups.contains(&t.group())));
+    }
+
+    let mut builder = str_unwrap!(ResourceMapBuilder::new(resource_type, engine.data_alignment), "Failed to create the resource map: {error}");
+    for path in &tags {
+        let tag = str_unwrap!(cache.open_tag_copy(path), "Failed to open {path}: {error}");
+        str_unwrap!(builder.add_tag(path, tag.as_ref(), engine), "Failed to add {path}: {error}");
+    }
+
+    let map = str_unwrap!(builder.build(), "Failed to build {output:?}: {error}");
+    str_unwrap!(std::fs::write(&output, map.data()), "Failed to write {output:?}: {error}");
+
+    let l = logger.lock();
+    l.neutral_fmt_ln(format_args!("Tags:           {}", tags.len()));
+    l.neutral_fmt_ln(format_args!("Resources:      {}", map.len()));
+    l.neutral_fmt_ln(format_args!("File size:      {}", bytes_to_mib(map.data().len())));
+    l.success_fmt_ln(format_args!("Wrote {output:?}"));
+
+    Ok(())
+}
@@ -0,0 +1,104 @@
+use std::env::Args;
+use crate::cli::{CommandLineParser, CommandLineValueType, Parameter};
+use crate::project::VerbProject;
+use ringhopper::data::sound::load_sound_sources;
+use ringhopper::definitions::{Sound, SoundChannelCount, SoundClass, SoundFormat};
+use ringhopper::primitives::primitive::TagGroup;
+use ringhopper::tag::sound::{compile_sound, sample_rate_from_u32, SoundCompileOptions};
+use ringhopper::tag::tree::TagTree;
+use crate::threading::{DisplayMode, do_with_threads, ProcessSuccessType};
+use crate::util::{make_stdout_logger, parse_enum};
+
+#[derive(Clone)]
+struct UserData {
+    options: SoundCompileOptions,
+    sound_class: Option<SoundClass>
+}
+
+pub fn sound(args: Args, description: &'static str, project: Option<VerbProject>) -> Result<(), String> {
+    let parser = CommandLineParser::new(description, "<sound*> [args]")
+        .set_project(project)
+        .add_tags(false)
+        .add_data()
+        .add_help()
+        .add_cow_tags()
+        .add_jobs()
+        .add_custom_parameter(Parameter::single("format", 'F', "Set the sound format. Default: existing tag's format", "<format>", Some(CommandLineValueType::String)))
+        .add_custom_parameter(Parameter::single("class", 'c', "Set the sound class. Default: existing tag's class", "<class>", Some(CommandLineValueType::String)))
+        .add_custom_parameter(Parameter::single("sample-rate", 'r', "Resample to the given sample rate (22050 or 44100). Default: 44100 if any source exceeds 22050, otherwise 22050", "<rate>", Some(CommandLineValueType::UInteger)))
+        .add_custom_parameter(Parameter::single("channel-count", 'C', "Convert to the given channel count (1 or 2). Default: highest channel count of the sources", "<count>", Some(CommandLineValueType::UInteger)))
+        .add_custom_parameter(Parameter::single("quality", 'q', "Set the Ogg Vorbis quality, from -0.1 to 1.0. Default: 1.0", "<quality>", Some(CommandLineValueType::Float)))
+        .add_custom_parameter(Parameter::single("split", 's', "Split long permutations into subpermutations. This is needed for long sounds such as music.", "", None))
+        .add_custom_parameter(Parameter::single("no-split", 'S', "Do not split long permutations into subpermutations.", "", None))
+        .set_required_extra_parameters(1)
+        .parse(args)?;
+
+    let split = match (parser.get_custom("split").is_some(), parser.get_custom("no-split").is_some()) {
+        (true, true) => return Err("--split and --no-split cannot be used together".to_owned()),
+        (true, false) => Some(true),
+        (false, true) => Some(false),
+        (false, false) => None
+    };
+
+    let sample_rate = match parser.get_custom("sample-rate").map(|r| r[0].uinteger()) {
+        Some(n) => Some(sample_rate_from_u32(n).ok_or_else(|| format!("Invalid sample rate {n}; valid options are: 22050, 44100"))?),
+        None => None
+    };
+
+    let channel_count = match parser.get_custom("channel-count").map(|c| c[0].uinteger()) {
+        Some(1) => Some(SoundChannelCount::Mono),
+        Some(2) => Some(SoundChannelCount::Stereo),
+        Some(n) => return Err(format!("Invalid channel count {n}; valid options are: 1, 2")),
+        None => None
+    };
+
+    let ogg_vorbis_quality = parser.get_custom("quality").map(|q| q[0].float()).unwrap_or(1.0);
+    if !(-0.1..=1.0).contains(&ogg_vorbis_quality) {
+        return Err(format!("Invalid quality {ogg_vorbis_quality}; quality must be between -0.1 and 1.0"))
+    }
+
+    let user_data = UserData {
+        options: SoundCompileOptions {
+            format: parse_enum(parser.get_custom("format").map(|f| f[0].string()), "format")?,
+            sample_rate,
+            channel_count,
+            split_long_permutations: split,
+            ogg_vorbis_quality
+        },
+        sound_class: parse_enum(parser.get_custom("class").map(|c| c[0].string()), "class")?
+    };
+
+    if user_data.options.format == Some(SoundFormat::ImaADPCM) {
+        return Err("Encoding IMA ADPCM is not supported".to_owned())
+    }
+
+    let tag = parser.get_extra()[0].clone();
+    do_with_threads(parser.get_virtual_tags_directory(), parser, &tag, Some(TagGroup::Sound), user_data, DisplayMode::ShowAll, make_stdout_logger(), |context, path, user_data, _| {
+        let data_path = context
+            .args
+            .get_data()
+            .join(path.to_native_path())
+            .with_extension("");
+
+        if !data_path.is_dir() {
+            return Ok(ProcessSuccessType::Skipped("no directory to import in data"))
+        }
+
+        let sources = load_sound_sources(&data_path)?;
+
+        let mut tag = if context.tags_directory.contains(path) {
+            let mut tag = context.tags_directory.open_tag_copy(path)?;
+            tag.as_any_mut().downcast_mut::<Sound>().unwrap().to_owned()
+        }
+        else {
+            Sound::default()
+        };
+
+        if let Some(sound_class) = user_data.sound_class {
+            tag.sound_class = sound_class;
+        }
+
+        compile_sound(&mut tag, &sources, &user_data.options)?;
+        ProcessSuccessType::wrap_write_result(context.tags_directory.write_tag(path, &tag))
+    })
+}
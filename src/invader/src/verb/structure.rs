@@ -0,0 +1,61 @@
+use std::collections::HashMap;
+use std::env::Args;
+use std::path::Path;
+use crate::cli::CommandLineParser;
+use crate::project::VerbProject;
+use ringhopper::data::jms::load_jms_from_path;
+use ringhopper::definitions::ScenarioStructureBSP;
+use ringhopper::error::Error;
+use ringhopper::primitives::primitive::TagGroup;
+use ringhopper::tag::scenario_structure_bsp::compile_scenario_structure_bsp;
+use ringhopper::tag::tree::TagTree;
+use crate::threading::{DisplayMode, do_with_threads, ProcessSuccessType};
+use crate::util::make_stdout_logger;
+use crate::verb::model::{find_shader, SHADER_GROUPS};
+use crate::verb::print_tag_results;
+
+pub fn structure(args: Args, description: &'static str, project: Option<VerbProject>) -> Result<(), String> {
+    let parser = CommandLineParser::new(description, "<scenario_structure_bsp*> [args]")
+        .set_project(project)
+        .add_tags(false)
+        .add_data()
+        .add_help()
+        .add_cow_tags()
+        .add_jobs()
+        .set_required_extra_parameters(1)
+        .parse(args)?;
+
+    let tag = parser.get_extra()[0].clone();
+    do_with_threads(parser.get_virtual_tags_directory(), parser, &tag, Some(TagGroup::ScenarioStructureBSP), (), DisplayMode::ShowAll, make_stdout_logger(), |context, path, _, logger| {
+        let native_path = path.to_native_path();
+        let native_path = Path::new(&native_path);
+        let tag_directory = native_path.parent().map(|p| p.to_path_buf()).unwrap_or_default();
+        let jms_path = context.args.get_data().join(tag_directory).join("models").join(format!("{}.jms", path.base_name()));
+        if !jms_path.is_file() {
+            return Ok(ProcessSuccessType::Skipped("no JMS file to import in data"))
+        }
+
+        let jms = load_jms_from_path(&jms_path)?;
+        let mut tag = if context.tags_directory.contains(path) {
+            context.tags_directory.open_tag_copy(path)?.as_any().downcast_ref::<ScenarioStructureBSP>().unwrap().to_owned()
+        }
+        else {
+            ScenarioStructureBSP::default()
+        };
+
+        // Fog planes are made from materials that resolve to fog tags.
+        let material_groups: Vec<TagGroup> = SHADER_GROUPS.iter().copied().chain([TagGroup::Fog]).collect();
+        let result = compile_scenario_structure_bsp(&mut tag, &jms, |name| find_shader(&context.tags_directory, path, name, &material_groups))?;
+
+        let has_errors = !result.is_ok();
+        if has_errors || !result.warnings.is_empty() {
+            let results = HashMap::from([(path.clone(), result)]);
+            print_tag_results(&logger.lock(), &results, format_args!("Compiled {path}"));
+        }
+        if has_errors {
+            return Err(Error::Other(format!("{} has errors in its level geometry", jms_path.display())))
+        }
+
+        ProcessSuccessType::wrap_write_result(context.tags_directory.write_tag(path, &tag))
+    })
+}
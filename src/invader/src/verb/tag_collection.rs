@@ -1,5 +1,6 @@
 use std::env::Args;
 use crate::cli::CommandLineParser;
+use crate::project::VerbProject;
 use ringhopper::definitions::{TagCollection, UIWidgetCollection};
 use ringhopper::error::Error;
 use ringhopper::primitives::primitive::TagGroup;
@@ -11,8 +12,9 @@ use crate::util::read_file;
 
 macro_rules! make_tag_collection_fn {
     ($name:tt, $tag_struct:tt) => {
-        pub fn $name(args: Args, description: &'static str) -> Result<(), String> {
+        pub fn $name(args: Args, description: &'static str, project: Option<VerbProject>) -> Result<(), String> {
             let parser = CommandLineParser::new(description, "<tag_collection*> [args]")
+                .set_project(project)
                 .add_tags(false)
                 .add_data()
                 .add_help()
@@ -1,5 +1,6 @@
 use std::env::Args;
 use crate::cli::CommandLineParser;
+use crate::project::VerbProject;
 use ringhopper::tag::unicode_string_list::*;
 use ringhopper::definitions::UnicodeStringList;
 use ringhopper::error::Error;
@@ -8,8 +9,9 @@ use ringhopper::tag::tree::TagTree;
 use crate::threading::{DisplayMode, do_with_threads, ProcessSuccessType};
 use crate::util::{make_stdout_logger, read_file};
 
-pub fn unicode_strings(args: Args, description: &'static str) -> Result<(), String> {
+pub fn unicode_strings(args: Args, description: &'static str, project: Option<VerbProject>) -> Result<(), String> {
     let parser = CommandLineParser::new(description, "<unicode_string_list*> [args]")
+        .set_project(project)
         .add_tags(false)
         .add_data()
         .add_help()
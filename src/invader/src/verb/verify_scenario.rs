@@ -1,44 +1,85 @@
+use std::collections::HashMap;
 use std::num::NonZeroUsize;
+use std::sync::Mutex;
 use std::{env::Args, sync::Arc};
-use crate::cli::CommandLineParser;
+use crate::cli::{CommandLineParser, OutputFormat};
+use crate::project::VerbProject;
 use ringhopper::tag::tree::{CachingTagTree, CachingTagTreeWriteStrategy};
-use ringhopper::primitives::primitive::TagGroup;
+use ringhopper::primitives::primitive::{TagGroup, TagPath};
+use ringhopper::tag::result::TagResult;
 use ringhopper::tag::verify::verify;
 use ringhopper_engines::Engine;
 use crate::threading::{DisplayMode, do_with_threads, ProcessSuccessType};
 use crate::util::make_stdout_logger;
-use crate::verb::print_tag_results;
+use crate::verb::{print_structured_tag_results, print_tag_results, suppress_tag_results};
 use crate::util::StdoutLogger;
 
 #[derive(Clone)]
 struct UserData {
     engine: &'static Engine,
-    logger: Arc<StdoutLogger>
+    logger: Arc<StdoutLogger>,
+    format: OutputFormat,
+
+    /// Results of all scenarios, if not outputting text
+    results: Arc<Mutex<HashMap<TagPath, TagResult>>>
 }
 
-pub fn verify_scenario(args: Args, description: &'static str) -> Result<(), String> {
+pub fn verify_scenario(args: Args, description: &'static str, project: Option<VerbProject>) -> Result<(), String> {
     let parser = CommandLineParser::new(description, "<scenario*> [args]")
+        .set_project(project)
         .add_tags(true)
         .add_help()
         .add_engine()
         .add_jobs()
+        .add_diagnostics_output()
         .set_required_extra_parameters(1)
         .parse(args)?;
 
     let tag = parser.get_extra()[0].clone();
     let data = UserData {
         engine: parser.get_engine(),
-        logger: make_stdout_logger()
+        logger: make_stdout_logger(),
+        format: parser.get_output_format(),
+        results: Arc::new(Mutex::new(HashMap::new()))
     };
 
     let logger = data.logger.clone();
     let tree = Arc::new(CachingTagTree::new(parser.get_virtual_tags_directory(), CachingTagTreeWriteStrategy::Manual));
 
-    do_with_threads(tree, parser, &tag, Some(TagGroup::Scenario), data, DisplayMode::Silent, logger, |context, scenario_path, user_data, _| {
+    do_with_threads(tree, parser, &tag, Some(TagGroup::Scenario), data.clone(), DisplayMode::Silent, logger.clone(), |context, scenario_path, user_data, _| {
         let threads = unsafe { NonZeroUsize::new_unchecked(2) }; // TODO: add subjobs later?
-        let everything = verify(scenario_path, context.tags_directory.clone(), user_data.engine, threads)?;
-        let locked = user_data.logger.lock();
-        print_tag_results(&locked, &everything, format_args!("Verified {scenario_path}"));
+        let mut everything = verify(scenario_path, context.tags_directory.clone(), user_data.engine, threads)?;
+        suppress_tag_results(&mut everything, &context.args);
+
+        if user_data.format == OutputFormat::Text {
+            let locked = user_data.logger.lock();
+            print_tag_results(&locked, &everything, format_args!("Verified {scenario_path}"));
+            return Ok(ProcessSuccessType::Success)
+        }
+
+        // Tags shared between scenarios are only reported once per issue.
+        let mut results = user_data.results.lock().unwrap();
+        for (path, result) in everything {
+            let existing = results.entry(path).or_default();
+            for (list, new) in [
+                (&mut existing.pedantic_warnings, result.pedantic_warnings),
+                (&mut existing.warnings, result.warnings),
+                (&mut existing.errors, result.errors)
+            ] {
+                for diagnostic in new {
+                    if !list.contains(&diagnostic) {
+                        list.push(diagnostic);
+                    }
+                }
+            }
+        }
+
         Ok(ProcessSuccessType::Success)
-    })
+    })?;
+
+    if data.format != OutputFormat::Text {
+        print_structured_tag_results(&logger.lock(), &data.results.lock().unwrap(), data.format);
+    }
+
+    Ok(())
 }
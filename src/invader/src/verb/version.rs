@@ -1,6 +1,7 @@
 use std::env::Args;
+use crate::project::VerbProject;
 
-pub fn version(_: Args, _: &'static str) -> Result<(), String> {
+pub fn version(_: Args, _: &'static str, _: Option<VerbProject>) -> Result<(), String> {
     println!();
     println!("================================================================================");
     println!();
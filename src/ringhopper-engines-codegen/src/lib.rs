@@ -1,6 +1,6 @@
 extern crate ringhopper_definitions;
 
-use ringhopper_definitions::{EngineCacheParser, Engine, load_all_definitions, EngineCompressionType};
+use ringhopper_definitions::{EngineCacheParser, Engine, load_all_definitions, EngineCompressionType, ParsedDefinitions, SupportedEngines};
 use std::fmt::Write;
 use proc_macro::TokenStream;
 
@@ -87,6 +87,17 @@ pub fn generate_ringhopper_engines(_: TokenStream) -> TokenStream {
             alignment: {}
         }}", engine.bitmap_options.swizzled, engine.bitmap_options.texture_dimension_must_modulo_block_size, engine.bitmap_options.cubemap_faces_stored_separately, engine.bitmap_options.alignment);
 
+        // Opcodes and global indices are the position among everything the engine supports, in definition order.
+        let mut script_functions = String::new();
+        for function in definitions.script_functions.iter().filter(|f| engine_supports(engine, &f.supported_engines, &definitions)) {
+            write!(&mut script_functions, "EngineScriptFunction {{ name: \"{name}\", return_type: \"{return_type}\", parameters: {parameters} }},",
+                   name=function.name, return_type=function.return_type, parameters=make_string_list(function.parameters.as_slice())).unwrap();
+        }
+        let mut script_globals = String::new();
+        for global in definitions.script_globals.iter().filter(|g| engine_supports(engine, &g.supported_engines, &definitions)) {
+            write!(&mut script_globals, "EngineScriptGlobal {{ name: \"{name}\", value_type: \"{value_type}\" }},", name=global.name, value_type=global.value_type).unwrap();
+        }
+
         write!(&mut engine_code, "Engine {{
             name: \"{name}\",
             display_name: \"{display_name}\",
@@ -110,7 +121,9 @@ pub fn generate_ringhopper_engines(_: TokenStream) -> TokenStream {
             cache_parser: EngineCacheParser::{cache_parser},
             compression_type: EngineCompressionType::{compression_type},
             required_tags: {required_tags},
-            obfuscated_header_layout: {obfuscated_header_layout}
+            obfuscated_header_layout: {obfuscated_header_layout},
+            script_functions: &[{script_functions}],
+            script_globals: &[{script_globals}]
         }},").unwrap();
     }
 
@@ -118,3 +131,20 @@ pub fn generate_ringhopper_engines(_: TokenStream) -> TokenStream {
 
     engine_code.parse().unwrap()
 }
+
+/// Return `true` if the engine or any engine it inherits is supported.
+fn engine_supports(engine: &Engine, supported_engines: &SupportedEngines, definitions: &ParsedDefinitions) -> bool {
+    let SupportedEngines::SomeEngines(supported_engines) = supported_engines else {
+        return true
+    };
+
+    let mut current = Some(engine);
+    while let Some(engine) = current {
+        if supported_engines.contains(&engine.name) {
+            return true
+        }
+        current = engine.inherits.as_ref().and_then(|parent| definitions.engines.get(parent));
+    }
+
+    false
+}
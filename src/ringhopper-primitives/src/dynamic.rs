@@ -78,7 +78,20 @@ pub struct TagFieldMetadata {
     pub read_only: bool,
     pub cache_only: bool,
     pub non_cached: bool,
-    pub allowed_references: Option<&'static [TagGroup]>
+    pub allowed_references: Option<&'static [TagGroup]>,
+
+    /// Path of the reflexive this index refers to from the root of the tag, such as `regions.permutations`, where each
+    /// reflexive along the way is the element containing this field.
+    pub index_target: Option<&'static str>,
+
+    /// Lowest value this field can be set to, if limited. Angles are in degrees, and enums are the integer value.
+    pub minimum: Option<f64>,
+
+    /// Highest value this field can be set to, if limited. Angles are in degrees, and enums are the integer value.
+    pub maximum: Option<f64>,
+
+    /// Names of the engines that use this field, if it is not used by all engines.
+    pub supported_engines: Option<&'static [&'static str]>
 }
 
 /// Trait for dynamically accessing an array of fields, including reflexives.
@@ -119,6 +132,13 @@ pub trait DynamicReflexive: DynamicTagDataArray {
     ///
     /// Panics if `index` > `len()`
     fn insert_moved(&mut self, index: usize, item: &mut dyn DynamicTagData);
+
+    /// Remove the item at index `index`, shifting all items after it down.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` >= `len()`
+    fn remove(&mut self, index: usize);
 }
 
 #[derive(PartialEq, Debug)]
@@ -310,6 +330,14 @@ pub trait DynamicEnumImpl {
 
     /// Retrieve all values for the enum.
     fn str_vals() -> &'static [&'static str];
+
+    /// Get an enum value from its integer value.
+    ///
+    /// Returns `None` if the integer does not correspond to a valid option.
+    fn from_u16(value: u16) -> Option<Self> where Self: Sized;
+
+    /// Convert the value to its integer value.
+    fn to_u16(&self) -> u16;
 }
 
 /// Dynamic trait for enum objects.
@@ -326,6 +354,12 @@ pub trait DynamicEnum: DynamicTagData {
 
     /// Retrieve all values for the enum.
     fn get_possible_enum_string_values(&self) -> &'static [&'static str];
+
+    /// Overwrite the enum object with the integer value.
+    fn set_enum_integer_value(&mut self, value: u16) -> RinghopperResult<()>;
+
+    /// Convert the enum into its equivalent integer value.
+    fn get_enum_integer_value(&self) -> u16;
 }
 
 impl<T: DynamicTagData + DynamicEnumImpl> DynamicEnum for T {
@@ -346,4 +380,18 @@ impl<T: DynamicTagData + DynamicEnumImpl> DynamicEnum for T {
     fn get_possible_enum_string_values(&self) -> &'static [&'static str] {
         Self::str_vals()
     }
+
+    fn set_enum_integer_value(&mut self, value: u16) -> RinghopperResult<()> {
+        if let Some(n) = Self::from_u16(value) {
+            *self = n;
+            Ok(())
+        }
+        else {
+            Err(Error::InvalidEnum)
+        }
+    }
+
+    fn get_enum_integer_value(&self) -> u16 {
+        self.to_u16()
+    }
 }
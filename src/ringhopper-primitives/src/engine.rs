@@ -67,7 +67,13 @@ pub struct Engine {
     pub cache_parser: EngineCacheParser,
     
     /// If true, this uses an obfuscated header layout.
-    pub obfuscated_header_layout: bool
+    pub obfuscated_header_layout: bool,
+
+    /// Built-in script functions, where the index is the function's opcode.
+    pub script_functions: &'static [EngineScriptFunction],
+
+    /// Built-in script globals, where the index is the global's index.
+    pub script_globals: &'static [EngineScriptGlobal]
 }
 
 #[derive(Copy, Clone, PartialEq, Debug)]
@@ -132,3 +138,16 @@ pub struct EngineBaseMemoryAddress {
     pub address: u64,
     pub inferred: bool
 }
+
+#[derive(Copy, Clone, Debug)]
+pub struct EngineScriptFunction {
+    pub name: &'static str,
+    pub return_type: &'static str,
+    pub parameters: &'static [&'static str]
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct EngineScriptGlobal {
+    pub name: &'static str,
+    pub value_type: &'static str
+}
@@ -1,6 +1,8 @@
+use std::collections::HashMap;
 use crate::engine::Engine;
-use crate::error::RinghopperResult;
-use crate::primitive::{ID, IDType, Index, TagPath, TagReference};
+use crate::error::{Error, OverflowCheck, RinghopperResult};
+use crate::parse::U32SizeConversion;
+use crate::primitive::{Address, ID, IDType, Index, TagPath, TagReference};
 use crate::tag::PrimaryTagStructDyn;
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
@@ -116,6 +118,14 @@ pub trait Map {
         path: &TagPath
     ) -> Option<&Tag>;
 
+    /// Get the ID of the tag in the tag array for the tag path.
+    ///
+    /// Returns None if the tag does not exist in the map.
+    fn get_tag_id(
+        &self,
+        path: &TagPath
+    ) -> Option<ID>;
+
     /// Get the scenario tag for the tag ID.
     fn get_scenario_tag(&self) -> &Tag;
 
@@ -204,3 +214,54 @@ pub trait Map {
             .ok()
     }
 }
+
+/// Information about a tag needed to refer to it when building a cache file.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct MapWriteTag {
+    /// ID of the tag in the tag array.
+    pub id: ID,
+
+    /// Address of the tag's path in tag data.
+    pub path_address: Address
+}
+
+/// State used when serializing tag data into a cache file.
+pub struct MapWriteContext<'a> {
+    /// Engine being targeted.
+    pub engine: &'static Engine,
+
+    /// Address that the first byte of the data being written will be loaded at.
+    pub base_address: usize,
+
+    /// All tags that will be in the cache file.
+    pub tags: &'a HashMap<TagPath, MapWriteTag>,
+
+    /// Data stored outside of tag data which is referred to by a file offset (e.g. sound samples).
+    pub file_data: &'a mut Vec<u8>,
+
+    /// File offset that `file_data` will start at.
+    pub file_data_offset: usize
+}
+
+impl<'a> MapWriteContext<'a> {
+    /// Get the address for the offset in the data being written.
+    ///
+    /// Returns an error if the address cannot fit in 32 bits.
+    pub fn address_at(&self, offset: usize) -> RinghopperResult<Address> {
+        Ok(Address { address: self.base_address.add_overflow_checked(offset)?.into_u32()? })
+    }
+
+    /// Get the tag info for a path.
+    ///
+    /// Returns an error if the tag will not be in the cache file.
+    pub fn get_tag(&self, path: &TagPath) -> RinghopperResult<MapWriteTag> {
+        self.tags.get(path).copied().ok_or_else(|| Error::TagNotFound(path.to_owned()))
+    }
+
+    /// Append data to the file data, returning its file offset.
+    pub fn append_file_data(&mut self, bytes: &[u8]) -> RinghopperResult<u32> {
+        let offset = self.file_data_offset.add_overflow_checked(self.file_data.len())?.into_u32()?;
+        self.file_data.extend_from_slice(bytes);
+        Ok(offset)
+    }
+}
@@ -8,7 +8,8 @@ use byteorder::*;
 use crate::dynamic::{DynamicTagData, DynamicTagDataType, SimplePrimitiveType};
 
 use crate::error::RinghopperResult;
-use crate::map::{DomainType, Map};
+use crate::map::{DomainType, Map, MapWriteContext};
+use crate::primitive::calculate_padding_for_alignment;
 
 /// Maximum length for an array.
 ///
@@ -43,6 +44,26 @@ pub trait TagData: TagDataDefaults {
 
     /// Read data from the map.
     fn read_from_map<M: Map>(map: &M, address: usize, domain_type: &DomainType) -> RinghopperResult<Self> where Self: Sized;
+
+    /// Write data in cache file format.
+    ///
+    /// - `data` is the entire region being built (e.g. tag data or BSP data).
+    /// - `at` is the position of the data to write.
+    /// - `struct_end` is the end of the struct.
+    /// - `context` is used for resolving addresses, tag IDs, and data stored outside of `data`.
+    ///
+    /// Extra data, such as reflexive elements, is appended to the end of `data`, aligned to [`MAP_DATA_ALIGNMENT`].
+    fn write_to_map(&self, data: &mut Vec<u8>, at: usize, struct_end: usize, context: &mut MapWriteContext) -> RinghopperResult<()>;
+}
+
+/// Alignment of extra data appended when writing cache file data.
+pub const MAP_DATA_ALIGNMENT: usize = 4;
+
+/// Pad `data` to [`MAP_DATA_ALIGNMENT`] and then reserve `size` zeroed bytes, returning the offset to the reserved bytes.
+pub fn reserve_map_data(data: &mut Vec<u8>, size: usize) -> RinghopperResult<usize> {
+    let start = data.len().add_overflow_checked(calculate_padding_for_alignment(data.len(), MAP_DATA_ALIGNMENT))?;
+    data.resize(start.add_overflow_checked(size)?, 0);
+    Ok(start)
 }
 
 /// Functionality for defaulting zeroed values.
@@ -259,6 +280,9 @@ impl<T: SimpleTagData + TagDataDefaults + Sized> TagData for T {
         };
         T::read::<LittleEndian>(data, 0, data.len())
     }
+    fn write_to_map(&self, data: &mut Vec<u8>, at: usize, struct_end: usize, _: &mut MapWriteContext) -> RinghopperResult<()> {
+        self.write::<LittleEndian>(data, at, struct_end)
+    }
 }
 
 impl <T: SimplePrimitive> TagDataDefaults for T {}
@@ -4,7 +4,7 @@ use byteorder::ByteOrder;
 use crate::dynamic::*;
 use crate::parse::*;
 use crate::error::*;
-use crate::map::{DomainType, Map};
+use crate::map::{DomainType, Map, MapWriteContext};
 
 /// Defines the lower and upper bound with fields.
 #[derive(Copy, Clone, PartialEq, Debug)]
@@ -106,6 +106,12 @@ impl<T: TagData> TagData for Bounds<T> {
             upper: T::read_from_map(map, address.add_overflow_checked(T::size())?, domain_type)?
         })
     }
+
+    fn write_to_map(&self, data: &mut Vec<u8>, at: usize, struct_end: usize, context: &mut MapWriteContext) -> RinghopperResult<()> {
+        self.lower.write_to_map(data, at, struct_end, context)?;
+        self.upper.write_to_map(data, at.add_overflow_checked(T::size())?, struct_end, context)?;
+        Ok(())
+    }
 }
 
 impl<T: TagData + Default> Default for Bounds<T> {
@@ -6,7 +6,7 @@ use crate::error::*;
 use byteorder::*;
 use std::fmt::Display;
 use crate::dynamic::{DynamicReflexive, DynamicTagData, DynamicTagDataArray, DynamicTagDataType, SimplePrimitiveType, TagFieldMetadata};
-use crate::map::{DomainType, Map, ResourceMapType};
+use crate::map::{DomainType, Map, MapWriteContext, ResourceMapType};
 
 /// 16-bit index type
 pub type Index = Option<u16>;
@@ -334,6 +334,15 @@ impl DataData for FileData {
 
         Ok(Self { bytes: data.to_vec() })
     }
+
+    fn write_to_map(&self, data: &mut Vec<u8>, at: usize, struct_end: usize, context: &mut MapWriteContext) -> RinghopperResult<()> {
+        let file_offset = if self.bytes.is_empty() { 0 } else { context.append_file_data(&self.bytes)? };
+        DataC {
+            size: self.bytes.len().into_u32()?,
+            file_offset,
+            ..Default::default()
+        }.write_to_map(data, at, struct_end, context)
+    }
 }
 
 pub(crate) trait DataData: TagDataDefaults + Sized {
@@ -356,6 +365,23 @@ pub(crate) trait DataData: TagDataDefaults + Sized {
 
         Self::from_bytes(data.as_ref())
     }
+
+    fn write_to_map(&self, data: &mut Vec<u8>, at: usize, struct_end: usize, context: &mut MapWriteContext) -> RinghopperResult<()> {
+        let bytes = self.get_bytes();
+        let address = if bytes.is_empty() {
+            Address::default()
+        }
+        else {
+            let offset = reserve_map_data(data, bytes.len())?;
+            data[offset..].copy_from_slice(bytes);
+            context.address_at(offset)?
+        };
+        DataC {
+            size: bytes.len().into_u32()?,
+            address,
+            ..Default::default()
+        }.write_to_map(data, at, struct_end, context)
+    }
 }
 
 // Used to bypass conflicting implementation error
@@ -390,6 +416,10 @@ macro_rules! make_tag_data_implementation_for_datadata {
             fn read_from_map<M: Map>(map: &M, address: usize, domain_type: &DomainType) -> RinghopperResult<Self> {
                 DataData::read_from_map(map, address, domain_type)
             }
+
+            fn write_to_map(&self, data: &mut Vec<u8>, at: usize, struct_end: usize, context: &mut MapWriteContext) -> RinghopperResult<()> {
+                DataData::write_to_map(self, data, at, struct_end, context)
+            }
         }
     };
 }
@@ -652,6 +682,26 @@ impl<T: TagData + Sized> TagData for Reflexive<T> {
 
         Ok(result)
     }
+
+    fn write_to_map(&self, data: &mut Vec<u8>, at: usize, struct_end: usize, context: &mut MapWriteContext) -> RinghopperResult<()> {
+        let count = self.items.len().into_u32()?;
+        if count == 0 {
+            return ReflexiveC::<T>::with_params(0, Address::default()).write_to_map(data, at, struct_end, context)
+        }
+
+        let item_size = T::size();
+        let total_bytes_to_write = self.items.len().mul_overflow_checked(item_size)?;
+        let mut write_offset = reserve_map_data(data, total_bytes_to_write)?;
+        let address = context.address_at(write_offset)?;
+
+        for i in self {
+            let struct_end = write_offset + item_size;
+            i.write_to_map(data, write_offset, struct_end, context)?;
+            write_offset = struct_end;
+        }
+
+        ReflexiveC::<T>::with_params(count, address).write_to_map(data, at, struct_end, context)
+    }
 }
 
 impl<T: TagData + Sized> TagDataDefaults for Reflexive<T> {
@@ -720,8 +770,13 @@ impl SimplePrimitive for Address {
     }
 }
 
-pub(crate) type ReflexiveAccessRange = (usize, usize); // [start, end]
-pub(crate) fn parse_range(matcher: &str, len: usize) -> Result<Vec<ReflexiveAccessRange>, &'static str> {
+/// Inclusive range of indices (`[start, end]`) returned by [`parse_range`].
+pub type ReflexiveAccessRange = (usize, usize);
+
+/// Parse a range subscript such as `0-3,5,7-e` or `*` for an array of length `len`.
+///
+/// Returned ranges are sorted, deduplicated, and merged where contiguous.
+pub fn parse_range(matcher: &str, len: usize) -> Result<Vec<ReflexiveAccessRange>, &'static str> {
     const RANGE_SEPARATOR: u8 = ',' as u8;
     const START_END_SEPARATOR: u8 = '-' as u8;
     const EVERYTHING: u8 = '*' as u8;
@@ -864,6 +919,10 @@ impl<T: DynamicTagData + Sized + Default + Clone> DynamicTagData for Reflexive<T
     fn as_array_mut(&mut self) -> Option<&mut dyn DynamicTagDataArray> {
         Some(self as &mut dyn DynamicTagDataArray)
     }
+
+    fn as_reflexive_mut(&mut self) -> Option<&mut dyn DynamicReflexive> {
+        Some(self as &mut dyn DynamicReflexive)
+    }
 }
 
 impl<T: DynamicTagData + Sized + Default + Clone> DynamicTagDataArray for Reflexive<T> {
@@ -894,6 +953,10 @@ impl<T: DynamicTagData + Sized + Default + Clone> DynamicReflexive for Reflexive
         let item = std::mem::take(item.as_any_mut().downcast_mut::<T>().unwrap());
         self.items.insert(index, item);
     }
+
+    fn remove(&mut self, index: usize) {
+        self.items.remove(index);
+    }
 }
 
 /// Lower level C implementation of a reflexive.
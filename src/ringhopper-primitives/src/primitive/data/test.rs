@@ -84,6 +84,10 @@ impl TagData for Vector3DHolder {
     fn read_from_map<M: Map>(_map: &M, _address: usize, _domain_type: &DomainType) -> RinghopperResult<Self> where Self: Sized {
         unimplemented!()
     }
+
+    fn write_to_map(&self, _data: &mut Vec<u8>, _at: usize, _struct_end: usize, _context: &mut MapWriteContext) -> RinghopperResult<()> {
+        unimplemented!()
+    }
 }
 impl TagDataDefaults for Vector3DHolder {}
 
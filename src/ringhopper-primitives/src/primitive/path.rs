@@ -6,7 +6,7 @@ use std::convert::From;
 use std::fmt::Display;
 use std::fmt::Write;
 use crate::dynamic::{DynamicTagData, DynamicTagDataType};
-use crate::map::{DomainType, Map};
+use crate::map::{DomainType, Map, MapWriteContext};
 
 /// Halo path separator
 pub const HALO_PATH_SEPARATOR: char = '\\';
@@ -524,6 +524,28 @@ impl TagData for TagReference {
 
         Ok(TagReference::Set(tag.tag_path.clone()))
     }
+
+    fn write_to_map(&self, data: &mut Vec<u8>, at: usize, struct_end: usize, context: &mut MapWriteContext) -> RinghopperResult<()> {
+        let construct_to_write = match self {
+            TagReference::Null(group) => {
+                TagReferenceC {
+                    tag_group: *group,
+                    tag_id: ID::null(),
+                    ..Default::default()
+                }
+            },
+            TagReference::Set(path) => {
+                let tag = context.get_tag(path)?;
+                TagReferenceC {
+                    tag_group: path.group,
+                    path_address: tag.path_address,
+                    path_length: path.path.len().into_u32()?,
+                    tag_id: tag.id
+                }
+            }
+        };
+        construct_to_write.write_to_map(data, at, struct_end, context)
+    }
 }
 
 impl TagDataDefaults for TagReference {}
@@ -3,7 +3,7 @@ use std::fmt::{Display, Debug, Formatter};
 use std::str::FromStr;
 use std::sync::{Arc, Mutex};
 use crate::dynamic::{DynamicTagData, DynamicTagDataType, SimplePrimitiveType, TagFieldMetadata};
-use crate::map::{DomainType, Map};
+use crate::map::{DomainType, Map, MapWriteContext};
 use crate::parse::{fits, SimplePrimitive, TagData, TagDataDefaults, U32SizeConversion};
 
 use super::*;
@@ -204,6 +204,16 @@ impl UTF16String {
         }
     }
 
+    /// Create a UTF-16 string from little endian UTF-16 bytes.
+    ///
+    /// Unlike [`UTF16String::from_str`], the data is not validated, so it can be used to preserve invalid strings.
+    pub fn from_utf16_bytes(bytes: &[u8]) -> Self {
+        Self {
+            data: bytes.to_owned(),
+            cache: Mutex::new(None)
+        }
+    }
+
     /// Get the bytes for the underlying UTF-16 data.
     pub fn get_utf16_bytes(&self) -> &[u8] {
         self.data.as_slice()
@@ -7,6 +7,7 @@ use crate::dynamic::*;
 use crate::parse::*;
 use crate::error::*;
 use crate::crc32::CRC32;
+use crate::map::MapWriteContext;
 
 use std::any::Any;
 
@@ -50,6 +51,11 @@ pub trait PrimaryTagStructDyn: PrimaryTagStruct + Any {
 
     /// Clone this object.
     fn clone_inner(&self) -> Box<dyn PrimaryTagStructDyn>;
+
+    /// Append this tag to `data` in cache file format.
+    ///
+    /// Returns the offset to the tag's base struct in `data`.
+    fn append_to_map_data(&self, data: &mut Vec<u8>, context: &mut MapWriteContext) -> RinghopperResult<usize>;
 }
 
 impl dyn PrimaryTagStructDyn {
@@ -88,6 +94,12 @@ impl<T: PrimaryTagStruct + Sized + Send + Clone + Any> PrimaryTagStructDyn for T
     fn clone_inner(&self) -> Box<dyn PrimaryTagStructDyn> {
         Box::new(self.clone()) as Box<dyn PrimaryTagStructDyn>
     }
+    fn append_to_map_data(&self, data: &mut Vec<u8>, context: &mut MapWriteContext) -> RinghopperResult<usize> {
+        let size = <T as TagData>::size();
+        let at = reserve_map_data(data, size)?;
+        self.write_to_map(data, at, at.add_overflow_checked(size)?, context)?;
+        Ok(at)
+    }
 }
 
 /// If CRC32 is set to this, then disable CRC32 checks.
@@ -329,6 +341,38 @@ pub fn for_each_field<P: FnMut(Option<TagFieldMetadata>, &dyn DynamicTagData)>(d
     recursion(data, &mut predicate);
 }
 
+/// Iterate through each [`DynamicTagData`] of a block, along with a matcher for the field (e.g. `nodes[2].name`).
+pub fn for_each_field_with_matcher<P: FnMut(&str, Option<TagFieldMetadata>, &dyn DynamicTagData)>(data: &dyn DynamicTagData, mut predicate: P) {
+    fn recursion<P: FnMut(&str, Option<TagFieldMetadata>, &dyn DynamicTagData)>(data: &dyn DynamicTagData, matcher: &mut String, predicate: &mut P) {
+        for field_name in data.fields() {
+            let length_before = matcher.len();
+            if !matcher.is_empty() {
+                matcher.push('.');
+            }
+            *matcher += field_name;
+
+            let field = data.get_field(field_name).unwrap();
+            predicate(matcher, data.get_metadata_for_field(field_name), field);
+
+            if let Some(arr) = field.as_array() {
+                for i in 0..arr.len() {
+                    let item = arr.get_at_index(i).unwrap();
+                    let length_before = matcher.len();
+                    *matcher += &format!("[{i}]");
+                    recursion(item, matcher, predicate);
+                    matcher.truncate(length_before);
+                }
+            }
+            else if !field.fields().is_empty() {
+                recursion(field, matcher, predicate);
+            }
+
+            matcher.truncate(length_before);
+        }
+    }
+    recursion(data, &mut String::new(), &mut predicate);
+}
+
 /// Mutably iterate through each [`DynamicTagData`] of a block.
 pub fn for_each_field_mut<P: FnMut(Option<TagFieldMetadata>, &mut dyn DynamicTagData)>(data: &mut dyn DynamicTagData, access_read_only_fields: bool, mut predicate: P) {
     fn recursion<P: FnMut(Option<TagFieldMetadata>, &mut dyn DynamicTagData)>(data: &mut dyn DynamicTagData, predicate: &mut P, access_read_only_fields: bool) {
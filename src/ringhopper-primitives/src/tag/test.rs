@@ -1,4 +1,4 @@
-use crate::map::{DomainType, Map};
+use crate::map::{DomainType, Map, MapWriteContext};
 use crate::parse::*;
 use super::*;
 
@@ -27,6 +27,10 @@ impl TagData for String {
     fn read_from_map<M: Map>(_map: &M, _address: usize, _domain_type: &DomainType) -> RinghopperResult<Self> where Self: Sized {
         unimplemented!()
     }
+
+    fn write_to_map(&self, _data: &mut Vec<u8>, _at: usize, _struct_end: usize, _context: &mut MapWriteContext) -> RinghopperResult<()> {
+        unimplemented!()
+    }
 }
 
 impl TagData for UnicodeStringList {
@@ -43,6 +47,10 @@ impl TagData for UnicodeStringList {
     fn read_from_map<M: Map>(_map: &M, _address: usize, _domain_type: &DomainType) -> RinghopperResult<Self> where Self: Sized {
         unimplemented!()
     }
+
+    fn write_to_map(&self, _data: &mut Vec<u8>, _at: usize, _struct_end: usize, _context: &mut MapWriteContext) -> RinghopperResult<()> {
+        unimplemented!()
+    }
 }
 
 impl TagDataDefaults for String {}
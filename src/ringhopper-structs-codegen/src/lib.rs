@@ -20,6 +20,7 @@ pub fn generate_ringhopper_structs(_: TokenStream) -> TokenStream {
 
     let mut read_any_tag_lines = String::new();
     let mut read_any_map_lines = String::new();
+    let mut new_tag_lines = String::new();
     let mut referenceable_tag_groups_hint = String::new();
     let mut supported_groups_for_engines = String::new();
     let mut defaultable_tag_groups_hint = String::new();
@@ -67,6 +68,7 @@ pub fn generate_ringhopper_structs(_: TokenStream) -> TokenStream {
         writeln!(referenceable_tag_groups_hint, "TagGroup::{enum_name} => &[{list}],").unwrap();
         writeln!(read_any_tag_lines, "TagGroup::{enum_name} => b(TagFile::read_tag_from_file_buffer::<{struct_name}>(file, ParseStrictness::Relaxed)),").unwrap();
         writeln!(read_any_map_lines, "TagGroup::{enum_name} => b({struct_name}::read_from_map(map, tag_info.address, &tag_info.domain)),").unwrap();
+        writeln!(new_tag_lines, "TagGroup::{enum_name} => b(Ok({struct_name}::default())),").unwrap();
     }
 
     stream.extend(format!("
@@ -101,6 +103,18 @@ pub fn generate_ringhopper_structs(_: TokenStream) -> TokenStream {
         }}
     }}
 
+    /// Create a new tag of the given group.
+    ///
+    /// All fields are zeroed; default values are not set.
+    ///
+    /// Returns `Err` if the group does not correspond to any known tag group.
+    pub fn new_tag_of_group(group: TagGroup) -> RinghopperResult<Box<dyn PrimaryTagStructDyn>> {{
+        match group {{
+            {new_tag_lines}
+            _ => Err(Error::TagGroupUnimplemented)
+        }}
+    }}
+
     /// Get all tag groups this tag group can reference.
     pub fn get_all_referenceable_tag_groups_for_group(what: TagGroup) -> &'static [TagGroup] {{
         match what {{
@@ -262,6 +276,58 @@ fn recursive_look_for_defaults_for_struct(struct_name: &str, definitions: &Parse
     false
 }
 
+/// Find the path from the root of the tag to the reflexive an index in `struct_name` refers to.
+///
+/// The definitions only name the reflexive, which is a field of the closest struct containing the index that has it.
+/// This is resolved everywhere the struct is used, and if it is not the same reflexive everywhere, `None` is returned.
+fn index_target_path(definitions: &ParsedDefinitions, struct_name: &str, reflexive: &str) -> Option<String> {
+    /// `ancestors` holds every struct containing this one, along with its path from the root.
+    fn recursion<'a>(definitions: &'a ParsedDefinitions, object: &'a str, path: String, struct_name: &str, reflexive: &str, ancestors: &mut Vec<(&'a Struct, String)>, paths: &mut HashSet<Option<String>>) {
+        let NamedObject::Struct(s) = &definitions.objects[object] else {
+            return
+        };
+
+        for f in &s.fields {
+            if f.flags.exclude {
+                continue
+            }
+            if let StructFieldType::Object(FieldObject::NamedObject(n) | FieldObject::Reflexive(n)) = &f.field_type {
+                let name = safe_str(&f.name, SafetyLevel::Matcher);
+                let child_path = if path.is_empty() { name.into_owned() } else { format!("{path}.{name}") };
+                ancestors.push((s, path.clone()));
+                recursion(definitions, n, child_path, struct_name, reflexive, ancestors, paths);
+                ancestors.pop();
+            }
+        }
+
+        if s.name == struct_name {
+            let target = safe_str(reflexive, SafetyLevel::Matcher);
+            let has_target = |s: &Struct| s.fields.iter().any(|f| {
+                !f.flags.exclude && matches!(f.field_type, StructFieldType::Object(FieldObject::Reflexive(_))) && safe_str(&f.name, SafetyLevel::Matcher) == target
+            });
+            let found = if has_target(s) {
+                Some(&path)
+            }
+            else {
+                ancestors.iter().rev().find(|(ancestor, _)| has_target(ancestor)).map(|(_, path)| path)
+            };
+            paths.insert(found.map(|path| if path.is_empty() { target.into_owned() } else { format!("{path}.{target}") }));
+        }
+    }
+
+    let mut paths = HashSet::new();
+    for group in definitions.groups.values() {
+        recursion(definitions, &group.struct_name, String::new(), struct_name, reflexive, &mut Vec::new(), &mut paths);
+    }
+
+    if paths.len() == 1 {
+        paths.into_iter().next().unwrap()
+    }
+    else {
+        None
+    }
+}
+
 impl ToTokenStream for Struct {
     fn to_token_stream(&self, definitions: &ParsedDefinitions) -> TokenStream {
         let struct_name = &self.name;
@@ -414,6 +480,7 @@ impl ToTokenStream for Struct {
         let mut write_out = String::new();
         let mut read_tag_in = String::new();
         let mut read_map_in = String::new();
+        let mut write_map_out = String::new();
 
         let mut field_list = String::new();
         let mut getter = String::new();
@@ -438,7 +505,22 @@ impl ToTokenStream for Struct {
                 "None".to_owned()
             };
 
-            let metadata = build_metadata(&field.flags, &allowed_references);
+            let index_target = match (&field.field_type, &field.reflexive) {
+                (StructFieldType::Object(FieldObject::Index), Some(reflexive)) => match index_target_path(definitions, struct_name, reflexive) {
+                    Some(path) => format!("Some(\"{path}\")"),
+                    None => "None".to_owned()
+                },
+                _ => "None".to_owned()
+            };
+
+            let limit = |value: &Option<StaticValue>| match value {
+                Some(n) => format!("Some({n} as f64)"),
+                None => "None".to_owned()
+            };
+            let minimum = limit(&field.minimum);
+            let maximum = limit(&field.maximum);
+
+            let metadata = build_metadata(&field.flags, definitions, &allowed_references, &index_target, &minimum, &maximum);
             let field_name = &field.name_rust_field;
             let field_matcher = &fields_with_matchers[i];
             writeln!(&mut metadata_matcher, "\"{field_matcher}\" => Some({metadata}),").unwrap();
@@ -523,12 +605,27 @@ impl ToTokenStream for Struct {
                         format!("<{field_type}>::read_from_map(map, _pos, domain_type)?")
                     };
                     writeln!(&mut read_map_in, "output.{field_name} = {read_map_code};").unwrap();
+
+                    let write_map_code = if self.flags.shifted_by_one {
+                        let value = if field_type == "Index" { format!("self.{field_name}.unwrap_or(0)") } else { format!("(self.{field_name} as u16)") };
+                        format!("{value}.wrapping_sub(1).write_to_map(data, _pos, struct_end, context)?")
+                    }
+                    else if field_type == "BSPVertexData" {
+                        let compressed = field_name.starts_with("compressed");
+                        format!("if context.engine.compressed_models == {compressed} {{ self.{field_name}.write_to_map(data, _pos, struct_end, context)? }} else {{ <{field_type}>::default().write_to_map(data, _pos, struct_end, context)? }}")
+                    }
+                    else {
+                        format!("self.{field_name}.write_to_map(data, _pos, struct_end, context)?")
+                    };
+                    writeln!(&mut write_map_out, "{write_map_code};").unwrap();
                 }
                 writeln!(&mut read_map_in, "let _pos = _pos.add_overflow_checked({length})?;").unwrap();
+                writeln!(&mut write_map_out, "let _pos = _pos.add_overflow_checked({length})?;").unwrap();
             }
         }
         else {
             read_map_in = "BAD".to_owned();
+            write_map_out = "BAD".to_owned();
         }
 
         // Defaulting code
@@ -647,6 +744,12 @@ impl ToTokenStream for Struct {
                     {read_map_in}
                     Ok(output)
                 }}
+
+                fn write_to_map(&self, data: &mut Vec<u8>, at: usize, struct_end: usize, context: &mut MapWriteContext) -> RinghopperResult<()> {{
+                    let _pos = at;
+                    {write_map_out}
+                    Ok(())
+                }}
             }}")
         }.parse::<TokenStream>().unwrap();
 
@@ -837,6 +940,12 @@ impl ToTokenStream for Enum {
                     {field_name_list}
                 ]
             }}
+            fn from_u16(value: u16) -> Option<Self> {{
+                value.try_into().ok()
+            }}
+            fn to_u16(&self) -> u16 {{
+                *self as u16
+            }}
         }}").parse::<TokenStream>().unwrap();
 
         let mut tokens = TokenStream::default();
@@ -849,7 +958,7 @@ impl ToTokenStream for Enum {
 }
 
 impl ToTokenStream for Bitfield {
-    fn to_token_stream(&self, _definitions: &ParsedDefinitions) -> TokenStream {
+    fn to_token_stream(&self, definitions: &ParsedDefinitions) -> TokenStream {
         let struct_name = &self.name;
 
         let field_names_rust = self.fields.iter().map(|s| safe_str(&s.name, SafetyLevel::RustCompilation)).collect::<Vec<Cow<str>>>();
@@ -891,7 +1000,7 @@ impl ToTokenStream for Bitfield {
                 continue
             }
             let field_matcher = &field_names_matchers[i];
-            let metadata = build_metadata(&field.flags, "None");
+            let metadata = build_metadata(&field.flags, definitions, "None", "None", "None", "None");
             writeln!(&mut all_metadata, "\"{field_matcher}\" => Some({metadata}),").unwrap();
         }
 
@@ -1005,6 +1114,11 @@ impl ToTokenStream for Bitfield {
                     let read_in = u{width}::read_from_map(map, address, domain_type)? & {not_tag_only};
                     Ok(read_in.into())
                 }}
+
+                fn write_to_map(&self, data: &mut Vec<u8>, at: usize, struct_end: usize, context: &mut MapWriteContext) -> RinghopperResult<()> {{
+                    let output = u{width}::from(*self) & {not_tag_only};
+                    output.write_to_map(data, at, struct_end, context)
+                }}
             }}").parse::<TokenStream>().unwrap()
         };
 
@@ -1113,7 +1227,7 @@ fn safe_str(string: &str, safety_level: SafetyLevel) -> Cow<str> {
     string
 }
 
-fn build_metadata(flags: &Flags, allowed_references: &str) -> String {
+fn build_metadata(flags: &Flags, definitions: &ParsedDefinitions, allowed_references: &str, index_target: &str, minimum: &str, maximum: &str) -> String {
     let comment = if let Some(n) = &flags.comment {
         let r = n.replace("\\", "\\\\").replace("\"", "\\\"").replace("\n", "\\n");
         format!("Some(\"{r}\")")
@@ -1124,12 +1238,29 @@ fn build_metadata(flags: &Flags, allowed_references: &str) -> String {
     let read_only = flags.uneditable_in_editor;
     let cache_only = flags.cache_only;
     let non_cached = flags.non_cached;
+    let supported_engines = if let SupportedEngines::SomeEngines(e) = &flags.supported_engines {
+        let mut engines = resolve_all_engines_for_parents(e.iter(), definitions).into_iter().collect::<Vec<&str>>();
+        engines.sort();
+
+        let mut list = String::new();
+        for engine in engines {
+            list += &format!("\"{engine}\",");
+        }
+        format!("Some(&[{list}])")
+    }
+    else {
+        "None".to_owned()
+    };
 
     format!("TagFieldMetadata {{
         comment: {comment},
         read_only: {read_only},
         cache_only: {cache_only},
         non_cached: {non_cached},
-        allowed_references: {allowed_references}
+        allowed_references: {allowed_references},
+        index_target: {index_target},
+        minimum: {minimum},
+        maximum: {maximum},
+        supported_engines: {supported_engines}
     }}")
 }
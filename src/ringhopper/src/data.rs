@@ -1 +1,5 @@
 pub mod bitmap;
+pub mod gltf;
+pub mod jma;
+pub mod jms;
+pub mod sound;
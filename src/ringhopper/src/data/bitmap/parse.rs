@@ -43,6 +43,35 @@ impl Image {
         data
     }
 
+    /// Convert the image into a PNG file.
+    ///
+    /// Returns `Err` if the image is too large to be encoded.
+    pub fn to_png(&self) -> RinghopperResult<Vec<u8>> {
+        use png::*;
+
+        let pixel_count = self.width.mul_overflow_checked(self.height)?;
+        let mut pixels_r8g8b8a8 = Vec::with_capacity(pixel_count.mul_overflow_checked(4)?);
+        for i in &self.data {
+            let color: Pixel32Bytes = (*i).into();
+            pixels_r8g8b8a8.extend_from_slice(&[color.red, color.green, color.blue, color.alpha]);
+        }
+
+        let width = u32::try_from(self.width).map_err(|_| Error::Other(format!("cannot encode a PNG with a width of {}", self.width)))?;
+        let height = u32::try_from(self.height).map_err(|_| Error::Other(format!("cannot encode a PNG with a height of {}", self.height)))?;
+        let png_error = |e: EncodingError| Error::Other(format!("failed to encode PNG: {e}"));
+
+        let mut data = Vec::new();
+        let mut encoder = Encoder::new(Cursor::new(&mut data), width, height);
+        encoder.set_color(ColorType::Rgba);
+        encoder.set_depth(BitDepth::Eight);
+
+        let mut writer = encoder.write_header().map_err(png_error)?;
+        writer.write_image_data(&pixels_r8g8b8a8).map_err(png_error)?;
+        writer.finish().map_err(png_error)?;
+
+        Ok(data)
+    }
+
     /// Parse a JPEG-XL image file into an image.
     ///
     /// Returns `Err` if an error occurred.
@@ -1,9 +1,13 @@
 use std::collections::HashSet;
+use std::ops::Range;
 use std::path::{Path, PathBuf};
 use primitives::error::{Error, OverflowCheck, RinghopperResult};
 use primitives::primitive::{Pixel32, Pixel32Bytes};
 use crate::data::bitmap::{autodetect_image_extension, Image, load_image_from_path};
 
+#[cfg(test)]
+mod test;
+
 /// Iterator for loose color plates.
 ///
 /// Loose color plates are stored where each bitmap is a separate image file, with the name format
@@ -288,3 +292,133 @@ pub fn make_color_plate_from_loose(data_dir: &Path) -> RinghopperResult<Image> {
 
     Ok(full_color_plate)
 }
+
+/// A sequence of bitmaps read from a color plate.
+#[derive(Clone, Default)]
+pub struct ColorPlateSequence {
+    pub bitmaps: Vec<Image>
+}
+
+/// Read all sequences and bitmaps from a color plate.
+///
+/// A color plate begins with a header where the top-left pixel is the background color, the pixel to the right of it
+/// is the sequence divider color, and the pixel to the right of that is the dummy space color (if it is different
+/// from the others). The rest of the header row is filled with the sequence divider color, which begins the first
+/// sequence. Each other sequence begins after a row filled with the sequence divider color, and bitmaps in a
+/// sequence are separated by rows or columns of background color. Dummy space inside of a bitmap is made transparent.
+///
+/// If the image is not a color plate, then the whole image is returned as one bitmap in one sequence.
+///
+/// Returns `Err` if the color plate is empty or has a sequence with no bitmaps.
+pub fn read_color_plate(plate: &Image) -> RinghopperResult<Vec<ColorPlateSequence>> {
+    let width = plate.width;
+    let height = plate.height;
+    if width == 0 || height == 0 || plate.data.len() != width.mul_overflow_checked(height)? {
+        return Err(Error::Other("color plate is empty or has an invalid size".to_owned()))
+    }
+
+    let pixel = |x: usize, y: usize| plate.data[x + y * width];
+    let single_bitmap = || vec![ColorPlateSequence { bitmaps: vec![plate.clone()] }];
+
+    if width < 2 || height < 2 {
+        return Ok(single_bitmap())
+    }
+
+    let background = pixel(0, 0);
+    let divider = pixel(1, 0);
+    if background == divider {
+        return Ok(single_bitmap())
+    }
+    let dummy_space = if width >= 3 && pixel(2, 0) != background && pixel(2, 0) != divider { Some(pixel(2, 0)) } else { None };
+
+    // The rest of the header row is the divider for the first sequence.
+    let header_width = if dummy_space.is_some() { 3 } else { 2 };
+    let is_divider_row = |y: usize| {
+        let start = if y == 0 { header_width } else { 0 };
+        start < width && (start..width).all(|x| pixel(x, y) == divider)
+    };
+    let divider_rows: Vec<usize> = (0..height).filter(|y| is_divider_row(*y)).collect();
+    if divider_rows.is_empty() {
+        return Ok(single_bitmap())
+    }
+
+    let is_content = |x: usize, y: usize| {
+        let p = pixel(x, y);
+        p != background && p != divider
+    };
+
+    let mut sequences = Vec::with_capacity(divider_rows.len());
+    for (index, start) in divider_rows.iter().enumerate() {
+        let rows = start + 1..divider_rows.get(index + 1).copied().unwrap_or(height);
+        let mut regions = Vec::new();
+        find_bitmap_regions(&is_content, 0..width, rows, &mut regions);
+
+        let bitmaps = regions.into_iter().map(|(columns, rows)| {
+            let mut bitmap = Image {
+                width: columns.len(),
+                height: rows.len(),
+                data: Vec::with_capacity(columns.len() * rows.len())
+            };
+            for y in rows {
+                for x in columns.clone() {
+                    let p = pixel(x, y);
+                    bitmap.data.push(if Some(p) == dummy_space { Pixel32::default() } else { p });
+                }
+            }
+            bitmap
+        }).collect();
+
+        sequences.push(ColorPlateSequence { bitmaps });
+    }
+
+    Ok(sequences)
+}
+
+/// Find the columns and rows of each bitmap in a region, in order from top to bottom and then left to right.
+///
+/// Bitmaps are separated by rows or columns without any content.
+fn find_bitmap_regions<F: Fn(usize, usize) -> bool>(is_content: &F, columns: Range<usize>, rows: Range<usize>, regions: &mut Vec<(Range<usize>, Range<usize>)>) {
+    let row_has_content = |y: usize| columns.clone().any(|x| is_content(x, y));
+    let row_runs = content_runs(rows.clone(), row_has_content);
+    if row_runs.len() > 1 {
+        for run in row_runs {
+            find_bitmap_regions(is_content, columns.clone(), run, regions);
+        }
+        return
+    }
+    let Some(rows) = row_runs.into_iter().next() else {
+        return
+    };
+
+    let column_has_content = |x: usize| rows.clone().any(|y| is_content(x, y));
+    let column_runs = content_runs(columns, column_has_content);
+    if column_runs.len() > 1 {
+        for run in column_runs {
+            find_bitmap_regions(is_content, run, rows.clone(), regions);
+        }
+        return
+    }
+    if let Some(columns) = column_runs.into_iter().next() {
+        regions.push((columns, rows));
+    }
+}
+
+/// Get each contiguous run of lines that have content.
+fn content_runs<F: Fn(usize) -> bool>(lines: Range<usize>, has_content: F) -> Vec<Range<usize>> {
+    let mut runs = Vec::new();
+    let mut run_start = None;
+    for line in lines.clone() {
+        match (has_content(line), run_start) {
+            (true, None) => run_start = Some(line),
+            (false, Some(start)) => {
+                runs.push(start..line);
+                run_start = None;
+            },
+            _ => ()
+        }
+    }
+    if let Some(start) = run_start {
+        runs.push(start..lines.end);
+    }
+    runs
+}
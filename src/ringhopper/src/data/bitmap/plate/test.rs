@@ -0,0 +1,81 @@
+use super::*;
+
+const B: Pixel32 = Pixel32 { color: 0xFF0000FF };
+const D: Pixel32 = Pixel32 { color: 0xFFFF00FF };
+const C: Pixel32 = Pixel32 { color: 0xFF00FFFF };
+const W: Pixel32 = Pixel32 { color: 0xFFFFFFFF };
+const R: Pixel32 = Pixel32 { color: 0xFFFF0000 };
+
+#[test]
+fn read_color_plate_sequences() {
+    let plate = Image {
+        width: 7,
+        height: 7,
+        data: vec![
+            B, D, C, D, D, D, D,
+            B, W, W, B, R, B, B,
+            B, W, C, B, R, B, B,
+            B, B, B, B, B, B, B,
+            D, D, D, D, D, D, D,
+            B, B, B, B, B, B, B,
+            B, B, R, R, R, B, B,
+        ]
+    };
+
+    let sequences = read_color_plate(&plate).unwrap();
+    assert_eq!(sequences.len(), 2);
+
+    let first = &sequences[0].bitmaps;
+    assert_eq!(first.len(), 2);
+    assert_eq!((first[0].width, first[0].height), (2, 2));
+    assert_eq!(first[0].data, vec![W, W, W, Pixel32::default()]);
+    assert_eq!((first[1].width, first[1].height), (1, 2));
+
+    let second = &sequences[1].bitmaps;
+    assert_eq!(second.len(), 1);
+    assert_eq!((second[0].width, second[0].height), (3, 1));
+    assert_eq!(second[0].data, vec![R, R, R]);
+}
+
+#[test]
+fn read_color_plate_without_header() {
+    let plate = Image {
+        width: 2,
+        height: 2,
+        data: vec![W, R, R, W]
+    };
+
+    let sequences = read_color_plate(&plate).unwrap();
+    assert_eq!(sequences.len(), 1);
+    assert_eq!(sequences[0].bitmaps.len(), 1);
+    assert_eq!(sequences[0].bitmaps[0].data, plate.data);
+}
+
+#[test]
+fn read_color_plate_stacked_bitmaps() {
+    let plate = Image {
+        width: 6,
+        height: 7,
+        data: vec![
+            B, D, D, D, D, D,
+            B, W, W, B, R, B,
+            B, B, B, B, R, B,
+            B, R, B, B, R, B,
+            B, R, B, B, B, B,
+            B, B, B, B, B, B,
+            B, W, W, W, B, B,
+        ]
+    };
+
+    let sequences = read_color_plate(&plate).unwrap();
+    assert_eq!(sequences.len(), 1);
+
+    // Read from top to bottom, then left to right
+    let bitmaps = &sequences[0].bitmaps;
+    let sizes: Vec<(usize, usize)> = bitmaps.iter().map(|b| (b.width, b.height)).collect();
+    assert_eq!(sizes, [(2, 1), (1, 2), (1, 3), (3, 1)]);
+    assert_eq!(bitmaps[0].data, vec![W, W]);
+    assert_eq!(bitmaps[1].data, vec![R, R]);
+    assert_eq!(bitmaps[2].data, vec![R, R, R]);
+    assert_eq!(bitmaps[3].data, vec![W, W, W]);
+}
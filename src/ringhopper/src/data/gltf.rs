@@ -0,0 +1,262 @@
+use std::fmt::Write;
+use primitives::primitive::{Quaternion, Vector2D, Vector3D};
+
+#[cfg(test)]
+mod test;
+
+const GLB_MAGIC: u32 = 0x46546C67;
+const GLB_VERSION: u32 = 2;
+const GLB_CHUNK_JSON: u32 = 0x4E4F534A;
+const GLB_CHUNK_BIN: u32 = 0x004E4942;
+
+const COMPONENT_TYPE_UNSIGNED_INT: u32 = 5125;
+const COMPONENT_TYPE_FLOAT: u32 = 5126;
+const TARGET_ARRAY_BUFFER: u32 = 34962;
+const TARGET_ELEMENT_ARRAY_BUFFER: u32 = 34963;
+
+/// Represents a node in a glTF scene.
+#[derive(Clone, Debug, Default)]
+pub struct GLTFNode {
+    /// Name of the node.
+    pub name: String,
+
+    /// Translation relative to the parent node.
+    pub translation: Vector3D,
+
+    /// Rotation relative to the parent node.
+    pub rotation: Quaternion,
+
+    /// Indices of the child nodes.
+    pub children: Vec<usize>,
+
+    /// Index of the mesh of this node, if any.
+    pub mesh: Option<usize>
+}
+
+/// Represents a set of triangles that share a material.
+#[derive(Clone, Debug, Default)]
+pub struct GLTFPrimitive {
+    /// Positions of each vertex.
+    pub positions: Vec<Vector3D>,
+
+    /// Normals of each vertex.
+    pub normals: Vec<Vector3D>,
+
+    /// Texture coordinates of each vertex.
+    ///
+    /// Like model tags, (0, 0) is the top-left of the texture.
+    pub texture_coordinates: Vec<Vector2D>,
+
+    /// Vertex indices, where each three indices is a triangle.
+    pub indices: Vec<u32>,
+
+    /// Index of the material, if any.
+    pub material: Option<usize>
+}
+
+/// Represents a mesh in a glTF scene.
+#[derive(Clone, Debug, Default)]
+pub struct GLTFMesh {
+    /// Name of the mesh.
+    pub name: String,
+
+    /// Primitives of the mesh.
+    pub primitives: Vec<GLTFPrimitive>
+}
+
+/// Represents a glTF 2.0 scene.
+///
+/// This only supports what is needed to view model geometry: a node hierarchy, triangle meshes, and named materials.
+#[derive(Clone, Debug, Default)]
+pub struct GLTF {
+    /// All nodes.
+    pub nodes: Vec<GLTFNode>,
+
+    /// All meshes.
+    pub meshes: Vec<GLTFMesh>,
+
+    /// Names of all materials.
+    pub materials: Vec<String>,
+
+    /// Indices of the nodes at the root of the scene.
+    pub scene_nodes: Vec<usize>
+}
+
+impl GLTF {
+    /// Encode as a binary glTF (.glb) file.
+    pub fn to_glb(&self) -> Vec<u8> {
+        let mut buffer = GLTFBuffer::default();
+        let mut meshes_json = Vec::with_capacity(self.meshes.len());
+
+        for mesh in &self.meshes {
+            let mut primitives_json = Vec::with_capacity(mesh.primitives.len());
+            for primitive in &mesh.primitives {
+                let mut attributes = String::new();
+                let _ = write!(attributes, "\"POSITION\":{}", buffer.add_vector3d(&primitive.positions, true));
+                if !primitive.normals.is_empty() {
+                    let _ = write!(attributes, ",\"NORMAL\":{}", buffer.add_vector3d(&primitive.normals, false));
+                }
+                if !primitive.texture_coordinates.is_empty() {
+                    let _ = write!(attributes, ",\"TEXCOORD_0\":{}", buffer.add_vector2d(&primitive.texture_coordinates));
+                }
+
+                let indices = buffer.add_indices(&primitive.indices);
+                let material = primitive.material.map(|m| format!(",\"material\":{m}")).unwrap_or_default();
+                primitives_json.push(format!("{{\"attributes\":{{{attributes}}},\"indices\":{indices},\"mode\":4{material}}}"));
+            }
+            meshes_json.push(format!("{{\"name\":{},\"primitives\":[{}]}}", json_string(&mesh.name), primitives_json.join(",")));
+        }
+
+        let nodes_json: Vec<String> = self.nodes.iter().map(|node| {
+            let mut json = format!(
+                "{{\"name\":{},\"translation\":[{},{},{}],\"rotation\":[{},{},{},{}]",
+                json_string(&node.name),
+                json_float(node.translation.x), json_float(node.translation.y), json_float(node.translation.z),
+                json_float(node.rotation.x), json_float(node.rotation.y), json_float(node.rotation.z), json_float(node.rotation.w)
+            );
+            if !node.children.is_empty() {
+                let _ = write!(json, ",\"children\":[{}]", join_indices(&node.children));
+            }
+            if let Some(mesh) = node.mesh {
+                let _ = write!(json, ",\"mesh\":{mesh}");
+            }
+            json.push('}');
+            json
+        }).collect();
+
+        let materials_json: Vec<String> = self.materials.iter().map(|m| format!("{{\"name\":{}}}", json_string(m))).collect();
+
+        let mut json = String::new();
+        let _ = write!(json, "{{\"asset\":{{\"version\":\"2.0\",\"generator\":\"ringhopper\"}},\"scene\":0,\"scenes\":[{{\"nodes\":[{}]}}]", join_indices(&self.scene_nodes));
+        if !nodes_json.is_empty() {
+            let _ = write!(json, ",\"nodes\":[{}]", nodes_json.join(","));
+        }
+        if !meshes_json.is_empty() {
+            let _ = write!(json, ",\"meshes\":[{}]", meshes_json.join(","));
+        }
+        if !materials_json.is_empty() {
+            let _ = write!(json, ",\"materials\":[{}]", materials_json.join(","));
+        }
+        if !buffer.accessors.is_empty() {
+            let _ = write!(
+                json,
+                ",\"accessors\":[{}],\"bufferViews\":[{}],\"buffers\":[{{\"byteLength\":{}}}]",
+                buffer.accessors.join(","),
+                buffer.buffer_views.join(","),
+                buffer.data.len()
+            );
+        }
+        json.push('}');
+
+        let mut json = json.into_bytes();
+        while !json.len().is_multiple_of(4) {
+            json.push(b' ');
+        }
+        let mut data = buffer.data;
+        while !data.len().is_multiple_of(4) {
+            data.push(0);
+        }
+
+        let mut output = Vec::with_capacity(12 + 8 + json.len() + 8 + data.len());
+        let total_length = 12 + 8 + json.len() + if data.is_empty() { 0 } else { 8 + data.len() };
+        output.extend_from_slice(&GLB_MAGIC.to_le_bytes());
+        output.extend_from_slice(&GLB_VERSION.to_le_bytes());
+        output.extend_from_slice(&(total_length as u32).to_le_bytes());
+
+        output.extend_from_slice(&(json.len() as u32).to_le_bytes());
+        output.extend_from_slice(&GLB_CHUNK_JSON.to_le_bytes());
+        output.extend_from_slice(&json);
+
+        if !data.is_empty() {
+            output.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            output.extend_from_slice(&GLB_CHUNK_BIN.to_le_bytes());
+            output.extend_from_slice(&data);
+        }
+
+        output
+    }
+}
+
+/// Binary buffer with its accessors and buffer views as JSON.
+#[derive(Default)]
+struct GLTFBuffer {
+    data: Vec<u8>,
+    accessors: Vec<String>,
+    buffer_views: Vec<String>
+}
+
+impl GLTFBuffer {
+    fn add_view(&mut self, bytes: &[u8], target: u32) -> usize {
+        let offset = self.data.len();
+        self.data.extend_from_slice(bytes);
+        self.buffer_views.push(format!("{{\"buffer\":0,\"byteOffset\":{offset},\"byteLength\":{},\"target\":{target}}}", bytes.len()));
+        self.buffer_views.len() - 1
+    }
+
+    fn add_accessor(&mut self, view: usize, component_type: u32, count: usize, accessor_type: &str, bounds: Option<(String, String)>) -> usize {
+        let bounds = bounds.map(|(min, max)| format!(",\"min\":[{min}],\"max\":[{max}]")).unwrap_or_default();
+        self.accessors.push(format!("{{\"bufferView\":{view},\"componentType\":{component_type},\"count\":{count},\"type\":\"{accessor_type}\"{bounds}}}"));
+        self.accessors.len() - 1
+    }
+
+    fn add_vector3d(&mut self, vectors: &[Vector3D], with_bounds: bool) -> usize {
+        let bytes: Vec<u8> = vectors.iter().flat_map(|v| [v.x, v.y, v.z]).flat_map(f32::to_le_bytes).collect();
+        let view = self.add_view(&bytes, TARGET_ARRAY_BUFFER);
+
+        // Positions require bounds.
+        let bounds = with_bounds.then(|| {
+            let mut min = [f32::MAX; 3];
+            let mut max = [f32::MIN; 3];
+            for v in vectors {
+                for (i, c) in [v.x, v.y, v.z].into_iter().enumerate() {
+                    min[i] = min[i].min(c);
+                    max[i] = max[i].max(c);
+                }
+            }
+            if vectors.is_empty() {
+                min = [0.0; 3];
+                max = [0.0; 3];
+            }
+            let format = |v: [f32; 3]| v.map(json_float).join(",");
+            (format(min), format(max))
+        });
+
+        self.add_accessor(view, COMPONENT_TYPE_FLOAT, vectors.len(), "VEC3", bounds)
+    }
+
+    fn add_vector2d(&mut self, vectors: &[Vector2D]) -> usize {
+        let bytes: Vec<u8> = vectors.iter().flat_map(|v| [v.x, v.y]).flat_map(f32::to_le_bytes).collect();
+        let view = self.add_view(&bytes, TARGET_ARRAY_BUFFER);
+        self.add_accessor(view, COMPONENT_TYPE_FLOAT, vectors.len(), "VEC2", None)
+    }
+
+    fn add_indices(&mut self, indices: &[u32]) -> usize {
+        let bytes: Vec<u8> = indices.iter().flat_map(|i| i.to_le_bytes()).collect();
+        let view = self.add_view(&bytes, TARGET_ELEMENT_ARRAY_BUFFER);
+        self.add_accessor(view, COMPONENT_TYPE_UNSIGNED_INT, indices.len(), "SCALAR", None)
+    }
+}
+
+fn join_indices(indices: &[usize]) -> String {
+    indices.iter().map(|i| i.to_string()).collect::<Vec<String>>().join(",")
+}
+
+/// JSON does not support NaN or infinity, so these are written as 0.
+fn json_float(value: f32) -> String {
+    if value.is_finite() { value.to_string() } else { "0".to_owned() }
+}
+
+fn json_string(value: &str) -> String {
+    let mut result = String::with_capacity(value.len() + 2);
+    result.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            c if (c as u32) < 0x20 => { let _ = write!(result, "\\u{:04x}", c as u32); },
+            c => result.push(c)
+        }
+    }
+    result.push('"');
+    result
+}
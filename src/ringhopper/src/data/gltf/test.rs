@@ -0,0 +1,46 @@
+use super::*;
+
+fn read_u32(data: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes(data[at..at + 4].try_into().unwrap())
+}
+
+#[test]
+fn encode_glb() {
+    let gltf = GLTF {
+        nodes: vec![
+            GLTFNode { name: "root \"node\"".to_owned(), children: vec![1], ..Default::default() },
+            GLTFNode { name: "mesh".to_owned(), mesh: Some(0), ..Default::default() }
+        ],
+        meshes: vec![GLTFMesh {
+            name: "triangle".to_owned(),
+            primitives: vec![GLTFPrimitive {
+                positions: vec![Vector3D { x: 0.0, y: 0.0, z: 0.0 }, Vector3D { x: 1.0, y: 0.0, z: 0.0 }, Vector3D { x: 0.0, y: 2.0, z: 0.0 }],
+                normals: vec![Vector3D { x: 0.0, y: 0.0, z: 1.0 }; 3],
+                texture_coordinates: vec![Vector2D::default(); 3],
+                indices: vec![0, 1, 2],
+                material: Some(0)
+            }]
+        }],
+        materials: vec!["metal".to_owned()],
+        scene_nodes: vec![0]
+    };
+
+    let glb = gltf.to_glb();
+    assert_eq!(GLB_MAGIC, read_u32(&glb, 0));
+    assert_eq!(GLB_VERSION, read_u32(&glb, 4));
+    assert_eq!(glb.len(), read_u32(&glb, 8) as usize);
+
+    let json_length = read_u32(&glb, 12) as usize;
+    assert_eq!(GLB_CHUNK_JSON, read_u32(&glb, 16));
+    assert_eq!(0, json_length % 4);
+    let json = std::str::from_utf8(&glb[20..20 + json_length]).unwrap();
+    assert!(json.contains("\"name\":\"root \\\"node\\\"\""), "{json}");
+    assert!(json.contains("\"min\":[0,0,0],\"max\":[1,2,0]"), "{json}");
+    assert!(json.contains("\"materials\":[{\"name\":\"metal\"}]"), "{json}");
+
+    // 3 positions + 3 normals + 3 texture coordinates + 3 indices
+    let bin_start = 20 + json_length;
+    assert_eq!(GLB_CHUNK_BIN, read_u32(&glb, bin_start + 4));
+    assert_eq!(36 + 36 + 24 + 12, read_u32(&glb, bin_start) as usize);
+    assert!(json.contains("\"buffers\":[{\"byteLength\":108}]"), "{json}");
+}
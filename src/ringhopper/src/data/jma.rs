@@ -0,0 +1,275 @@
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+use primitives::error::{Error, RinghopperResult};
+use primitives::primitive::{Quaternion, Vector3D};
+use super::jms::JMSReader;
+
+#[cfg(test)]
+mod test;
+
+/// Version of JMA files that can be read.
+pub const JMA_VERSION: u32 = 16392;
+
+/// Kind of animation, determined by the file extension of a JMA-family file.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum JMAKind {
+    /// Base animation with horizontal root node movement (.jma).
+    JMA,
+
+    /// Base animation with no root node movement (.jmm).
+    JMM,
+
+    /// Overlay animation, applied on top of other animations (.jmo).
+    JMO,
+
+    /// Replacement animation, replacing other animations for some nodes (.jmr).
+    JMR,
+
+    /// Base animation with horizontal root node movement and turning (.jmt).
+    JMT,
+
+    /// Base animation that is relative to the world rather than the object (.jmw).
+    JMW,
+
+    /// Base animation with horizontal and vertical root node movement and turning (.jmz).
+    JMZ
+}
+
+impl JMAKind {
+    /// All kinds of animations.
+    pub const ALL: [JMAKind; 7] = [JMAKind::JMA, JMAKind::JMM, JMAKind::JMO, JMAKind::JMR, JMAKind::JMT, JMAKind::JMW, JMAKind::JMZ];
+
+    /// Get the file extension, without the leading period.
+    pub fn extension(self) -> &'static str {
+        match self {
+            JMAKind::JMA => "jma",
+            JMAKind::JMM => "jmm",
+            JMAKind::JMO => "jmo",
+            JMAKind::JMR => "jmr",
+            JMAKind::JMT => "jmt",
+            JMAKind::JMW => "jmw",
+            JMAKind::JMZ => "jmz"
+        }
+    }
+
+    /// Get the kind of animation from a file extension, without the leading period.
+    pub fn from_extension(extension: &str) -> Option<JMAKind> {
+        JMAKind::ALL.into_iter().find(|k| k.extension().eq_ignore_ascii_case(extension))
+    }
+}
+
+/// Represents a node (bone) in a JMA file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct JMANode {
+    /// Name of the node.
+    pub name: String,
+
+    /// Index of the first child node, if any.
+    pub first_child: Option<usize>,
+
+    /// Index of the next sibling node, if any.
+    pub sibling: Option<usize>
+}
+
+/// Represents the position of a node on a single frame of a JMA file.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct JMANodeTransform {
+    /// Rotation relative to the parent node.
+    pub rotation: Quaternion,
+
+    /// Translation relative to the parent node, in JMS units.
+    pub translation: Vector3D,
+
+    /// Scale of the node.
+    pub scale: f32
+}
+
+/// Represents a JMA-family animation file.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct JMA {
+    /// Frames per second.
+    pub frame_rate: u32,
+
+    /// Checksum of the node list, used to check if the animation matches the model.
+    pub node_list_checksum: i32,
+
+    /// All nodes.
+    pub nodes: Vec<JMANode>,
+
+    /// All frames, where each frame has a transform for each node.
+    pub frames: Vec<Vec<JMANodeTransform>>
+}
+
+impl JMA {
+    /// Parse a JMA-family file.
+    ///
+    /// Returns `Err` if the file is not a valid JMA file or any indices are out-of-bounds.
+    pub fn parse(text: &str) -> RinghopperResult<JMA> {
+        let mut reader = JMSReader::new(text);
+
+        let version: u32 = reader.read_value()?;
+        if version != JMA_VERSION {
+            return Err(Error::Other(format!("unsupported JMA version {version} (expected {JMA_VERSION})")))
+        }
+
+        let frame_count = reader.read_count()?;
+        let frame_rate = reader.read_value()?;
+
+        // Actors are unused.
+        for _ in 0..reader.read_count()? {
+            reader.read_name()?;
+        }
+
+        let node_count = reader.read_count()?;
+        let mut jma = JMA {
+            frame_rate,
+            node_list_checksum: reader.read_value::<i64>()? as i32,
+            ..Default::default()
+        };
+
+        for _ in 0..node_count {
+            jma.nodes.push(JMANode {
+                name: reader.read_name()?,
+                first_child: reader.read_index()?,
+                sibling: reader.read_index()?
+            });
+        }
+
+        for _ in 0..frame_count {
+            let mut frame = Vec::with_capacity(node_count);
+            for _ in 0..node_count {
+                frame.push(JMANodeTransform {
+                    rotation: reader.read_quaternion()?,
+                    translation: reader.read_vector3d()?,
+                    scale: reader.read_value()?
+                });
+            }
+            jma.frames.push(frame);
+        }
+
+        jma.check_indices()?;
+        Ok(jma)
+    }
+
+    /// Check all indices for any out-of-bounds.
+    pub fn check_indices(&self) -> RinghopperResult<()> {
+        let node_count = self.nodes.len();
+        for node in &self.nodes {
+            for index in [node.first_child, node.sibling].into_iter().flatten() {
+                if index >= node_count {
+                    return Err(Error::Other(format!("node index {index} is out-of-bounds ({node_count} total)")))
+                }
+            }
+        }
+        for (index, frame) in self.frames.iter().enumerate() {
+            if frame.len() != node_count {
+                return Err(Error::Other(format!("frame {index} has {} node(s) instead of {node_count}", frame.len())))
+            }
+        }
+        Ok(())
+    }
+
+    /// Get the parent of each node.
+    ///
+    /// Returns `Err` if a node has more than one parent.
+    pub fn node_parents(&self) -> RinghopperResult<Vec<Option<usize>>> {
+        let mut parents = vec![None; self.nodes.len()];
+        for (parent, node) in self.nodes.iter().enumerate() {
+            let mut child = node.first_child;
+            while let Some(c) = child {
+                if parents[c].is_some() || c == 0 {
+                    return Err(Error::Other(format!("node `{}` has more than one parent", self.nodes[c].name)))
+                }
+                parents[c] = Some(parent);
+                child = self.nodes[c].sibling;
+            }
+        }
+        Ok(parents)
+    }
+
+    /// Encode as a JMA-family file.
+    pub fn to_jma(&self) -> Vec<u8> {
+        fn index(index: Option<usize>) -> i64 {
+            index.map(|i| i as i64).unwrap_or(-1)
+        }
+
+        let mut output = String::new();
+        let _ = writeln!(output, "{JMA_VERSION}");
+        let _ = writeln!(output, "{}", self.frames.len());
+        let _ = writeln!(output, "{}", self.frame_rate);
+        let _ = writeln!(output, "1\nunnamedActor");
+        let _ = writeln!(output, "{}", self.nodes.len());
+        let _ = writeln!(output, "{}", self.node_list_checksum);
+
+        for node in &self.nodes {
+            let _ = writeln!(output, "{}\n{}\n{}", node.name, index(node.first_child), index(node.sibling));
+        }
+
+        for frame in &self.frames {
+            for node in frame {
+                let r = node.rotation;
+                let t = node.translation;
+                let _ = writeln!(output, "{}\t{}\t{}\t{}", r.x, r.y, r.z, r.w);
+                let _ = writeln!(output, "{}\t{}\t{}", t.x, t.y, t.z);
+                let _ = writeln!(output, "{}", node.scale);
+            }
+        }
+
+        output.into_bytes()
+    }
+}
+
+/// A JMA-family file used to create a single animation of a model_animations tag.
+pub struct JMAAnimationSource {
+    /// Name of the animation.
+    pub name: String,
+
+    /// Kind of animation.
+    pub kind: JMAKind,
+
+    /// Animation data.
+    pub jma: JMA
+}
+
+/// Load a JMA-family file at the given path.
+pub fn load_jma_from_path<P: AsRef<Path>>(path: P) -> RinghopperResult<JMA> {
+    let path = path.as_ref();
+    let data = std::fs::read(path).map_err(|e| Error::FailedToReadFile(path.to_path_buf(), e))?;
+    let text = String::from_utf8_lossy(&data);
+    JMA::parse(&text).map_err(|e| Error::Other(format!("failed to parse {path:?}: {e}")))
+}
+
+/// Load the JMA-family files in a directory as animations.
+///
+/// The animation name is the file name without the extension, and the kind is determined by the extension. Files are
+/// sorted by name.
+///
+/// Returns `Err` if an error occurred, if an animation name is used more than once, or if no JMA-family files were
+/// found.
+pub fn load_animation_sources(directory: &Path) -> RinghopperResult<Vec<JMAAnimationSource>> {
+    let mut paths: Vec<(PathBuf, JMAKind)> = Vec::new();
+    let entries = std::fs::read_dir(directory).map_err(|e| Error::FailedToReadFile(directory.to_path_buf(), e))?;
+    for entry in entries {
+        let path = entry.map_err(|e| Error::FailedToReadFile(directory.to_path_buf(), e))?.path();
+        let kind = path.extension().and_then(|e| e.to_str()).and_then(JMAKind::from_extension);
+        if let (true, Some(kind)) = (path.is_file(), kind) {
+            paths.push((path, kind));
+        }
+    }
+    paths.sort_by(|a, b| a.0.cmp(&b.0));
+
+    if paths.is_empty() {
+        return Err(Error::Other(format!("{directory:?} contains no animation files")))
+    }
+
+    let mut sources: Vec<JMAAnimationSource> = Vec::with_capacity(paths.len());
+    for (path, kind) in paths {
+        let name = path.file_stem().unwrap_or_default().to_string_lossy().into_owned();
+        if sources.iter().any(|s| s.name == name) {
+            return Err(Error::Other(format!("animation `{name}` has more than one file")))
+        }
+        sources.push(JMAAnimationSource { name, kind, jma: load_jma_from_path(&path)? });
+    }
+
+    Ok(sources)
+}
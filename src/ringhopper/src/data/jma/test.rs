@@ -0,0 +1,67 @@
+use super::*;
+
+const TEST_JMA: &str = "16392
+2
+30
+1
+unnamedActor
+2
+3251
+frame root
+1
+-1
+bone 1
+-1
+-1
+0.0\t0.0\t0.0\t1.0
+0.0\t0.0\t0.0
+1.0
+0.0\t0.0\t0.0\t1.0
+10.0\t0.0\t0.0
+1.0
+0.0\t0.0\t0.0\t1.0
+5.0\t0.0\t0.0
+1.0
+0.0\t0.0\t0.6\t0.8
+10.0\t0.0\t0.0
+1.0
+";
+
+#[test]
+fn parse_jma() {
+    let jma = JMA::parse(TEST_JMA).unwrap();
+    assert_eq!(30, jma.frame_rate);
+    assert_eq!(3251, jma.node_list_checksum);
+
+    assert_eq!(2, jma.nodes.len());
+    assert_eq!("frame root", jma.nodes[0].name);
+    assert_eq!(Some(1), jma.nodes[0].first_child);
+    assert_eq!(vec![None, Some(0)], jma.node_parents().unwrap());
+
+    assert_eq!(2, jma.frames.len());
+    assert_eq!(5.0, jma.frames[1][0].translation.x);
+    assert_eq!(0.8, jma.frames[1][1].rotation.w);
+}
+
+#[test]
+fn reject_invalid_jma() {
+    assert!(JMA::parse(&TEST_JMA.replace("16392", "16391")).is_err());
+    assert!(JMA::parse(&TEST_JMA[..TEST_JMA.len() - 5]).is_err());
+
+    let error = JMA::parse(&TEST_JMA.replace("frame root\n1\n", "frame root\n2\n")).unwrap_err().to_string();
+    assert!(error.contains("node index 2 is out-of-bounds"), "{error}");
+}
+
+#[test]
+fn jma_round_trip() {
+    let jma = JMA::parse(TEST_JMA).unwrap();
+    let encoded = jma.to_jma();
+    assert_eq!(jma, JMA::parse(std::str::from_utf8(&encoded).unwrap()).unwrap());
+}
+
+#[test]
+fn jma_kind_from_extension() {
+    assert_eq!(Some(JMAKind::JMO), JMAKind::from_extension("JMO"));
+    assert_eq!(Some(JMAKind::JMZ), JMAKind::from_extension("jmz"));
+    assert_eq!(None, JMAKind::from_extension("jms"));
+}
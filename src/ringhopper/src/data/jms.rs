@@ -0,0 +1,544 @@
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use primitives::error::{Error, RinghopperResult};
+use primitives::primitive::{Quaternion, Vector2D, Vector3D};
+
+#[cfg(test)]
+mod test;
+
+/// Version of JMS files that can be read.
+pub const JMS_VERSION: u32 = 8200;
+
+/// Number of JMS units in a world unit.
+pub const JMS_UNITS_PER_WORLD_UNIT: f32 = 100.0;
+
+/// Represents a node (bone) in a JMS file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct JMSNode {
+    /// Name of the node.
+    pub name: String,
+
+    /// Index of the first child of the node, if any.
+    pub first_child: Option<usize>,
+
+    /// Index of the next sibling of the node, if any.
+    pub sibling: Option<usize>,
+
+    /// Default rotation relative to the parent node.
+    pub rotation: Quaternion,
+
+    /// Default translation relative to the parent node, in JMS units.
+    pub translation: Vector3D
+}
+
+/// Represents a material in a JMS file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct JMSMaterial {
+    /// Name of the material.
+    ///
+    /// This is used to find the shader tag.
+    pub name: String,
+
+    /// Path to the texture used by the material when it was exported.
+    pub tiff_path: String
+}
+
+/// Symbols that can be at the end of a material name.
+const MATERIAL_SYMBOLS: [char; 7] = ['%', '#', '!', '@', '^', '-', '&'];
+
+impl JMSMaterial {
+    /// Get the name of the material without any symbols at the end of it.
+    pub fn shader_name(&self) -> &str {
+        self.name.trim_end_matches(MATERIAL_SYMBOLS)
+    }
+
+    /// Get the properties set by symbols at the end of the name.
+    pub fn properties(&self) -> JMSMaterialProperties {
+        let symbols = &self.name[self.shader_name().len()..];
+        JMSMaterialProperties {
+            two_sided: symbols.contains('%'),
+            transparent: symbols.contains('#'),
+            render_only: symbols.contains('!'),
+            collision_only: symbols.contains('@'),
+            ladder: symbols.contains('^'),
+            breakable: symbols.contains('-'),
+            ai_deafening: symbols.contains('&')
+        }
+    }
+}
+
+/// Properties of a material given by symbols at the end of its name.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct JMSMaterialProperties {
+    /// `%` - both sides of the surface collide
+    pub two_sided: bool,
+
+    /// `#` - both sides of the surface collide, and it does not block visibility
+    pub transparent: bool,
+
+    /// `!` - the surface is rendered but does not collide
+    pub render_only: bool,
+
+    /// `@` - the surface collides but is not rendered
+    pub collision_only: bool,
+
+    /// `^` - the surface can be climbed
+    pub ladder: bool,
+
+    /// `-` - the surface can be broken
+    pub breakable: bool,
+
+    /// `&` - AI cannot hear through the surface (used on portals)
+    pub ai_deafening: bool
+}
+
+/// Represents a marker in a JMS file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct JMSMarker {
+    /// Name of the marker.
+    pub name: String,
+
+    /// Region of the marker, or `None` if the marker is in every region.
+    pub region: Option<usize>,
+
+    /// Node the marker is attached to.
+    pub node: usize,
+
+    /// Rotation relative to the node.
+    pub rotation: Quaternion,
+
+    /// Translation relative to the node, in JMS units.
+    pub translation: Vector3D,
+
+    /// Radius of the marker, in JMS units.
+    pub radius: f32
+}
+
+/// Represents a vertex in a JMS file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct JMSVertex {
+    /// Primary node of the vertex.
+    pub node0: usize,
+
+    /// Position of the vertex, in JMS units.
+    pub position: Vector3D,
+
+    /// Normal of the vertex.
+    pub normal: Vector3D,
+
+    /// Secondary node of the vertex, if any.
+    pub node1: Option<usize>,
+
+    /// Weight of the secondary node.
+    ///
+    /// The weight of the primary node is `1.0 - node1_weight`.
+    pub node1_weight: f32,
+
+    /// Texture coordinates of the vertex.
+    ///
+    /// Note that the V coordinate is flipped compared to model tags.
+    pub texture_coordinates: Vector2D
+}
+
+/// Represents a triangle in a JMS file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct JMSTriangle {
+    /// Region of the triangle.
+    pub region: usize,
+
+    /// Material of the triangle.
+    pub material: usize,
+
+    /// Vertex indices of the triangle.
+    pub vertices: [usize; 3]
+}
+
+/// Represents a JMS file, the format used for model geometry sources.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct JMS {
+    /// Checksum of the node list.
+    ///
+    /// This must match animations used with the model.
+    pub node_list_checksum: i32,
+
+    /// All nodes.
+    pub nodes: Vec<JMSNode>,
+
+    /// All materials.
+    pub materials: Vec<JMSMaterial>,
+
+    /// All markers.
+    pub markers: Vec<JMSMarker>,
+
+    /// Names of all regions.
+    pub regions: Vec<String>,
+
+    /// All vertices.
+    pub vertices: Vec<JMSVertex>,
+
+    /// All triangles.
+    pub triangles: Vec<JMSTriangle>
+}
+
+impl JMS {
+    /// Parse a JMS file.
+    ///
+    /// Returns `Err` if the file is not a valid JMS file or any indices are out-of-bounds.
+    pub fn parse(text: &str) -> RinghopperResult<JMS> {
+        let mut reader = JMSReader::new(text);
+
+        let version: u32 = reader.read_value()?;
+        if version != JMS_VERSION {
+            return Err(Error::Other(format!("unsupported JMS version {version} (expected {JMS_VERSION})")))
+        }
+
+        let mut jms = JMS {
+            node_list_checksum: reader.read_value::<i64>()? as i32,
+            ..Default::default()
+        };
+
+        for _ in 0..reader.read_count()? {
+            jms.nodes.push(JMSNode {
+                name: reader.read_name()?,
+                first_child: reader.read_index()?,
+                sibling: reader.read_index()?,
+                rotation: reader.read_quaternion()?,
+                translation: reader.read_vector3d()?
+            });
+        }
+
+        for _ in 0..reader.read_count()? {
+            jms.materials.push(JMSMaterial {
+                name: reader.read_name()?,
+                tiff_path: reader.read_name()?
+            });
+        }
+
+        for _ in 0..reader.read_count()? {
+            jms.markers.push(JMSMarker {
+                name: reader.read_name()?,
+                region: reader.read_index()?,
+                node: reader.read_value()?,
+                rotation: reader.read_quaternion()?,
+                translation: reader.read_vector3d()?,
+                radius: reader.read_value()?
+            });
+        }
+
+        for _ in 0..reader.read_count()? {
+            jms.regions.push(reader.read_name()?);
+        }
+
+        for _ in 0..reader.read_count()? {
+            let node0 = reader.read_value()?;
+            let position = reader.read_vector3d()?;
+            let normal = reader.read_vector3d()?;
+            let node1 = reader.read_index()?;
+            let node1_weight = reader.read_value()?;
+            let texture_coordinates = Vector2D { x: reader.read_value()?, y: reader.read_value()? };
+            let _w: f32 = reader.read_value()?;
+            jms.vertices.push(JMSVertex { node0, position, normal, node1, node1_weight, texture_coordinates });
+        }
+
+        for _ in 0..reader.read_count()? {
+            jms.triangles.push(JMSTriangle {
+                region: reader.read_value()?,
+                material: reader.read_value()?,
+                vertices: [reader.read_value()?, reader.read_value()?, reader.read_value()?]
+            });
+        }
+
+        jms.check_indices()?;
+        Ok(jms)
+    }
+
+    /// Check all indices for any out-of-bounds.
+    pub fn check_indices(&self) -> RinghopperResult<()> {
+        let node_count = self.nodes.len();
+        let check = |what: &str, index: usize, count: usize| if index >= count {
+            Err(Error::Other(format!("{what} index {index} is out-of-bounds ({count} total)")))
+        }
+        else {
+            Ok(())
+        };
+
+        for node in &self.nodes {
+            node.first_child.map_or(Ok(()), |n| check("node", n, node_count))?;
+            node.sibling.map_or(Ok(()), |n| check("node", n, node_count))?;
+        }
+        for marker in &self.markers {
+            check("node", marker.node, node_count)?;
+            marker.region.map_or(Ok(()), |r| check("region", r, self.regions.len()))?;
+        }
+        for vertex in &self.vertices {
+            check("node", vertex.node0, node_count)?;
+            vertex.node1.map_or(Ok(()), |n| check("node", n, node_count))?;
+        }
+        for triangle in &self.triangles {
+            check("region", triangle.region, self.regions.len())?;
+            check("material", triangle.material, self.materials.len())?;
+            for v in triangle.vertices {
+                check("vertex", v, self.vertices.len())?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Get the parent of each node.
+    ///
+    /// Returns `Err` if a node has more than one parent.
+    pub fn node_parents(&self) -> RinghopperResult<Vec<Option<usize>>> {
+        let mut parents = vec![None; self.nodes.len()];
+        for (parent, node) in self.nodes.iter().enumerate() {
+            let mut child = node.first_child;
+            while let Some(c) = child {
+                if parents[c].is_some() || c == 0 {
+                    return Err(Error::Other(format!("node `{}` has more than one parent", self.nodes[c].name)))
+                }
+                parents[c] = Some(parent);
+                child = self.nodes[c].sibling;
+            }
+        }
+        Ok(parents)
+    }
+
+    /// Encode as a JMS file.
+    pub fn to_jms(&self) -> Vec<u8> {
+        fn index(index: Option<usize>) -> i64 {
+            index.map(|i| i as i64).unwrap_or(-1)
+        }
+
+        let mut output = String::new();
+        let _ = writeln!(output, "{JMS_VERSION}");
+        let _ = writeln!(output, "{}", self.node_list_checksum);
+
+        let _ = writeln!(output, "{}", self.nodes.len());
+        for node in &self.nodes {
+            let _ = writeln!(output, "{}", node.name);
+            let _ = writeln!(output, "{}", index(node.first_child));
+            let _ = writeln!(output, "{}", index(node.sibling));
+            write_quaternion(&mut output, &node.rotation);
+            write_vector3d(&mut output, &node.translation);
+        }
+
+        let _ = writeln!(output, "{}", self.materials.len());
+        for material in &self.materials {
+            let _ = writeln!(output, "{}", material.name);
+            let _ = writeln!(output, "{}", material.tiff_path);
+        }
+
+        let _ = writeln!(output, "{}", self.markers.len());
+        for marker in &self.markers {
+            let _ = writeln!(output, "{}", marker.name);
+            let _ = writeln!(output, "{}", index(marker.region));
+            let _ = writeln!(output, "{}", marker.node);
+            write_quaternion(&mut output, &marker.rotation);
+            write_vector3d(&mut output, &marker.translation);
+            let _ = writeln!(output, "{}", marker.radius);
+        }
+
+        let _ = writeln!(output, "{}", self.regions.len());
+        for region in &self.regions {
+            let _ = writeln!(output, "{region}");
+        }
+
+        let _ = writeln!(output, "{}", self.vertices.len());
+        for vertex in &self.vertices {
+            let _ = writeln!(output, "{}", vertex.node0);
+            write_vector3d(&mut output, &vertex.position);
+            write_vector3d(&mut output, &vertex.normal);
+            let _ = writeln!(output, "{}", index(vertex.node1));
+            let _ = writeln!(output, "{}", vertex.node1_weight);
+            let _ = writeln!(output, "{}\t{}", vertex.texture_coordinates.x, vertex.texture_coordinates.y);
+            let _ = writeln!(output, "0");
+        }
+
+        let _ = writeln!(output, "{}", self.triangles.len());
+        for triangle in &self.triangles {
+            let _ = writeln!(output, "{}", triangle.region);
+            let _ = writeln!(output, "{}", triangle.material);
+            let [a, b, c] = triangle.vertices;
+            let _ = writeln!(output, "{a}\t{b}\t{c}");
+        }
+
+        output.into_bytes()
+    }
+}
+
+fn write_vector3d(output: &mut String, vector: &Vector3D) {
+    let _ = writeln!(output, "{}\t{}\t{}", vector.x, vector.y, vector.z);
+}
+
+fn write_quaternion(output: &mut String, quaternion: &Quaternion) {
+    let _ = writeln!(output, "{}\t{}\t{}\t{}", quaternion.x, quaternion.y, quaternion.z, quaternion.w);
+}
+
+/// Reads JMS files and similar formats, such as JMA files.
+///
+/// Values are separated by whitespace, but names occupy an entire line since they can contain spaces.
+pub(super) struct JMSReader<'a> {
+    lines: std::iter::Enumerate<std::str::Lines<'a>>,
+    line_number: usize,
+    tokens: std::str::SplitWhitespace<'a>
+}
+
+impl<'a> JMSReader<'a> {
+    pub(super) fn new(text: &'a str) -> Self {
+        Self {
+            lines: text.lines().enumerate(),
+            line_number: 0,
+            tokens: "".split_whitespace()
+        }
+    }
+
+    pub(super) fn error(&self, message: &str) -> Error {
+        Error::Other(format!("line {}: {message}", self.line_number))
+    }
+
+    pub(super) fn next_line(&mut self) -> RinghopperResult<&'a str> {
+        let (index, line) = self.lines.next().ok_or_else(|| self.error("unexpected end of file"))?;
+        self.line_number = index + 1;
+        Ok(line)
+    }
+
+    pub(super) fn read_token(&mut self) -> RinghopperResult<&'a str> {
+        loop {
+            if let Some(token) = self.tokens.next() {
+                return Ok(token)
+            }
+            self.tokens = self.next_line()?.split_whitespace();
+        }
+    }
+
+    pub(super) fn read_value<T: FromStr>(&mut self) -> RinghopperResult<T> {
+        let token = self.read_token()?;
+        token.parse().map_err(|_| self.error(&format!("`{token}` is not a valid {}", std::any::type_name::<T>())))
+    }
+
+    pub(super) fn read_count(&mut self) -> RinghopperResult<usize> {
+        self.read_value()
+    }
+
+    pub(super) fn read_index(&mut self) -> RinghopperResult<Option<usize>> {
+        let value: i64 = self.read_value()?;
+        match value {
+            -1 => Ok(None),
+            n if n >= 0 => Ok(Some(n as usize)),
+            n => Err(self.error(&format!("{n} is not a valid index")))
+        }
+    }
+
+    pub(super) fn read_name(&mut self) -> RinghopperResult<String> {
+        if self.tokens.clone().next().is_some() {
+            return Err(self.error("expected a new line"))
+        }
+        self.tokens = "".split_whitespace();
+        Ok(self.next_line()?.trim().to_owned())
+    }
+
+    pub(super) fn read_vector3d(&mut self) -> RinghopperResult<Vector3D> {
+        Ok(Vector3D { x: self.read_value()?, y: self.read_value()?, z: self.read_value()? })
+    }
+
+    pub(super) fn read_quaternion(&mut self) -> RinghopperResult<Quaternion> {
+        Ok(Quaternion { x: self.read_value()?, y: self.read_value()?, z: self.read_value()?, w: self.read_value()? })
+    }
+}
+
+/// Level of detail of model geometry.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ModelLOD {
+    SuperLow,
+    Low,
+    Medium,
+    High,
+    SuperHigh
+}
+
+impl ModelLOD {
+    /// All levels of detail, from lowest to highest.
+    pub const ALL: [ModelLOD; 5] = [ModelLOD::SuperLow, ModelLOD::Low, ModelLOD::Medium, ModelLOD::High, ModelLOD::SuperHigh];
+
+    /// Get the name used in JMS file names.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ModelLOD::SuperLow => "superlow",
+            ModelLOD::Low => "low",
+            ModelLOD::Medium => "medium",
+            ModelLOD::High => "high",
+            ModelLOD::SuperHigh => "superhigh"
+        }
+    }
+
+    /// Get the level of detail from a name used in JMS file names.
+    pub fn from_name(name: &str) -> Option<ModelLOD> {
+        ModelLOD::ALL.into_iter().find(|l| l.as_str().eq_ignore_ascii_case(name))
+    }
+}
+
+/// A JMS file used to create a single permutation at a single level of detail of a model tag.
+pub struct JMSModelSource {
+    /// Name of the permutation.
+    pub permutation: String,
+
+    /// Level of detail.
+    pub lod: ModelLOD,
+
+    /// Geometry.
+    pub jms: JMS
+}
+
+/// Load a JMS file at the given path.
+pub fn load_jms_from_path<P: AsRef<Path>>(path: P) -> RinghopperResult<JMS> {
+    let path = path.as_ref();
+    let data = std::fs::read(path).map_err(|e| Error::FailedToReadFile(path.to_path_buf(), e))?;
+    let text = String::from_utf8_lossy(&data);
+    JMS::parse(&text).map_err(|e| Error::Other(format!("failed to parse {path:?}: {e}")))
+}
+
+/// Load the JMS files in a directory as model permutations.
+///
+/// Files are named `<permutation> <lod>.jms`, where `<lod>` is `superlow`, `low`, `medium`, `high`, or `superhigh`. If
+/// the level of detail is omitted, `superhigh` is used. Files are sorted by name.
+///
+/// Returns `Err` if an error occurred, if a permutation has the same level of detail more than once, or if no JMS
+/// files were found.
+pub fn load_model_sources(directory: &Path) -> RinghopperResult<Vec<JMSModelSource>> {
+    let mut paths: Vec<PathBuf> = Vec::new();
+    let entries = std::fs::read_dir(directory).map_err(|e| Error::FailedToReadFile(directory.to_path_buf(), e))?;
+    for entry in entries {
+        let path = entry.map_err(|e| Error::FailedToReadFile(directory.to_path_buf(), e))?.path();
+        if path.is_file() && path.extension().is_some_and(|e| e.eq_ignore_ascii_case("jms")) {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+
+    if paths.is_empty() {
+        return Err(Error::Other(format!("{directory:?} contains no JMS files")))
+    }
+
+    let mut sources: Vec<JMSModelSource> = Vec::with_capacity(paths.len());
+    for path in paths {
+        let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+        let (permutation, lod) = parse_permutation_name(&stem);
+        if sources.iter().any(|s| s.permutation == permutation && s.lod == lod) {
+            return Err(Error::Other(format!("permutation `{permutation}` has more than one {} JMS", lod.as_str())))
+        }
+        sources.push(JMSModelSource { permutation, lod, jms: load_jms_from_path(&path)? });
+    }
+
+    Ok(sources)
+}
+
+/// Split a JMS file name into a permutation name and level of detail.
+pub fn parse_permutation_name(name: &str) -> (String, ModelLOD) {
+    if let Some((permutation, lod)) = name.rsplit_once(' ') {
+        if let Some(lod) = ModelLOD::from_name(lod) {
+            return (permutation.trim_end().to_owned(), lod)
+        }
+    }
+    (name.to_owned(), ModelLOD::SuperHigh)
+}
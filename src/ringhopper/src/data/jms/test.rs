@@ -0,0 +1,122 @@
+use super::*;
+
+const TEST_JMS: &str = "8200
+3251
+2
+frame root
+1
+-1
+0.0\t0.0\t0.0\t1.0
+0.0\t0.0\t0.0
+bone 1
+-1
+-1
+0.0\t0.0\t0.0\t1.0
+10.0\t0.0\t0.0
+1
+metal
+<none>
+1
+muzzle
+-1
+1
+0.0\t0.0\t0.0\t1.0
+5.0\t0.0\t0.0
+1.0
+1
+base
+3
+0
+0.0\t0.0\t0.0
+0.0\t0.0\t1.0
+-1
+0.0
+0.0\t0.0
+0
+0
+100.0\t0.0\t0.0
+0.0\t0.0\t1.0
+1
+0.25
+1.0\t0.0
+0
+0
+0.0\t100.0\t0.0
+0.0\t0.0\t1.0
+-1
+0.0
+0.0\t1.0
+0
+1
+0
+0
+0\t1\t2
+";
+
+#[test]
+fn parse_jms() {
+    let jms = JMS::parse(TEST_JMS).unwrap();
+    assert_eq!(3251, jms.node_list_checksum);
+
+    assert_eq!(2, jms.nodes.len());
+    assert_eq!("frame root", jms.nodes[0].name);
+    assert_eq!(Some(1), jms.nodes[0].first_child);
+    assert_eq!(None, jms.nodes[0].sibling);
+    assert_eq!(10.0, jms.nodes[1].translation.x);
+    assert_eq!(vec![None, Some(0)], jms.node_parents().unwrap());
+
+    assert_eq!("metal", jms.materials[0].name);
+    assert_eq!("<none>", jms.materials[0].tiff_path);
+
+    assert_eq!("muzzle", jms.markers[0].name);
+    assert_eq!(None, jms.markers[0].region);
+    assert_eq!(1, jms.markers[0].node);
+
+    assert_eq!(vec!["base".to_owned()], jms.regions);
+
+    assert_eq!(3, jms.vertices.len());
+    assert_eq!(Some(1), jms.vertices[1].node1);
+    assert_eq!(0.25, jms.vertices[1].node1_weight);
+    assert_eq!(1.0, jms.vertices[2].texture_coordinates.y);
+
+    assert_eq!(vec![JMSTriangle { region: 0, material: 0, vertices: [0, 1, 2] }], jms.triangles);
+}
+
+#[test]
+fn reject_invalid_jms() {
+    assert!(JMS::parse("8199\n0\n0\n0\n0\n0\n0\n0\n").is_err());
+    assert!(JMS::parse(&TEST_JMS[..TEST_JMS.len() - 8]).is_err());
+
+    // Vertex 3 does not exist
+    let error = JMS::parse(&TEST_JMS.replace("0\t1\t2", "0\t1\t3")).unwrap_err().to_string();
+    assert!(error.contains("vertex index 3 is out-of-bounds"), "{error}");
+
+    let error = JMS::parse(&TEST_JMS.replace("3251\n2\n", "3251\nx\n")).unwrap_err().to_string();
+    assert!(error.starts_with("line 3:"), "{error}");
+}
+
+#[test]
+fn parse_permutation_names() {
+    assert_eq!(("base".to_owned(), ModelLOD::SuperHigh), parse_permutation_name("base"));
+    assert_eq!(("base".to_owned(), ModelLOD::Low), parse_permutation_name("base low"));
+    assert_eq!(("damaged".to_owned(), ModelLOD::SuperLow), parse_permutation_name("damaged SuperLow"));
+    assert_eq!(("two words".to_owned(), ModelLOD::SuperHigh), parse_permutation_name("two words"));
+}
+
+#[test]
+fn jms_round_trip() {
+    let jms = JMS::parse(TEST_JMS).unwrap();
+    let encoded = jms.to_jms();
+    assert_eq!(jms, JMS::parse(std::str::from_utf8(&encoded).unwrap()).unwrap());
+}
+
+#[test]
+fn parse_material_symbols() {
+    let material = JMSMaterial { name: "glass%-".to_owned(), tiff_path: String::new() };
+    assert_eq!("glass", material.shader_name());
+    assert_eq!(JMSMaterialProperties { two_sided: true, breakable: true, ..Default::default() }, material.properties());
+
+    let material = JMSMaterial { name: "+portal&".to_owned(), tiff_path: String::new() };
+    assert_eq!("+portal", material.shader_name());
+    assert!(material.properties().ai_deafening);
+}
@@ -0,0 +1,251 @@
+use std::path::{Path, PathBuf};
+use primitives::error::{Error, RinghopperResult};
+
+mod flac;
+mod wav;
+
+#[cfg(test)]
+mod test;
+
+/// Represents uncompressed audio.
+#[derive(Clone, Default)]
+pub struct Audio {
+    /// Number of samples per second for each channel.
+    pub sample_rate: u32,
+
+    /// Number of channels.
+    pub channel_count: u32,
+
+    /// Interleaved samples, normalized to `[-1.0, 1.0]`.
+    pub samples: Vec<f32>
+}
+
+const AUDIO_LOADING_FUNCTIONS: &'static [(&'static str, fn(data: &[u8]) -> RinghopperResult<Audio>)] = &[
+    ("flac", Audio::from_flac),
+    ("wav", Audio::from_wav),
+];
+
+/// Load audio at the given path.
+///
+/// Returns `Err` if the audio is unsupported or an error occurred.
+pub fn load_audio_from_path<P: AsRef<Path>>(path: P) -> RinghopperResult<Audio> {
+    let path = path.as_ref();
+    let extension = get_audio_extension(path)
+        .ok_or_else(|| Error::Other(format!("unrecognized audio extension for `{path:?}`")))?;
+
+    let data = std::fs::read(path)
+        .map_err(|e| Error::FailedToReadFile(path.to_path_buf(), e))?;
+
+    for (load_ext, loader) in AUDIO_LOADING_FUNCTIONS {
+        if load_ext.eq_ignore_ascii_case(extension) {
+            return loader(&data)
+        }
+    }
+
+    unreachable!()
+}
+
+fn get_audio_extension(path: &Path) -> Option<&str> {
+    let extension = path.extension()?.to_str()?;
+    AUDIO_LOADING_FUNCTIONS
+        .iter()
+        .any(|(e, _)| e.eq_ignore_ascii_case(extension))
+        .then_some(extension)
+}
+
+impl Audio {
+    /// Get the number of samples in each channel.
+    pub fn frame_count(&self) -> usize {
+        if self.channel_count == 0 {
+            0
+        }
+        else {
+            self.samples.len() / self.channel_count as usize
+        }
+    }
+
+    /// Get the length of the audio in seconds.
+    pub fn duration(&self) -> f64 {
+        if self.sample_rate == 0 {
+            0.0
+        }
+        else {
+            self.frame_count() as f64 / self.sample_rate as f64
+        }
+    }
+
+    /// Resample the audio to the given sample rate using a windowed sinc filter.
+    ///
+    /// When downsampling, frequencies above the new Nyquist frequency are filtered out to prevent aliasing.
+    pub fn resample(&self, sample_rate: u32) -> Audio {
+        if sample_rate == self.sample_rate || self.frame_count() == 0 {
+            return Audio { sample_rate, ..self.clone() }
+        }
+
+        let channel_count = self.channel_count as usize;
+        let input_frames = self.frame_count();
+        let output_frames = ((input_frames as u64 * sample_rate as u64).div_ceil(self.sample_rate as u64)) as usize;
+        let ratio = self.sample_rate as f64 / sample_rate as f64;
+
+        // Cutoff frequency relative to the input's Nyquist frequency, and the kernel's radius in input frames
+        let cutoff = (1.0 / ratio).min(1.0);
+        let radius = RESAMPLE_ZERO_CROSSINGS as f64 / cutoff;
+
+        let sample_at = |frame: isize, channel: usize| -> f32 {
+            let frame = frame.clamp(0, input_frames as isize - 1) as usize;
+            self.samples[frame * channel_count + channel]
+        };
+
+        let mut samples = Vec::with_capacity(output_frames * channel_count);
+        let mut sums = vec![0.0f64; channel_count];
+        for frame in 0..output_frames {
+            let position = frame as f64 * ratio;
+            let first = (position - radius).ceil() as isize;
+            let last = (position + radius).floor() as isize;
+
+            sums.fill(0.0);
+            let mut total_weight = 0.0;
+            for input_frame in first..=last {
+                let weight = resample_kernel((position - input_frame as f64) * cutoff, radius * cutoff);
+                total_weight += weight;
+                for (channel, sum) in sums.iter_mut().enumerate() {
+                    *sum += sample_at(input_frame, channel) as f64 * weight;
+                }
+            }
+
+            // Normalize so the gain is exactly 1 regardless of where the kernel is sampled
+            samples.extend(sums.iter().map(|sum| ((sum / total_weight) as f32).clamp(-1.0, 1.0)));
+        }
+
+        Audio { sample_rate, channel_count: self.channel_count, samples }
+    }
+
+    /// Convert the audio to the given channel count.
+    ///
+    /// Only conversions between mono and stereo are supported. Returns `Err` if the conversion is not supported.
+    pub fn remix(&self, channel_count: u32) -> RinghopperResult<Audio> {
+        let samples = match (self.channel_count, channel_count) {
+            (a, b) if a == b => self.samples.clone(),
+            (1, 2) => self.samples.iter().flat_map(|s| [*s, *s]).collect(),
+            (2, 1) => self.samples.chunks_exact(2).map(|s| (s[0] + s[1]) / 2.0).collect(),
+            (a, b) => return Err(Error::Other(format!("cannot convert {a} channel audio to {b} channel audio")))
+        };
+
+        Ok(Audio { sample_rate: self.sample_rate, channel_count, samples })
+    }
+
+    /// Convert the samples to signed 16-bit PCM.
+    pub fn to_pcm16(&self) -> Vec<i16> {
+        self.samples.iter().map(|s| (s * 32768.0).round().clamp(-32768.0, 32767.0) as i16).collect()
+    }
+
+    /// Create audio from signed 16-bit PCM.
+    pub fn from_pcm16(samples: &[i16], channel_count: u32, sample_rate: u32) -> Audio {
+        Audio {
+            sample_rate,
+            channel_count,
+            samples: samples.iter().map(|s| *s as f32 / 32768.0).collect()
+        }
+    }
+}
+
+/// Number of zero crossings on each side of the resampling filter.
+const RESAMPLE_ZERO_CROSSINGS: usize = 16;
+
+/// Blackman-windowed sinc function, where `x` is in units of zero crossings and the window ends at `±radius`.
+fn resample_kernel(x: f64, radius: f64) -> f64 {
+    if x.abs() >= radius {
+        return 0.0
+    }
+    let sinc = if x == 0.0 { 1.0 } else { (std::f64::consts::PI * x).sin() / (std::f64::consts::PI * x) };
+    let t = x / radius;
+    let window = 0.42 + 0.5 * (std::f64::consts::PI * t).cos() + 0.08 * (2.0 * std::f64::consts::PI * t).cos();
+    sinc * window
+}
+
+/// Audio files used to create a single permutation of a sound tag.
+pub struct SoundPermutationSource {
+    /// Name of the permutation.
+    pub name: String,
+
+    /// Audio of the permutation.
+    pub audio: Audio
+}
+
+/// Audio files used to create a single pitch range of a sound tag.
+pub struct SoundPitchRangeSource {
+    /// Name of the pitch range.
+    pub name: String,
+
+    /// Permutations of the pitch range.
+    pub permutations: Vec<SoundPermutationSource>
+}
+
+/// Load the audio files in a directory as sound tag pitch ranges.
+///
+/// If the directory contains audio files, they are loaded as permutations of a single pitch range named `default`.
+/// Otherwise, each subdirectory is loaded as a pitch range. Files and directories are sorted by name.
+///
+/// Returns `Err` if an error occurred, if both audio files and subdirectories are present, or if no audio was found.
+pub fn load_sound_sources(data_dir: &Path) -> RinghopperResult<Vec<SoundPitchRangeSource>> {
+    let (files, directories) = read_sorted_directory(data_dir)?;
+
+    let pitch_ranges = if !files.is_empty() {
+        if !directories.is_empty() {
+            return Err(Error::Other(format!("{data_dir:?} contains both audio files and pitch range directories")))
+        }
+        vec![SoundPitchRangeSource { name: "default".to_owned(), permutations: load_permutations(&files)? }]
+    }
+    else {
+        let mut pitch_ranges = Vec::with_capacity(directories.len());
+        for directory in directories {
+            let (files, _) = read_sorted_directory(&directory)?;
+            if files.is_empty() {
+                return Err(Error::Other(format!("{directory:?} contains no audio files")))
+            }
+            pitch_ranges.push(SoundPitchRangeSource {
+                name: file_name_of(&directory),
+                permutations: load_permutations(&files)?
+            });
+        }
+        pitch_ranges
+    };
+
+    if pitch_ranges.is_empty() {
+        return Err(Error::Other(format!("{data_dir:?} contains no audio files")))
+    }
+
+    Ok(pitch_ranges)
+}
+
+/// Return the audio files and the subdirectories in a directory, sorted by name.
+fn read_sorted_directory(directory: &Path) -> RinghopperResult<(Vec<PathBuf>, Vec<PathBuf>)> {
+    let mut files = Vec::new();
+    let mut directories = Vec::new();
+
+    let entries = std::fs::read_dir(directory).map_err(|e| Error::FailedToReadFile(directory.to_path_buf(), e))?;
+    for entry in entries {
+        let path = entry.map_err(|e| Error::FailedToReadFile(directory.to_path_buf(), e))?.path();
+        if path.is_dir() {
+            directories.push(path);
+        }
+        else if get_audio_extension(&path).is_some() {
+            files.push(path);
+        }
+    }
+
+    files.sort();
+    directories.sort();
+    Ok((files, directories))
+}
+
+fn load_permutations(files: &[PathBuf]) -> RinghopperResult<Vec<SoundPermutationSource>> {
+    files.iter().map(|path| Ok(SoundPermutationSource {
+        name: path.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default(),
+        audio: load_audio_from_path(path)?
+    })).collect()
+}
+
+fn file_name_of(path: &Path) -> String {
+    path.file_name().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default()
+}
@@ -0,0 +1,372 @@
+use primitives::error::{Error, RinghopperResult};
+use crate::data::sound::Audio;
+
+#[cfg(test)]
+mod test;
+
+struct StreamInfo {
+    sample_rate: u32,
+    channel_count: u32,
+    bits_per_sample: u32,
+    total_samples: u64
+}
+
+/// Reads big endian bit sequences.
+struct BitReader<'a> {
+    data: &'a [u8],
+    position: usize
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, position: 0 }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.position >= self.data.len() * 8
+    }
+
+    fn read(&mut self, bits: u32) -> RinghopperResult<u64> {
+        debug_assert!(bits <= 64);
+        if self.position + bits as usize > self.data.len() * 8 {
+            return Err(Error::Other("unexpected end of FLAC data".to_owned()))
+        }
+
+        let mut value = 0u64;
+        for _ in 0..bits {
+            let byte = self.data[self.position / 8];
+            let bit = (byte >> (7 - self.position % 8)) & 1;
+            value = (value << 1) | bit as u64;
+            self.position += 1;
+        }
+        Ok(value)
+    }
+
+    fn read_signed(&mut self, bits: u32) -> RinghopperResult<i64> {
+        if bits == 0 {
+            return Ok(0)
+        }
+        let value = self.read(bits)?;
+        let shift = 64 - bits;
+        Ok(((value << shift) as i64) >> shift)
+    }
+
+    fn read_unary(&mut self) -> RinghopperResult<u32> {
+        let mut count = 0;
+        while self.read(1)? == 0 {
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    fn read_utf8_number(&mut self) -> RinghopperResult<u64> {
+        let first = self.read(8)?;
+        let leading_ones = (first as u8).leading_ones();
+        match leading_ones {
+            0 => return Ok(first),
+            1 | 8 => return Err(Error::Other("invalid FLAC frame number".to_owned())),
+            _ => ()
+        };
+
+        let mut value = first & (0x7F >> leading_ones);
+        for _ in 1..leading_ones {
+            value = (value << 6) | (self.read(8)? & 0x3F);
+        }
+        Ok(value)
+    }
+
+    fn align_to_byte(&mut self) {
+        self.position = self.position.div_ceil(8) * 8;
+    }
+}
+
+impl Audio {
+    /// Decode a FLAC file.
+    ///
+    /// Checksums are not verified.
+    ///
+    /// Returns `Err` if the file is invalid or unsupported.
+    pub fn from_flac(data: &[u8]) -> RinghopperResult<Audio> {
+        if data.len() < 4 || &data[0..4] != b"fLaC" {
+            return Err(Error::Other("not a FLAC file".to_owned()))
+        }
+
+        let mut offset = 4;
+        let mut stream_info = None;
+        loop {
+            let header = data.get(offset..offset + 4).ok_or_else(|| Error::Other("FLAC metadata is truncated".to_owned()))?;
+            let last = header[0] & 0x80 != 0;
+            let block_type = header[0] & 0x7F;
+            let length = u32::from_be_bytes([0, header[1], header[2], header[3]]) as usize;
+            let block = data.get(offset + 4..offset + 4 + length).ok_or_else(|| Error::Other("FLAC metadata is truncated".to_owned()))?;
+
+            if block_type == 0 {
+                stream_info = Some(read_stream_info(block)?);
+            }
+
+            offset += 4 + length;
+            if last {
+                break
+            }
+        }
+
+        let stream_info = stream_info.ok_or_else(|| Error::Other("FLAC file has no STREAMINFO block".to_owned()))?;
+        let channel_count = stream_info.channel_count as usize;
+        let scale = 1.0 / (1u64 << (stream_info.bits_per_sample - 1)) as f64;
+
+        let mut samples = Vec::with_capacity((stream_info.total_samples as usize).saturating_mul(channel_count));
+        let mut reader = BitReader::new(&data[offset..]);
+        let mut channels = vec![Vec::new(); channel_count];
+
+        while !reader.is_empty() {
+            if stream_info.total_samples != 0 && samples.len() as u64 >= stream_info.total_samples * channel_count as u64 {
+                break
+            }
+
+            read_frame(&mut reader, &stream_info, &mut channels)?;
+            for i in 0..channels[0].len() {
+                for channel in &channels {
+                    samples.push((channel[i] as f64 * scale) as f32);
+                }
+            }
+        }
+
+        if stream_info.total_samples != 0 {
+            samples.truncate((stream_info.total_samples as usize).saturating_mul(channel_count));
+        }
+
+        Ok(Audio {
+            sample_rate: stream_info.sample_rate,
+            channel_count: stream_info.channel_count,
+            samples
+        })
+    }
+}
+
+fn read_stream_info(block: &[u8]) -> RinghopperResult<StreamInfo> {
+    let mut reader = BitReader::new(block);
+    reader.read(16)?; // minimum block size
+    reader.read(16)?; // maximum block size
+    reader.read(24)?; // minimum frame size
+    reader.read(24)?; // maximum frame size
+    let sample_rate = reader.read(20)? as u32;
+    let channel_count = reader.read(3)? as u32 + 1;
+    let bits_per_sample = reader.read(5)? as u32 + 1;
+    let total_samples = reader.read(36)?;
+
+    if sample_rate == 0 {
+        return Err(Error::Other("FLAC file has a sample rate of 0 Hz".to_owned()))
+    }
+    if bits_per_sample < 4 {
+        return Err(Error::Other(format!("FLAC file has unsupported {bits_per_sample}-bit samples")))
+    }
+
+    Ok(StreamInfo { sample_rate, channel_count, bits_per_sample, total_samples })
+}
+
+fn read_frame(reader: &mut BitReader, stream_info: &StreamInfo, channels: &mut [Vec<i64>]) -> RinghopperResult<()> {
+    let sync = reader.read(15)?;
+    if sync != 0x7FFC {
+        return Err(Error::Other("FLAC frame has an invalid sync code".to_owned()))
+    }
+    reader.read(1)?; // blocking strategy
+
+    let block_size_code = reader.read(4)?;
+    let sample_rate_code = reader.read(4)?;
+    let channel_assignment = reader.read(4)?;
+    let sample_size_code = reader.read(3)?;
+    reader.read(1)?; // reserved
+    reader.read_utf8_number()?; // frame or sample number
+
+    let block_size = match block_size_code {
+        0 => return Err(Error::Other("FLAC frame has a reserved block size".to_owned())),
+        1 => 192,
+        2..=5 => 576 << (block_size_code - 2),
+        6 => reader.read(8)? as usize + 1,
+        7 => reader.read(16)? as usize + 1,
+        _ => 256 << (block_size_code - 8)
+    };
+
+    match sample_rate_code {
+        12 => { reader.read(8)?; },
+        13 | 14 => { reader.read(16)?; },
+        15 => return Err(Error::Other("FLAC frame has an invalid sample rate".to_owned())),
+        _ => ()
+    }
+
+    let bits_per_sample = match sample_size_code {
+        0 => stream_info.bits_per_sample,
+        1 => 8,
+        2 => 12,
+        4 => 16,
+        5 => 20,
+        6 => 24,
+        7 => 32,
+        _ => return Err(Error::Other("FLAC frame has a reserved sample size".to_owned()))
+    };
+
+    reader.read(8)?; // CRC-8
+
+    let channel_count = match channel_assignment {
+        0..=7 => channel_assignment as usize + 1,
+        8..=10 => 2,
+        _ => return Err(Error::Other("FLAC frame has a reserved channel assignment".to_owned()))
+    };
+    if channel_count != channels.len() {
+        return Err(Error::Other(format!("FLAC frame has {channel_count} channels, but the stream has {}", channels.len())))
+    }
+
+    for (index, channel) in channels.iter_mut().enumerate() {
+        // The side channel has an extra bit.
+        let side = matches!((channel_assignment, index), (8, 1) | (9, 0) | (10, 1));
+        read_subframe(reader, block_size, bits_per_sample + side as u32, channel)?;
+    }
+
+    if channels.len() == 2 {
+        let (left, right) = channels.split_at_mut(1);
+        for (a, b) in left[0].iter_mut().zip(right[0].iter_mut()) {
+            match channel_assignment {
+                8 => *b = *a - *b,
+                9 => *a += *b,
+                10 => {
+                    let mid = (*a << 1) | (*b & 1);
+                    let side = *b;
+                    *a = (mid + side) >> 1;
+                    *b = (mid - side) >> 1;
+                },
+                _ => ()
+            }
+        }
+    }
+
+    reader.align_to_byte();
+    reader.read(16)?; // CRC-16
+
+    Ok(())
+}
+
+fn read_subframe(reader: &mut BitReader, block_size: usize, bits_per_sample: u32, output: &mut Vec<i64>) -> RinghopperResult<()> {
+    output.clear();
+
+    if reader.read(1)? != 0 {
+        return Err(Error::Other("FLAC subframe has invalid padding".to_owned()))
+    }
+
+    let subframe_type = reader.read(6)?;
+    let wasted_bits = if reader.read(1)? == 1 {
+        reader.read_unary()? + 1
+    }
+    else {
+        0
+    };
+    let bits_per_sample = bits_per_sample.checked_sub(wasted_bits)
+        .ok_or_else(|| Error::Other("FLAC subframe has too many wasted bits".to_owned()))?;
+
+    match subframe_type {
+        0 => {
+            let value = reader.read_signed(bits_per_sample)?;
+            output.resize(block_size, value);
+        },
+        1 => {
+            for _ in 0..block_size {
+                output.push(reader.read_signed(bits_per_sample)?);
+            }
+        },
+        8..=12 => {
+            let order = (subframe_type - 8) as usize;
+            let coefficients: &[i64] = match order {
+                0 => &[],
+                1 => &[1],
+                2 => &[2, -1],
+                3 => &[3, -3, 1],
+                _ => &[4, -6, 4, -1]
+            };
+            for _ in 0..order {
+                output.push(reader.read_signed(bits_per_sample)?);
+            }
+            read_residual(reader, block_size, order, output)?;
+            predict(output, order, coefficients, 0);
+        },
+        32..=63 => {
+            let order = (subframe_type - 31) as usize;
+            for _ in 0..order {
+                output.push(reader.read_signed(bits_per_sample)?);
+            }
+
+            let precision = reader.read(4)? as u32 + 1;
+            if precision == 16 {
+                return Err(Error::Other("FLAC subframe has an invalid LPC precision".to_owned()))
+            }
+            let shift = reader.read_signed(5)?;
+            if shift < 0 {
+                return Err(Error::Other("FLAC subframe has a negative LPC shift".to_owned()))
+            }
+
+            let mut coefficients = Vec::with_capacity(order);
+            for _ in 0..order {
+                coefficients.push(reader.read_signed(precision)?);
+            }
+
+            read_residual(reader, block_size, order, output)?;
+            predict(output, order, &coefficients, shift as u32);
+        },
+        _ => return Err(Error::Other(format!("FLAC subframe has a reserved type {subframe_type}")))
+    }
+
+    if wasted_bits > 0 {
+        for sample in output.iter_mut() {
+            *sample <<= wasted_bits;
+        }
+    }
+
+    Ok(())
+}
+
+/// Read residuals, appending them to `output` after the warm-up samples.
+fn read_residual(reader: &mut BitReader, block_size: usize, order: usize, output: &mut Vec<i64>) -> RinghopperResult<()> {
+    let (parameter_bits, escape) = match reader.read(2)? {
+        0 => (4, 0xF),
+        1 => (5, 0x1F),
+        _ => return Err(Error::Other("FLAC residual has a reserved coding method".to_owned()))
+    };
+
+    let partition_order = reader.read(4)? as u32;
+    let partition_count = 1usize << partition_order;
+    let partition_size = block_size >> partition_order;
+    if partition_size * partition_count != block_size || partition_size < order {
+        return Err(Error::Other("FLAC residual has an invalid partition order".to_owned()))
+    }
+
+    for partition in 0..partition_count {
+        let count = if partition == 0 { partition_size - order } else { partition_size };
+        let parameter = reader.read(parameter_bits)? as u32;
+
+        if parameter == escape {
+            let bits = reader.read(5)? as u32;
+            for _ in 0..count {
+                output.push(reader.read_signed(bits)?);
+            }
+        }
+        else {
+            for _ in 0..count {
+                let quotient = reader.read_unary()? as u64;
+                let value = (quotient << parameter) | reader.read(parameter)?;
+                output.push((value >> 1) as i64 ^ -((value & 1) as i64));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Restore samples from residuals in place.
+fn predict(samples: &mut [i64], order: usize, coefficients: &[i64], shift: u32) {
+    for i in order..samples.len() {
+        let prediction: i64 = coefficients
+            .iter()
+            .enumerate()
+            .map(|(j, c)| c.wrapping_mul(samples[i - 1 - j]))
+            .fold(0i64, |a, b| a.wrapping_add(b));
+        samples[i] = samples[i].wrapping_add(prediction >> shift);
+    }
+}
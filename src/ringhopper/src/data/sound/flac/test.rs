@@ -0,0 +1,241 @@
+use super::*;
+
+/// Writes big endian bit sequences.
+#[derive(Default)]
+struct BitWriter {
+    data: Vec<u8>,
+    bits: usize
+}
+
+impl BitWriter {
+    fn write(&mut self, bits: u32, value: u64) {
+        for i in (0..bits).rev() {
+            if self.bits.is_multiple_of(8) {
+                self.data.push(0);
+            }
+            let bit = ((value >> i) & 1) as u8;
+            *self.data.last_mut().unwrap() |= bit << (7 - self.bits % 8);
+            self.bits += 1;
+        }
+    }
+
+    fn write_signed(&mut self, bits: u32, value: i64) {
+        self.write(bits, value as u64 & (u64::MAX >> (64 - bits)));
+    }
+
+    fn write_unary(&mut self, zeros: u32) {
+        self.write(zeros, 0);
+        self.write(1, 1);
+    }
+
+    fn write_rice(&mut self, parameter: u32, value: i64) {
+        let value = if value >= 0 { (value as u64) << 1 } else { ((-value as u64) << 1) - 1 };
+        self.write_unary((value >> parameter) as u32);
+        self.write(parameter, value & ((1 << parameter) - 1));
+    }
+
+    fn align_to_byte(&mut self) {
+        self.bits = self.data.len() * 8;
+    }
+
+    /// Write a frame header for 16-bit samples with an explicit block size.
+    fn write_frame_header(&mut self, frame_number: u8, block_size: usize, channel_assignment: u64) {
+        self.write(15, 0x7FFC);
+        self.write(1, 0); // fixed block size
+        self.write(4, 7); // 16-bit block size at the end of the header
+        self.write(4, 0); // sample rate from STREAMINFO
+        self.write(4, channel_assignment);
+        self.write(3, 0); // sample size from STREAMINFO
+        self.write(1, 0);
+        self.write(8, frame_number as u64);
+        self.write(16, block_size as u64 - 1);
+        self.write(8, 0); // CRC-8 (not verified)
+    }
+
+    /// Write a subframe header.
+    fn write_subframe_header(&mut self, subframe_type: u64, wasted_bits: u32) {
+        self.write(1, 0);
+        self.write(6, subframe_type);
+        if wasted_bits > 0 {
+            self.write(1, 1);
+            self.write_unary(wasted_bits - 1);
+        }
+        else {
+            self.write(1, 0);
+        }
+    }
+
+    fn write_verbatim(&mut self, bits_per_sample: u32, samples: &[i64]) {
+        self.write_subframe_header(1, 0);
+        for sample in samples {
+            self.write_signed(bits_per_sample, *sample);
+        }
+    }
+
+    fn end_frame(&mut self) {
+        self.align_to_byte();
+        self.write(16, 0); // CRC-16 (not verified)
+    }
+}
+
+/// Make a 16-bit 44.1 kHz FLAC file, writing the frames with `write_frames`.
+fn make_flac(channel_count: u64, total_samples: u64, write_frames: impl FnOnce(&mut BitWriter)) -> Vec<u8> {
+    let mut writer = BitWriter::default();
+    writer.data.extend_from_slice(b"fLaC");
+    writer.write(8, 0x80); // last metadata block, STREAMINFO
+    writer.write(24, 34);
+    writer.write(16, 16);
+    writer.write(16, 4096);
+    writer.write(24, 0);
+    writer.write(24, 0);
+    writer.write(20, 44100);
+    writer.write(3, channel_count - 1);
+    writer.write(5, 16 - 1);
+    writer.write(36, total_samples);
+    writer.data.extend_from_slice(&[0u8; 16]); // MD5 (not verified)
+    writer.align_to_byte();
+
+    write_frames(&mut writer);
+    writer.data
+}
+
+fn decode_pcm16(flac: &[u8]) -> Vec<i16> {
+    Audio::from_flac(flac).unwrap().to_pcm16()
+}
+
+#[test]
+fn decodes_stereo_decorrelation() {
+    let left: [i64; 4] = [100, -200, 300, 32767];
+    let right: [i64; 4] = [50, -50, 0, -32768];
+    let side: Vec<i64> = left.iter().zip(right).map(|(l, r)| l - r).collect();
+    let mid: Vec<i64> = left.iter().zip(right).map(|(l, r)| (l + r) >> 1).collect();
+    let expected: Vec<i16> = left.iter().zip(right).flat_map(|(l, r)| [*l as i16, r as i16]).collect();
+
+    // Independent, left/side, right/side, and mid/side; the side channel has an extra bit
+    type Subframe<'a> = (u32, &'a [i64]);
+    let assignments: [(u64, Subframe, Subframe); 4] = [
+        (1, (16, &left), (16, &right)),
+        (8, (16, &left), (17, &side)),
+        (9, (17, &side), (16, &right)),
+        (10, (16, &mid), (17, &side))
+    ];
+
+    for (channel_assignment, (first_bits, first), (second_bits, second)) in assignments {
+        let flac = make_flac(2, 4, |w| {
+            w.write_frame_header(0, 4, channel_assignment);
+            w.write_verbatim(first_bits, first);
+            w.write_verbatim(second_bits, second);
+            w.end_frame();
+        });
+
+        let audio = Audio::from_flac(&flac).unwrap();
+        assert_eq!(44100, audio.sample_rate);
+        assert_eq!(2, audio.channel_count);
+        assert_eq!(expected, audio.to_pcm16(), "channel assignment {channel_assignment}");
+    }
+}
+
+#[test]
+fn decodes_lpc_with_escaped_partition() {
+    let expected: [i16; 8] = [10, 20, 35, 45, 50, 52, 40, -10];
+
+    let flac = make_flac(1, 8, |w| {
+        w.write_frame_header(0, 8, 0);
+
+        // Order 2, predicting (4 * s[i-1] - 2 * s[i-2]) >> 1
+        w.write_subframe_header(32 + 1, 0);
+        w.write_signed(16, 10);
+        w.write_signed(16, 20);
+        w.write(4, 15 - 1); // precision
+        w.write_signed(5, 1); // shift
+        w.write_signed(15, 4);
+        w.write_signed(15, -2);
+
+        // Two partitions; the first excludes the warm-up samples
+        w.write(2, 0);
+        w.write(4, 1);
+        w.write(4, 3);
+        for residual in [5, -5] {
+            w.write_rice(3, residual);
+        }
+
+        // Escaped with 7-bit residuals
+        w.write(4, 0xF);
+        w.write(5, 7);
+        for residual in [-5, -3, -14, -38] {
+            w.write_signed(7, residual);
+        }
+
+        w.end_frame();
+    });
+
+    assert_eq!(expected.to_vec(), decode_pcm16(&flac));
+}
+
+#[test]
+fn decodes_fixed_with_5_bit_escaped_partition() {
+    let flac = make_flac(1, 4, |w| {
+        w.write_frame_header(0, 4, 0);
+
+        // Order 2 fixed predictor
+        w.write_subframe_header(8 + 2, 0);
+        w.write_signed(16, 1000);
+        w.write_signed(16, 1010);
+
+        // 5-bit Rice parameters, where 0x1F is the escape code
+        w.write(2, 1);
+        w.write(4, 0);
+        w.write(5, 0x1F);
+        w.write(5, 4);
+        w.write_signed(4, 0);
+        w.write_signed(4, -5);
+
+        w.end_frame();
+    });
+
+    assert_eq!(vec![1000, 1010, 1020, 1025], decode_pcm16(&flac));
+}
+
+#[test]
+fn decodes_wasted_bits() {
+    let flac = make_flac(1, 8, |w| {
+        // Verbatim samples stored without their 3 low (zero) bits
+        w.write_frame_header(0, 4, 0);
+        w.write_subframe_header(1, 3);
+        for sample in [1, -2, 100, -4096] {
+            w.write_signed(13, sample);
+        }
+        w.end_frame();
+
+        // Constant with wasted bits
+        w.write_frame_header(1, 4, 0);
+        w.write_subframe_header(0, 3);
+        w.write_signed(13, 3);
+        w.end_frame();
+    });
+
+    assert_eq!(vec![8, -16, 800, -32768, 24, 24, 24, 24], decode_pcm16(&flac));
+}
+
+#[test]
+fn rejects_invalid_flac() {
+    assert!(Audio::from_flac(b"RIFF").is_err());
+
+    // Too many wasted bits for 16-bit samples
+    let flac = make_flac(1, 4, |w| {
+        w.write_frame_header(0, 4, 0);
+        w.write_subframe_header(0, 17);
+        w.end_frame();
+    });
+    assert!(Audio::from_flac(&flac).is_err());
+
+    // Partition order too high for the block size
+    let flac = make_flac(1, 4, |w| {
+        w.write_frame_header(0, 4, 0);
+        w.write_subframe_header(8, 0);
+        w.write(2, 0);
+        w.write(4, 3);
+        w.end_frame();
+    });
+    assert!(Audio::from_flac(&flac).is_err());
+}
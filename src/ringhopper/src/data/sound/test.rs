@@ -0,0 +1,49 @@
+use super::*;
+
+fn make_tone(frequency: f64, sample_rate: u32, frame_count: usize) -> Audio {
+    let samples = (0..frame_count)
+        .map(|i| (0.5 * (2.0 * std::f64::consts::PI * frequency * i as f64 / sample_rate as f64).sin()) as f32)
+        .collect();
+    Audio { sample_rate, channel_count: 1, samples }
+}
+
+/// RMS of the samples, ignoring the edges where the input is clamped.
+fn rms(audio: &Audio) -> f64 {
+    let margin = audio.samples.len() / 8;
+    let samples = &audio.samples[margin..audio.samples.len() - margin];
+    (samples.iter().map(|s| (*s as f64).powi(2)).sum::<f64>() / samples.len() as f64).sqrt()
+}
+
+#[test]
+fn resample_keeps_frequencies_below_nyquist() {
+    let resampled = make_tone(1000.0, 44100, 4410).resample(22050);
+    assert_eq!(resampled.sample_rate, 22050);
+    assert_eq!(resampled.frame_count(), 2205);
+
+    let expected = make_tone(1000.0, 22050, 2205);
+    let margin = 2205 / 8;
+    for (a, b) in resampled.samples.iter().zip(expected.samples.iter()).skip(margin).take(2205 - margin * 2) {
+        assert!((a - b).abs() < 0.01, "{a} != {b}");
+    }
+}
+
+#[test]
+fn resample_filters_frequencies_above_nyquist() {
+    // 15 kHz cannot be represented at 22050 Hz and would otherwise alias to 7050 Hz
+    let tone = make_tone(15000.0, 44100, 4410);
+    assert!(rms(&tone) > 0.3);
+
+    let resampled = tone.resample(22050);
+    assert!(rms(&resampled) < 0.01, "{}", rms(&resampled));
+}
+
+#[test]
+fn resample_keeps_channels_separate() {
+    let audio = Audio { sample_rate: 22050, channel_count: 2, samples: [0.25, -0.5].repeat(1000) };
+    let resampled = audio.resample(44100);
+    assert_eq!(resampled.frame_count(), 2000);
+    for frame in resampled.samples.chunks(2) {
+        assert!((frame[0] - 0.25).abs() < 0.001);
+        assert!((frame[1] + 0.5).abs() < 0.001);
+    }
+}
@@ -0,0 +1,132 @@
+use primitives::error::{Error, OverflowCheck, RinghopperResult};
+use crate::data::sound::Audio;
+
+const WAVE_FORMAT_PCM: u16 = 0x0001;
+const WAVE_FORMAT_IEEE_FLOAT: u16 = 0x0003;
+const WAVE_FORMAT_EXTENSIBLE: u16 = 0xFFFE;
+
+struct WavFormat {
+    format: u16,
+    channel_count: u16,
+    sample_rate: u32,
+    bits_per_sample: u16
+}
+
+impl Audio {
+    /// Decode a RIFF WAVE file.
+    ///
+    /// Integer PCM (8, 16, 24, or 32-bit) and floating point (32 or 64-bit) samples are supported.
+    ///
+    /// Returns `Err` if the file is invalid or unsupported.
+    pub fn from_wav(data: &[u8]) -> RinghopperResult<Audio> {
+        if data.len() < 12 || &data[0..4] != b"RIFF" || &data[8..12] != b"WAVE" {
+            return Err(Error::Other("not a RIFF WAVE file".to_owned()))
+        }
+
+        let mut format = None;
+        let mut samples = None;
+        let mut offset = 12usize;
+
+        while offset.add_overflow_checked(8)? <= data.len() {
+            let id = &data[offset..offset + 4];
+            let size = u32::from_le_bytes(data[offset + 4..offset + 8].try_into().unwrap()) as usize;
+            let start = offset + 8;
+            let end = start.add_overflow_checked(size)?;
+            let Some(chunk) = data.get(start..end) else {
+                return Err(Error::Other(format!("WAV chunk {:?} is out of bounds", String::from_utf8_lossy(id))))
+            };
+
+            match id {
+                b"fmt " => format = Some(read_format(chunk)?),
+                b"data" => samples = Some(chunk),
+                _ => ()
+            }
+
+            // Chunks are padded to 16-bit boundaries
+            offset = end + (size & 1);
+        }
+
+        let format = format.ok_or_else(|| Error::Other("WAV file has no fmt chunk".to_owned()))?;
+        let samples = samples.ok_or_else(|| Error::Other("WAV file has no data chunk".to_owned()))?;
+
+        if format.channel_count == 0 {
+            return Err(Error::Other("WAV file has no channels".to_owned()))
+        }
+        if format.sample_rate == 0 {
+            return Err(Error::Other("WAV file has a sample rate of 0 Hz".to_owned()))
+        }
+
+        let bytes_per_sample = (format.bits_per_sample as usize).div_ceil(8);
+        let decode: fn(&[u8]) -> f32 = match (format.format, bytes_per_sample) {
+            (WAVE_FORMAT_PCM, 1) => |b| (b[0] as f32 - 128.0) / 128.0,
+            (WAVE_FORMAT_PCM, 2) => |b| i16::from_le_bytes([b[0], b[1]]) as f32 / 32768.0,
+            (WAVE_FORMAT_PCM, 3) => |b| (i32::from_le_bytes([0, b[0], b[1], b[2]]) >> 8) as f32 / 8388608.0,
+            (WAVE_FORMAT_PCM, 4) => |b| (i32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64 / 2147483648.0) as f32,
+            (WAVE_FORMAT_IEEE_FLOAT, 4) => |b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]),
+            (WAVE_FORMAT_IEEE_FLOAT, 8) => |b| f64::from_le_bytes(b.try_into().unwrap()) as f32,
+            (f, _) => return Err(Error::Other(format!("unsupported WAV format 0x{f:04X} with {}-bit samples", format.bits_per_sample)))
+        };
+
+        let frame_size = bytes_per_sample * format.channel_count as usize;
+        let samples = samples
+            .chunks_exact(frame_size)
+            .flat_map(|f| f.chunks_exact(bytes_per_sample))
+            .map(|s| decode(s).clamp(-1.0, 1.0))
+            .collect();
+
+        Ok(Audio {
+            sample_rate: format.sample_rate,
+            channel_count: format.channel_count as u32,
+            samples
+        })
+    }
+
+    /// Encode the audio as a 16-bit PCM RIFF WAVE file.
+    pub fn to_wav(&self) -> Vec<u8> {
+        let pcm = self.to_pcm16();
+        let data_size = (pcm.len() * 2) as u32;
+        let block_align = (self.channel_count * 2) as u16;
+
+        let mut output = Vec::with_capacity(44 + pcm.len() * 2);
+        output.extend_from_slice(b"RIFF");
+        output.extend_from_slice(&(36 + data_size).to_le_bytes());
+        output.extend_from_slice(b"WAVEfmt ");
+        output.extend_from_slice(&16u32.to_le_bytes());
+        output.extend_from_slice(&WAVE_FORMAT_PCM.to_le_bytes());
+        output.extend_from_slice(&(self.channel_count as u16).to_le_bytes());
+        output.extend_from_slice(&self.sample_rate.to_le_bytes());
+        output.extend_from_slice(&(self.sample_rate * block_align as u32).to_le_bytes());
+        output.extend_from_slice(&block_align.to_le_bytes());
+        output.extend_from_slice(&16u16.to_le_bytes());
+        output.extend_from_slice(b"data");
+        output.extend_from_slice(&data_size.to_le_bytes());
+        for sample in pcm {
+            output.extend_from_slice(&sample.to_le_bytes());
+        }
+        output
+    }
+}
+
+fn read_format(chunk: &[u8]) -> RinghopperResult<WavFormat> {
+    if chunk.len() < 16 {
+        return Err(Error::Other("WAV fmt chunk is too small".to_owned()))
+    }
+
+    let read_u16 = |at: usize| u16::from_le_bytes([chunk[at], chunk[at + 1]]);
+    let mut format = read_u16(0);
+
+    // The actual format is the first two bytes of the subformat GUID.
+    if format == WAVE_FORMAT_EXTENSIBLE {
+        if chunk.len() < 26 {
+            return Err(Error::Other("WAV fmt chunk is too small for WAVE_FORMAT_EXTENSIBLE".to_owned()))
+        }
+        format = read_u16(24);
+    }
+
+    Ok(WavFormat {
+        format,
+        channel_count: read_u16(2),
+        sample_rate: u32::from_le_bytes(chunk[4..8].try_into().unwrap()),
+        bits_per_sample: read_u16(14)
+    })
+}
@@ -1,26 +1,33 @@
 use std::fs::File;
-use std::io::Read;
+use std::io::{Read, Write};
+use std::borrow::Cow;
 use std::ops::Range;
 use std::path::Path;
 use std::sync::Arc;
 
-use flate2::FlushDecompress;
+use flate2::{Compression, FlushDecompress};
+use flate2::write::ZlibEncoder;
 
 use definitions::{read_any_tag_from_map, Scenario, ScenarioType};
 use primitives::engine::{Engine, EngineCacheParser, EngineCompressionType};
 use primitives::error::{Error, RinghopperResult};
-use primitives::map::Map;
-use primitives::primitive::{TagGroup, TagPath};
+use primitives::byteorder::LittleEndian;
+use primitives::map::{Map, ResourceMapType};
+use primitives::primitive::{calculate_padding_for_alignment, TagGroup, TagPath};
 use primitives::tag::{ParseStrictness, PrimaryTagStructDyn};
 
 use crate::map::extract::*;
 use crate::map::gearbox::GearboxCacheFile;
 use crate::map::header::ParsedCacheFileHeader;
+use crate::map::resource::ResourceMap;
 use crate::map::xbox::XboxCacheFile;
 use crate::tag::object::downcast_base_object_mut;
-use crate::tag::tree::{TagFilter, TagTree, TagTreeItem, TreeType};
+use crate::tag::tree::{TagDirectoryIndex, TagFilter, TagTree, TagTreeItem, TagTreeItemType, TreeType};
 
 mod extract;
+mod prepare;
+pub mod build;
+pub mod map_diff;
 pub mod resource;
 
 pub mod header;
@@ -28,6 +35,9 @@ pub mod gearbox;
 pub mod xbox;
 mod util;
 
+#[cfg(test)]
+mod test;
+
 type SizeRange = Range<usize>;
 
 #[derive(Clone)]
@@ -78,8 +88,8 @@ macro_rules! tag_tree_impl {
             self.extract_tag(path)
         }
 
-        fn files_in_path(&self, _path: &str) -> Option<Vec<TagTreeItem>> {
-            todo!("files_in_path not yet implemented for MapTagTree")
+        fn files_in_path(&self, path: &str) -> Option<Vec<TagTreeItem>> {
+            self.get_directory_index().files_in_path(path, self)
         }
 
         fn write_tag(&mut self, _path: &TagPath, _tag: &dyn PrimaryTagStructDyn) -> RinghopperResult<bool> {
@@ -95,8 +105,7 @@ macro_rules! tag_tree_impl {
         }
 
         fn root(&self) -> TagTreeItem {
-            // TagTreeItem::new(TagTreeItemType::Directory, Cow::default(), None, self)
-            todo!()
+            TagTreeItem::new(TagTreeItemType::Directory, Cow::default(), None, self)
         }
 
         fn get_all_tags_with_filter(&self, filter: Option<&TagFilter>) -> Vec<TagPath> {
@@ -118,6 +127,14 @@ macro_rules! tag_tree_impl {
 pub trait MapTagTree: Map {
     /// Get the scenario type for the map.
     fn get_scenario_type(&self) -> ScenarioType;
+
+    /// Get the index of virtual directories for browsing the map's tags.
+    fn get_directory_index(&self) -> &TagDirectoryIndex;
+
+    /// Get the resource map of the given type loaded with the map, if any.
+    fn get_resource_map(&self, _resource_type: ResourceMapType) -> Option<&ResourceMap> {
+        None
+    }
 }
 impl<M: MapTagTree> TagTree for M {
     tag_tree_impl!();
@@ -147,7 +164,12 @@ macro_rules! make_map_load_fn {
             file.read_to_end(&mut map).map_err(io)?;
             drop(file);
 
-            let map = decompress_map_data(map, &header, engine)?;
+            let map = if is_map_data_compressed(&map, &header, engine) {
+                decompress_map_data(map, &header, engine)?
+            }
+            else {
+                map
+            };
 
             match engine.cache_parser {
                 EngineCacheParser::PC => {
@@ -180,7 +202,27 @@ macro_rules! make_map_load_fn {
 make_map_load_fn!(load_map_from_filesystem, MapTagTree, "Load the map from the filesystem as a map.");
 make_map_load_fn!(load_map_from_filesystem_as_tag_tree, TagTree, "Load the map from the filesystem as a tag tree.");
 
-fn decompress_map_data(data: Vec<u8>, header: &ParsedCacheFileHeader, engine: &Engine) -> RinghopperResult<Vec<u8>> {
+/// Return `true` if the map data is compressed.
+///
+/// Compressed maps are shorter than the decompressed size in the header and start with a zlib stream after the header.
+/// Maps for engines that do not use compression are never compressed.
+pub fn is_map_data_compressed(data: &[u8], header: &ParsedCacheFileHeader, engine: &Engine) -> bool {
+    match engine.compression_type {
+        EngineCompressionType::Uncompressed => false,
+        EngineCompressionType::Deflate => {
+            let Some(&[cmf, flg]) = data.get(CACHE_FILE_HEADER_LEN..CACHE_FILE_HEADER_LEN + 2) else {
+                return false
+            };
+            data.len() != header.decompressed_size && cmf & 0x0F == 8 && (cmf as u16 * 256 + flg as u16) % 31 == 0
+        }
+    }
+}
+
+/// Decompress the map data if the engine uses compression.
+///
+/// The header is updated to clear the compression padding. If the engine does not use compression, the data is returned
+/// as-is.
+pub fn decompress_map_data(data: Vec<u8>, header: &ParsedCacheFileHeader, engine: &Engine) -> RinghopperResult<Vec<u8>> {
     if engine.compression_type == EngineCompressionType::Uncompressed {
         return Ok(data)
     }
@@ -205,7 +247,50 @@ fn decompress_map_data(data: Vec<u8>, header: &ParsedCacheFileHeader, engine: &E
             decompressor
                 .decompress(compressed_data, &mut result[compressed_start..], FlushDecompress::Finish)
                 .map_err(|e| Error::MapParseFailure(format!("decompression failed: flate2 error: {e}")))?;
-            Ok(result)
         }
     }
+
+    let mut header = *header;
+    header.compression_padding = 0;
+    result[..compressed_start].copy_from_slice(&header.as_bytes::<LittleEndian>());
+
+    Ok(result)
+}
+
+/// Alignment of compressed cache files.
+const COMPRESSED_MAP_ALIGNMENT: usize = 0x800;
+
+/// Compress the map data if the engine uses compression.
+///
+/// This is the inverse of [`decompress_map_data`]. The header is updated with the decompressed size and the padding
+/// needed to align the compressed file. If the engine does not use compression, the data is returned as-is.
+pub fn compress_map_data(data: Vec<u8>, engine: &Engine) -> RinghopperResult<Vec<u8>> {
+    if engine.compression_type == EngineCompressionType::Uncompressed {
+        return Ok(data)
+    }
+
+    let mut header = ParsedCacheFileHeader::read_from_map_data(&data)?;
+    header.decompressed_size = data.len();
+
+    let mut result = Vec::with_capacity(data.len());
+    result.extend_from_slice(&[0u8; CACHE_FILE_HEADER_LEN]);
+
+    match engine.compression_type {
+        EngineCompressionType::Uncompressed => unreachable!(),
+        EngineCompressionType::Deflate => {
+            let mut compressor = ZlibEncoder::new(result, Compression::best());
+            compressor
+                .write_all(&data[CACHE_FILE_HEADER_LEN..])
+                .map_err(|e| Error::Other(format!("compression failed: flate2 error: {e}")))?;
+            result = compressor
+                .finish()
+                .map_err(|e| Error::Other(format!("compression failed: flate2 error: {e}")))?;
+        }
+    }
+
+    header.compression_padding = calculate_padding_for_alignment(result.len(), COMPRESSED_MAP_ALIGNMENT);
+    result.resize(result.len() + header.compression_padding, 0);
+    result[..CACHE_FILE_HEADER_LEN].copy_from_slice(&header.as_bytes::<LittleEndian>());
+
+    Ok(result)
 }
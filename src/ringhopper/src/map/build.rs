@@ -0,0 +1,366 @@
+//! Functionality for building cache files.
+
+use std::collections::HashMap;
+use definitions::{CacheFileTag, CacheFileTagDataHeader, CacheFileTagDataHeaderExternalModels, CacheFileTagDataHeaderInternalModels, DetailObjectCollection, Scenario, ScenarioStructureBSPCompiledHeader, ScenarioType};
+use primitives::byteorder::LittleEndian;
+use primitives::crc32::CRC32;
+use primitives::engine::Engine;
+use primitives::error::{Error, OverflowCheck, RinghopperResult};
+use primitives::map::{MapWriteContext, MapWriteTag};
+use primitives::parse::{reserve_map_data, SimpleTagData, TagData};
+use primitives::primitive::{Address, ID, IDType, String32, TagGroup, TagPath};
+use primitives::tag::PrimaryTagStructDyn;
+use crate::map::{compress_map_data, CACHE_FILE_HEADER_LEN};
+use crate::map::header::ParsedCacheFileHeader;
+use crate::map::prepare::*;
+use crate::tag::dependency::recursively_get_dependencies_for_map;
+use crate::tag::object::downcast_base_object_mut;
+use crate::tag::tree::TagTree;
+
+#[cfg(test)]
+mod test;
+
+/// Options for building a cache file.
+#[derive(Clone, Default)]
+pub struct CacheFileBuildOptions {
+    /// Name to put in the header.
+    ///
+    /// If `None`, the base name of the scenario is used.
+    pub name: Option<String>,
+
+    /// Build string to put in the header.
+    ///
+    /// This is ignored if the engine enforces a build string.
+    pub build: Option<String>
+}
+
+/// Result of building a cache file.
+pub struct BuiltCacheFile {
+    /// Data of the cache file.
+    ///
+    /// This is compressed if the engine uses compression.
+    pub data: Vec<u8>,
+
+    /// Number of tags in the cache file.
+    pub tag_count: usize,
+
+    /// Amount of tag space used, including the largest BSP.
+    pub used_tag_space: usize,
+
+    /// CRC32 of the cache file.
+    pub crc32: u32
+}
+
+struct BuiltBSP {
+    path: TagPath,
+    data: Vec<u8>
+}
+
+/// Build a cache file for the given engine.
+///
+/// Returns an error if a tag fails to be built or the map exceeds any of the engine's limits.
+pub fn build_cache_file<T: TagTree>(
+    tag_tree: &T,
+    scenario: &TagPath,
+    engine: &'static Engine,
+    options: &CacheFileBuildOptions
+) -> RinghopperResult<BuiltCacheFile> {
+    if !engine.build_target {
+        return Err(Error::Other(format!("engine `{}` is not a build target", engine.name)))
+    }
+    if engine.external_bsps {
+        return Err(Error::Other(format!("building cache files with external BSP vertices for `{}` is not supported", engine.name)))
+    }
+    if scenario.group() != TagGroup::Scenario {
+        return Err(Error::Other(format!("`{scenario}` is not a scenario tag")))
+    }
+
+    // Scenario tag always goes first
+    let mut all_tags: Vec<TagPath> = recursively_get_dependencies_for_map(scenario, tag_tree, engine)?
+        .into_iter()
+        .filter(|t| t != scenario)
+        .collect();
+    all_tags.sort();
+    all_tags.insert(0, scenario.to_owned());
+
+    let tag_count = all_tags.len();
+    if tag_count > u16::MAX as usize {
+        return Err(Error::Other(format!("maximum tag count exceeded ({tag_count} > {})", u16::MAX)))
+    }
+
+    let mut scenario_tag: Scenario = tag_tree
+        .open_tag_copy(scenario)?
+        .as_any()
+        .downcast_ref::<Scenario>()
+        .unwrap()
+        .clone();
+    let scenario_type = scenario_tag._type;
+
+    let base_address = engine.base_memory_address.address as usize;
+    let max_tag_space = engine.max_tag_space as usize;
+
+    // Layout the tag data header, tag array, and tag paths.
+    let header_size = if engine.external_models {
+        CacheFileTagDataHeaderExternalModels::simple_size()
+    }
+    else {
+        CacheFileTagDataHeaderInternalModels::simple_size()
+    };
+    let tag_array_offset = header_size;
+    let mut tag_data = vec![0u8; tag_array_offset.add_overflow_checked(CacheFileTag::simple_size().mul_overflow_checked(tag_count)?)?];
+
+    let mut tags: HashMap<TagPath, MapWriteTag> = HashMap::with_capacity(tag_count);
+    for (index, path) in all_tags.iter().enumerate() {
+        let path_offset = tag_data.len();
+        tag_data.extend_from_slice(path.path().as_bytes());
+        tag_data.push(0);
+        tags.insert(path.to_owned(), MapWriteTag {
+            id: ID::new(Some(index as u16), IDType::Tag.salt()),
+            path_address: address_for_offset(base_address, path_offset)?
+        });
+    }
+
+    // Get the global z offsets of the detail object collections needed for BSPs
+    let mut global_z_offset = [0.0f32; 32];
+    for (index, palette) in scenario_tag.detail_object_collection_palette.items.iter().enumerate().take(global_z_offset.len()) {
+        let Some(path) = palette.reference.path() else { continue };
+        let tag = tag_tree.open_tag_copy(path)?;
+        let collection: &DetailObjectCollection = tag.as_any().downcast_ref().unwrap();
+        global_z_offset[index] = collection.global_z_offset * 0.125;
+    }
+
+    // BSPs are compiled first since the scenario needs their offsets.
+    let mut file_data = Vec::new();
+    let mut model_data = ModelData::default();
+    let mut bsps: Vec<BuiltBSP> = Vec::with_capacity(scenario_tag.structure_bsps.items.len());
+    for structure_bsp in &scenario_tag.structure_bsps.items {
+        let Some(path) = structure_bsp.structure_bsp.path() else {
+            return Err(Error::InvalidTagData(format!("`{scenario}` has a null BSP reference")))
+        };
+        if bsps.iter().any(|b| &b.path == path) {
+            return Err(Error::InvalidTagData(format!("`{scenario}` references `{path}` more than once")))
+        }
+
+        let mut bsp = tag_tree.open_tag_copy(path)?;
+        prepare_scenario_structure_bsp_tag(bsp.as_any_mut().downcast_mut().unwrap(), &global_z_offset, engine.compressed_models)?;
+
+        // Compile once to get the size, then again once we know where it will be loaded.
+        let mut dummy_file_data = Vec::new();
+        let size = compile_bsp(bsp.as_ref(), engine, 0, &tags, &mut dummy_file_data, 0)?.len();
+        let bsp_base_address = base_address
+            .add_overflow_checked(max_tag_space)?
+            .checked_sub(size)
+            .ok_or_else(|| Error::Other(format!("`{path}` is larger than the maximum tag space ({size} > {max_tag_space})")))?;
+
+        let file_data_offset = CACHE_FILE_HEADER_LEN.add_overflow_checked(bsps.iter().map(|b| b.data.len()).sum())?;
+        let mut bsp_file_data = Vec::new();
+        let mut data = compile_bsp(bsp.as_ref(), engine, bsp_base_address, &tags, &mut bsp_file_data, file_data_offset)?;
+        debug_assert_eq!(data.len(), size);
+
+        if !bsp_file_data.is_empty() {
+            return Err(Error::InvalidTagData(format!("`{path}` has data that cannot be stored in a BSP")))
+        }
+
+        data.shrink_to_fit();
+        bsps.push(BuiltBSP { path: path.to_owned(), data });
+    }
+
+    let mut offset = CACHE_FILE_HEADER_LEN;
+    for (structure_bsp, built) in scenario_tag.structure_bsps.items.iter_mut().zip(bsps.iter()) {
+        let size = built.data.len();
+        structure_bsp.bsp_start = u32::try_from(offset).map_err(|_| Error::ArrayLimitExceeded)?;
+        structure_bsp.bsp_size = u32::try_from(size).map_err(|_| Error::ArrayLimitExceeded)?;
+        structure_bsp.bsp_address = u32::try_from(base_address + max_tag_space - size).map_err(|_| Error::ArrayLimitExceeded)?;
+        offset = offset.add_overflow_checked(size)?;
+    }
+    let file_data_offset = offset;
+
+    // Now compile every other tag.
+    let mut cached_tags: Vec<CacheFileTag> = Vec::with_capacity(tag_count);
+    for path in &all_tags {
+        let group = path.group();
+        let [tag_group, secondary_group, tertiary_group] = group.full_subgroup_tree();
+        let mut cached_tag = CacheFileTag {
+            tag_group,
+            secondary_group,
+            tertiary_group,
+            id: tags[path].id,
+            path: tags[path].path_address,
+            ..Default::default()
+        };
+
+        if group != TagGroup::ScenarioStructureBSP {
+            let mut context = MapWriteContext {
+                engine,
+                base_address,
+                tags: &tags,
+                file_data: &mut file_data,
+                file_data_offset
+            };
+
+            let tag_offset = if path == scenario {
+                prepare_scenario_tag(&mut scenario_tag, &mut context)?;
+                scenario_tag.append_to_map_data(&mut tag_data, &mut context)?
+            }
+            else {
+                let mut tag = tag_tree.open_tag_copy(path)?;
+                prepare_tag(tag.as_mut(), &mut model_data, &mut context)?;
+                tag.append_to_map_data(&mut tag_data, &mut context)?
+            };
+
+            cached_tag.data = address_for_offset(base_address, tag_offset)?;
+        }
+
+        cached_tags.push(cached_tag);
+    }
+
+    // Write the tag array and header
+    let cache_file_tag_size = CacheFileTag::simple_size();
+    for (index, cached_tag) in cached_tags.iter().enumerate() {
+        let at = tag_array_offset + index * cache_file_tag_size;
+        cached_tag.write::<LittleEndian>(&mut tag_data, at, at + cache_file_tag_size)?;
+    }
+
+    let cache_file_tag_data_header = CacheFileTagDataHeader {
+        tag_array_address: address_for_offset(base_address, tag_array_offset)?,
+        scenario_tag: tags[scenario].id,
+        tag_count: tag_count as u32,
+        ..Default::default()
+    };
+
+    let model_data_file_offset = file_data_offset.add_overflow_checked(file_data.len())?;
+    let model_data_size = model_data.vertices.len().add_overflow_checked(model_data.triangles.len())?;
+    if engine.external_models {
+        CacheFileTagDataHeaderExternalModels {
+            cache_file_tag_data_header,
+            model_data_file_offset: u32::try_from(model_data_file_offset).map_err(|_| Error::ArrayLimitExceeded)?,
+            model_data_size: u32::try_from(model_data_size).map_err(|_| Error::ArrayLimitExceeded)?,
+            model_triangle_offset: u32::try_from(model_data.vertices.len()).map_err(|_| Error::ArrayLimitExceeded)?,
+            ..Default::default()
+        }.write::<LittleEndian>(&mut tag_data, 0, header_size)?;
+    }
+    else {
+        CacheFileTagDataHeaderInternalModels {
+            cache_file_tag_data_header,
+            ..Default::default()
+        }.write::<LittleEndian>(&mut tag_data, 0, header_size)?;
+    }
+
+    // Check limits
+    let largest_bsp = bsps.iter().map(|b| b.data.len()).max().unwrap_or(0);
+    let used_tag_space = tag_data.len().add_overflow_checked(largest_bsp)?;
+    if used_tag_space > max_tag_space {
+        return Err(Error::Other(format!("maximum tag space exceeded ({used_tag_space} > {max_tag_space})")))
+    }
+
+    let tag_data_offset = model_data_file_offset.add_overflow_checked(model_data_size)?;
+    let file_size = tag_data_offset.add_overflow_checked(tag_data.len())?;
+    let max_file_size = match scenario_type {
+        ScenarioType::Singleplayer => engine.max_cache_file_size.singleplayer,
+        ScenarioType::Multiplayer => engine.max_cache_file_size.multiplayer,
+        ScenarioType::UserInterface => engine.max_cache_file_size.user_interface
+    } as usize;
+    if file_size > max_file_size {
+        return Err(Error::Other(format!("maximum cache file size exceeded ({file_size} > {max_file_size})")))
+    }
+
+    // Calculate the CRC32 in the same order as the engine
+    let mut hasher = CRC32::new();
+    for bsp in &bsps {
+        hasher.update(&bsp.data);
+    }
+    if engine.external_models {
+        hasher.update(&model_data.vertices);
+        hasher.update(&model_data.triangles);
+    }
+    hasher.update(&tag_data);
+    let crc32 = hasher.crc();
+
+    let name = options.name.as_deref().unwrap_or(scenario.base_name());
+    let header = ParsedCacheFileHeader {
+        name: String32::from_str(name)?,
+        build: String32::from_str(options.build.as_deref().unwrap_or(""))?,
+        cache_version: engine.cache_file_version,
+        tag_data_offset,
+        tag_data_size: tag_data.len(),
+        decompressed_size: file_size,
+        compression_padding: 0,
+        map_type: scenario_type,
+        crc32
+    }.into_header_for_engine(engine);
+
+    let mut data = Vec::with_capacity(file_size);
+    data.extend_from_slice(&header.as_bytes::<LittleEndian>());
+    for bsp in &bsps {
+        data.extend_from_slice(&bsp.data);
+    }
+    data.extend_from_slice(&file_data);
+    data.extend_from_slice(&model_data.vertices);
+    data.extend_from_slice(&model_data.triangles);
+    data.extend_from_slice(&tag_data);
+    debug_assert_eq!(data.len(), file_size);
+
+    Ok(BuiltCacheFile {
+        data: compress_map_data(data, engine)?,
+        tag_count,
+        used_tag_space,
+        crc32
+    })
+}
+
+fn address_for_offset(base_address: usize, offset: usize) -> RinghopperResult<Address> {
+    let address = base_address.add_overflow_checked(offset)?;
+    Ok(Address { address: u32::try_from(address).map_err(|_| Error::ArrayLimitExceeded)? })
+}
+
+fn compile_bsp(
+    bsp: &dyn PrimaryTagStructDyn,
+    engine: &'static Engine,
+    base_address: usize,
+    tags: &HashMap<TagPath, MapWriteTag>,
+    file_data: &mut Vec<u8>,
+    file_data_offset: usize
+) -> RinghopperResult<Vec<u8>> {
+    let header_size = ScenarioStructureBSPCompiledHeader::simple_size();
+    let mut data = Vec::new();
+    reserve_map_data(&mut data, header_size)?;
+
+    let mut context = MapWriteContext {
+        engine,
+        base_address,
+        tags,
+        file_data,
+        file_data_offset
+    };
+    let offset = bsp.append_to_map_data(&mut data, &mut context)?;
+
+    ScenarioStructureBSPCompiledHeader {
+        pointer: address_for_offset(base_address, offset)?,
+        ..Default::default()
+    }.write::<LittleEndian>(&mut data, 0, header_size)?;
+
+    Ok(data)
+}
+
+fn prepare_tag(tag: &mut dyn PrimaryTagStructDyn, model_data: &mut ModelData, context: &mut MapWriteContext) -> RinghopperResult<()> {
+    match tag.group() {
+        TagGroup::ActorVariant => prepare_actor_variant_tag(tag.as_any_mut().downcast_mut().unwrap()),
+        TagGroup::Bitmap => prepare_bitmap_tag(tag.as_any_mut().downcast_mut().unwrap(), context)?,
+        TagGroup::ContinuousDamageEffect => prepare_continuous_damage_effect_tag(tag.as_any_mut().downcast_mut().unwrap()),
+        TagGroup::DamageEffect => prepare_damage_effect_tag(tag.as_any_mut().downcast_mut().unwrap()),
+        TagGroup::GBXModel => prepare_gbxmodel_tag(tag.as_any_mut().downcast_mut().unwrap(), model_data, context)?,
+        TagGroup::Light => prepare_light_tag(tag.as_any_mut().downcast_mut().unwrap()),
+        TagGroup::ModelAnimations => prepare_model_animations_tag(tag.as_any_mut().downcast_mut().unwrap())?,
+        TagGroup::Model => prepare_model_tag(tag.as_any_mut().downcast_mut().unwrap(), model_data, context)?,
+        TagGroup::PointPhysics => prepare_point_physics_tag(tag.as_any_mut().downcast_mut().unwrap()),
+        TagGroup::Projectile => prepare_projectile_tag(tag.as_any_mut().downcast_mut().unwrap()),
+        TagGroup::Sound => prepare_sound_tag(tag.as_any_mut().downcast_mut().unwrap())?,
+        _ => ()
+    };
+
+    if let Some(n) = downcast_base_object_mut(tag) {
+        prepare_object_tag(n)?;
+    }
+
+    Ok(())
+}
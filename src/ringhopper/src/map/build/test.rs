@@ -0,0 +1,202 @@
+use std::collections::HashMap;
+use definitions::*;
+use primitives::engine::EngineCacheParser;
+use primitives::byteorder::LittleEndian;
+use primitives::map::{Map, MapWriteContext};
+use primitives::parse::SimpleTagData;
+use primitives::primitive::{BSPVertexData, TagPath, UTF16String, Vector2D, Vector3D};
+use primitives::tag::{ParseStrictness, PrimaryTagStructDyn};
+use ringhopper_engines::ALL_SUPPORTED_ENGINES;
+use crate::map::{decompress_map_data, MapTagTree};
+use crate::map::gearbox::GearboxCacheFile;
+use crate::map::header::ParsedCacheFileHeader;
+use crate::map::prepare::prepare_bitmap_tag;
+use crate::tag::compare::compare_tags;
+use crate::tag::scenario::compile_scripts;
+use crate::tag::tree::{MockTagTree, TagTree};
+use super::*;
+
+const SCENARIO: &str = "levels\\test\\roundtrip\\roundtrip.scenario";
+const STRINGS: &str = "levels\\test\\roundtrip\\strings.unicode_string_list";
+const BSP: &str = "levels\\test\\roundtrip\\roundtrip.scenario_structure_bsp";
+
+fn generate_test_tag_tree(engine: &Engine) -> MockTagTree {
+    generate_test_tag_tree_with(engine, Scenario::default(), Vec::new())
+}
+
+/// Make a tag tree with the scenario, any other tags it references, and the engine's required tags.
+fn generate_test_tag_tree_with(engine: &Engine, mut scenario: Scenario, tags: Vec<(&str, Box<dyn PrimaryTagStructDyn>)>) -> MockTagTree {
+    let mut items: HashMap<String, Option<Box<dyn PrimaryTagStructDyn>>> = HashMap::new();
+    for (path, tag) in tags {
+        items.insert(TagPath::from_path(path).unwrap().to_internal_path(), Some(tag));
+    }
+
+    // Cache files need a script node table even if there are no scripts.
+    compile_scripts(&mut scenario, engine).unwrap();
+    items.insert(TagPath::from_path(SCENARIO).unwrap().to_internal_path(), Some(Box::new(scenario)));
+
+    for path in engine.required_tags.all.iter().chain(engine.required_tags.singleplayer) {
+        let path = TagPath::from_path(path).unwrap();
+        items.insert(path.to_internal_path(), Some(new_tag_of_group(path.group()).unwrap()));
+    }
+
+    MockTagTree {
+        items,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn build_and_parse_cache_file() {
+    let scenario = TagPath::from_path(SCENARIO).unwrap();
+
+    for engine in build_engines() {
+        let tag_tree = generate_test_tag_tree(engine);
+        let options = CacheFileBuildOptions { name: Some("roundtrip".to_owned()), build: None };
+        let built = build_cache_file(&tag_tree, &scenario, engine, &options).unwrap();
+        assert_eq!(built.tag_count, tag_tree.items.len(), "tag count mismatch for {}", engine.name);
+
+        let header = ParsedCacheFileHeader::read_from_map_data(&built.data).unwrap();
+        assert_eq!(header.match_engine().map(|e| e.name), Some(engine.name));
+        assert_eq!(header.name.as_str(), "roundtrip");
+        assert_eq!(header.map_type, ScenarioType::Singleplayer);
+        assert_eq!(header.crc32, built.crc32);
+
+        let data = decompress_map_data(built.data, &header, engine).unwrap();
+        let map = GearboxCacheFile::new(data, Vec::new(), Vec::new(), Vec::new(), ParseStrictness::Strict).unwrap();
+        assert_eq!(map.get_name(), "roundtrip");
+        assert_eq!(map.get_scenario_type(), ScenarioType::Singleplayer);
+
+        let mut all_tags = map.get_all_tags();
+        all_tags.sort();
+        let mut expected: Vec<TagPath> = tag_tree.items.keys().map(|p| TagPath::from_path(p).unwrap()).collect();
+        expected.sort();
+        assert_eq!(all_tags, expected);
+
+        for path in &all_tags {
+            let tag = map.open_tag_copy(path).unwrap();
+            assert_eq!(tag.group(), path.group());
+        }
+    }
+}
+
+fn build_engines() -> impl Iterator<Item = &'static Engine> {
+    ALL_SUPPORTED_ENGINES
+        .iter()
+        .filter(|e| e.build_target && !e.external_bsps && e.cache_parser == EngineCacheParser::PC)
+}
+
+fn build_and_open(tag_tree: &MockTagTree, engine: &'static Engine) -> GearboxCacheFile {
+    let scenario = TagPath::from_path(SCENARIO).unwrap();
+    let built = build_cache_file(tag_tree, &scenario, engine, &CacheFileBuildOptions::default()).unwrap();
+    let header = ParsedCacheFileHeader::read_from_map_data(&built.data).unwrap();
+    let data = decompress_map_data(built.data, &header, engine).unwrap();
+    GearboxCacheFile::new(data, Vec::new(), Vec::new(), Vec::new(), ParseStrictness::Strict).unwrap()
+}
+
+#[test]
+fn built_tags_match_their_source() {
+    let mut strings = UnicodeStringList::default();
+    strings.strings = ["first", "second", ""].iter().map(|s| UnicodeStringListString { string: UTF16String::from_str(s) }).collect();
+
+    let mut scenario = Scenario::default();
+    scenario.references.items.push(Default::default());
+    scenario.references.items[0].reference = TagPath::from_path(STRINGS).unwrap().into();
+
+    for engine in build_engines() {
+        let tag_tree = generate_test_tag_tree_with(engine, scenario.clone(), vec![(STRINGS, Box::new(strings.clone()))]);
+        let map = build_and_open(&tag_tree, engine);
+
+        let extracted = map.open_tag_copy(&TagPath::from_path(STRINGS).unwrap()).unwrap();
+        let differences = compare_tags(&strings, extracted.as_ref(), false, false);
+        assert!(differences.is_empty(), "{}: {:?}", engine.name, differences.iter().map(|d| format!("{}: {}", d.path, d.difference)).collect::<Vec<_>>());
+    }
+}
+
+#[test]
+fn build_scenario_with_bsp() {
+    const VERTEX_COUNT: usize = 3;
+
+    let mut vertices = Vec::new();
+    for i in 0..VERTEX_COUNT {
+        let vertex = ScenarioStructureBSPMaterialUncompressedRenderedVertex {
+            position: Vector3D { x: i as f32, y: 1.0, z: 2.0 },
+            normal: Vector3D { x: 0.0, y: 0.0, z: 1.0 },
+            texture_coords: Vector2D { x: 0.5, y: i as f32 },
+            ..Default::default()
+        };
+        vertices.extend_from_slice(vertex.as_bytes::<LittleEndian>().unwrap().bytes());
+    }
+    for _ in 0..VERTEX_COUNT {
+        let vertex = ScenarioStructureBSPMaterialUncompressedLightmapVertex {
+            normal: Vector3D { x: 0.0, y: 1.0, z: 0.0 },
+            ..Default::default()
+        };
+        vertices.extend_from_slice(vertex.as_bytes::<LittleEndian>().unwrap().bytes());
+    }
+
+    let mut material = ScenarioStructureBSPMaterial {
+        uncompressed_vertices: BSPVertexData { bytes: vertices.clone() },
+        ..Default::default()
+    };
+    material.rendered_vertices.vertex_count = VERTEX_COUNT as u32;
+    material.lightmap_vertices.vertex_count = VERTEX_COUNT as u32;
+
+    let mut bsp = ScenarioStructureBSP::default();
+    bsp.lightmaps.items.push(Default::default());
+    bsp.lightmaps.items[0].materials.items.push(material);
+
+    let mut scenario = Scenario::default();
+    scenario.structure_bsps.items.push(Default::default());
+    scenario.structure_bsps.items[0].structure_bsp = TagPath::from_path(BSP).unwrap().into();
+
+    for engine in build_engines().filter(|e| !e.compressed_models) {
+        let tag_tree = generate_test_tag_tree_with(engine, scenario.clone(), vec![(BSP, Box::new(bsp.clone()))]);
+        let map = build_and_open(&tag_tree, engine);
+
+        let extracted = map.open_tag_copy(&TagPath::from_path(BSP).unwrap()).unwrap();
+        let extracted: &ScenarioStructureBSP = extracted.as_any().downcast_ref().unwrap();
+        let material = &extracted.lightmaps.items[0].materials.items[0];
+        assert_eq!(material.uncompressed_vertices.bytes, vertices, "{}", engine.name);
+        assert_eq!(material.rendered_vertices.offset, 0, "{}", engine.name);
+        assert_eq!(
+            material.lightmap_vertices.offset as usize,
+            VERTEX_COUNT * ScenarioStructureBSPMaterialUncompressedRenderedVertex::simple_size(),
+            "{}", engine.name
+        );
+    }
+}
+
+#[test]
+fn reject_swizzled_compressed_bitmap() {
+    let engine = ALL_SUPPORTED_ENGINES.iter().find(|e| e.bitmap_options.swizzled).unwrap();
+
+    let mut bitmap = Bitmap::default();
+    let mut data = BitmapData {
+        width: 4,
+        height: 4,
+        depth: 1,
+        format: BitmapDataFormat::DXT1,
+        pixel_data_size: 8,
+        ..Default::default()
+    };
+    data.flags.compressed = true;
+    data.flags.swizzled = true;
+    bitmap.bitmap_data.items.push(data);
+    bitmap.processed_pixel_data.bytes = vec![0u8; 8];
+
+    let tags = HashMap::new();
+    let mut file_data = Vec::new();
+    let mut context = MapWriteContext {
+        engine,
+        base_address: 0,
+        tags: &tags,
+        file_data: &mut file_data,
+        file_data_offset: 0
+    };
+    assert!(matches!(prepare_bitmap_tag(&mut bitmap, &mut context), Err(Error::InvalidTagData(_))));
+
+    // Fine if it is not swizzled
+    bitmap.bitmap_data.items[0].flags.swizzled = false;
+    assert!(prepare_bitmap_tag(&mut bitmap, &mut context).is_ok());
+}
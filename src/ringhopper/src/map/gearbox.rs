@@ -11,6 +11,7 @@ use primitives::tag::{IGNORED_CRC32, ParseStrictness, PrimaryTagStructDyn};
 use ringhopper_structs::{CacheFileTagDataHeader, CacheFileTagDataHeaderInternalModels};
 use crate::map::{BSPDomain, extract_tag_from_map, MapTagTree, SizeRange};
 use crate::map::resource::ResourceMap;
+use crate::tag::tree::TagDirectoryIndex;
 
 pub struct GearboxCacheFile {
     name: String,
@@ -27,6 +28,7 @@ pub struct GearboxCacheFile {
     base_memory_address: usize,
     tags: Vec<Option<Tag>>,
     ids: HashMap<TagPath, ID>,
+    directory_index: TagDirectoryIndex,
     scenario_tag: ID,
     merged_sound_resources: HashMap<DomainType, Vec<u8>>, // for Halo Custom Edition
     bitmaps: Option<ResourceMap>,
@@ -60,6 +62,7 @@ impl GearboxCacheFile {
             merged_sound_resources: HashMap::new(),
             bsp_vertex_data: Vec::new(),
             ids: Default::default(),
+            directory_index: Default::default(),
             bitmaps: if bitmaps.is_empty() { None } else { Some(ResourceMap::from_data(bitmaps)?) },
             sounds: if sounds.is_empty() { None } else { Some(ResourceMap::from_data(sounds)?) },
             loc: if loc.is_empty() { None } else { Some(ResourceMap::from_data(loc)?) },
@@ -91,6 +94,7 @@ impl GearboxCacheFile {
             map.load_model_data()?;
         }
 
+        map.directory_index = TagDirectoryIndex::new(ids.keys());
         map.ids = ids;
         map.tags = tags;
 
@@ -379,6 +383,10 @@ impl Map for GearboxCacheFile {
         self.get_tag_by_id(*self.ids.get(path)?)
     }
 
+    fn get_tag_id(&self, path: &TagPath) -> Option<ID> {
+        self.ids.get(path).copied()
+    }
+
     fn get_scenario_tag(&self) -> &Tag {
         self.get_tag_by_id(self.scenario_tag).unwrap()
     }
@@ -391,4 +399,16 @@ impl MapTagTree for GearboxCacheFile {
     fn get_scenario_type(&self) -> ScenarioType {
         self.scenario_tag_data._type
     }
+
+    fn get_directory_index(&self) -> &TagDirectoryIndex {
+        &self.directory_index
+    }
+
+    fn get_resource_map(&self, resource_type: ResourceMapType) -> Option<&ResourceMap> {
+        match resource_type {
+            ResourceMapType::Bitmaps => self.bitmaps.as_ref(),
+            ResourceMapType::Sounds => self.sounds.as_ref(),
+            ResourceMapType::Loc => self.loc.as_ref()
+        }
+    }
 }
@@ -0,0 +1,304 @@
+use std::collections::{HashMap, HashSet};
+use primitives::map::{DomainType, Map, ResourceMapType};
+use primitives::primitive::TagPath;
+use crate::map::MapTagTree;
+use crate::tag::compare::{compare_tags, TagComparisonDifference};
+
+#[cfg(test)]
+mod test;
+
+/// Options for [`diff_maps`].
+#[derive(Copy, Clone)]
+pub struct MapDiffOptions {
+    /// Extract tags present in both maps and compare their contents.
+    ///
+    /// This is the slowest part of comparing maps.
+    pub compare_tag_contents: bool,
+
+    /// Also compare cache-only fields when comparing tag contents.
+    pub compare_cache_only_fields: bool
+}
+
+impl Default for MapDiffOptions {
+    fn default() -> Self {
+        Self {
+            compare_tag_contents: true,
+            compare_cache_only_fields: true
+        }
+    }
+}
+
+/// A header field that differs between two maps.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MapHeaderDifference {
+    /// Name of the field.
+    pub field: &'static str,
+
+    /// Value in the first map.
+    pub first: String,
+
+    /// Value in the second map.
+    pub second: String
+}
+
+/// A domain (region of data) that differs between two maps.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MapDomainDifference {
+    /// The domain.
+    pub domain: DomainType,
+
+    /// Size and base address of the domain in the first map, if present.
+    pub first: Option<(usize, usize)>,
+
+    /// Size and base address of the domain in the second map, if present.
+    pub second: Option<(usize, usize)>,
+
+    /// The data differs, even if the size and base address match.
+    pub data_changed: bool
+}
+
+/// Where a tag's base struct is located in a map.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MapTagLocation {
+    /// Index of the tag in the tag array.
+    pub index: usize,
+
+    /// Domain of the tag's base struct.
+    pub domain: DomainType,
+
+    /// Address of the tag's base struct in its domain.
+    pub address: usize
+}
+
+/// A tag that is in both maps.
+#[derive(Clone, Debug)]
+pub struct MapTagDifference {
+    /// Path of the tag.
+    pub path: TagPath,
+
+    /// Location of the tag in the first map.
+    pub first: MapTagLocation,
+
+    /// Location of the tag in the second map.
+    pub second: MapTagLocation,
+
+    /// Differences in the tag's contents.
+    ///
+    /// This is empty if [`MapDiffOptions::compare_tag_contents`] is disabled.
+    pub differences: Vec<TagComparisonDifference>
+}
+
+impl MapTagDifference {
+    /// Return `true` if the tag has a different ID (i.e. index in the tag array).
+    pub fn id_changed(&self) -> bool {
+        self.first.index != self.second.index
+    }
+
+    /// Return `true` if the tag's base struct is in a different domain or at a different address.
+    pub fn location_changed(&self) -> bool {
+        self.first.domain != self.second.domain || self.first.address != self.second.address
+    }
+
+    /// Return `true` if the tag's contents differ.
+    pub fn content_changed(&self) -> bool {
+        !self.differences.is_empty()
+    }
+}
+
+/// Differences between two maps.
+#[derive(Clone, Debug, Default)]
+pub struct MapDiff {
+    /// Header fields that differ.
+    pub header: Vec<MapHeaderDifference>,
+
+    /// Domains that differ.
+    pub domains: Vec<MapDomainDifference>,
+
+    /// Tags only in the second map.
+    pub added_tags: Vec<TagPath>,
+
+    /// Tags only in the first map.
+    pub removed_tags: Vec<TagPath>,
+
+    /// Tags in both maps whose ID, location, or contents differ.
+    pub changed_tags: Vec<MapTagDifference>
+}
+
+impl MapDiff {
+    /// Return `true` if no differences were found.
+    pub fn is_empty(&self) -> bool {
+        self.header.is_empty()
+            && self.domains.is_empty()
+            && self.added_tags.is_empty()
+            && self.removed_tags.is_empty()
+            && self.changed_tags.is_empty()
+    }
+}
+
+/// Compare two maps.
+///
+/// Unlike comparing the tags extracted from each map, this also compares data that only exists in cache files, such as
+/// tag IDs, where each tag is located, header fields, and the domains themselves. Tags that fail to extract are
+/// reported as differences rather than errors.
+pub fn diff_maps(first: &dyn MapTagTree, second: &dyn MapTagTree, options: &MapDiffOptions) -> MapDiff {
+    let mut diff = MapDiff::default();
+
+    diff_headers(first, second, &mut diff);
+    diff_domains(first, second, &mut diff);
+
+    let first_tags = get_tag_locations(first);
+    let second_tags = get_tag_locations(second);
+
+    let mut paths: Vec<&TagPath> = first_tags.keys().chain(second_tags.keys()).collect::<HashSet<_>>().into_iter().collect();
+    paths.sort();
+
+    for path in paths {
+        let (first_location, second_location) = match (first_tags.get(path), second_tags.get(path)) {
+            (Some(a), Some(b)) => (a, b),
+            (Some(_), None) => {
+                diff.removed_tags.push(path.to_owned());
+                continue
+            },
+            (None, Some(_)) => {
+                diff.added_tags.push(path.to_owned());
+                continue
+            },
+            (None, None) => unreachable!()
+        };
+
+        let differences = if options.compare_tag_contents {
+            diff_tag_contents(first, second, path, options.compare_cache_only_fields)
+        }
+        else {
+            Vec::new()
+        };
+
+        let difference = MapTagDifference {
+            path: path.to_owned(),
+            first: first_location.to_owned(),
+            second: second_location.to_owned(),
+            differences
+        };
+
+        if difference.id_changed() || difference.location_changed() || difference.content_changed() {
+            diff.changed_tags.push(difference);
+        }
+    }
+
+    diff
+}
+
+fn diff_headers(first: &dyn MapTagTree, second: &dyn MapTagTree, diff: &mut MapDiff) {
+    fn optional<T: ToString>(value: Option<T>) -> String {
+        value.map(|v| v.to_string()).unwrap_or_else(|| "n/a".to_owned())
+    }
+
+    fn fields(map: &dyn MapTagTree) -> [(&'static str, String); 9] {
+        [
+            ("name", map.get_name().to_owned()),
+            ("build", map.get_build_string().to_owned()),
+            ("engine", map.get_engine().name.to_owned()),
+            ("scenario type", map.get_scenario_type().to_string()),
+            ("scenario tag", map.get_scenario_tag().tag_path.to_string()),
+            ("crc32", optional(map.get_crc32().map(|(header, _)| format!("0x{header:08X}")))),
+            ("uncompressed size", optional(map.get_uncompressed_size())),
+            ("used tag space", optional(map.get_used_tag_space())),
+            ("estimated max tag space", optional(map.get_estimated_max_tag_space()))
+        ]
+    }
+
+    for ((field, first), (_, second)) in fields(first).into_iter().zip(fields(second)) {
+        if first != second {
+            diff.header.push(MapHeaderDifference { field, first, second });
+        }
+    }
+}
+
+fn diff_domains(first: &dyn MapTagTree, second: &dyn MapTagTree, diff: &mut MapDiff) {
+    // MapData is left out since it contains everything else, and the header is compared separately.
+    let mut domains = vec![
+        DomainType::TagData,
+        DomainType::ModelVertexData,
+        DomainType::ModelTriangleData,
+        DomainType::ResourceMapFile(ResourceMapType::Bitmaps),
+        DomainType::ResourceMapFile(ResourceMapType::Sounds),
+        DomainType::ResourceMapFile(ResourceMapType::Loc)
+    ];
+
+    // BSPs are indexed, so keep going until neither map has one.
+    for i in 0.. {
+        let bsp = DomainType::BSP(i);
+        if first.get_domain(&bsp).is_none() && second.get_domain(&bsp).is_none() {
+            break
+        }
+        domains.push(bsp);
+        domains.push(DomainType::BSPVertices(i));
+    }
+
+    for domain in domains {
+        let first_data = first.get_domain(&domain);
+        let second_data = second.get_domain(&domain);
+        let layout = |data: Option<(&[u8], usize)>| data.map(|(bytes, base)| (bytes.len(), base));
+
+        let data_changed = match (first_data, second_data) {
+            (Some((a, _)), Some((b, _))) => a != b,
+            (None, None) => false,
+            _ => true
+        };
+
+        let first = layout(first_data);
+        let second = layout(second_data);
+        if first != second || data_changed {
+            diff.domains.push(MapDomainDifference { domain, first, second, data_changed });
+        }
+    }
+}
+
+fn get_tag_locations(map: &dyn MapTagTree) -> HashMap<TagPath, MapTagLocation> {
+    map.get_all_tags()
+        .into_iter()
+        .filter_map(|path| {
+            let index = map.get_tag_id(&path)?.index()? as usize;
+            let tag = map.get_tag(&path)?;
+            let location = MapTagLocation {
+                index,
+                domain: tag.domain.to_owned(),
+                address: resolve_address(map, &tag.domain, tag.address)
+            };
+            Some((path, location))
+        })
+        .collect()
+}
+
+/// Resource map entries are addressed from the start of the entry, so use the offset into the resource map file instead.
+///
+/// The address is left as-is if the entry is not in a loaded resource map.
+fn resolve_address(map: &dyn MapTagTree, domain: &DomainType, address: usize) -> usize {
+    let DomainType::ResourceMapEntry(resource_type, path) = domain else {
+        return address
+    };
+
+    map.get_resource_map(*resource_type)
+        .and_then(|resource_map| resource_map.get_by_path(path))
+        .and_then(|resource| resource.get_data_offset().checked_add(address))
+        .unwrap_or(address)
+}
+
+fn diff_tag_contents(first: &dyn MapTagTree, second: &dyn MapTagTree, path: &TagPath, compare_cache_only_fields: bool) -> Vec<TagComparisonDifference> {
+    let extraction_failure = |which: &str, error: primitives::error::Error| vec![TagComparisonDifference {
+        depth: 0,
+        path: String::new(),
+        difference: format!("failed to extract from the {which} map: {error}")
+    }];
+
+    let first_tag = match first.extract_tag(path) {
+        Ok(n) => n,
+        Err(e) => return extraction_failure("first", e)
+    };
+    let second_tag = match second.extract_tag(path) {
+        Ok(n) => n,
+        Err(e) => return extraction_failure("second", e)
+    };
+
+    compare_tags(first_tag.as_ref(), second_tag.as_ref(), compare_cache_only_fields, false)
+}
@@ -0,0 +1,148 @@
+use definitions::ScenarioType;
+use primitives::engine::Engine;
+use primitives::error::{Error, RinghopperResult};
+use primitives::map::{DomainType, Map, ResourceMapType, Tag};
+use primitives::primitive::{ID, IDType, TagGroup, TagPath};
+use primitives::tag::PrimaryTagStructDyn;
+use ringhopper_engines::ALL_SUPPORTED_ENGINES;
+use crate::map::MapTagTree;
+use crate::map::resource::{ResourceMap, ResourceMapBuilder};
+use crate::tag::tree::TagDirectoryIndex;
+use super::*;
+
+const BASE_ADDRESS: usize = 0x40440000;
+
+struct TestMap {
+    name: &'static str,
+    tags: Vec<Option<Tag>>,
+    tag_data: Vec<u8>,
+    sounds: Option<ResourceMap>,
+    directory_index: TagDirectoryIndex
+}
+
+impl TestMap {
+    fn new(name: &'static str, tags: &[Option<(&str, usize)>], tag_data: Vec<u8>) -> Self {
+        let tags = tags
+            .iter()
+            .map(|t| t.map(|(path, address)| Tag {
+                tag_path: TagPath::from_path(path).unwrap(),
+                address: BASE_ADDRESS + address,
+                domain: DomainType::TagData
+            }))
+            .collect::<Vec<Option<Tag>>>();
+        let directory_index = TagDirectoryIndex::new(tags.iter().flatten().map(|t| &t.tag_path));
+        Self { name, tags, tag_data, sounds: None, directory_index }
+    }
+}
+
+impl Map for TestMap {
+    fn get_name(&self) -> &str {
+        self.name
+    }
+    fn get_build_string(&self) -> &str {
+        "test"
+    }
+    fn get_engine(&self) -> &'static Engine {
+        &ALL_SUPPORTED_ENGINES[0]
+    }
+    fn extract_tag(&self, path: &TagPath) -> RinghopperResult<Box<dyn PrimaryTagStructDyn>> {
+        Err(Error::TagNotFound(path.to_owned()))
+    }
+    fn get_domain(&self, domain: &DomainType) -> Option<(&[u8], usize)> {
+        match domain {
+            DomainType::TagData => Some((&self.tag_data, BASE_ADDRESS)),
+            DomainType::ResourceMapFile(ResourceMapType::Sounds) => self.sounds.as_ref().map(|s| (s.data(), 0)),
+            DomainType::ResourceMapEntry(ResourceMapType::Sounds, path) => Some((self.sounds.as_ref()?.get_by_path(path)?.get_data(), 0)),
+            _ => None
+        }
+    }
+    fn get_tag_by_id(&self, id: ID) -> Option<&Tag> {
+        self.tags.get(id.index()? as usize)?.as_ref()
+    }
+    fn get_tag(&self, path: &TagPath) -> Option<&Tag> {
+        self.tags.iter().flatten().find(|t| &t.tag_path == path)
+    }
+    fn get_tag_id(&self, path: &TagPath) -> Option<ID> {
+        let index = self.tags.iter().position(|t| t.as_ref().is_some_and(|t| &t.tag_path == path))?;
+        Some(ID::new(Some(index as u16), IDType::Tag as u16))
+    }
+    fn get_scenario_tag(&self) -> &Tag {
+        self.tags[0].as_ref().unwrap()
+    }
+    fn get_all_tags(&self) -> Vec<TagPath> {
+        self.tags.iter().flatten().map(|t| t.tag_path.to_owned()).collect()
+    }
+}
+
+impl MapTagTree for TestMap {
+    fn get_scenario_type(&self) -> ScenarioType {
+        ScenarioType::Singleplayer
+    }
+    fn get_directory_index(&self) -> &TagDirectoryIndex {
+        &self.directory_index
+    }
+    fn get_resource_map(&self, resource_type: ResourceMapType) -> Option<&ResourceMap> {
+        match resource_type {
+            ResourceMapType::Sounds => self.sounds.as_ref(),
+            _ => None
+        }
+    }
+}
+
+#[test]
+fn diffs_maps() {
+    let first = TestMap::new("first", &[Some(("levels\\test\\test.scenario", 0)), Some(("a.sound", 0x10)), Some(("b.sound", 0x20))], vec![0; 0x40]);
+    let second = TestMap::new("second", &[Some(("levels\\test\\test.scenario", 0)), None, Some(("b.sound", 0x30)), Some(("c.sound", 0x20))], vec![1; 0x40]);
+
+    let options = MapDiffOptions { compare_tag_contents: false, ..Default::default() };
+    let diff = diff_maps(&first, &second, &options);
+    assert!(!diff.is_empty());
+
+    assert_eq!(vec![MapHeaderDifference { field: "name", first: "first".to_owned(), second: "second".to_owned() }], diff.header);
+    assert_eq!(vec![TagPath::new("a", TagGroup::Sound).unwrap()], diff.removed_tags);
+    assert_eq!(vec![TagPath::new("c", TagGroup::Sound).unwrap()], diff.added_tags);
+
+    // The IDs match and the data differs, but the size and address of tag data does not.
+    assert_eq!(1, diff.domains.len());
+    assert_eq!(DomainType::TagData, diff.domains[0].domain);
+    assert_eq!(diff.domains[0].first, diff.domains[0].second);
+    assert!(diff.domains[0].data_changed);
+
+    // b moved from 0x20 to 0x30, but its ID did not change. The scenario tag is unchanged.
+    assert_eq!(1, diff.changed_tags.len());
+    let b = &diff.changed_tags[0];
+    assert_eq!("b.sound", b.path.to_string());
+    assert!(!b.id_changed());
+    assert!(b.location_changed());
+    assert!(!b.content_changed());
+
+    assert!(diff_maps(&first, &first, &options).is_empty());
+}
+
+#[test]
+fn reports_extraction_failures() {
+    let map = TestMap::new("map", &[Some(("levels\\test\\test.scenario", 0))], vec![0; 0x10]);
+    let diff = diff_maps(&map, &map, &MapDiffOptions::default());
+    assert_eq!(1, diff.changed_tags.len());
+    assert!(diff.changed_tags[0].content_changed());
+}
+
+#[test]
+fn resolves_resource_map_entry_addresses() {
+    let mut map = TestMap::new("resources", &[Some(("levels\\test\\test.scenario", 0))], vec![0; 0x10]);
+    let entry = |path: &str| DomainType::ResourceMapEntry(ResourceMapType::Sounds, path.to_owned());
+
+    // Nothing to resolve against without the resource map
+    assert_eq!(4, resolve_address(&map, &entry("second"), 4));
+
+    let mut builder = ResourceMapBuilder::new(ResourceMapType::Sounds, 0x10).unwrap();
+    builder.add("first", &[1, 2, 3]).unwrap();
+    builder.add("second", &[0; 0x10]).unwrap();
+    map.sounds = Some(builder.build().unwrap());
+
+    let data_offset = map.sounds.as_ref().unwrap().get_by_path("second").unwrap().get_data_offset();
+    assert!(data_offset > 0);
+    assert_eq!(data_offset + 4, resolve_address(&map, &entry("second"), 4));
+    assert_eq!(4, resolve_address(&map, &entry("missing"), 4));
+    assert_eq!(4, resolve_address(&map, &DomainType::TagData, 4));
+}
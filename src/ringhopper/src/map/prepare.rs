@@ -0,0 +1,348 @@
+use std::num::NonZeroUsize;
+use definitions::{BitmapDataType, ModelTriangleStripData, ScenarioStructureBSP};
+use primitives::map::MapWriteContext;
+use primitives::primitive::{calculate_padding_for_alignment, Address, Index, Pixel32};
+use crate::constants::{TICK_RATE, TICK_RATE_RECIPROCOL};
+use crate::definitions::*;
+use crate::primitives::byteorder::{BigEndian, LittleEndian};
+use crate::primitives::error::{Error, OverflowCheck, RinghopperResult};
+use crate::primitives::parse::SimpleTagData;
+use crate::tag::bitmap::{bytes_per_block, COMPRESSED_BITMAP_DATA_FORMATS, MipmapFaceIterator, MipmapMetadata, MipmapTextureIterator, MipmapType, pixels_per_block_length, Swizzlable, swizzle};
+use crate::tag::model::{ModelFunctions, ModelPartGet};
+use crate::tag::model_animations::flip_endianness_for_model_animations_animation;
+use crate::tag::scenario::{flip_scenario_script_endianness, resolve_scenario_script_tag_references};
+use crate::tag::scenario_structure_bsp::recompress_scenario_structure_bsp_vertices;
+
+// These functions are the inverse of the functions in extract.rs, converting tag data into what is expected in a cache
+// file.
+
+fn multiply_by_tick_rate(val: &mut f32) {
+    *val = *val * TICK_RATE;
+}
+
+fn divide_by_tick_rate(val: &mut f32) {
+    *val = *val / TICK_RATE;
+}
+
+pub fn prepare_damage_effect_tag(damage_effect: &mut DamageEffect) {
+    multiply_by_tick_rate(&mut damage_effect.camera_shaking.wobble_period);
+}
+
+pub fn prepare_actor_variant_tag(actor_variant: &mut ActorVariant) {
+    divide_by_tick_rate(&mut actor_variant.grenades.grenade_velocity);
+}
+
+pub fn prepare_continuous_damage_effect_tag(continuous_damage_effect: &mut ContinuousDamageEffect) {
+    multiply_by_tick_rate(&mut continuous_damage_effect.camera_shaking.wobble_period);
+}
+
+pub fn prepare_point_physics_tag(point_physics: &mut PointPhysics) {
+    point_physics.air_friction *= 10000.0;
+    point_physics.water_friction *= 10000.0;
+}
+
+pub fn prepare_projectile_tag(projectile: &mut Projectile) {
+    divide_by_tick_rate(&mut projectile.minimum_velocity);
+    divide_by_tick_rate(&mut projectile.initial_velocity);
+    divide_by_tick_rate(&mut projectile.final_velocity);
+
+    for i in &mut projectile.material_response {
+        divide_by_tick_rate(&mut i.potential_and.upper);
+        divide_by_tick_rate(&mut i.potential_and.lower);
+    }
+}
+
+pub fn prepare_light_tag(light: &mut Light) {
+    multiply_by_tick_rate(&mut light.effect_parameters.duration);
+}
+
+pub fn prepare_scenario_tag(scenario: &mut Scenario, context: &mut MapWriteContext) -> RinghopperResult<()> {
+    resolve_scenario_script_tag_references::<BigEndian>(scenario, context.tags)?;
+    flip_scenario_script_endianness::<BigEndian, LittleEndian>(scenario)?;
+
+    for i in &mut scenario.cutscene_titles {
+        multiply_by_tick_rate(&mut i.fade_in_time);
+        multiply_by_tick_rate(&mut i.fade_out_time);
+        i.up_time = i.up_time * TICK_RATE + i.fade_in_time;
+    }
+
+    Ok(())
+}
+
+pub fn prepare_scenario_structure_bsp_tag(bsp: &mut ScenarioStructureBSP, global_z_offset: &[f32; 32], compressed: bool) -> RinghopperResult<()> {
+    for obj in &mut bsp.detail_objects {
+        for cell in &obj.cells.items {
+            let mut reference_vector_offset = cell.count_index as usize;
+            for bit_offset in 0..32 {
+                let bit = (cell.valid_layers_flags >> bit_offset as u32) & 1;
+                if bit == 0 {
+                    continue
+                }
+
+                let Some(vector) = obj.z_reference_vectors.items.get_mut(reference_vector_offset) else {
+                    return Err(Error::InvalidTagData(format!("Unable to get z reference vector #{reference_vector_offset}")))
+                };
+                reference_vector_offset = reference_vector_offset.add_overflow_checked(1)?;
+                vector.z_reference_l += global_z_offset[bit_offset];
+            }
+        }
+    }
+
+    if compressed {
+        recompress_scenario_structure_bsp_vertices(bsp)?;
+    }
+
+    // Offsets are relative to the start of the material's vertex data, where lightmap vertices follow rendered vertices.
+    let rendered_vertex_size = if compressed {
+        ScenarioStructureBSPMaterialCompressedRenderedVertex::simple_size()
+    }
+    else {
+        ScenarioStructureBSPMaterialUncompressedRenderedVertex::simple_size()
+    };
+    for lightmap in &mut bsp.lightmaps {
+        for material in &mut lightmap.materials {
+            let lightmap_offset = rendered_vertex_size.mul_overflow_checked(material.rendered_vertices.vertex_count as usize)?;
+            material.rendered_vertices.offset = 0;
+            material.lightmap_vertices.offset = u32::try_from(lightmap_offset).map_err(|_| Error::ArrayLimitExceeded)?;
+        }
+    }
+
+    Ok(())
+}
+
+pub fn prepare_object_tag(object: &mut Object) -> RinghopperResult<()> {
+    for cc in &mut object.change_colors {
+        // Weights are stored as partial weights from 0.0 - 1.0 in cache files
+        let total: f32 = cc.permutations.items.iter().map(|p| p.weight.max(0.0)).sum();
+        let count = cc.permutations.items.len();
+        let mut cumulative = 0.0;
+        for (index, permutation) in cc.permutations.items.iter_mut().enumerate() {
+            let weight = if total > 0.0 { permutation.weight.max(0.0) / total } else { 1.0 / count as f32 };
+            cumulative += weight;
+            permutation.weight = if index + 1 == count { 1.0 } else { cumulative };
+        }
+    }
+
+    Ok(())
+}
+
+pub fn prepare_model_animations_tag(model_animations: &mut ModelAnimations) -> RinghopperResult<()> {
+    for animation in &mut model_animations.animations {
+        flip_endianness_for_model_animations_animation::<BigEndian, LittleEndian>(animation)?;
+
+        if animation.flags.compressed_data {
+            // Only the compressed data is stored in cache files
+            let offset = animation.offset_to_compressed_data as usize;
+            if offset > animation.frame_data.bytes.len() {
+                return Err(Error::InvalidTagData(format!("Animation {} has an out-of-bounds compressed data offset", animation.name)))
+            }
+            animation.frame_data.bytes.drain(..offset);
+            animation.offset_to_compressed_data = 0;
+            animation.default_data.bytes.clear();
+        }
+    }
+
+    Ok(())
+}
+
+pub fn prepare_sound_tag(tag: &mut Sound) -> RinghopperResult<()> {
+    let permutations = tag.pitch_ranges.items.iter_mut().flat_map(|p| p.permutations.items.iter_mut());
+    for permutation in permutations {
+        if permutation.format != SoundFormat::PCM {
+            continue;
+        }
+        if permutation.samples.bytes.len() % 2 == 1 {
+            return Err(Error::InvalidTagData("Sound data is 16-bit PCM, but one or more permutations have an odd number of bytes".to_owned()));
+        }
+
+        // swap endian of 16-bit PCM
+        let swapped: Vec<u8> = permutation.samples.bytes.chunks(2).map(|b| [b[1], b[0]]).flatten().collect();
+        permutation.samples.bytes = swapped;
+    }
+
+    tag.maximum_bend_rate = tag.maximum_bend_rate.powf(TICK_RATE_RECIPROCOL);
+
+    Ok(())
+}
+
+/// Model vertex and triangle data stored outside of tag data.
+#[derive(Default)]
+pub struct ModelData {
+    pub vertices: Vec<u8>,
+    pub triangles: Vec<u8>
+}
+
+macro_rules! prepare_model {
+    ($model:expr, $model_data:expr, $context:expr) => {{
+        if !$context.engine.external_models {
+            return Err(Error::Other(format!("Building models for `{}` is not supported", $context.engine.name)))
+        }
+
+        $model.check_indices()?;
+        $model.fix_uncompressed_vertices();
+        $model.flip_lod_cutoffs();
+
+        for geo in &mut $model.geometries {
+            for part in &mut geo.parts {
+                let part = part.get_model_part_mut();
+
+                let vertex_count = part.uncompressed_vertices.items.len();
+                if vertex_count > 0xFFFF {
+                    return Err(Error::InvalidTagData(format!("Model data is invalid: vertex count is too high (0x{vertex_count:X} > 0xFFFF)")))
+                }
+
+                let vertex_offset = $model_data.vertices.len();
+                let vertex_size = ModelVertexUncompressed::simple_size();
+                $model_data.vertices.resize(vertex_offset.add_overflow_checked(vertex_size.mul_overflow_checked(vertex_count)?)?, 0);
+                for (index, vertex) in part.uncompressed_vertices.items.iter().enumerate() {
+                    let at = vertex_offset + index * vertex_size;
+                    vertex.write::<LittleEndian>(&mut $model_data.vertices, at, at + vertex_size)?;
+                }
+
+                // Trailing null indices are padding for the last triangle strip
+                let mut indices: Vec<Index> = part.triangle_data.items.iter().flat_map(|t: &ModelTriangleStripData| t.indices).collect();
+                while indices.last().is_some_and(|i| i.is_none()) {
+                    indices.pop();
+                }
+                let triangle_count = indices.len().saturating_sub(2);
+                let max_triangles = 0xFFFE*3;
+                if triangle_count > max_triangles {
+                    return Err(Error::InvalidTagData(format!("Model data is invalid: triangle count is too high (0x{triangle_count:X} > 0x{max_triangles:X})")))
+                }
+
+                let triangle_offset = $model_data.triangles.len();
+                let index_size = Index::simple_size();
+                $model_data.triangles.resize(triangle_offset.add_overflow_checked(index_size.mul_overflow_checked(indices.len())?)?, 0);
+                for (index, value) in indices.iter().enumerate() {
+                    let at = triangle_offset + index * index_size;
+                    value.write::<LittleEndian>(&mut $model_data.triangles, at, at + index_size)?;
+                }
+
+                part.vertices.vertex_count = vertex_count as u32;
+                part.vertices.vertex_pointer = Address { address: u32::try_from(vertex_offset).map_err(|_| Error::ArrayLimitExceeded)? };
+                part.triangles.triangle_count = triangle_count as u32;
+                part.triangles.triangle_pointer = Address { address: u32::try_from(triangle_offset).map_err(|_| Error::ArrayLimitExceeded)? };
+            }
+        }
+
+        Ok(())
+    }}
+}
+
+pub fn prepare_model_tag(model: &mut Model, model_data: &mut ModelData, context: &MapWriteContext) -> RinghopperResult<()> {
+    prepare_model!(model, model_data, context)
+}
+
+pub fn prepare_gbxmodel_tag(gbxmodel: &mut GBXModel, model_data: &mut ModelData, context: &MapWriteContext) -> RinghopperResult<()> {
+    prepare_model!(gbxmodel, model_data, context)
+}
+
+pub fn prepare_bitmap_tag(tag: &mut Bitmap, context: &mut MapWriteContext) -> RinghopperResult<()> {
+    let engine = context.engine;
+    let engine_name = engine.name;
+    let alignment = engine.bitmap_options.alignment;
+
+    for i in &mut tag.bitmap_data {
+        let offset = i.pixel_data_offset as usize;
+        let length = i.pixel_data_size as usize;
+        let end = offset.add_overflow_checked(length)?;
+        let pixel_data = tag.processed_pixel_data.bytes.get(offset..end)
+            .ok_or_else(|| Error::InvalidTagData(format!("Bitmap data is out of bounds (0x{offset:08X}[0x{length:08X}])")))?;
+
+        if i.flags.swizzled {
+            if !engine.bitmap_options.swizzled {
+                return Err(Error::InvalidTagData(format!("Bitmap is marked as swizzled, but this is not allowed for `{engine_name}` maps.")));
+            }
+            if i.flags.compressed || COMPRESSED_BITMAP_DATA_FORMATS.contains(&i.format) {
+                return Err(Error::InvalidTagData("Bitmap is marked as swizzled and compressed which is not allowed.".to_string()));
+            }
+        }
+
+        let block_size = pixels_per_block_length(i.format);
+        let bytes_per_block = bytes_per_block(i.format);
+        let metadata = MipmapTextureIterator::new_from_bitmap_data(&i)?;
+        let mipmap_format = MipmapType::get_mipmap_type(&i)?;
+
+        let mut data = pixel_data.to_vec();
+
+        if i.flags.swizzled {
+            let mut swizzled = Vec::with_capacity(data.len());
+
+            let mut swizzle_mipmap = |m: MipmapMetadata| -> RinghopperResult<()> {
+                let start = m.block_offset * bytes_per_block.get();
+                let end = start.add_overflow_checked(m.block_count.mul_overflow_checked(bytes_per_block.get())?)?;
+                let input = data.get(start..end).ok_or_else(|| Error::InvalidTagData("Bitmap data is too small".to_owned()))?;
+
+                fn swizzle_blocks<T: SimpleTagData + Swizzlable>(metadata: MipmapMetadata, input: &[u8], output: &mut Vec<u8>) -> RinghopperResult<()> {
+                    let data: Vec<T> = T::read_chunks_to_iterator::<LittleEndian>(input).unwrap().into_infallible().collect();
+                    let mut swizzled: Vec<T> = vec![Default::default(); metadata.block_count];
+                    swizzle(&data, &mut swizzled, metadata.width, metadata.height, metadata.depth, false)?;
+                    for i in swizzled {
+                        output.extend_from_slice(i.as_bytes::<LittleEndian>().unwrap().bytes());
+                    }
+                    Ok(())
+                }
+
+                match bytes_per_block.get() {
+                    1 => swizzle_blocks::<u8>(m, input, &mut swizzled),
+                    2 => swizzle_blocks::<u16>(m, input, &mut swizzled),
+                    4 => swizzle_blocks::<Pixel32>(m, input, &mut swizzled),
+                    n => Err(Error::InvalidTagData(format!("Cannot swizzle bitmap data with {n}-byte pixels")))
+                }
+            };
+
+            if matches!(mipmap_format, MipmapType::Cubemap) {
+                for m in MipmapFaceIterator::new_from_bitmap_data(&i)? {
+                    swizzle_mipmap(m)?;
+                }
+            }
+            else {
+                for m in metadata {
+                    swizzle_mipmap(m)?;
+                }
+            }
+
+            data = swizzled;
+        }
+
+        // Cubemaps are stored mipmap-first in tags, but some engines want each face stored separately
+        if engine.bitmap_options.cubemap_faces_stored_separately && i._type == BitmapDataType::CubeMap {
+            let mipmaps: Vec<MipmapMetadata> = MipmapFaceIterator::new(
+                NonZeroUsize::new(i.width as usize).ok_or_else(|| Error::InvalidTagData("width is 0".to_owned()))?,
+                NonZeroUsize::new(i.height as usize).ok_or_else(|| Error::InvalidTagData("height is 0".to_owned()))?,
+                MipmapType::TwoDimensional,
+                block_size,
+                Some(i.mipmap_count as usize)
+            ).collect();
+
+            let bitmap_length: usize = mipmaps.iter().map(|m| m.block_count * bytes_per_block.get()).sum();
+            let bitmap_length_padded = bitmap_length + calculate_padding_for_alignment(bitmap_length, alignment);
+
+            let mut faces = vec![0u8; bitmap_length_padded * 6];
+            let mut input = data.as_slice();
+            for mipmap in mipmaps {
+                for face in [0, 2, 1, 3, 4, 5] {
+                    let size = mipmap.block_count * bytes_per_block.get();
+                    if input.len() < size {
+                        return Err(Error::InvalidTagData("Bitmap data is too small".to_owned()));
+                    }
+                    let start = face * bitmap_length_padded + mipmap.block_offset * bytes_per_block.get();
+                    faces[start..start + size].copy_from_slice(&input[..size]);
+                    input = &input[size..];
+                }
+            }
+
+            data = faces;
+        }
+
+        data.resize(data.len().add_overflow_checked(calculate_padding_for_alignment(data.len(), alignment))?, 0);
+
+        i.pixel_data_offset = context.append_file_data(&data)?;
+        i.pixel_data_size = u32::try_from(data.len()).map_err(|_| Error::ArrayLimitExceeded)?;
+        i.flags.external = false;
+    }
+
+    tag.processed_pixel_data.bytes.clear();
+
+    Ok(())
+}
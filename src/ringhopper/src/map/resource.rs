@@ -1,8 +1,18 @@
-use definitions::{ResourceMapHeader, ResourceMapResource};
+use std::collections::HashMap;
+use definitions::{Bitmap, ResourceMapHeader, ResourceMapResource, Sound};
+use definitions::ResourceMapType as ResourceMapHeaderType;
 use primitives::byteorder::LittleEndian;
+use primitives::engine::Engine;
 use primitives::error::{Error, OverflowCheck, RinghopperResult};
+use primitives::map::{MapWriteContext, ResourceMapType};
 use primitives::parse::SimpleTagData;
+use primitives::primitive::{calculate_padding_for_alignment, TagGroup, TagPath};
+use primitives::tag::PrimaryTagStructDyn;
 use crate::map::SizeRange;
+use crate::map::prepare::{prepare_bitmap_tag, prepare_sound_tag};
+
+#[cfg(test)]
+mod test;
 
 #[derive(Default, Clone)]
 pub struct ResourceMap {
@@ -172,3 +182,211 @@ impl<'a> Resource<'a> {
         self.path
     }
 }
+
+/// Builds resource maps (e.g. bitmaps.map, sounds.map, loc.map).
+///
+/// Resource maps are written as the header, the data of each resource (padded to the alignment), the paths, and then
+/// the resource array. A resource map laid out like this will be rebuilt byte-for-byte.
+#[derive(Clone)]
+pub struct ResourceMapBuilder {
+    resource_type: ResourceMapType,
+    alignment: usize,
+    resources: Vec<(String, Vec<u8>)>,
+    indices: HashMap<String, usize>
+}
+
+impl ResourceMapBuilder {
+    /// Instantiate an empty builder.
+    ///
+    /// `alignment` is the alignment of each resource's data (e.g. [`Engine::data_alignment`]).
+    ///
+    /// Returns an error if `alignment` is 0.
+    pub fn new(resource_type: ResourceMapType, alignment: usize) -> RinghopperResult<Self> {
+        if alignment == 0 {
+            return Err(Error::Other("resource map alignment must be non-zero".to_owned()))
+        }
+        Ok(Self {
+            resource_type,
+            alignment,
+            resources: Vec::new(),
+            indices: HashMap::new()
+        })
+    }
+
+    /// Instantiate a builder with all of the resources of an existing resource map.
+    ///
+    /// Returns an error if the resource map is of an unknown type or has duplicate paths.
+    pub fn from_resource_map(map: &ResourceMap, alignment: usize) -> RinghopperResult<Self> {
+        let header = ResourceMapHeader::read::<LittleEndian>(map.data(), 0, map.data().len())
+            .map_err(|e| Error::MapParseFailure(format!("Resource map parse failure: can't read resource map header: {e}")))?;
+        let resource_type = match header._type {
+            ResourceMapHeaderType::Bitmaps => ResourceMapType::Bitmaps,
+            ResourceMapHeaderType::Sounds => ResourceMapType::Sounds,
+            ResourceMapHeaderType::Loc => ResourceMapType::Loc
+        };
+
+        let mut builder = Self::new(resource_type, alignment)?;
+        for i in 0..map.len() {
+            // SAFETY: i is always in bounds
+            let resource = unsafe { map.get_unchecked(i) };
+            builder.add(resource.get_path(), resource.get_data())?;
+        }
+        Ok(builder)
+    }
+
+    /// Get the type of resource map being built.
+    pub fn resource_type(&self) -> ResourceMapType {
+        self.resource_type
+    }
+
+    /// Get the number of resources.
+    pub fn len(&self) -> usize {
+        self.resources.len()
+    }
+
+    /// Get the index of a resource by its path.
+    ///
+    /// If `path` does not match anything, `None` is returned.
+    pub fn index_of(&self, path: &str) -> Option<usize> {
+        self.indices.get(path).copied()
+    }
+
+    /// Add a resource, returning its index.
+    ///
+    /// If a resource with the same path and data exists, the index of the existing resource is returned. Returns an
+    /// error if one exists with different data, or if there are too many resources.
+    pub fn add(&mut self, path: &str, data: &[u8]) -> RinghopperResult<usize> {
+        if let Some(index) = self.index_of(path) {
+            return if self.resources[index].1 == data {
+                Ok(index)
+            }
+            else {
+                Err(Error::Other(format!("resource `{path}` already exists with different data")))
+            }
+        }
+
+        let index = self.resources.len();
+        if index >= u32::MAX as usize {
+            return Err(Error::ArrayLimitExceeded)
+        }
+
+        self.resources.push((path.to_owned(), data.to_owned()));
+        self.indices.insert(path.to_owned(), index);
+        Ok(index)
+    }
+
+    /// Add the resources of a tag, returning their indices.
+    ///
+    /// The tag is prepared for `engine` the same way it is when building a cache file (e.g. 16-bit PCM is byte-swapped and
+    /// bitmaps are swizzled or have their cubemap faces rearranged).
+    ///
+    /// - Bitmap tags add the pixel data of each bitmap as `<path>__<bitmap>`.
+    /// - Sound tags add the samples of each permutation as `<path>__<pitch range>__<permutation>`.
+    /// - Font, hud_message_text, and unicode_string_list tags add the tag data as `<path>`, with pointers relative to the
+    ///   start of the resource. These cannot reference other tags, since the resource map is shared between maps.
+    ///
+    /// Returns an error if the tag cannot be stored in this type of resource map.
+    pub fn add_tag(&mut self, path: &TagPath, tag: &dyn PrimaryTagStructDyn, engine: &'static Engine) -> RinghopperResult<Vec<usize>> {
+        let base_path = path.path();
+        let mut indices = Vec::new();
+
+        let tags = HashMap::new();
+        let mut file_data = Vec::new();
+        let mut context = MapWriteContext {
+            engine,
+            base_address: 0,
+            tags: &tags,
+            file_data: &mut file_data,
+            file_data_offset: 0
+        };
+
+        match (self.resource_type, path.group()) {
+            (ResourceMapType::Bitmaps, TagGroup::Bitmap) => {
+                let mut bitmap: Bitmap = tag.as_any().downcast_ref::<Bitmap>().unwrap().clone();
+                prepare_bitmap_tag(&mut bitmap, &mut context)?;
+                for (index, data) in bitmap.bitmap_data.items.iter().enumerate() {
+                    let offset = data.pixel_data_offset as usize;
+                    let end = offset.add_overflow_checked(data.pixel_data_size as usize)?;
+                    indices.push(self.add(&format!("{base_path}__{index}"), &file_data[offset..end])?);
+                }
+            },
+            (ResourceMapType::Sounds, TagGroup::Sound) => {
+                let mut sound: Sound = tag.as_any().downcast_ref::<Sound>().unwrap().clone();
+                prepare_sound_tag(&mut sound)?;
+                for (pitch_range_index, pitch_range) in sound.pitch_ranges.items.iter().enumerate() {
+                    for (permutation_index, permutation) in pitch_range.permutations.items.iter().enumerate() {
+                        indices.push(self.add(&format!("{base_path}__{pitch_range_index}__{permutation_index}"), &permutation.samples.bytes)?);
+                    }
+                }
+            },
+            (ResourceMapType::Loc, TagGroup::Font | TagGroup::HUDMessageText | TagGroup::UnicodeStringList) => {
+                let mut data = Vec::new();
+                let offset = tag.append_to_map_data(&mut data, &mut context)?;
+                debug_assert_eq!(offset, 0);
+                if !file_data.is_empty() {
+                    return Err(Error::InvalidTagData(format!("`{path}` has data that cannot be stored in a resource map")))
+                }
+                indices.push(self.add(base_path, &data)?);
+            },
+            (resource_type, group) => return Err(Error::Other(format!("`{group}` tags cannot be added to a {resource_type:?} resource map")))
+        }
+
+        Ok(indices)
+    }
+
+    /// Write the resource map.
+    ///
+    /// Returns an error if the resource map would exceed 4 GiB.
+    pub fn build(&self) -> RinghopperResult<ResourceMap> {
+        let header_size = ResourceMapHeader::simple_size();
+        let resource_size = ResourceMapResource::simple_size();
+
+        let mut data = vec![0u8; header_size];
+        let mut array = Vec::with_capacity(self.resources.len());
+
+        for (_, resource_data) in &self.resources {
+            let padding = calculate_padding_for_alignment(data.len(), self.alignment);
+            data.resize(data.len().add_overflow_checked(padding)?, 0);
+
+            array.push(ResourceMapResource {
+                data_offset: to_u32(data.len())?,
+                data_size: to_u32(resource_data.len())?,
+                ..Default::default()
+            });
+            data.extend_from_slice(resource_data);
+        }
+
+        let path_data_offset = data.len();
+        for ((path, _), resource) in self.resources.iter().zip(array.iter_mut()) {
+            resource.path_offset = to_u32(data.len() - path_data_offset)?;
+            data.extend_from_slice(path.as_bytes());
+            data.push(0);
+        }
+
+        let array_offset = data.len();
+        data.resize(array_offset.add_overflow_checked(resource_size.mul_overflow_checked(array.len())?)?, 0);
+        for (index, resource) in array.iter().enumerate() {
+            let offset = array_offset + index * resource_size;
+            resource.write::<LittleEndian>(&mut data, offset, offset + resource_size)?;
+        }
+
+        ResourceMapHeader {
+            _type: match self.resource_type {
+                ResourceMapType::Bitmaps => ResourceMapHeaderType::Bitmaps,
+                ResourceMapType::Sounds => ResourceMapHeaderType::Sounds,
+                ResourceMapType::Loc => ResourceMapHeaderType::Loc
+            },
+            path_data_offset: to_u32(path_data_offset)?,
+            array_offset: to_u32(array_offset)?,
+            count: to_u32(array.len())?,
+            ..Default::default()
+        }.write::<LittleEndian>(&mut data, 0, header_size)?;
+
+        to_u32(data.len())?;
+        ResourceMap::from_data(data)
+    }
+}
+
+fn to_u32(value: usize) -> RinghopperResult<u32> {
+    u32::try_from(value).map_err(|_| Error::ArrayLimitExceeded)
+}
@@ -0,0 +1,188 @@
+use definitions::{Bitmap, BitmapData, BitmapDataFormat, Sound, SoundFormat, UnicodeStringList, UnicodeStringListString};
+use primitives::primitive::UTF16String;
+use ringhopper_engines::ALL_SUPPORTED_ENGINES;
+use super::*;
+
+fn make_test_map(alignment: usize) -> ResourceMap {
+    let mut builder = ResourceMapBuilder::new(ResourceMapType::Bitmaps, alignment).unwrap();
+    builder.add("ui\\shell\\bitmaps\\background", &[1, 2, 3]).unwrap();
+    builder.add("ui\\shell\\bitmaps\\cursor", &[4, 5, 6, 7, 8]).unwrap();
+    builder.add("empty", &[]).unwrap();
+    builder.build().unwrap()
+}
+
+#[test]
+fn build_resource_map() {
+    let map = make_test_map(4);
+    assert_eq!(map.len(), 3);
+
+    let cursor = map.get_by_path("ui\\shell\\bitmaps\\cursor").unwrap();
+    assert_eq!(cursor.get_data(), &[4, 5, 6, 7, 8]);
+    assert_eq!(cursor.get_data_offset() % 4, 0);
+
+    assert_eq!(map.get(0).unwrap().get_path(), "ui\\shell\\bitmaps\\background");
+    assert!(map.get(2).unwrap().get_data().is_empty());
+}
+
+#[test]
+fn resource_map_zero_alignment() {
+    assert!(ResourceMapBuilder::new(ResourceMapType::Bitmaps, 0).is_err());
+}
+
+#[test]
+fn resource_map_round_trip() {
+    for alignment in [1, 4, 0x800] {
+        let map = make_test_map(alignment);
+        let rebuilt = ResourceMapBuilder::from_resource_map(&map, alignment).unwrap().build().unwrap();
+        assert_eq!(map.data(), rebuilt.data());
+    }
+}
+
+#[test]
+fn stock_resource_map_round_trip() {
+    // Laid out like the stock resource maps: the header, unpadded data, null-terminated paths, and then the array.
+    let resources: [(&str, &[u8]); 3] = [
+        ("sound\\sfx\\ui\\cursor__0__0", &[0x10, 0x20, 0x30]),
+        ("sound\\sfx\\ui\\cursor", &[0x40; 7]),
+        ("sound\\sfx\\ui\\back__0__0", &[0x50, 0x60])
+    ];
+
+    let header_size = ResourceMapHeader::simple_size();
+    let resource_size = ResourceMapResource::simple_size();
+
+    let mut data = vec![0u8; header_size];
+    let mut array = Vec::new();
+    for (_, bytes) in resources {
+        array.push(ResourceMapResource { data_offset: data.len() as u32, data_size: bytes.len() as u32, ..Default::default() });
+        data.extend_from_slice(bytes);
+    }
+    let path_data_offset = data.len();
+    for ((path, _), resource) in resources.iter().zip(array.iter_mut()) {
+        resource.path_offset = (data.len() - path_data_offset) as u32;
+        data.extend_from_slice(path.as_bytes());
+        data.push(0);
+    }
+    let array_offset = data.len();
+    data.resize(array_offset + resource_size * array.len(), 0);
+    for (index, resource) in array.iter().enumerate() {
+        let at = array_offset + index * resource_size;
+        resource.write::<LittleEndian>(&mut data, at, at + resource_size).unwrap();
+    }
+    ResourceMapHeader {
+        _type: ResourceMapHeaderType::Sounds,
+        path_data_offset: path_data_offset as u32,
+        array_offset: array_offset as u32,
+        count: array.len() as u32,
+        ..Default::default()
+    }.write::<LittleEndian>(&mut data, 0, header_size).unwrap();
+
+    let map = ResourceMap::from_data(data.clone()).unwrap();
+    for (path, bytes) in resources {
+        assert_eq!(map.get_by_path(path).unwrap().get_data(), bytes);
+    }
+
+    let rebuilt = ResourceMapBuilder::from_resource_map(&map, 1).unwrap();
+    assert_eq!(rebuilt.resource_type(), ResourceMapType::Sounds);
+    assert_eq!(rebuilt.build().unwrap().into_data(), data);
+}
+
+#[test]
+fn resource_map_duplicates() {
+    let mut builder = ResourceMapBuilder::new(ResourceMapType::Sounds, 1).unwrap();
+    assert_eq!(builder.add("a", &[1]).unwrap(), 0);
+    assert_eq!(builder.add("b", &[2]).unwrap(), 1);
+    assert_eq!(builder.add("a", &[1]).unwrap(), 0);
+    assert!(builder.add("a", &[2]).is_err());
+    assert_eq!(builder.index_of("b"), Some(1));
+    assert_eq!(builder.index_of("c"), None);
+}
+
+#[test]
+fn build_resource_maps_from_tags() {
+    let engine = ALL_SUPPORTED_ENGINES.iter().find(|e| e.resource_maps.is_some_and(|r| r.externally_indexed_tags)).unwrap();
+
+    let mut bitmap = Bitmap::default();
+    bitmap.processed_pixel_data.bytes = (0..24).collect();
+    for (offset, size, width) in [(0, 16, 4), (16, 8, 2)] {
+        bitmap.bitmap_data.items.push(BitmapData {
+            width,
+            height: 4,
+            depth: 1,
+            format: BitmapDataFormat::A8,
+            pixel_data_offset: offset,
+            pixel_data_size: size,
+            ..Default::default()
+        });
+    }
+    let bitmap_path = TagPath::from_path("ui\\shell\\bitmaps\\background.bitmap").unwrap();
+
+    let mut bitmaps = ResourceMapBuilder::new(ResourceMapType::Bitmaps, 0x1000).unwrap();
+    assert_eq!(bitmaps.add_tag(&bitmap_path, &bitmap, engine).unwrap(), [0, 1]);
+
+    // Pixel data is padded like it is in cache files
+    let padded_pixels = |range: std::ops::Range<usize>| {
+        let mut pixels = bitmap.processed_pixel_data.bytes[range].to_vec();
+        pixels.resize(pixels.len() + calculate_padding_for_alignment(pixels.len(), engine.bitmap_options.alignment), 0);
+        pixels
+    };
+
+    let mut sound = Sound::default();
+    sound.pitch_ranges.items.push(Default::default());
+    let permutations = &mut sound.pitch_ranges.items[0].permutations.items;
+    for (format, samples) in [(SoundFormat::PCM, vec![1, 2, 3, 4]), (SoundFormat::OggVorbis, vec![5, 6])] {
+        permutations.push(Default::default());
+        let permutation = permutations.last_mut().unwrap();
+        permutation.format = format;
+        permutation.samples.bytes = samples;
+    }
+    let sound_path = TagPath::from_path("sound\\sfx\\ui\\cursor.sound").unwrap();
+
+    // Each resource map only takes its own tags
+    assert!(bitmaps.add_tag(&sound_path, &sound, engine).is_err());
+
+    let mut sounds = ResourceMapBuilder::new(ResourceMapType::Sounds, 1).unwrap();
+    assert_eq!(sounds.add_tag(&sound_path, &sound, engine).unwrap(), [0, 1]);
+
+    let mut strings = UnicodeStringList::default();
+    strings.strings = ["first", "second", ""].iter().map(|s| UnicodeStringListString { string: UTF16String::from_str(s) }).collect();
+    let strings_path = TagPath::from_path("ui\\shell\\strings\\main_menu.unicode_string_list").unwrap();
+
+    let mut loc = ResourceMapBuilder::new(ResourceMapType::Loc, 1).unwrap();
+    assert!(loc.add_tag(&bitmap_path, &bitmap, engine).is_err());
+    assert_eq!(loc.add_tag(&strings_path, &strings, engine).unwrap(), [0]);
+
+    for (builder, resource_type, expected) in [
+        (bitmaps, ResourceMapType::Bitmaps, vec![("ui\\shell\\bitmaps\\background__0", padded_pixels(0..16)), ("ui\\shell\\bitmaps\\background__1", padded_pixels(16..24))]),
+
+        // 16-bit PCM is stored little endian
+        (sounds, ResourceMapType::Sounds, vec![("sound\\sfx\\ui\\cursor__0__0", vec![2, 1, 4, 3]), ("sound\\sfx\\ui\\cursor__0__1", vec![5, 6])])
+    ] {
+        let alignment = builder.alignment;
+        let data = builder.build().unwrap().into_data();
+
+        // Parse it back from the written data, and the type should be kept
+        let map = ResourceMap::from_data(data).unwrap();
+        assert_eq!(map.len(), expected.len());
+        for (path, bytes) in expected {
+            let resource = map.get_by_path(path).unwrap();
+            assert_eq!(resource.get_data(), bytes);
+            assert_eq!(resource.get_data_offset() % alignment, 0);
+        }
+
+        let rebuilt = ResourceMapBuilder::from_resource_map(&map, alignment).unwrap();
+        assert_eq!(rebuilt.resource_type(), resource_type);
+        assert_eq!(map.data(), rebuilt.build().unwrap().data());
+    }
+
+    // Loc tags are stored with the base struct at the start, and pointers are relative to the resource
+    let map = loc.build().unwrap();
+    assert_eq!(map.len(), 1);
+    let data = map.get_by_path("ui\\shell\\strings\\main_menu").unwrap().get_data();
+    assert_eq!(u32::from_le_bytes(data[0..4].try_into().unwrap()), 3);
+    let address = u32::from_le_bytes(data[4..8].try_into().unwrap()) as usize;
+    assert!(address > 0 && address < data.len());
+
+    let rebuilt = ResourceMapBuilder::from_resource_map(&map, 1).unwrap();
+    assert_eq!(rebuilt.resource_type(), ResourceMapType::Loc);
+    assert_eq!(map.data(), rebuilt.build().unwrap().data());
+}
@@ -0,0 +1,60 @@
+use definitions::ScenarioType;
+use primitives::byteorder::LittleEndian;
+use primitives::engine::EngineCompressionType;
+use primitives::primitive::String32;
+use ringhopper_engines::ALL_SUPPORTED_ENGINES;
+use super::*;
+
+fn make_test_map(engine: &Engine) -> Vec<u8> {
+    let body: Vec<u8> = (0..0x10000u32).map(|i| (i / 7 % 251) as u8).collect();
+    let file_size = CACHE_FILE_HEADER_LEN + body.len();
+
+    let header = ParsedCacheFileHeader {
+        name: String32::from_str("compress").unwrap(),
+        build: String32::from_str("").unwrap(),
+        cache_version: engine.cache_file_version,
+        tag_data_offset: CACHE_FILE_HEADER_LEN,
+        tag_data_size: body.len(),
+        decompressed_size: file_size,
+        compression_padding: 0,
+        map_type: ScenarioType::Multiplayer,
+        crc32: 0
+    }.into_header_for_engine(engine);
+
+    let mut data = Vec::with_capacity(file_size);
+    data.extend_from_slice(&header.as_bytes::<LittleEndian>());
+    data.extend_from_slice(&body);
+    data
+}
+
+#[test]
+fn compress_decompress_round_trip() {
+    let engines = ALL_SUPPORTED_ENGINES.iter().filter(|e| e.compression_type != EngineCompressionType::Uncompressed);
+    for engine in engines {
+        let map = make_test_map(engine);
+        let header = ParsedCacheFileHeader::read_from_map_data(&map).unwrap();
+        assert!(!is_map_data_compressed(&map, &header, engine));
+
+        let compressed = compress_map_data(map.clone(), engine).unwrap();
+        assert!(compressed.len() < map.len());
+        assert_eq!(compressed.len() % COMPRESSED_MAP_ALIGNMENT, 0);
+
+        let compressed_header = ParsedCacheFileHeader::read_from_map_data(&compressed).unwrap();
+        assert_eq!(compressed_header.decompressed_size, map.len());
+        assert!(is_map_data_compressed(&compressed, &compressed_header, engine));
+
+        // The header should be restored too, including the compression padding
+        let decompressed = decompress_map_data(compressed, &compressed_header, engine).unwrap();
+        assert_eq!(decompressed, map);
+        assert!(!is_map_data_compressed(&decompressed, &compressed_header, engine));
+    }
+}
+
+#[test]
+fn uncompressed_engines_are_never_compressed() {
+    let engine = ALL_SUPPORTED_ENGINES.iter().find(|e| e.compression_type == EngineCompressionType::Uncompressed).unwrap();
+    let map = make_test_map(engine);
+    let header = ParsedCacheFileHeader::read_from_map_data(&map).unwrap();
+    assert!(!is_map_data_compressed(&map, &header, engine));
+    assert_eq!(compress_map_data(map.clone(), engine).unwrap(), map);
+}
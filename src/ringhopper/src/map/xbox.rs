@@ -9,6 +9,7 @@ use primitives::primitive::{ID, TagPath};
 use primitives::tag::{ParseStrictness, PrimaryTagStructDyn};
 
 use crate::map::{BSPDomain, extract_tag_from_map, MapTagTree, SizeRange};
+use crate::tag::tree::TagDirectoryIndex;
 
 pub struct XboxCacheFile {
     name: String,
@@ -21,6 +22,7 @@ pub struct XboxCacheFile {
     base_memory_address: usize,
     tags: Vec<Option<Tag>>,
     ids: HashMap<TagPath, ID>,
+    directory_index: TagDirectoryIndex,
     scenario_tag: ID,
     scenario_tag_data: Scenario,
     uncompressed_size: usize,
@@ -46,6 +48,7 @@ impl XboxCacheFile {
             tags: Default::default(),
             scenario_tag: ID::null(),
             ids: Default::default(),
+            directory_index: Default::default(),
             scenario_tag_data: Scenario::default()
         };
 
@@ -64,6 +67,7 @@ impl XboxCacheFile {
         map.scenario_tag = tag_data_header.cache_file_tag_data_header.scenario_tag;
 
         let (tags, _cached_tags, ids) = super::util::get_all_tags(&mut map, tag_address, tag_count, CacheFileTagDataHeaderInternalModels::simple_size())?;
+        map.directory_index = TagDirectoryIndex::new(ids.keys());
         map.ids = ids;
         map.tags = tags;
 
@@ -152,6 +156,10 @@ impl Map for XboxCacheFile {
         self.get_tag_by_id(*self.ids.get(path)?)
     }
 
+    fn get_tag_id(&self, path: &TagPath) -> Option<ID> {
+        self.ids.get(path).copied()
+    }
+
     fn get_scenario_tag(&self) -> &Tag {
         self.get_tag_by_id(self.scenario_tag).unwrap()
     }
@@ -164,4 +172,8 @@ impl MapTagTree for XboxCacheFile {
     fn get_scenario_type(&self) -> ScenarioType {
         self.scenario_tag_data._type
     }
+
+    fn get_directory_index(&self) -> &TagDirectoryIndex {
+        &self.directory_index
+    }
 }
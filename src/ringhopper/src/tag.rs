@@ -10,12 +10,16 @@ pub mod dependency;
 pub mod tag_collection;
 pub mod nudge;
 pub mod compare;
+pub mod json;
+pub mod edit;
 pub mod convert;
 pub mod model;
 pub mod model_animations;
 pub mod scenario;
 pub mod object;
 pub mod scenario_structure_bsp;
+pub mod collision_bsp;
+pub mod model_collision_geometry;
 pub mod bitmap;
 pub mod archive;
 pub mod recover;
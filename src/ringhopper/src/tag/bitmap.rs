@@ -1,12 +1,20 @@
 #[cfg(test)]
 mod test;
 mod swizzle;
+mod compile;
+mod codec;
+mod decode;
 
 pub use swizzle::*;
+pub use compile::*;
+pub use codec::*;
+pub use decode::*;
 
 use std::iter::FusedIterator;
 use std::num::NonZeroUsize;
-use flate2::FlushDecompress;
+use std::io::Write;
+use flate2::{Compression, FlushDecompress};
+use flate2::write::ZlibEncoder;
 use definitions::{Bitmap, BitmapData, BitmapDataFormat, BitmapDataType};
 use primitives::byteorder::{BigEndian, LittleEndian};
 use primitives::error::{Error, OverflowCheck, RinghopperResult};
@@ -359,3 +367,25 @@ pub fn extract_compressed_color_plate_data(bitmap: &Bitmap) -> RinghopperResult<
 
     Ok(Some(Image { width, height, data }))
 }
+
+/// Compress an [`Image`] into color plate data.
+///
+/// This is the inverse of [`extract_compressed_color_plate_data`].
+pub fn compress_color_plate_data(image: &Image) -> RinghopperResult<Vec<u8>> {
+    let pixel_count = image.width.mul_overflow_checked(image.height)?;
+    let uncompressed_size = pixel_count.mul_overflow_checked(Pixel32::simple_size())?;
+    let uncompressed_size_u32 = u32::try_from(uncompressed_size).map_err(|_| Error::ArrayLimitExceeded)?;
+
+    let mut uncompressed_data = vec![0u8; uncompressed_size];
+    for (index, pixel) in image.data.iter().take(pixel_count).enumerate() {
+        let offset = index * Pixel32::simple_size();
+        pixel.write::<LittleEndian>(&mut uncompressed_data, offset, offset + Pixel32::simple_size())?;
+    }
+
+    let mut data = vec![0u8; u32::simple_size()];
+    uncompressed_size_u32.write::<BigEndian>(&mut data, 0, data.len())?;
+
+    let mut compressor = ZlibEncoder::new(data, Compression::best());
+    compressor.write_all(&uncompressed_data).map_err(|e| Error::Other(format!("zlib error: {e}")))?;
+    compressor.finish().map_err(|e| Error::Other(format!("zlib error: {e}")))
+}
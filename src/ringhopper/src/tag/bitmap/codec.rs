@@ -0,0 +1,248 @@
+use definitions::BitmapDataFormat;
+use primitives::error::{Error, OverflowCheck, RinghopperResult};
+use primitives::primitive::{Pixel32, Pixel32Bytes};
+
+mod dxt;
+mod bc7;
+
+/// Pixels in a 4x4 block stored as RGBA, row by row.
+type Block = [[u8; 4]; 16];
+
+/// Quality level to use when block compressing pixels.
+///
+/// Higher levels are slower but generally produce less error.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum BlockCompressionQuality {
+    /// Use a diagonal of the bounding box of the block's colors as endpoints.
+    Fast,
+
+    /// Fit endpoints along the principal axis of the block's colors.
+    #[default]
+    Normal,
+
+    /// Fit endpoints along the principal axis, then iteratively refine them.
+    Best
+}
+
+/// Decode block compressed data into pixels.
+///
+/// `width` and `height` do not need to be a multiple of the block size; any excess pixels in the edge blocks are
+/// discarded.
+///
+/// Returns `Err` if `format` is not block compressed or `data` is too small.
+pub fn decode_block_compressed_pixels(format: BitmapDataFormat, width: usize, height: usize, data: &[u8]) -> RinghopperResult<Vec<Pixel32>> {
+    let block_size = compressed_block_size(format)?;
+    let (blocks_wide, blocks_tall) = (width.div_ceil(4), height.div_ceil(4));
+    let expected_size = blocks_wide.mul_overflow_checked(blocks_tall)?.mul_overflow_checked(block_size)?;
+    if data.len() < expected_size {
+        return Err(Error::InvalidTagData(format!("{width}x{height} {format} data requires {expected_size} bytes, but only {} are present", data.len())))
+    }
+
+    let mut pixels = vec![Pixel32::default(); width * height];
+    for (index, block_data) in data[..expected_size].chunks_exact(block_size).enumerate() {
+        let block = match format {
+            BitmapDataFormat::DXT1 => dxt::decode_dxt1(block_data.try_into().unwrap()),
+            BitmapDataFormat::DXT3 => dxt::decode_dxt3(block_data.try_into().unwrap()),
+            BitmapDataFormat::DXT5 => dxt::decode_dxt5(block_data.try_into().unwrap()),
+            BitmapDataFormat::BC7 => bc7::decode_bc7(block_data.try_into().unwrap()),
+            _ => unreachable!()
+        };
+
+        let bx = (index % blocks_wide) * 4;
+        let by = (index / blocks_wide) * 4;
+        for y in 0..4.min(height - by) {
+            for x in 0..4.min(width - bx) {
+                let [red, green, blue, alpha] = block[x + y * 4];
+                pixels[bx + x + (by + y) * width] = Pixel32Bytes { alpha, red, green, blue }.into();
+            }
+        }
+    }
+
+    Ok(pixels)
+}
+
+/// Encode pixels into block compressed data.
+///
+/// `width` and `height` do not need to be a multiple of the block size; edge blocks are padded by repeating the
+/// last row and column.
+///
+/// For DXT1, pixels with less than 50% opacity are encoded as fully transparent, and all other pixels are encoded
+/// as opaque.
+///
+/// Returns `Err` if `format` is not block compressed or `pixels` is not `width * height` pixels.
+pub fn encode_block_compressed_pixels(format: BitmapDataFormat, width: usize, height: usize, pixels: &[Pixel32], quality: BlockCompressionQuality) -> RinghopperResult<Vec<u8>> {
+    let block_size = compressed_block_size(format)?;
+    if pixels.len() != width.mul_overflow_checked(height)? {
+        return Err(Error::InvalidTagData(format!("expected {width}x{height} pixels, got {} pixels", pixels.len())))
+    }
+
+    let (blocks_wide, blocks_tall) = (width.div_ceil(4), height.div_ceil(4));
+    let mut output = Vec::with_capacity(blocks_wide.mul_overflow_checked(blocks_tall)?.mul_overflow_checked(block_size)?);
+    if pixels.is_empty() {
+        return Ok(output)
+    }
+
+    for by in (0..height).step_by(4) {
+        for bx in (0..width).step_by(4) {
+            let mut block: Block = [[0; 4]; 16];
+            for (i, pixel) in block.iter_mut().enumerate() {
+                let x = (bx + i % 4).min(width - 1);
+                let y = (by + i / 4).min(height - 1);
+                let bytes = Pixel32Bytes::from(pixels[x + y * width]);
+                *pixel = [bytes.red, bytes.green, bytes.blue, bytes.alpha];
+            }
+
+            match format {
+                BitmapDataFormat::DXT1 => output.extend_from_slice(&dxt::encode_dxt1(&block, quality)),
+                BitmapDataFormat::DXT3 => output.extend_from_slice(&dxt::encode_dxt3(&block, quality)),
+                BitmapDataFormat::DXT5 => output.extend_from_slice(&dxt::encode_dxt5(&block, quality)),
+                BitmapDataFormat::BC7 => output.extend_from_slice(&bc7::encode_bc7(&block, quality)),
+                _ => unreachable!()
+            }
+        }
+    }
+
+    Ok(output)
+}
+
+fn compressed_block_size(format: BitmapDataFormat) -> RinghopperResult<usize> {
+    match format {
+        BitmapDataFormat::DXT1 => Ok(8),
+        BitmapDataFormat::DXT3 | BitmapDataFormat::DXT5 | BitmapDataFormat::BC7 => Ok(16),
+        _ => Err(Error::InvalidTagData(format!("{format} is not a block compressed format")))
+    }
+}
+
+/// Get the squared distance between two colors over the first `N` channels.
+fn distance<const N: usize>(a: &[u8; 4], b: &[u8; 4]) -> u32 {
+    a.iter().zip(b.iter()).take(N).map(|(a, b)| {
+        let d = *a as i32 - *b as i32;
+        (d * d) as u32
+    }).sum()
+}
+
+/// Find the endpoints of a line approximating the given colors over the first `N` channels.
+///
+/// The endpoints are returned as floating point values, so they may still need to be clamped and quantized.
+fn fit_endpoints<const N: usize>(colors: &[[u8; 4]], quality: BlockCompressionQuality) -> ([f32; N], [f32; N]) {
+    let mut min = [255.0f32; N];
+    let mut max = [0.0f32; N];
+    let mut mean = [0.0f32; N];
+    for color in colors {
+        for c in 0..N {
+            let v = color[c] as f32;
+            min[c] = min[c].min(v);
+            max[c] = max[c].max(v);
+            mean[c] += v;
+        }
+    }
+
+    if colors.is_empty() {
+        return ([0.0; N], [0.0; N])
+    }
+
+    for m in &mut mean {
+        *m /= colors.len() as f32;
+    }
+
+    // Use the diagonal of the bounding box that follows the channel with the widest range.
+    let primary = (0..N).max_by(|a, b| (max[*a] - min[*a]).total_cmp(&(max[*b] - min[*b]))).unwrap();
+    for c in 0..N {
+        let covariance: f32 = colors.iter().map(|color| (color[c] as f32 - mean[c]) * (color[primary] as f32 - mean[primary])).sum();
+        if covariance < 0.0 {
+            std::mem::swap(&mut min[c], &mut max[c]);
+        }
+    }
+
+    if quality == BlockCompressionQuality::Fast {
+        return (max, min)
+    }
+
+    // Find the principal axis via power iteration on the covariance matrix.
+    let mut covariance = [[0.0f32; N]; N];
+    for color in colors {
+        for a in 0..N {
+            for b in 0..N {
+                covariance[a][b] += (color[a] as f32 - mean[a]) * (color[b] as f32 - mean[b]);
+            }
+        }
+    }
+
+    // Start from the bounding box diagonal since it is usually close to the principal axis.
+    let mut axis = [0.0f32; N];
+    for c in 0..N {
+        axis[c] = max[c] - min[c];
+    }
+    for _ in 0..8 {
+        let mut next = [0.0f32; N];
+        for a in 0..N {
+            for b in 0..N {
+                next[a] += covariance[a][b] * axis[b];
+            }
+        }
+        let length = next.iter().map(|v| v * v).sum::<f32>().sqrt();
+        if length < f32::EPSILON {
+            break
+        }
+        for c in 0..N {
+            axis[c] = next[c] / length;
+        }
+    }
+
+    let length = axis.iter().map(|v| v * v).sum::<f32>().sqrt();
+    if length < f32::EPSILON {
+        return (mean, mean)
+    }
+
+    let mut low = f32::MAX;
+    let mut high = f32::MIN;
+    for color in colors {
+        let projection: f32 = (0..N).map(|c| (color[c] as f32 - mean[c]) * axis[c]).sum::<f32>() / length;
+        low = low.min(projection);
+        high = high.max(projection);
+    }
+
+    let mut start = [0.0f32; N];
+    let mut end = [0.0f32; N];
+    for c in 0..N {
+        start[c] = (mean[c] + axis[c] / length * high).clamp(0.0, 255.0);
+        end[c] = (mean[c] + axis[c] / length * low).clamp(0.0, 255.0);
+    }
+    (start, end)
+}
+
+/// Solve for the endpoints that best fit the given colors with the given interpolation weights via least squares.
+///
+/// Each weight is the fraction of the second endpoint used for the color. Returns `None` if the system is singular.
+fn refine_endpoints<const N: usize>(colors: &[[u8; 4]], weights: &[f32]) -> Option<([f32; N], [f32; N])> {
+    let mut aa = 0.0f32;
+    let mut ab = 0.0f32;
+    let mut bb = 0.0f32;
+    let mut ax = [0.0f32; N];
+    let mut bx = [0.0f32; N];
+
+    for (color, w) in colors.iter().zip(weights) {
+        let a = 1.0 - w;
+        let b = *w;
+        aa += a * a;
+        ab += a * b;
+        bb += b * b;
+        for c in 0..N {
+            ax[c] += a * color[c] as f32;
+            bx[c] += b * color[c] as f32;
+        }
+    }
+
+    let determinant = aa * bb - ab * ab;
+    if determinant.abs() < f32::EPSILON {
+        return None
+    }
+
+    let mut start = [0.0f32; N];
+    let mut end = [0.0f32; N];
+    for c in 0..N {
+        start[c] = ((ax[c] * bb - bx[c] * ab) / determinant).clamp(0.0, 255.0);
+        end[c] = ((bx[c] * aa - ax[c] * ab) / determinant).clamp(0.0, 255.0);
+    }
+    Some((start, end))
+}
@@ -0,0 +1,484 @@
+use super::{Block, BlockCompressionQuality, distance, fit_endpoints, refine_endpoints};
+
+struct ModeInfo {
+    subsets: usize,
+    partition_bits: u32,
+    rotation_bits: u32,
+    index_selection_bits: u32,
+    color_bits: u32,
+    alpha_bits: u32,
+    endpoint_pbits: bool,
+    shared_pbits: bool,
+    index_bits: u32,
+    secondary_index_bits: u32
+}
+
+const fn mode(subsets: usize, partition_bits: u32, rotation_bits: u32, index_selection_bits: u32, color_bits: u32, alpha_bits: u32, endpoint_pbits: bool, shared_pbits: bool, index_bits: u32, secondary_index_bits: u32) -> ModeInfo {
+    ModeInfo { subsets, partition_bits, rotation_bits, index_selection_bits, color_bits, alpha_bits, endpoint_pbits, shared_pbits, index_bits, secondary_index_bits }
+}
+
+const MODES: [ModeInfo; 8] = [
+    mode(3, 4, 0, 0, 4, 0, true, false, 3, 0),
+    mode(2, 6, 0, 0, 6, 0, false, true, 3, 0),
+    mode(3, 6, 0, 0, 5, 0, false, false, 2, 0),
+    mode(2, 6, 0, 0, 7, 0, true, false, 2, 0),
+    mode(1, 0, 2, 1, 5, 6, false, false, 2, 3),
+    mode(1, 0, 2, 0, 7, 8, false, false, 2, 2),
+    mode(1, 0, 0, 0, 7, 7, true, false, 4, 0),
+    mode(2, 6, 0, 0, 5, 5, true, false, 2, 0)
+];
+
+const WEIGHTS_2: [u32; 4] = [0, 21, 43, 64];
+const WEIGHTS_3: [u32; 8] = [0, 9, 18, 27, 37, 46, 55, 64];
+const WEIGHTS_4: [u32; 16] = [0, 4, 9, 13, 17, 21, 26, 30, 34, 38, 43, 47, 51, 55, 60, 64];
+
+/// Two-subset partitions; each bit is set if the pixel is in the second subset.
+const PARTITIONS_2: [u16; 64] = [
+    0xCCCC, 0x8888, 0xEEEE, 0xECC8, 0xC880, 0xFEEC, 0xFEC8, 0xEC80, 0xC800, 0xFFEC, 0xFE80, 0xE800, 0xFFE8, 0xFF00, 0xFFF0, 0xF000,
+    0xF710, 0x008E, 0x7100, 0x08CE, 0x008C, 0x7310, 0x3100, 0x8CCE, 0x088C, 0x3110, 0x6666, 0x366C, 0x17E8, 0x0FF0, 0x718E, 0x399C,
+    0xAAAA, 0xF0F0, 0x5A5A, 0x33CC, 0x3C3C, 0x55AA, 0x9696, 0xA55A, 0x73CE, 0x13C8, 0x324C, 0x3BDC, 0x6996, 0xC33C, 0x9966, 0x0660,
+    0x0272, 0x04E4, 0x4E40, 0x2720, 0xC936, 0x936C, 0x39C6, 0x639C, 0x9336, 0x9CC6, 0x817E, 0xE718, 0xCCF0, 0x0FCC, 0x7744, 0xEE22
+];
+
+/// Three-subset partitions; each pixel's subset is stored in two bits.
+const PARTITIONS_3: [u32; 64] = [
+    0xAA685050, 0x6A5A5040, 0x5A5A4200, 0x5450A0A8, 0xA5A50000, 0xA0A05050, 0x5555A0A0, 0x5A5A5050,
+    0xAA550000, 0xAA555500, 0xAAAA5500, 0x90909090, 0x94949494, 0xA4A4A4A4, 0xA9A59450, 0x2A0A4250,
+    0xA5945040, 0x0A425054, 0xA5A5A500, 0x55A0A0A0, 0xA8A85454, 0x6A6A4040, 0xA4A45000, 0x1A1A0500,
+    0x0050A4A4, 0xAAA59090, 0x14696914, 0x69691400, 0xA08585A0, 0xAA821414, 0x50A4A450, 0x6A5A0200,
+    0xA9A58000, 0x5090A0A8, 0xA8A09050, 0x24242424, 0x00AA5500, 0x24924924, 0x24499224, 0x50A50A50,
+    0x500AA550, 0xAAAA4444, 0x66660000, 0xA5A0A5A0, 0x50A050A0, 0x69286928, 0x44AAAA44, 0x66666600,
+    0xAA444444, 0x54A854A8, 0x95809580, 0x96969600, 0xA85454A8, 0x80959580, 0xAA141414, 0x96960000,
+    0xAAAA1414, 0xA05050A0, 0xA0A5A5A0, 0x96000000, 0x40804080, 0xA9A8A9A8, 0xAAAAAA44, 0x2A4A5254
+];
+
+const ANCHORS_2: [u8; 64] = [
+    15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15,
+    15, 2, 8, 2, 2, 8, 8, 15, 2, 8, 2, 2, 8, 8, 2, 2,
+    15, 15, 6, 8, 2, 8, 15, 15, 2, 8, 2, 2, 2, 15, 15, 6,
+    6, 2, 6, 8, 15, 15, 2, 2, 15, 15, 15, 15, 15, 2, 2, 15
+];
+
+const ANCHORS_3_SECOND: [u8; 64] = [
+    3, 3, 15, 15, 8, 3, 15, 15, 8, 8, 6, 6, 6, 5, 3, 3,
+    3, 3, 8, 15, 3, 3, 6, 10, 5, 8, 8, 6, 8, 5, 15, 15,
+    8, 15, 3, 5, 6, 10, 8, 15, 15, 3, 15, 5, 15, 15, 15, 15,
+    3, 15, 5, 5, 5, 8, 5, 10, 5, 10, 8, 13, 15, 12, 3, 3
+];
+
+const ANCHORS_3_THIRD: [u8; 64] = [
+    15, 8, 8, 3, 15, 15, 3, 8, 15, 15, 15, 15, 15, 15, 15, 8,
+    15, 8, 15, 3, 15, 8, 15, 8, 3, 15, 6, 10, 15, 15, 10, 8,
+    15, 3, 15, 10, 10, 8, 9, 10, 6, 15, 8, 15, 3, 6, 6, 8,
+    15, 3, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 3, 15, 15, 8
+];
+
+struct BitReader {
+    bits: u128,
+    position: u32
+}
+
+impl BitReader {
+    fn read(&mut self, count: u32) -> u32 {
+        if count == 0 {
+            return 0
+        }
+        let value = (self.bits >> self.position) & ((1u128 << count) - 1);
+        self.position += count;
+        value as u32
+    }
+}
+
+struct BitWriter {
+    bits: u128,
+    position: u32
+}
+
+impl BitWriter {
+    fn write(&mut self, value: u32, count: u32) {
+        debug_assert!(count == 32 || value < (1 << count));
+        self.bits |= (value as u128) << self.position;
+        self.position += count;
+    }
+}
+
+fn subset_of(subsets: usize, partition: usize, pixel: usize) -> usize {
+    match subsets {
+        1 => 0,
+        2 => ((PARTITIONS_2[partition] >> pixel) & 1) as usize,
+        3 => ((PARTITIONS_3[partition] >> (pixel * 2)) & 3) as usize,
+        _ => unreachable!()
+    }
+}
+
+fn is_anchor(subsets: usize, partition: usize, pixel: usize) -> bool {
+    pixel == 0 || match subsets {
+        2 => ANCHORS_2[partition] as usize == pixel,
+        3 => ANCHORS_3_SECOND[partition] as usize == pixel || ANCHORS_3_THIRD[partition] as usize == pixel,
+        _ => false
+    }
+}
+
+fn expand(value: u32, bits: u32) -> u8 {
+    let value = value << (8 - bits);
+    (value | (value >> bits)) as u8
+}
+
+fn interpolate(e0: u8, e1: u8, index: u32, index_bits: u32) -> u8 {
+    let weight = match index_bits {
+        2 => WEIGHTS_2[index as usize],
+        3 => WEIGHTS_3[index as usize],
+        4 => WEIGHTS_4[index as usize],
+        _ => unreachable!()
+    };
+    (((64 - weight) * e0 as u32 + weight * e1 as u32 + 32) >> 6) as u8
+}
+
+pub fn decode_bc7(data: &[u8; 16]) -> Block {
+    let mode_index = data[0].trailing_zeros() as usize;
+    if mode_index >= MODES.len() {
+        // Reserved mode; decodes to transparent black
+        return [[0; 4]; 16]
+    }
+
+    let info = &MODES[mode_index];
+    let mut reader = BitReader { bits: u128::from_le_bytes(*data), position: mode_index as u32 + 1 };
+
+    let partition = reader.read(info.partition_bits) as usize;
+    let rotation = reader.read(info.rotation_bits);
+    let index_selection = reader.read(info.index_selection_bits);
+
+    let mut endpoints = [[[0u32; 4]; 2]; 3];
+    for channel in 0..3 {
+        for subset in &mut endpoints[..info.subsets] {
+            for endpoint in subset.iter_mut() {
+                endpoint[channel] = reader.read(info.color_bits);
+            }
+        }
+    }
+    if info.alpha_bits > 0 {
+        for subset in &mut endpoints[..info.subsets] {
+            for endpoint in subset.iter_mut() {
+                endpoint[3] = reader.read(info.alpha_bits);
+            }
+        }
+    }
+
+    let mut pbits = [[0u32; 2]; 3];
+    if info.endpoint_pbits {
+        for subset in &mut pbits[..info.subsets] {
+            for p in subset.iter_mut() {
+                *p = reader.read(1);
+            }
+        }
+    }
+    else if info.shared_pbits {
+        for subset in &mut pbits[..info.subsets] {
+            let p = reader.read(1);
+            *subset = [p, p];
+        }
+    }
+
+    let has_pbits = info.endpoint_pbits || info.shared_pbits;
+    let mut colors = [[[0u8; 4]; 2]; 3];
+    for subset in 0..info.subsets {
+        for endpoint in 0..2 {
+            for channel in 0..4 {
+                let channel_bits = if channel == 3 { info.alpha_bits } else { info.color_bits };
+                colors[subset][endpoint][channel] = if channel_bits == 0 {
+                    255
+                }
+                else if has_pbits {
+                    expand((endpoints[subset][endpoint][channel] << 1) | pbits[subset][endpoint], channel_bits + 1)
+                }
+                else {
+                    expand(endpoints[subset][endpoint][channel], channel_bits)
+                };
+            }
+        }
+    }
+
+    let mut primary = [0u32; 16];
+    for (pixel, index) in primary.iter_mut().enumerate() {
+        *index = reader.read(info.index_bits - is_anchor(info.subsets, partition, pixel) as u32);
+    }
+    let mut secondary = [0u32; 16];
+    if info.secondary_index_bits > 0 {
+        for (pixel, index) in secondary.iter_mut().enumerate() {
+            *index = reader.read(info.secondary_index_bits - (pixel == 0) as u32);
+        }
+    }
+
+    let mut block = [[0u8; 4]; 16];
+    for (pixel, output) in block.iter_mut().enumerate() {
+        let [e0, e1] = colors[subset_of(info.subsets, partition, pixel)];
+
+        let (color_index, color_bits, alpha_index, alpha_bits) = if info.secondary_index_bits == 0 {
+            (primary[pixel], info.index_bits, primary[pixel], info.index_bits)
+        }
+        else if index_selection == 0 {
+            (primary[pixel], info.index_bits, secondary[pixel], info.secondary_index_bits)
+        }
+        else {
+            (secondary[pixel], info.secondary_index_bits, primary[pixel], info.index_bits)
+        };
+
+        for channel in 0..3 {
+            output[channel] = interpolate(e0[channel], e1[channel], color_index, color_bits);
+        }
+        output[3] = interpolate(e0[3], e1[3], alpha_index, alpha_bits);
+
+        match rotation {
+            1 => output.swap(0, 3),
+            2 => output.swap(1, 3),
+            3 => output.swap(2, 3),
+            _ => ()
+        }
+    }
+
+    block
+}
+
+/// Quantize an endpoint to 7 bits per channel plus a p-bit, returning the channels, p-bit, and reconstructed color.
+fn quantize_endpoint(endpoint: &[f32; 4], pbit: Option<u32>) -> ([u32; 4], u32, [u8; 4]) {
+    let candidates = match pbit {
+        Some(p) => p..=p,
+        None => 0..=1
+    };
+
+    candidates.map(|p| {
+        let mut channels = [0u32; 4];
+        let mut color = [0u8; 4];
+        let mut error = 0.0;
+        for c in 0..4 {
+            channels[c] = ((endpoint[c] - p as f32) / 2.0).round().clamp(0.0, 127.0) as u32;
+            color[c] = ((channels[c] << 1) | p) as u8;
+            error += (color[c] as f32 - endpoint[c]).powi(2);
+        }
+        (channels, p, color, error)
+    })
+    .min_by(|a, b| a.3.total_cmp(&b.3))
+    .map(|(channels, p, color, _)| (channels, p, color))
+    .unwrap()
+}
+
+struct Mode6Candidate {
+    endpoints: [[u32; 4]; 2],
+    pbits: [u32; 2],
+    indices: [u32; 16],
+    error: u32
+}
+
+fn evaluate_mode_6(block: &Block, start: &[f32; 4], end: &[f32; 4], pbits: [Option<u32>; 2]) -> Mode6Candidate {
+    let (e0, p0, c0) = quantize_endpoint(start, pbits[0]);
+    let (e1, p1, c1) = quantize_endpoint(end, pbits[1]);
+
+    let mut palette = [[0u8; 4]; 16];
+    for (index, color) in palette.iter_mut().enumerate() {
+        for channel in 0..4 {
+            color[channel] = interpolate(c0[channel], c1[channel], index as u32, 4);
+        }
+    }
+
+    let mut indices = [0u32; 16];
+    let mut error = 0;
+    for (pixel, index) in indices.iter_mut().enumerate() {
+        let (best, best_error) = palette
+            .iter()
+            .enumerate()
+            .map(|(i, p)| (i, distance::<4>(&block[pixel], p)))
+            .min_by_key(|(_, e)| *e)
+            .unwrap();
+        *index = best as u32;
+        error += best_error;
+    }
+
+    Mode6Candidate { endpoints: [e0, e1], pbits: [p0, p1], indices, error }
+}
+
+fn encode_mode_6(block: &Block, quality: BlockCompressionQuality) -> Mode6Candidate {
+    // Opaque blocks need both p-bits set for alpha to decode as exactly 255.
+    let opaque = block.iter().all(|p| p[3] == 255);
+    let free_pbits = if opaque { [Some(1), Some(1)] } else { [None, None] };
+
+    let (start, end) = fit_endpoints::<4>(block, quality);
+    let mut best = evaluate_mode_6(block, &start, &end, free_pbits);
+
+    if quality == BlockCompressionQuality::Best {
+        let mut endpoints = (start, end);
+        for _ in 0..4 {
+            let weights: Vec<f32> = best.indices.iter().map(|i| WEIGHTS_4[*i as usize] as f32 / 64.0).collect();
+            let Some(refined) = refine_endpoints::<4>(block, &weights) else { break };
+            let candidate = evaluate_mode_6(block, &refined.0, &refined.1, free_pbits);
+            if candidate.error >= best.error {
+                break
+            }
+            best = candidate;
+            endpoints = refined;
+        }
+
+        for pbits in [[0, 0], [0, 1], [1, 0], [1, 1]] {
+            if opaque && pbits != [1, 1] {
+                continue
+            }
+            let candidate = evaluate_mode_6(block, &endpoints.0, &endpoints.1, [Some(pbits[0]), Some(pbits[1])]);
+            if candidate.error < best.error {
+                best = candidate;
+            }
+        }
+    }
+
+    // The most significant bit of the anchor index is implied to be 0.
+    if best.indices[0] >= 8 {
+        best.endpoints.swap(0, 1);
+        best.pbits.swap(0, 1);
+        for index in &mut best.indices {
+            *index = 15 - *index;
+        }
+    }
+
+    best
+}
+
+fn write_mode_6(candidate: &Mode6Candidate) -> [u8; 16] {
+    let mut writer = BitWriter { bits: 0, position: 0 };
+    writer.write(1 << 6, 7);
+    for channel in 0..4 {
+        for endpoint in &candidate.endpoints {
+            writer.write(endpoint[channel], 7);
+        }
+    }
+    for pbit in candidate.pbits {
+        writer.write(pbit, 1);
+    }
+    for (pixel, index) in candidate.indices.iter().enumerate() {
+        writer.write(*index, if pixel == 0 { 3 } else { 4 });
+    }
+    debug_assert_eq!(writer.position, 128);
+
+    writer.bits.to_le_bytes()
+}
+
+struct Mode5Candidate {
+    color_endpoints: [[u32; 3]; 2],
+    alpha_endpoints: [u32; 2],
+    color_indices: [u32; 16],
+    alpha_indices: [u32; 16],
+    error: u32
+}
+
+fn evaluate_mode_5_color(block: &Block, start: &[f32; 3], end: &[f32; 3]) -> ([[u32; 3]; 2], [u32; 16], u32) {
+    let mut endpoints = [[0u32; 3]; 2];
+    let mut colors = [[0u8; 4]; 2];
+    for (endpoint, (quantized, color)) in [start, end].into_iter().zip(endpoints.iter_mut().zip(colors.iter_mut())) {
+        for c in 0..3 {
+            quantized[c] = (endpoint[c] * 127.0 / 255.0).round().clamp(0.0, 127.0) as u32;
+            color[c] = expand(quantized[c], 7);
+        }
+    }
+
+    let mut palette = [[0u8; 4]; 4];
+    for (index, color) in palette.iter_mut().enumerate() {
+        for c in 0..3 {
+            color[c] = interpolate(colors[0][c], colors[1][c], index as u32, 2);
+        }
+    }
+
+    let mut indices = [0u32; 16];
+    let mut error = 0;
+    for (pixel, index) in indices.iter_mut().enumerate() {
+        let (best, best_error) = palette
+            .iter()
+            .enumerate()
+            .map(|(i, p)| (i, distance::<3>(&block[pixel], p)))
+            .min_by_key(|(_, e)| *e)
+            .unwrap();
+        *index = best as u32;
+        error += best_error;
+    }
+
+    (endpoints, indices, error)
+}
+
+fn encode_mode_5(block: &Block, quality: BlockCompressionQuality) -> Mode5Candidate {
+    let (start, end) = fit_endpoints::<3>(block, quality);
+    let (mut color_endpoints, mut color_indices, mut color_error) = evaluate_mode_5_color(block, &start, &end);
+
+    if quality == BlockCompressionQuality::Best {
+        for _ in 0..4 {
+            let weights: Vec<f32> = color_indices.iter().map(|i| WEIGHTS_2[*i as usize] as f32 / 64.0).collect();
+            let Some((start, end)) = refine_endpoints::<3>(block, &weights) else { break };
+            let candidate = evaluate_mode_5_color(block, &start, &end);
+            if candidate.2 >= color_error {
+                break
+            }
+            (color_endpoints, color_indices, color_error) = candidate;
+        }
+    }
+
+    // Alpha is stored at full precision, so the extremes can be used directly.
+    let alpha_max = block.iter().map(|p| p[3]).max().unwrap();
+    let alpha_min = block.iter().map(|p| p[3]).min().unwrap();
+    let mut alpha_endpoints = [alpha_max as u32, alpha_min as u32];
+    let mut alpha_indices = [0u32; 16];
+    let mut alpha_error = 0;
+    for (pixel, index) in alpha_indices.iter_mut().enumerate() {
+        let (best, best_error) = (0..4)
+            .map(|i| (i, (block[pixel][3] as i32 - interpolate(alpha_max, alpha_min, i, 2) as i32).pow(2) as u32))
+            .min_by_key(|(_, e)| *e)
+            .unwrap();
+        *index = best;
+        alpha_error += best_error;
+    }
+
+    // The most significant bit of each anchor index is implied to be 0.
+    if color_indices[0] >= 2 {
+        color_endpoints.swap(0, 1);
+        for index in &mut color_indices {
+            *index = 3 - *index;
+        }
+    }
+    if alpha_indices[0] >= 2 {
+        alpha_endpoints.swap(0, 1);
+        for index in &mut alpha_indices {
+            *index = 3 - *index;
+        }
+    }
+
+    Mode5Candidate { color_endpoints, alpha_endpoints, color_indices, alpha_indices, error: color_error + alpha_error }
+}
+
+fn write_mode_5(candidate: &Mode5Candidate) -> [u8; 16] {
+    let mut writer = BitWriter { bits: 0, position: 0 };
+    writer.write(1 << 5, 6);
+    writer.write(0, 2);
+    for channel in 0..3 {
+        for endpoint in &candidate.color_endpoints {
+            writer.write(endpoint[channel], 7);
+        }
+    }
+    for endpoint in candidate.alpha_endpoints {
+        writer.write(endpoint, 8);
+    }
+    for indices in [&candidate.color_indices, &candidate.alpha_indices] {
+        for (pixel, index) in indices.iter().enumerate() {
+            writer.write(*index, if pixel == 0 { 1 } else { 2 });
+        }
+    }
+    debug_assert_eq!(writer.position, 128);
+
+    writer.bits.to_le_bytes()
+}
+
+/// Encode a block with whichever of mode 5 (separate color and alpha) or mode 6 (combined color and alpha) has the
+/// least error.
+pub fn encode_bc7(block: &Block, quality: BlockCompressionQuality) -> [u8; 16] {
+    let mode_5 = encode_mode_5(block, quality);
+    let mode_6 = encode_mode_6(block, quality);
+    if mode_5.error < mode_6.error {
+        write_mode_5(&mode_5)
+    }
+    else {
+        write_mode_6(&mode_6)
+    }
+}
@@ -0,0 +1,286 @@
+use super::{Block, BlockCompressionQuality, distance, fit_endpoints, refine_endpoints};
+
+/// Pixels with alpha below this are treated as transparent in DXT1.
+const DXT1_ALPHA_THRESHOLD: u8 = 128;
+
+pub fn decode_dxt1(data: &[u8; 8]) -> Block {
+    decode_color(data, false)
+}
+
+pub fn decode_dxt3(data: &[u8; 16]) -> Block {
+    let mut block = decode_color(data[8..].try_into().unwrap(), true);
+    let alpha = u64::from_le_bytes(data[..8].try_into().unwrap());
+    for (i, pixel) in block.iter_mut().enumerate() {
+        pixel[3] = ((alpha >> (i * 4)) & 0xF) as u8 * 17;
+    }
+    block
+}
+
+pub fn decode_dxt5(data: &[u8; 16]) -> Block {
+    let mut block = decode_color(data[8..].try_into().unwrap(), true);
+    let alpha = decode_alpha(data[..8].try_into().unwrap());
+    for (pixel, alpha) in block.iter_mut().zip(alpha) {
+        pixel[3] = alpha;
+    }
+    block
+}
+
+pub fn encode_dxt1(block: &Block, quality: BlockCompressionQuality) -> [u8; 8] {
+    let mut transparent = [false; 16];
+    for (t, pixel) in transparent.iter_mut().zip(block.iter()) {
+        *t = pixel[3] < DXT1_ALPHA_THRESHOLD;
+    }
+    encode_color(block, &transparent, false, quality)
+}
+
+pub fn encode_dxt3(block: &Block, quality: BlockCompressionQuality) -> [u8; 16] {
+    let mut alpha = 0u64;
+    for (i, pixel) in block.iter().enumerate() {
+        let a4 = (pixel[3] as u64 * 15 + 127) / 255;
+        alpha |= a4 << (i * 4);
+    }
+
+    let mut output = [0u8; 16];
+    output[..8].copy_from_slice(&alpha.to_le_bytes());
+    output[8..].copy_from_slice(&encode_color(block, &[false; 16], true, quality));
+    output
+}
+
+pub fn encode_dxt5(block: &Block, quality: BlockCompressionQuality) -> [u8; 16] {
+    let mut alpha = [0u8; 16];
+    for (a, pixel) in alpha.iter_mut().zip(block.iter()) {
+        *a = pixel[3];
+    }
+
+    let mut output = [0u8; 16];
+    output[..8].copy_from_slice(&encode_alpha(&alpha, quality));
+    output[8..].copy_from_slice(&encode_color(block, &[false; 16], true, quality));
+    output
+}
+
+fn unpack_565(color: u16) -> [u8; 4] {
+    let r = ((color >> 11) & 0x1F) as u8;
+    let g = ((color >> 5) & 0x3F) as u8;
+    let b = (color & 0x1F) as u8;
+    [(r << 3) | (r >> 2), (g << 2) | (g >> 4), (b << 3) | (b >> 2), 255]
+}
+
+fn pack_565(color: &[f32; 3]) -> u16 {
+    let r = (color[0] * 31.0 / 255.0).round() as u16;
+    let g = (color[1] * 63.0 / 255.0).round() as u16;
+    let b = (color[2] * 31.0 / 255.0).round() as u16;
+    (r << 11) | (g << 5) | b
+}
+
+/// Get the palette for the color endpoints.
+///
+/// DXT1 uses three colors plus transparency if `color0 <= color1`, but DXT3 and DXT5 always use four colors.
+fn color_palette(color0: u16, color1: u16, four_color: bool) -> [[u8; 4]; 4] {
+    let c0 = unpack_565(color0);
+    let c1 = unpack_565(color1);
+    let mut palette = [c0, c1, [0; 4], [0; 4]];
+
+    for c in 0..3 {
+        let (a, b) = (c0[c] as u32, c1[c] as u32);
+        if four_color {
+            palette[2][c] = ((2 * a + b + 1) / 3) as u8;
+            palette[3][c] = ((a + 2 * b + 1) / 3) as u8;
+        }
+        else {
+            palette[2][c] = ((a + b + 1) / 2) as u8;
+        }
+    }
+    palette[2][3] = 255;
+    if four_color {
+        palette[3][3] = 255;
+    }
+
+    palette
+}
+
+fn decode_color(data: &[u8; 8], force_four_color: bool) -> Block {
+    let color0 = u16::from_le_bytes([data[0], data[1]]);
+    let color1 = u16::from_le_bytes([data[2], data[3]]);
+    let indices = u32::from_le_bytes(data[4..].try_into().unwrap());
+    let palette = color_palette(color0, color1, force_four_color || color0 > color1);
+
+    let mut block = [[0; 4]; 16];
+    for (i, pixel) in block.iter_mut().enumerate() {
+        *pixel = palette[((indices >> (i * 2)) & 3) as usize];
+    }
+    block
+}
+
+struct ColorCandidate {
+    color0: u16,
+    color1: u16,
+    indices: [u8; 16],
+    error: u32
+}
+
+fn evaluate_color(block: &Block, transparent: &[bool; 16], force_four_color: bool, start: &[f32; 3], end: &[f32; 3]) -> ColorCandidate {
+    let three_color = transparent.iter().any(|t| *t);
+    let mut color0 = pack_565(start);
+    let mut color1 = pack_565(end);
+
+    // The order of the endpoints determines the mode in DXT1.
+    if three_color == (color0 > color1) {
+        std::mem::swap(&mut color0, &mut color1);
+    }
+
+    let four_color = force_four_color || color0 > color1;
+    let palette = color_palette(color0, color1, four_color);
+    let usable = if four_color { 4 } else { 3 };
+
+    let mut indices = [0u8; 16];
+    let mut error = 0;
+    for i in 0..16 {
+        if transparent[i] {
+            indices[i] = 3;
+            continue;
+        }
+
+        let (best, best_error) = palette[..usable]
+            .iter()
+            .enumerate()
+            .map(|(index, p)| (index, distance::<3>(&block[i], p)))
+            .min_by_key(|(_, e)| *e)
+            .unwrap();
+        indices[i] = best as u8;
+        error += best_error;
+    }
+
+    ColorCandidate { color0, color1, indices, error }
+}
+
+fn encode_color(block: &Block, transparent: &[bool; 16], force_four_color: bool, quality: BlockCompressionQuality) -> [u8; 8] {
+    let colors: Vec<[u8; 4]> = block.iter().zip(transparent).filter(|(_, t)| !**t).map(|(c, _)| *c).collect();
+    let (start, end) = fit_endpoints::<3>(&colors, quality);
+    let mut best = evaluate_color(block, transparent, force_four_color, &start, &end);
+
+    if quality == BlockCompressionQuality::Best {
+        for _ in 0..4 {
+            let four_color = force_four_color || best.color0 > best.color1;
+            let weights: Vec<f32> = best.indices
+                .iter()
+                .zip(transparent)
+                .filter(|(_, t)| !**t)
+                .map(|(i, _)| match (i, four_color) {
+                    (0, _) => 0.0,
+                    (1, _) => 1.0,
+                    (2, true) => 1.0 / 3.0,
+                    (3, true) => 2.0 / 3.0,
+                    _ => 0.5
+                })
+                .collect();
+
+            let Some((start, end)) = refine_endpoints::<3>(&colors, &weights) else { break };
+            let candidate = evaluate_color(block, transparent, force_four_color, &start, &end);
+            if candidate.error >= best.error {
+                break
+            }
+            best = candidate;
+        }
+    }
+
+    let mut indices = 0u32;
+    for (i, index) in best.indices.iter().enumerate() {
+        indices |= (*index as u32) << (i * 2);
+    }
+
+    let mut output = [0u8; 8];
+    output[0..2].copy_from_slice(&best.color0.to_le_bytes());
+    output[2..4].copy_from_slice(&best.color1.to_le_bytes());
+    output[4..8].copy_from_slice(&indices.to_le_bytes());
+    output
+}
+
+fn alpha_palette(alpha0: u8, alpha1: u8) -> [u8; 8] {
+    let (a, b) = (alpha0 as u32, alpha1 as u32);
+    let mut palette = [alpha0, alpha1, 0, 0, 0, 0, 0, 255];
+    if alpha0 > alpha1 {
+        for i in 1..7u32 {
+            palette[i as usize + 1] = (((7 - i) * a + i * b + 3) / 7) as u8;
+        }
+    }
+    else {
+        for i in 1..5u32 {
+            palette[i as usize + 1] = (((5 - i) * a + i * b + 2) / 5) as u8;
+        }
+    }
+    palette
+}
+
+fn decode_alpha(data: &[u8; 8]) -> [u8; 16] {
+    let palette = alpha_palette(data[0], data[1]);
+    let mut bits = [0u8; 8];
+    bits[..6].copy_from_slice(&data[2..]);
+    let indices = u64::from_le_bytes(bits);
+
+    let mut alpha = [0u8; 16];
+    for (i, a) in alpha.iter_mut().enumerate() {
+        *a = palette[((indices >> (i * 3)) & 7) as usize];
+    }
+    alpha
+}
+
+fn evaluate_alpha(alpha: &[u8; 16], alpha0: u8, alpha1: u8) -> (u64, u32) {
+    let palette = alpha_palette(alpha0, alpha1);
+    let mut indices = 0u64;
+    let mut error = 0;
+    for (i, a) in alpha.iter().enumerate() {
+        let (best, best_error) = palette
+            .iter()
+            .enumerate()
+            .map(|(index, p)| (index, (*a as i32 - *p as i32).unsigned_abs().pow(2)))
+            .min_by_key(|(_, e)| *e)
+            .unwrap();
+        indices |= (best as u64) << (i * 3);
+        error += best_error;
+    }
+    (indices, error)
+}
+
+fn encode_alpha(alpha: &[u8; 16], quality: BlockCompressionQuality) -> [u8; 8] {
+    let max = *alpha.iter().max().unwrap();
+    let min = *alpha.iter().min().unwrap();
+
+    // Eight interpolated values between the extremes
+    let mut candidates = vec![(max, min)];
+
+    // Six interpolated values, with 0 and 255 available separately
+    if quality != BlockCompressionQuality::Fast {
+        let inner = alpha.iter().copied().filter(|a| *a != 0 && *a != 255);
+        let inner_min = inner.clone().min().unwrap_or(0);
+        let inner_max = inner.max().unwrap_or(0);
+        candidates.push((inner_min, inner_max));
+    }
+
+    if quality == BlockCompressionQuality::Best {
+        let base = candidates.clone();
+        for (a0, a1) in base {
+            for d0 in -2i32..=2 {
+                for d1 in -2i32..=2 {
+                    let a0 = (a0 as i32 + d0).clamp(0, 255) as u8;
+                    let a1 = (a1 as i32 + d1).clamp(0, 255) as u8;
+                    candidates.push((a0, a1));
+                }
+            }
+        }
+    }
+
+    let (alpha0, alpha1, indices, _) = candidates
+        .into_iter()
+        .map(|(a0, a1)| {
+            let (indices, error) = evaluate_alpha(alpha, a0, a1);
+            (a0, a1, indices, error)
+        })
+        .min_by_key(|c| c.3)
+        .unwrap();
+
+    let mut output = [0u8; 8];
+    output[0] = alpha0;
+    output[1] = alpha1;
+    output[2..].copy_from_slice(&indices.to_le_bytes()[..6]);
+    output
+}
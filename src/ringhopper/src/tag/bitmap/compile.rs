@@ -0,0 +1,480 @@
+use std::num::NonZeroUsize;
+use definitions::{Bitmap, BitmapData, BitmapDataFormat, BitmapDataType, BitmapFormat, BitmapGroupSequence, BitmapGroupSprite, BitmapSpriteBudgetSize, BitmapSpriteUsage, BitmapType, BitmapUsage};
+use primitives::byteorder::LittleEndian;
+use primitives::error::{Error, OverflowCheck, RinghopperResult};
+use primitives::parse::SimpleTagData;
+use primitives::primitive::{Color, Pixel32, Pixel32Bytes};
+use crate::data::bitmap::Image;
+use crate::data::bitmap::plate::{ColorPlateSequence, read_color_plate};
+use super::{BlockCompressionQuality, bytes_per_block, COMPRESSED_BITMAP_DATA_FORMATS, compress_color_plate_data, encode_block_compressed_pixels, MipmapMetadata, MipmapTextureIterator, MipmapType, pixels_per_block_length};
+
+/// A texture to be added to a bitmap tag, consisting of one or more layers of equal size.
+///
+/// 2D textures have one layer, 3D textures have one layer per slice, and cubemaps have six layers.
+struct Texture {
+    width: usize,
+    height: usize,
+    bitmap_type: BitmapDataType,
+    layers: Vec<Image>
+}
+
+/// Compile a bitmap tag from a color plate.
+///
+/// The settings of the bitmap tag (type, format, usage, mipmap count, and sprite settings) are used to generate the
+/// bitmap data, sequences, and processed pixel data. The color plate is also stored in the tag. `quality` is used for block compressed
+/// formats.
+///
+/// Returns `Err` if the color plate is invalid for the bitmap's settings.
+pub fn compile_bitmap(tag: &mut Bitmap, color_plate: &Image, quality: BlockCompressionQuality) -> RinghopperResult<()> {
+    let sequences = read_color_plate(color_plate)?;
+
+    tag.color_plate.width = color_plate.width.try_into().map_err(|_| Error::Other("color plate is too wide".to_owned()))?;
+    tag.color_plate.height = color_plate.height.try_into().map_err(|_| Error::Other("color plate is too tall".to_owned()))?;
+    tag.color_plate.compressed_data.bytes = compress_color_plate_data(color_plate)?;
+
+    tag.bitmap_data.items.clear();
+    tag.bitmap_group_sequence.items.clear();
+    tag.processed_pixel_data.bytes.clear();
+
+    if tag._type == BitmapType::Sprites {
+        return compile_sprites(tag, &sequences, quality);
+    }
+
+    for (sequence_index, sequence) in sequences.into_iter().enumerate() {
+        let textures = match tag._type {
+            BitmapType::_2dTextures | BitmapType::InterfaceBitmaps => sequence.bitmaps
+                .into_iter()
+                .map(|b| Texture { width: b.width, height: b.height, bitmap_type: BitmapDataType::_2dTexture, layers: vec![b] })
+                .collect(),
+            BitmapType::_3dTextures => vec![make_layered_texture(sequence_index, sequence.bitmaps, BitmapDataType::_3dTexture)?],
+            BitmapType::CubeMaps => {
+                if sequence.bitmaps.len() != 6 {
+                    return Err(Error::Other(format!("sequence #{sequence_index} has {} bitmaps, but cubemaps require 6", sequence.bitmaps.len())))
+                }
+                vec![make_layered_texture(sequence_index, sequence.bitmaps, BitmapDataType::CubeMap)?]
+            },
+            BitmapType::Sprites => unreachable!()
+        };
+
+        let first_bitmap_index = tag.bitmap_data.items.len();
+        let bitmap_count = textures.len();
+        let generate_mipmaps = tag._type != BitmapType::InterfaceBitmaps;
+        for texture in textures {
+            add_texture(tag, texture, generate_mipmaps, quality)?;
+        }
+
+        tag.bitmap_group_sequence.items.push(BitmapGroupSequence {
+            first_bitmap_index: Some(first_bitmap_index.try_into().map_err(|_| Error::IndexLimitExceeded)?),
+            bitmap_count: bitmap_count.try_into().map_err(|_| Error::IndexLimitExceeded)?,
+            ..Default::default()
+        });
+    }
+
+    Ok(())
+}
+
+fn make_layered_texture(sequence_index: usize, layers: Vec<Image>, bitmap_type: BitmapDataType) -> RinghopperResult<Texture> {
+    let first = layers.first().ok_or_else(|| Error::Other(format!("sequence #{sequence_index} is empty")))?;
+    let (width, height) = (first.width, first.height);
+
+    if layers.iter().any(|l| l.width != width || l.height != height) {
+        return Err(Error::Other(format!("sequence #{sequence_index} has bitmaps of different sizes")))
+    }
+    if bitmap_type == BitmapDataType::CubeMap && width != height {
+        return Err(Error::Other(format!("sequence #{sequence_index} has non-square cubemap faces ({width}x{height})")))
+    }
+    if bitmap_type == BitmapDataType::_3dTexture && !layers.len().is_power_of_two() {
+        return Err(Error::Other(format!("sequence #{sequence_index} has a non-power-of-two depth ({})", layers.len())))
+    }
+
+    Ok(Texture { width, height, bitmap_type, layers })
+}
+
+fn compile_sprites(tag: &mut Bitmap, sequences: &[ColorPlateSequence], quality: BlockCompressionQuality) -> RinghopperResult<()> {
+    let all_sprites: Vec<&Image> = sequences.iter().flat_map(|s| s.bitmaps.iter()).collect();
+    let spacing = tag.sprite_spacing as usize;
+    let budget_count = tag.sprite_budget_count as usize;
+
+    // Without a budget, everything goes on one sheet which is as large as it needs to be.
+    let (length, positions) = if budget_count == 0 {
+        let largest = all_sprites.iter().map(|s| s.width.max(s.height)).max().unwrap_or(1);
+        let mut length = largest.next_power_of_two();
+        loop {
+            if let Some(n) = pack_sprites(&all_sprites, length, spacing, 1) {
+                break (length, n)
+            }
+            length *= 2;
+            if length > u16::MAX as usize {
+                return Err(Error::Other("sprites do not fit in a sprite sheet".to_owned()))
+            }
+        }
+    }
+    else {
+        let length = match tag.sprite_budget_size {
+            BitmapSpriteBudgetSize::_32x32 => 32,
+            BitmapSpriteBudgetSize::_64x64 => 64,
+            BitmapSpriteBudgetSize::_128x128 => 128,
+            BitmapSpriteBudgetSize::_256x256 => 256,
+            BitmapSpriteBudgetSize::_512x512 => 512,
+            BitmapSpriteBudgetSize::_1024x1024 => 1024
+        };
+        let positions = pack_sprites(&all_sprites, length, spacing, budget_count)
+            .ok_or_else(|| Error::Other(format!("sprites do not fit in {budget_count} {length}x{length} sprite sheet(s)")))?;
+        (length, positions)
+    };
+    let sheet_count = positions.iter().map(|(sheet, _, _)| sheet + 1).max().unwrap_or(1);
+
+    // Empty space should not affect what is behind the sprite.
+    let background: Pixel32 = match tag.sprite_usage {
+        BitmapSpriteUsage::BlendAddSubtractMax => Pixel32Bytes { alpha: 0, red: 0, green: 0, blue: 0 },
+        BitmapSpriteUsage::MultiplyMin => Pixel32Bytes { alpha: 0, red: 255, green: 255, blue: 255 },
+        BitmapSpriteUsage::DoubleMultiply => Pixel32Bytes { alpha: 0, red: 127, green: 127, blue: 127 }
+    }.into();
+
+    let mut sheets = vec![Image { width: length, height: length, data: vec![background; length * length] }; sheet_count];
+    for (sprite, (sheet, x, y)) in all_sprites.iter().zip(positions.iter()) {
+        for sy in 0..sprite.height {
+            let row = &sprite.data[sy * sprite.width..(sy + 1) * sprite.width];
+            let start = x + (y + sy) * length;
+            sheets[*sheet].data[start..start + sprite.width].copy_from_slice(row);
+        }
+    }
+
+    let mut positions = positions.into_iter();
+    let sheet_length = length as f32;
+    for sequence in sequences {
+        let sprites = sequence.bitmaps.iter().zip(&mut positions).map(|(sprite, (sheet, x, y))| Ok(BitmapGroupSprite {
+            bitmap_index: Some(sheet.try_into().map_err(|_| Error::IndexLimitExceeded)?),
+            left: x as f32 / sheet_length,
+            right: (x + sprite.width) as f32 / sheet_length,
+            top: y as f32 / sheet_length,
+            bottom: (y + sprite.height) as f32 / sheet_length,
+            ..Default::default()
+        })).collect::<RinghopperResult<Vec<_>>>()?;
+
+        let mut group_sequence = BitmapGroupSequence {
+            first_bitmap_index: Some(0),
+            bitmap_count: sheet_count.try_into().map_err(|_| Error::IndexLimitExceeded)?,
+            ..Default::default()
+        };
+        group_sequence.sprites.items = sprites;
+        tag.bitmap_group_sequence.items.push(group_sequence);
+    }
+
+    for sheet in sheets {
+        add_texture(tag, Texture { width: length, height: length, bitmap_type: BitmapDataType::_2dTexture, layers: vec![sheet] }, false, quality)?;
+    }
+
+    Ok(())
+}
+
+/// Pack sprites into rows on up to `max_sheets` square sheets, returning the sheet and top-left corner of each sprite.
+///
+/// Returns `None` if the sprites do not fit.
+fn pack_sprites(sprites: &[&Image], length: usize, spacing: usize, max_sheets: usize) -> Option<Vec<(usize, usize, usize)>> {
+    let mut order: Vec<usize> = (0..sprites.len()).collect();
+    order.sort_by(|a, b| sprites[*b].height.cmp(&sprites[*a].height));
+
+    let mut positions = vec![(0, 0, 0); sprites.len()];
+    let mut sheet = 0;
+    let mut x = 0;
+    let mut y = 0;
+    let mut row_height = 0;
+
+    for index in order {
+        let sprite = sprites[index];
+        if sprite.width > length || sprite.height > length {
+            return None
+        }
+        if x + sprite.width > length {
+            x = 0;
+            y += row_height + spacing;
+            row_height = 0;
+        }
+        if y + sprite.height > length {
+            sheet += 1;
+            x = 0;
+            y = 0;
+            row_height = 0;
+        }
+        if sheet >= max_sheets {
+            return None
+        }
+
+        positions[index] = (sheet, x, y);
+        x += sprite.width + spacing;
+        row_height = row_height.max(sprite.height);
+    }
+
+    Some(positions)
+}
+
+fn add_texture(tag: &mut Bitmap, mut texture: Texture, generate_mipmaps: bool, quality: BlockCompressionQuality) -> RinghopperResult<()> {
+    if tag.usage == BitmapUsage::HeightMap {
+        for layer in &mut texture.layers {
+            *layer = height_map_to_vector_map(layer, tag.bump_height);
+        }
+    }
+
+    let format = choose_bitmap_data_format(tag.encoding_format, texture.layers.iter().flat_map(|l| l.data.iter().copied()));
+
+    let width = NonZeroUsize::new(texture.width).ok_or_else(|| Error::Other("bitmap has zero width".to_owned()))?;
+    let height = NonZeroUsize::new(texture.height).ok_or_else(|| Error::Other("bitmap has zero height".to_owned()))?;
+    let depth = texture.layers.len();
+    let power_of_two = width.is_power_of_two() && height.is_power_of_two() && depth.is_power_of_two();
+
+    let mipmap_type = match texture.bitmap_type {
+        BitmapDataType::_2dTexture => MipmapType::TwoDimensional,
+        BitmapDataType::_3dTexture => MipmapType::ThreeDimensional(NonZeroUsize::new(depth).unwrap()),
+        BitmapDataType::CubeMap => MipmapType::Cubemap
+    };
+
+    let mipmap_limit = if !generate_mipmaps || !power_of_two {
+        Some(0)
+    }
+    else if tag.mipmap_count == 0 {
+        None
+    }
+    else {
+        Some(tag.mipmap_count as usize)
+    };
+
+    let bytes_per_block = bytes_per_block(format).get();
+    let block_length = pixels_per_block_length(format);
+
+    let pixel_data_offset = tag.processed_pixel_data.bytes.len();
+    let mut layers = texture.layers;
+    let mut mipmap_count = 0;
+    let mipmaps: Vec<MipmapMetadata> = MipmapTextureIterator::new(width, height, mipmap_type, block_length, mipmap_limit).collect();
+    let last_mipmap = mipmaps.last().map_or(0, |m| m.mipmap_index);
+    for mipmap in mipmaps {
+        if mipmap.mipmap_index > 0 {
+            layers = generate_mipmap(&layers, texture.bitmap_type == BitmapDataType::_3dTexture);
+            if matches!(tag.usage, BitmapUsage::HeightMap | BitmapUsage::VectorMap) {
+                layers.iter_mut().for_each(normalize_vectors);
+            }
+        }
+
+        // Detail maps fade to gray as they get smaller, faster with a higher detail fade factor.
+        let faded;
+        let encoded_layers = if tag.usage == BitmapUsage::DetailMap && mipmap.mipmap_index > 0 {
+            let amount = (mipmap.mipmap_index as f32 * (1.0 + tag.detail_fade_factor) / last_mipmap as f32).min(1.0);
+            faded = layers.iter().map(|l| fade_to_gray(l, amount)).collect::<Vec<Image>>();
+            &faded
+        }
+        else {
+            &layers
+        };
+
+        let start = tag.processed_pixel_data.bytes.len();
+        for layer in encoded_layers {
+            debug_assert_eq!((layer.width, layer.height), (mipmap.width, mipmap.height));
+            if COMPRESSED_BITMAP_DATA_FORMATS.contains(&format) {
+                let encoded = encode_block_compressed_pixels(format, layer.width, layer.height, &layer.data, quality)?;
+                tag.processed_pixel_data.bytes.extend_from_slice(&encoded);
+            }
+            else {
+                encode_pixels(format, &layer.data, &mut tag.processed_pixel_data.bytes)?;
+            }
+        }
+        debug_assert_eq!(tag.processed_pixel_data.bytes.len() - start, mipmap.block_count * bytes_per_block);
+
+        mipmap_count = mipmap.mipmap_index;
+    }
+    let pixel_data_size = tag.processed_pixel_data.bytes.len() - pixel_data_offset;
+
+    let mut bitmap_data = BitmapData {
+        width: width.get().try_into().map_err(|_| Error::Other(format!("bitmap is too wide ({width})")))?,
+        height: height.get().try_into().map_err(|_| Error::Other(format!("bitmap is too tall ({height})")))?,
+        depth: depth.try_into().map_err(|_| Error::Other(format!("bitmap is too deep ({depth})")))?,
+        _type: texture.bitmap_type,
+        format,
+        mipmap_count: mipmap_count.try_into().map_err(|_| Error::IndexLimitExceeded)?,
+        pixel_data_offset: pixel_data_offset.try_into().map_err(|_| Error::ArrayLimitExceeded)?,
+        pixel_data_size: pixel_data_size.try_into().map_err(|_| Error::ArrayLimitExceeded)?,
+        ..Default::default()
+    };
+    bitmap_data.flags.power_of_two_dimensions = power_of_two;
+    tag.bitmap_data.items.push(bitmap_data);
+
+    Ok(())
+}
+
+/// Halve the dimensions of each layer, also halving the number of layers for 3D textures.
+fn generate_mipmap(layers: &[Image], merge_layers: bool) -> Vec<Image> {
+    let halved: Vec<Image> = layers.iter().map(|layer| {
+        let width = (layer.width / 2).max(1);
+        let height = (layer.height / 2).max(1);
+        let mut data = Vec::with_capacity(width * height);
+
+        for y in 0..height {
+            for x in 0..width {
+                let xs = [(x * 2).min(layer.width - 1), (x * 2 + 1).min(layer.width - 1)];
+                let ys = [(y * 2).min(layer.height - 1), (y * 2 + 1).min(layer.height - 1)];
+                data.push(average_pixels(ys.iter().flat_map(|py| xs.iter().map(move |px| layer.data[px + py * layer.width]))));
+            }
+        }
+
+        Image { width, height, data }
+    }).collect();
+
+    if !merge_layers || halved.len() == 1 {
+        return halved
+    }
+
+    halved.chunks(2).map(|pair| Image {
+        width: pair[0].width,
+        height: pair[0].height,
+        data: pair[0].data.iter().zip(pair[1].data.iter()).map(|(a, b)| average_pixels([*a, *b].into_iter())).collect()
+    }).collect()
+}
+
+/// Convert a height map into a vector map, where `bump_height` is the height of white relative to the width of a pixel.
+fn height_map_to_vector_map(layer: &Image, bump_height: f32) -> Image {
+    // Heights wrap around since textures tile
+    let height_at = |x: usize, y: usize| luminance(layer.data[x % layer.width + (y % layer.height) * layer.width]) as f32 / 255.0;
+
+    let mut data = Vec::with_capacity(layer.data.len());
+    for y in 0..layer.height {
+        for x in 0..layer.width {
+            let dx = (height_at(x + 1, y) - height_at(x + layer.width - 1, y)) * bump_height / 2.0;
+            let dy = (height_at(x, y + 1) - height_at(x, y + layer.height - 1)) * bump_height / 2.0;
+            let alpha = Pixel32Bytes::from(layer.data[x + y * layer.width]).alpha;
+            data.push(encode_vector(-dx, -dy, 1.0, alpha));
+        }
+    }
+
+    Image { width: layer.width, height: layer.height, data }
+}
+
+/// Make each vector in a vector map unit length again, such as after averaging.
+fn normalize_vectors(layer: &mut Image) {
+    let decode = |channel: u8| channel as f32 / 127.5 - 1.0;
+    for pixel in &mut layer.data {
+        let bytes = Pixel32Bytes::from(*pixel);
+        *pixel = encode_vector(decode(bytes.red), decode(bytes.green), decode(bytes.blue), bytes.alpha);
+    }
+}
+
+fn encode_vector(x: f32, y: f32, z: f32, alpha: u8) -> Pixel32 {
+    let length = (x * x + y * y + z * z).sqrt();
+    let (x, y, z) = if length > 0.0 { (x / length, y / length, z / length) } else { (0.0, 0.0, 1.0) };
+    let encode = |channel: f32| ((channel + 1.0) * 127.5).round().clamp(0.0, 255.0) as u8;
+    Pixel32Bytes { alpha, red: encode(x), green: encode(y), blue: encode(z) }.into()
+}
+
+/// Blend the color of each pixel towards gray by `amount` (0 = unchanged, 1 = gray).
+fn fade_to_gray(layer: &Image, amount: f32) -> Image {
+    let fade = |channel: u8| (channel as f32 + (127.0 - channel as f32) * amount).round() as u8;
+    Image {
+        width: layer.width,
+        height: layer.height,
+        data: layer.data.iter().map(|pixel| {
+            let bytes = Pixel32Bytes::from(*pixel);
+            Pixel32Bytes { red: fade(bytes.red), green: fade(bytes.green), blue: fade(bytes.blue), ..bytes }.into()
+        }).collect()
+    }
+}
+
+fn average_pixels<I: Iterator<Item = Pixel32>>(pixels: I) -> Pixel32 {
+    let mut sum = [0u32; 4];
+    let mut count = 0;
+    for pixel in pixels {
+        let bytes = Pixel32Bytes::from(pixel);
+        sum[0] += bytes.alpha as u32;
+        sum[1] += bytes.red as u32;
+        sum[2] += bytes.green as u32;
+        sum[3] += bytes.blue as u32;
+        count += 1;
+    }
+    let avg = |channel: u32| ((channel + count / 2) / count.max(1)) as u8;
+    Pixel32Bytes {
+        alpha: avg(sum[0]),
+        red: avg(sum[1]),
+        green: avg(sum[2]),
+        blue: avg(sum[3])
+    }.into()
+}
+
+/// Choose the bitmap data format to use for the given tag format and pixels.
+pub fn choose_bitmap_data_format<I: Iterator<Item = Pixel32>>(format: BitmapFormat, pixels: I) -> BitmapDataFormat {
+    let mut opaque = true;
+    let mut one_bit_alpha = true;
+    let mut alpha_is_luminance = true;
+    let mut white = true;
+
+    for pixel in pixels {
+        let bytes = Pixel32Bytes::from(pixel);
+        opaque &= bytes.alpha == 255;
+        one_bit_alpha &= bytes.alpha == 255 || bytes.alpha == 0;
+        alpha_is_luminance &= bytes.alpha == luminance(pixel);
+        white &= bytes.red == 255 && bytes.green == 255 && bytes.blue == 255;
+    }
+
+    match format {
+        BitmapFormat::_32Bit => if opaque { BitmapDataFormat::X8R8G8B8 } else { BitmapDataFormat::A8R8G8B8 },
+        BitmapFormat::_16Bit => if opaque {
+            BitmapDataFormat::R5G6B5
+        }
+        else if one_bit_alpha {
+            BitmapDataFormat::A1R5G5B5
+        }
+        else {
+            BitmapDataFormat::A4R4G4B4
+        },
+        BitmapFormat::Monochrome => if opaque {
+            BitmapDataFormat::Y8
+        }
+        else if white {
+            BitmapDataFormat::A8
+        }
+        else if alpha_is_luminance {
+            BitmapDataFormat::AY8
+        }
+        else {
+            BitmapDataFormat::A8Y8
+        },
+        BitmapFormat::DXT1 => BitmapDataFormat::DXT1,
+        BitmapFormat::DXT3 => if opaque { BitmapDataFormat::DXT1 } else { BitmapDataFormat::DXT3 },
+        BitmapFormat::DXT5 => if opaque { BitmapDataFormat::DXT1 } else { BitmapDataFormat::DXT5 },
+        BitmapFormat::BC7 => BitmapDataFormat::BC7
+    }
+}
+
+fn luminance(pixel: Pixel32) -> u8 {
+    (pixel.luma() * 255.0).round() as u8
+}
+
+/// Encode pixels into the given uncompressed bitmap data format, appending them to `output`.
+///
+/// Returns `Err` if the format is not an uncompressed format.
+pub fn encode_pixels(format: BitmapDataFormat, pixels: &[Pixel32], output: &mut Vec<u8>) -> RinghopperResult<()> {
+    let bytes_per_pixel = bytes_per_block(format).get();
+    if pixels_per_block_length(format).get() != 1 || format == BitmapDataFormat::P8 {
+        return Err(Error::Other(format!("cannot encode pixels to {format:?}")))
+    }
+
+    let start = output.len();
+    output.resize(start.add_overflow_checked(pixels.len().mul_overflow_checked(bytes_per_pixel)?)?, 0);
+
+    for (index, pixel) in pixels.iter().enumerate() {
+        let at = start + index * bytes_per_pixel;
+        let end = at + bytes_per_pixel;
+        let p = Pixel32Bytes::from(*pixel);
+        let (a, r, g, b) = (p.alpha as u16, p.red as u16, p.green as u16, p.blue as u16);
+
+        match format {
+            BitmapDataFormat::A8R8G8B8 => pixel.write::<LittleEndian>(output, at, end)?,
+            BitmapDataFormat::X8R8G8B8 => Pixel32::from(Pixel32Bytes { alpha: 255, ..p }).write::<LittleEndian>(output, at, end)?,
+            BitmapDataFormat::R5G6B5 => ((r >> 3) << 11 | (g >> 2) << 5 | (b >> 3)).write::<LittleEndian>(output, at, end)?,
+            BitmapDataFormat::A1R5G5B5 => ((a >> 7) << 15 | (r >> 3) << 10 | (g >> 3) << 5 | (b >> 3)).write::<LittleEndian>(output, at, end)?,
+            BitmapDataFormat::A4R4G4B4 => ((a >> 4) << 12 | (r >> 4) << 8 | (g >> 4) << 4 | (b >> 4)).write::<LittleEndian>(output, at, end)?,
+            BitmapDataFormat::A8 => output[at] = p.alpha,
+            BitmapDataFormat::Y8 | BitmapDataFormat::AY8 => output[at] = luminance(*pixel),
+            BitmapDataFormat::A8Y8 => (a << 8 | luminance(*pixel) as u16).write::<LittleEndian>(output, at, end)?,
+            _ => unreachable!()
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,169 @@
+use definitions::{Bitmap, BitmapData, BitmapDataFormat};
+use primitives::byteorder::LittleEndian;
+use primitives::error::{Error, OverflowCheck, RinghopperResult};
+use primitives::parse::SimpleTagData;
+use primitives::primitive::{Pixel32, Pixel32Bytes};
+use crate::data::bitmap::Image;
+use super::{bytes_per_block, COMPRESSED_BITMAP_DATA_FORMATS, decode_block_compressed_pixels, MipmapFaceIterator, MipmapMetadata, MipmapTextureIterator, MipmapType, swizzle, Swizzlable};
+
+/// Palette for P8 bitmap data, where each pixel is an index into the palette.
+pub type P8Palette = [Pixel32; 256];
+
+/// A single face of a single mipmap decoded from a bitmap data entry.
+pub struct DecodedBitmapFace {
+    /// Mipmap index.
+    ///
+    /// 0 = base map, 1 onwards = mipmap number
+    pub mipmap_index: usize,
+
+    /// Face index.
+    ///
+    /// For cubemaps, this is 0-5, for 3D textures this is the slice, and for 2D textures, this is always 0.
+    pub face_index: usize,
+
+    /// Decoded pixels of the face.
+    pub image: Image
+}
+
+/// Decode every mipmap and face of a bitmap data entry into images.
+///
+/// Swizzled data is deswizzled first. P8 data is looked up in `palette`, as the palette is not stored in the tag.
+///
+/// Returns `Err` if the bitmap data is out-of-bounds or malformed, or if it is P8 and no palette is given.
+pub fn decode_bitmap_data(bitmap: &Bitmap, bitmap_data_index: usize, palette: Option<&P8Palette>) -> RinghopperResult<Vec<DecodedBitmapFace>> {
+    let data = bitmap.bitmap_data.items.get(bitmap_data_index)
+        .ok_or_else(|| Error::InvalidTagData(format!("bitmap data #{bitmap_data_index} does not exist")))?;
+
+    let bytes_per_block = bytes_per_block(data.format).get();
+    let total_length = MipmapTextureIterator::new_from_bitmap_data(data)?
+        .map(|m| m.block_count)
+        .sum::<usize>()
+        .mul_overflow_checked(bytes_per_block)?;
+
+    let offset = data.pixel_data_offset as usize;
+    let pixels = offset.add_overflow_checked(total_length)
+        .ok()
+        .and_then(|end| bitmap.processed_pixel_data.bytes.get(offset..end))
+        .ok_or_else(|| Error::InvalidTagData(format!("bitmap data #{bitmap_data_index} has out-of-bounds pixel data")))?;
+
+    let deswizzled;
+    let pixels = if data.flags.swizzled {
+        deswizzled = deswizzle_pixel_data(data, pixels)?;
+        deswizzled.as_slice()
+    }
+    else {
+        pixels
+    };
+
+    let compressed = COMPRESSED_BITMAP_DATA_FORMATS.contains(&data.format);
+    MipmapFaceIterator::new_from_bitmap_data(data)?.map(|mipmap| {
+        let start = mipmap.block_offset * bytes_per_block;
+        let face = &pixels[start..start + mipmap.block_count * bytes_per_block];
+        let face_pixels = if compressed {
+            decode_block_compressed_pixels(data.format, mipmap.width, mipmap.height, face)?
+        }
+        else {
+            decode_pixels(data.format, face, palette)?
+        };
+
+        Ok(DecodedBitmapFace {
+            mipmap_index: mipmap.mipmap_index,
+            face_index: mipmap.face_index,
+            image: Image { width: mipmap.width, height: mipmap.height, data: face_pixels }
+        })
+    }).collect()
+}
+
+/// Decode pixels from the given uncompressed bitmap data format.
+///
+/// P8 pixels are indices into `palette`.
+///
+/// Returns `Err` if the format is block compressed, or if it is P8 and no palette is given.
+pub fn decode_pixels(format: BitmapDataFormat, data: &[u8], palette: Option<&P8Palette>) -> RinghopperResult<Vec<Pixel32>> {
+    if COMPRESSED_BITMAP_DATA_FORMATS.contains(&format) {
+        return Err(Error::InvalidTagData(format!("cannot decode {format} as uncompressed pixels")))
+    }
+    if format == BitmapDataFormat::P8 {
+        let palette = palette.ok_or_else(|| Error::InvalidTagData(format!("cannot decode {format} without a palette")))?;
+        return Ok(data.iter().map(|p| palette[*p as usize]).collect())
+    }
+
+    let bytes_per_pixel = bytes_per_block(format).get();
+    let pixels = data.chunks_exact(bytes_per_pixel).map(|p| {
+        let value = match bytes_per_pixel {
+            1 => p[0] as u32,
+            2 => u16::from_le_bytes([p[0], p[1]]) as u32,
+            4 => u32::from_le_bytes([p[0], p[1], p[2], p[3]]),
+            n => unreachable!("cannot decode {n} byte pixels")
+        };
+
+        let expand_5 = |v: u32| (((v & 0x1F) << 3) | ((v & 0x1F) >> 2)) as u8;
+        let expand_6 = |v: u32| (((v & 0x3F) << 2) | ((v & 0x3F) >> 4)) as u8;
+        let expand_4 = |v: u32| ((v & 0xF) * 17) as u8;
+
+        match format {
+            BitmapDataFormat::A8R8G8B8 => Pixel32 { color: value },
+            BitmapDataFormat::X8R8G8B8 => Pixel32 { color: value | 0xFF000000 },
+            BitmapDataFormat::R5G6B5 => Pixel32Bytes { alpha: 255, red: expand_5(value >> 11), green: expand_6(value >> 5), blue: expand_5(value) }.into(),
+            BitmapDataFormat::A1R5G5B5 => Pixel32Bytes { alpha: if value & 0x8000 != 0 { 255 } else { 0 }, red: expand_5(value >> 10), green: expand_5(value >> 5), blue: expand_5(value) }.into(),
+            BitmapDataFormat::A4R4G4B4 => Pixel32Bytes { alpha: expand_4(value >> 12), red: expand_4(value >> 8), green: expand_4(value >> 4), blue: expand_4(value) }.into(),
+            BitmapDataFormat::A8 => Pixel32Bytes { alpha: value as u8, red: 255, green: 255, blue: 255 }.into(),
+            BitmapDataFormat::Y8 => Pixel32::from_y8(value as u8),
+            BitmapDataFormat::AY8 => Pixel32Bytes { alpha: value as u8, red: value as u8, green: value as u8, blue: value as u8 }.into(),
+            BitmapDataFormat::A8Y8 => Pixel32::from_a8y8(value as u16),
+            _ => unreachable!()
+        }
+    }).collect();
+
+    Ok(pixels)
+}
+
+/// Deswizzle the pixel data of a bitmap data entry.
+///
+/// Returns `Err` if the bitmap data cannot be deswizzled.
+pub fn deswizzle_pixel_data(data: &BitmapData, pixels: &[u8]) -> RinghopperResult<Vec<u8>> {
+    if COMPRESSED_BITMAP_DATA_FORMATS.contains(&data.format) {
+        return Err(Error::InvalidTagData(format!("cannot deswizzle {} data", data.format)))
+    }
+
+    fn deswizzle_mipmap<T: SimpleTagData + Swizzlable>(metadata: MipmapMetadata, input: &[u8], output: &mut Vec<u8>) -> RinghopperResult<()> {
+        let mut data: Vec<T> = Vec::with_capacity(metadata.block_count);
+        data.extend(T::read_chunks_to_iterator::<LittleEndian>(input).unwrap().into_infallible());
+
+        let mut deswizzled: Vec<T> = vec![Default::default(); metadata.block_count];
+        swizzle(&data, &mut deswizzled, metadata.width, metadata.height, metadata.depth, true)?;
+
+        for i in deswizzled {
+            output.extend_from_slice(i.as_bytes::<LittleEndian>().unwrap().bytes());
+        }
+
+        Ok(())
+    }
+
+    let bytes_per_block = bytes_per_block(data.format).get();
+    let mut output = Vec::with_capacity(pixels.len());
+    let mut deswizzle = |metadata: MipmapMetadata| -> RinghopperResult<()> {
+        let start = metadata.block_offset * bytes_per_block;
+        let input = &pixels[start..start + metadata.block_count * bytes_per_block];
+        match bytes_per_block {
+            1 => deswizzle_mipmap::<u8>(metadata, input, &mut output),
+            2 => deswizzle_mipmap::<u16>(metadata, input, &mut output),
+            4 => deswizzle_mipmap::<Pixel32>(metadata, input, &mut output),
+            n => unreachable!("cannot deswizzle {n} len")
+        }
+    };
+
+    // Cubemap faces are swizzled separately, whereas 3D textures are swizzled as a whole.
+    if MipmapType::get_mipmap_type(data)? == MipmapType::Cubemap {
+        for metadata in MipmapFaceIterator::new_from_bitmap_data(data)? {
+            deswizzle(metadata)?;
+        }
+    }
+    else {
+        for metadata in MipmapTextureIterator::new_from_bitmap_data(data)? {
+            deswizzle(metadata)?;
+        }
+    }
+
+    Ok(output)
+}
@@ -148,3 +148,215 @@ fn iterates_block_compression() {
 
     assert!(d.next().is_none());
 }
+
+#[test]
+fn compiles_2d_texture_with_mipmaps() {
+    use definitions::{BitmapFormat, BitmapType};
+    use primitives::primitive::Pixel32Bytes;
+
+    let image = Image {
+        width: 4,
+        height: 4,
+        data: vec![Pixel32Bytes { alpha: 255, red: 0x10, green: 0x20, blue: 0x30 }.into(); 16]
+    };
+
+    let mut tag = Bitmap::default();
+    tag._type = BitmapType::_2dTextures;
+    tag.encoding_format = BitmapFormat::_32Bit;
+    compile_bitmap(&mut tag, &image, BlockCompressionQuality::default()).unwrap();
+
+    assert_eq!(1, tag.bitmap_data.items.len());
+    assert_eq!(1, tag.bitmap_group_sequence.items.len());
+
+    let data = &tag.bitmap_data.items[0];
+    assert_eq!(BitmapDataFormat::X8R8G8B8, data.format);
+    assert_eq!(2, data.mipmap_count);
+    assert_eq!((4 * 4 + 2 * 2 + 1) * 4, data.pixel_data_size as usize);
+    assert_eq!(tag.processed_pixel_data.bytes.len(), data.pixel_data_size as usize);
+    assert_eq!(&[0x30, 0x20, 0x10, 0xFF], &tag.processed_pixel_data.bytes[0..4]);
+}
+
+#[test]
+fn block_compression_round_trip() {
+    use primitives::primitive::Pixel32Bytes;
+
+    let pixels: Vec<Pixel32> = (0..64).map(|i| Pixel32Bytes { alpha: 255, red: i * 4, green: 255 - i * 4, blue: 0x80 }.into()).collect();
+
+    for format in COMPRESSED_BITMAP_DATA_FORMATS {
+        for quality in [BlockCompressionQuality::Fast, BlockCompressionQuality::Normal, BlockCompressionQuality::Best] {
+            let encoded = encode_block_compressed_pixels(*format, 8, 8, &pixels, quality).unwrap();
+            assert_eq!(4 * bytes_per_block(*format).get(), encoded.len());
+
+            let decoded = decode_block_compressed_pixels(*format, 8, 8, &encoded).unwrap();
+            for (original, decoded) in pixels.iter().zip(decoded.iter()) {
+                let original = Pixel32Bytes::from(*original);
+                let decoded = Pixel32Bytes::from(*decoded);
+                assert_eq!(255, decoded.alpha, "{format} should stay opaque");
+                assert!((original.red as i32 - decoded.red as i32).abs() <= 16, "{format} {quality:?}: {original} became {decoded}");
+                assert!((original.green as i32 - decoded.green as i32).abs() <= 16, "{format} {quality:?}: {original} became {decoded}");
+                assert!((original.blue as i32 - decoded.blue as i32).abs() <= 16, "{format} {quality:?}: {original} became {decoded}");
+            }
+        }
+    }
+}
+
+#[test]
+fn dxt1_one_bit_alpha() {
+    use primitives::primitive::Pixel32Bytes;
+
+    let pixels: Vec<Pixel32> = (0..30u8)
+        .map(|i| Pixel32Bytes { alpha: if i % 3 == 0 { 0x20 } else { 0xE0 }, red: i * 8, green: 0x40, blue: 0x40 }.into())
+        .collect();
+
+    let encoded = encode_block_compressed_pixels(BitmapDataFormat::DXT1, 6, 5, &pixels, BlockCompressionQuality::Best).unwrap();
+    let decoded = decode_block_compressed_pixels(BitmapDataFormat::DXT1, 6, 5, &encoded).unwrap();
+    assert_eq!(30, decoded.len());
+    for (i, pixel) in decoded.iter().enumerate() {
+        let expected = if i % 3 == 0 { 0 } else { 255 };
+        assert_eq!(expected, Pixel32Bytes::from(*pixel).alpha, "pixel #{i}");
+    }
+}
+
+#[test]
+fn decode_uncompressed_pixels() {
+    use primitives::primitive::Pixel32Bytes;
+
+    let pixels: Vec<Pixel32> = [
+        Pixel32Bytes { alpha: 255, red: 0xFF, green: 0x00, blue: 0x00 },
+        Pixel32Bytes { alpha: 0, red: 0x00, green: 0xFF, blue: 0x00 },
+        Pixel32Bytes { alpha: 255, red: 0x00, green: 0x00, blue: 0xFF },
+        Pixel32Bytes { alpha: 0, red: 0xFF, green: 0xFF, blue: 0xFF }
+    ].into_iter().map(Pixel32::from).collect();
+
+    for format in [BitmapDataFormat::A8R8G8B8, BitmapDataFormat::A1R5G5B5, BitmapDataFormat::A4R4G4B4] {
+        let mut encoded = Vec::new();
+        encode_pixels(format, &pixels, &mut encoded).unwrap();
+        assert_eq!(pixels, decode_pixels(format, &encoded, None).unwrap(), "{format} round trip");
+    }
+
+    let mut encoded = Vec::new();
+    encode_pixels(BitmapDataFormat::X8R8G8B8, &pixels, &mut encoded).unwrap();
+    for (original, decoded) in pixels.iter().zip(decode_pixels(BitmapDataFormat::X8R8G8B8, &encoded, None).unwrap()) {
+        assert_eq!(Pixel32Bytes { alpha: 255, ..Pixel32Bytes::from(*original) }, Pixel32Bytes::from(decoded));
+    }
+
+    assert_eq!(Pixel32::from_y8(0x40), decode_pixels(BitmapDataFormat::Y8, &[0x40], None).unwrap()[0]);
+    assert_eq!(Pixel32::from_a8y8(0x8040), decode_pixels(BitmapDataFormat::A8Y8, &[0x40, 0x80], None).unwrap()[0]);
+    assert!(decode_pixels(BitmapDataFormat::DXT1, &[0; 8], None).is_err());
+
+    let mut palette = [Pixel32::default(); 256];
+    palette[0x40] = Pixel32Bytes { alpha: 255, red: 0x80, green: 0x80, blue: 0xFF }.into();
+    palette[0xFF] = Pixel32Bytes { alpha: 0, red: 0x12, green: 0x34, blue: 0x56 }.into();
+    assert_eq!(vec![palette[0x40], palette[0xFF], palette[0]], decode_pixels(BitmapDataFormat::P8, &[0x40, 0xFF, 0x00], Some(&palette)).unwrap());
+    assert!(decode_pixels(BitmapDataFormat::P8, &[0x40], None).is_err());
+}
+
+#[test]
+fn decode_compiled_bitmap_data() {
+    use definitions::{BitmapFormat, BitmapType};
+    use primitives::primitive::Pixel32Bytes;
+
+    let image = Image {
+        width: 4,
+        height: 2,
+        data: (0..8u8).map(|i| Pixel32Bytes { alpha: 255, red: i * 30, green: 0x80, blue: 255 - i * 30 }.into()).collect()
+    };
+
+    let mut tag = Bitmap::default();
+    tag._type = BitmapType::_2dTextures;
+    tag.encoding_format = BitmapFormat::_32Bit;
+    compile_bitmap(&mut tag, &image, BlockCompressionQuality::default()).unwrap();
+
+    let faces = decode_bitmap_data(&tag, 0, None).unwrap();
+    assert_eq!(3, faces.len());
+    assert_eq!(image.data, faces[0].image.data);
+    assert_eq!((2, 1), (faces[1].image.width, faces[1].image.height));
+    assert_eq!((1, 1), (faces[2].image.width, faces[2].image.height));
+    assert!(decode_bitmap_data(&tag, 1, None).is_err());
+}
+
+#[test]
+fn compiles_sprites_within_budget() {
+    use definitions::{BitmapFormat, BitmapSpriteBudgetSize, BitmapType};
+    use primitives::primitive::{Pixel32, Pixel32Bytes};
+
+    let background: Pixel32 = Pixel32Bytes { alpha: 255, red: 0, green: 0, blue: 255 }.into();
+    let divider: Pixel32 = Pixel32Bytes { alpha: 255, red: 255, green: 0, blue: 255 }.into();
+    let white: Pixel32 = Pixel32Bytes { alpha: 255, red: 255, green: 255, blue: 255 }.into();
+
+    // One sequence with two 20x20 sprites, which cannot share a 32x32 sheet
+    let (width, height) = (43, 21);
+    let mut plate = Image { width, height, data: vec![background; width * height] };
+    plate.data[1..width].fill(divider);
+    for y in 1..height {
+        plate.data[y * width + 1..y * width + 21].fill(white);
+        plate.data[y * width + 22..y * width + 42].fill(white);
+    }
+
+    let mut tag = Bitmap::default();
+    tag._type = BitmapType::Sprites;
+    tag.encoding_format = BitmapFormat::_32Bit;
+    tag.sprite_budget_size = BitmapSpriteBudgetSize::_32x32;
+    tag.sprite_budget_count = 2;
+    tag.sprite_spacing = 4;
+    compile_bitmap(&mut tag, &plate, BlockCompressionQuality::default()).unwrap();
+
+    assert_eq!(2, tag.bitmap_data.items.len());
+    assert!(tag.bitmap_data.items.iter().all(|b| (b.width, b.height) == (32, 32)));
+
+    let sequence = &tag.bitmap_group_sequence.items[0];
+    assert_eq!(2, sequence.bitmap_count);
+    let sheets: Vec<Option<u16>> = sequence.sprites.items.iter().map(|s| s.bitmap_index).collect();
+    assert_eq!(vec![Some(0), Some(1)], sheets);
+
+    // Over budget
+    tag.sprite_budget_count = 1;
+    assert!(compile_bitmap(&mut tag, &plate, BlockCompressionQuality::default()).is_err());
+}
+
+#[test]
+fn detail_maps_fade_to_gray() {
+    use definitions::{BitmapFormat, BitmapType, BitmapUsage};
+    use primitives::primitive::Pixel32Bytes;
+
+    let image = Image {
+        width: 4,
+        height: 4,
+        data: vec![Pixel32Bytes { alpha: 255, red: 255, green: 255, blue: 255 }.into(); 16]
+    };
+
+    let mut tag = Bitmap::default();
+    tag._type = BitmapType::_2dTextures;
+    tag.encoding_format = BitmapFormat::_32Bit;
+    tag.usage = BitmapUsage::DetailMap;
+    compile_bitmap(&mut tag, &image, BlockCompressionQuality::default()).unwrap();
+
+    // The base map is unchanged and the last mipmap is gray
+    let bytes = &tag.processed_pixel_data.bytes;
+    assert_eq!(&[0xFF, 0xFF, 0xFF, 0xFF], &bytes[0..4]);
+    assert_eq!(&[0x7F, 0x7F, 0x7F, 0xFF], &bytes[bytes.len() - 4..]);
+}
+
+#[test]
+fn height_maps_become_vector_maps() {
+    use definitions::{BitmapFormat, BitmapType, BitmapUsage};
+    use primitives::primitive::Pixel32Bytes;
+
+    let image = Image {
+        width: 4,
+        height: 4,
+        data: vec![Pixel32Bytes { alpha: 255, red: 0x80, green: 0x80, blue: 0x80 }.into(); 16]
+    };
+
+    let mut tag = Bitmap::default();
+    tag._type = BitmapType::_2dTextures;
+    tag.encoding_format = BitmapFormat::_32Bit;
+    tag.usage = BitmapUsage::HeightMap;
+    tag.bump_height = 5.0;
+    compile_bitmap(&mut tag, &image, BlockCompressionQuality::default()).unwrap();
+
+    // A flat height map points straight up
+    for pixel in tag.processed_pixel_data.bytes.chunks(4) {
+        assert_eq!(&[0xFF, 0x80, 0x80, 0xFF], pixel);
+    }
+}
@@ -2,29 +2,41 @@ use primitives::{primitive::{TagGroup, TagPath}, tag::PrimaryTagStructDyn};
 
 mod sound;
 mod model;
+mod model_animations;
 mod scenario;
 mod unicode_string_list;
 mod scenario_structure_bsp;
 mod floats;
+mod indices;
+mod ranges;
 
 pub enum BludgeonResult {
     Done,
     CannotRepair
 }
 
-pub fn bludgeon_tag(tag: &mut dyn PrimaryTagStructDyn, path: &TagPath) -> BludgeonResult {
+/// Optional repairs which change data that is not broken.
+#[derive(Copy, Clone, Default)]
+pub struct BludgeonOptions {
+    /// Decompress compressed animations so they can be edited.
+    ///
+    /// Animations that still have their uncompressed data are not decoded, since compression is lossy.
+    pub decompress_animations: bool
+}
+
+pub fn bludgeon_tag(tag: &mut dyn PrimaryTagStructDyn, path: &TagPath, options: &BludgeonOptions) -> BludgeonResult {
     floats::fix_bad_floats(tag);
+    indices::fix_bad_indices(tag);
+    ranges::fix_out_of_range_values(tag);
 
     match tag.group() {
         TagGroup::Model | TagGroup::GBXModel => model::repair_model(tag),
+        TagGroup::ModelAnimations => model_animations::repair_model_animations(tag, options),
         TagGroup::Sound => sound::repair_sound(tag),
         TagGroup::Scenario => scenario::repair_scenario(tag, path),
         TagGroup::UnicodeStringList => unicode_string_list::repair_unicode_string_list(tag),
         TagGroup::ScenarioStructureBSP => scenario_structure_bsp::repair_scenario_structure_bsp(tag),
 
-        // TODO: indices
-        // TODO: out-of-range clamping
-
         // VERIFY THAT THIS IS NOT JUST AUTOMATICALLY FIXED:
         // - TODO: uppercase tag references??
 
@@ -0,0 +1,13 @@
+use primitives::primitive::Index;
+use primitives::tag::PrimaryTagStructDyn;
+use crate::tag::verify::indices::find_bad_indices;
+
+pub fn fix_bad_indices(tag: &mut dyn PrimaryTagStructDyn) {
+    let data = tag.as_mut_dynamic();
+    for bad in find_bad_indices(data) {
+        data.foreach_mut(&bad.matcher, |field| {
+            *field.unwrap().as_any_mut().downcast_mut::<Index>().unwrap() = None;
+            true
+        });
+    }
+}
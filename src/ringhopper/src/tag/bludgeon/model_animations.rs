@@ -0,0 +1,22 @@
+use primitives::tag::PrimaryTagStructDyn;
+use ringhopper_structs::ModelAnimations;
+
+use crate::tag::model_animations::decompress_animation;
+
+use super::{BludgeonOptions, BludgeonResult};
+
+pub fn repair_model_animations(tag: &mut dyn PrimaryTagStructDyn, options: &BludgeonOptions) -> BludgeonResult {
+    if !options.decompress_animations {
+        return BludgeonResult::Done
+    }
+
+    let model_animations: &mut ModelAnimations = tag.as_any_mut().downcast_mut().unwrap();
+
+    // Extracted tags often only have compressed data, so decompress it to make it editable. Animations that cannot be
+    // decompressed are left compressed rather than failing the whole tag.
+    for animation in &mut model_animations.animations {
+        let _ = decompress_animation(animation);
+    }
+
+    BludgeonResult::Done
+}
@@ -0,0 +1,54 @@
+use primitives::dynamic::{DynamicTagData, DynamicTagDataType, SimplePrimitiveType, TagFieldMetadata};
+use primitives::primitive::Angle;
+use primitives::tag::{for_each_field_mut, PrimaryTagStructDyn};
+use crate::tag::verify::ranges::{clamp_ranged_value, get_ranged_value};
+
+pub fn fix_out_of_range_values(tag: &mut dyn PrimaryTagStructDyn) {
+    fn clamp_field(field: &mut dyn DynamicTagData, metadata: &TagFieldMetadata) {
+        if let Some(array) = field.as_array_mut() {
+            for i in 0..array.len() {
+                clamp_field(array.get_at_index_mut(i).unwrap(), metadata);
+            }
+            return
+        }
+
+        let Some(value) = get_ranged_value(field) else { return };
+        let Some(clamped) = clamp_ranged_value(field, value, metadata) else { return };
+
+        match field.data_type() {
+            DynamicTagDataType::SimplePrimitive(t) => {
+                let any = field.as_any_mut();
+                match t {
+                    SimplePrimitiveType::I8 => *any.downcast_mut::<i8>().unwrap() = clamped as i8,
+                    SimplePrimitiveType::U8 => *any.downcast_mut::<u8>().unwrap() = clamped as u8,
+                    SimplePrimitiveType::I16 => *any.downcast_mut::<i16>().unwrap() = clamped as i16,
+                    SimplePrimitiveType::U16 => *any.downcast_mut::<u16>().unwrap() = clamped as u16,
+                    SimplePrimitiveType::I32 => *any.downcast_mut::<i32>().unwrap() = clamped as i32,
+                    SimplePrimitiveType::U32 => *any.downcast_mut::<u32>().unwrap() = clamped as u32,
+                    SimplePrimitiveType::Float => *any.downcast_mut::<f32>().unwrap() = clamped as f32,
+                    SimplePrimitiveType::Angle => *any.downcast_mut::<Angle>().unwrap() = Angle::from_degrees(clamped as f32),
+                    _ => unreachable!()
+                }
+            },
+            DynamicTagDataType::Enum => {
+                // Not every integer is a valid option, so use the closest option that is in range.
+                let e = field.as_enum_mut().unwrap();
+                let (step, limit) = if Some(clamped) == metadata.minimum { (1.0, metadata.maximum) } else { (-1.0, metadata.minimum) };
+                let mut candidate = clamped;
+                while !limit.is_some_and(|l| (candidate - l) * step > 0.0) && (0.0..=u16::MAX as f64).contains(&candidate) {
+                    if e.set_enum_integer_value(candidate as u16).is_ok() {
+                        break
+                    }
+                    candidate += step;
+                }
+            },
+            _ => unreachable!()
+        }
+    }
+
+    for_each_field_mut(tag.as_mut_dynamic(), false, |metadata, field| {
+        if let Some(metadata) = metadata.filter(|m| m.minimum.is_some() || m.maximum.is_some()) {
+            clamp_field(field, &metadata);
+        }
+    });
+}
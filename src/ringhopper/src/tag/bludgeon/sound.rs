@@ -1,7 +1,7 @@
 use primitives::{dynamic::DynamicTagDataArray, tag::PrimaryTagStructDyn};
 use ringhopper_structs::Sound;
 
-use crate::tag::{sound::SoundPermutationMetadata, verify::sound::{find_actual_permutation_count, sound_tag_actually_contains_split_permutations, sound_tag_is_fubar}};
+use crate::tag::{sound::{find_actual_permutation_count, sound_tag_actually_contains_split_permutations, SoundPermutationMetadata}, verify::sound::sound_tag_is_fubar};
 
 use super::BludgeonResult;
 
@@ -0,0 +1,831 @@
+use std::collections::{BTreeMap, HashMap};
+use definitions::{ModelCollisionGeometryBSP, ModelCollisionGeometryBSP2DNode, ModelCollisionGeometryBSP2DReference, ModelCollisionGeometryBSP3DNode, ModelCollisionGeometryBSPEdge, ModelCollisionGeometryBSPLeaf, ModelCollisionGeometryBSPPlane, ModelCollisionGeometryBSPSurface, ModelCollisionGeometryBSPSurfaceFlags, ModelCollisionGeometryBSPVertex};
+use primitives::primitive::{Index, Plane2D, Plane3D, Vector, Vector2D, Vector3D};
+use crate::data::jms::JMS_UNITS_PER_WORLD_UNIT;
+use crate::tag::model::cross;
+use crate::tag::result::{Diagnostic, TagResult};
+
+#[cfg(test)]
+mod test;
+
+/// Set on a child index if it refers to a leaf (3D BSPs) or a surface (2D BSPs) rather than a node.
+pub const BSP_LEAF_FLAG: u32 = 0x80000000;
+
+/// Index for no node, leaf, surface, or edge.
+///
+/// In a 3D BSP, a child with this index is solid space.
+pub const BSP_NULL: u32 = 0xFFFFFFFF;
+
+/// Distance, in world units, within which a point is considered to be on a plane.
+const PLANE_EPSILON: f32 = 0.0001;
+
+/// Distance within which a point is considered to be on a plane when finding leaf connections.
+///
+/// This is larger than [`PLANE_EPSILON`] since connections start out as very large polygons.
+const CONNECTION_EPSILON: f32 = 0.001;
+
+/// Maximum number of planes tested when choosing how to split a node.
+const MAX_SPLITTER_CANDIDATES: usize = 32;
+
+/// A triangle to compile into a collision BSP.
+#[derive(Clone, Debug, Default)]
+pub struct CollisionTriangle {
+    /// Vertices in world units, counterclockwise when viewed from the front.
+    pub vertices: [Vector3D; 3],
+
+    /// Material index, if any.
+    pub material: Index,
+
+    /// Surface flags.
+    pub flags: ModelCollisionGeometryBSPSurfaceFlags
+}
+
+/// A collision BSP compiled with [`compile_collision_bsp`].
+pub struct CompiledCollisionBSP {
+    /// The BSP.
+    pub bsp: ModelCollisionGeometryBSP,
+
+    /// Index of the triangle each surface was made from.
+    pub surface_triangles: Vec<usize>,
+
+    /// Surfaces each leaf references.
+    pub leaf_surfaces: Vec<Vec<u32>>
+}
+
+/// Return `true` if the triangle is too thin to have a plane.
+///
+/// Degenerate triangles are skipped by [`compile_collision_bsp`], so callers should report them.
+pub fn is_degenerate_triangle(vertices: &[Vector3D; 3]) -> bool {
+    let [a, b, c] = *vertices;
+    let longest_edge = [b - a, c - b, a - c].iter().map(|e| e.magnitude_squared()).fold(0.0f32, f32::max);
+    if longest_edge == 0.0 {
+        return true
+    }
+
+    // (twice the area)^2 / base^2 = height^2
+    let height_squared = cross(b - a, c - a).magnitude_squared() / longest_edge;
+    height_squared < PLANE_EPSILON * PLANE_EPSILON
+}
+
+/// Compile triangles into a collision BSP.
+///
+/// Each triangle becomes a surface. Surfaces face the same way as their triangles, and the space behind one-sided
+/// surfaces is solid. Open edges, edges shared by more than two surfaces, and overlapping surfaces are reported in
+/// `result`. Degenerate triangles (see [`is_degenerate_triangle`]) are skipped.
+///
+/// `portals` are triangles that split the BSP without colliding, so that leaves do not cross them.
+pub fn compile_collision_bsp(triangles: &[CollisionTriangle], portals: &[[Vector3D; 3]], result: &mut TagResult) -> CompiledCollisionBSP {
+    let mut builder = CollisionBSPBuilder {
+        result,
+        bsp: ModelCollisionGeometryBSP::default(),
+        plane_lookup: HashMap::new(),
+        vertex_lookup: HashMap::new(),
+        surface_triangles: Vec::new(),
+        surface_vertices: Vec::new(),
+        leaf_surfaces: Vec::new(),
+        bsp2d_lookup: HashMap::new()
+    };
+
+    builder.add_surfaces(triangles);
+    builder.add_edges();
+
+    let mut fragments: Vec<Fragment> = builder.surface_vertices
+        .iter()
+        .enumerate()
+        .map(|(surface, vertices)| Fragment {
+            surface: Some(surface as u32),
+            plane: builder.bsp.surfaces.items[surface].plane,
+            points: vertices.iter().map(|v| builder.bsp.vertices.items[*v as usize].point).collect()
+        })
+        .collect();
+
+    for portal in portals.iter().filter(|p| !is_degenerate_triangle(p)) {
+        let [a, b, c] = *portal;
+        let normal = cross(b - a, c - a).normalize();
+        let plane = builder.add_plane(Plane3D { vector: normal, d: normal.dot(&a) });
+        fragments.push(Fragment { surface: None, plane, points: portal.to_vec() });
+    }
+
+    if !fragments.is_empty() {
+        builder.build_3d_node(fragments, Vec::new(), false);
+    }
+
+    CompiledCollisionBSP {
+        bsp: builder.bsp,
+        surface_triangles: builder.surface_triangles,
+        leaf_surfaces: builder.leaf_surfaces
+    }
+}
+
+/// Find the leaf that contains a point.
+///
+/// Returns `None` if the point is in solid space or the BSP is invalid.
+pub fn find_leaf(bsp: &ModelCollisionGeometryBSP, point: Vector3D) -> Option<usize> {
+    if bsp.bsp3d_nodes.items.is_empty() {
+        return None
+    }
+
+    let mut child = 0u32;
+    for _ in 0..=bsp.bsp3d_nodes.items.len() {
+        if child == BSP_NULL {
+            return None
+        }
+        if child & BSP_LEAF_FLAG != 0 {
+            return Some((child & !BSP_LEAF_FLAG) as usize)
+        }
+
+        let node = bsp.bsp3d_nodes.items.get(child as usize)?;
+        let plane = bsp.planes.items.get(node.plane as usize)?.plane;
+        child = if point.distance_from_plane(&plane) >= 0.0 { node.front_child } else { node.back_child };
+    }
+
+    // Nodes loop back on themselves.
+    None
+}
+
+/// Part of a plane where two leaves of a BSP touch, or where a leaf reaches outside of the BSP.
+pub struct LeafConnection {
+    /// Leaves in front of and behind the plane, or `None` for outside of the BSP.
+    pub leaves: [Option<usize>; 2],
+
+    /// Plane the connection is on.
+    pub plane: Plane3D,
+
+    /// Convex polygon of the connection.
+    pub points: Vec<Vector3D>
+}
+
+/// Find where leaves of a BSP touch each other and where they reach outside of the BSP's vertices.
+///
+/// Connections to solid space are not included. If a leaf reaches outside, then the surfaces around it are not closed.
+pub fn find_leaf_connections(bsp: &ModelCollisionGeometryBSP) -> Vec<LeafConnection> {
+    if bsp.bsp3d_nodes.items.is_empty() || bsp.vertices.items.is_empty() {
+        return Vec::new()
+    }
+
+    let mut lower = bsp.vertices.items[0].point;
+    let mut upper = lower;
+    for vertex in &bsp.vertices.items {
+        let point = vertex.point;
+        lower = Vector3D { x: lower.x.min(point.x), y: lower.y.min(point.y), z: lower.z.min(point.z) };
+        upper = Vector3D { x: upper.x.max(point.x), y: upper.y.max(point.y), z: upper.z.max(point.z) };
+    }
+    let margin = Vector3D { x: 1.0, y: 1.0, z: 1.0 };
+    let (lower, upper) = (lower - margin, upper + margin);
+
+    let furthest = [lower.x, lower.y, lower.z, upper.x, upper.y, upper.z].into_iter().fold(0.0f32, |a, b| a.max(b.abs()));
+    let mut connector = LeafConnector {
+        bsp,
+        portals: Vec::new(),
+        sides: HashMap::new(),
+        size: furthest * 4.0
+    };
+
+    // Start with a box around everything.
+    let box_planes = [
+        Plane3D { vector: Vector3D { x: 1.0, y: 0.0, z: 0.0 }, d: upper.x },
+        Plane3D { vector: Vector3D { x: -1.0, y: 0.0, z: 0.0 }, d: -lower.x },
+        Plane3D { vector: Vector3D { x: 0.0, y: 1.0, z: 0.0 }, d: upper.y },
+        Plane3D { vector: Vector3D { x: 0.0, y: -1.0, z: 0.0 }, d: -lower.y },
+        Plane3D { vector: Vector3D { x: 0.0, y: 0.0, z: 1.0 }, d: upper.z },
+        Plane3D { vector: Vector3D { x: 0.0, y: 0.0, z: -1.0 }, d: -lower.z }
+    ];
+    for plane in box_planes {
+        let mut points = connector.base_polygon(&plane);
+        for other in box_planes.iter().filter(|p| **p != plane) {
+            points = split_polygon(&points, other, CONNECTION_EPSILON).1;
+        }
+        connector.add(TreePortal { plane, points, sides: [TreeSide::Outside, TreeSide::Node(0)] });
+    }
+
+    connector.connect(0);
+
+    let leaf = |side: TreeSide| match side {
+        TreeSide::Leaf(leaf) => Some(Some(leaf)),
+        TreeSide::Outside => Some(None),
+        _ => None
+    };
+
+    connector.portals
+        .into_iter()
+        .filter_map(|p| {
+            let leaves = [leaf(p.sides[0])?, leaf(p.sides[1])?];
+            (leaves != [None, None]).then_some(LeafConnection { leaves, plane: p.plane, points: p.points })
+        })
+        .collect()
+}
+
+/// Get the index of a plane in the BSP, adding it if it is not present.
+pub fn find_or_add_plane(bsp: &mut ModelCollisionGeometryBSP, plane: Plane3D) -> u32 {
+    let key = plane_key(&plane);
+    if let Some(index) = bsp.planes.items.iter().position(|p| plane_key(&p.plane) == key) {
+        return index as u32
+    }
+    bsp.planes.items.push(ModelCollisionGeometryBSPPlane { plane });
+    (bsp.planes.items.len() - 1) as u32
+}
+
+/// Format a position in world units as JMS units for error messages.
+pub(crate) fn jms_position(position: Vector3D) -> String {
+    let Vector3D { x, y, z } = position.scale(JMS_UNITS_PER_WORLD_UNIT);
+    format!("({x:.3}, {y:.3}, {z:.3})")
+}
+
+/// Return `true` if both planes are the same within tolerance.
+pub(crate) fn planes_match(a: &Plane3D, b: &Plane3D) -> bool {
+    plane_key(a) == plane_key(b)
+}
+
+fn plane_key(plane: &Plane3D) -> [i32; 4] {
+    let normal = plane.vector.scale(10000.0);
+    [normal.x.round() as i32, normal.y.round() as i32, normal.z.round() as i32, (plane.d / PLANE_EPSILON).round() as i32]
+}
+
+fn vertex_key(point: Vector3D) -> [i64; 3] {
+    let point = point.scale(0.1 / PLANE_EPSILON);
+    [point.x.round() as i64, point.y.round() as i64, point.z.round() as i64]
+}
+
+/// Part of a surface that is being sorted into the BSP.
+#[derive(Clone)]
+struct Fragment {
+    /// Surface index, or `None` for portals.
+    surface: Option<u32>,
+    plane: u32,
+    points: Vec<Vector3D>
+}
+
+#[derive(Copy, Clone, PartialEq)]
+enum Side {
+    Front,
+    Back,
+    On,
+    Spanning
+}
+
+fn classify<V: Copy>(points: &[V], distance: impl Fn(V) -> f32) -> Side {
+    let (mut front, mut back) = (false, false);
+    for point in points {
+        let distance = distance(*point);
+        front |= distance > PLANE_EPSILON;
+        back |= distance < -PLANE_EPSILON;
+    }
+    match (front, back) {
+        (true, true) => Side::Spanning,
+        (true, false) => Side::Front,
+        (false, true) => Side::Back,
+        (false, false) => Side::On
+    }
+}
+
+/// Split a convex polygon into the parts in front of and behind a plane.
+fn split_polygon(points: &[Vector3D], plane: &Plane3D, epsilon: f32) -> (Vec<Vector3D>, Vec<Vector3D>) {
+    let mut front = Vec::with_capacity(points.len() + 1);
+    let mut back = Vec::with_capacity(points.len() + 1);
+
+    for (index, point) in points.iter().enumerate() {
+        let next = points[(index + 1) % points.len()];
+        let distance = point.distance_from_plane(plane);
+        let next_distance = next.distance_from_plane(plane);
+
+        if distance >= -epsilon {
+            front.push(*point);
+        }
+        if distance <= epsilon {
+            back.push(*point);
+        }
+
+        let crosses = (distance > epsilon && next_distance < -epsilon) || (distance < -epsilon && next_distance > epsilon);
+        if crosses {
+            let t = distance / (distance - next_distance);
+            let intersection = *point + (next - *point).scale(t);
+            front.push(intersection);
+            back.push(intersection);
+        }
+    }
+
+    (front, back)
+}
+
+/// Project a point onto the axis-aligned plane closest to a plane.
+///
+/// The axes are chosen so that polygons facing the same way as the plane stay counterclockwise.
+fn project(point: Vector3D, normal: Vector3D) -> Vector2D {
+    let (x, y, z) = (normal.x.abs(), normal.y.abs(), normal.z.abs());
+    let (u, v, positive) = if x >= y && x >= z {
+        (point.y, point.z, normal.x > 0.0)
+    }
+    else if y >= z {
+        (point.z, point.x, normal.y > 0.0)
+    }
+    else {
+        (point.x, point.y, normal.z > 0.0)
+    };
+
+    if positive { Vector2D { x: u, y: v } } else { Vector2D { x: v, y: u } }
+}
+
+struct CollisionBSPBuilder<'a> {
+    result: &'a mut TagResult,
+    bsp: ModelCollisionGeometryBSP,
+    plane_lookup: HashMap<[i32; 4], u32>,
+    vertex_lookup: HashMap<[i64; 3], u32>,
+    surface_triangles: Vec<usize>,
+    surface_vertices: Vec<[u32; 3]>,
+    leaf_surfaces: Vec<Vec<u32>>,
+    bsp2d_lookup: HashMap<(u32, Vec<u32>), u32>
+}
+
+impl CollisionBSPBuilder<'_> {
+    fn add_plane(&mut self, plane: Plane3D) -> u32 {
+        *self.plane_lookup.entry(plane_key(&plane)).or_insert_with(|| {
+            self.bsp.planes.items.push(ModelCollisionGeometryBSPPlane { plane });
+            (self.bsp.planes.items.len() - 1) as u32
+        })
+    }
+
+    fn add_vertex(&mut self, point: Vector3D) -> u32 {
+        *self.vertex_lookup.entry(vertex_key(point)).or_insert_with(|| {
+            self.bsp.vertices.items.push(ModelCollisionGeometryBSPVertex { point, first_edge: BSP_NULL });
+            (self.bsp.vertices.items.len() - 1) as u32
+        })
+    }
+
+    fn add_surfaces(&mut self, triangles: &[CollisionTriangle]) {
+        for (triangle_index, triangle) in triangles.iter().enumerate() {
+            if is_degenerate_triangle(&triangle.vertices) {
+                continue
+            }
+
+            let vertices = triangle.vertices.map(|v| self.add_vertex(v));
+            let [a, b, c] = vertices;
+            if a == b || b == c || a == c {
+                continue
+            }
+
+            let [a, b, c] = vertices.map(|v| self.bsp.vertices.items[v as usize].point);
+            let normal = cross(b - a, c - a).normalize();
+            let plane = self.add_plane(Plane3D { vector: normal, d: normal.dot(&a) });
+
+            self.bsp.surfaces.items.push(ModelCollisionGeometryBSPSurface {
+                plane,
+                first_edge: BSP_NULL,
+                flags: triangle.flags.clone(),
+                material: triangle.material,
+                ..Default::default()
+            });
+            self.surface_triangles.push(triangle_index);
+            self.surface_vertices.push(vertices);
+        }
+    }
+
+    /// Connect surfaces with edges.
+    ///
+    /// An edge's left surface goes from the start vertex to the end vertex, continuing to the forward edge, and its right
+    /// surface goes from the end vertex to the start vertex, continuing to the reverse edge.
+    fn add_edges(&mut self) {
+        let mut edge_lookup: HashMap<(u32, u32), u32> = HashMap::new();
+
+        for surface in 0..self.surface_vertices.len() {
+            let vertices = self.surface_vertices[surface];
+            let mut surface_edges = [0u32; 3];
+
+            for (i, surface_edge) in surface_edges.iter_mut().enumerate() {
+                let (start, end) = (vertices[i], vertices[(i + 1) % 3]);
+                let key = (start.min(end), start.max(end));
+
+                *surface_edge = match edge_lookup.get(&key).copied() {
+                    Some(edge) if self.bsp.edges.items[edge as usize].right_surface == BSP_NULL => {
+                        let existing = &mut self.bsp.edges.items[edge as usize];
+                        existing.right_surface = surface as u32;
+                        if existing.start_vertex == start {
+                            let (a, b) = (self.vertex_position(start), self.vertex_position(end));
+                            self.result.warnings.push(Diagnostic::new("CBS001", format!("Surfaces sharing the edge from {a} to {b} face opposite ways; one of them may be flipped")));
+                        }
+                        edge
+                    },
+                    Some(_) => {
+                        let (a, b) = (self.vertex_position(start), self.vertex_position(end));
+                        self.result.errors.push(Diagnostic::new("CBS002", format!("Edge from {a} to {b} is shared by more than two surfaces")));
+                        self.new_edge(start, end, surface as u32)
+                    },
+                    None => {
+                        let edge = self.new_edge(start, end, surface as u32);
+                        edge_lookup.insert(key, edge);
+                        edge
+                    }
+                };
+            }
+
+            for i in 0..3 {
+                let next = surface_edges[(i + 1) % 3];
+                let edge = &mut self.bsp.edges.items[surface_edges[i] as usize];
+                if edge.left_surface == surface as u32 {
+                    edge.forward_edge = next;
+                }
+                else {
+                    edge.reverse_edge = next;
+                }
+            }
+            self.bsp.surfaces.items[surface].first_edge = surface_edges[0];
+        }
+
+        // Two-sided surfaces (e.g. fences) are allowed to have open edges.
+        for edge in &self.bsp.edges.items {
+            if edge.right_surface == BSP_NULL && !self.bsp.surfaces.items[edge.left_surface as usize].flags.two_sided {
+                let (a, b) = (self.vertex_position(edge.start_vertex), self.vertex_position(edge.end_vertex));
+                self.result.errors.push(Diagnostic::new("CBS003", format!("Open edge from {a} to {b}")));
+            }
+        }
+    }
+
+    fn new_edge(&mut self, start: u32, end: u32, surface: u32) -> u32 {
+        let edge = self.bsp.edges.items.len() as u32;
+        self.bsp.edges.items.push(ModelCollisionGeometryBSPEdge {
+            start_vertex: start,
+            end_vertex: end,
+            forward_edge: BSP_NULL,
+            reverse_edge: BSP_NULL,
+            left_surface: surface,
+            right_surface: BSP_NULL
+        });
+        for vertex in [start, end] {
+            let vertex = &mut self.bsp.vertices.items[vertex as usize];
+            if vertex.first_edge == BSP_NULL {
+                vertex.first_edge = edge;
+            }
+        }
+        edge
+    }
+
+    fn vertex_position(&self, vertex: u32) -> String {
+        jms_position(self.bsp.vertices.items[vertex as usize].point)
+    }
+
+    fn plane(&self, plane: u32) -> Plane3D {
+        self.bsp.planes.items[plane as usize].plane
+    }
+
+    fn is_two_sided(&self, surface: u32) -> bool {
+        self.bsp.surfaces.items[surface as usize].flags.two_sided
+    }
+
+    /// Pick the plane that splits the fewest fragments while keeping both sides balanced.
+    ///
+    /// Portals are only used once no surfaces are left, since surfaces decide which space is solid.
+    fn choose_splitter(&self, fragments: &[Fragment]) -> u32 {
+        let surfaces: Vec<&Fragment> = fragments.iter().filter(|f| f.surface.is_some()).collect();
+        let candidates = if surfaces.is_empty() { fragments.iter().collect() } else { surfaces };
+
+        let step = (candidates.len() / MAX_SPLITTER_CANDIDATES).max(1);
+        let mut best = (usize::MAX, candidates[0].plane);
+
+        for candidate in candidates.iter().step_by(step).take(MAX_SPLITTER_CANDIDATES).map(|f| f.plane) {
+            let plane = self.plane(candidate);
+            let (mut front, mut back, mut spanning) = (0usize, 0usize, 0usize);
+            for fragment in fragments {
+                match classify(&fragment.points, |p| p.distance_from_plane(&plane)) {
+                    Side::Front => front += 1,
+                    Side::Back => back += 1,
+                    Side::Spanning => spanning += 1,
+                    Side::On => ()
+                }
+            }
+
+            let score = spanning * 4 + front.abs_diff(back);
+            if score < best.0 {
+                best = (score, candidate);
+            }
+        }
+
+        best.1
+    }
+
+    /// Build a 3D node, returning its index.
+    ///
+    /// `boundary` contains fragments of surfaces that face into the space this node divides. `solid` is whether the
+    /// space is solid if no surfaces on the node's plane say otherwise.
+    fn build_3d_node(&mut self, fragments: Vec<Fragment>, boundary: Vec<Fragment>, solid: bool) -> u32 {
+        let splitter = self.choose_splitter(&fragments);
+        let plane = self.plane(splitter);
+
+        let (mut front, mut back) = (Vec::new(), Vec::new());
+        let (mut front_boundary, mut back_boundary) = (Vec::new(), Vec::new());
+        let (mut front_open, mut back_open) = (false, false);
+        let (mut front_closed, mut back_closed) = (false, false);
+
+        let sort = |fragment: Fragment, front: &mut Vec<Fragment>, back: &mut Vec<Fragment>| {
+            match classify(&fragment.points, |p| p.distance_from_plane(&plane)) {
+                Side::Front | Side::On => front.push(fragment),
+                Side::Back => back.push(fragment),
+                Side::Spanning => {
+                    let (front_points, back_points) = split_polygon(&fragment.points, &plane, PLANE_EPSILON);
+                    if front_points.len() >= 3 {
+                        front.push(Fragment { points: front_points, ..fragment.clone() });
+                    }
+                    if back_points.len() >= 3 {
+                        back.push(Fragment { points: back_points, ..fragment });
+                    }
+                }
+            }
+        };
+
+        for fragment in fragments {
+            if classify(&fragment.points, |p| p.distance_from_plane(&plane)) != Side::On {
+                sort(fragment, &mut front, &mut back);
+                continue
+            }
+
+            // Portals on the plane are no longer needed.
+            let Some(surface) = fragment.surface else {
+                continue
+            };
+
+            // Surfaces on the plane bound the space on the side they face, and the space behind one-sided surfaces is
+            // solid. Two-sided surfaces bound both sides.
+            let two_sided = self.is_two_sided(surface);
+            let faces_front = self.plane(fragment.plane).vector.dot(&plane.vector) > 0.0;
+            if faces_front {
+                front_open = true;
+                back_open |= two_sided;
+                back_closed |= !two_sided;
+                if two_sided {
+                    back_boundary.push(fragment.clone());
+                }
+                front_boundary.push(fragment);
+            }
+            else {
+                back_open = true;
+                front_open |= two_sided;
+                front_closed |= !two_sided;
+                if two_sided {
+                    front_boundary.push(fragment.clone());
+                }
+                back_boundary.push(fragment);
+            }
+        }
+
+        for fragment in boundary {
+            sort(fragment, &mut front_boundary, &mut back_boundary);
+        }
+
+        let front_solid = !front_open && (front_closed || solid);
+        let back_solid = !back_open && (back_closed || solid);
+
+        let index = self.bsp.bsp3d_nodes.items.len();
+        self.bsp.bsp3d_nodes.items.push(ModelCollisionGeometryBSP3DNode { plane: splitter, back_child: BSP_NULL, front_child: BSP_NULL });
+
+        let front_child = self.build_3d_child(front, front_boundary, front_solid);
+        let back_child = self.build_3d_child(back, back_boundary, back_solid);
+
+        let node = &mut self.bsp.bsp3d_nodes.items[index];
+        node.front_child = front_child;
+        node.back_child = back_child;
+        index as u32
+    }
+
+    fn build_3d_child(&mut self, fragments: Vec<Fragment>, boundary: Vec<Fragment>, solid: bool) -> u32 {
+        if !fragments.is_empty() {
+            self.build_3d_node(fragments, boundary, solid)
+        }
+        else if solid {
+            BSP_NULL
+        }
+        else {
+            self.add_leaf(boundary)
+        }
+    }
+
+    /// Add a leaf for empty space, returning its child index.
+    fn add_leaf(&mut self, boundary: Vec<Fragment>) -> u32 {
+        let mut planes: BTreeMap<u32, Vec<u32>> = BTreeMap::new();
+        for fragment in boundary {
+            let Some(surface) = fragment.surface else {
+                continue
+            };
+            let surfaces = planes.entry(fragment.plane).or_default();
+            if !surfaces.contains(&surface) {
+                surfaces.push(surface);
+            }
+        }
+
+        let first_bsp2d_reference = self.bsp.bsp2d_references.items.len() as u32;
+        let mut all_surfaces = Vec::new();
+        let mut contains_double_sided_surfaces = false;
+
+        for (plane, mut surfaces) in planes {
+            surfaces.sort_unstable();
+            contains_double_sided_surfaces |= surfaces.iter().any(|s| self.is_two_sided(*s));
+            all_surfaces.extend_from_slice(&surfaces);
+
+            let bsp2d_node = match self.bsp2d_lookup.get(&(plane, surfaces.clone())) {
+                Some(n) => *n,
+                None => {
+                    let n = self.build_bsp2d(plane, &surfaces);
+                    self.bsp2d_lookup.insert((plane, surfaces), n);
+                    n
+                }
+            };
+            self.bsp.bsp2d_references.items.push(ModelCollisionGeometryBSP2DReference { plane, bsp2d_node });
+        }
+
+        let mut leaf = ModelCollisionGeometryBSPLeaf {
+            bsp2d_reference_count: (self.bsp.bsp2d_references.items.len() as u32 - first_bsp2d_reference) as u16,
+            first_bsp2d_reference,
+            ..Default::default()
+        };
+        leaf.flags.contains_double_sided_surfaces = contains_double_sided_surfaces;
+
+        self.bsp.leaves.items.push(leaf);
+        self.leaf_surfaces.push(all_surfaces);
+        (self.bsp.leaves.items.len() - 1) as u32 | BSP_LEAF_FLAG
+    }
+
+    /// Build a 2D BSP for finding which surface on a plane contains a point.
+    fn build_bsp2d(&mut self, plane: u32, surfaces: &[u32]) -> u32 {
+        let normal = self.plane(plane).vector;
+        let polygons = surfaces
+            .iter()
+            .map(|s| (*s, self.surface_vertices[*s as usize].map(|v| project(self.bsp.vertices.items[v as usize].point, normal)).to_vec()))
+            .collect();
+        self.build_bsp2d_node(polygons)
+    }
+
+    fn build_bsp2d_node(&mut self, polygons: Vec<(u32, Vec<Vector2D>)>) -> u32 {
+        if polygons.len() == 1 {
+            return polygons[0].0 | BSP_LEAF_FLAG
+        }
+
+        // Use polygon edges as splitting lines. Polygons that cross a line go on both sides.
+        let mut best: Option<(usize, Plane2D)> = None;
+        for (_, points) in polygons.iter().take(MAX_SPLITTER_CANDIDATES) {
+            for (index, point) in points.iter().enumerate() {
+                let direction = points[(index + 1) % points.len()] - *point;
+                let normal = Vector2D { x: -direction.y, y: direction.x }.normalize();
+                let line = Plane2D { vector: normal, d: normal.dot(point) };
+
+                let (mut front, mut back, mut spanning) = (0usize, 0usize, 0usize);
+                for (_, other) in &polygons {
+                    match classify(other, |p| p.distance_from_plane(&line)) {
+                        Side::Front | Side::On => front += 1,
+                        Side::Back => back += 1,
+                        Side::Spanning => spanning += 1
+                    }
+                }
+
+                if front + spanning == polygons.len() || back + spanning == polygons.len() {
+                    continue
+                }
+
+                let score = spanning * 4 + front.abs_diff(back);
+                if best.is_none_or(|b| score < b.0) {
+                    best = Some((score, line));
+                }
+            }
+        }
+
+        let Some((_, line)) = best else {
+            let surface = polygons[0].0;
+            let [a, b, c] = self.surface_vertices[surface as usize].map(|v| self.bsp.vertices.items[v as usize].point);
+            let centroid = (a + b + c).scale(1.0 / 3.0);
+            self.result.warnings.push(Diagnostic::new("CBS004", format!("{} coplanar surfaces overlap near {}", polygons.len(), jms_position(centroid))));
+            return surface | BSP_LEAF_FLAG
+        };
+
+        let (mut right, mut left) = (Vec::new(), Vec::new());
+        for polygon in polygons {
+            match classify(&polygon.1, |p| p.distance_from_plane(&line)) {
+                Side::Front | Side::On => right.push(polygon),
+                Side::Back => left.push(polygon),
+                Side::Spanning => {
+                    right.push(polygon.clone());
+                    left.push(polygon);
+                }
+            }
+        }
+
+        let index = self.bsp.bsp2d_nodes.items.len();
+        self.bsp.bsp2d_nodes.items.push(ModelCollisionGeometryBSP2DNode { plane: line, left_child: BSP_NULL, right_child: BSP_NULL });
+        let right_child = self.build_bsp2d_node(right);
+        let left_child = self.build_bsp2d_node(left);
+
+        let node = &mut self.bsp.bsp2d_nodes.items[index];
+        node.right_child = right_child;
+        node.left_child = left_child;
+        index as u32
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+enum TreeSide {
+    Node(u32),
+    Leaf(usize),
+    Solid,
+    Outside
+}
+
+impl TreeSide {
+    fn from_child(child: u32) -> TreeSide {
+        if child == BSP_NULL {
+            TreeSide::Solid
+        }
+        else if child & BSP_LEAF_FLAG != 0 {
+            TreeSide::Leaf((child & !BSP_LEAF_FLAG) as usize)
+        }
+        else {
+            TreeSide::Node(child)
+        }
+    }
+}
+
+/// Polygon between the two sides of a BSP node.
+struct TreePortal {
+    plane: Plane3D,
+    points: Vec<Vector3D>,
+
+    /// Sides in front of and behind the plane
+    sides: [TreeSide; 2]
+}
+
+/// Finds connections between leaves by splitting polygons down the tree.
+struct LeafConnector<'a> {
+    bsp: &'a ModelCollisionGeometryBSP,
+    portals: Vec<TreePortal>,
+    sides: HashMap<TreeSide, Vec<usize>>,
+    size: f32
+}
+
+impl LeafConnector<'_> {
+    fn add(&mut self, portal: TreePortal) {
+        if portal.points.len() < 3 {
+            return
+        }
+        let index = self.portals.len();
+        for side in portal.sides {
+            if matches!(side, TreeSide::Node(_) | TreeSide::Leaf(_)) {
+                self.sides.entry(side).or_default().push(index);
+            }
+        }
+        self.portals.push(portal);
+    }
+
+    /// Get a polygon on the plane that is bigger than everything in the BSP.
+    fn base_polygon(&self, plane: &Plane3D) -> Vec<Vector3D> {
+        let normal = plane.vector;
+        let axis = if normal.x.abs() < 0.6 { Vector3D { x: 1.0, y: 0.0, z: 0.0 } } else { Vector3D { x: 0.0, y: 1.0, z: 0.0 } };
+        let u = cross(normal, axis).normalize();
+        let v = cross(normal, u);
+        let center = normal.scale(plane.d);
+        let (u, v) = (u.scale(self.size), v.scale(self.size));
+        vec![center - u - v, center + u - v, center + u + v, center - u + v]
+    }
+
+    fn connect(&mut self, node_index: u32) {
+        let Some(node) = self.bsp.bsp3d_nodes.items.get(node_index as usize) else {
+            return
+        };
+        let Some(plane) = self.bsp.planes.items.get(node.plane as usize).map(|p| p.plane) else {
+            return
+        };
+        let this = TreeSide::Node(node_index);
+        let front = TreeSide::from_child(node.front_child);
+        let back = TreeSide::from_child(node.back_child);
+        let bounding = self.sides.remove(&this).unwrap_or_default();
+
+        // The new portal between both children is the node's plane cut down to the space the node divides.
+        let mut points = self.base_polygon(&plane);
+        for p in &bounding {
+            let portal = &self.portals[*p];
+            let (front_points, back_points) = split_polygon(&points, &portal.plane, CONNECTION_EPSILON);
+            points = if portal.sides[0] == this { front_points } else { back_points };
+            if points.len() < 3 {
+                break
+            }
+        }
+
+        // Portals around this node now go around its children.
+        for p in bounding {
+            let slot = if self.portals[p].sides[0] == this { 0 } else { 1 };
+            let other = self.portals[p].sides[1 - slot];
+            if let Some(list) = self.sides.get_mut(&other) {
+                list.retain(|i| *i != p);
+            }
+
+            let portal_plane = self.portals[p].plane;
+            let (front_points, back_points) = split_polygon(&self.portals[p].points, &plane, CONNECTION_EPSILON);
+            for (points, child) in [(front_points, front), (back_points, back)] {
+                let mut sides = self.portals[p].sides;
+                sides[slot] = child;
+                self.add(TreePortal { plane: portal_plane, points, sides });
+            }
+        }
+
+        self.add(TreePortal { plane, points, sides: [front, back] });
+
+        for child in [front, back] {
+            if let TreeSide::Node(child) = child {
+                self.connect(child);
+            }
+        }
+    }
+}
@@ -0,0 +1,137 @@
+use primitives::primitive::Vector3D;
+use crate::tag::result::TagResult;
+use super::*;
+
+/// Make a cube from (-1,-1,-1) to (1,1,1) with faces pointing outward (or inward if `inward` is set).
+fn cube(inward: bool) -> Vec<CollisionTriangle> {
+    let corner = |i: usize| Vector3D {
+        x: if i & 1 != 0 { 1.0 } else { -1.0 },
+        y: if i & 2 != 0 { 1.0 } else { -1.0 },
+        z: if i & 4 != 0 { 1.0 } else { -1.0 }
+    };
+
+    // Counterclockwise when viewed from outside
+    let faces = [
+        [0, 2, 3, 1], // -z
+        [4, 5, 7, 6], // +z
+        [0, 1, 5, 4], // -y
+        [2, 6, 7, 3], // +y
+        [0, 4, 6, 2], // -x
+        [1, 3, 7, 5]  // +x
+    ];
+
+    let mut triangles = Vec::new();
+    for [a, b, c, d] in faces {
+        for mut vertices in [[a, b, c], [a, c, d]] {
+            if inward {
+                vertices.reverse();
+            }
+            triangles.push(CollisionTriangle { vertices: vertices.map(corner), ..Default::default() });
+        }
+    }
+    triangles
+}
+
+#[test]
+fn compile_closed_model() {
+    let mut result = TagResult::default();
+    let compiled = compile_collision_bsp(&cube(false), &[], &mut result);
+    assert!(result.is_ok(), "{:?}", result.errors);
+    assert!(result.warnings.is_empty(), "{:?}", result.warnings);
+
+    let bsp = &compiled.bsp;
+    assert_eq!(12, bsp.surfaces.items.len());
+    assert_eq!(6, bsp.planes.items.len());
+    assert_eq!(8, bsp.vertices.items.len());
+    assert_eq!(18, bsp.edges.items.len());
+    assert!(bsp.edges.items.iter().all(|e| e.right_surface != BSP_NULL && e.forward_edge != BSP_NULL && e.reverse_edge != BSP_NULL));
+
+    // Walking the edges of each surface goes around its triangle.
+    for (index, surface) in bsp.surfaces.items.iter().enumerate() {
+        let mut edge = surface.first_edge;
+        for _ in 0..3 {
+            let e = &bsp.edges.items[edge as usize];
+            edge = if e.left_surface == index as u32 { e.forward_edge } else { e.reverse_edge };
+        }
+        assert_eq!(surface.first_edge, edge);
+    }
+
+    // Models are solid inside.
+    assert_eq!(None, find_leaf(bsp, Vector3D::zero()));
+    let outside = find_leaf(bsp, Vector3D { x: 0.0, y: 0.0, z: 2.0 }).expect("outside should be empty");
+    let leaf = &bsp.leaves.items[outside];
+    assert!(leaf.bsp2d_reference_count > 0);
+    assert!(compiled.leaf_surfaces[outside].iter().all(|s| bsp.planes.items[bsp.surfaces.items[*s as usize].plane as usize].plane.vector.z == 1.0));
+}
+
+#[test]
+fn compile_closed_level() {
+    let mut result = TagResult::default();
+    let compiled = compile_collision_bsp(&cube(true), &[], &mut result);
+    assert!(result.is_ok(), "{:?}", result.errors);
+
+    // Levels are solid outside.
+    let inside = find_leaf(&compiled.bsp, Vector3D::zero()).expect("inside should be empty");
+    assert_eq!(None, find_leaf(&compiled.bsp, Vector3D { x: 0.0, y: 0.0, z: 2.0 }));
+    assert_eq!(12, compiled.leaf_surfaces[inside].len());
+}
+
+#[test]
+fn report_open_edges_and_skip_degenerate_triangles() {
+    let mut triangles = cube(true);
+    triangles.truncate(10);
+    triangles.push(CollisionTriangle { vertices: [Vector3D::zero(), Vector3D { x: 1.0, y: 0.0, z: 0.0 }, Vector3D { x: 2.0, y: 0.0, z: 0.0 }], ..Default::default() });
+    assert!(is_degenerate_triangle(&triangles[10].vertices));
+
+    let mut result = TagResult::default();
+    let compiled = compile_collision_bsp(&triangles, &[], &mut result);
+    assert_eq!(10, compiled.bsp.surfaces.items.len());
+    assert_eq!((0..10).collect::<Vec<usize>>(), compiled.surface_triangles);
+    assert_eq!(4, result.errors.len(), "{:?}", result.errors);
+    assert!(result.errors.iter().all(|e| e.starts_with("Open edge")));
+
+    // Two-sided surfaces can have open edges.
+    for triangle in &mut triangles {
+        triangle.flags.two_sided = true;
+    }
+    let mut result = TagResult::default();
+    compile_collision_bsp(&triangles[..2], &[], &mut result);
+    assert!(result.is_ok(), "{:?}", result.errors);
+}
+
+#[test]
+fn find_connections_between_leaves() {
+    let corner = |y: f32, z: f32| Vector3D { x: 0.0, y, z };
+    let portal = [
+        [corner(-1.0, -1.0), corner(1.0, -1.0), corner(1.0, 1.0)],
+        [corner(-1.0, -1.0), corner(1.0, 1.0), corner(-1.0, 1.0)]
+    ];
+
+    let mut result = TagResult::default();
+    let compiled = compile_collision_bsp(&cube(true), &portal, &mut result);
+    assert!(result.is_ok(), "{:?}", result.errors);
+
+    // Portals do not collide, but they split leaves.
+    assert_eq!(12, compiled.bsp.surfaces.items.len());
+    let left = find_leaf(&compiled.bsp, Vector3D { x: -0.5, y: 0.0, z: 0.0 }).unwrap();
+    let right = find_leaf(&compiled.bsp, Vector3D { x: 0.5, y: 0.0, z: 0.0 }).unwrap();
+    assert_ne!(left, right);
+
+    let connections = find_leaf_connections(&compiled.bsp);
+    assert!(connections.iter().all(|c| c.leaves[0].is_some() && c.leaves[1].is_some()));
+    let between: Vec<&LeafConnection> = connections.iter().filter(|c| c.plane.vector.x.abs() == 1.0 && c.plane.d == 0.0).collect();
+    assert!(!between.is_empty());
+    for connection in between {
+        let mut leaves = connection.leaves.map(|l| l.unwrap());
+        leaves.sort();
+        let mut expected = [left, right];
+        expected.sort();
+        assert_eq!(expected, leaves);
+    }
+
+    // Removing a wall lets the inside reach the outside.
+    let mut open = cube(true);
+    open.truncate(10);
+    let compiled = compile_collision_bsp(&open, &[], &mut TagResult::default());
+    assert!(find_leaf_connections(&compiled.bsp).iter().any(|c| c.leaves.contains(&None)));
+}
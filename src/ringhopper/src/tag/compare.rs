@@ -4,7 +4,7 @@ use primitives::dynamic::{DynamicEnum, DynamicTagData, DynamicTagDataArray, Dyna
 use primitives::primitive::{Address, Angle, BSPVertexData, ColorARGB, Pixel32, ColorRGB, CompressedFloat, CompressedVector2D, CompressedVector3D, Data, Euler2D, Euler3D, FileData, ID, Index, Matrix3x3, Plane2D, Plane3D, Quaternion, Rectangle, ScenarioScriptNodeValue, String32, TagGroup, TagReference, UTF16String, Vector2D, Vector2DInt, Vector3D, Matrix2x3};
 use primitives::tag::PrimaryTagStructDyn;
 
-#[derive(Clone)]
+#[derive(Clone, Debug)]
 pub struct TagComparisonDifference {
     pub depth: usize,
     pub path: String,
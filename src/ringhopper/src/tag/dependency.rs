@@ -8,7 +8,7 @@ use primitives::engine::Engine;
 use primitives::error::{Error, RinghopperResult};
 use primitives::primitive::{HALO_PATH_SEPARATOR_STR, TagGroup, TagPath, TagReference};
 use primitives::tag::{for_each_field, for_each_field_mut};
-use crate::tag::result::TagResult;
+use crate::tag::result::{Diagnostic, TagResult};
 use crate::tag::tree::{CachingTagTree, CachingTagTreeWriteStrategy, iterate_through_all_tags, TagFilter, TagTree, VirtualTagsDirectory};
 
 /// Iterate through each [`TagReference`] of a block.
@@ -244,23 +244,23 @@ pub fn refactor_paths_for_tag_tree(
             let mut result = TagResult::default();
 
             let Some((index, actual_path)) = dir.path_for_tag(old_path) else {
-                result.errors.push(format!("Couldn't get the fs path to {old_path}. Aborting..."));
+                result.errors.push(Diagnostic::new("REF001", format!("Couldn't get the fs path to {old_path}. Aborting...")));
                 results.insert(old_path.to_owned(), result);
                 break;
             };
             let new_actual_path = dir.get_directory(index).unwrap().join(new_path.to_native_path());
             let Some(parent) = new_actual_path.parent() else {
-                result.errors.push(format!("Couldn't find the parent to {}. Aborting...", new_actual_path.display()));
+                result.errors.push(Diagnostic::new("REF002", format!("Couldn't find the parent to {}. Aborting...", new_actual_path.display())));
                 results.insert(old_path.to_owned(), result);
                 break;
             };
             if let Err(e) = std::fs::create_dir_all(parent) {
-                result.errors.push(format!("Couldn't create dirs {}: {e:?}; Aborting...", parent.display()));
+                result.errors.push(Diagnostic::new("REF003", format!("Couldn't create dirs {}: {e:?}; Aborting...", parent.display())));
                 results.insert(old_path.to_owned(), result);
                 break;
             };
             if let Err(e) = std::fs::rename(&actual_path, &new_actual_path) {
-                result.errors.push(format!("Couldn't create dirs {}: {e:?}; Aborting...", parent.display()));
+                result.errors.push(Diagnostic::new("REF003", format!("Couldn't create dirs {}: {e:?}; Aborting...", parent.display())));
                 results.insert(old_path.to_owned(), result);
                 break;
             };
@@ -340,7 +340,7 @@ pub fn refactor_paths_for_tag_tree(
                 Ok(n) => n,
                 Err(e) => {
                     let mut l = context.results.lock().unwrap();
-                    l.get_mut(tag).unwrap().errors.push(format!("Can't open {tag}: {e}"));
+                    l.get_mut(tag).unwrap().errors.push(Diagnostic::new("REF004", format!("Can't open {tag}: {e}")));
                     continue;
                 }
             };
@@ -388,7 +388,7 @@ pub fn refactor_paths_for_tag_tree(
                     results.get_mut(i).unwrap()
                 }
             };
-            r.errors.push(format!("Could not fix dependencies in {i}: {e} (you will need to fix the dependencies yourself)"));
+            r.errors.push(Diagnostic::new("REF005", format!("Could not fix dependencies in {i}: {e} (you will need to fix the dependencies yourself)")));
         }
     }
 
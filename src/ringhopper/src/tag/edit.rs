@@ -0,0 +1,512 @@
+use primitives::dynamic::{DynamicTagData, DynamicTagDataType, SimplePrimitiveType};
+use primitives::error::{Error, RinghopperResult};
+use primitives::primitive::{parse_range, Address, Angle, BSPVertexData, ColorARGB, ColorRGB, CompressedFloat, CompressedVector2D, CompressedVector3D, Data, Euler2D, Euler3D, FileData, ID, Index, Matrix2x3, Matrix3x3, Pixel32, Pixel32Bytes, Plane2D, Plane3D, Quaternion, Rectangle, ScenarioScriptNodeValue, String32, TagGroup, TagPath, TagReference, UTF16String, Vector2D, Vector2DInt, Vector3D};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use crate::tag::json::{data_from_json, data_to_json};
+
+#[cfg(test)]
+mod test;
+
+/// Get the value of every field matched by `matcher`, formatted with [`format_value`].
+pub fn get_values(data: &dyn DynamicTagData, matcher: &str) -> RinghopperResult<Vec<String>> {
+    validate(data, matcher)?;
+    let mut values = Vec::new();
+    data.foreach(matcher, |field| {
+        values.push(format_value(field.unwrap()));
+        true
+    });
+    Ok(values)
+}
+
+/// Set every field matched by `matcher` to `value`, parsed with [`parse_value`].
+///
+/// Returns the number of fields set.
+pub fn set_values(data: &mut dyn DynamicTagData, matcher: &str, value: &str) -> RinghopperResult<usize> {
+    validate(data, matcher)?;
+    let mut count = 0;
+    let mut result = Ok(());
+    data.foreach_mut(matcher, |field| {
+        result = parse_value(field.unwrap(), value);
+        count += 1;
+        result.is_ok()
+    });
+    result.map_err(|e| Error::InvalidTagData(format!("{matcher}: {e}")))?;
+    Ok(count)
+}
+
+/// Get the number of elements of every array or reflexive matched by `matcher`.
+pub fn count_elements(data: &dyn DynamicTagData, matcher: &str) -> RinghopperResult<Vec<usize>> {
+    validate(data, matcher)?;
+    let mut counts = Vec::new();
+    let mut subscriptable = true;
+    data.foreach(matcher, |field| {
+        match field.unwrap().as_array() {
+            Some(n) => counts.push(n.len()),
+            None => subscriptable = false
+        }
+        subscriptable
+    });
+    if !subscriptable {
+        return Err(Error::Other(format!("`{matcher}` does not refer to an array or reflexive")))
+    }
+    Ok(counts)
+}
+
+/// Insert `count` default elements into every reflexive matched by `matcher`.
+///
+/// If `matcher` ends with a single index (e.g. `nodes[2]`), the elements are inserted at that index. Otherwise, they
+/// are appended to the end.
+///
+/// Returns the total number of elements inserted.
+pub fn insert_elements(data: &mut dyn DynamicTagData, matcher: &str, count: usize) -> RinghopperResult<usize> {
+    let (reflexive_matcher, index) = match split_subscript(matcher) {
+        Some((reflexive_matcher, subscript)) => {
+            let index = subscript
+                .parse::<usize>()
+                .map_err(|_| Error::Other(format!("invalid matcher `{matcher}`: insertion index must be a single number")))?;
+            (reflexive_matcher, Some(index))
+        },
+        None => (matcher, None)
+    };
+
+    validate(data, reflexive_matcher)?;
+    let mut inserted = 0;
+    let mut result = Ok(());
+    data.foreach_mut(reflexive_matcher, |field| {
+        let Some(reflexive) = field.unwrap().as_reflexive_mut() else {
+            result = Err(Error::Other(format!("`{reflexive_matcher}` does not refer to a reflexive")));
+            return false
+        };
+        let length = reflexive.len();
+        let index = index.unwrap_or(length);
+        if index > length {
+            result = Err(Error::Other(format!("cannot insert at index {index} of `{reflexive_matcher}` (only {length} element(s))")));
+            return false
+        }
+        for _ in 0..count {
+            reflexive.insert_default(index);
+        }
+        inserted += count;
+        true
+    });
+    result.map(|_| inserted)
+}
+
+/// Delete elements from every reflexive matched by `matcher`.
+///
+/// `matcher` must end with a range of elements to delete (e.g. `nodes[1-3]` or `nodes[*]`).
+///
+/// Returns the total number of elements deleted.
+pub fn delete_elements(data: &mut dyn DynamicTagData, matcher: &str) -> RinghopperResult<usize> {
+    let Some((reflexive_matcher, subscript)) = split_subscript(matcher) else {
+        return Err(Error::Other(format!("invalid matcher `{matcher}`: expected a range of elements to delete (e.g. `{matcher}[0]`)")))
+    };
+
+    validate(data, reflexive_matcher)?;
+    let mut deleted = 0;
+    let mut result = Ok(());
+    data.foreach_mut(reflexive_matcher, |field| {
+        let Some(reflexive) = field.unwrap().as_reflexive_mut() else {
+            result = Err(Error::Other(format!("`{reflexive_matcher}` does not refer to a reflexive")));
+            return false
+        };
+        let ranges = match parse_range(subscript, reflexive.len()) {
+            Ok(n) => n,
+            Err(e) => {
+                result = Err(Error::Other(format!("invalid matcher `{matcher}`: {e}")));
+                return false
+            }
+        };
+
+        // Go backwards so removing an element does not shift the ones we have yet to remove.
+        for &(start, end) in ranges.iter().rev() {
+            for index in (start..=end).rev() {
+                reflexive.remove(index);
+            }
+            deleted += end - start + 1;
+        }
+        true
+    });
+    result.map(|_| deleted)
+}
+
+/// Format the value of a field as a string that can be read back with [`parse_value`].
+///
+/// Values with multiple components (e.g. vectors and colors) are comma-separated, angles are in degrees, addresses
+/// are hexadecimal, tag references are paths with an extension, and data is base64. Blocks, reflexives, and arrays are
+/// formatted as JSON.
+pub fn format_value(data: &dyn DynamicTagData) -> String {
+    match data.data_type() {
+        DynamicTagDataType::Block | DynamicTagDataType::Reflexive | DynamicTagDataType::Array => serde_json::to_string_pretty(&data_to_json(data)).unwrap(),
+        DynamicTagDataType::Enum => data.as_enum().unwrap().get_enum_string_value().to_owned(),
+        DynamicTagDataType::TagReference => match data.as_any().downcast_ref::<TagReference>().unwrap() {
+            TagReference::Set(path) => path.to_internal_path(),
+            TagReference::Null(_) => "null".to_owned()
+        },
+        DynamicTagDataType::Data => BASE64.encode(&data.as_any().downcast_ref::<Data>().unwrap().bytes),
+        DynamicTagDataType::FileData => BASE64.encode(&data.as_any().downcast_ref::<FileData>().unwrap().bytes),
+        DynamicTagDataType::BSPVertexData => BASE64.encode(&data.as_any().downcast_ref::<BSPVertexData>().unwrap().bytes),
+        DynamicTagDataType::UTF16String => match data.as_any().downcast_ref::<UTF16String>().unwrap().get_string_lossy() {
+            Ok(n) | Err(n) => n.as_str().to_owned()
+        },
+        DynamicTagDataType::SimplePrimitive(primitive_type) => {
+            macro_rules! format_as {
+                ($prim:ty) => {
+                    data.as_any().downcast_ref::<$prim>().unwrap().format()
+                };
+            }
+
+            match primitive_type {
+                SimplePrimitiveType::Bool => format_as!(bool),
+                SimplePrimitiveType::String32 => format_as!(String32),
+                SimplePrimitiveType::I8 => format_as!(i8),
+                SimplePrimitiveType::U8 => format_as!(u8),
+                SimplePrimitiveType::I16 => format_as!(i16),
+                SimplePrimitiveType::U16 => format_as!(u16),
+                SimplePrimitiveType::I32 => format_as!(i32),
+                SimplePrimitiveType::U32 => format_as!(u32),
+                SimplePrimitiveType::Size => format_as!(usize),
+                SimplePrimitiveType::Float => format_as!(f32),
+                SimplePrimitiveType::Angle => format_as!(Angle),
+                SimplePrimitiveType::Vector2D => format_as!(Vector2D),
+                SimplePrimitiveType::Vector3D => format_as!(Vector3D),
+                SimplePrimitiveType::Plane2D => format_as!(Plane2D),
+                SimplePrimitiveType::Plane3D => format_as!(Plane3D),
+                SimplePrimitiveType::Euler2D => format_as!(Euler2D),
+                SimplePrimitiveType::Euler3D => format_as!(Euler3D),
+                SimplePrimitiveType::Quaternion => format_as!(Quaternion),
+                SimplePrimitiveType::Matrix2x3 => format_as!(Matrix2x3),
+                SimplePrimitiveType::Matrix3x3 => format_as!(Matrix3x3),
+                SimplePrimitiveType::Vector2DInt => format_as!(Vector2DInt),
+                SimplePrimitiveType::Rectangle => format_as!(Rectangle),
+                SimplePrimitiveType::CompressedVector3D => format_as!(CompressedVector3D),
+                SimplePrimitiveType::CompressedVector2D => format_as!(CompressedVector2D),
+                SimplePrimitiveType::CompressedFloat => format_as!(CompressedFloat),
+                SimplePrimitiveType::ColorRGB => format_as!(ColorRGB),
+                SimplePrimitiveType::Pixel32 => format_as!(Pixel32),
+                SimplePrimitiveType::ColorARGB => format_as!(ColorARGB),
+                SimplePrimitiveType::Index => format_as!(Index),
+                SimplePrimitiveType::ID => format_as!(ID),
+                SimplePrimitiveType::TagGroup => format_as!(TagGroup),
+                SimplePrimitiveType::Address => format_as!(Address),
+                SimplePrimitiveType::ScenarioScriptNodeValue => format_as!(ScenarioScriptNodeValue),
+            }
+        }
+    }
+}
+
+/// Overwrite the value of a field with a string in the format used by [`format_value`].
+///
+/// Setting a tag reference to `null` keeps its group. Blocks, reflexives, and arrays are read as JSON, and reflexives
+/// cannot be shrunk this way.
+pub fn parse_value(data: &mut dyn DynamicTagData, value: &str) -> Result<(), String> {
+    match data.data_type() {
+        DynamicTagDataType::Block | DynamicTagDataType::Reflexive | DynamicTagDataType::Array => {
+            let json = serde_json::from_str(value).map_err(|e| format!("invalid JSON: {e}"))?;
+            data_from_json(data, &json).map_err(|e| e.to_string())
+        },
+
+        DynamicTagDataType::Enum => {
+            let enum_value = data.as_enum_mut().unwrap();
+            enum_value.set_enum_string_value(value).map_err(|_| {
+                format!("invalid enum value `{value}` (expected one of: {})", enum_value.get_possible_enum_string_values().join(", "))
+            })
+        },
+
+        DynamicTagDataType::TagReference => {
+            let reference = data.as_any_mut().downcast_mut::<TagReference>().unwrap();
+            *reference = if value == "null" {
+                TagReference::Null(reference.group())
+            }
+            else {
+                TagReference::Set(TagPath::from_path(value).map_err(|_| format!("invalid tag path `{value}` (expected a path with an extension, e.g. `weapons\\pistol\\pistol.weapon`)"))?)
+            };
+            Ok(())
+        },
+
+        DynamicTagDataType::Data => {
+            data.as_any_mut().downcast_mut::<Data>().unwrap().bytes = BASE64.decode(value).map_err(|e| format!("invalid base64: {e}"))?;
+            Ok(())
+        },
+        DynamicTagDataType::FileData => {
+            data.as_any_mut().downcast_mut::<FileData>().unwrap().bytes = BASE64.decode(value).map_err(|e| format!("invalid base64: {e}"))?;
+            Ok(())
+        },
+        DynamicTagDataType::BSPVertexData => {
+            data.as_any_mut().downcast_mut::<BSPVertexData>().unwrap().bytes = BASE64.decode(value).map_err(|e| format!("invalid base64: {e}"))?;
+            Ok(())
+        },
+
+        DynamicTagDataType::UTF16String => {
+            *data.as_any_mut().downcast_mut::<UTF16String>().unwrap() = UTF16String::from_str(value);
+            Ok(())
+        },
+
+        DynamicTagDataType::SimplePrimitive(primitive_type) => {
+            macro_rules! parse {
+                ($prim:ty) => {
+                    *data.as_any_mut().downcast_mut::<$prim>().unwrap() = <$prim>::parse(value)?
+                };
+            }
+
+            match primitive_type {
+                SimplePrimitiveType::Bool => parse!(bool),
+                SimplePrimitiveType::String32 => parse!(String32),
+                SimplePrimitiveType::I8 => parse!(i8),
+                SimplePrimitiveType::U8 => parse!(u8),
+                SimplePrimitiveType::I16 => parse!(i16),
+                SimplePrimitiveType::U16 => parse!(u16),
+                SimplePrimitiveType::I32 => parse!(i32),
+                SimplePrimitiveType::U32 => parse!(u32),
+                SimplePrimitiveType::Size => parse!(usize),
+                SimplePrimitiveType::Float => parse!(f32),
+                SimplePrimitiveType::Angle => parse!(Angle),
+                SimplePrimitiveType::Vector2D => parse!(Vector2D),
+                SimplePrimitiveType::Vector3D => parse!(Vector3D),
+                SimplePrimitiveType::Plane2D => parse!(Plane2D),
+                SimplePrimitiveType::Plane3D => parse!(Plane3D),
+                SimplePrimitiveType::Euler2D => parse!(Euler2D),
+                SimplePrimitiveType::Euler3D => parse!(Euler3D),
+                SimplePrimitiveType::Quaternion => parse!(Quaternion),
+                SimplePrimitiveType::Matrix2x3 => parse!(Matrix2x3),
+                SimplePrimitiveType::Matrix3x3 => parse!(Matrix3x3),
+                SimplePrimitiveType::Vector2DInt => parse!(Vector2DInt),
+                SimplePrimitiveType::Rectangle => parse!(Rectangle),
+                SimplePrimitiveType::CompressedVector3D => parse!(CompressedVector3D),
+                SimplePrimitiveType::CompressedVector2D => parse!(CompressedVector2D),
+                SimplePrimitiveType::CompressedFloat => parse!(CompressedFloat),
+                SimplePrimitiveType::ColorRGB => parse!(ColorRGB),
+                SimplePrimitiveType::Pixel32 => parse!(Pixel32),
+                SimplePrimitiveType::ColorARGB => parse!(ColorARGB),
+                SimplePrimitiveType::Index => parse!(Index),
+                SimplePrimitiveType::ID => parse!(ID),
+                SimplePrimitiveType::TagGroup => parse!(TagGroup),
+                SimplePrimitiveType::Address => parse!(Address),
+                SimplePrimitiveType::ScenarioScriptNodeValue => parse!(ScenarioScriptNodeValue),
+            }
+            Ok(())
+        }
+    }
+}
+
+fn validate(data: &dyn DynamicTagData, matcher: &str) -> RinghopperResult<()> {
+    data.validate_matcher(matcher).map_err(|e| Error::Other(format!("invalid matcher `{matcher}`: {e}")))
+}
+
+/// Split a matcher that ends with a subscript into the matcher before it and the contents of the subscript.
+fn split_subscript(matcher: &str) -> Option<(&str, &str)> {
+    let without_end = matcher.strip_suffix(']')?;
+    let start = without_end.rfind('[')?;
+    Some((&matcher[..start], &without_end[start + 1..]))
+}
+
+/// Conversion between simple primitives and strings.
+///
+/// Values with multiple components are written as comma-separated lists of their components.
+trait FieldValue: Sized {
+    /// Number of comma-separated components this value is made of.
+    const COMPONENTS: usize;
+
+    fn format(&self) -> String;
+    fn parse_components<'a>(components: &mut impl Iterator<Item = &'a str>) -> Result<Self, String>;
+
+    fn parse(value: &str) -> Result<Self, String> {
+        let components: Vec<&str> = value.split(',').map(str::trim).collect();
+        if components.len() != Self::COMPONENTS {
+            return Err(format!("expected {} comma-separated value(s), got {}", Self::COMPONENTS, components.len()))
+        }
+        Self::parse_components(&mut components.into_iter())
+    }
+}
+
+macro_rules! field_value_number {
+    ($($t:ty),*) => {$(
+        impl FieldValue for $t {
+            const COMPONENTS: usize = 1;
+            fn format(&self) -> String {
+                self.to_string()
+            }
+            fn parse_components<'a>(components: &mut impl Iterator<Item = &'a str>) -> Result<Self, String> {
+                let value = components.next().unwrap();
+                value.parse().map_err(|_| format!("cannot parse `{value}` as {}", stringify!($t)))
+            }
+        }
+    )*};
+}
+
+field_value_number!(i8, u8, i16, u16, i32, u32, usize, f32);
+
+/// Implement string conversion for a type that is converted to and from another type that implements it.
+macro_rules! field_value_via {
+    ($t:ty, $via:ty, $to:expr, $from:expr) => {
+        impl FieldValue for $t {
+            const COMPONENTS: usize = <$via>::COMPONENTS;
+            fn format(&self) -> String {
+                let to: fn(&$t) -> $via = $to;
+                to(self).format()
+            }
+            fn parse_components<'a>(components: &mut impl Iterator<Item = &'a str>) -> Result<Self, String> {
+                let from: fn($via) -> $t = $from;
+                <$via>::parse_components(components).map(from)
+            }
+        }
+    };
+}
+
+field_value_via!(Angle, f32, |a| a.to_degrees(), Angle::from_degrees);
+field_value_via!(CompressedFloat, f32, |v| (*v).into(), CompressedFloat::from);
+field_value_via!(CompressedVector2D, Vector2D, |v| (*v).into(), CompressedVector2D::from);
+field_value_via!(CompressedVector3D, Vector3D, |v| (*v).into(), CompressedVector3D::from);
+field_value_via!(Pixel32, Pixel32Bytes, |p| (*p).into(), Pixel32::from);
+field_value_via!(Matrix2x3, [Vector3D; 2], |m| m.vectors, |vectors| Matrix2x3 { vectors });
+field_value_via!(Matrix3x3, [Vector3D; 3], |m| m.vectors, |vectors| Matrix3x3 { vectors });
+
+/// Implement string conversion for a struct as a list of each of its fields' components in order.
+macro_rules! field_value_struct {
+    ($t:ty { $($field:ident: $field_type:ty),* }) => {
+        impl FieldValue for $t {
+            const COMPONENTS: usize = 0 $(+ <$field_type>::COMPONENTS)*;
+            fn format(&self) -> String {
+                [$(self.$field.format()),*].join(", ")
+            }
+            fn parse_components<'a>(components: &mut impl Iterator<Item = &'a str>) -> Result<Self, String> {
+                Ok(Self {
+                    $($field: <$field_type>::parse_components(components)?),*
+                })
+            }
+        }
+    };
+}
+
+field_value_struct!(Vector2D { x: f32, y: f32 });
+field_value_struct!(Vector3D { x: f32, y: f32, z: f32 });
+field_value_struct!(Quaternion { x: f32, y: f32, z: f32, w: f32 });
+field_value_struct!(Vector2DInt { x: i16, y: i16 });
+field_value_struct!(Rectangle { top: i16, left: i16, bottom: i16, right: i16 });
+field_value_struct!(Euler2D { yaw: Angle, pitch: Angle });
+field_value_struct!(Euler3D { yaw: Angle, pitch: Angle, roll: Angle });
+field_value_struct!(Plane2D { vector: Vector2D, d: f32 });
+field_value_struct!(Plane3D { vector: Vector3D, d: f32 });
+field_value_struct!(ColorRGB { red: f32, green: f32, blue: f32 });
+field_value_struct!(ColorARGB { alpha: f32, red: f32, green: f32, blue: f32 });
+field_value_struct!(Pixel32Bytes { alpha: u8, red: u8, green: u8, blue: u8 });
+
+impl<const N: usize> FieldValue for [Vector3D; N] {
+    const COMPONENTS: usize = N * Vector3D::COMPONENTS;
+    fn format(&self) -> String {
+        self.iter().map(Vector3D::format).collect::<Vec<String>>().join(", ")
+    }
+    fn parse_components<'a>(components: &mut impl Iterator<Item = &'a str>) -> Result<Self, String> {
+        let mut vectors = [Vector3D::default(); N];
+        for vector in &mut vectors {
+            *vector = Vector3D::parse_components(components)?;
+        }
+        Ok(vectors)
+    }
+}
+
+impl FieldValue for bool {
+    const COMPONENTS: usize = 1;
+    fn format(&self) -> String {
+        self.to_string()
+    }
+    fn parse_components<'a>(components: &mut impl Iterator<Item = &'a str>) -> Result<Self, String> {
+        match components.next().unwrap() {
+            "true" | "1" => Ok(true),
+            "false" | "0" => Ok(false),
+            n => Err(format!("cannot parse `{n}` as a boolean (expected `true` or `false`)"))
+        }
+    }
+}
+
+impl FieldValue for Index {
+    const COMPONENTS: usize = 1;
+    fn format(&self) -> String {
+        match self {
+            Some(n) => n.to_string(),
+            None => "null".to_owned()
+        }
+    }
+    fn parse_components<'a>(components: &mut impl Iterator<Item = &'a str>) -> Result<Self, String> {
+        match components.next().unwrap() {
+            "null" => Ok(None),
+            n => u16::parse(n).map(Some)
+        }
+    }
+}
+
+impl FieldValue for ID {
+    const COMPONENTS: usize = 2;
+    fn format(&self) -> String {
+        match (self.index(), self.salt()) {
+            (Some(index), Some(salt)) => format!("{index}, {salt}"),
+            _ => "null".to_owned()
+        }
+    }
+    fn parse_components<'a>(components: &mut impl Iterator<Item = &'a str>) -> Result<Self, String> {
+        let index = u16::parse_components(components)?;
+        let salt = u16::parse_components(components)?;
+        Ok(ID::new(Some(index), salt))
+    }
+    fn parse(value: &str) -> Result<Self, String> {
+        if value.trim() == "null" {
+            return Ok(ID::null())
+        }
+        let components: Vec<&str> = value.split(',').map(str::trim).collect();
+        if components.len() != Self::COMPONENTS {
+            return Err(format!("expected `null` or an index and salt, got `{value}`"))
+        }
+        Self::parse_components(&mut components.into_iter())
+    }
+}
+
+/// Implement string conversion for a 32-bit value that is written in hexadecimal.
+macro_rules! field_value_hex {
+    ($t:ty, $field:ident) => {
+        impl FieldValue for $t {
+            const COMPONENTS: usize = 1;
+            fn format(&self) -> String {
+                format!("0x{:08X}", self.$field)
+            }
+            fn parse_components<'a>(components: &mut impl Iterator<Item = &'a str>) -> Result<Self, String> {
+                let value = components.next().unwrap();
+                let parsed = match value.strip_prefix("0x").or_else(|| value.strip_prefix("0X")) {
+                    Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                    None => value.parse().ok()
+                };
+                parsed
+                    .map(|$field| Self { $field })
+                    .ok_or_else(|| format!("cannot parse `{value}` as a 32-bit integer"))
+            }
+        }
+    };
+}
+
+field_value_hex!(Address, address);
+field_value_hex!(ScenarioScriptNodeValue, data);
+
+impl FieldValue for String32 {
+    const COMPONENTS: usize = 1;
+    fn format(&self) -> String {
+        self.as_str().to_owned()
+    }
+    fn parse_components<'a>(components: &mut impl Iterator<Item = &'a str>) -> Result<Self, String> {
+        Self::parse(components.next().unwrap())
+    }
+    fn parse(value: &str) -> Result<Self, String> {
+        // Strings may contain commas, so take the value as-is.
+        String32::from_str(value).map_err(|e| e.to_string())
+    }
+}
+
+impl FieldValue for TagGroup {
+    const COMPONENTS: usize = 1;
+    fn format(&self) -> String {
+        self.as_str().to_owned()
+    }
+    fn parse_components<'a>(components: &mut impl Iterator<Item = &'a str>) -> Result<Self, String> {
+        let group = components.next().unwrap();
+        TagGroup::from_str(group).map_err(|_| format!("unknown tag group `{group}`"))
+    }
+}
@@ -0,0 +1,103 @@
+use definitions::{AnimationFrameInfoType, Model, ModelAnimations, ModelAnimationsAnimation, ModelNode, ModelShaderReference};
+use primitives::primitive::{Quaternion, Reflexive, String32, TagGroup, TagPath, TagReference, Vector3D};
+use super::*;
+
+fn test_model() -> Model {
+    Model {
+        nodes: Reflexive::new((0..4).map(|i| ModelNode {
+            name: String32::from_str(&format!("node {i}")).unwrap(),
+            parent_node_index: if i == 0 { None } else { Some(0) },
+            ..Default::default()
+        }).collect()),
+        shaders: Reflexive::new(vec![
+            ModelShaderReference { shader: TagReference::Set(TagPath::new("shaders\\metal", TagGroup::ShaderModel).unwrap()), ..Default::default() }
+        ]),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn get_and_set_values() {
+    let mut model = test_model();
+
+    assert_eq!(get_values(&model, "nodes[1-2].name").unwrap(), ["node 1", "node 2"]);
+    assert_eq!(get_values(&model, "nodes[*].parent_node_index").unwrap(), ["null", "0", "0", "0"]);
+    assert_eq!(get_values(&model, "shaders[0].shader").unwrap(), ["shaders\\metal.shader_model"]);
+
+    assert_eq!(set_values(&mut model, "nodes[1-e].default_translation", "1, -2.5, 3e3").unwrap(), 3);
+    assert_eq!(model.nodes.items[3].default_translation, Vector3D { x: 1.0, y: -2.5, z: 3000.0 });
+    assert_eq!(model.nodes.items[0].default_translation, Vector3D::default());
+    assert_eq!(get_values(&model, "nodes[2].default_translation").unwrap(), ["1, -2.5, 3000"]);
+
+    set_values(&mut model, "nodes[0].default_rotation", "0,0,0,1").unwrap();
+    assert_eq!(model.nodes.items[0].default_rotation, Quaternion { x: 0.0, y: 0.0, z: 0.0, w: 1.0 });
+
+    set_values(&mut model, "nodes[2].name", "renamed, with a comma").unwrap();
+    assert_eq!(model.nodes.items[2].name.as_str(), "renamed, with a comma");
+
+    set_values(&mut model, "nodes[*].parent_node_index", "null").unwrap();
+    assert!(model.nodes.items.iter().all(|n| n.parent_node_index.is_none()));
+
+    set_values(&mut model, "shaders[0].shader", "null").unwrap();
+    assert_eq!(model.shaders.items[0].shader, TagReference::Null(TagGroup::ShaderModel));
+    set_values(&mut model, "shaders[0].shader", "shaders\\glass.shader_transparent_glass").unwrap();
+    assert_eq!(model.shaders.items[0].shader.path().unwrap().group(), TagGroup::ShaderTransparentGlass);
+
+    let mut animations = ModelAnimations {
+        animations: Reflexive::new(vec![ModelAnimationsAnimation::default()]),
+        ..Default::default()
+    };
+    set_values(&mut animations, "animations[0].frame_info_type", "dx_dy_dyaw").unwrap();
+    assert_eq!(animations.animations.items[0].frame_info_type, AnimationFrameInfoType::DxDyDyaw);
+    set_values(&mut animations, "animations[0].frame_data", "AAEC/w==").unwrap();
+    assert_eq!(animations.animations.items[0].frame_data.bytes, [0, 1, 2, 255]);
+}
+
+#[test]
+fn reject_bad_values() {
+    let mut model = test_model();
+    assert!(set_values(&mut model, "nodes[0].default_translation", "1, 2").is_err());
+    assert!(set_values(&mut model, "nodes[0].default_translation", "1, 2, x").is_err());
+    assert!(set_values(&mut model, "nodes[0].parent_node_index", "65536").is_err());
+    assert!(set_values(&mut model, "nodes[4].name", "out of bounds").is_err());
+    assert!(set_values(&mut model, "nodes[0].not_a_field", "0").is_err());
+    assert!(set_values(&mut model, "shaders[0].shader", "no extension").is_err());
+    assert!(get_values(&model, "nodes[0").is_err());
+
+    let mut animations = ModelAnimations {
+        animations: Reflexive::new(vec![ModelAnimationsAnimation::default()]),
+        ..Default::default()
+    };
+    assert!(set_values(&mut animations, "animations[0].frame_info_type", "sideways").is_err());
+}
+
+#[test]
+fn insert_delete_and_count() {
+    let mut model = test_model();
+    assert_eq!(count_elements(&model, "nodes").unwrap(), [4]);
+    assert!(count_elements(&model, "node_list_checksum").is_err());
+
+    assert_eq!(insert_elements(&mut model, "nodes", 2).unwrap(), 2);
+    assert_eq!(model.nodes.items.len(), 6);
+    assert_eq!(model.nodes.items[5].name.as_str(), "");
+
+    assert_eq!(insert_elements(&mut model, "nodes[1]", 1).unwrap(), 1);
+    assert_eq!(model.nodes.items[0].name.as_str(), "node 0");
+    assert_eq!(model.nodes.items[1].name.as_str(), "");
+    assert_eq!(model.nodes.items[2].name.as_str(), "node 1");
+    assert!(insert_elements(&mut model, "nodes[8]", 1).is_err());
+    assert!(insert_elements(&mut model, "nodes[0-1]", 1).is_err());
+    assert!(insert_elements(&mut model, "node_list_checksum", 1).is_err());
+
+    // nodes are now: node 0, (blank), node 1, node 2, node 3, (blank), (blank)
+    assert_eq!(delete_elements(&mut model, "nodes[1,5-e]").unwrap(), 3);
+    assert_eq!(get_values(&model, "nodes[*].name").unwrap(), ["node 0", "node 1", "node 2", "node 3"]);
+
+    assert_eq!(delete_elements(&mut model, "nodes[0,2]").unwrap(), 2);
+    assert_eq!(get_values(&model, "nodes[*].name").unwrap(), ["node 1", "node 3"]);
+    assert!(delete_elements(&mut model, "nodes").is_err());
+    assert!(delete_elements(&mut model, "nodes[2]").is_err());
+
+    assert_eq!(delete_elements(&mut model, "nodes[*]").unwrap(), 2);
+    assert_eq!(count_elements(&model, "nodes").unwrap(), [0]);
+}
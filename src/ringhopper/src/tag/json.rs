@@ -0,0 +1,466 @@
+use primitives::dynamic::{DynamicTagData, DynamicTagDataType, SimplePrimitiveType};
+use primitives::error::{Error, RinghopperResult};
+use primitives::primitive::{Address, Angle, BSPVertexData, ColorARGB, ColorRGB, CompressedFloat, CompressedVector2D, CompressedVector3D, Data, Euler2D, Euler3D, FileData, ID, Index, Matrix2x3, Matrix3x3, Pixel32, Pixel32Bytes, Plane2D, Plane3D, Quaternion, Rectangle, ScenarioScriptNodeValue, String32, TagGroup, TagPath, TagReference, UTF16String, Vector2D, Vector2DInt, Vector3D};
+use primitives::tag::PrimaryTagStructDyn;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use serde_json::{json, Value};
+
+#[cfg(test)]
+mod test;
+
+/// Convert a tag into JSON.
+///
+/// The document is an object with the tag's `group` and its `data`. Every field is written, including cache-only
+/// fields, so the tag can be converted back with [`tag_from_json`] without losing anything.
+///
+/// Blocks are objects, reflexives and arrays are arrays, enums are strings, and data is base64. Floats are written
+/// exactly as stored, so angles are in radians. Infinite values are written as strings, and NaN is written as an
+/// object with its raw `bits` so the payload is kept.
+pub fn tag_to_json(tag: &dyn PrimaryTagStructDyn) -> Value {
+    json!({
+        "group": tag.group().as_str(),
+        "data": data_to_json(tag.as_dynamic())
+    })
+}
+
+/// Convert JSON written by [`tag_to_json`] back into a tag.
+///
+/// Fields that are missing from the JSON are left zeroed. Unknown fields are an error.
+pub fn tag_from_json(json: &Value) -> RinghopperResult<Box<dyn PrimaryTagStructDyn>> {
+    let group = match json.get("group") {
+        Some(Value::String(group)) => TagGroup::from_str(group).map_err(|_| Error::InvalidTagData(format!("unknown tag group `{group}`")))?,
+        _ => return Err(Error::InvalidTagData("expected a `group` string".to_owned()))
+    };
+    let data = json.get("data").ok_or_else(|| Error::InvalidTagData("expected a `data` object".to_owned()))?;
+
+    let mut tag = definitions::new_tag_of_group(group)?;
+    data_from_json(tag.as_mut_dynamic(), data)?;
+    Ok(tag)
+}
+
+/// Convert any tag data into JSON.
+pub fn data_to_json(data: &dyn DynamicTagData) -> Value {
+    match data.data_type() {
+        DynamicTagDataType::Block => Value::Object(data
+            .fields()
+            .iter()
+            .map(|f| (f.to_string(), data_to_json(data.get_field(f).unwrap())))
+            .collect()),
+
+        DynamicTagDataType::Reflexive | DynamicTagDataType::Array => {
+            let array = data.as_array().unwrap();
+            Value::Array((0..array.len()).map(|i| data_to_json(array.get_at_index(i).unwrap())).collect())
+        },
+
+        DynamicTagDataType::Enum => Value::String(data.as_enum().unwrap().get_enum_string_value().to_owned()),
+
+        DynamicTagDataType::TagReference => {
+            let reference = data.as_any().downcast_ref::<TagReference>().unwrap();
+            json!({
+                "group": reference.group().to_json(),
+                "path": reference.path().map(|p| p.path())
+            })
+        },
+
+        DynamicTagDataType::Data => Value::String(BASE64.encode(&data.as_any().downcast_ref::<Data>().unwrap().bytes)),
+        DynamicTagDataType::FileData => Value::String(BASE64.encode(&data.as_any().downcast_ref::<FileData>().unwrap().bytes)),
+        DynamicTagDataType::BSPVertexData => Value::String(BASE64.encode(&data.as_any().downcast_ref::<BSPVertexData>().unwrap().bytes)),
+
+        DynamicTagDataType::UTF16String => {
+            let string = data.as_any().downcast_ref::<UTF16String>().unwrap();
+            match string.get_string() {
+                Ok(n) => Value::String(n.as_str().to_owned()),
+                Err(_) => json!({ "utf16_base64": BASE64.encode(string.get_utf16_bytes()) })
+            }
+        },
+
+        DynamicTagDataType::SimplePrimitive(primitive_type) => {
+            macro_rules! to_json {
+                ($prim:ty) => {
+                    data.as_any().downcast_ref::<$prim>().unwrap().to_json()
+                };
+            }
+
+            match primitive_type {
+                SimplePrimitiveType::Bool => to_json!(bool),
+                SimplePrimitiveType::String32 => to_json!(String32),
+                SimplePrimitiveType::I8 => to_json!(i8),
+                SimplePrimitiveType::U8 => to_json!(u8),
+                SimplePrimitiveType::I16 => to_json!(i16),
+                SimplePrimitiveType::U16 => to_json!(u16),
+                SimplePrimitiveType::I32 => to_json!(i32),
+                SimplePrimitiveType::U32 => to_json!(u32),
+                SimplePrimitiveType::Size => to_json!(usize),
+                SimplePrimitiveType::Float => to_json!(f32),
+                SimplePrimitiveType::Angle => to_json!(Angle),
+                SimplePrimitiveType::Vector2D => to_json!(Vector2D),
+                SimplePrimitiveType::Vector3D => to_json!(Vector3D),
+                SimplePrimitiveType::Plane2D => to_json!(Plane2D),
+                SimplePrimitiveType::Plane3D => to_json!(Plane3D),
+                SimplePrimitiveType::Euler2D => to_json!(Euler2D),
+                SimplePrimitiveType::Euler3D => to_json!(Euler3D),
+                SimplePrimitiveType::Quaternion => to_json!(Quaternion),
+                SimplePrimitiveType::Matrix2x3 => to_json!(Matrix2x3),
+                SimplePrimitiveType::Matrix3x3 => to_json!(Matrix3x3),
+                SimplePrimitiveType::Vector2DInt => to_json!(Vector2DInt),
+                SimplePrimitiveType::Rectangle => to_json!(Rectangle),
+                SimplePrimitiveType::CompressedVector3D => to_json!(CompressedVector3D),
+                SimplePrimitiveType::CompressedVector2D => to_json!(CompressedVector2D),
+                SimplePrimitiveType::CompressedFloat => to_json!(CompressedFloat),
+                SimplePrimitiveType::ColorRGB => to_json!(ColorRGB),
+                SimplePrimitiveType::Pixel32 => to_json!(Pixel32),
+                SimplePrimitiveType::ColorARGB => to_json!(ColorARGB),
+                SimplePrimitiveType::Index => to_json!(Index),
+                SimplePrimitiveType::ID => to_json!(ID),
+                SimplePrimitiveType::TagGroup => to_json!(TagGroup),
+                SimplePrimitiveType::Address => to_json!(Address),
+                SimplePrimitiveType::ScenarioScriptNodeValue => to_json!(ScenarioScriptNodeValue),
+            }
+        }
+    }
+}
+
+/// Overwrite tag data with values from JSON.
+///
+/// Reflexives are extended to fit the JSON, but they cannot be shrunk, so a reflexive with more elements than the JSON
+/// is an error. Errors include the path to the field that could not be read.
+pub fn data_from_json(data: &mut dyn DynamicTagData, json: &Value) -> RinghopperResult<()> {
+    let mut path = String::new();
+    read_data(data, json, &mut path)
+        .map_err(|e| Error::InvalidTagData(if path.is_empty() { e } else { format!("{path}: {e}") }))
+}
+
+/// Read tag data from JSON, setting `path` to the field that could not be read on error.
+fn read_data(data: &mut dyn DynamicTagData, json: &Value, path: &mut String) -> Result<(), String> {
+    match data.data_type() {
+        DynamicTagDataType::Block => {
+            let Value::Object(object) = json else {
+                return Err(format!("expected an object, got {}", type_name(json)))
+            };
+            let length_before = path.len();
+            for (key, value) in object {
+                let field = data.get_field_mut(key).ok_or_else(|| format!("unknown field `{key}`"))?;
+                if !path.is_empty() {
+                    path.push('.');
+                }
+                *path += key;
+                read_data(field, value, path)?;
+                path.truncate(length_before);
+            }
+            Ok(())
+        },
+
+        DynamicTagDataType::Reflexive | DynamicTagDataType::Array => {
+            let Value::Array(array) = json else {
+                return Err(format!("expected an array, got {}", type_name(json)))
+            };
+
+            let length = data.as_array().unwrap().len();
+            if data.data_type() == DynamicTagDataType::Reflexive {
+                if length > array.len() {
+                    return Err(format!("expected at least {length} element(s), got {}", array.len()))
+                }
+                let reflexive = data.as_reflexive_mut().unwrap();
+                for i in length..array.len() {
+                    reflexive.insert_default(i);
+                }
+            }
+            else if length != array.len() {
+                return Err(format!("expected {length} element(s), got {}", array.len()))
+            }
+
+            let length_before = path.len();
+            let elements = data.as_array_mut().unwrap();
+            for (index, value) in array.iter().enumerate() {
+                *path += &format!("[{index}]");
+                read_data(elements.get_at_index_mut(index).unwrap(), value, path)?;
+                path.truncate(length_before);
+            }
+            Ok(())
+        },
+
+        DynamicTagDataType::Enum => {
+            let value = expect_string(json)?;
+            let enum_value = data.as_enum_mut().unwrap();
+            enum_value.set_enum_string_value(value).map_err(|_| {
+                format!("invalid enum value `{value}` (expected one of: {})", enum_value.get_possible_enum_string_values().join(", "))
+            })
+        },
+
+        DynamicTagDataType::TagReference => {
+            let group = TagGroup::from_json(json.get("group").ok_or("expected a `group`")?)?;
+            let reference = match json.get("path") {
+                None | Some(Value::Null) => TagReference::Null(group),
+                Some(path) => TagReference::Set(TagPath::new(expect_string(path)?, group).map_err(|e| e.to_string())?)
+            };
+            *data.as_any_mut().downcast_mut::<TagReference>().unwrap() = reference;
+            Ok(())
+        },
+
+        DynamicTagDataType::Data => {
+            data.as_any_mut().downcast_mut::<Data>().unwrap().bytes = decode_json_base64(json)?;
+            Ok(())
+        },
+        DynamicTagDataType::FileData => {
+            data.as_any_mut().downcast_mut::<FileData>().unwrap().bytes = decode_json_base64(json)?;
+            Ok(())
+        },
+        DynamicTagDataType::BSPVertexData => {
+            data.as_any_mut().downcast_mut::<BSPVertexData>().unwrap().bytes = decode_json_base64(json)?;
+            Ok(())
+        },
+
+        DynamicTagDataType::UTF16String => {
+            let string = match json {
+                Value::String(s) => UTF16String::from_str(s),
+                Value::Object(_) => UTF16String::from_utf16_bytes(&decode_json_base64(json.get("utf16_base64").ok_or("expected a `utf16_base64` string")?)?),
+                _ => return Err(format!("expected a string, got {}", type_name(json)))
+            };
+            *data.as_any_mut().downcast_mut::<UTF16String>().unwrap() = string;
+            Ok(())
+        },
+
+        DynamicTagDataType::SimplePrimitive(primitive_type) => {
+            macro_rules! from_json {
+                ($prim:ty) => {
+                    *data.as_any_mut().downcast_mut::<$prim>().unwrap() = <$prim>::from_json(json)?
+                };
+            }
+
+            match primitive_type {
+                SimplePrimitiveType::Bool => from_json!(bool),
+                SimplePrimitiveType::String32 => from_json!(String32),
+                SimplePrimitiveType::I8 => from_json!(i8),
+                SimplePrimitiveType::U8 => from_json!(u8),
+                SimplePrimitiveType::I16 => from_json!(i16),
+                SimplePrimitiveType::U16 => from_json!(u16),
+                SimplePrimitiveType::I32 => from_json!(i32),
+                SimplePrimitiveType::U32 => from_json!(u32),
+                SimplePrimitiveType::Size => from_json!(usize),
+                SimplePrimitiveType::Float => from_json!(f32),
+                SimplePrimitiveType::Angle => from_json!(Angle),
+                SimplePrimitiveType::Vector2D => from_json!(Vector2D),
+                SimplePrimitiveType::Vector3D => from_json!(Vector3D),
+                SimplePrimitiveType::Plane2D => from_json!(Plane2D),
+                SimplePrimitiveType::Plane3D => from_json!(Plane3D),
+                SimplePrimitiveType::Euler2D => from_json!(Euler2D),
+                SimplePrimitiveType::Euler3D => from_json!(Euler3D),
+                SimplePrimitiveType::Quaternion => from_json!(Quaternion),
+                SimplePrimitiveType::Matrix2x3 => from_json!(Matrix2x3),
+                SimplePrimitiveType::Matrix3x3 => from_json!(Matrix3x3),
+                SimplePrimitiveType::Vector2DInt => from_json!(Vector2DInt),
+                SimplePrimitiveType::Rectangle => from_json!(Rectangle),
+                SimplePrimitiveType::CompressedVector3D => from_json!(CompressedVector3D),
+                SimplePrimitiveType::CompressedVector2D => from_json!(CompressedVector2D),
+                SimplePrimitiveType::CompressedFloat => from_json!(CompressedFloat),
+                SimplePrimitiveType::ColorRGB => from_json!(ColorRGB),
+                SimplePrimitiveType::Pixel32 => from_json!(Pixel32),
+                SimplePrimitiveType::ColorARGB => from_json!(ColorARGB),
+                SimplePrimitiveType::Index => from_json!(Index),
+                SimplePrimitiveType::ID => from_json!(ID),
+                SimplePrimitiveType::TagGroup => from_json!(TagGroup),
+                SimplePrimitiveType::Address => from_json!(Address),
+                SimplePrimitiveType::ScenarioScriptNodeValue => from_json!(ScenarioScriptNodeValue),
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Describe the type of a JSON value for error messages.
+fn type_name(json: &Value) -> &'static str {
+    match json {
+        Value::Null => "null",
+        Value::Bool(_) => "a boolean",
+        Value::Number(_) => "a number",
+        Value::String(_) => "a string",
+        Value::Array(_) => "an array",
+        Value::Object(_) => "an object"
+    }
+}
+
+fn expect_string(json: &Value) -> Result<&str, String> {
+    match json {
+        Value::String(s) => Ok(s),
+        _ => Err(format!("expected a string, got {}", type_name(json)))
+    }
+}
+
+fn decode_json_base64(json: &Value) -> Result<Vec<u8>, String> {
+    BASE64.decode(expect_string(json)?).map_err(|e| format!("invalid base64: {e}"))
+}
+
+/// Conversion between simple primitives and JSON.
+trait JsonPrimitive: Sized {
+    fn to_json(&self) -> Value;
+    fn from_json(json: &Value) -> Result<Self, String>;
+}
+
+macro_rules! json_integer {
+    ($($t:ty),*) => {$(
+        impl JsonPrimitive for $t {
+            fn to_json(&self) -> Value {
+                Value::from(*self)
+            }
+            fn from_json(json: &Value) -> Result<Self, String> {
+                match json {
+                    Value::Number(n) => n.to_string().parse().map_err(|_| format!("expected an integer between {} and {}, got {n}", <$t>::MIN, <$t>::MAX)),
+                    _ => Err(format!("expected an integer, got {}", type_name(json)))
+                }
+            }
+        }
+    )*};
+}
+
+json_integer!(i8, u8, i16, u16, i32, u32, usize);
+
+/// Implement JSON conversion for a type that is converted to and from another type that implements it.
+macro_rules! json_via {
+    ($t:ty, $via:ty, $to:expr, $from:expr) => {
+        impl JsonPrimitive for $t {
+            fn to_json(&self) -> Value {
+                let to: fn(&$t) -> $via = $to;
+                to(self).to_json()
+            }
+            fn from_json(json: &Value) -> Result<Self, String> {
+                let from: fn($via) -> $t = $from;
+                <$via>::from_json(json).map(from)
+            }
+        }
+    };
+}
+
+json_via!(Angle, f32, |a| a.angle, |angle| Angle { angle });
+json_via!(ID, u32, |id| id.as_u32(), ID::from_u32);
+json_via!(Address, u32, |a| a.address, |address| Address { address });
+json_via!(ScenarioScriptNodeValue, u32, |v| v.data, |data| ScenarioScriptNodeValue { data });
+json_via!(CompressedFloat, u16, |v| v.data, |data| CompressedFloat { data });
+json_via!(CompressedVector2D, u32, |v| v.data, |data| CompressedVector2D { data });
+json_via!(CompressedVector3D, u32, |v| v.data, |data| CompressedVector3D { data });
+json_via!(Pixel32, Pixel32Bytes, |p| (*p).into(), Pixel32::from);
+
+/// Implement JSON conversion for a struct as an object with every field required.
+macro_rules! json_struct {
+    ($t:ty { $($field:ident),* }) => {
+        impl JsonPrimitive for $t {
+            fn to_json(&self) -> Value {
+                json!({ $(stringify!($field): self.$field.to_json()),* })
+            }
+            fn from_json(json: &Value) -> Result<Self, String> {
+                let Value::Object(object) = json else {
+                    return Err(format!("expected an object, got {}", type_name(json)))
+                };
+                if let Some((key, _)) = object.iter().find(|(k, _)| ![$(stringify!($field)),*].contains(&k.as_str())) {
+                    return Err(format!("unknown field `{key}`"))
+                }
+                Ok(Self {
+                    $($field: JsonPrimitive::from_json(json.get(stringify!($field)).ok_or(concat!("expected a `", stringify!($field), "`"))?)?),*
+                })
+            }
+        }
+    };
+}
+
+json_struct!(Vector2D { x, y });
+json_struct!(Vector3D { x, y, z });
+json_struct!(Quaternion { x, y, z, w });
+json_struct!(Vector2DInt { x, y });
+json_struct!(Rectangle { top, left, bottom, right });
+json_struct!(Euler2D { yaw, pitch });
+json_struct!(Euler3D { yaw, pitch, roll });
+json_struct!(Plane2D { vector, d });
+json_struct!(Plane3D { vector, d });
+json_struct!(Matrix2x3 { vectors });
+json_struct!(Matrix3x3 { vectors });
+json_struct!(ColorRGB { red, green, blue });
+json_struct!(ColorARGB { alpha, red, green, blue });
+json_struct!(Pixel32Bytes { alpha, red, green, blue });
+
+impl JsonPrimitive for bool {
+    fn to_json(&self) -> Value {
+        Value::Bool(*self)
+    }
+    fn from_json(json: &Value) -> Result<Self, String> {
+        match json {
+            Value::Bool(b) => Ok(*b),
+            _ => Err(format!("expected a boolean, got {}", type_name(json)))
+        }
+    }
+}
+
+impl JsonPrimitive for f32 {
+    fn to_json(&self) -> Value {
+        // JSON has no NaN or infinity, and NaN can have any payload, so keep its bits
+        if self.is_nan() {
+            json!({ "bits": self.to_bits() })
+        }
+        else if self.is_infinite() {
+            Value::String(if *self > 0.0 { "Infinity" } else { "-Infinity" }.to_owned())
+        }
+        else {
+            Value::from(*self)
+        }
+    }
+    fn from_json(json: &Value) -> Result<Self, String> {
+        match json {
+            Value::Number(n) => n.to_string().parse().map_err(|_| format!("invalid number {n}")),
+            Value::String(s) if s == "Infinity" => Ok(f32::INFINITY),
+            Value::String(s) if s == "-Infinity" => Ok(f32::NEG_INFINITY),
+            Value::Object(object) if object.len() == 1 && object.contains_key("bits") => u32::from_json(&object["bits"]).map(f32::from_bits),
+            _ => Err(format!("expected a number, got {}", type_name(json)))
+        }
+    }
+}
+
+impl<const N: usize> JsonPrimitive for [Vector3D; N] {
+    fn to_json(&self) -> Value {
+        Value::Array(self.iter().map(Vector3D::to_json).collect())
+    }
+    fn from_json(json: &Value) -> Result<Self, String> {
+        match json {
+            Value::Array(a) if a.len() == N => {
+                let mut vectors = [Vector3D::default(); N];
+                for (vector, json) in vectors.iter_mut().zip(a) {
+                    *vector = Vector3D::from_json(json)?;
+                }
+                Ok(vectors)
+            },
+            _ => Err(format!("expected an array of {N} vectors"))
+        }
+    }
+}
+
+impl JsonPrimitive for Index {
+    fn to_json(&self) -> Value {
+        match self {
+            Some(n) => Value::from(*n),
+            None => Value::Null
+        }
+    }
+    fn from_json(json: &Value) -> Result<Self, String> {
+        match json {
+            Value::Null => Ok(None),
+            _ => u16::from_json(json).map(Some)
+        }
+    }
+}
+
+impl JsonPrimitive for String32 {
+    fn to_json(&self) -> Value {
+        Value::String(self.as_str().to_owned())
+    }
+    fn from_json(json: &Value) -> Result<Self, String> {
+        String32::from_str(expect_string(json)?).map_err(|e| e.to_string())
+    }
+}
+
+impl JsonPrimitive for TagGroup {
+    fn to_json(&self) -> Value {
+        Value::String(self.as_str().to_owned())
+    }
+    fn from_json(json: &Value) -> Result<Self, String> {
+        let group = expect_string(json)?;
+        TagGroup::from_str(group).map_err(|_| format!("unknown tag group `{group}`"))
+    }
+}
@@ -0,0 +1,107 @@
+use definitions::{AnimationFrameInfoType, Model, ModelAnimations, ModelAnimationsAnimation, ModelNode, ModelShaderReference};
+use primitives::primitive::{Quaternion, Reflexive, String32, TagGroup, TagPath, TagReference, Vector3D};
+use serde_json::json;
+use crate::tag::compare::compare_tags;
+use super::*;
+
+fn test_model() -> Model {
+    Model {
+        node_list_checksum: -123456789,
+        nodes: Reflexive::new(vec![
+            ModelNode {
+                name: String32::from_str("frame \"root\"").unwrap(),
+                first_child_node_index: Some(1),
+                default_rotation: Quaternion { x: 0.0, y: 0.0, z: std::f32::consts::FRAC_1_SQRT_2, w: std::f32::consts::FRAC_1_SQRT_2 },
+                ..Default::default()
+            },
+            ModelNode {
+                name: String32::from_str("bone").unwrap(),
+                parent_node_index: Some(0),
+                default_translation: Vector3D { x: -0.0, y: 1.0e-30, z: f32::MAX },
+                node_distance_from_parent: f32::INFINITY,
+                ..Default::default()
+            }
+        ]),
+        shaders: Reflexive::new(vec![
+            ModelShaderReference { shader: TagReference::Set(TagPath::new("shaders\\metal", TagGroup::ShaderModel).unwrap()), ..Default::default() },
+            ModelShaderReference { shader: TagReference::Null(TagGroup::ShaderEnvironment), permutation: 3 }
+        ]),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn model_json_round_trip() {
+    let model = test_model();
+    let json = serde_json::to_string_pretty(&tag_to_json(&model)).unwrap();
+    assert!(json.contains("\"group\": \"model\""), "{json}");
+    assert!(json.contains("\"name\": \"frame \\\"root\\\"\""), "{json}");
+    assert!(json.contains("\"node_distance_from_parent\": \"Infinity\""), "{json}");
+
+    let parsed: Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(parsed["data"]["shaders"][1]["shader"], json!({"group": "shader_environment", "path": null}));
+
+    let imported = tag_from_json(&parsed).unwrap();
+    let differences = compare_tags(&model, imported.as_ref(), true, false);
+    assert!(differences.is_empty(), "{differences:?}");
+
+    let imported = imported.get_ref::<Model>().unwrap();
+    assert_eq!((-0.0f32).to_bits(), imported.nodes.items[1].default_translation.x.to_bits());
+    assert_eq!(model.to_tag_file().unwrap(), imported.to_tag_file().unwrap());
+}
+
+#[test]
+fn model_animations_json_round_trip() {
+    let animations = ModelAnimations {
+        animations: Reflexive::new(vec![ModelAnimationsAnimation {
+            name: String32::from_str("stand idle").unwrap(),
+            frame_info_type: AnimationFrameInfoType::DxDy,
+            frame_data: (0..=255).collect(),
+            ..Default::default()
+        }]),
+        ..Default::default()
+    };
+
+    let json = serde_json::to_string(&tag_to_json(&animations)).unwrap();
+    let imported = tag_from_json(&serde_json::from_str(&json).unwrap()).unwrap();
+    assert_eq!(animations.to_tag_file().unwrap(), imported.to_tag_file().unwrap());
+}
+
+#[test]
+fn nan_keeps_its_bits() {
+    let nan = f32::from_bits(0x7FC0_1234);
+    let model = Model {
+        nodes: Reflexive::new(vec![ModelNode { node_distance_from_parent: nan, ..Default::default() }]),
+        ..Default::default()
+    };
+
+    let json = tag_to_json(&model);
+    assert_eq!(json["data"]["nodes"][0]["node_distance_from_parent"], json!({"bits": 0x7FC0_1234}));
+
+    let imported = tag_from_json(&serde_json::from_str(&json.to_string()).unwrap()).unwrap();
+    let imported = imported.get_ref::<Model>().unwrap();
+    assert_eq!(nan.to_bits(), imported.nodes.items[0].node_distance_from_parent.to_bits());
+}
+
+#[test]
+fn report_bad_tag_json() {
+    let error_for = |json: &str| tag_from_json(&serde_json::from_str(json).unwrap()).err().expect("should fail").to_string();
+
+    let error = error_for(r#"{"group": "model", "data": {"nodes": [{}, {"parent_node_index": 65536}]}}"#);
+    assert!(error.contains("nodes[1].parent_node_index: expected an integer between 0 and 65535"), "{error}");
+
+    let error = error_for(r#"{"group": "model", "data": {"nodes": [{"bogus": 1}]}}"#);
+    assert!(error.contains("nodes[0]: unknown field `bogus`"), "{error}");
+
+    let error = error_for(r#"{"group": "model", "data": {"nodes": [{"default_translation": {"x": 0, "y": 0}}]}}"#);
+    assert!(error.contains("nodes[0].default_translation: expected a `z`"), "{error}");
+
+    let error = error_for(r#"{"group": "model", "data": {"shaders": [{"shader": {"group": "not a group"}}]}}"#);
+    assert!(error.contains("shaders[0].shader: unknown tag group `not a group`"), "{error}");
+
+    let error = error_for(r#"{"group": "model_animations", "data": {"animations": [{"frame_info_type": "sideways"}]}}"#);
+    assert!(error.contains("animations[0].frame_info_type: invalid enum value `sideways`"), "{error}");
+
+    let error = error_for(r#"{"group": "not a group", "data": {}}"#);
+    assert!(error.contains("unknown tag group"), "{error}");
+}
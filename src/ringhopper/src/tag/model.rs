@@ -4,6 +4,14 @@ use primitives::error::{Error, RinghopperResult};
 use primitives::primitive::{Index, Reflexive, TagGroup, Vector2D};
 use primitives::tag::PrimaryTagStructDyn;
 
+#[cfg(test)]
+mod test;
+mod compile;
+mod export;
+
+pub use compile::*;
+pub use export::*;
+
 pub trait ModelFunctions {
     /// Convert into a model tag.
     ///
@@ -0,0 +1,541 @@
+use std::collections::{BTreeMap, HashMap};
+use definitions::{GBXModel, GBXModelGeometry, GBXModelGeometryPart, Model, ModelDetailCutoff, ModelGeometry, ModelGeometryPart, ModelNode, ModelRegion, ModelRegionPermutation, ModelRegionPermutationMarker, ModelShaderReference, ModelTriangleStripData, ModelVertexUncompressed};
+use primitives::error::{Error, RinghopperResult};
+use primitives::primitive::{Reflexive, String32, TagReference, Vector, Vector2D, Vector3D};
+use crate::data::jms::{JMSModelSource, JMSVertex, ModelLOD, JMS, JMS_UNITS_PER_WORLD_UNIT};
+use super::ModelFunctions;
+
+/// Options for compiling a model from JMS files.
+#[derive(Copy, Clone, Default)]
+pub struct ModelCompileOptions {
+    /// Set the LoD cutoffs, in pixels, ordered from super low to super high (the same order as [`ModelLOD::ALL`]).
+    ///
+    /// These are stored the way tag files store them; [`ModelFunctions::flip_lod_cutoffs`] converts them when the tag
+    /// enters a cache file. If `None`, the existing cutoffs are kept.
+    pub lod_cutoffs: Option<[f32; 5]>
+}
+
+/// Compile JMS files into a model tag.
+///
+/// Nodes, regions, shaders, and geometries are replaced. `find_shader` is called once for each unique material name to
+/// get a reference to its shader tag.
+pub fn compile_model<F: FnMut(&str) -> RinghopperResult<TagReference>>(tag: &mut Model, sources: &[JMSModelSource], options: &ModelCompileOptions, find_shader: F) -> RinghopperResult<()> {
+    let compiled = CompiledModel::compile(sources, find_shader)?;
+
+    tag.node_list_checksum = compiled.node_list_checksum;
+    tag.nodes.items = compiled.nodes;
+    tag.regions.items = compiled.regions;
+    tag.shaders.items = compiled.shaders;
+    tag.geometries.items = compiled.geometries;
+    tag.runtime_markers.items.clear();
+
+    if let Some(lod_cutoffs) = options.lod_cutoffs {
+        set_lod_cutoffs(&mut tag.detail_cutoff, lod_cutoffs);
+    }
+
+    tag.recompress_vertices();
+    Ok(())
+}
+
+/// Compile JMS files into a gbxmodel tag.
+///
+/// Nodes, regions, shaders, and geometries are replaced. `find_shader` is called once for each unique material name to
+/// get a reference to its shader tag.
+///
+/// Local nodes are not used.
+pub fn compile_gbxmodel<F: FnMut(&str) -> RinghopperResult<TagReference>>(tag: &mut GBXModel, sources: &[JMSModelSource], options: &ModelCompileOptions, find_shader: F) -> RinghopperResult<()> {
+    let compiled = CompiledModel::compile(sources, find_shader)?;
+
+    tag.node_list_checksum = compiled.node_list_checksum;
+    tag.nodes.items = compiled.nodes;
+    tag.regions.items = compiled.regions;
+    tag.shaders.items = compiled.shaders;
+    tag.geometries.items = compiled.geometries.into_iter().map(|g| GBXModelGeometry {
+        flags: g.flags,
+        parts: Reflexive {
+            items: g.parts.items.into_iter().map(|p| GBXModelGeometryPart {
+                model_geometry_part: p,
+                ..Default::default()
+            }).collect()
+        }
+    }).collect();
+    tag.runtime_markers.items.clear();
+    tag.flags.parts_have_local_nodes = false;
+
+    if let Some(lod_cutoffs) = options.lod_cutoffs {
+        set_lod_cutoffs(&mut tag.detail_cutoff, lod_cutoffs);
+    }
+
+    tag.recompress_vertices();
+    Ok(())
+}
+
+fn set_lod_cutoffs(cutoff: &mut ModelDetailCutoff, [super_low, low, medium, high, super_high]: [f32; 5]) {
+    *cutoff = ModelDetailCutoff { super_low, low, medium, high, super_high };
+}
+
+pub(crate) fn name_to_string32(what: &str, name: &str) -> RinghopperResult<String32> {
+    String32::from_str(name).map_err(|_| Error::Other(format!("{what} name `{name}` is longer than 31 characters")))
+}
+
+/// Geometry indices and markers of a permutation in a region.
+#[derive(Default)]
+struct PermutationBuilder {
+    geometries: [Option<usize>; 5],
+    markers: Vec<ModelRegionPermutationMarker>
+}
+
+struct CompiledModel<F> {
+    find_shader: F,
+    node_list_checksum: i32,
+    nodes: Vec<ModelNode>,
+    regions: Vec<ModelRegion>,
+    shader_names: Vec<String>,
+    shaders: Vec<ModelShaderReference>,
+    geometries: Vec<ModelGeometry>
+}
+
+impl<F: FnMut(&str) -> RinghopperResult<TagReference>> CompiledModel<F> {
+    fn compile(sources: &[JMSModelSource], find_shader: F) -> RinghopperResult<Self> {
+        let first = match sources.first() {
+            Some(n) => &n.jms,
+            None => return Err(Error::Other("no JMS files to compile".to_owned()))
+        };
+
+        let mut model = CompiledModel {
+            find_shader,
+            node_list_checksum: first.node_list_checksum,
+            nodes: Vec::new(),
+            regions: Vec::new(),
+            shader_names: Vec::new(),
+            shaders: Vec::new(),
+            geometries: Vec::new()
+        };
+
+        model.compile_nodes(first)?;
+        for source in sources {
+            let same_nodes = source.jms.node_list_checksum == first.node_list_checksum
+                && source.jms.nodes.len() == first.nodes.len()
+                && source.jms.nodes.iter().zip(first.nodes.iter()).all(|(a, b)| a.name == b.name);
+            if !same_nodes {
+                return Err(Error::Other(format!("permutation `{}` ({}) has a different node list than the other JMS files", source.permutation, source.lod.as_str())))
+            }
+        }
+
+        // Regions and permutations are sorted by name.
+        let mut regions: BTreeMap<&str, BTreeMap<&str, PermutationBuilder>> = BTreeMap::new();
+        for source in sources {
+            let lod_index = source.lod as usize;
+            let jms = &source.jms;
+
+            let mut triangles_by_region: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+            for (index, triangle) in jms.triangles.iter().enumerate() {
+                triangles_by_region.entry(triangle.region).or_default().push(index);
+            }
+
+            for (region, triangles) in triangles_by_region {
+                let geometry = model.compile_geometry(jms, &triangles)
+                    .map_err(|e| Error::Other(format!("permutation `{}` ({}), region `{}`: {e}", source.permutation, source.lod.as_str(), jms.regions[region])))?;
+                model.geometries.push(geometry);
+
+                regions
+                    .entry(jms.regions[region].as_str())
+                    .or_default()
+                    .entry(source.permutation.as_str())
+                    .or_default()
+                    .geometries[lod_index] = Some(model.geometries.len() - 1);
+            }
+        }
+
+        // Markers are taken from the highest level of detail of each permutation.
+        let mut highest_lods: BTreeMap<&str, &JMSModelSource> = BTreeMap::new();
+        for source in sources {
+            let highest = highest_lods.entry(source.permutation.as_str()).or_insert(source);
+            if source.lod > highest.lod {
+                *highest = source;
+            }
+        }
+        for (permutation, source) in highest_lods {
+            let jms = &source.jms;
+            for marker in &jms.markers {
+                let marker_regions: Vec<&str> = match marker.region {
+                    Some(r) => vec![jms.regions[r].as_str()],
+                    None => jms.triangles.iter().map(|t| jms.regions[t.region].as_str()).collect::<std::collections::BTreeSet<_>>().into_iter().collect()
+                };
+                for region in marker_regions {
+                    regions
+                        .entry(region)
+                        .or_default()
+                        .entry(permutation)
+                        .or_default()
+                        .markers
+                        .push(ModelRegionPermutationMarker {
+                            name: name_to_string32("marker", &marker.name)?,
+                            node_index: Some(marker.node as u16),
+                            rotation: marker.rotation.normalize(),
+                            translation: marker.translation.scale(1.0 / JMS_UNITS_PER_WORLD_UNIT)
+                        });
+                }
+            }
+        }
+
+        for (region_name, permutations) in regions {
+            let mut region = ModelRegion {
+                name: name_to_string32("region", region_name)?,
+                ..Default::default()
+            };
+
+            for (permutation_name, permutation) in permutations {
+                let [super_low, low, medium, high, super_high] = fill_missing_lods(permutation.geometries).map(|g| g.map(|i| i as u16));
+                region.permutations.items.push(ModelRegionPermutation {
+                    name: name_to_string32("permutation", permutation_name)?,
+                    super_low,
+                    low,
+                    medium,
+                    high,
+                    super_high,
+                    markers: Reflexive { items: permutation.markers },
+                    ..Default::default()
+                });
+            }
+
+            model.regions.push(region);
+        }
+
+        if model.geometries.len() > u16::MAX as usize {
+            return Err(Error::Other(format!("too many geometries ({} > {})", model.geometries.len(), u16::MAX)))
+        }
+
+        Ok(model)
+    }
+
+    fn compile_nodes(&mut self, jms: &JMS) -> RinghopperResult<()> {
+        if jms.nodes.is_empty() {
+            return Err(Error::Other("JMS has no nodes".to_owned()))
+        }
+        if jms.nodes.len() > u16::MAX as usize {
+            return Err(Error::Other(format!("too many nodes ({} > {})", jms.nodes.len(), u16::MAX)))
+        }
+
+        let parents = jms.node_parents()?;
+        for (node, parent) in jms.nodes.iter().zip(parents) {
+            let default_translation = node.translation.scale(1.0 / JMS_UNITS_PER_WORLD_UNIT);
+            self.nodes.push(ModelNode {
+                name: name_to_string32("node", &node.name)?,
+                next_sibling_node_index: node.sibling.map(|n| n as u16),
+                first_child_node_index: node.first_child.map(|n| n as u16),
+                parent_node_index: parent.map(|n| n as u16),
+                default_translation,
+                default_rotation: node.rotation.normalize(),
+                node_distance_from_parent: default_translation.magnitude_squared().sqrt(),
+                ..Default::default()
+            });
+        }
+
+        Ok(())
+    }
+
+    fn shader_index(&mut self, name: &str) -> RinghopperResult<u16> {
+        if let Some(index) = self.shader_names.iter().position(|n| n == name) {
+            return Ok(index as u16)
+        }
+
+        let shader = (self.find_shader)(name)?;
+        self.shader_names.push(name.to_owned());
+        self.shaders.push(ModelShaderReference { shader, ..Default::default() });
+
+        if self.shaders.len() > u16::MAX as usize {
+            return Err(Error::Other(format!("too many shaders ({} > {})", self.shaders.len(), u16::MAX)))
+        }
+
+        Ok((self.shaders.len() - 1) as u16)
+    }
+
+    /// Compile the given triangles into a geometry with one part per material.
+    fn compile_geometry(&mut self, jms: &JMS, triangles: &[usize]) -> RinghopperResult<ModelGeometry> {
+        let mut triangles_by_material: BTreeMap<usize, Vec<[usize; 3]>> = BTreeMap::new();
+        for &t in triangles {
+            let triangle = &jms.triangles[t];
+            triangles_by_material.entry(triangle.material).or_default().push(triangle.vertices);
+        }
+
+        let mut geometry = ModelGeometry::default();
+        for (material, triangles) in triangles_by_material {
+            let shader_index = self.shader_index(&jms.materials[material].name)?;
+            if let Some(part) = compile_part(jms, &triangles, shader_index)? {
+                geometry.parts.items.push(part);
+            }
+        }
+
+        Ok(geometry)
+    }
+}
+
+/// Fill in missing levels of detail with the next highest level of detail, or the next lowest if there is none.
+fn fill_missing_lods(mut geometries: [Option<usize>; 5]) -> [Option<usize>; 5] {
+    debug_assert_eq!(ModelLOD::ALL.len(), geometries.len());
+
+    for i in (0..geometries.len() - 1).rev() {
+        if geometries[i].is_none() {
+            geometries[i] = geometries[i + 1];
+        }
+    }
+    for i in 1..geometries.len() {
+        if geometries[i].is_none() {
+            geometries[i] = geometries[i - 1];
+        }
+    }
+
+    geometries
+}
+
+fn convert_vertex(vertex: &JMSVertex) -> ModelVertexUncompressed {
+    let node1_index = vertex.node1.filter(|n| *n != vertex.node0 && vertex.node1_weight > 0.0);
+    let node1_weight = if node1_index.is_some() { vertex.node1_weight.clamp(0.0, 1.0) } else { 0.0 };
+
+    ModelVertexUncompressed {
+        position: vertex.position.scale(1.0 / JMS_UNITS_PER_WORLD_UNIT),
+        normal: vertex.normal.normalize(),
+        texture_coords: Vector2D {
+            x: vertex.texture_coordinates.x,
+            y: 1.0 - vertex.texture_coordinates.y
+        },
+        node0_index: Some(vertex.node0 as u16),
+        node1_index: node1_index.map(|n| n as u16),
+        node0_weight: 1.0 - node1_weight,
+        node1_weight,
+        ..Default::default()
+    }
+}
+
+/// Get a key for deduplicating vertices.
+///
+/// Binormals and tangents are excluded since they are calculated after deduplication.
+fn vertex_key(vertex: &ModelVertexUncompressed) -> [u32; 12] {
+    [
+        vertex.position.x.to_bits(),
+        vertex.position.y.to_bits(),
+        vertex.position.z.to_bits(),
+        vertex.normal.x.to_bits(),
+        vertex.normal.y.to_bits(),
+        vertex.normal.z.to_bits(),
+        vertex.texture_coords.x.to_bits(),
+        vertex.texture_coords.y.to_bits(),
+        vertex.node0_index.map(|n| n as u32).unwrap_or(u32::MAX),
+        vertex.node1_index.map(|n| n as u32).unwrap_or(u32::MAX),
+        vertex.node0_weight.to_bits(),
+        vertex.node1_weight.to_bits()
+    ]
+}
+
+/// Compile a part from triangles that share a shader.
+///
+/// Returns `None` if every triangle is degenerate.
+fn compile_part(jms: &JMS, triangles: &[[usize; 3]], shader_index: u16) -> RinghopperResult<Option<ModelGeometryPart>> {
+    let mut vertices: Vec<ModelVertexUncompressed> = Vec::new();
+    let mut vertex_indices: HashMap<[u32; 12], u16> = HashMap::new();
+    let mut part_triangles: Vec<[u16; 3]> = Vec::with_capacity(triangles.len());
+
+    for triangle in triangles {
+        let mut indices = [0u16; 3];
+        for (index, jms_vertex) in indices.iter_mut().zip(triangle) {
+            let vertex = convert_vertex(&jms.vertices[*jms_vertex]);
+            let key = vertex_key(&vertex);
+            *index = match vertex_indices.get(&key) {
+                Some(n) => *n,
+                None => {
+                    // 0xFFFF is a null index
+                    if vertices.len() >= u16::MAX as usize {
+                        return Err(Error::Other(format!("vertex count is too high (> 0x{:X})", u16::MAX)))
+                    }
+                    let new_index = vertices.len() as u16;
+                    vertices.push(vertex);
+                    vertex_indices.insert(key, new_index);
+                    new_index
+                }
+            };
+        }
+
+        let [a, b, c] = indices;
+        if a != b && b != c && a != c {
+            part_triangles.push(indices);
+        }
+    }
+
+    if part_triangles.is_empty() {
+        return Ok(None)
+    }
+
+    calculate_tangents(&mut vertices, &part_triangles);
+
+    let strip = generate_triangle_strip(&part_triangles);
+    let triangle_data = strip
+        .chunks(3)
+        .map(|c| ModelTriangleStripData {
+            indices: [Some(c[0]), c.get(1).copied(), c.get(2).copied()]
+        })
+        .collect();
+
+    let mut part = ModelGeometryPart {
+        shader_index: Some(shader_index),
+        prev_filthy_part_index: 255,
+        next_filthy_part_index: 255,
+        triangle_data: Reflexive { items: triangle_data },
+        ..Default::default()
+    };
+    calculate_centroid(&mut part, &vertices);
+    part.uncompressed_vertices.items = vertices;
+
+    Ok(Some(part))
+}
+
+pub(crate) fn cross(a: Vector3D, b: Vector3D) -> Vector3D {
+    Vector3D {
+        x: a.y * b.z - a.z * b.y,
+        y: a.z * b.x - a.x * b.z,
+        z: a.x * b.y - a.y * b.x
+    }
+}
+
+/// Calculate binormals and tangents from texture coordinates.
+pub(crate) fn calculate_tangents(vertices: &mut [ModelVertexUncompressed], triangles: &[[u16; 3]]) {
+    let mut tangents = vec![Vector3D::zero(); vertices.len()];
+    let mut binormals = vec![Vector3D::zero(); vertices.len()];
+
+    for triangle in triangles {
+        let [a, b, c] = triangle.map(|i| i as usize);
+        let (va, vb, vc) = (&vertices[a], &vertices[b], &vertices[c]);
+
+        let edge1 = vb.position - va.position;
+        let edge2 = vc.position - va.position;
+        let uv1 = vb.texture_coords - va.texture_coords;
+        let uv2 = vc.texture_coords - va.texture_coords;
+
+        let determinant = uv1.x * uv2.y - uv2.x * uv1.y;
+        if determinant == 0.0 {
+            continue
+        }
+
+        let r = 1.0 / determinant;
+        let tangent = (edge1 * uv2.y - edge2 * uv1.y) * r;
+        let binormal = (edge2 * uv1.x - edge1 * uv2.x) * r;
+
+        for i in [a, b, c] {
+            tangents[i] += tangent;
+            binormals[i] += binormal;
+        }
+    }
+
+    for (vertex, (tangent, binormal)) in vertices.iter_mut().zip(tangents.into_iter().zip(binormals)) {
+        let normal = vertex.normal;
+
+        // Gram-Schmidt orthogonalize against the normal, falling back to any perpendicular vector.
+        let mut tangent = tangent - normal * normal.dot(&tangent);
+        if tangent.magnitude_squared() < 1e-12 {
+            let axis = if normal.x.abs() < 0.9 { Vector3D { x: 1.0, y: 0.0, z: 0.0 } } else { Vector3D { x: 0.0, y: 1.0, z: 0.0 } };
+            tangent = cross(normal, axis);
+        }
+        let tangent = tangent.normalize();
+
+        let mut computed_binormal = cross(normal, tangent);
+        if computed_binormal.dot(&binormal) < 0.0 {
+            computed_binormal = -computed_binormal;
+        }
+
+        vertex.tangent = tangent;
+        vertex.binormal = computed_binormal.normalize();
+    }
+}
+
+/// Calculate the centroid and the two most heavily weighted nodes.
+fn calculate_centroid(part: &mut ModelGeometryPart, vertices: &[ModelVertexUncompressed]) {
+    if vertices.is_empty() {
+        return
+    }
+
+    let mut centroid = Vector3D::zero();
+    let mut node_weights: BTreeMap<u16, f32> = BTreeMap::new();
+    for vertex in vertices {
+        centroid += vertex.position;
+        for (node, weight) in [(vertex.node0_index, vertex.node0_weight), (vertex.node1_index, vertex.node1_weight)] {
+            if let Some(node) = node {
+                *node_weights.entry(node).or_default() += weight;
+            }
+        }
+    }
+    part.centroid = centroid.scale(1.0 / vertices.len() as f32);
+
+    let mut node_weights: Vec<(u16, f32)> = node_weights.into_iter().collect();
+    node_weights.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+
+    let primary = node_weights.first().copied();
+    let secondary = node_weights.get(1).copied();
+    let total = primary.map(|n| n.1).unwrap_or_default() + secondary.map(|n| n.1).unwrap_or_default();
+
+    part.centroid_primary_node = primary.map(|n| n.0);
+    part.centroid_secondary_node = secondary.map(|n| n.0);
+    if total > 0.0 {
+        part.centroid_primary_weight = primary.map(|n| n.1).unwrap_or_default() / total;
+        part.centroid_secondary_weight = secondary.map(|n| n.1).unwrap_or_default() / total;
+    }
+}
+
+/// Generate a single triangle strip from triangles.
+///
+/// Strips are built greedily and joined with degenerate triangles. The winding order of each triangle is preserved:
+/// odd triangles in the strip are flipped, as is standard for triangle strips.
+pub fn generate_triangle_strip(triangles: &[[u16; 3]]) -> Vec<u16> {
+    let mut triangles_by_edge: HashMap<(u16, u16), Vec<usize>> = HashMap::new();
+    for (index, &[a, b, c]) in triangles.iter().enumerate() {
+        for edge in [(a, b), (b, c), (c, a)] {
+            triangles_by_edge.entry(edge).or_default().push(index);
+        }
+    }
+
+    let mut used = vec![false; triangles.len()];
+    let mut result: Vec<u16> = Vec::with_capacity(triangles.len() * 3);
+
+    for start in 0..triangles.len() {
+        if used[start] {
+            continue
+        }
+        used[start] = true;
+
+        let mut strip = triangles[start].to_vec();
+        loop {
+            let len = strip.len();
+            let edge = if (len - 2).is_multiple_of(2) {
+                (strip[len - 2], strip[len - 1])
+            }
+            else {
+                (strip[len - 1], strip[len - 2])
+            };
+
+            let next = triangles_by_edge
+                .get(&edge)
+                .and_then(|t| t.iter().copied().find(|t| !used[*t]));
+
+            let Some(next) = next else {
+                break
+            };
+
+            used[next] = true;
+            let triangle = triangles[next];
+            let position = triangle.iter().position(|v| *v == edge.1).unwrap();
+            strip.push(triangle[(position + 1) % 3]);
+        }
+
+        if let Some(&last) = result.last() {
+            // Each strip has to start on an even triangle to keep its winding order.
+            let odd = result.len() % 2 == 1;
+            result.push(last);
+            result.push(strip[0]);
+            if odd {
+                result.push(strip[0]);
+            }
+        }
+        result.extend_from_slice(&strip);
+    }
+
+    result
+}
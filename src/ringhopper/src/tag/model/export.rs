@@ -0,0 +1,269 @@
+use definitions::{Model, ModelGeometryPart, ModelRegionPermutation, ModelVertexUncompressed};
+use primitives::error::{Error, RinghopperResult};
+use primitives::primitive::{Index, Vector, Vector2D};
+use crate::data::gltf::{GLTFMesh, GLTFNode, GLTFPrimitive, GLTF};
+use crate::data::jms::{JMSMarker, JMSMaterial, JMSModelSource, JMSNode, JMSTriangle, JMSVertex, ModelLOD, JMS, JMS_UNITS_PER_WORLD_UNIT};
+use super::{decompress_model_vertex, ModelFunctions};
+
+/// Convert a triangle strip into a list of triangles.
+///
+/// The strip ends at the first null index. Degenerate triangles, which are used to join strips, are skipped.
+pub fn triangle_strip_to_triangles(strip: &[Index]) -> Vec<[u16; 3]> {
+    let indices: Vec<u16> = strip.iter().map_while(|i| *i).collect();
+    indices
+        .windows(3)
+        .enumerate()
+        .map(|(i, w)| if i % 2 == 0 { [w[0], w[1], w[2]] } else { [w[1], w[0], w[2]] })
+        .filter(|[a, b, c]| a != b && b != c && a != c)
+        .collect()
+}
+
+/// Export a model as JMS files, one for each permutation and level of detail.
+///
+/// A level of detail is only exported if its geometry differs from the next highest level of detail. Markers are only
+/// exported with the super high level of detail.
+///
+/// To export a gbxmodel tag, convert it with [`ModelFunctions::convert_to_model`] first.
+pub fn export_model_to_jms(model: &Model) -> RinghopperResult<Vec<JMSModelSource>> {
+    model.check_indices()?;
+
+    let nodes: Vec<JMSNode> = model.nodes.items.iter().map(|n| JMSNode {
+        name: n.name.to_string(),
+        first_child: n.first_child_node_index.map(|i| i as usize),
+        sibling: n.next_sibling_node_index.map(|i| i as usize),
+        rotation: n.default_rotation,
+        translation: n.default_translation.scale(JMS_UNITS_PER_WORLD_UNIT)
+    }).collect();
+
+    let materials: Vec<JMSMaterial> = shader_names(model).into_iter().map(|name| JMSMaterial {
+        name,
+        tiff_path: "<none>".to_owned()
+    }).collect();
+
+    let regions: Vec<String> = model.regions.items.iter().map(|r| r.name.to_string()).collect();
+
+    let mut sources = Vec::new();
+    for permutation_name in permutation_names(model) {
+        let permutations: Vec<(usize, &ModelRegionPermutation)> = model
+            .regions
+            .items
+            .iter()
+            .enumerate()
+            .filter_map(|(index, region)| region.permutations.items.iter().find(|p| p.name.as_str() == permutation_name).map(|p| (index, p)))
+            .collect();
+
+        for lod in exported_lods(permutations.iter().map(|p| p.1)) {
+            let mut jms = JMS {
+                node_list_checksum: model.node_list_checksum,
+                nodes: nodes.clone(),
+                materials: materials.clone(),
+                regions: regions.clone(),
+                ..Default::default()
+            };
+
+            for &(region_index, permutation) in &permutations {
+                if lod == ModelLOD::SuperHigh {
+                    for marker in &permutation.markers {
+                        jms.markers.push(JMSMarker {
+                            name: marker.name.to_string(),
+                            region: Some(region_index),
+                            node: marker.node_index.unwrap_or_default() as usize,
+                            rotation: marker.rotation,
+                            translation: marker.translation.scale(JMS_UNITS_PER_WORLD_UNIT),
+                            radius: 1.0
+                        });
+                    }
+                }
+
+                let Some(geometry) = lod_geometries(permutation)[lod as usize] else {
+                    continue
+                };
+                for part in &model.geometries.items[geometry as usize].parts {
+                    export_part_to_jms(part, region_index, &mut jms)?;
+                }
+            }
+
+            sources.push(JMSModelSource { permutation: permutation_name.to_owned(), lod, jms });
+        }
+    }
+
+    Ok(sources)
+}
+
+/// Export a model as a binary glTF (.glb) file.
+///
+/// Each node of the model becomes a node in the scene, with markers as child nodes prefixed with `#`. Each exported
+/// level of detail of each permutation of each region (see [`export_model_to_jms`]) becomes a mesh node named
+/// `<region> <permutation> <lod>`.
+///
+/// To export a gbxmodel tag, convert it with [`ModelFunctions::convert_to_model`] first.
+pub fn export_model_to_gltf(model: &Model) -> RinghopperResult<Vec<u8>> {
+    model.check_indices()?;
+
+    let mut gltf = GLTF {
+        materials: shader_names(model),
+        ..Default::default()
+    };
+
+    for node in &model.nodes {
+        let mut children = Vec::new();
+        let mut child = node.first_child_node_index;
+        while let Some(c) = child {
+            if children.contains(&(c as usize)) {
+                return Err(Error::InvalidTagData("corrupted model: node hierarchy has a loop".to_owned()))
+            }
+            children.push(c as usize);
+            child = model.nodes.items[c as usize].next_sibling_node_index;
+        }
+
+        gltf.nodes.push(GLTFNode {
+            name: node.name.to_string(),
+            translation: node.default_translation,
+            rotation: node.default_rotation,
+            children,
+            mesh: None
+        });
+    }
+    gltf.scene_nodes = model.nodes.items.iter().enumerate().filter(|n| n.1.parent_node_index.is_none()).map(|n| n.0).collect();
+
+    for geometry in &model.geometries {
+        let mut mesh = GLTFMesh::default();
+        for part in &geometry.parts {
+            let vertices = part_vertices(part);
+            mesh.primitives.push(GLTFPrimitive {
+                positions: vertices.iter().map(|v| v.position).collect(),
+                normals: vertices.iter().map(|v| v.normal).collect(),
+                texture_coordinates: vertices.iter().map(|v| v.texture_coords).collect(),
+                indices: part_triangles(part).into_iter().flatten().map(|i| i as u32).collect(),
+                material: part.shader_index.map(|s| s as usize)
+            });
+        }
+        gltf.meshes.push(mesh);
+    }
+
+    for region in &model.regions {
+        for permutation in &region.permutations {
+            for marker in &permutation.markers {
+                let Some(node) = marker.node_index else {
+                    continue
+                };
+                gltf.nodes.push(GLTFNode {
+                    name: format!("#{}", marker.name),
+                    translation: marker.translation,
+                    rotation: marker.rotation,
+                    ..Default::default()
+                });
+                let marker_node = gltf.nodes.len() - 1;
+                gltf.nodes[node as usize].children.push(marker_node);
+            }
+
+            let geometries = lod_geometries(permutation);
+            for lod in exported_lods(std::iter::once(permutation)) {
+                let Some(geometry) = geometries[lod as usize] else {
+                    continue
+                };
+                gltf.nodes.push(GLTFNode {
+                    name: format!("{} {} {}", region.name, permutation.name, lod.as_str()),
+                    mesh: Some(geometry as usize),
+                    rotation: Vector::one(),
+                    ..Default::default()
+                });
+                gltf.scene_nodes.push(gltf.nodes.len() - 1);
+            }
+        }
+    }
+
+    Ok(gltf.to_glb())
+}
+
+/// Get the names of all shaders, which are used as material names.
+fn shader_names(model: &Model) -> Vec<String> {
+    model.shaders.items.iter().map(|s| match s.shader.path() {
+        Some(path) => path.base_name().to_owned(),
+        None => "null".to_owned()
+    }).collect()
+}
+
+/// Get the unique permutation names of all regions in order.
+fn permutation_names(model: &Model) -> Vec<&str> {
+    let mut names: Vec<&str> = Vec::new();
+    for region in &model.regions {
+        for permutation in &region.permutations {
+            if !names.contains(&permutation.name.as_str()) {
+                names.push(permutation.name.as_str());
+            }
+        }
+    }
+    names
+}
+
+/// Get the geometry indices of a permutation, in the same order as [`ModelLOD::ALL`].
+fn lod_geometries(permutation: &ModelRegionPermutation) -> [Index; 5] {
+    [permutation.super_low, permutation.low, permutation.medium, permutation.high, permutation.super_high]
+}
+
+/// Get the levels of detail worth exporting, from highest to lowest.
+///
+/// Super high is always exported. Lower levels of detail are exported if they differ from the next highest level of
+/// detail in any region, since the model compiler falls back to the next highest level of detail.
+fn exported_lods<'a, I: Iterator<Item = &'a ModelRegionPermutation> + Clone>(permutations: I) -> Vec<ModelLOD> {
+    let mut lods = vec![ModelLOD::SuperHigh];
+    for lod in (0..ModelLOD::ALL.len() - 1).rev() {
+        if permutations.clone().any(|p| {
+            let geometries = lod_geometries(p);
+            geometries[lod] != geometries[lod + 1]
+        }) {
+            lods.push(ModelLOD::ALL[lod]);
+        }
+    }
+    lods
+}
+
+/// Get the vertices of a part, decompressing them if there are no uncompressed vertices.
+fn part_vertices(part: &ModelGeometryPart) -> Vec<ModelVertexUncompressed> {
+    if part.uncompressed_vertices.items.is_empty() {
+        part.compressed_vertices.items.iter().map(decompress_model_vertex).collect()
+    }
+    else {
+        part.uncompressed_vertices.items.clone()
+    }
+}
+
+fn part_triangles(part: &ModelGeometryPart) -> Vec<[u16; 3]> {
+    let strip: Vec<Index> = part.triangle_data.items.iter().flat_map(|t| t.indices).collect();
+    triangle_strip_to_triangles(&strip)
+}
+
+fn export_part_to_jms(part: &ModelGeometryPart, region: usize, jms: &mut JMS) -> RinghopperResult<()> {
+    let material = part.shader_index.ok_or_else(|| Error::InvalidTagData("corrupted model: part has no shader".to_owned()))? as usize;
+    let vertices = part_vertices(part);
+    let first_vertex = jms.vertices.len();
+
+    for triangle in part_triangles(part) {
+        if triangle.iter().any(|v| *v as usize >= vertices.len()) {
+            return Err(Error::InvalidTagData("corrupted model: triangle index out-of-bounds".to_owned()))
+        }
+        jms.triangles.push(JMSTriangle {
+            region,
+            material,
+            vertices: triangle.map(|v| first_vertex + v as usize)
+        });
+    }
+
+    for vertex in vertices {
+        let node1 = vertex.node1_index.filter(|_| vertex.node1_weight > 0.0);
+        jms.vertices.push(JMSVertex {
+            node0: vertex.node0_index.unwrap_or_default() as usize,
+            position: vertex.position.scale(JMS_UNITS_PER_WORLD_UNIT),
+            normal: vertex.normal,
+            node1: node1.map(|n| n as usize),
+            node1_weight: if node1.is_some() { vertex.node1_weight } else { 0.0 },
+            texture_coordinates: Vector2D {
+                x: vertex.texture_coords.x,
+                y: 1.0 - vertex.texture_coords.y
+            }
+        });
+    }
+
+    Ok(())
+}
@@ -0,0 +1,220 @@
+use definitions::Model;
+use primitives::primitive::{Index, Quaternion, TagPath, TagReference, Vector2D, Vector3D};
+use crate::data::jms::*;
+use super::*;
+
+fn canonical_triangle(triangle: [u16; 3]) -> [u16; 3] {
+    let min = triangle.iter().position(|v| v == triangle.iter().min().unwrap()).unwrap();
+    [triangle[min], triangle[(min + 1) % 3], triangle[(min + 2) % 3]]
+}
+
+fn decode_triangle_strip(strip: &[u16]) -> Vec<[u16; 3]> {
+    let strip: Vec<Index> = strip.iter().map(|i| Some(*i)).collect();
+    let mut triangles: Vec<[u16; 3]> = triangle_strip_to_triangles(&strip).into_iter().map(canonical_triangle).collect();
+    triangles.sort();
+    triangles
+}
+
+#[test]
+fn triangle_strips_preserve_triangles() {
+    // A 4x4 grid of quads plus a disconnected triangle
+    let mut triangles = Vec::new();
+    for y in 0..4u16 {
+        for x in 0..4u16 {
+            let a = y * 5 + x;
+            let b = a + 1;
+            let c = a + 5;
+            let d = c + 1;
+            triangles.push([a, b, d]);
+            triangles.push([a, d, c]);
+        }
+    }
+    triangles.push([100, 101, 102]);
+
+    let strip = generate_triangle_strip(&triangles);
+    assert!(strip.len() < triangles.len() * 3);
+
+    let mut expected: Vec<[u16; 3]> = triangles.into_iter().map(canonical_triangle).collect();
+    expected.sort();
+    assert_eq!(expected, decode_triangle_strip(&strip));
+}
+
+fn vertex(position: Vector3D, texture_coordinates: Vector2D) -> JMSVertex {
+    JMSVertex {
+        node0: 1,
+        position,
+        normal: Vector3D { x: 0.0, y: 0.0, z: 1.0 },
+        node1: None,
+        node1_weight: 0.0,
+        texture_coordinates
+    }
+}
+
+fn test_jms() -> JMS {
+    let node = |name: &str, first_child, translation| JMSNode {
+        name: name.to_owned(),
+        first_child,
+        sibling: None,
+        rotation: Quaternion { x: 0.0, y: 0.0, z: 0.0, w: 1.0 },
+        translation
+    };
+
+    JMS {
+        node_list_checksum: 1234,
+        nodes: vec![
+            node("frame root", Some(1), Vector3D::default()),
+            node("bone", None, Vector3D { x: 300.0, y: 0.0, z: 400.0 })
+        ],
+        materials: vec![
+            JMSMaterial { name: "metal".to_owned(), tiff_path: "<none>".to_owned() },
+            JMSMaterial { name: "glass".to_owned(), tiff_path: "<none>".to_owned() }
+        ],
+        markers: vec![
+            JMSMarker {
+                name: "muzzle".to_owned(),
+                region: None,
+                node: 1,
+                rotation: Quaternion { x: 0.0, y: 0.0, z: 0.0, w: 1.0 },
+                translation: Vector3D { x: 100.0, y: 0.0, z: 0.0 },
+                radius: 1.0
+            }
+        ],
+        regions: vec!["hull".to_owned(), "base".to_owned()],
+        vertices: vec![
+            vertex(Vector3D { x: 0.0, y: 0.0, z: 0.0 }, Vector2D { x: 0.0, y: 0.0 }),
+            vertex(Vector3D { x: 100.0, y: 0.0, z: 0.0 }, Vector2D { x: 1.0, y: 0.0 }),
+            vertex(Vector3D { x: 100.0, y: 100.0, z: 0.0 }, Vector2D { x: 1.0, y: 1.0 }),
+            vertex(Vector3D { x: 0.0, y: 100.0, z: 0.0 }, Vector2D { x: 0.0, y: 1.0 }),
+
+            // duplicate of vertex 2
+            vertex(Vector3D { x: 100.0, y: 100.0, z: 0.0 }, Vector2D { x: 1.0, y: 1.0 })
+        ],
+        triangles: vec![
+            JMSTriangle { region: 1, material: 0, vertices: [0, 1, 2] },
+            JMSTriangle { region: 1, material: 0, vertices: [0, 4, 3] },
+            JMSTriangle { region: 0, material: 1, vertices: [0, 1, 3] }
+        ]
+    }
+}
+
+#[test]
+fn compile_model_from_jms() {
+    let source = |permutation: &str, lod| JMSModelSource { permutation: permutation.to_owned(), lod, jms: test_jms() };
+    let sources = vec![source("base", ModelLOD::SuperHigh), source("base", ModelLOD::Low), source("damaged", ModelLOD::SuperHigh)];
+
+    let mut shaders_looked_up = Vec::new();
+    let mut model = Model::default();
+    let options = ModelCompileOptions { lod_cutoffs: Some([1.0, 2.0, 3.0, 4.0, 5.0]) };
+    compile_model(&mut model, &sources, &options, |name| {
+        shaders_looked_up.push(name.to_owned());
+        Ok(TagReference::Set(TagPath::new(&format!("shaders\\{name}"), TagGroup::ShaderModel).unwrap()))
+    }).unwrap();
+
+    assert_eq!(vec!["glass", "metal"], shaders_looked_up);
+    assert_eq!(1234, model.node_list_checksum);
+    assert_eq!(1.0, model.detail_cutoff.super_low);
+    assert_eq!(5.0, model.detail_cutoff.super_high);
+
+    assert_eq!(2, model.nodes.items.len());
+    assert_eq!(Some(0), model.nodes.items[1].parent_node_index);
+    assert_eq!(5.0, model.nodes.items[1].node_distance_from_parent);
+
+    // Regions and permutations are sorted by name.
+    let regions: Vec<&str> = model.regions.items.iter().map(|r| r.name.as_str()).collect();
+    assert_eq!(vec!["base", "hull"], regions);
+
+    let base = &model.regions.items[0].permutations.items[0];
+    assert_eq!("base", base.name.as_str());
+    assert_eq!(1, base.markers.items.len());
+    assert_eq!(1.0, base.markers.items[0].translation.x);
+
+    // Missing LoDs use the next highest LoD.
+    assert_ne!(base.super_high, base.low);
+    assert_eq!(base.super_high, base.high);
+    assert_eq!(base.super_high, base.medium);
+    assert_eq!(base.low, base.super_low);
+
+    // Two regions for each of the three JMS files
+    assert_eq!(6, model.geometries.items.len());
+    let part = &model.geometries.items[base.super_high.unwrap() as usize].parts.items[0];
+    assert_eq!(4, part.uncompressed_vertices.items.len());
+    assert_eq!(4, part.compressed_vertices.items.len());
+    assert_eq!(Some(1), part.shader_index);
+    assert_eq!(Some(1), part.centroid_primary_node);
+
+    let vertex = &part.uncompressed_vertices.items[0];
+    assert_eq!(1.0, vertex.texture_coords.y);
+    assert_eq!(1.0, vertex.tangent.x);
+
+    model.check_indices().unwrap();
+}
+
+#[test]
+fn compile_model_rejects_mismatched_nodes() {
+    let mut other = test_jms();
+    other.nodes[1].name = "other".to_owned();
+
+    let sources = vec![
+        JMSModelSource { permutation: "base".to_owned(), lod: ModelLOD::SuperHigh, jms: test_jms() },
+        JMSModelSource { permutation: "other".to_owned(), lod: ModelLOD::SuperHigh, jms: other }
+    ];
+
+    let result = compile_model(&mut Model::default(), &sources, &ModelCompileOptions::default(), |_| Ok(TagReference::Null(TagGroup::Shader)));
+    assert!(result.is_err());
+}
+
+#[test]
+fn export_model_to_jms_and_back() {
+    let source = |permutation: &str, lod| JMSModelSource { permutation: permutation.to_owned(), lod, jms: test_jms() };
+    let sources = vec![source("base", ModelLOD::SuperHigh), source("base", ModelLOD::Low), source("damaged", ModelLOD::SuperHigh)];
+    let find_shader = |name: &str| Ok(TagReference::Set(TagPath::new(&format!("shaders\\{name}"), TagGroup::ShaderModel).unwrap()));
+
+    let mut model = Model::default();
+    compile_model(&mut model, &sources, &ModelCompileOptions::default(), find_shader).unwrap();
+
+    // Only LoDs that differ from the next highest LoD are exported.
+    let exported = export_model_to_jms(&model).unwrap();
+    let names: Vec<(&str, ModelLOD)> = exported.iter().map(|s| (s.permutation.as_str(), s.lod)).collect();
+    assert_eq!(vec![("base", ModelLOD::SuperHigh), ("base", ModelLOD::Low), ("damaged", ModelLOD::SuperHigh)], names);
+
+    let jms = &exported[0].jms;
+    assert_eq!(1234, jms.node_list_checksum);
+    assert_eq!(vec!["base".to_owned(), "hull".to_owned()], jms.regions);
+    assert_eq!(3, jms.triangles.len());
+    assert_eq!(7, jms.vertices.len());
+    assert_eq!(2, jms.markers.len());
+    assert_eq!(100.0, jms.markers[0].translation.x);
+    assert!(exported[1].jms.markers.is_empty());
+    jms.check_indices().unwrap();
+
+    // Recompiling the exported JMS files results in the same model.
+    let reparsed: Vec<JMSModelSource> = exported.iter().map(|s| JMSModelSource {
+        permutation: s.permutation.clone(),
+        lod: s.lod,
+        jms: JMS::parse(std::str::from_utf8(&s.jms.to_jms()).unwrap()).unwrap()
+    }).collect();
+    let mut recompiled = Model::default();
+    compile_model(&mut recompiled, &reparsed, &ModelCompileOptions::default(), find_shader).unwrap();
+
+    let triangles = |model: &Model, geometry: Index| {
+        let mut triangles: Vec<[u16; 3]> = model.geometries.items[geometry.unwrap() as usize].parts.items.iter().flat_map(|p| {
+            let strip: Vec<Index> = p.triangle_data.items.iter().flat_map(|t| t.indices).collect();
+            triangle_strip_to_triangles(&strip)
+        }).map(canonical_triangle).collect();
+        triangles.sort();
+        triangles
+    };
+    assert_eq!(model.regions.items.len(), recompiled.regions.items.len());
+    for (a, b) in model.regions.items.iter().zip(recompiled.regions.items.iter()) {
+        assert_eq!(a.permutations.items.len(), b.permutations.items.len());
+        for (a, b) in a.permutations.items.iter().zip(b.permutations.items.iter()) {
+            assert_eq!(a.name, b.name);
+            assert_eq!(a.markers.items.len(), b.markers.items.len());
+            assert_eq!(triangles(&model, a.super_high), triangles(&recompiled, b.super_high));
+            assert_eq!(triangles(&model, a.super_low), triangles(&recompiled, b.super_low));
+        }
+    }
+
+    let glb = export_model_to_gltf(&model).unwrap();
+    assert_eq!(b"glTF", &glb[0..4]);
+}
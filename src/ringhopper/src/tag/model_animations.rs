@@ -3,6 +3,16 @@ use primitives::byteorder::ByteOrder;
 use primitives::error::{Error, OverflowCheck, RinghopperResult};
 use primitives::parse::SimpleTagData;
 
+#[cfg(test)]
+mod test;
+mod compile;
+mod compression;
+mod export;
+
+pub use compile::*;
+pub use compression::*;
+pub use export::*;
+
 #[derive(Default, Clone, Copy, Debug)]
 pub enum FrameDataType {
     #[default]
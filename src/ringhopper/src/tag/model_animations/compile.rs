@@ -0,0 +1,290 @@
+use definitions::{AnimationFrameInfoType, AnimationType, ModelAnimations, ModelAnimationsAnimation, ModelAnimationsAnimationGraphNode};
+use primitives::error::{Error, RinghopperResult};
+use primitives::primitive::{Quaternion, String32, Vector, Vector3D};
+use crate::data::jma::{JMAAnimationSource, JMAKind, JMANodeTransform, JMA};
+use crate::data::jms::JMS_UNITS_PER_WORLD_UNIT;
+
+/// Maximum number of nodes an animation can have, since node flags are stored as 64-bit masks.
+pub const MAX_ANIMATION_NODES: usize = 64;
+
+/// Compile JMA-family files into a model_animations tag.
+///
+/// Animations are matched by name: existing animations are replaced, keeping any fields that are not derived from the
+/// animation data (such as sounds and next animations), and new animations are added to the end.
+///
+/// If the tag has no nodes, nodes are created from the animations. Otherwise, the animations must have the same nodes
+/// as the tag.
+pub fn compile_animations(tag: &mut ModelAnimations, sources: &[JMAAnimationSource]) -> RinghopperResult<()> {
+    let first = match sources.first() {
+        Some(n) => &n.jma,
+        None => return Err(Error::Other("no animation files to compile".to_owned()))
+    };
+
+    if first.nodes.len() > MAX_ANIMATION_NODES {
+        return Err(Error::Other(format!("animations have {} nodes (more than {MAX_ANIMATION_NODES})", first.nodes.len())))
+    }
+
+    for source in sources {
+        let same_nodes = source.jma.node_list_checksum == first.node_list_checksum
+            && source.jma.nodes.len() == first.nodes.len()
+            && source.jma.nodes.iter().zip(first.nodes.iter()).all(|(a, b)| a.name == b.name);
+        if !same_nodes {
+            return Err(Error::Other(format!("animation `{}` has a different node list than `{}`", source.name, sources[0].name)))
+        }
+    }
+
+    if tag.nodes.items.is_empty() {
+        tag.nodes.items = compile_nodes(first)?;
+    }
+    else {
+        let same_nodes = tag.nodes.items.len() == first.nodes.len()
+            && tag.nodes.items.iter().zip(first.nodes.iter()).all(|(a, b)| a.name.as_str() == b.name);
+        if !same_nodes {
+            return Err(Error::Other("animations have a different node list than the tag".to_owned()))
+        }
+    }
+
+    for source in sources {
+        let existing = tag.animations.items.iter().position(|a| a.name.as_str() == source.name);
+        let mut animation = existing.map(|i| tag.animations.items[i].clone()).unwrap_or_default();
+        compile_animation(&mut animation, source)?;
+        match existing {
+            Some(i) => tag.animations.items[i] = animation,
+            None => tag.animations.items.push(animation)
+        }
+    }
+
+    Ok(())
+}
+
+fn compile_nodes(jma: &JMA) -> RinghopperResult<Vec<ModelAnimationsAnimationGraphNode>> {
+    let parents = jma.node_parents()?;
+    jma.nodes.iter().zip(parents).map(|(node, parent)| Ok(ModelAnimationsAnimationGraphNode {
+        name: name_to_string32("node", &node.name)?,
+        next_sibling_node_index: node.sibling.map(|i| i as u16),
+        first_child_node_index: node.first_child.map(|i| i as u16),
+        parent_node_index: parent.map(|i| i as u16),
+        ..Default::default()
+    })).collect()
+}
+
+fn name_to_string32(what: &str, name: &str) -> RinghopperResult<String32> {
+    String32::from_str(name).map_err(|_| Error::Other(format!("{what} name `{name}` is longer than 31 characters")))
+}
+
+fn compile_animation(animation: &mut ModelAnimationsAnimation, source: &JMAAnimationSource) -> RinghopperResult<()> {
+    let jma = &source.jma;
+    let node_count = jma.nodes.len();
+    let frame_count = jma.frames.len();
+    if frame_count == 0 {
+        return Err(Error::Other(format!("animation `{}` has no frames", source.name)))
+    }
+    if frame_count > u16::MAX as usize {
+        return Err(Error::Other(format!("animation `{}` has {frame_count} frames (more than {})", source.name, u16::MAX)))
+    }
+
+    let (animation_type, frame_info_type) = match source.kind {
+        JMAKind::JMA => (AnimationType::Base, AnimationFrameInfoType::DxDy),
+        JMAKind::JMM | JMAKind::JMW => (AnimationType::Base, AnimationFrameInfoType::None),
+        JMAKind::JMO => (AnimationType::Overlay, AnimationFrameInfoType::None),
+        JMAKind::JMR => (AnimationType::Replacement, AnimationFrameInfoType::None),
+        JMAKind::JMT => (AnimationType::Base, AnimationFrameInfoType::DxDyDyaw),
+        JMAKind::JMZ => (AnimationType::Base, AnimationFrameInfoType::DxDyDzDyaw)
+    };
+
+    // Convert to world units.
+    let mut frames: Vec<Vec<JMANodeTransform>> = jma.frames.iter().map(|frame| frame.iter().map(|node| JMANodeTransform {
+        rotation: node.rotation.normalize(),
+        translation: node.translation.scale(1.0 / JMS_UNITS_PER_WORLD_UNIT),
+        scale: node.scale
+    }).collect()).collect();
+
+    let frame_info = extract_frame_info(&mut frames, frame_info_type);
+
+    if animation_type == AnimationType::Overlay {
+        make_relative_to_first_frame(&mut frames);
+    }
+
+    let encoded: Vec<Vec<EncodedNodeTransform>> = frames.iter().map(|frame| frame.iter().map(EncodedNodeTransform::encode).collect()).collect();
+
+    // A node's rotation, translation, or scale is only stored for each frame if it changes. Otherwise, it is stored
+    // once in the default data.
+    let mut rotation_flags = 0u64;
+    let mut transform_flags = 0u64;
+    let mut scale_flags = 0u64;
+    for node in 0..node_count {
+        let first = &encoded[0][node];
+        let mask = 1u64 << node;
+        if encoded.iter().any(|f| f[node].rotation != first.rotation) {
+            rotation_flags |= mask;
+        }
+        if encoded.iter().any(|f| f[node].translation != first.translation) {
+            transform_flags |= mask;
+        }
+        if encoded.iter().any(|f| f[node].scale != first.scale) {
+            scale_flags |= mask;
+        }
+    }
+
+    let mut frame_data = Vec::new();
+    for frame in &encoded {
+        write_node_transforms(&mut frame_data, frame, rotation_flags, transform_flags, scale_flags);
+    }
+
+    let mut default_data = Vec::new();
+    write_node_transforms(&mut default_data, &encoded[0], !rotation_flags, !transform_flags, !scale_flags);
+
+    let frame_size = frame_data.len() / frame_count;
+    if frame_size > u16::MAX as usize {
+        return Err(Error::Other(format!("animation `{}` has {frame_size} bytes per frame (more than {})", source.name, u16::MAX)))
+    }
+
+    let flag_data = |flags: u64| [flags as u32, (flags >> 32) as u32];
+
+    animation.name = name_to_string32("animation", &source.name)?;
+    animation._type = animation_type;
+    animation.frame_info_type = frame_info_type;
+    animation.frame_count = frame_count as u16;
+    animation.frame_size = frame_size as u16;
+    animation.node_count = node_count as u16;
+    animation.node_list_checksum = jma.node_list_checksum;
+    animation.node_rotation_flag_data = flag_data(rotation_flags);
+    animation.node_transform_flag_data = flag_data(transform_flags);
+    animation.node_scale_flag_data = flag_data(scale_flags);
+    animation.flags.compressed_data = false;
+    animation.flags.world_relative = source.kind == JMAKind::JMW;
+    animation.offset_to_compressed_data = 0;
+    animation.frame_info.bytes = frame_info;
+    animation.default_data.bytes = default_data;
+    animation.frame_data.bytes = frame_data;
+
+    if animation.loop_frame_index as usize >= frame_count {
+        animation.loop_frame_index = 0;
+    }
+
+    Ok(())
+}
+
+/// Rotation, translation, and scale of a node as stored in a tag.
+struct EncodedNodeTransform {
+    rotation: [i16; 4],
+    translation: [f32; 3],
+    scale: f32
+}
+
+impl EncodedNodeTransform {
+    fn encode(transform: &JMANodeTransform) -> Self {
+        let compress = |c: f32| (c.clamp(-1.0, 1.0) * i16::MAX as f32).round() as i16;
+        let Quaternion { x, y, z, w } = transform.rotation;
+        let Vector3D { x: tx, y: ty, z: tz } = transform.translation;
+        Self {
+            rotation: [compress(x), compress(y), compress(z), compress(w)],
+            translation: [tx, ty, tz],
+            scale: transform.scale
+        }
+    }
+}
+
+/// Write the rotations, translations, and scales of nodes whose flags are set.
+///
+/// This is the same order that [`FrameDataIterator`](super::FrameDataIterator) reads them in. Tag data is big endian.
+fn write_node_transforms(data: &mut Vec<u8>, nodes: &[EncodedNodeTransform], rotation_flags: u64, transform_flags: u64, scale_flags: u64) {
+    for (index, node) in nodes.iter().enumerate() {
+        let mask = 1u64 << index;
+        if rotation_flags & mask != 0 {
+            data.extend(node.rotation.iter().flat_map(|c| c.to_be_bytes()));
+        }
+        if transform_flags & mask != 0 {
+            data.extend(node.translation.iter().flat_map(|c| c.to_be_bytes()));
+        }
+        if scale_flags & mask != 0 {
+            data.extend_from_slice(&node.scale.to_be_bytes());
+        }
+    }
+}
+
+/// Move the root node's movement into frame info.
+///
+/// Each frame's frame info is the movement from that frame to the next frame; the last frame does not move. The root
+/// node's movement is removed from the frames, leaving it where it was on the first frame.
+fn extract_frame_info(frames: &mut [Vec<JMANodeTransform>], frame_info_type: AnimationFrameInfoType) -> Vec<u8> {
+    let (use_z, use_yaw) = match frame_info_type {
+        AnimationFrameInfoType::None => return Vec::new(),
+        AnimationFrameInfoType::DxDy => (false, false),
+        AnimationFrameInfoType::DxDyDyaw => (false, true),
+        AnimationFrameInfoType::DxDyDzDyaw => (true, true)
+    };
+
+    if frames.first().is_none_or(|f| f.is_empty()) {
+        return Vec::new()
+    }
+
+    let roots: Vec<JMANodeTransform> = frames.iter().map(|f| f[0]).collect();
+    let mut frame_info = Vec::new();
+    for (index, root) in roots.iter().enumerate() {
+        let next = roots.get(index + 1).unwrap_or(root);
+        let mut values = vec![next.translation.x - root.translation.x, next.translation.y - root.translation.y];
+        if use_z {
+            values.push(next.translation.z - root.translation.z);
+        }
+        if use_yaw {
+            values.push(wrap_angle(yaw(next.rotation) - yaw(root.rotation)));
+        }
+        frame_info.extend(values.iter().flat_map(|v| v.to_be_bytes()));
+    }
+
+    let first = roots[0];
+    for frame in frames.iter_mut() {
+        let root = &mut frame[0];
+        root.translation.x = first.translation.x;
+        root.translation.y = first.translation.y;
+        if use_z {
+            root.translation.z = first.translation.z;
+        }
+        if use_yaw {
+            let turned = wrap_angle(yaw(root.rotation) - yaw(first.rotation));
+            root.rotation = multiply_quaternions(z_rotation(-turned), root.rotation).normalize();
+        }
+    }
+
+    frame_info
+}
+
+/// Store each frame as the difference from the first frame.
+fn make_relative_to_first_frame(frames: &mut [Vec<JMANodeTransform>]) {
+    let Some(first) = frames.first().cloned() else {
+        return
+    };
+    for frame in frames.iter_mut() {
+        for (node, base) in frame.iter_mut().zip(first.iter()) {
+            let inverse = Quaternion { x: -base.rotation.x, y: -base.rotation.y, z: -base.rotation.z, w: base.rotation.w };
+            node.rotation = multiply_quaternions(inverse, node.rotation).normalize();
+            node.translation -= base.translation;
+            node.scale = if base.scale != 0.0 { node.scale / base.scale } else { node.scale };
+        }
+    }
+}
+
+pub(crate) fn multiply_quaternions(a: Quaternion, b: Quaternion) -> Quaternion {
+    Quaternion {
+        x: a.w * b.x + a.x * b.w + a.y * b.z - a.z * b.y,
+        y: a.w * b.y - a.x * b.z + a.y * b.w + a.z * b.x,
+        z: a.w * b.z + a.x * b.y - a.y * b.x + a.z * b.w,
+        w: a.w * b.w - a.x * b.x - a.y * b.y - a.z * b.z
+    }
+}
+
+pub(super) fn yaw(q: Quaternion) -> f32 {
+    (2.0 * (q.w * q.z + q.x * q.y)).atan2(1.0 - 2.0 * (q.y * q.y + q.z * q.z))
+}
+
+pub(super) fn z_rotation(angle: f32) -> Quaternion {
+    let (sin, cos) = (angle * 0.5).sin_cos();
+    Quaternion { x: 0.0, y: 0.0, z: sin, w: cos }
+}
+
+fn wrap_angle(angle: f32) -> f32 {
+    let tau = std::f32::consts::TAU;
+    let wrapped = (angle + std::f32::consts::PI).rem_euclid(tau) - std::f32::consts::PI;
+    if wrapped <= -std::f32::consts::PI { wrapped + tau } else { wrapped }
+}
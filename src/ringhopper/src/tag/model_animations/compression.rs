@@ -0,0 +1,414 @@
+use definitions::ModelAnimationsAnimation;
+use primitives::error::{Error, OverflowCheck, RinghopperResult};
+use primitives::primitive::{Quaternion, Vector, Vector3D};
+use super::{FrameDataIterator, MAX_ANIMATION_NODES};
+
+// Compressed animation data is stored after the uncompressed frame data (at `offset_to_compressed_data`). Unlike the
+// rest of the tag, it is little endian in both tag files and cache files. It starts with a header of 11 offsets,
+// relative to the start of the compressed data:
+//
+// - rotation keyframe headers, rotation keyframes, default rotations, rotation values
+// - translation keyframe headers, translation keyframes, default translations, translation values
+// - scale keyframe headers, scale keyframes, default scales (scale values immediately follow default scales)
+//
+// Each node with an animated rotation, translation, or scale (see `FrameDataIterator`) has a 32-bit keyframe header,
+// where the lower 12 bits are the number of keyframes and the upper 20 bits are the index of its first keyframe. A
+// keyframe is a 16-bit frame index, and it has a corresponding value at the same index. The default value is used for
+// frame 0, and frames between keyframes are interpolated.
+//
+// Rotations are packed into 48 bits, with each component being a 12-bit ones' complement integer. Translations are
+// three 32-bit floats, and scales are one 32-bit float.
+
+const COMPRESSED_HEADER_OFFSET_COUNT: usize = 11;
+const MAX_KEYFRAME_COUNT: usize = 0xFFF;
+const MAX_KEYFRAME_INDEX: usize = 0xFFFFF;
+const COMPRESSED_QUATERNION_SCALE: f32 = 2047.0;
+const UNCOMPRESSED_QUATERNION_SCALE: f32 = i16::MAX as f32;
+
+const ROTATION_TOLERANCE: f32 = 1.0 / COMPRESSED_QUATERNION_SCALE;
+const TRANSLATION_TOLERANCE: f32 = 0.0001;
+const SCALE_TOLERANCE: f32 = 0.0001;
+
+/// Decompress the compressed frame data of an animation, if it is compressed.
+///
+/// The compressed data is replaced with uncompressed frame data. If the animation still has its uncompressed frame
+/// data before the compressed data, that is kept as-is, since decoding compressed data is lossy.
+pub fn decompress_animation(animation: &mut ModelAnimationsAnimation) -> RinghopperResult<()> {
+    if !animation.flags.compressed_data {
+        return Ok(())
+    }
+
+    check_node_count(animation)?;
+    let offset = animation.offset_to_compressed_data as usize;
+    let uncompressed_size = FrameDataIterator::for_animation(animation).to_size().mul_overflow_checked(animation.frame_count as usize)?;
+
+    if offset != 0 && offset == uncompressed_size && offset <= animation.frame_data.bytes.len() {
+        animation.frame_data.bytes.truncate(offset);
+    }
+    else {
+        let compressed = animation.frame_data.bytes.get(offset..).ok_or_else(|| Error::InvalidTagData(format!("model animation compressed data offset {offset} is out of bounds")))?;
+        let frames = AnimationFrames::decode_compressed(animation, compressed)?;
+        animation.frame_data.bytes = frames.encode_uncompressed(animation);
+    }
+
+    animation.offset_to_compressed_data = 0;
+    animation.flags.compressed_data = false;
+    Ok(())
+}
+
+/// Compress the frame data of an animation, if it is not already compressed.
+///
+/// The uncompressed frame data is kept, and the compressed data is appended to it.
+pub fn compress_animation(animation: &mut ModelAnimationsAnimation) -> RinghopperResult<()> {
+    if animation.flags.compressed_data {
+        return Ok(())
+    }
+
+    check_node_count(animation)?;
+    if animation.frame_count == 0 {
+        return Err(Error::InvalidTagData("model animation has no frames".to_owned()))
+    }
+
+    let frames = AnimationFrames::decode_uncompressed(animation)?;
+    let compressed = frames.encode_compressed()?;
+
+    let offset = animation.frame_data.bytes.len();
+    if offset.add_overflow_checked(compressed.len())? > u32::MAX as usize {
+        return Err(Error::ArrayLimitExceeded)
+    }
+
+    animation.frame_data.bytes.extend_from_slice(&compressed);
+    animation.offset_to_compressed_data = offset as u32;
+    animation.flags.compressed_data = true;
+    Ok(())
+}
+
+/// Values of each animated node on each frame.
+///
+/// Each list has one entry per node with the corresponding flag set, in node order, and each entry has one value per
+/// frame.
+#[derive(Default)]
+struct AnimationFrames {
+    rotations: Vec<Vec<Quaternion>>,
+    translations: Vec<Vec<Vector3D>>,
+    scales: Vec<Vec<f32>>
+}
+
+pub(super) fn node_flags(flag_data: [u32; 2]) -> u64 {
+    ((flag_data[1] as u64) << 32) | (flag_data[0] as u64)
+}
+
+fn animated_node_count(flag_data: [u32; 2], node_count: usize) -> usize {
+    let flags = node_flags(flag_data);
+    (0..node_count).filter(|n| flags & (1 << n) != 0).count()
+}
+
+fn check_node_count(animation: &ModelAnimationsAnimation) -> RinghopperResult<()> {
+    if animation.node_count as usize > MAX_ANIMATION_NODES {
+        return Err(Error::InvalidTagData(format!("model animation has {} nodes (more than {MAX_ANIMATION_NODES})", animation.node_count)))
+    }
+    Ok(())
+}
+
+impl AnimationFrames {
+    fn decode_uncompressed(animation: &ModelAnimationsAnimation) -> RinghopperResult<Self> {
+        let frame_count = animation.frame_count as usize;
+        let frame_size = FrameDataIterator::for_animation(animation).to_size();
+        let expected_size = frame_size.mul_overflow_checked(frame_count)?;
+        let data = &animation.frame_data.bytes;
+        if data.len() != expected_size {
+            return Err(Error::InvalidTagData(format!("model animation frame data size is wrong ({actual} actual != {expected_size} expected)", actual=data.len())))
+        }
+
+        let node_count = animation.node_count as usize;
+        let rotation_flags = node_flags(animation.node_rotation_flag_data);
+        let transform_flags = node_flags(animation.node_transform_flag_data);
+        let scale_flags = node_flags(animation.node_scale_flag_data);
+
+        let mut frames = AnimationFrames {
+            rotations: vec![Vec::with_capacity(frame_count); animated_node_count(animation.node_rotation_flag_data, node_count)],
+            translations: vec![Vec::with_capacity(frame_count); animated_node_count(animation.node_transform_flag_data, node_count)],
+            scales: vec![Vec::with_capacity(frame_count); animated_node_count(animation.node_scale_flag_data, node_count)]
+        };
+
+        // Tag data is big endian.
+        let mut reader = ByteReader { data, offset: 0 };
+        for _ in 0..frame_count {
+            let (mut r, mut t, mut s) = (0, 0, 0);
+            for node in 0..node_count {
+                let mask = 1u64 << node;
+                if rotation_flags & mask != 0 {
+                    let c: [i16; 4] = std::array::from_fn(|_| i16::from_be_bytes(reader.read()));
+                    frames.rotations[r].push(Quaternion {
+                        x: c[0] as f32 / UNCOMPRESSED_QUATERNION_SCALE,
+                        y: c[1] as f32 / UNCOMPRESSED_QUATERNION_SCALE,
+                        z: c[2] as f32 / UNCOMPRESSED_QUATERNION_SCALE,
+                        w: c[3] as f32 / UNCOMPRESSED_QUATERNION_SCALE
+                    });
+                    r += 1;
+                }
+                if transform_flags & mask != 0 {
+                    let c: [f32; 3] = std::array::from_fn(|_| f32::from_be_bytes(reader.read()));
+                    frames.translations[t].push(Vector3D { x: c[0], y: c[1], z: c[2] });
+                    t += 1;
+                }
+                if scale_flags & mask != 0 {
+                    frames.scales[s].push(f32::from_be_bytes(reader.read()));
+                    s += 1;
+                }
+            }
+        }
+
+        Ok(frames)
+    }
+
+    fn encode_uncompressed(&self, animation: &ModelAnimationsAnimation) -> Vec<u8> {
+        let frame_count = animation.frame_count as usize;
+        let node_count = animation.node_count as usize;
+        let rotation_flags = node_flags(animation.node_rotation_flag_data);
+        let transform_flags = node_flags(animation.node_transform_flag_data);
+        let scale_flags = node_flags(animation.node_scale_flag_data);
+
+        let mut data = Vec::new();
+        for frame in 0..frame_count {
+            let (mut r, mut t, mut s) = (0, 0, 0);
+            for node in 0..node_count {
+                let mask = 1u64 << node;
+                if rotation_flags & mask != 0 {
+                    let q = self.rotations[r][frame];
+                    for c in [q.x, q.y, q.z, q.w] {
+                        let c = (c.clamp(-1.0, 1.0) * UNCOMPRESSED_QUATERNION_SCALE).round() as i16;
+                        data.extend_from_slice(&c.to_be_bytes());
+                    }
+                    r += 1;
+                }
+                if transform_flags & mask != 0 {
+                    let v = self.translations[t][frame];
+                    for c in [v.x, v.y, v.z] {
+                        data.extend_from_slice(&c.to_be_bytes());
+                    }
+                    t += 1;
+                }
+                if scale_flags & mask != 0 {
+                    data.extend_from_slice(&self.scales[s][frame].to_be_bytes());
+                    s += 1;
+                }
+            }
+        }
+        data
+    }
+
+    fn decode_compressed(animation: &ModelAnimationsAnimation, data: &[u8]) -> RinghopperResult<Self> {
+        let frame_count = animation.frame_count as usize;
+        let node_count = animation.node_count as usize;
+        if frame_count == 0 {
+            return Err(Error::InvalidTagData("compressed model animation has no frames".to_owned()))
+        }
+
+        let header_size = COMPRESSED_HEADER_OFFSET_COUNT * 4;
+        if data.len() < header_size {
+            return Err(Error::InvalidTagData("compressed model animation data is too small for its header".to_owned()))
+        }
+        let offsets: [usize; COMPRESSED_HEADER_OFFSET_COUNT] = std::array::from_fn(|i| u32::from_le_bytes(data[i * 4..i * 4 + 4].try_into().unwrap()) as usize);
+
+        let rotation_count = animated_node_count(animation.node_rotation_flag_data, node_count);
+        let translation_count = animated_node_count(animation.node_transform_flag_data, node_count);
+        let scale_count = animated_node_count(animation.node_scale_flag_data, node_count);
+
+        let rotations = decode_channel(data, frame_count, rotation_count, [offsets[0], offsets[1], offsets[2], offsets[3]], 6, decompress_quaternion, nlerp)?;
+        let translations = decode_channel(data, frame_count, translation_count, [offsets[4], offsets[5], offsets[6], offsets[7]], 12, |b| {
+            let c: [f32; 3] = std::array::from_fn(|i| f32::from_le_bytes(b[i * 4..i * 4 + 4].try_into().unwrap()));
+            Vector3D { x: c[0], y: c[1], z: c[2] }
+        }, |a, b, t| *a + (*b - *a).scale(t))?;
+        let scale_values = offsets[10].add_overflow_checked(scale_count.mul_overflow_checked(4)?)?;
+        let scales = decode_channel(data, frame_count, scale_count, [offsets[8], offsets[9], offsets[10], scale_values], 4, |b| f32::from_le_bytes(b.try_into().unwrap()), |a, b, t| a + (b - a) * t)?;
+
+        Ok(AnimationFrames { rotations, translations, scales })
+    }
+
+    fn encode_compressed(&self) -> RinghopperResult<Vec<u8>> {
+        let mut data = vec![0u8; COMPRESSED_HEADER_OFFSET_COUNT * 4];
+        let mut offsets = Vec::with_capacity(COMPRESSED_HEADER_OFFSET_COUNT);
+
+        encode_channel(&mut data, &mut offsets, &self.rotations, |a, b, t, expected| nlerp(a, b, t).distance_squared(expected) <= ROTATION_TOLERANCE * ROTATION_TOLERANCE, |q| compress_quaternion(*q).to_vec())?;
+        encode_channel(&mut data, &mut offsets, &self.translations, |a, b, t, expected| (*a + (*b - *a).scale(t)).distance_squared(expected) <= TRANSLATION_TOLERANCE * TRANSLATION_TOLERANCE, |v| {
+            [v.x, v.y, v.z].iter().flat_map(|c| c.to_le_bytes()).collect()
+        })?;
+        encode_channel(&mut data, &mut offsets, &self.scales, |a, b, t, expected| (a + (b - a) * t - expected).abs() <= SCALE_TOLERANCE, |s| s.to_le_bytes().to_vec())?;
+
+        // Scale values immediately follow default scales, so there is no offset for them.
+        offsets.pop();
+
+        if data.len() > u32::MAX as usize {
+            return Err(Error::ArrayLimitExceeded)
+        }
+        for (i, offset) in offsets.into_iter().enumerate() {
+            data[i * 4..i * 4 + 4].copy_from_slice(&(offset as u32).to_le_bytes());
+        }
+        Ok(data)
+    }
+}
+
+pub(super) struct ByteReader<'a> {
+    pub(super) data: &'a [u8],
+    pub(super) offset: usize
+}
+
+impl ByteReader<'_> {
+    pub(super) fn read<const N: usize>(&mut self) -> [u8; N] {
+        let bytes = self.data[self.offset..self.offset + N].try_into().unwrap();
+        self.offset += N;
+        bytes
+    }
+}
+
+fn get_bytes(data: &[u8], offset: usize, length: usize) -> RinghopperResult<&[u8]> {
+    offset
+        .checked_add(length)
+        .and_then(|end| data.get(offset..end))
+        .ok_or_else(|| Error::InvalidTagData(format!("compressed model animation data at offset {offset} is out of bounds")))
+}
+
+/// Decode one channel (rotations, translations, or scales) of compressed data.
+///
+/// `offsets` are the offsets of the keyframe headers, keyframes, default values, and values.
+fn decode_channel<T: Copy>(
+    data: &[u8],
+    frame_count: usize,
+    node_count: usize,
+    [header_offset, keyframe_offset, default_offset, value_offset]: [usize; 4],
+    value_size: usize,
+    decode: impl Fn(&[u8]) -> T,
+    interpolate: impl Fn(&T, &T, f32) -> T
+) -> RinghopperResult<Vec<Vec<T>>> {
+    let mut nodes = Vec::with_capacity(node_count);
+    for node in 0..node_count {
+        let header = u32::from_le_bytes(get_bytes(data, header_offset + node * 4, 4)?.try_into().unwrap()) as usize;
+        let keyframe_count = header & MAX_KEYFRAME_COUNT;
+        let first_keyframe = header >> 12;
+
+        let mut keyframes: Vec<(usize, T)> = Vec::with_capacity(keyframe_count + 1);
+        keyframes.push((0, decode(get_bytes(data, default_offset + node * value_size, value_size)?)));
+        for k in first_keyframe..first_keyframe + keyframe_count {
+            let frame = u16::from_le_bytes(get_bytes(data, keyframe_offset + k * 2, 2)?.try_into().unwrap()) as usize;
+            if frame <= keyframes.last().unwrap().0 || frame >= frame_count {
+                return Err(Error::InvalidTagData(format!("compressed model animation has an invalid keyframe {frame}")))
+            }
+            keyframes.push((frame, decode(get_bytes(data, value_offset + k * value_size, value_size)?)));
+        }
+
+        let mut values = Vec::with_capacity(frame_count);
+        let mut next = 1;
+        for frame in 0..frame_count {
+            while next < keyframes.len() && keyframes[next].0 <= frame {
+                next += 1;
+            }
+            let (start_frame, start) = &keyframes[next - 1];
+            values.push(match keyframes.get(next) {
+                Some((end_frame, end)) => interpolate(start, end, (frame - start_frame) as f32 / (end_frame - start_frame) as f32),
+                None => *start
+            });
+        }
+        nodes.push(values);
+    }
+    Ok(nodes)
+}
+
+/// Encode one channel (rotations, translations, or scales) of compressed data, appending its offsets to `offsets`.
+///
+/// Keyframes are only placed where interpolating between keyframes would not be within tolerance.
+fn encode_channel<T>(
+    data: &mut Vec<u8>,
+    offsets: &mut Vec<usize>,
+    nodes: &[Vec<T>],
+    within_tolerance: impl Fn(&T, &T, f32, &T) -> bool,
+    encode: impl Fn(&T) -> Vec<u8>
+) -> RinghopperResult<()> {
+    let keyframes: Vec<Vec<usize>> = nodes.iter().map(|values| {
+        let mut keyframes = Vec::new();
+        let mut start = 0;
+        while start + 1 < values.len() {
+            let mut end = start + 1;
+            while end + 1 < values.len() && (start + 1..=end).all(|f| {
+                let t = (f - start) as f32 / (end + 1 - start) as f32;
+                within_tolerance(&values[start], &values[end + 1], t, &values[f])
+            }) {
+                end += 1;
+            }
+            keyframes.push(end);
+            start = end;
+        }
+        keyframes
+    }).collect();
+
+    let mut headers = Vec::with_capacity(keyframes.len());
+    let mut first_keyframe = 0usize;
+    for k in &keyframes {
+        if k.len() > MAX_KEYFRAME_COUNT || first_keyframe > MAX_KEYFRAME_INDEX {
+            return Err(Error::Other("animation has too many keyframes to compress".to_owned()))
+        }
+        headers.push((first_keyframe << 12 | k.len()) as u32);
+        first_keyframe += k.len();
+    }
+
+    offsets.push(data.len());
+    data.extend(headers.iter().flat_map(|h| h.to_le_bytes()));
+
+    offsets.push(data.len());
+    for frame in keyframes.iter().flatten() {
+        data.extend_from_slice(&(*frame as u16).to_le_bytes());
+    }
+    while !data.len().is_multiple_of(4) {
+        data.push(0);
+    }
+
+    offsets.push(data.len());
+    for values in nodes {
+        data.extend_from_slice(&encode(&values[0]));
+    }
+
+    offsets.push(data.len());
+    for (values, keyframes) in nodes.iter().zip(keyframes.iter()) {
+        for frame in keyframes {
+            data.extend_from_slice(&encode(&values[*frame]));
+        }
+    }
+    while !data.len().is_multiple_of(4) {
+        data.push(0);
+    }
+
+    Ok(())
+}
+
+/// Interpolate between two rotations along the shortest path.
+fn nlerp(a: &Quaternion, b: &Quaternion, t: f32) -> Quaternion {
+    let b = if a.dot(b) < 0.0 { -*b } else { *b };
+    (*a + (b - *a).scale(t)).normalize()
+}
+
+fn decompress_quaternion(bytes: &[u8]) -> Quaternion {
+    let words: [u64; 3] = std::array::from_fn(|i| u16::from_le_bytes([bytes[i * 2], bytes[i * 2 + 1]]) as u64);
+    let packed = (words[0] << 32) | (words[1] << 16) | words[2];
+    let component = |shift: u32| {
+        let value = ((packed >> shift) & 0xFFF) as i32;
+        let value = if value & 0x800 != 0 { value - 0xFFF } else { value };
+        value as f32 / COMPRESSED_QUATERNION_SCALE
+    };
+    Quaternion { x: component(36), y: component(24), z: component(12), w: component(0) }.normalize()
+}
+
+fn compress_quaternion(quaternion: Quaternion) -> [u8; 6] {
+    let component = |value: f32, shift: u32| {
+        let value = (value.clamp(-1.0, 1.0) * COMPRESSED_QUATERNION_SCALE).round() as i32;
+        let value = if value < 0 { value + 0xFFF } else { value };
+        ((value as u64) & 0xFFF) << shift
+    };
+    let q = quaternion.normalize();
+    let packed = component(q.x, 36) | component(q.y, 24) | component(q.z, 12) | component(q.w, 0);
+
+    let mut bytes = [0u8; 6];
+    bytes[0..2].copy_from_slice(&((packed >> 32) as u16).to_le_bytes());
+    bytes[2..4].copy_from_slice(&((packed >> 16) as u16).to_le_bytes());
+    bytes[4..6].copy_from_slice(&(packed as u16).to_le_bytes());
+    bytes
+}
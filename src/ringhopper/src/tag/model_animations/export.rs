@@ -0,0 +1,175 @@
+use definitions::{AnimationFrameInfoType, AnimationType, ModelAnimations, ModelAnimationsAnimation};
+use primitives::error::{Error, OverflowCheck, RinghopperResult};
+use primitives::primitive::{Quaternion, Vector, Vector3D};
+use crate::data::jma::{JMAAnimationSource, JMAKind, JMANode, JMANodeTransform, JMA};
+use crate::data::jms::JMS_UNITS_PER_WORLD_UNIT;
+use super::compile::{multiply_quaternions, z_rotation};
+use super::compression::{node_flags, ByteReader};
+use super::{decompress_animation, FrameDataIterator, MAX_ANIMATION_NODES};
+
+/// Frame rate of all animations.
+const ANIMATION_FRAME_RATE: u32 = 30;
+
+/// Export all animations of a model_animations tag as JMA-family files.
+///
+/// Frames are reconstructed from the default data and frame data, and root node movement from frame info is applied
+/// back onto the root node. Compressed animations are decompressed first. Overlay animations are exported as they are
+/// stored, relative to the pose they are applied on top of.
+pub fn export_animations_to_jma(tag: &ModelAnimations) -> RinghopperResult<Vec<JMAAnimationSource>> {
+    let nodes: Vec<JMANode> = tag.nodes.items.iter().map(|n| JMANode {
+        name: n.name.to_string(),
+        first_child: n.first_child_node_index.map(|i| i as usize),
+        sibling: n.next_sibling_node_index.map(|i| i as usize)
+    }).collect();
+
+    let node_count = nodes.len();
+    for node in &nodes {
+        for index in [node.first_child, node.sibling].into_iter().flatten() {
+            if index >= node_count {
+                return Err(Error::InvalidTagData(format!("model animations node index {index} is out-of-bounds ({node_count} total)")))
+            }
+        }
+    }
+
+    tag.animations.items.iter().map(|a| export_animation(a, &nodes)).collect()
+}
+
+/// Get the kind of JMA-family file that creates an animation of this type.
+fn jma_kind(animation: &ModelAnimationsAnimation) -> JMAKind {
+    match (animation._type, animation.frame_info_type) {
+        (AnimationType::Overlay, _) => JMAKind::JMO,
+        (AnimationType::Replacement, _) => JMAKind::JMR,
+        (_, AnimationFrameInfoType::DxDy) => JMAKind::JMA,
+        (_, AnimationFrameInfoType::DxDyDyaw) => JMAKind::JMT,
+        (_, AnimationFrameInfoType::DxDyDzDyaw) => JMAKind::JMZ,
+        (_, AnimationFrameInfoType::None) if animation.flags.world_relative => JMAKind::JMW,
+        (_, AnimationFrameInfoType::None) => JMAKind::JMM
+    }
+}
+
+fn export_animation(animation: &ModelAnimationsAnimation, nodes: &[JMANode]) -> RinghopperResult<JMAAnimationSource> {
+    let name = animation.name.to_string();
+    let mut animation = animation.clone();
+    decompress_animation(&mut animation)?;
+
+    let node_count = animation.node_count as usize;
+    let frame_count = animation.frame_count as usize;
+    if node_count > MAX_ANIMATION_NODES {
+        return Err(Error::InvalidTagData(format!("animation `{name}` has {node_count} nodes (more than {MAX_ANIMATION_NODES})")))
+    }
+    if node_count != nodes.len() {
+        return Err(Error::InvalidTagData(format!("animation `{name}` has {node_count} node(s), but the tag has {}", nodes.len())))
+    }
+
+    let frame_size = FrameDataIterator::for_animation(&animation).to_size();
+    let expected_frame_data = frame_size.mul_overflow_checked(frame_count)?;
+    if animation.frame_data.bytes.len() != expected_frame_data {
+        return Err(Error::InvalidTagData(format!("animation `{name}` frame data size is wrong ({actual} actual != {expected_frame_data} expected)", actual=animation.frame_data.bytes.len())))
+    }
+    let default_size = FrameDataIterator::for_animation_inverted(&animation).to_size();
+    if animation.default_data.bytes.len() != default_size {
+        return Err(Error::InvalidTagData(format!("animation `{name}` default data size is wrong ({actual} actual != {default_size} expected)", actual=animation.default_data.bytes.len())))
+    }
+
+    let rotation_flags = node_flags(animation.node_rotation_flag_data);
+    let transform_flags = node_flags(animation.node_transform_flag_data);
+    let scale_flags = node_flags(animation.node_scale_flag_data);
+
+    // Values that do not change are stored once in the default data.
+    let mut defaults = vec![JMANodeTransform { rotation: Quaternion::one(), translation: Vector3D::zero(), scale: 1.0 }; node_count];
+    read_node_transforms(&mut ByteReader { data: &animation.default_data.bytes, offset: 0 }, &mut defaults, !rotation_flags, !transform_flags, !scale_flags);
+
+    let mut reader = ByteReader { data: &animation.frame_data.bytes, offset: 0 };
+    let mut frames = Vec::with_capacity(frame_count);
+    for _ in 0..frame_count {
+        let mut frame = defaults.clone();
+        read_node_transforms(&mut reader, &mut frame, rotation_flags, transform_flags, scale_flags);
+        frames.push(frame);
+    }
+
+    apply_frame_info(&animation, &name, &mut frames)?;
+
+    for frame in &mut frames {
+        for node in frame {
+            node.rotation = node.rotation.normalize();
+            node.translation = node.translation.scale(JMS_UNITS_PER_WORLD_UNIT);
+        }
+    }
+
+    Ok(JMAAnimationSource {
+        name,
+        kind: jma_kind(&animation),
+        jma: JMA {
+            frame_rate: ANIMATION_FRAME_RATE,
+            node_list_checksum: animation.node_list_checksum,
+            nodes: nodes.to_vec(),
+            frames
+        }
+    })
+}
+
+/// Read the rotations, translations, and scales of nodes whose flags are set.
+///
+/// Tag data is big endian.
+fn read_node_transforms(reader: &mut ByteReader, nodes: &mut [JMANodeTransform], rotation_flags: u64, transform_flags: u64, scale_flags: u64) {
+    let uncompress = |c: i16| c as f32 / i16::MAX as f32;
+    for (index, node) in nodes.iter_mut().enumerate() {
+        let mask = 1u64 << index;
+        if rotation_flags & mask != 0 {
+            let c: [i16; 4] = std::array::from_fn(|_| i16::from_be_bytes(reader.read()));
+            node.rotation = Quaternion { x: uncompress(c[0]), y: uncompress(c[1]), z: uncompress(c[2]), w: uncompress(c[3]) };
+        }
+        if transform_flags & mask != 0 {
+            let c: [f32; 3] = std::array::from_fn(|_| f32::from_be_bytes(reader.read()));
+            node.translation = Vector3D { x: c[0], y: c[1], z: c[2] };
+        }
+        if scale_flags & mask != 0 {
+            node.scale = f32::from_be_bytes(reader.read());
+        }
+    }
+}
+
+/// Move the root node by the movement in the frame info.
+///
+/// This reverses what the animation compiler does: frame info is the movement from each frame to the next frame.
+fn apply_frame_info(animation: &ModelAnimationsAnimation, name: &str, frames: &mut [Vec<JMANodeTransform>]) -> RinghopperResult<()> {
+    let (use_z, use_yaw) = match animation.frame_info_type {
+        AnimationFrameInfoType::None => return Ok(()),
+        AnimationFrameInfoType::DxDy => (false, false),
+        AnimationFrameInfoType::DxDyDyaw => (false, true),
+        AnimationFrameInfoType::DxDyDzDyaw => (true, true)
+    };
+
+    let values_per_frame = 2 + use_z as usize + use_yaw as usize;
+    let expected_size = (values_per_frame * 4).mul_overflow_checked(frames.len())?;
+    let data = &animation.frame_info.bytes;
+    if data.len() != expected_size {
+        return Err(Error::InvalidTagData(format!("animation `{name}` has {} byte(s) for frame info when it should be {expected_size}", data.len())))
+    }
+    if frames.first().is_none_or(|f| f.is_empty()) {
+        return Ok(())
+    }
+
+    let mut reader = ByteReader { data, offset: 0 };
+    let mut offset = Vector3D::zero();
+    let mut turned = 0.0f32;
+    for frame in frames.iter_mut() {
+        let root = &mut frame[0];
+        root.translation += offset;
+        if use_yaw {
+            root.rotation = multiply_quaternions(z_rotation(turned), root.rotation.normalize());
+        }
+
+        let mut read = || f32::from_be_bytes(reader.read());
+        offset.x += read();
+        offset.y += read();
+        if use_z {
+            offset.z += read();
+        }
+        if use_yaw {
+            turned += read();
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,274 @@
+use definitions::{AnimationFrameInfoType, AnimationType, ModelAnimations, ModelAnimationsAnimation};
+use primitives::byteorder::{BigEndian, LittleEndian};
+use primitives::primitive::{Quaternion, TagPath, Vector, Vector3D};
+use crate::data::jma::*;
+use crate::tag::bludgeon::{bludgeon_tag, BludgeonOptions, BludgeonResult};
+use super::*;
+
+fn transform(rotation: Quaternion, translation: Vector3D) -> JMANodeTransform {
+    JMANodeTransform { rotation, translation, scale: 1.0 }
+}
+
+fn test_jma() -> JMA {
+    let identity = Quaternion { x: 0.0, y: 0.0, z: 0.0, w: 1.0 };
+    let turned = Quaternion { x: 0.0, y: 0.0, z: std::f32::consts::FRAC_1_SQRT_2, w: std::f32::consts::FRAC_1_SQRT_2 };
+    let bone = Vector3D { x: 10.0, y: 0.0, z: 0.0 };
+
+    JMA {
+        frame_rate: 30,
+        node_list_checksum: 1234,
+        nodes: vec![
+            JMANode { name: "frame root".to_owned(), first_child: Some(1), sibling: None },
+            JMANode { name: "bone".to_owned(), first_child: None, sibling: None }
+        ],
+        frames: vec![
+            vec![transform(identity, Vector3D::default()), transform(identity, bone)],
+            vec![transform(identity, Vector3D { x: 50.0, y: 0.0, z: 0.0 }), transform(turned, bone)],
+            vec![transform(identity, Vector3D { x: 100.0, y: 0.0, z: 0.0 }), transform(identity, bone)]
+        ]
+    }
+}
+
+#[test]
+fn compile_animations_from_jma() {
+    let sources = vec![
+        JMAAnimationSource { name: "stand idle".to_owned(), kind: JMAKind::JMM, jma: test_jma() },
+        JMAAnimationSource { name: "stand move-front".to_owned(), kind: JMAKind::JMA, jma: test_jma() }
+    ];
+
+    let mut tag = ModelAnimations::default();
+    compile_animations(&mut tag, &sources).unwrap();
+
+    assert_eq!(2, tag.nodes.items.len());
+    assert_eq!(Some(0), tag.nodes.items[1].parent_node_index);
+    assert_eq!(2, tag.animations.items.len());
+
+    // Only the bone rotates; the root moves, but not in the .jma since movement goes into frame info.
+    let idle = &tag.animations.items[0];
+    assert_eq!("stand idle", idle.name.as_str());
+    assert_eq!(AnimationType::Base, idle._type);
+    assert_eq!(AnimationFrameInfoType::None, idle.frame_info_type);
+    assert_eq!(3, idle.frame_count);
+    assert_eq!([0b10, 0], idle.node_rotation_flag_data);
+    assert_eq!([0b01, 0], idle.node_transform_flag_data);
+    assert_eq!([0, 0], idle.node_scale_flag_data);
+    assert_eq!(8 + 12, idle.frame_size);
+
+    let moving = &tag.animations.items[1];
+    assert_eq!(AnimationFrameInfoType::DxDy, moving.frame_info_type);
+    assert_eq!([0b00, 0], moving.node_transform_flag_data);
+    assert_eq!(8, moving.frame_size);
+    assert_eq!(3 * 8, moving.frame_info.bytes.len());
+    assert_eq!(0.5, f32::from_be_bytes(moving.frame_info.bytes[0..4].try_into().unwrap()));
+    assert_eq!(0.0, f32::from_be_bytes(moving.frame_info.bytes[16..20].try_into().unwrap()));
+
+    // Frame data, default data, and frame info must be the sizes the rest of ringhopper expects.
+    for animation in &mut tag.animations.items {
+        assert_eq!(animation.frame_data.bytes.len(), FrameDataIterator::for_animation(animation).to_size() * animation.frame_count as usize);
+        flip_endianness_for_model_animations_animation::<BigEndian, LittleEndian>(animation).unwrap();
+    }
+}
+
+#[test]
+fn compile_animations_merges_into_existing_tag() {
+    let mut tag = ModelAnimations::default();
+    compile_animations(&mut tag, &[JMAAnimationSource { name: "stand idle".to_owned(), kind: JMAKind::JMM, jma: test_jma() }]).unwrap();
+    tag.animations.items[0].next_animation = Some(0);
+
+    let mut jma = test_jma();
+    jma.frames.truncate(1);
+    let sources = vec![
+        JMAAnimationSource { name: "stand idle".to_owned(), kind: JMAKind::JMO, jma: jma.clone() },
+        JMAAnimationSource { name: "stand fire".to_owned(), kind: JMAKind::JMR, jma }
+    ];
+    compile_animations(&mut tag, &sources).unwrap();
+
+    assert_eq!(2, tag.animations.items.len());
+    let idle = &tag.animations.items[0];
+    assert_eq!(AnimationType::Overlay, idle._type);
+    assert_eq!(1, idle.frame_count);
+    assert_eq!(Some(0), idle.next_animation);
+    assert_eq!(AnimationType::Replacement, tag.animations.items[1]._type);
+
+    let mut other = test_jma();
+    other.nodes[1].name = "other".to_owned();
+    let result = compile_animations(&mut tag, &[JMAAnimationSource { name: "other".to_owned(), kind: JMAKind::JMA, jma: other }]);
+    assert!(result.is_err());
+}
+
+#[test]
+fn compress_and_decompress_animations() {
+    let mut jma = test_jma();
+    for frame in 3..40 {
+        let angle = frame as f32 * 0.1;
+        let rotation = Quaternion { x: 0.0, y: 0.0, z: (angle * 0.5).sin(), w: (angle * 0.5).cos() };
+        jma.frames.push(vec![
+            transform(rotation, Vector3D { x: frame as f32, y: 0.0, z: 0.0 }),
+            JMANodeTransform { scale: 1.0 + frame as f32 * 0.01, ..transform(rotation, Vector3D { x: 10.0, y: 0.0, z: 0.0 }) }
+        ]);
+    }
+
+    let mut tag = ModelAnimations::default();
+    compile_animations(&mut tag, &[JMAAnimationSource { name: "stand idle".to_owned(), kind: JMAKind::JMM, jma }]).unwrap();
+
+    let original = tag.animations.items[0].clone();
+    let mut animation = original.clone();
+    compress_animation(&mut animation).unwrap();
+    assert!(animation.flags.compressed_data);
+    assert_eq!(original.frame_data.bytes.len(), animation.offset_to_compressed_data as usize);
+    assert_eq!(original.frame_data.bytes, animation.frame_data.bytes[..original.frame_data.bytes.len()]);
+
+    // The uncompressed data is kept exactly as it was if it is there.
+    let mut with_uncompressed = animation.clone();
+    decompress_animation(&mut with_uncompressed).unwrap();
+    assert!(!with_uncompressed.flags.compressed_data);
+    assert_eq!(original.frame_data.bytes, with_uncompressed.frame_data.bytes);
+
+    // Compressed data can be decoded without the uncompressed data, like in extracted tags.
+    animation.frame_data.bytes.drain(..original.frame_data.bytes.len());
+    animation.offset_to_compressed_data = 0;
+    decompress_animation(&mut animation).unwrap();
+    assert!(!animation.flags.compressed_data);
+    assert_eq!(0, animation.offset_to_compressed_data);
+    assert_eq!(original.frame_data.bytes.len(), animation.frame_data.bytes.len());
+
+    // Rotations lose some precision, but translations and scales should be nearly identical.
+    let read_f32 = |data: &[u8], at: usize| f32::from_be_bytes(data[at..at + 4].try_into().unwrap());
+    let read_i16 = |data: &[u8], at: usize| i16::from_be_bytes(data[at..at + 2].try_into().unwrap());
+    let frame_size = original.frame_size as usize;
+    assert_eq!(8 + 12 + 8 + 4, frame_size);
+    for frame in 0..original.frame_count as usize {
+        let at = frame * frame_size;
+
+        // root rotation + root translation + bone rotation + bone scale
+        for c in 0..4 {
+            let offset = at + c * 2;
+            assert!((read_i16(&original.frame_data.bytes, offset) - read_i16(&animation.frame_data.bytes, offset)).abs() <= 64, "frame {frame}");
+        }
+        for offset in [at + 8, at + 12, at + 16, at + 28] {
+            assert!((read_f32(&original.frame_data.bytes, offset) - read_f32(&animation.frame_data.bytes, offset)).abs() < 0.001, "frame {frame}");
+        }
+    }
+
+    // Decompressing an uncompressed animation does nothing.
+    let mut uncompressed = original.clone();
+    decompress_animation(&mut uncompressed).unwrap();
+    assert_eq!(original.frame_data.bytes, uncompressed.frame_data.bytes);
+}
+
+#[test]
+fn export_animations_to_jma_and_back() {
+    let mut turning = test_jma();
+    for (index, frame) in turning.frames.iter_mut().enumerate() {
+        let angle = index as f32 * 0.25;
+        frame[0].rotation = Quaternion { x: 0.0, y: 0.0, z: (angle * 0.5).sin(), w: (angle * 0.5).cos() };
+    }
+
+    let sources = vec![
+        JMAAnimationSource { name: "stand idle".to_owned(), kind: JMAKind::JMM, jma: test_jma() },
+        JMAAnimationSource { name: "stand move-front".to_owned(), kind: JMAKind::JMA, jma: test_jma() },
+        JMAAnimationSource { name: "stand turn-left".to_owned(), kind: JMAKind::JMT, jma: turning.clone() },
+        JMAAnimationSource { name: "stand fire".to_owned(), kind: JMAKind::JMW, jma: test_jma() }
+    ];
+
+    let mut tag = ModelAnimations::default();
+    compile_animations(&mut tag, &sources).unwrap();
+
+    // Compressed animations are exported the same way.
+    compress_animation(&mut tag.animations.items[1]).unwrap();
+
+    let exported = export_animations_to_jma(&tag).unwrap();
+    let kinds: Vec<JMAKind> = exported.iter().map(|s| s.kind).collect();
+    assert_eq!(vec![JMAKind::JMM, JMAKind::JMA, JMAKind::JMT, JMAKind::JMW], kinds);
+
+    for (source, exported) in sources.iter().zip(exported.iter()) {
+        assert_eq!(source.name, exported.name);
+        assert_eq!(source.jma.nodes, exported.jma.nodes);
+        assert_eq!(source.jma.node_list_checksum, exported.jma.node_list_checksum);
+        assert_eq!(source.jma.frames.len(), exported.jma.frames.len());
+
+        for (expected, actual) in source.jma.frames.iter().flatten().zip(exported.jma.frames.iter().flatten()) {
+            let rotation_matches = expected.rotation.distance_squared(&actual.rotation) < 0.00001
+                || expected.rotation.distance_squared(&-actual.rotation) < 0.00001;
+            assert!(rotation_matches, "{}: {expected:?} != {actual:?}", source.name);
+            assert!(expected.translation.distance_squared(&actual.translation) < 0.001, "{}: {expected:?} != {actual:?}", source.name);
+            assert_eq!(expected.scale, actual.scale);
+        }
+    }
+}
+
+/// Compressed data for one node over three frames, written out by hand rather than by the compressor.
+///
+/// The node rotates from identity to 180 degrees about Z and translates from the origin to (10, 20, 30), each with a
+/// single keyframe on the last frame.
+fn hand_compressed_animation() -> ModelAnimationsAnimation {
+    let mut data = Vec::new();
+    for offset in [44u32, 48, 52, 58, 64, 68, 72, 84, 96, 96, 96] {
+        data.extend_from_slice(&offset.to_le_bytes());
+    }
+
+    data.extend_from_slice(&1u32.to_le_bytes()); // rotation keyframe header: 1 keyframe starting at 0
+    data.extend_from_slice(&[2, 0, 0, 0]); // rotation keyframe on frame 2 (+ padding)
+    data.extend_from_slice(&[0x00, 0x00, 0x00, 0x00, 0xFF, 0x07]); // default rotation: w = 2047
+    data.extend_from_slice(&[0x00, 0x00, 0x7F, 0x00, 0x00, 0xF0]); // rotation value: z = 2047
+
+    data.extend_from_slice(&1u32.to_le_bytes()); // translation keyframe header: 1 keyframe starting at 0
+    data.extend_from_slice(&[2, 0, 0, 0]); // translation keyframe on frame 2 (+ padding)
+    for c in [0.0f32, 0.0, 0.0, 10.0, 20.0, 30.0] {
+        data.extend_from_slice(&c.to_le_bytes()); // default translation, then translation value
+    }
+    assert_eq!(96, data.len());
+
+    let mut animation = ModelAnimationsAnimation {
+        frame_count: 3,
+        node_count: 1,
+        node_rotation_flag_data: [1, 0],
+        node_transform_flag_data: [1, 0],
+        frame_size: 8 + 12,
+        ..Default::default()
+    };
+    animation.flags.compressed_data = true;
+    animation.frame_data.bytes = data;
+    animation
+}
+
+#[test]
+fn decompress_hand_compressed_animation() {
+    let mut animation = hand_compressed_animation();
+    decompress_animation(&mut animation).unwrap();
+    assert!(!animation.flags.compressed_data);
+
+    let half = (std::f32::consts::FRAC_1_SQRT_2 * i16::MAX as f32).round() as i16;
+    let frames: [([i16; 4], [f32; 3]); 3] = [
+        ([0, 0, 0, i16::MAX], [0.0, 0.0, 0.0]),
+        ([0, 0, half, half], [5.0, 10.0, 15.0]),
+        ([0, 0, i16::MAX, 0], [10.0, 20.0, 30.0])
+    ];
+
+    let mut expected = Vec::new();
+    for (rotation, translation) in frames {
+        rotation.iter().for_each(|c| expected.extend_from_slice(&c.to_be_bytes()));
+        translation.iter().for_each(|c| expected.extend_from_slice(&c.to_be_bytes()));
+    }
+    assert_eq!(expected, animation.frame_data.bytes);
+}
+
+#[test]
+fn bludgeon_decompresses_animations_only_when_asked() {
+    let mut broken = hand_compressed_animation();
+    broken.frame_data.bytes.truncate(60);
+
+    let mut tag = ModelAnimations::default();
+    tag.animations.items.push(hand_compressed_animation());
+    tag.animations.items.push(broken);
+    let path = TagPath::from_path("characters\\test\\test.model_animations").unwrap();
+
+    assert!(matches!(bludgeon_tag(&mut tag, &path, &BludgeonOptions::default()), BludgeonResult::Done));
+    assert!(tag.animations.items.iter().all(|a| a.flags.compressed_data));
+
+    // The broken animation is left alone without failing the whole tag.
+    let options = BludgeonOptions { decompress_animations: true };
+    assert!(matches!(bludgeon_tag(&mut tag, &path, &options), BludgeonResult::Done));
+    assert!(!tag.animations.items[0].flags.compressed_data);
+    assert!(tag.animations.items[1].flags.compressed_data);
+}
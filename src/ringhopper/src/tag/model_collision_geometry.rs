@@ -0,0 +1,72 @@
+use primitives::primitive::{Quaternion, Vector, Vector3D};
+use crate::data::jms::{JMSNode, JMS_UNITS_PER_WORLD_UNIT};
+use crate::tag::model::cross;
+use crate::tag::model_animations::multiply_quaternions;
+
+#[cfg(test)]
+mod test;
+mod compile;
+mod export;
+
+pub use compile::*;
+pub use export::*;
+
+/// Position and rotation of a node relative to the model, in world units.
+#[derive(Copy, Clone)]
+struct NodeTransform {
+    rotation: Quaternion,
+    translation: Vector3D
+}
+
+impl NodeTransform {
+    /// Transform a point relative to the node into a point relative to the model.
+    fn to_model(self, point: Vector3D) -> Vector3D {
+        rotate(self.rotation, point) + self.translation
+    }
+
+    /// Transform a point relative to the model into a point relative to the node.
+    fn to_node(self, point: Vector3D) -> Vector3D {
+        rotate(conjugate(self.rotation), point - self.translation)
+    }
+
+    /// Rotate a direction relative to the node into a direction relative to the model.
+    fn rotate_to_model(self, direction: Vector3D) -> Vector3D {
+        rotate(self.rotation, direction)
+    }
+}
+
+/// Get the transform of each node in its default pose.
+///
+/// Nodes that cannot be reached from a root node are left at the origin.
+fn node_transforms(nodes: &[JMSNode], parents: &[Option<usize>]) -> Vec<NodeTransform> {
+    let identity = NodeTransform { rotation: Quaternion { x: 0.0, y: 0.0, z: 0.0, w: 1.0 }, translation: Vector3D::zero() };
+    let mut transforms = vec![identity; nodes.len()];
+
+    let mut queue: Vec<usize> = (0..nodes.len()).filter(|n| parents[*n].is_none()).collect();
+    while let Some(node) = queue.pop() {
+        let parent = parents[node].map(|p| transforms[p]).unwrap_or(identity);
+        let jms_node = &nodes[node];
+        transforms[node] = NodeTransform {
+            rotation: multiply_quaternions(parent.rotation, jms_node.rotation.normalize()).normalize(),
+            translation: parent.to_model(jms_node.translation.scale(1.0 / JMS_UNITS_PER_WORLD_UNIT))
+        };
+
+        let mut child = jms_node.first_child;
+        while let Some(c) = child {
+            queue.push(c);
+            child = nodes[c].sibling;
+        }
+    }
+
+    transforms
+}
+
+fn conjugate(q: Quaternion) -> Quaternion {
+    Quaternion { x: -q.x, y: -q.y, z: -q.z, w: q.w }
+}
+
+fn rotate(q: Quaternion, v: Vector3D) -> Vector3D {
+    let u = Vector3D { x: q.x, y: q.y, z: q.z };
+    let t = cross(u, v).scale(2.0);
+    v + t.scale(q.w) + cross(u, t)
+}
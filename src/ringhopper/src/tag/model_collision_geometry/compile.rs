@@ -0,0 +1,222 @@
+use std::collections::{BTreeMap, BTreeSet};
+use definitions::{ModelCollisionGeometry, ModelCollisionGeometryBSP, ModelCollisionGeometryBSPSurfaceFlags, ModelCollisionGeometryNode, ModelCollisionGeometryPermutation, ModelCollisionGeometrySphere};
+use primitives::error::{Error, RinghopperResult};
+use primitives::primitive::{Reflexive, Vector};
+use crate::data::jms::{JMSModelSource, ModelLOD, JMS, JMS_UNITS_PER_WORLD_UNIT};
+use crate::tag::collision_bsp::{compile_collision_bsp, is_degenerate_triangle, jms_position, CollisionTriangle};
+use crate::tag::model::name_to_string32;
+use crate::tag::result::{Diagnostic, TagResult};
+use super::node_transforms;
+
+/// Name that markers must start with to become pathfinding spheres.
+pub const PATHFINDING_SPHERE_MARKER: &str = "pathfinding sphere";
+
+/// Compile JMS files into a model_collision_geometry tag.
+///
+/// Each JMS is a permutation. Every triangle must be attached to exactly one node, and every node can only have
+/// triangles in one region. Markers named `pathfinding sphere` become pathfinding spheres.
+///
+/// Nodes, regions, permutations, materials, and pathfinding spheres are replaced. Materials and regions that already
+/// exist in the tag keep their settings (such as damage multipliers).
+///
+/// Problems with the geometry, such as open edges, are returned as errors in the [`TagResult`]. The tag should not be
+/// saved if there are any errors.
+pub fn compile_model_collision_geometry(tag: &mut ModelCollisionGeometry, sources: &[JMSModelSource]) -> RinghopperResult<TagResult> {
+    let first = match sources.first() {
+        Some(n) => &n.jms,
+        None => return Err(Error::Other("no JMS files to compile".to_owned()))
+    };
+    if first.nodes.is_empty() {
+        return Err(Error::Other("JMS has no nodes".to_owned()))
+    }
+    if first.nodes.len() > u16::MAX as usize {
+        return Err(Error::Other(format!("too many nodes ({} > {})", first.nodes.len(), u16::MAX)))
+    }
+
+    for source in sources {
+        if source.lod != ModelLOD::SuperHigh {
+            return Err(Error::Other(format!("permutation `{}` has a level of detail ({}), but collision geometry does not use them", source.permutation, source.lod.as_str())))
+        }
+        source.jms.check_indices()?;
+        let same_nodes = source.jms.nodes.len() == first.nodes.len()
+            && source.jms.nodes.iter().zip(first.nodes.iter()).all(|(a, b)| a.name == b.name);
+        if !same_nodes {
+            return Err(Error::Other(format!("permutation `{}` has a different node list than the other JMS files", source.permutation)))
+        }
+    }
+
+    let mut result = TagResult::default();
+    let parents = first.node_parents()?;
+    let transforms = node_transforms(&first.nodes, &parents);
+
+    // Sort each triangle into a node and find each node's region.
+    let mut node_regions: Vec<Option<&str>> = vec![None; first.nodes.len()];
+    let mut node_triangles: Vec<Vec<Vec<usize>>> = vec![vec![Vec::new(); first.nodes.len()]; sources.len()];
+    for (source_index, source) in sources.iter().enumerate() {
+        let jms = &source.jms;
+        for (index, triangle) in jms.triangles.iter().enumerate() {
+            let Some(node) = triangle_node(jms, index) else {
+                result.errors.push(Diagnostic::new("MCG001", format!("Triangle #{index} of permutation `{}` is attached to more than one node", source.permutation)));
+                continue
+            };
+
+            let region = jms.regions[triangle.region].as_str();
+            match node_regions[node] {
+                None => node_regions[node] = Some(region),
+                Some(r) if r != region => {
+                    result.errors.push(Diagnostic::new("MCG002", format!("Node `{}` has triangles in more than one region (`{r}` and `{region}`)", first.nodes[node].name)));
+                    continue
+                },
+                Some(_) => ()
+            }
+
+            node_triangles[source_index][node].push(index);
+        }
+    }
+
+    // Regions and permutations are sorted by name.
+    let mut regions: BTreeMap<&str, BTreeSet<usize>> = BTreeMap::new();
+    for (source_index, nodes) in node_triangles.iter().enumerate() {
+        for (node, triangles) in nodes.iter().enumerate() {
+            if let (Some(region), false) = (node_regions[node], triangles.is_empty()) {
+                regions.entry(region).or_default().insert(source_index);
+            }
+        }
+    }
+    let region_permutations: Vec<(&str, Vec<usize>)> = regions
+        .into_iter()
+        .map(|(region, permutations)| {
+            let mut permutations: Vec<usize> = permutations.into_iter().collect();
+            permutations.sort_by(|a, b| sources[*a].permutation.cmp(&sources[*b].permutation));
+            (region, permutations)
+        })
+        .collect();
+
+    // Materials are in the order they are first used.
+    let mut material_names: Vec<&str> = Vec::new();
+    let mut nodes: Vec<ModelCollisionGeometryNode> = Vec::with_capacity(first.nodes.len());
+    for (node_index, node) in first.nodes.iter().enumerate() {
+        let region_index = node_regions[node_index].and_then(|r| region_permutations.iter().position(|p| p.0 == r));
+        let mut bsps = Vec::new();
+
+        if let Some(region_index) = region_index {
+            for source_index in &region_permutations[region_index].1 {
+                let source = &sources[*source_index];
+                let jms = &source.jms;
+                let mut triangles = Vec::new();
+                for &triangle in &node_triangles[*source_index][node_index] {
+                    let jms_triangle = &jms.triangles[triangle];
+                    let material = &jms.materials[jms_triangle.material];
+                    let properties = material.properties();
+                    let material_index = match material_names.iter().position(|n| *n == material.shader_name()) {
+                        Some(n) => n,
+                        None => {
+                            material_names.push(material.shader_name());
+                            material_names.len() - 1
+                        }
+                    };
+
+                    triangles.push(CollisionTriangle {
+                        vertices: jms_triangle.vertices.map(|v| transforms[node_index].to_node(jms.vertices[v].position.scale(1.0 / JMS_UNITS_PER_WORLD_UNIT))),
+                        material: Some(u16::try_from(material_index).map_err(|_| Error::ArrayLimitExceeded)?),
+                        flags: ModelCollisionGeometryBSPSurfaceFlags {
+                            two_sided: properties.two_sided || properties.transparent,
+                            climbable: properties.ladder,
+                            breakable: properties.breakable,
+                            ..Default::default()
+                        }
+                    });
+                }
+
+                bsps.push(compile_node_bsp(&triangles, jms, &node_triangles[*source_index][node_index], &source.permutation, &node.name, &mut result));
+            }
+        }
+
+        nodes.push(ModelCollisionGeometryNode {
+            name: name_to_string32("node", &node.name)?,
+            region: region_index.map(|r| r as u16),
+            parent_node: parents[node_index].map(|n| n as u16),
+            next_sibling_node: node.sibling.map(|n| n as u16),
+            first_child_node: node.first_child.map(|n| n as u16),
+            bsps: Reflexive { items: bsps }
+        });
+    }
+
+    let mut materials = Vec::with_capacity(material_names.len());
+    for name in material_names {
+        let mut material = tag.materials.items.iter().find(|m| m.name.as_str() == name).cloned().unwrap_or_default();
+        material.name = name_to_string32("material", name)?;
+        materials.push(material);
+    }
+
+    let mut compiled_regions = Vec::with_capacity(region_permutations.len());
+    for (name, permutations) in &region_permutations {
+        let mut region = tag.regions.items.iter().find(|r| r.name.as_str() == *name).cloned().unwrap_or_default();
+        region.name = name_to_string32("region", name)?;
+        region.permutations.items = permutations
+            .iter()
+            .map(|p| name_to_string32("permutation", &sources[*p].permutation).map(|name| ModelCollisionGeometryPermutation { name }))
+            .collect::<RinghopperResult<Vec<_>>>()?;
+        compiled_regions.push(region);
+    }
+
+    let mut pathfinding_spheres: Vec<ModelCollisionGeometrySphere> = Vec::new();
+    for source in sources {
+        for marker in &source.jms.markers {
+            if !marker.name.to_ascii_lowercase().starts_with(PATHFINDING_SPHERE_MARKER) {
+                continue
+            }
+            let sphere = ModelCollisionGeometrySphere {
+                node: Some(marker.node as u16),
+                center: marker.translation.scale(1.0 / JMS_UNITS_PER_WORLD_UNIT),
+                radius: marker.radius / JMS_UNITS_PER_WORLD_UNIT
+            };
+            let duplicate = pathfinding_spheres.iter().any(|s| s.node == sphere.node && s.center == sphere.center && s.radius == sphere.radius);
+            if !duplicate {
+                pathfinding_spheres.push(sphere);
+            }
+        }
+    }
+
+    tag.materials.items = materials;
+    tag.regions.items = compiled_regions;
+    tag.nodes.items = nodes;
+    tag.pathfinding_spheres.items = pathfinding_spheres;
+
+    Ok(result)
+}
+
+/// Get the node a triangle is attached to, or `None` if it is weighted to more than one node.
+fn triangle_node(jms: &JMS, triangle: usize) -> Option<usize> {
+    let vertices = jms.triangles[triangle].vertices.map(|v| &jms.vertices[v]);
+    let node = vertices[0].node0;
+    let single_node = vertices
+        .iter()
+        .all(|v| v.node0 == node && (v.node1.is_none() || v.node1 == Some(node) || v.node1_weight <= 0.0));
+    single_node.then_some(node)
+}
+
+/// Compile a node's triangles for a permutation, reporting problems with the permutation and node name.
+fn compile_node_bsp(triangles: &[CollisionTriangle], jms: &JMS, jms_triangles: &[usize], permutation: &str, node: &str, result: &mut TagResult) -> ModelCollisionGeometryBSP {
+    if triangles.is_empty() {
+        return ModelCollisionGeometryBSP::default()
+    }
+
+    let mut bsp_result = TagResult::default();
+    for (triangle, jms_triangle) in triangles.iter().zip(jms_triangles) {
+        if is_degenerate_triangle(&triangle.vertices) {
+            let position = jms.triangles[*jms_triangle].vertices.map(|v| jms.vertices[v].position.scale(1.0 / JMS_UNITS_PER_WORLD_UNIT));
+            let center = (position[0] + position[1] + position[2]).scale(1.0 / 3.0);
+            bsp_result.errors.push(Diagnostic::new("MCG003", format!("Triangle #{jms_triangle} is degenerate near {}", jms_position(center))));
+        }
+    }
+
+    let compiled = compile_collision_bsp(triangles, &[], &mut bsp_result);
+
+    let context = |diagnostic: Diagnostic| Diagnostic { message: format!("Permutation `{permutation}`, node `{node}`: {}", diagnostic.message), ..diagnostic };
+    result.pedantic_warnings.extend(bsp_result.pedantic_warnings.into_iter().map(context));
+    result.warnings.extend(bsp_result.warnings.into_iter().map(context));
+    result.errors.extend(bsp_result.errors.into_iter().map(context));
+
+    compiled.bsp
+}
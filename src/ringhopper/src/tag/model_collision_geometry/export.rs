@@ -0,0 +1,172 @@
+use definitions::{ModelCollisionGeometry, ModelCollisionGeometryBSP, ModelCollisionGeometryBSPSurface, ModelNode};
+use primitives::error::{Error, RinghopperResult};
+use primitives::primitive::{Index, Quaternion, Vector, Vector2D, Vector3D};
+use crate::data::jms::{JMSMarker, JMSMaterial, JMSModelSource, JMSNode, JMSTriangle, JMSVertex, ModelLOD, JMS, JMS_UNITS_PER_WORLD_UNIT};
+use crate::tag::model::cross;
+use super::{node_transforms, NodeTransform, PATHFINDING_SPHERE_MARKER};
+
+/// Export a model_collision_geometry tag as JMS files, one for each permutation.
+///
+/// Collision geometry is stored relative to each node, but the tag does not store where each node is. If `model_nodes`
+/// is set (such as from the model tag used with the collision geometry), then the nodes are posed the same way as the
+/// model's nodes, and the JMS files line up with the model. Otherwise, every node is placed at the origin.
+///
+/// Surface flags are exported as material symbols, and pathfinding spheres are exported as markers.
+pub fn export_model_collision_geometry_to_jms(tag: &ModelCollisionGeometry, model_nodes: Option<&[ModelNode]>) -> RinghopperResult<Vec<JMSModelSource>> {
+    let model_nodes = model_nodes.filter(|n| n.len() == tag.nodes.items.len());
+
+    let mut nodes = Vec::with_capacity(tag.nodes.items.len());
+    for (index, node) in tag.nodes.items.iter().enumerate() {
+        let model_node = model_nodes.map(|n| &n[index]);
+        nodes.push(JMSNode {
+            name: node.name.to_string(),
+            first_child: node.first_child_node.map(|i| i as usize),
+            sibling: node.next_sibling_node.map(|i| i as usize),
+            rotation: model_node.map(|n| n.default_rotation).unwrap_or(Quaternion { x: 0.0, y: 0.0, z: 0.0, w: 1.0 }),
+            translation: model_node.map(|n| n.default_translation.scale(JMS_UNITS_PER_WORLD_UNIT)).unwrap_or_default()
+        });
+        if [node.first_child_node, node.next_sibling_node].into_iter().flatten().any(|n| n as usize >= tag.nodes.items.len()) {
+            return Err(Error::InvalidTagData(format!("node #{index} refers to a node that does not exist")))
+        }
+    }
+
+    let parents: Vec<Option<usize>> = tag.nodes.items.iter().map(|n| n.parent_node.map(|p| p as usize)).collect();
+    let transforms = node_transforms(&nodes, &parents);
+
+    let markers: Vec<JMSMarker> = tag.pathfinding_spheres.items.iter().map(|sphere| JMSMarker {
+        name: PATHFINDING_SPHERE_MARKER.to_owned(),
+        region: None,
+        node: sphere.node.unwrap_or_default() as usize,
+        rotation: Quaternion { x: 0.0, y: 0.0, z: 0.0, w: 1.0 },
+        translation: sphere.center.scale(JMS_UNITS_PER_WORLD_UNIT),
+        radius: sphere.radius * JMS_UNITS_PER_WORLD_UNIT
+    }).collect();
+
+    let regions: Vec<String> = tag.regions.items.iter().map(|r| r.name.to_string()).collect();
+
+    // Permutations are matched by name across regions.
+    let mut permutation_names: Vec<&str> = Vec::new();
+    for region in &tag.regions.items {
+        for permutation in &region.permutations.items {
+            if !permutation_names.contains(&permutation.name.as_str()) {
+                permutation_names.push(permutation.name.as_str());
+            }
+        }
+    }
+
+    let mut sources = Vec::with_capacity(permutation_names.len());
+    for permutation_name in permutation_names {
+        let mut jms = JMS {
+            nodes: nodes.clone(),
+            regions: regions.clone(),
+            markers: markers.clone(),
+            ..Default::default()
+        };
+        let mut material_keys: Vec<(Index, String)> = Vec::new();
+
+        for (region_index, region) in tag.regions.items.iter().enumerate() {
+            let Some(permutation_index) = region.permutations.items.iter().position(|p| p.name.as_str() == permutation_name) else {
+                continue
+            };
+
+            for (node_index, node) in tag.nodes.items.iter().enumerate() {
+                if node.region != Some(region_index as u16) {
+                    continue
+                }
+                let Some(bsp) = node.bsps.items.get(permutation_index) else {
+                    continue
+                };
+                export_bsp_to_jms(tag, bsp, node_index, region_index, &transforms[node_index], &mut jms, &mut material_keys)
+                    .map_err(|e| Error::InvalidTagData(format!("node `{}`, permutation `{permutation_name}`: {e}", node.name)))?;
+            }
+        }
+
+        sources.push(JMSModelSource { permutation: permutation_name.to_owned(), lod: ModelLOD::SuperHigh, jms });
+    }
+
+    Ok(sources)
+}
+
+/// Add each surface of a BSP to a JMS as a fan of triangles.
+fn export_bsp_to_jms(tag: &ModelCollisionGeometry, bsp: &ModelCollisionGeometryBSP, node: usize, region: usize, transform: &NodeTransform, jms: &mut JMS, material_keys: &mut Vec<(Index, String)>) -> Result<(), String> {
+    for (surface_index, surface) in bsp.surfaces.items.iter().enumerate() {
+        let vertices = surface_vertices(bsp, surface_index)?;
+        if vertices.len() < 3 {
+            continue
+        }
+        let points: Vec<Vector3D> = vertices.iter().map(|v| bsp.vertices.items[*v as usize].point).collect();
+        let normal = cross(points[1] - points[0], points[2] - points[0]).normalize();
+
+        let material = jms_material(tag, surface, jms, material_keys);
+        let first_vertex = jms.vertices.len();
+        for point in &points {
+            jms.vertices.push(JMSVertex {
+                node0: node,
+                position: transform.to_model(*point).scale(JMS_UNITS_PER_WORLD_UNIT),
+                normal: transform.rotate_to_model(normal),
+                node1: None,
+                node1_weight: 0.0,
+                texture_coordinates: Vector2D::default()
+            });
+        }
+        for i in 1..vertices.len() - 1 {
+            jms.triangles.push(JMSTriangle { region, material, vertices: [first_vertex, first_vertex + i, first_vertex + i + 1] });
+        }
+    }
+    Ok(())
+}
+
+/// Walk the edges of a surface to get its vertices in order.
+fn surface_vertices(bsp: &ModelCollisionGeometryBSP, surface: usize) -> Result<Vec<u32>, String> {
+    let first_edge = bsp.surfaces.items[surface].first_edge;
+    let mut vertices = Vec::new();
+    let mut edge_index = first_edge;
+    loop {
+        let edge = bsp.edges.items.get(edge_index as usize).ok_or_else(|| format!("surface #{surface} refers to an invalid edge"))?;
+        let (vertex, next) = if edge.left_surface as usize == surface {
+            (edge.start_vertex, edge.forward_edge)
+        }
+        else if edge.right_surface as usize == surface {
+            (edge.end_vertex, edge.reverse_edge)
+        }
+        else {
+            return Err(format!("edge #{edge_index} is not part of surface #{surface}"))
+        };
+
+        if vertex as usize >= bsp.vertices.items.len() {
+            return Err(format!("edge #{edge_index} refers to an invalid vertex"))
+        }
+        vertices.push(vertex);
+        edge_index = next;
+
+        if edge_index == first_edge {
+            return Ok(vertices)
+        }
+        if vertices.len() > bsp.edges.items.len() {
+            return Err(format!("edges of surface #{surface} do not form a loop"))
+        }
+    }
+}
+
+/// Get the JMS material for a surface, adding it if needed.
+fn jms_material(tag: &ModelCollisionGeometry, surface: &ModelCollisionGeometryBSPSurface, jms: &mut JMS, material_keys: &mut Vec<(Index, String)>) -> usize {
+    let mut symbols = String::new();
+    for (set, symbol) in [(surface.flags.two_sided, '%'), (surface.flags.climbable, '^'), (surface.flags.breakable, '-')] {
+        if set {
+            symbols.push(symbol);
+        }
+    }
+
+    let key = (surface.material, symbols);
+    if let Some(index) = material_keys.iter().position(|k| *k == key) {
+        return index
+    }
+
+    let name = surface.material
+        .and_then(|m| tag.materials.items.get(m as usize))
+        .map(|m| m.name.to_string())
+        .unwrap_or_else(|| "unnamed".to_owned());
+    jms.materials.push(JMSMaterial { name: format!("{name}{}", key.1), tiff_path: "<none>".to_owned() });
+    material_keys.push(key);
+    material_keys.len() - 1
+}
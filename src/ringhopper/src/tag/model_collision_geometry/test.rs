@@ -0,0 +1,171 @@
+use definitions::{ModelCollisionGeometry, ModelNode};
+use primitives::primitive::{Quaternion, Vector, Vector2D, Vector3D};
+use crate::data::jms::*;
+use super::*;
+
+/// Add a cube with faces pointing outward, in JMS units.
+fn add_cube(jms: &mut JMS, center: Vector3D, half_size: f32, node: usize, region: usize, material: usize) {
+    let corner = |i: usize| Vector3D {
+        x: center.x + if i & 1 != 0 { half_size } else { -half_size },
+        y: center.y + if i & 2 != 0 { half_size } else { -half_size },
+        z: center.z + if i & 4 != 0 { half_size } else { -half_size }
+    };
+
+    // Counterclockwise when viewed from outside
+    let faces = [[0, 2, 3, 1], [4, 5, 7, 6], [0, 1, 5, 4], [2, 6, 7, 3], [0, 4, 6, 2], [1, 3, 7, 5]];
+    for [a, b, c, d] in faces {
+        for triangle in [[a, b, c], [a, c, d]] {
+            let first = jms.vertices.len();
+            for position in triangle.map(corner) {
+                jms.vertices.push(JMSVertex {
+                    node0: node,
+                    position,
+                    normal: Vector3D::zero(),
+                    node1: None,
+                    node1_weight: 0.0,
+                    texture_coordinates: Vector2D::default()
+                });
+            }
+            jms.triangles.push(JMSTriangle { region, material, vertices: [first, first + 1, first + 2] });
+        }
+    }
+}
+
+/// Make a JMS with a base cube on the root node and a cube on a child node that is turned 90 degrees.
+fn test_jms(cube_size: f32) -> JMS {
+    let identity = Quaternion { x: 0.0, y: 0.0, z: 0.0, w: 1.0 };
+    let turned = Quaternion { x: 0.0, y: 0.0, z: std::f32::consts::FRAC_1_SQRT_2, w: std::f32::consts::FRAC_1_SQRT_2 };
+
+    let mut jms = JMS {
+        nodes: vec![
+            JMSNode { name: "frame".to_owned(), first_child: Some(1), sibling: None, rotation: identity, translation: Vector3D::zero() },
+            JMSNode { name: "arm".to_owned(), first_child: None, sibling: None, rotation: turned, translation: Vector3D { x: 100.0, y: 0.0, z: 0.0 } }
+        ],
+        materials: vec![
+            JMSMaterial { name: "metal".to_owned(), tiff_path: "<none>".to_owned() },
+            JMSMaterial { name: "glass%".to_owned(), tiff_path: "<none>".to_owned() }
+        ],
+        markers: vec![JMSMarker { name: "pathfinding sphere".to_owned(), region: None, node: 0, rotation: identity, translation: Vector3D { x: 0.0, y: 0.0, z: 10.0 }, radius: 50.0 }],
+        regions: vec!["base".to_owned(), "arm".to_owned()],
+        ..Default::default()
+    };
+
+    add_cube(&mut jms, Vector3D::zero(), 40.0, 0, 0, 0);
+    add_cube(&mut jms, Vector3D { x: 100.0, y: 0.0, z: 0.0 }, cube_size, 1, 1, 1);
+    jms
+}
+
+fn test_sources() -> Vec<JMSModelSource> {
+    vec![
+        JMSModelSource { permutation: "damaged".to_owned(), lod: ModelLOD::SuperHigh, jms: test_jms(10.0) },
+        JMSModelSource { permutation: "base".to_owned(), lod: ModelLOD::SuperHigh, jms: test_jms(20.0) }
+    ]
+}
+
+#[test]
+fn compile_collision_geometry() {
+    let mut tag = ModelCollisionGeometry::default();
+    let result = compile_model_collision_geometry(&mut tag, &test_sources()).unwrap();
+    assert!(result.is_ok(), "{:?}", result.errors);
+
+    // Regions and permutations are sorted by name.
+    let region_names: Vec<&str> = tag.regions.items.iter().map(|r| r.name.as_str()).collect();
+    assert_eq!(vec!["arm", "base"], region_names);
+    let permutation_names: Vec<&str> = tag.regions.items[0].permutations.items.iter().map(|p| p.name.as_str()).collect();
+    assert_eq!(vec!["base", "damaged"], permutation_names);
+
+    let material_names: Vec<&str> = tag.materials.items.iter().map(|m| m.name.as_str()).collect();
+    assert_eq!(vec!["metal", "glass"], material_names);
+
+    assert_eq!(2, tag.nodes.items.len());
+    let arm = &tag.nodes.items[1];
+    assert_eq!(Some(0), arm.region);
+    assert_eq!(Some(0), arm.parent_node);
+    assert_eq!(2, arm.bsps.items.len());
+
+    // Each node's geometry is relative to the node.
+    for (bsp, half_size) in arm.bsps.items.iter().zip([0.2, 0.1]) {
+        assert_eq!(12, bsp.surfaces.items.len());
+        assert!(bsp.surfaces.items.iter().all(|s| s.flags.two_sided && s.material == Some(1)));
+        for vertex in &bsp.vertices.items {
+            for c in [vertex.point.x, vertex.point.y, vertex.point.z] {
+                assert!((c.abs() - half_size).abs() < 0.0001, "{:?}", vertex.point);
+            }
+        }
+    }
+
+    assert_eq!(1, tag.pathfinding_spheres.items.len());
+    assert_eq!(Some(0), tag.pathfinding_spheres.items[0].node);
+    assert_eq!(0.5, tag.pathfinding_spheres.items[0].radius);
+}
+
+#[test]
+fn report_bad_collision_geometry() {
+    // A triangle weighted to two nodes
+    let mut sources = test_sources();
+    sources[0].jms.vertices[0].node1 = Some(1);
+    sources[0].jms.vertices[0].node1_weight = 0.5;
+    let result = compile_model_collision_geometry(&mut ModelCollisionGeometry::default(), &sources).unwrap();
+    assert!(result.errors.iter().any(|e| e.contains("more than one node")), "{:?}", result.errors);
+
+    // A node in two regions
+    let mut sources = test_sources();
+    sources[1].jms.triangles[0].region = 1;
+    let result = compile_model_collision_geometry(&mut ModelCollisionGeometry::default(), &sources).unwrap();
+    assert!(result.errors.iter().any(|e| e.contains("more than one region")), "{:?}", result.errors);
+
+    // Open edges
+    let mut sources = test_sources();
+    sources[0].jms.triangles.remove(0);
+    let result = compile_model_collision_geometry(&mut ModelCollisionGeometry::default(), &sources).unwrap();
+    assert!(result.errors.iter().any(|e| e.starts_with("Permutation `damaged`, node `frame`: Open edge")), "{:?}", result.errors);
+}
+
+#[test]
+fn export_collision_geometry_to_jms_and_back() {
+    let mut tag = ModelCollisionGeometry::default();
+    compile_model_collision_geometry(&mut tag, &test_sources()).unwrap();
+
+    let model_nodes: Vec<ModelNode> = test_jms(10.0).nodes.iter().map(|n| ModelNode {
+        default_rotation: n.rotation,
+        default_translation: n.translation.scale(1.0 / JMS_UNITS_PER_WORLD_UNIT),
+        ..Default::default()
+    }).collect();
+
+    for nodes in [Some(model_nodes.as_slice()), None] {
+        let exported = export_model_collision_geometry_to_jms(&tag, nodes).unwrap();
+        let permutations: Vec<&str> = exported.iter().map(|s| s.permutation.as_str()).collect();
+        assert_eq!(vec!["base", "damaged"], permutations);
+
+        let base = &exported[0].jms;
+        assert_eq!(24, base.triangles.len());
+        assert_eq!(vec!["glass%", "metal"], base.materials.iter().map(|m| m.name.as_str()).collect::<Vec<_>>());
+        assert_eq!(PATHFINDING_SPHERE_MARKER, base.markers[0].name);
+
+        // Posed like the model, the arm cube is back where it was in the original JMS.
+        if nodes.is_some() {
+            let arm_positions = base.triangles.iter().filter(|t| t.region == 0).flat_map(|t| t.vertices.map(|v| base.vertices[v].position));
+            for position in arm_positions {
+                assert!((position.x - 100.0).abs() - 20.0 < 0.01, "{position:?}");
+            }
+        }
+
+        let mut recompiled = ModelCollisionGeometry::default();
+        let result = compile_model_collision_geometry(&mut recompiled, &exported).unwrap();
+        assert!(result.is_ok(), "{:?}", result.errors);
+        assert_eq!(tag.regions.items.len(), recompiled.regions.items.len());
+        assert_eq!(tag.materials.items.len(), recompiled.materials.items.len());
+        assert_eq!(tag.pathfinding_spheres.items.len(), recompiled.pathfinding_spheres.items.len());
+
+        for (original, recompiled) in tag.nodes.items.iter().zip(recompiled.nodes.items.iter()) {
+            assert_eq!(original.bsps.items.len(), recompiled.bsps.items.len());
+            for (a, b) in original.bsps.items.iter().zip(recompiled.bsps.items.iter()) {
+                assert_eq!(a.surfaces.items.len(), b.surfaces.items.len());
+                assert_eq!(a.vertices.items.len(), b.vertices.items.len());
+                for vertex in &a.vertices.items {
+                    assert!(b.vertices.items.iter().any(|v| v.point.distance_squared(&vertex.point) < 0.00001), "{:?}", vertex.point);
+                }
+            }
+        }
+    }
+}
@@ -5,7 +5,12 @@ use primitives::error::Error::InvalidTagData;
 use primitives::error::RinghopperResult;
 use primitives::primitive::{TagGroup, TagPath};
 use primitives::tag::PrimaryTagStructDyn;
+use crate::data::jms::ModelLOD;
 use crate::tag::bitmap::extract_compressed_color_plate_data;
+use crate::tag::model::{export_model_to_gltf, export_model_to_jms, ModelFunctions};
+use crate::tag::model_animations::export_animations_to_jma;
+use crate::tag::model_collision_geometry::export_model_collision_geometry_to_jms;
+use crate::tag::sound::{decode_permutation_chain, find_actual_permutation_count};
 use crate::tag::unicode_string_list::UnicodeStringListFunctions;
 
 pub type RecoverFunction = fn(tag_path: &TagPath, tag_data: &Box<dyn PrimaryTagStructDyn>) -> RinghopperResult<Option<HashMap<PathBuf, Vec<u8>>>>;
@@ -13,7 +18,12 @@ pub type RecoverFunction = fn(tag_path: &TagPath, tag_data: &Box<dyn PrimaryTagS
 pub fn get_recover_function(group: TagGroup) -> Option<RecoverFunction> {
     match group {
         TagGroup::Bitmap => Some(recover_bitmap),
+        TagGroup::GBXModel => Some(recover_model),
+        TagGroup::Model => Some(recover_model),
+        TagGroup::ModelAnimations => Some(recover_model_animations),
+        TagGroup::ModelCollisionGeometry => Some(recover_model_collision_geometry),
         TagGroup::Scenario => Some(recover_scenario_scripts),
+        TagGroup::Sound => Some(recover_sound),
         TagGroup::UnicodeStringList => Some(recover_unicode_string_lists),
         _ => None
     }
@@ -33,6 +43,134 @@ fn recover_bitmap(tag_path: &TagPath, tag_data: &Box<dyn PrimaryTagStructDyn>) -
     Ok(Some(fs))
 }
 
+fn recover_model(tag_path: &TagPath, tag_data: &Box<dyn PrimaryTagStructDyn>) -> RinghopperResult<Option<HashMap<PathBuf, Vec<u8>>>> {
+    let model = match tag_data.group() {
+        TagGroup::GBXModel => {
+            let gbxmodel = tag_data.as_any().downcast_ref::<GBXModel>().unwrap();
+            gbxmodel.check_indices()?;
+            gbxmodel.clone().convert_to_model()
+        },
+        _ => tag_data.as_any().downcast_ref::<Model>().unwrap().clone()
+    };
+    if model.geometries.items.is_empty() {
+        return Ok(None)
+    }
+
+    // Lay out JMS files the same way the model compiler reads them.
+    let base_path = PathBuf::from(tag_path.to_native_path());
+    let models_dir = base_path.parent().map(|p| p.join("models")).unwrap_or_else(|| PathBuf::from("models"));
+
+    let sources = export_model_to_jms(&model)?;
+    let permutation_names: Vec<&str> = sources.iter().filter(|s| s.lod == ModelLOD::SuperHigh).map(|s| s.permutation.as_str()).collect();
+
+    // Each permutation is exported starting with its super high LOD, followed by its other LODs.
+    let mut fs = HashMap::new();
+    for (index, permutation_sources) in sources.chunk_by(|_, next| next.lod != ModelLOD::SuperHigh).enumerate() {
+        let permutation = unique_file_name(&permutation_sources[0].permutation, index, &permutation_names);
+        for source in permutation_sources {
+            let file_name = match source.lod {
+                ModelLOD::SuperHigh => format!("{permutation}.jms"),
+                lod => format!("{permutation} {}.jms", lod.as_str())
+            };
+            fs.insert(models_dir.join(file_name), source.jms.to_jms());
+        }
+    }
+    fs.insert(base_path.with_extension("glb"), export_model_to_gltf(&model)?);
+
+    Ok(Some(fs))
+}
+
+fn recover_model_animations(tag_path: &TagPath, tag_data: &Box<dyn PrimaryTagStructDyn>) -> RinghopperResult<Option<HashMap<PathBuf, Vec<u8>>>> {
+    let model_animations = tag_data.as_any().downcast_ref::<ModelAnimations>().unwrap();
+    if model_animations.animations.items.is_empty() {
+        return Ok(None)
+    }
+
+    // Lay out files the same way the animation compiler reads them.
+    let animations_dir = PathBuf::from(tag_path.to_native_path()).parent().map(|p| p.join("animations")).unwrap_or_else(|| PathBuf::from("animations"));
+
+    let sources = export_animations_to_jma(model_animations)?;
+    let names: Vec<&str> = sources.iter().map(|s| s.name.as_str()).collect();
+
+    let mut fs = HashMap::new();
+    for (index, source) in sources.iter().enumerate() {
+        let file_name = format!("{}.{}", unique_file_name(&source.name, index, &names), source.kind.extension());
+        fs.insert(animations_dir.join(file_name), source.jma.to_jma());
+    }
+
+    Ok(Some(fs))
+}
+
+fn recover_model_collision_geometry(tag_path: &TagPath, tag_data: &Box<dyn PrimaryTagStructDyn>) -> RinghopperResult<Option<HashMap<PathBuf, Vec<u8>>>> {
+    let collision = tag_data.as_any().downcast_ref::<ModelCollisionGeometry>().unwrap();
+    if collision.nodes.items.iter().all(|n| n.bsps.items.is_empty()) {
+        return Ok(None)
+    }
+
+    // Lay out JMS files the same way the collision compiler reads them. Nodes are not posed since the tag does not
+    // store where they are.
+    let physics_dir = PathBuf::from(tag_path.to_native_path()).parent().map(|p| p.join("physics")).unwrap_or_else(|| PathBuf::from("physics"));
+
+    let sources = export_model_collision_geometry_to_jms(collision, None)?;
+    let names: Vec<&str> = sources.iter().map(|s| s.permutation.as_str()).collect();
+
+    let mut fs = HashMap::new();
+    for (index, source) in sources.iter().enumerate() {
+        fs.insert(physics_dir.join(format!("{}.jms", unique_file_name(&source.permutation, index, &names))), source.jms.to_jms());
+    }
+
+    Ok(Some(fs))
+}
+
+fn recover_sound(tag_path: &TagPath, tag_data: &Box<dyn PrimaryTagStructDyn>) -> RinghopperResult<Option<HashMap<PathBuf, Vec<u8>>>> {
+    let sound = tag_data.as_any().downcast_ref::<Sound>().unwrap();
+    if sound.pitch_ranges.items.iter().all(|p| p.permutations.items.is_empty()) {
+        return Ok(None)
+    }
+
+    // Lay out files the same way the sound compiler reads them: if there is only one pitch range, permutations go
+    // directly in the sound's directory, otherwise each pitch range gets its own directory.
+    let base_dir = PathBuf::from(tag_path.to_native_path()).with_extension("");
+    let multiple_pitch_ranges = sound.pitch_ranges.items.len() > 1;
+    let pitch_range_names: Vec<&str> = sound.pitch_ranges.items.iter().map(|p| p.name.as_str()).collect();
+
+    let mut fs = HashMap::new();
+    for (pitch_range_index, pitch_range) in sound.pitch_ranges.items.iter().enumerate() {
+        let pitch_range_dir = if multiple_pitch_ranges {
+            base_dir.join(unique_file_name(pitch_range.name.as_str(), pitch_range_index, &pitch_range_names))
+        }
+        else {
+            base_dir.clone()
+        };
+
+        let actual_permutation_count = find_actual_permutation_count(sound, pitch_range) as usize;
+        let actual_permutations = &pitch_range.permutations.items[..actual_permutation_count.min(pitch_range.permutations.items.len())];
+        let names: Vec<&str> = actual_permutations.iter().map(|p| p.name.as_str()).collect();
+
+        for (permutation_index, permutation) in actual_permutations.iter().enumerate() {
+            let audio = decode_permutation_chain(sound, pitch_range, permutation_index)
+                .map_err(|e| InvalidTagData(format!("Failed to decode permutation #{permutation_index} (`{}`) of pitch range #{pitch_range_index}: {e}", permutation.name.as_str())))?;
+            let file_name = unique_file_name(permutation.name.as_str(), permutation_index, &names);
+            fs.insert(pitch_range_dir.join(file_name).with_extension("wav"), audio.to_wav());
+        }
+    }
+
+    Ok(Some(fs))
+}
+
+/// Get a file name for an element, appending its index if the name is empty or shared with another element.
+fn unique_file_name(name: &str, index: usize, all_names: &[&str]) -> String {
+    if name.is_empty() {
+        format!("{index}")
+    }
+    else if all_names.iter().filter(|n| **n == name).count() > 1 {
+        format!("{name}_{index}")
+    }
+    else {
+        name.to_owned()
+    }
+}
+
 fn recover_unicode_string_lists(tag_path: &TagPath, tag_data: &Box<dyn PrimaryTagStructDyn>) -> RinghopperResult<Option<HashMap<PathBuf, Vec<u8>>>> {
     let unicode_string_list: &UnicodeStringList = tag_data.as_any().downcast_ref().unwrap();
     let data = unicode_string_list.as_text_data().map_err(|e| InvalidTagData(format!("{e:?}")))?;
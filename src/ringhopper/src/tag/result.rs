@@ -1,16 +1,91 @@
 use std::collections::HashMap;
+use std::fmt::Display;
+use std::ops::Deref;
 use std::sync::{Arc, Mutex};
 use definitions::ScenarioType;
 use primitives::engine::Engine;
 use primitives::primitive::TagPath;
 use primitives::tag::PrimaryTagStructDyn;
+use serde_json::json;
 use crate::tag::tree::TagTree;
 
+#[cfg(test)]
+mod test;
+
+/// Severity of a [`Diagnostic`].
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Hash)]
+pub enum Severity {
+    PedanticWarning,
+    Warning,
+    Error
+}
+
+impl Severity {
+    /// Get the name of the severity as used in JSON output.
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Severity::PedanticWarning => "pedantic-warning",
+            Severity::Warning => "warning",
+            Severity::Error => "error"
+        }
+    }
+
+    /// Get the SARIF `level` of the severity.
+    pub const fn sarif_level(self) -> &'static str {
+        match self {
+            Severity::PedanticWarning => "note",
+            Severity::Warning => "warning",
+            Severity::Error => "error"
+        }
+    }
+}
+
+/// Issue found with a tag.
+///
+/// This dereferences to its message.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Diagnostic {
+    /// Stable code of the check that found the issue, such as `BMP003`.
+    pub code: &'static str,
+
+    /// Field the issue was found in, in matcher syntax (e.g. `bitmap_data[2].format`), if known.
+    pub field: Option<String>,
+
+    /// Description of the issue.
+    pub message: String
+}
+
+impl Diagnostic {
+    /// Create a diagnostic that is not tied to a specific field.
+    pub fn new<S: Into<String>>(code: &'static str, message: S) -> Self {
+        Self { code, field: None, message: message.into() }
+    }
+
+    /// Set the field the issue was found in.
+    pub fn with_field<S: Into<String>>(mut self, field: S) -> Self {
+        self.field = Some(field.into());
+        self
+    }
+}
+
+impl Deref for Diagnostic {
+    type Target = str;
+    fn deref(&self) -> &str {
+        &self.message
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
 #[derive(Clone, Default)]
 pub struct TagResult {
-    pub pedantic_warnings: Vec<String>,
-    pub warnings: Vec<String>,
-    pub errors: Vec<String>
+    pub pedantic_warnings: Vec<Diagnostic>,
+    pub warnings: Vec<Diagnostic>,
+    pub errors: Vec<Diagnostic>
 }
 
 impl TagResult {
@@ -23,6 +98,108 @@ impl TagResult {
         self.warnings.append(&mut other.warnings);
         self.errors.append(&mut other.errors);
     }
+
+    /// Iterate through all diagnostics, from least to most severe.
+    pub fn diagnostics(&self) -> impl Iterator<Item = (Severity, &Diagnostic)> {
+        fn with_severity(severity: Severity, list: &[Diagnostic]) -> impl Iterator<Item = (Severity, &Diagnostic)> {
+            list.iter().map(move |d| (severity, d))
+        }
+        with_severity(Severity::PedanticWarning, &self.pedantic_warnings)
+            .chain(with_severity(Severity::Warning, &self.warnings))
+            .chain(with_severity(Severity::Error, &self.errors))
+    }
+
+    /// Remove all diagnostics with any of the given codes.
+    pub fn suppress<S: AsRef<str>>(&mut self, codes: &[S]) {
+        let suppressed = |d: &Diagnostic| codes.iter().any(|c| c.as_ref() == d.code);
+        self.pedantic_warnings.retain(|d| !suppressed(d));
+        self.warnings.retain(|d| !suppressed(d));
+        self.errors.retain(|d| !suppressed(d));
+    }
+}
+
+fn sorted_results(results: &HashMap<TagPath, TagResult>) -> Vec<(&TagPath, &TagResult)> {
+    let mut sorted: Vec<(&TagPath, &TagResult)> = results.iter().collect();
+    sorted.sort_by(|a, b| a.0.cmp(b.0));
+    sorted
+}
+
+/// Get the path of the tag relative to its tags directory, using forward slashes.
+fn tag_uri(path: &TagPath) -> String {
+    path.to_internal_path().replace('\\', "/")
+}
+
+/// Write all diagnostics as a JSON array, sorted by tag path.
+///
+/// Each diagnostic is an object with `code`, `severity`, `tag`, `field` (`null` if unknown), and `message`.
+pub fn results_to_json(results: &HashMap<TagPath, TagResult>) -> String {
+    let mut diagnostics = Vec::new();
+    for (path, result) in sorted_results(results) {
+        for (severity, diagnostic) in result.diagnostics() {
+            diagnostics.push(json!({
+                "code": diagnostic.code,
+                "severity": severity.as_str(),
+                "tag": path.to_internal_path(),
+                "field": diagnostic.field,
+                "message": diagnostic.message
+            }));
+        }
+    }
+    serde_json::to_string_pretty(&diagnostics).unwrap()
+}
+
+/// Write all diagnostics as a SARIF 2.1.0 log with a single run.
+///
+/// Tag locations are relative to the `TAGS` URI base, which the consumer can map to the tags directory.
+pub fn results_to_sarif(results: &HashMap<TagPath, TagResult>, tool_name: &str, tool_version: &str) -> String {
+    let mut rules: Vec<&str> = Vec::new();
+    let mut sarif_results = Vec::new();
+    for (path, result) in sorted_results(results) {
+        for (severity, diagnostic) in result.diagnostics() {
+            if !rules.contains(&diagnostic.code) {
+                rules.push(diagnostic.code);
+            }
+
+            let mut location = json!({
+                "physicalLocation": {
+                    "artifactLocation": {
+                        "uri": tag_uri(path),
+                        "uriBaseId": "TAGS"
+                    }
+                }
+            });
+            if let Some(field) = &diagnostic.field {
+                location["logicalLocations"] = json!([{
+                    "fullyQualifiedName": field,
+                    "kind": "member"
+                }]);
+            }
+
+            sarif_results.push(json!({
+                "ruleId": diagnostic.code,
+                "level": severity.sarif_level(),
+                "message": { "text": diagnostic.message },
+                "locations": [location]
+            }));
+        }
+    }
+    rules.sort();
+
+    let sarif = json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": tool_name,
+                    "version": tool_version,
+                    "rules": rules.into_iter().map(|r| json!({ "id": r })).collect::<Vec<_>>()
+                }
+            },
+            "results": sarif_results
+        }]
+    });
+    serde_json::to_string_pretty(&sarif).unwrap()
 }
 
 pub(crate) struct ScenarioContext<T: TagTree + Send + Sync> {
@@ -0,0 +1,78 @@
+use primitives::primitive::TagGroup;
+use serde_json::Value;
+use super::*;
+
+fn test_results() -> HashMap<TagPath, TagResult> {
+    let mut bitmap = TagResult::default();
+    bitmap.errors.push(Diagnostic::new("BMP003", "Bitmap #0 is DXT5, but the bitmap tag is set to DXT1 which doesn't match").with_field("bitmap_data[0]"));
+    bitmap.pedantic_warnings.push(Diagnostic::new("TST001", "Something minor"));
+
+    let mut model = TagResult::default();
+    model.warnings.push(Diagnostic::new("TST002", "Something \"quoted\""));
+
+    let mut results = HashMap::new();
+    results.insert(TagPath::new("weapons\\rifle\\rifle", TagGroup::Bitmap).unwrap(), bitmap);
+    results.insert(TagPath::new("characters\\cyborg\\cyborg", TagGroup::GBXModel).unwrap(), model);
+    results.insert(TagPath::new("levels\\test\\test", TagGroup::Scenario).unwrap(), TagResult::default());
+    results
+}
+
+#[test]
+fn diagnostics_and_suppression() {
+    let mut results = test_results();
+    let bitmap = results.get_mut(&TagPath::new("weapons\\rifle\\rifle", TagGroup::Bitmap).unwrap()).unwrap();
+
+    let all: Vec<(Severity, &str)> = bitmap.diagnostics().map(|(s, d)| (s, d.code)).collect();
+    assert_eq!(all, [(Severity::PedanticWarning, "TST001"), (Severity::Error, "BMP003")]);
+    assert!(bitmap.errors[0].starts_with("Bitmap #0"));
+
+    bitmap.suppress(&["BMP003"]);
+    assert!(bitmap.is_ok());
+    assert_eq!(bitmap.pedantic_warnings.len(), 1);
+}
+
+#[test]
+fn json_output() {
+    let json = serde_json::from_str::<Value>(&results_to_json(&test_results())).unwrap();
+    let Value::Array(diagnostics) = json else { panic!("expected an array") };
+    assert_eq!(diagnostics.len(), 3);
+
+    // Sorted by tag path, then from least to most severe
+    let first = &diagnostics[0];
+    assert_eq!(first.get("code"), Some(&json!("TST002")));
+    assert_eq!(first.get("severity"), Some(&json!("warning")));
+    assert_eq!(first.get("tag"), Some(&json!("characters\\cyborg\\cyborg.gbxmodel")));
+    assert_eq!(first.get("field"), Some(&Value::Null));
+    assert_eq!(first.get("message"), Some(&json!("Something \"quoted\"")));
+
+    let last = &diagnostics[2];
+    assert_eq!(last.get("code"), Some(&json!("BMP003")));
+    assert_eq!(last.get("severity"), Some(&json!("error")));
+    assert_eq!(last.get("field"), Some(&json!("bitmap_data[0]")));
+}
+
+#[test]
+fn sarif_output() {
+    let sarif = serde_json::from_str::<Value>(&results_to_sarif(&test_results(), "invader", "1.0")).unwrap();
+    assert_eq!(sarif.get("version"), Some(&json!("2.1.0")));
+
+    let Some(Value::Array(runs)) = sarif.get("runs") else { panic!("expected runs") };
+    let driver = runs[0].get("tool").and_then(|t| t.get("driver")).unwrap();
+    assert_eq!(driver.get("name"), Some(&json!("invader")));
+    let Some(Value::Array(rules)) = driver.get("rules") else { panic!("expected rules") };
+    let rule_ids: Vec<&Value> = rules.iter().map(|r| r.get("id").unwrap()).collect();
+    assert_eq!(rule_ids, [&json!("BMP003"), &json!("TST001"), &json!("TST002")]);
+
+    let Some(Value::Array(results)) = runs[0].get("results") else { panic!("expected results") };
+    assert_eq!(results.len(), 3);
+
+    let error = &results[2];
+    assert_eq!(error.get("ruleId"), Some(&json!("BMP003")));
+    assert_eq!(error.get("level"), Some(&json!("error")));
+    let Some(Value::Array(locations)) = error.get("locations") else { panic!("expected locations") };
+    let artifact = locations[0].get("physicalLocation").and_then(|l| l.get("artifactLocation")).unwrap();
+    assert_eq!(artifact.get("uri"), Some(&json!("weapons/rifle/rifle.bitmap")));
+    assert!(locations[0].get("logicalLocations").is_some());
+
+    assert_eq!(results[1].get("level"), Some(&json!("note")));
+}
@@ -1,11 +1,20 @@
 use std::borrow::Cow;
 use std::char::REPLACEMENT_CHARACTER;
+use std::collections::HashMap;
 use definitions::{Scenario, ScenarioScriptNode, ScenarioScriptNodeTable, ScenarioScriptType, ScenarioScriptValueType, ScenarioSourceFile};
 use primitives::byteorder::{BigEndian, ByteOrder};
 use primitives::dynamic::DynamicEnumImpl;
 use primitives::error::{Error, RinghopperResult};
+use primitives::map::MapWriteTag;
 use primitives::parse::SimpleTagData;
-use primitives::primitive::{Data, ID, Index, String32};
+use primitives::primitive::{Data, ID, Index, String32, TagPath};
+
+#[cfg(test)]
+mod test;
+mod compile;
+mod functions;
+
+pub use compile::*;
 
 fn for_each_node_in_scenario<
     From: ByteOrder,
@@ -72,6 +81,48 @@ pub(crate) fn flip_scenario_script_endianness<From: ByteOrder, To: ByteOrder>(sc
     )
 }
 
+/// Set each script node that refers to a tag to the ID of the tag in the cache file.
+pub(crate) fn resolve_scenario_script_tag_references<B: ByteOrder>(scenario: &mut Scenario, tags: &HashMap<TagPath, MapWriteTag>) -> RinghopperResult<()> {
+    let string_data = scenario.script_string_data.bytes.clone();
+    let mut result = Ok(());
+
+    for_each_node_in_scenario::<B, _, _, _>(
+        scenario,
+        |_, _| (),
+        |data, node| {
+            if result.is_err() || !node.flags.is_primitive || node.flags.is_global || node.flags.is_local_variable {
+                return
+            }
+
+            let path = match get_string_data(&string_data, node.string_offset as usize) {
+                Ok(string) => match script_tag_reference_path(&string, node._type.to_str()) {
+                    Some(path) => path,
+                    None => return
+                },
+                Err(e) => Err(e)
+            };
+
+            let id = path.and_then(|path| tags
+                .get(&path)
+                .map(|tag| tag.id)
+                .ok_or_else(|| Error::InvalidTagData(format!("Scripts refer to {path}, which is not in the cache file")))
+            );
+
+            match id {
+                Ok(id) => {
+                    let mut node = *node;
+                    node.data = id.into();
+                    node.write::<B>(data, 0, data.len()).unwrap();
+                },
+                Err(e) => result = Err(e)
+            }
+        },
+        |_| ()
+    )?;
+
+    result
+}
+
 pub fn decompile_scripts(scenario: &mut Scenario, scenario_name: &str) -> RinghopperResult<()> {
     check_for_duplicate_scripts(scenario)?;
 
@@ -419,9 +470,10 @@ fn check_scripts_are_ok(scenario: &Scenario, extracted_nodes: &Vec<ScenarioScrip
 }
 
 fn get_string_data_for_node<'a>(scenario: &'a Scenario, node: &ScenarioScriptNode) -> RinghopperResult<Cow<'a, str>> {
-    let offset = node.string_offset as usize;
-    let data = &scenario.script_string_data.bytes;
+    get_string_data(&scenario.script_string_data.bytes, node.string_offset as usize)
+}
 
+fn get_string_data(data: &[u8], offset: usize) -> RinghopperResult<Cow<str>> {
     if offset >= data.len() {
         return Err(Error::InvalidTagData(format!("String data offset 0x{offset:04X} out-of-bounds!")));
     }
@@ -0,0 +1,1031 @@
+use std::collections::HashMap;
+use std::ops::RangeInclusive;
+use definitions::{Scenario, ScenarioScriptNode, ScenarioScriptNodeTable, ScenarioScriptType, ScenarioScriptValueType};
+use primitives::byteorder::BigEndian;
+use primitives::dynamic::DynamicEnumImpl;
+use primitives::engine::Engine;
+use primitives::error::{Error, RinghopperResult};
+use primitives::parse::SimpleTagData;
+use primitives::primitive::{Data, ID, IDType, ScenarioScriptNodeValue, String32, TagGroup, TagPath};
+use super::functions::{find_script_function, find_script_global, ScriptFunctionKind};
+
+/// Compile the scenario's source files into scripts, globals, and script node data.
+///
+/// Any previously compiled scripts and globals are replaced. Source files are compiled in order, so scripts and
+/// globals appear in the order they are declared.
+///
+/// Returns `Err` with the file, line, and column if the scripts fail to compile or if more than the engine's
+/// maximum number of script nodes are needed.
+pub fn compile_scripts(scenario: &mut Scenario, engine: &Engine) -> RinghopperResult<()> {
+    let mut file_names = Vec::with_capacity(scenario.source_files.items.len());
+    let mut forms = Vec::new();
+
+    for (file, source_file) in scenario.source_files.items.iter().enumerate() {
+        file_names.push(format!("{}.hsc", source_file.name));
+        let source = String::from_utf8_lossy(&source_file.source.bytes);
+        forms.extend(parse_source(&source, file, &file_names)?);
+    }
+
+    // The node table stores counts as 16-bit integers, and 0xFFFF is reserved for null.
+    let max_nodes = engine.max_script_nodes.min(u16::MAX as u64 - 1) as usize;
+
+    let mut compiler = ScriptCompiler::new(scenario, &file_names, engine, max_nodes);
+    compiler.declare(&forms)?;
+    compiler.compile()?;
+    compiler.finish(scenario)
+}
+
+#[derive(Copy, Clone)]
+struct SourcePosition {
+    file: usize,
+    line: usize,
+    column: usize
+}
+
+enum Token {
+    Open,
+    Close,
+    Atom { text: String, quoted: bool }
+}
+
+enum Expression {
+    Atom { text: String, quoted: bool, position: SourcePosition },
+    List { items: Vec<Expression>, position: SourcePosition }
+}
+
+impl Expression {
+    fn position(&self) -> SourcePosition {
+        match self {
+            Expression::Atom { position, .. } => *position,
+            Expression::List { position, .. } => *position
+        }
+    }
+
+    /// Get the text of the atom if it is a bare (unquoted) word.
+    fn word(&self) -> Option<&str> {
+        match self {
+            Expression::Atom { text, quoted: false, .. } => Some(text),
+            _ => None
+        }
+    }
+}
+
+fn error_at(file_names: &[String], position: SourcePosition, message: String) -> Error {
+    Error::Other(format!("{}:{}:{}: {message}", file_names[position.file], position.line, position.column))
+}
+
+fn tokenize(source: &str, file: usize, file_names: &[String]) -> RinghopperResult<Vec<(Token, SourcePosition)>> {
+    let mut tokens = Vec::new();
+    let mut chars = source.chars().peekable();
+    let mut position = SourcePosition { file, line: 1, column: 1 };
+
+    macro_rules! advance {
+        () => {{
+            let c = chars.next();
+            if c == Some('\n') {
+                position.line += 1;
+                position.column = 1;
+            }
+            else if c.is_some() {
+                position.column += 1;
+            }
+            c
+        }};
+    }
+
+    while let Some(&c) = chars.peek() {
+        let start = position;
+        match c {
+            c if c.is_whitespace() => { advance!(); },
+            '(' => { advance!(); tokens.push((Token::Open, start)); },
+            ')' => { advance!(); tokens.push((Token::Close, start)); },
+            ';' => {
+                advance!();
+
+                // ;* block comments *;
+                if chars.peek() == Some(&'*') {
+                    advance!();
+                    let mut last = '\0';
+                    loop {
+                        match advance!() {
+                            Some(';') if last == '*' => break,
+                            Some(c) => last = c,
+                            None => return Err(error_at(file_names, start, "unterminated block comment".to_owned()))
+                        }
+                    }
+                }
+                else {
+                    while !matches!(chars.peek(), Some('\n') | None) {
+                        advance!();
+                    }
+                }
+            },
+            '"' => {
+                advance!();
+                let mut text = String::new();
+                loop {
+                    match advance!() {
+                        Some('"') => break,
+                        Some('\\') if chars.peek() == Some(&'"') => { advance!(); text.push('"'); },
+                        Some(c) => text.push(c),
+                        None => return Err(error_at(file_names, start, "unterminated string".to_owned()))
+                    }
+                }
+                tokens.push((Token::Atom { text, quoted: true }, start));
+            },
+            _ => {
+                let mut text = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || matches!(c, '(' | ')' | ';' | '"') {
+                        break
+                    }
+                    text.push(c);
+                    advance!();
+                }
+                tokens.push((Token::Atom { text, quoted: false }, start));
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+fn parse_source(source: &str, file: usize, file_names: &[String]) -> RinghopperResult<Vec<Expression>> {
+    let mut stack: Vec<(Vec<Expression>, SourcePosition)> = Vec::new();
+    let mut forms = Vec::new();
+
+    for (token, position) in tokenize(source, file, file_names)? {
+        let expression = match token {
+            Token::Open => {
+                stack.push((Vec::new(), position));
+                continue
+            },
+            Token::Close => {
+                let (items, position) = stack
+                    .pop()
+                    .ok_or_else(|| error_at(file_names, position, "unexpected `)`".to_owned()))?;
+                Expression::List { items, position }
+            },
+            Token::Atom { text, quoted } => Expression::Atom { text, quoted, position }
+        };
+
+        match stack.last_mut() {
+            Some((items, _)) => items.push(expression),
+            None => match expression {
+                Expression::List { .. } => forms.push(expression),
+                Expression::Atom { text, position, .. } => return Err(error_at(file_names, position, format!("expected `(`, got `{text}`")))
+            }
+        }
+    }
+
+    if let Some((_, position)) = stack.pop() {
+        return Err(error_at(file_names, position, "unclosed `(`".to_owned()))
+    }
+
+    Ok(forms)
+}
+
+fn value_type(name: &str) -> RinghopperResult<ScenarioScriptValueType> {
+    ScenarioScriptValueType::from_str(name).ok_or_else(|| Error::Other(format!("`{name}` is not a script value type")))
+}
+
+/// Return `true` if the type can be used for globals, parameters, and return values.
+fn is_declarable_type(value_type: ScenarioScriptValueType) -> bool {
+    !matches!(value_type.to_str(), "unparsed" | "special_form" | "function_name" | "passthrough")
+}
+
+fn is_unresolved_type(value_type: ScenarioScriptValueType) -> bool {
+    matches!(value_type.to_str(), "passthrough" | "void")
+}
+
+const NUMBER_TYPES: &[&str] = &["short", "long", "real"];
+const OBJECT_TYPES: &[&str] = &["object", "unit", "vehicle", "weapon", "device", "scenery"];
+const OBJECT_NAME_TYPES: &[&str] = &["object_name", "unit_name", "vehicle_name", "weapon_name", "device_name", "scenery_name"];
+
+/// Tag reference types and the group they refer to.
+///
+/// Object definitions can be of any object group, so their paths need an extension.
+const TAG_REFERENCE_TYPES: &[(&str, TagGroup)] = &[
+    ("sound", TagGroup::Sound),
+    ("effect", TagGroup::Effect),
+    ("damage", TagGroup::DamageEffect),
+    ("looping_sound", TagGroup::SoundLooping),
+    ("animation_graph", TagGroup::ModelAnimations),
+    ("actor_variant", TagGroup::ActorVariant),
+    ("damage_effect", TagGroup::DamageEffect),
+    ("object_definition", TagGroup::Object)
+];
+
+/// Scenario globals are distinguished from the engine's globals by this bit in their index.
+const SCENARIO_GLOBAL_BIT: i16 = 0x8000u16 as i16;
+
+/// Parse the path a script gives for a value of a tag reference type.
+///
+/// The group is implied by the value type unless the path has an extension of a group it allows.
+///
+/// Returns `None` if the type is not a tag reference type.
+pub(crate) fn script_tag_reference_path(text: &str, value_type: &str) -> Option<RinghopperResult<TagPath>> {
+    let (_, group) = TAG_REFERENCE_TYPES.iter().find(|(t, _)| *t == value_type)?;
+    let path = match TagPath::split_str_path(text) {
+        Some((path, path_group)) if path_group.full_subgroup_tree().contains(group) => TagPath::new(path, path_group),
+        _ if *group == TagGroup::Object => Err(Error::Other(format!("`{text}` needs the extension of an object tag group"))),
+        _ => TagPath::new(text, *group)
+    };
+    Some(path)
+}
+
+const GAME_DIFFICULTIES: &[&str] = &["easy", "normal", "hard", "impossible"];
+
+fn can_convert(from: ScenarioScriptValueType, to: ScenarioScriptValueType) -> bool {
+    let from = from.to_str();
+    let to = to.to_str();
+
+    if from == to || to == "void" || to == "passthrough" || from == "passthrough" {
+        return true
+    }
+    if NUMBER_TYPES.contains(&from) && NUMBER_TYPES.contains(&to) {
+        return true
+    }
+
+    let is_object = OBJECT_TYPES.contains(&from);
+    let is_object_name = OBJECT_NAME_TYPES.contains(&from);
+    match to {
+        "object" => is_object || is_object_name,
+        "object_name" => is_object_name,
+        "object_list" => is_object || is_object_name,
+        _ => is_object_name && from.strip_suffix("_name") == Some(to)
+    }
+}
+
+struct GlobalDeclaration<'a> {
+    name: String32,
+    value_type: ScenarioScriptValueType,
+    expression: &'a Expression
+}
+
+struct ScriptDeclaration<'a> {
+    name: String32,
+    script_type: ScenarioScriptType,
+    return_type: ScenarioScriptValueType,
+    parameters: Vec<(String32, ScenarioScriptValueType)>,
+    body: &'a [Expression],
+    position: SourcePosition
+}
+
+struct ScriptCompiler<'a> {
+    file_names: &'a [String],
+    engine: &'a Engine,
+    max_nodes: usize,
+
+    /// Names of scenario objects, trigger volumes, etc. that can be referred to by scripts, keyed by type.
+    scenario_names: HashMap<&'static str, Vec<String>>,
+
+    /// Encounters (`encounter`) and squads (`encounter/squad`) that can be referred to as `ai` values.
+    ai_names: HashMap<String, i32>,
+
+    /// Tags referred to by scripts, which are added to the scenario's references.
+    tag_references: Vec<TagPath>,
+
+    globals: Vec<GlobalDeclaration<'a>>,
+    scripts: Vec<ScriptDeclaration<'a>>,
+    global_expressions: Vec<ID>,
+    script_expressions: Vec<ID>,
+
+    /// Parameters of the script currently being compiled.
+    locals: Vec<(String32, ScenarioScriptValueType)>,
+
+    nodes: Vec<ScenarioScriptNode>,
+    string_data: Vec<u8>,
+    string_offsets: HashMap<String, u32>
+}
+
+impl<'a> ScriptCompiler<'a> {
+    fn new(scenario: &Scenario, file_names: &'a [String], engine: &'a Engine, max_nodes: usize) -> Self {
+        let mut scenario_names = HashMap::new();
+
+        macro_rules! add_names {
+            ($($value_type:expr => $field:ident),*) => {
+                $(scenario_names.insert($value_type, scenario.$field.items.iter().map(|i| i.name.as_str().to_owned()).collect());)*
+            };
+        }
+
+        add_names!(
+            "trigger_volume" => trigger_volumes,
+            "cutscene_flag" => cutscene_flags,
+            "cutscene_camera_point" => cutscene_camera_points,
+            "cutscene_title" => cutscene_titles,
+            "cutscene_recording" => recorded_animations,
+            "device_group" => device_groups,
+            "ai_command_list" => command_lists,
+            "starting_profile" => player_starting_profile,
+            "conversation" => ai_conversations,
+            "object_name" => object_names
+        );
+
+        // An encounter is referred to by its index, and a squad by its index in the upper 16 bits (with the top bit
+        // set) and its encounter's index in the lower 16 bits.
+        let mut ai_names = HashMap::new();
+        for (encounter_index, encounter) in scenario.encounters.items.iter().enumerate() {
+            let encounter_name = encounter.name.as_str();
+            ai_names.insert(encounter_name.to_owned(), encounter_index as i32);
+            for (squad_index, squad) in encounter.squads.items.iter().enumerate() {
+                let value = (((0x8000 | squad_index) << 16) | encounter_index) as i32;
+                ai_names.insert(format!("{encounter_name}/{}", squad.name), value);
+            }
+        }
+
+        Self {
+            file_names,
+            engine,
+            max_nodes,
+            scenario_names,
+            ai_names,
+            tag_references: Vec::new(),
+            globals: Vec::new(),
+            scripts: Vec::new(),
+            global_expressions: Vec::new(),
+            script_expressions: Vec::new(),
+            locals: Vec::new(),
+            nodes: Vec::new(),
+            string_data: Vec::new(),
+            string_offsets: HashMap::new()
+        }
+    }
+
+    fn error(&self, position: SourcePosition, message: String) -> Error {
+        error_at(self.file_names, position, message)
+    }
+
+    fn expect_word<'b>(&self, expression: &'b Expression, what: &str) -> RinghopperResult<&'b str> {
+        expression.word().ok_or_else(|| self.error(expression.position(), format!("expected {what}")))
+    }
+
+    fn expect_name(&self, expression: &Expression, what: &str) -> RinghopperResult<String32> {
+        let name = self.expect_word(expression, what)?;
+        String32::from_str(name).map_err(|_| self.error(expression.position(), format!("`{name}` is longer than 31 characters")))
+    }
+
+    fn expect_type(&self, expression: &Expression) -> RinghopperResult<ScenarioScriptValueType> {
+        let name = self.expect_word(expression, "a type")?;
+        ScenarioScriptValueType::from_str(name)
+            .filter(|t| is_declarable_type(*t))
+            .ok_or_else(|| self.error(expression.position(), format!("`{name}` is not a valid type")))
+    }
+
+    fn find_global(&self, name: &str) -> Option<usize> {
+        self.globals.iter().position(|g| g.name.as_str() == name)
+    }
+
+    fn find_script(&self, name: &str) -> Option<usize> {
+        self.scripts.iter().position(|s| s.name.as_str() == name)
+    }
+
+    /// Gather all globals and scripts so they can be referred to before they are declared.
+    fn declare(&mut self, forms: &'a [Expression]) -> RinghopperResult<()> {
+        for form in forms {
+            let Expression::List { items, position } = form else { unreachable!() };
+            let position = *position;
+            let keyword = items
+                .first()
+                .and_then(Expression::word)
+                .ok_or_else(|| self.error(position, "expected `global` or `script`".to_owned()))?;
+
+            match keyword {
+                "global" => {
+                    let [_, value_type, name, expression] = items.as_slice() else {
+                        return Err(self.error(position, "expected (global <type> <name> <expression>)".to_owned()))
+                    };
+                    let value_type = self.expect_type(value_type)?;
+                    if value_type.to_str() == "void" {
+                        return Err(self.error(position, "globals cannot be void".to_owned()))
+                    }
+                    let name_position = name.position();
+                    let name = self.expect_name(name, "a global name")?;
+                    if self.find_global(name.as_str()).is_some() {
+                        return Err(self.error(name_position, format!("global `{name}` is already defined")))
+                    }
+                    self.globals.push(GlobalDeclaration { name, value_type, expression });
+                },
+                "script" => {
+                    let script = self.declare_script(items, position)?;
+                    if self.find_script(script.name.as_str()).is_some() {
+                        return Err(self.error(position, format!("script `{}` is already defined", script.name)))
+                    }
+                    self.scripts.push(script);
+                },
+                n => return Err(self.error(items[0].position(), format!("expected `global` or `script`, got `{n}`")))
+            }
+        }
+
+        Ok(())
+    }
+
+    fn declare_script(&self, items: &'a [Expression], position: SourcePosition) -> RinghopperResult<ScriptDeclaration<'a>> {
+        let usage = || self.error(position, "expected (script <type> [return type] <name> <expression(s)>)".to_owned());
+
+        let script_type_expression = items.get(1).ok_or_else(usage)?;
+        let script_type_name = self.expect_word(script_type_expression, "a script type")?;
+        let script_type = ScenarioScriptType::from_str(script_type_name)
+            .ok_or_else(|| self.error(script_type_expression.position(), format!("`{script_type_name}` is not a valid script type")))?;
+
+        let (return_type, name_index) = match script_type {
+            ScenarioScriptType::Static | ScenarioScriptType::Stub => (self.expect_type(items.get(2).ok_or_else(usage)?)?, 3),
+            _ => (value_type("void")?, 2)
+        };
+
+        let mut parameters = Vec::new();
+        let name = match items.get(name_index).ok_or_else(usage)? {
+            Expression::List { items: signature, position } => {
+                let (name, signature_parameters) = signature
+                    .split_first()
+                    .ok_or_else(|| self.error(*position, "expected a script name".to_owned()))?;
+
+                for parameter in signature_parameters {
+                    let Expression::List { items: parameter, position } = parameter else {
+                        return Err(self.error(parameter.position(), "expected (<type> <name>)".to_owned()))
+                    };
+                    let [value_type, name] = parameter.as_slice() else {
+                        return Err(self.error(*position, "expected (<type> <name>)".to_owned()))
+                    };
+                    let value_type = self.expect_type(value_type)?;
+                    let name_position = name.position();
+                    let name = self.expect_name(name, "a parameter name")?;
+                    if parameters.iter().any(|(n, _)| *n == name) {
+                        return Err(self.error(name_position, format!("parameter `{name}` is already defined")))
+                    }
+                    parameters.push((name, value_type));
+                }
+
+                self.expect_name(name, "a script name")?
+            },
+            name => self.expect_name(name, "a script name")?
+        };
+
+        let body = &items[name_index + 1..];
+        if body.is_empty() {
+            return Err(self.error(position, format!("script `{name}` has no expressions")))
+        }
+
+        Ok(ScriptDeclaration { name, script_type, return_type, parameters, body, position })
+    }
+
+    fn compile(&mut self) -> RinghopperResult<()> {
+        for i in 0..self.globals.len() {
+            let global = &self.globals[i];
+            let (expression, value_type) = (global.expression, global.value_type);
+            let (id, _) = self.compile_expression(expression, value_type)?;
+            self.global_expressions.push(id);
+        }
+
+        let begin = self.find_required_function("begin")?;
+        for i in 0..self.scripts.len() {
+            let script = &self.scripts[i];
+            let (body, return_type, position) = (script.body, script.return_type, script.position);
+            self.locals = script.parameters.clone();
+
+            let (arguments, _) = self.compile_block(body, return_type)?;
+            let id = self.emit_call(begin, "begin", false, return_type, &arguments, position)?;
+            self.script_expressions.push(id);
+        }
+
+        self.locals.clear();
+        Ok(())
+    }
+
+    /// Get the opcode of a function the compiler itself emits.
+    fn find_required_function(&self, name: &str) -> RinghopperResult<u16> {
+        find_script_function(self.engine, name)
+            .map(|(opcode, _)| opcode)
+            .ok_or_else(|| Error::Other(format!("the target engine has no `{name}` function")))
+    }
+
+    fn finish(self, scenario: &mut Scenario) -> RinghopperResult<()> {
+        scenario.globals.items.clear();
+        for (global, id) in self.globals.iter().zip(self.global_expressions.iter()) {
+            scenario.globals.items.push(Default::default());
+            let item = scenario.globals.items.last_mut().unwrap();
+            item.name = global.name;
+            item._type = global.value_type;
+            item.initialization_expression_index = *id;
+        }
+
+        scenario.scripts.items.clear();
+        for (script, id) in self.scripts.iter().zip(self.script_expressions.iter()) {
+            scenario.scripts.items.push(Default::default());
+            let item = scenario.scripts.items.last_mut().unwrap();
+            item.name = script.name;
+            item.script_type = script.script_type;
+            item.return_type = script.return_type;
+            item.root_expression_index = *id;
+            item.parameters.items.clear();
+            for (name, value_type) in &script.parameters {
+                item.parameters.items.push(Default::default());
+                let parameter = item.parameters.items.last_mut().unwrap();
+                parameter.name = *name;
+                parameter.return_type = *value_type;
+            }
+        }
+
+        for path in &self.tag_references {
+            if !scenario.references.items.iter().any(|r| r.reference.path() == Some(path)) {
+                scenario.references.items.push(Default::default());
+                scenario.references.items.last_mut().unwrap().reference = path.clone().into();
+            }
+        }
+
+        // Keep whatever else was in the table header if it was valid.
+        let existing_data = &scenario.script_syntax_data.bytes;
+        let mut table = ScenarioScriptNodeTable::read::<BigEndian>(existing_data, 0, existing_data.len()).unwrap_or_default();
+        let table_size = ScenarioScriptNodeTable::simple_size();
+        let node_size = ScenarioScriptNode::simple_size();
+        table.size = self.nodes.len() as _;
+        table.maximum_count = self.max_nodes as _;
+        table.element_size = node_size as _;
+
+        let mut syntax_data = vec![0u8; table_size + node_size * self.max_nodes];
+        table.write::<BigEndian>(&mut syntax_data, 0, table_size)?;
+        for (i, node) in self.nodes.iter().enumerate() {
+            let offset = table_size + i * node_size;
+            node.write::<BigEndian>(&mut syntax_data, offset, offset + node_size)?;
+        }
+
+        scenario.script_syntax_data = Data::new(syntax_data);
+        scenario.script_string_data = Data::new(self.string_data);
+
+        Ok(())
+    }
+
+    fn string_offset(&mut self, string: &str) -> u32 {
+        if let Some(offset) = self.string_offsets.get(string) {
+            return *offset
+        }
+
+        let offset = self.string_data.len() as u32;
+        self.string_data.extend_from_slice(string.as_bytes());
+        self.string_data.push(0);
+        self.string_offsets.insert(string.to_owned(), offset);
+        offset
+    }
+
+    fn push_node(&mut self, mut node: ScenarioScriptNode, position: SourcePosition) -> RinghopperResult<ID> {
+        let index = self.nodes.len();
+        if index >= self.max_nodes {
+            return Err(self.error(position, format!("script node limit exceeded (the target engine allows at most {} nodes)", self.max_nodes)))
+        }
+
+        let id = ID::new(Some(index as u16), IDType::ScriptNode.salt());
+        node.salt = (id.as_u32() >> 16) as _;
+        self.nodes.push(node);
+        Ok(id)
+    }
+
+    fn link_nodes(&mut self, ids: &[ID]) {
+        for pair in ids.windows(2) {
+            self.nodes[pair[0].index().unwrap() as usize].next_node = pair[1];
+        }
+    }
+
+    /// Emit a function or script call with its already-compiled arguments.
+    fn emit_call(
+        &mut self,
+        index: u16,
+        name: &str,
+        is_script_call: bool,
+        return_type: ScenarioScriptValueType,
+        arguments: &[ID],
+        position: SourcePosition
+    ) -> RinghopperResult<ID> {
+        let string_offset = self.string_offset(name);
+        self.link_nodes(arguments);
+
+        let mut name_node = ScenarioScriptNode {
+            _type: value_type("function_name")?,
+            index_union: index as _,
+            string_offset,
+            next_node: arguments.first().copied().unwrap_or_default(),
+            ..Default::default()
+        };
+        name_node.flags.is_primitive = true;
+        let name_id = self.push_node(name_node, position)?;
+
+        let mut call_node = ScenarioScriptNode {
+            _type: return_type,
+            index_union: index as _,
+            string_offset,
+            data: name_id.into(),
+            ..Default::default()
+        };
+        call_node.flags.is_script_call = is_script_call;
+        self.push_node(call_node, position)
+    }
+
+    fn check_conversion(&self, from: ScenarioScriptValueType, to: ScenarioScriptValueType, position: SourcePosition) -> RinghopperResult<()> {
+        if can_convert(from, to) {
+            Ok(())
+        }
+        else {
+            Err(self.error(position, format!("expected {to}, got {from}")))
+        }
+    }
+
+    fn check_argument_count(&self, name: &str, count: usize, range: RangeInclusive<usize>, position: SourcePosition) -> RinghopperResult<()> {
+        let (min, max) = (*range.start(), *range.end());
+        let expected = if range.contains(&count) {
+            return Ok(())
+        }
+        else if min == max {
+            format!("{min}")
+        }
+        else if max == usize::MAX {
+            format!("at least {min}")
+        }
+        else {
+            format!("{min} to {max}")
+        };
+        Err(self.error(position, format!("`{name}` takes {expected} argument(s), but {count} were given")))
+    }
+
+    /// Compile an expression, returning its node and the type it evaluates to.
+    fn compile_expression(&mut self, expression: &Expression, expected: ScenarioScriptValueType) -> RinghopperResult<(ID, ScenarioScriptValueType)> {
+        match expression {
+            Expression::Atom { text, quoted, position } => self.compile_atom(text, *quoted, expected, *position),
+            Expression::List { items, position } => {
+                let (name, arguments) = items
+                    .split_first()
+                    .ok_or_else(|| self.error(*position, "expected a function call, got `()`".to_owned()))?;
+                let name = self.expect_word(name, "a function or script name")?;
+
+                if let Some((opcode, function)) = find_script_function(self.engine, name) {
+                    self.compile_function_call(opcode, name, function.kind, function.return_type, arguments, expected, *position)
+                }
+                else if let Some(script_index) = self.find_script(name) {
+                    self.compile_script_call(script_index, arguments, expected, *position)
+                }
+                else {
+                    Err(self.error(*position, format!("unknown function or script `{name}`")))
+                }
+            }
+        }
+    }
+
+    /// Compile a block of expressions where only the last one's value is used.
+    fn compile_block(&mut self, expressions: &[Expression], expected: ScenarioScriptValueType) -> RinghopperResult<(Vec<ID>, ScenarioScriptValueType)> {
+        let mut ids = Vec::with_capacity(expressions.len());
+        let mut result_type = expected;
+        for (i, expression) in expressions.iter().enumerate() {
+            let is_last = i + 1 == expressions.len();
+            let (id, value_type) = self.compile_expression(expression, if is_last { expected } else { value_type("void")? })?;
+            if is_last {
+                result_type = value_type;
+            }
+            ids.push(id);
+        }
+        Ok((ids, result_type))
+    }
+
+    fn compile_atom(&mut self, text: &str, quoted: bool, expected: ScenarioScriptValueType, position: SourcePosition) -> RinghopperResult<(ID, ScenarioScriptValueType)> {
+        let reference = if quoted {
+            None
+        }
+        else if let Some(index) = self.locals.iter().position(|(name, _)| name.as_str() == text) {
+            Some((index as i16, self.locals[index].1, true))
+        }
+        else if let Some(index) = self.find_global(text) {
+            Some((index as i16 | SCENARIO_GLOBAL_BIT, self.globals[index].value_type, false))
+        }
+        else if let Some((index, global)) = find_script_global(self.engine, text) {
+            let value_type = value_type(global.value_type).map_err(|e| self.error(position, e.to_string()))?;
+            Some((index as i16, value_type, false))
+        }
+        else {
+            None
+        };
+
+        if let Some((index, value_type, is_local)) = reference {
+            self.check_conversion(value_type, expected, position)?;
+
+            let node_type = if is_unresolved_type(expected) { value_type } else { expected };
+            let mut node = ScenarioScriptNode {
+                _type: node_type,
+                index_union: node_type as _,
+                string_offset: self.string_offset(text),
+                data: index.into(),
+                ..Default::default()
+            };
+            node.flags.is_primitive = true;
+            node.flags.is_local_variable = is_local;
+            node.flags.is_global = !is_local;
+            return Ok((self.push_node(node, position)?, value_type))
+        }
+
+        let value_type = if is_unresolved_type(expected) {
+            infer_literal_type(text, quoted)
+                .ok_or_else(|| self.error(position, format!("cannot determine the type of `{text}`")))?
+        }
+        else {
+            expected
+        };
+
+        let data = self.parse_literal(text, value_type, position)?;
+        let mut node = ScenarioScriptNode {
+            _type: value_type,
+            index_union: value_type as _,
+            string_offset: self.string_offset(text),
+            data,
+            ..Default::default()
+        };
+        node.flags.is_primitive = true;
+        Ok((self.push_node(node, position)?, value_type))
+    }
+
+    fn parse_literal(&mut self, text: &str, value_type: ScenarioScriptValueType, position: SourcePosition) -> RinghopperResult<ScenarioScriptNodeValue> {
+        let type_name = value_type.to_str();
+
+        // Tags are referred to by ID in cache files, so only the path can be checked here.
+        if let Some(path) = script_tag_reference_path(text, type_name) {
+            let path = path.map_err(|e| self.error(position, format!("`{text}` is not a valid {type_name}: {e}")))?;
+            if !self.tag_references.contains(&path) {
+                self.tag_references.push(path);
+            }
+            return Ok(ID::null().into())
+        }
+
+        let invalid = || self.error(position, format!("`{text}` is not a valid {type_name}"));
+
+        let find_index = |names: &[&str]| names
+            .iter()
+            .position(|n| *n == text)
+            .map(|i| ScenarioScriptNodeValue::from(i as i16))
+            .ok_or_else(invalid);
+
+        match type_name {
+            "boolean" => match text {
+                "true" | "on" | "1" => Ok(true.into()),
+                "false" | "off" | "0" => Ok(false.into()),
+                _ => Err(invalid())
+            },
+            "real" => text.parse::<f32>().map(Into::into).map_err(|_| invalid()),
+            "short" => text.parse::<i16>().map(Into::into).map_err(|_| invalid()),
+            "long" => text.parse::<i32>().map(Into::into).map_err(|_| invalid()),
+            "string" => Ok(ScenarioScriptNodeValue::default()),
+            "script" => self.find_script(text).map(|i| ScenarioScriptNodeValue::from(i as i16)).ok_or_else(invalid),
+            "game_difficulty" => find_index(GAME_DIFFICULTIES),
+            _ if text == "none" => Ok((-1i16).into()),
+            "ai" => self
+                .ai_names
+                .get(text)
+                .map(|v| ScenarioScriptNodeValue::from(*v))
+                .ok_or_else(|| self.error(position, format!("no encounter or squad named `{text}` exists in the scenario"))),
+            t => {
+                let key = if OBJECT_NAME_TYPES.contains(&t) { "object_name" } else { t };
+                let names = self
+                    .scenario_names
+                    .get(key)
+                    .ok_or_else(|| self.error(position, format!("values of type {t} cannot be compiled")))?;
+                names
+                    .iter()
+                    .position(|n| n == text)
+                    .map(|i| ScenarioScriptNodeValue::from(i as i16))
+                    .ok_or_else(|| self.error(position, format!("no {t} named `{text}` exists in the scenario")))
+            }
+        }
+    }
+
+    fn compile_script_call(
+        &mut self,
+        script_index: usize,
+        arguments: &[Expression],
+        expected: ScenarioScriptValueType,
+        position: SourcePosition
+    ) -> RinghopperResult<(ID, ScenarioScriptValueType)> {
+        let script = &self.scripts[script_index];
+        let name = script.name;
+        let return_type = script.return_type;
+        let parameters: Vec<ScenarioScriptValueType> = script.parameters.iter().map(|(_, t)| *t).collect();
+
+        if !matches!(script.script_type, ScenarioScriptType::Static | ScenarioScriptType::Stub) {
+            return Err(self.error(position, format!("script `{name}` is not static and cannot be called")))
+        }
+        if parameters.len() != arguments.len() {
+            return Err(self.error(position, format!("script `{name}` takes {} argument(s), but {} were given", parameters.len(), arguments.len())))
+        }
+        self.check_conversion(return_type, expected, position)?;
+
+        let mut ids = Vec::with_capacity(arguments.len());
+        for (argument, parameter_type) in arguments.iter().zip(parameters) {
+            ids.push(self.compile_expression(argument, parameter_type)?.0);
+        }
+
+        let node_type = if is_unresolved_type(expected) { return_type } else { expected };
+        Ok((self.emit_call(script_index as u16, name.as_str(), true, node_type, &ids, position)?, return_type))
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn compile_function_call(
+        &mut self,
+        opcode: u16,
+        name: &str,
+        kind: ScriptFunctionKind,
+        return_type: &str,
+        arguments: &[Expression],
+        expected: ScenarioScriptValueType,
+        position: SourcePosition
+    ) -> RinghopperResult<(ID, ScenarioScriptValueType)> {
+        let argument_count = arguments.len();
+        macro_rules! check_count {
+            ($range:expr) => {
+                self.check_argument_count(name, argument_count, $range, position)?
+            };
+        }
+
+        let mut result_type = value_type(return_type).map_err(|e| self.error(position, e.to_string()))?;
+        let mut ids = Vec::with_capacity(argument_count);
+
+        macro_rules! compile_arguments {
+            ($($value_type:expr),*) => {{
+                let types = [$(value_type($value_type)?),*];
+                for (argument, value_type) in arguments.iter().zip(types) {
+                    ids.push(self.compile_expression(argument, value_type)?.0);
+                }
+            }};
+        }
+
+        match kind {
+            ScriptFunctionKind::Normal(parameters) => {
+                check_count!(parameters.len()..=parameters.len());
+                for (argument, parameter) in arguments.iter().zip(parameters) {
+                    let parameter = value_type(parameter).map_err(|e| self.error(argument.position(), e.to_string()))?;
+                    ids.push(self.compile_expression(argument, parameter)?.0);
+                }
+            },
+            ScriptFunctionKind::Begin => {
+                check_count!(1..=usize::MAX);
+                let (block, block_type) = self.compile_block(arguments, expected)?;
+                ids = block;
+                result_type = block_type;
+            },
+            ScriptFunctionKind::If => {
+                check_count!(2..=3);
+                ids.push(self.compile_expression(&arguments[0], value_type("boolean")?)?.0);
+                let (then_id, then_type) = self.compile_expression(&arguments[1], expected)?;
+                ids.push(then_id);
+                if let Some(otherwise) = arguments.get(2) {
+                    ids.push(self.compile_expression(otherwise, if is_unresolved_type(expected) { then_type } else { expected })?.0);
+                }
+                result_type = then_type;
+            },
+            ScriptFunctionKind::Cond => return self.compile_cond(arguments, expected, position),
+            ScriptFunctionKind::Set => {
+                check_count!(2..=2);
+                let global_name = self.expect_word(&arguments[0], "a global name")?;
+                let global_type = match self.find_global(global_name) {
+                    Some(global) => self.globals[global].value_type,
+                    None => find_script_global(self.engine, global_name)
+                        .and_then(|(_, global)| value_type(global.value_type).ok())
+                        .ok_or_else(|| self.error(arguments[0].position(), format!("unknown global `{global_name}`")))?
+                };
+                ids.push(self.compile_expression(&arguments[0], global_type)?.0);
+                ids.push(self.compile_expression(&arguments[1], global_type)?.0);
+                result_type = global_type;
+            },
+            ScriptFunctionKind::Logic => {
+                check_count!(1..=usize::MAX);
+                for argument in arguments {
+                    ids.push(self.compile_expression(argument, value_type("boolean")?)?.0);
+                }
+            },
+            ScriptFunctionKind::Arithmetic => {
+                check_count!(1..=usize::MAX);
+                for argument in arguments {
+                    ids.push(self.compile_expression(argument, value_type("real")?)?.0);
+                }
+            },
+            ScriptFunctionKind::ArithmeticPair | ScriptFunctionKind::Inequality => {
+                check_count!(2..=2);
+                compile_arguments!("real", "real");
+            },
+            ScriptFunctionKind::Equality => {
+                check_count!(2..=2);
+                ids = self.compile_equality(&arguments[0], &arguments[1])?;
+            },
+            ScriptFunctionKind::Sleep => {
+                check_count!(1..=2);
+                compile_arguments!("short", "script");
+            },
+            ScriptFunctionKind::SleepUntil => {
+                check_count!(1..=3);
+                compile_arguments!("boolean", "short", "short");
+            },
+            ScriptFunctionKind::Wake => {
+                check_count!(1..=1);
+                compile_arguments!("script");
+            },
+            ScriptFunctionKind::Inspect => {
+                check_count!(1..=1);
+                compile_arguments!("passthrough");
+            }
+        }
+
+        self.check_conversion(result_type, expected, position)?;
+        let node_type = if is_unresolved_type(expected) { result_type } else { expected };
+        Ok((self.emit_call(opcode, name, false, node_type, &ids, position)?, result_type))
+    }
+
+    /// Compile both sides of a comparison, inferring the type from whichever side it can be determined.
+    fn compile_equality(&mut self, a: &Expression, b: &Expression) -> RinghopperResult<Vec<ID>> {
+        let node_count = self.nodes.len();
+        let string_data_size = self.string_data.len();
+        let tag_reference_count = self.tag_references.len();
+
+        match self.compile_expression(a, value_type("passthrough")?) {
+            Ok((a_id, a_type)) => {
+                let (b_id, _) = self.compile_expression(b, a_type)?;
+                Ok(vec![a_id, b_id])
+            },
+            Err(e) => {
+                // Roll back anything emitted by the failed attempt.
+                self.nodes.truncate(node_count);
+                self.string_data.truncate(string_data_size);
+                self.string_offsets.retain(|_, offset| (*offset as usize) < string_data_size);
+                self.tag_references.truncate(tag_reference_count);
+
+                let Ok((_, b_type)) = self.compile_expression(b, value_type("passthrough")?) else {
+                    return Err(e)
+                };
+                self.nodes.truncate(node_count);
+                self.string_data.truncate(string_data_size);
+                self.string_offsets.retain(|_, offset| (*offset as usize) < string_data_size);
+                self.tag_references.truncate(tag_reference_count);
+
+                let (a_id, _) = self.compile_expression(a, b_type)?;
+                let (b_id, _) = self.compile_expression(b, b_type)?;
+                Ok(vec![a_id, b_id])
+            }
+        }
+    }
+
+    /// Compile `cond` into nested `if` calls.
+    fn compile_cond(&mut self, clauses: &[Expression], expected: ScenarioScriptValueType, position: SourcePosition) -> RinghopperResult<(ID, ScenarioScriptValueType)> {
+        if clauses.is_empty() {
+            self.check_argument_count("cond", 0, 1..=usize::MAX, position)?;
+        }
+
+        let if_opcode = self.find_required_function("if")?;
+        let begin_opcode = self.find_required_function("begin")?;
+
+        let mut compiled = Vec::with_capacity(clauses.len());
+        let mut result_type = expected;
+        for clause in clauses {
+            let Expression::List { items, position } = clause else {
+                return Err(self.error(clause.position(), "expected (<condition> <expression(s)>)".to_owned()))
+            };
+            let [condition, body @ ..] = items.as_slice() else {
+                return Err(self.error(*position, "expected (<condition> <expression(s)>)".to_owned()))
+            };
+            if body.is_empty() {
+                return Err(self.error(*position, "expected (<condition> <expression(s)>)".to_owned()))
+            }
+
+            let (condition, _) = self.compile_expression(condition, value_type("boolean")?)?;
+            let (body_ids, body_type) = self.compile_block(body, result_type)?;
+            if is_unresolved_type(result_type) {
+                result_type = body_type;
+            }
+            let body = self.emit_call(begin_opcode, "begin", false, body_type, &body_ids, *position)?;
+            compiled.push((condition, body, *position));
+        }
+
+        let node_type = if is_unresolved_type(expected) { result_type } else { expected };
+        let mut otherwise: Option<ID> = None;
+        for (condition, body, position) in compiled.into_iter().rev() {
+            let mut arguments = vec![condition, body];
+            arguments.extend(otherwise);
+            otherwise = Some(self.emit_call(if_opcode, "if", false, node_type, &arguments, position)?);
+        }
+
+        Ok((otherwise.unwrap(), result_type))
+    }
+}
+
+/// Guess the type of a literal when nothing else determines it.
+fn infer_literal_type(text: &str, quoted: bool) -> Option<ScenarioScriptValueType> {
+    let name = if quoted {
+        "string"
+    }
+    else if matches!(text, "true" | "false" | "on" | "off") {
+        "boolean"
+    }
+    else if text.parse::<i16>().is_ok() {
+        "short"
+    }
+    else if text.parse::<i32>().is_ok() {
+        "long"
+    }
+    else if text.parse::<f32>().is_ok() {
+        "real"
+    }
+    else {
+        return None
+    };
+    value_type(name).ok()
+}
@@ -0,0 +1,113 @@
+use primitives::engine::{Engine, EngineScriptGlobal};
+
+/// How a function's arguments are checked and compiled.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub(super) enum ScriptFunctionKind {
+    /// Arguments are checked against the listed parameter types.
+    Normal(&'static [&'static str]),
+
+    /// Any number of expressions; the last one is the result.
+    Begin,
+
+    /// `(if <boolean> <then> [else])`
+    If,
+
+    /// `(cond (<boolean> <expression(s)>) ...)`
+    Cond,
+
+    /// `(set <global> <value>)`
+    Set,
+
+    /// One or more booleans.
+    Logic,
+
+    /// One or more numbers.
+    Arithmetic,
+
+    /// Exactly two numbers.
+    ArithmeticPair,
+
+    /// Two values of the same type.
+    Equality,
+
+    /// Two numbers.
+    Inequality,
+
+    /// `(sleep <short> [script])`
+    Sleep,
+
+    /// `(sleep_until <boolean> [short] [short])`
+    SleepUntil,
+
+    /// `(wake <script>)`
+    Wake,
+
+    /// `(inspect <expression>)`
+    Inspect
+}
+
+/// Built-in HaloScript function.
+pub(super) struct ScriptFunction {
+    /// Name of the function.
+    pub name: &'static str,
+
+    /// Return type, or `passthrough` if it is the type of its arguments.
+    pub return_type: &'static str,
+
+    /// How the function's arguments are compiled.
+    pub kind: ScriptFunctionKind
+}
+
+/// Functions whose arguments are not simply checked against their parameter types.
+const SPECIAL_FORMS: &[(&str, ScriptFunctionKind)] = &[
+    ("begin", ScriptFunctionKind::Begin),
+    ("begin_random", ScriptFunctionKind::Begin),
+    ("if", ScriptFunctionKind::If),
+    ("cond", ScriptFunctionKind::Cond),
+    ("set", ScriptFunctionKind::Set),
+    ("and", ScriptFunctionKind::Logic),
+    ("or", ScriptFunctionKind::Logic),
+    ("+", ScriptFunctionKind::Arithmetic),
+    ("-", ScriptFunctionKind::ArithmeticPair),
+    ("*", ScriptFunctionKind::Arithmetic),
+    ("/", ScriptFunctionKind::ArithmeticPair),
+    ("min", ScriptFunctionKind::Arithmetic),
+    ("max", ScriptFunctionKind::Arithmetic),
+    ("=", ScriptFunctionKind::Equality),
+    ("!=", ScriptFunctionKind::Equality),
+    (">", ScriptFunctionKind::Inequality),
+    ("<", ScriptFunctionKind::Inequality),
+    (">=", ScriptFunctionKind::Inequality),
+    ("<=", ScriptFunctionKind::Inequality),
+    ("sleep", ScriptFunctionKind::Sleep),
+    ("sleep_until", ScriptFunctionKind::SleepUntil),
+    ("wake", ScriptFunctionKind::Wake),
+    ("inspect", ScriptFunctionKind::Inspect)
+];
+
+/// Find one of the engine's built-in functions by name, returning its opcode and definition.
+pub(super) fn find_script_function(engine: &Engine, name: &str) -> Option<(u16, ScriptFunction)> {
+    let (opcode, function) = engine
+        .script_functions
+        .iter()
+        .enumerate()
+        .find(|(_, f)| f.name == name)?;
+
+    let kind = SPECIAL_FORMS
+        .iter()
+        .find(|(n, _)| *n == name)
+        .map(|(_, kind)| *kind)
+        .unwrap_or(ScriptFunctionKind::Normal(function.parameters));
+
+    Some((opcode as u16, ScriptFunction { name: function.name, return_type: function.return_type, kind }))
+}
+
+/// Find one of the engine's built-in globals by name, returning its index and definition.
+pub(super) fn find_script_global(engine: &Engine, name: &str) -> Option<(u16, &'static EngineScriptGlobal)> {
+    engine
+        .script_globals
+        .iter()
+        .enumerate()
+        .find(|(_, g)| g.name == name)
+        .map(|(i, g)| (i as u16, g))
+}
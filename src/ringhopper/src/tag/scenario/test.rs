@@ -0,0 +1,149 @@
+use ringhopper_engines::{Engine, ALL_SUPPORTED_ENGINES};
+use ringhopper_structs::{Scenario, ScenarioScriptType, ScenarioSourceFile};
+use primitives::primitive::{Data, String32, TagPath};
+use super::*;
+
+const SOURCE: &str = r#"
+;* test scripts *;
+(global short counter 0)
+(global boolean done false)
+
+(script static real (scale (real value) (short times))
+    (* value times))
+
+(script startup main
+    (set counter (+ counter 1)) ; comments are ignored
+    (sleep_until (>= counter 5) 30)
+    (if (not done)
+        (print "not done (yet)"))
+    (cond
+        ((= counter 5) (set done true))
+        ((> (scale 1.5 counter) 10.5) (set done false)))
+)
+"#;
+
+fn engine(max_script_nodes: u64) -> Engine {
+    Engine { max_script_nodes, ..*ALL_SUPPORTED_ENGINES.iter().find(|e| e.build_target).unwrap() }
+}
+
+fn scenario_with_source(source: &str) -> Scenario {
+    let mut scenario = Scenario::default();
+    scenario.source_files.items.push(ScenarioSourceFile {
+        name: String32::from_str("test").unwrap(),
+        source: Data::new(source.as_bytes().to_vec())
+    });
+    scenario
+}
+
+fn compile_error_with_engine(source: &str, engine: &Engine) -> String {
+    let mut scenario = scenario_with_source(source);
+    compile_scripts(&mut scenario, engine).unwrap_err().to_string()
+}
+
+fn compile_error(source: &str) -> String {
+    compile_error_with_engine(source, &engine(19001))
+}
+
+#[test]
+fn compiles_scripts() {
+    let mut scenario = scenario_with_source(SOURCE);
+    compile_scripts(&mut scenario, &engine(19001)).unwrap();
+
+    let globals: Vec<&str> = scenario.globals.items.iter().map(|g| g.name.as_str()).collect();
+    assert_eq!(vec!["counter", "done"], globals);
+
+    let scripts = &scenario.scripts.items;
+    assert_eq!(2, scripts.len());
+    assert_eq!(ScenarioScriptType::Static, scripts[0].script_type);
+    assert_eq!(2, scripts[0].parameters.items.len());
+    assert_eq!("main", scripts[1].name.as_str());
+
+    let node_size = ScenarioScriptNode::simple_size();
+    assert_eq!(ScenarioScriptNodeTable::simple_size() + node_size * 19001, scenario.script_syntax_data.bytes.len());
+
+    // Decompiling and recompiling should produce identical nodes.
+    let syntax_data = scenario.script_syntax_data.bytes.clone();
+    decompile_scripts(&mut scenario, "test").unwrap();
+    compile_scripts(&mut scenario, &engine(19001)).unwrap();
+    assert_eq!(syntax_data, scenario.script_syntax_data.bytes);
+}
+
+#[test]
+fn reports_error_positions() {
+    assert!(compile_error("(script startup a\n    (foo))").starts_with("test.hsc:2:5: unknown function or script `foo`"));
+    assert!(compile_error("(global short a \"b\")").starts_with("test.hsc:1:17: `b` is not a valid short"));
+    assert!(compile_error("(script startup a\n    (sleep 1)").starts_with("test.hsc:1:1: unclosed `(`"));
+    assert!(compile_error("(global boolean a (+ 1 2))").starts_with("test.hsc:1:19: expected boolean, got real"));
+    assert!(compile_error("(global short a 1)\n(global real a 1)").starts_with("test.hsc:2:14: global `a` is already defined"));
+}
+
+#[test]
+fn respects_max_script_nodes() {
+    let source = "(script startup a (sleep 1))";
+
+    // begin + sleep (each with a function name node) + 1
+    let mut scenario = scenario_with_source(source);
+    compile_scripts(&mut scenario, &engine(5)).unwrap();
+
+    let error = compile_error_with_engine(source, &engine(4));
+    assert!(error.contains("script node limit exceeded"), "{error}");
+}
+
+/// Mission script in the style of the stock campaign scripts, using engine functions and globals.
+const STOCK_SOURCE: &str = r#"
+(global boolean mission_started false)
+
+(script startup mission_start
+    (fade_out 0 0 0 0)
+    (set cheat_deathless_player false)
+    (ai_place first_encounter)
+    (object_create door_control)
+    (sleep_until (volume_test_objects hallway (players)) 15)
+    (sound_impulse_start sound\dialog\a10\intro none 1)
+    (if (= (game_difficulty_get) impossible)
+        (ai_place first_encounter/left_squad))
+    (set mission_started true)
+    (fade_in 0 0 0 30))
+
+(script continuous count_enemies
+    (sleep_until (<= (ai_living_count first_encounter) 0))
+    (game_won))
+"#;
+
+fn stock_scenario(source: &str) -> Scenario {
+    let mut scenario = scenario_with_source(source);
+    scenario.trigger_volumes.items.push(Default::default());
+    scenario.trigger_volumes.items[0].name = String32::from_str("hallway").unwrap();
+    scenario.object_names.items.push(Default::default());
+    scenario.object_names.items[0].name = String32::from_str("door_control").unwrap();
+    scenario.encounters.items.push(Default::default());
+    scenario.encounters.items[0].name = String32::from_str("first_encounter").unwrap();
+    scenario.encounters.items[0].squads.items.push(Default::default());
+    scenario.encounters.items[0].squads.items[0].name = String32::from_str("left_squad").unwrap();
+    scenario
+}
+
+#[test]
+fn compiles_stock_script() {
+    let mut scenario = stock_scenario(STOCK_SOURCE);
+    let engine = engine(19001);
+    compile_scripts(&mut scenario, &engine).unwrap();
+
+    // Engine globals are not declared in the scenario.
+    let globals: Vec<&str> = scenario.globals.items.iter().map(|g| g.name.as_str()).collect();
+    assert_eq!(vec!["mission_started"], globals);
+    assert_eq!(2, scenario.scripts.items.len());
+
+    // Tags used by the scripts are referenced by the scenario so they are built into the map.
+    let sound = TagPath::from_path("sound\\dialog\\a10\\intro.sound").unwrap();
+    let references: Vec<_> = scenario.references.items.iter().filter_map(|r| r.reference.path()).collect();
+    assert_eq!(vec![&sound], references);
+
+    // Compiling again does not duplicate the reference.
+    compile_scripts(&mut scenario, &engine).unwrap();
+    assert_eq!(1, scenario.references.items.len());
+
+    let mut scenario = stock_scenario(&STOCK_SOURCE.replace("left_squad", "right_squad"));
+    let error = compile_scripts(&mut scenario, &engine).unwrap_err().to_string();
+    assert!(error.contains("no encounter or squad named `first_encounter/right_squad`"), "{error}");
+}
@@ -3,6 +3,12 @@ use primitives::byteorder::LittleEndian;
 use primitives::error::{Error, OverflowCheck, RinghopperResult};
 use primitives::parse::{RawStructIteratorInfallible, SimpleTagData};
 
+#[cfg(test)]
+mod test;
+mod compile;
+
+pub use compile::*;
+
 /// Detect if compressed or uncompressed vertices are missing and attempt to restore them.
 pub fn recompress_scenario_structure_bsp_vertices(bsp: &mut ScenarioStructureBSP) -> RinghopperResult<bool> {
     let mut fixed = false;
@@ -0,0 +1,729 @@
+use std::collections::HashMap;
+use definitions::{ModelCollisionGeometryBSPSurfaceFlags, ModelVertexUncompressed, ScenarioStructureBSP, ScenarioStructureBSPCluster, ScenarioStructureBSPClusterPortal, ScenarioStructureBSPClusterPortalIndex, ScenarioStructureBSPClusterPortalVertex, ScenarioStructureBSPCollisionMaterial, ScenarioStructureBSPFogPalette, ScenarioStructureBSPFogPlane, ScenarioStructureBSPFogPlaneVertex, ScenarioStructureBSPFogRegion, ScenarioStructureBSPLeaf, ScenarioStructureBSPLightmap, ScenarioStructureBSPMaterial, ScenarioStructureBSPMaterialUncompressedRenderedVertex, ScenarioStructureBSPSubcluster, ScenarioStructureBSPSubclusterSurfaceIndex, ScenarioStructureBSPSurface, ScenarioStructureBSPWeatherPolyhedron, ScenarioStructureBSPWeatherPolyhedronPlane};
+use primitives::byteorder::LittleEndian;
+use primitives::error::{Error, RinghopperResult};
+use primitives::parse::SimpleTagData;
+use primitives::primitive::{BSPVertexData, Bounds, Plane3D, Reflexive, String32, TagGroup, TagReference, Vector, Vector2D, Vector3D};
+use crate::data::jms::{JMSMaterialProperties, JMS, JMS_UNITS_PER_WORLD_UNIT};
+use crate::tag::collision_bsp::{compile_collision_bsp, find_leaf, find_leaf_connections, find_or_add_plane, is_degenerate_triangle, jms_position, planes_match, CollisionTriangle, CompiledCollisionBSP, LeafConnection};
+use crate::tag::model::{calculate_tangents, cross};
+use crate::tag::result::{Diagnostic, TagResult};
+use super::recompress_scenario_structure_bsp_vertices;
+
+/// Distance, in world units, from a triangle to look for the leaf it is in.
+const LEAF_SEARCH_OFFSET: f32 = 0.01;
+
+/// Compile JMS level geometry into a scenario_structure_bsp tag.
+///
+/// Collision, clusters, cluster portals, fog planes, weather polyhedra, and rendered surfaces are replaced, and
+/// anything else (such as sound environments and lightmap settings) is kept. Lightmaps are not generated.
+///
+/// `find_shader` is called once for each unique material name (without symbols) to get a reference to its shader tag.
+/// If it returns a reference to a fog tag, triangles with the material make fog planes instead.
+///
+/// Materials with these names are special:
+/// - `+sky` - invisible collision; clusters it touches render the first sky of the scenario (`+sky1` for the second
+///   sky, etc.)
+/// - `+portal` and `+exactportal` - divides the level into clusters
+/// - `+weatherpoly` - convex volume where weather does not appear
+/// - `+seamsealer` - invisible collision for sealing the level
+///
+/// Problems with the level geometry, such as degenerate triangles, open edges, and leaks, are returned as errors in
+/// the [`TagResult`]. The tag should not be saved if there are any errors.
+pub fn compile_scenario_structure_bsp<F: FnMut(&str) -> RinghopperResult<TagReference>>(tag: &mut ScenarioStructureBSP, jms: &JMS, mut find_shader: F) -> RinghopperResult<TagResult> {
+    jms.check_indices()?;
+
+    let mut result = TagResult::default();
+    let mut shaders: HashMap<String, TagReference> = HashMap::new();
+    let mut materials = Vec::with_capacity(jms.materials.len());
+    for material in &jms.materials {
+        let name = material.shader_name().to_ascii_lowercase();
+        let kind = match name.as_str() {
+            "+portal" | "+exactportal" => MaterialKind::Portal,
+            "+weatherpoly" => MaterialKind::WeatherPolyhedron,
+            "+seamsealer" => MaterialKind::SeamSealer,
+            n if n.starts_with("+sky") => match n["+sky".len()..].parse::<u16>() {
+                Ok(n) => MaterialKind::Sky(n),
+                Err(_) if n == "+sky" => MaterialKind::Sky(0),
+                Err(_) => return Err(Error::Other(format!("sky material `{name}` does not end with a sky index")))
+            },
+            n if n.starts_with('+') => {
+                result.warnings.push(Diagnostic::new("BSP001", format!("Unknown special material `{name}`; triangles using it are ignored")));
+                MaterialKind::Ignored
+            },
+            n => {
+                let shader = match shaders.get(n) {
+                    Some(s) => s.clone(),
+                    None => {
+                        let shader = find_shader(n)?;
+                        shaders.insert(n.to_owned(), shader.clone());
+                        shader
+                    }
+                };
+                if shader.group() == TagGroup::Fog {
+                    MaterialKind::Fog(name, shader)
+                }
+                else {
+                    MaterialKind::Shader(shader)
+                }
+            }
+        };
+        materials.push((kind, material.properties()));
+    }
+
+    let mut level = Level {
+        jms,
+        materials,
+        positions: jms.vertices.iter().map(|v| v.position.scale(1.0 / JMS_UNITS_PER_WORLD_UNIT)).collect(),
+        valid: vec![true; jms.triangles.len()],
+        result
+    };
+
+    for (index, triangle) in jms.triangles.iter().enumerate() {
+        if level.materials[triangle.material].0 == MaterialKind::Ignored {
+            continue
+        }
+        let vertices = level.triangle_positions(index);
+        if is_degenerate_triangle(&vertices) {
+            level.result.errors.push(Diagnostic::new("BSP002", format!("Triangle #{index} is degenerate near {}", jms_position(centroid(&vertices)))));
+            level.valid[index] = false;
+        }
+    }
+
+    // Collision
+    let mut collision_materials: Vec<TagReference> = Vec::new();
+    let mut collision_triangles = Vec::new();
+    let mut collision_sources = Vec::new();
+    let mut portal_triangles = Vec::new();
+
+    for (index, triangle) in jms.triangles.iter().enumerate() {
+        if !level.valid[index] {
+            continue
+        }
+
+        let (kind, properties) = &level.materials[triangle.material];
+        let material = match kind {
+            MaterialKind::Shader(_) if properties.render_only => continue,
+            MaterialKind::Shader(shader) => {
+                let material = match collision_materials.iter().position(|m| m == shader) {
+                    Some(n) => n,
+                    None => {
+                        collision_materials.push(shader.clone());
+                        collision_materials.len() - 1
+                    }
+                };
+                Some(u16::try_from(material).map_err(|_| Error::ArrayLimitExceeded)?)
+            },
+            MaterialKind::Sky(_) | MaterialKind::SeamSealer => None,
+            MaterialKind::Portal => {
+                portal_triangles.push(index);
+                continue
+            },
+            _ => continue
+        };
+
+        collision_triangles.push(CollisionTriangle {
+            vertices: level.triangle_positions(index),
+            material,
+            flags: ModelCollisionGeometryBSPSurfaceFlags {
+                two_sided: properties.two_sided || properties.transparent,
+                invisible: properties.collision_only || material.is_none(),
+                climbable: properties.ladder,
+                breakable: properties.breakable
+            }
+        });
+        collision_sources.push(index);
+    }
+
+    let portal_vertices: Vec<[Vector3D; 3]> = portal_triangles.iter().map(|t| level.triangle_positions(*t)).collect();
+    let mut compiled = compile_collision_bsp(&collision_triangles, &portal_vertices, &mut level.result);
+    if compiled.bsp.surfaces.items.is_empty() {
+        level.result.errors.push(Diagnostic::new("BSP003", "The level has no collision geometry"));
+        return Ok(level.result)
+    }
+    if compiled.bsp.leaves.items.is_empty() {
+        level.result.errors.push(Diagnostic::new("BSP004", "The level has no open space; make sure triangles face inward"));
+        return Ok(level.result)
+    }
+
+    // Clusters
+    let connections = find_leaf_connections(&compiled.bsp);
+    if let Some(leak) = connections.iter().find_map(|c| c.leaves[0].or(c.leaves[1]).filter(|_| c.leaves.contains(&None))) {
+        let surfaces = &compiled.leaf_surfaces[leak];
+        let near = if surfaces.is_empty() {
+            "the edge of the level".to_owned()
+        }
+        else {
+            let points: Vec<Vector3D> = surfaces.iter().map(|s| centroid(&level.triangle_positions(collision_sources[compiled.surface_triangles[*s as usize]]))).collect();
+            jms_position(centroid(&points))
+        };
+        level.result.errors.push(Diagnostic::new("BSP005", format!("The level is not sealed; open space near {near} leaks outside of the level")));
+    }
+
+    let portals = level.group_portals(&portal_triangles, &mut compiled);
+    let (leaf_clusters, cluster_count, cluster_portals) = level.find_clusters(&compiled, &connections, &portals);
+
+    let mut clusters: Vec<ScenarioStructureBSPCluster> = (0..cluster_count).map(|_| ScenarioStructureBSPCluster::default()).collect();
+    for (portal_index, portal) in cluster_portals.iter().enumerate() {
+        for cluster in [portal.front_cluster, portal.back_cluster] {
+            clusters[cluster as usize].portals.items.push(ScenarioStructureBSPClusterPortalIndex { portal: portal_index as u16 });
+        }
+    }
+
+    for (leaf, surfaces) in compiled.leaf_surfaces.iter().enumerate() {
+        let cluster_index = leaf_clusters[leaf];
+        for surface in surfaces {
+            let triangle = collision_sources[compiled.surface_triangles[*surface as usize]];
+            let MaterialKind::Sky(sky) = level.materials[jms.triangles[triangle].material].0 else {
+                continue
+            };
+            let cluster = &mut clusters[cluster_index as usize];
+            match cluster.sky {
+                None => cluster.sky = Some(sky),
+                Some(n) if n != sky => {
+                    level.result.warnings.push(Diagnostic::new("BSP006", format!("Cluster #{cluster_index} touches more than one sky (#{n} and #{sky})")));
+                },
+                Some(_) => ()
+            }
+        }
+    }
+
+    // Rendered geometry
+    let mut surfaces = Vec::new();
+    let mut bsp_materials = Vec::new();
+    let mut cluster_surfaces: Vec<Vec<u32>> = vec![Vec::new(); cluster_count];
+    for (shader, triangles) in level.rendered_triangles() {
+        let first_surface = surfaces.len();
+        let material = level.compile_material(shader, &triangles, &mut surfaces)?;
+        bsp_materials.push(material);
+
+        for (offset, triangle) in triangles.iter().enumerate() {
+            let vertices = level.triangle_positions(*triangle);
+            let cluster = match level.find_cluster(&compiled, &leaf_clusters, &vertices) {
+                Some(n) => n,
+                None => {
+                    level.result.warnings.push(Diagnostic::new("BSP007", format!("Triangle #{triangle} near {} is outside of the level", jms_position(centroid(&vertices)))));
+                    0
+                }
+            };
+            cluster_surfaces[cluster as usize].push((first_surface + offset) as u32);
+        }
+    }
+
+    for (cluster, surface_indices) in clusters.iter_mut().zip(cluster_surfaces) {
+        if surface_indices.is_empty() {
+            continue
+        }
+        let points: Vec<Vector3D> = surface_indices
+            .iter()
+            .flat_map(|s| {
+                let surface: &ScenarioStructureBSPSurface = &surfaces[*s as usize];
+                [surface.vertex0_index, surface.vertex1_index, surface.vertex2_index]
+                    .map(|v| level.surface_vertex_position(&bsp_materials, *s as usize, v.unwrap_or_default()))
+            })
+            .collect();
+        let [world_bounds_x, world_bounds_y, world_bounds_z] = bounds(&points);
+        cluster.subclusters.items.push(ScenarioStructureBSPSubcluster {
+            world_bounds_x,
+            world_bounds_y,
+            world_bounds_z,
+            surface_indices: Reflexive { items: surface_indices.into_iter().map(|index| ScenarioStructureBSPSubclusterSurfaceIndex { index }).collect() }
+        });
+    }
+
+    // Fog
+    let (fog_palette, fog_planes) = level.compile_fog()?;
+    for (region, fog_plane) in fog_planes.iter().enumerate() {
+        let points: Vec<Vector3D> = fog_plane.vertices.items.iter().map(|v| v.point).collect();
+        let front = centroid(&points) + fog_plane.plane.vector.scale(LEAF_SEARCH_OFFSET);
+        if let Some(cluster) = find_leaf(&compiled.bsp, front).map(|l| leaf_clusters[l]) {
+            clusters[cluster as usize].fog = Some(region as u16);
+        }
+    }
+
+    let level_points: Vec<Vector3D> = collision_triangles.iter().flat_map(|t| t.vertices).collect();
+    let [world_bounds_x, world_bounds_y, world_bounds_z] = bounds(&level_points);
+
+    tag.collision_materials.items = collision_materials
+        .into_iter()
+        .map(|shader| ScenarioStructureBSPCollisionMaterial { shader, ..Default::default() })
+        .collect();
+    tag.leaves.items = leaf_clusters
+        .iter()
+        .map(|cluster| ScenarioStructureBSPLeaf { cluster: *cluster, ..Default::default() })
+        .collect();
+    tag.collision_bsp.items = vec![compiled.bsp];
+    tag.world_bounds_x = world_bounds_x;
+    tag.world_bounds_y = world_bounds_y;
+    tag.world_bounds_z = world_bounds_z;
+    tag.clusters.items = clusters;
+    tag.cluster_portals.items = cluster_portals;
+    tag.surfaces.items = surfaces;
+    tag.lightmaps.items = vec![ScenarioStructureBSPLightmap { bitmap: None, materials: Reflexive { items: bsp_materials } }];
+    tag.fog_regions.items = fog_planes
+        .iter()
+        .map(|plane| ScenarioStructureBSPFogRegion { fog: plane.front_region, ..Default::default() })
+        .collect();
+    tag.fog_planes.items = fog_planes
+        .into_iter()
+        .enumerate()
+        .map(|(region, plane)| ScenarioStructureBSPFogPlane { front_region: Some(region as u16), ..plane })
+        .collect();
+    tag.fog_palette.items = fog_palette;
+    tag.weather_polyhedra.items = level.compile_weather_polyhedra();
+
+    // These refer to surfaces and clusters that no longer exist.
+    tag.leaf_surfaces.items.clear();
+    tag.nodes.items.clear();
+
+    recompress_scenario_structure_bsp_vertices(tag)?;
+
+    Ok(level.result)
+}
+
+#[derive(Clone, PartialEq)]
+enum MaterialKind {
+    Shader(TagReference),
+    Fog(String, TagReference),
+    Sky(u16),
+    Portal,
+    WeatherPolyhedron,
+    SeamSealer,
+    Ignored
+}
+
+/// Coplanar, connected portal triangles.
+struct PortalGroup {
+    plane: u32,
+    triangles: Vec<[Vector3D; 3]>,
+    ai_deafening: bool
+}
+
+struct Level<'a> {
+    jms: &'a JMS,
+    materials: Vec<(MaterialKind, JMSMaterialProperties)>,
+    positions: Vec<Vector3D>,
+    valid: Vec<bool>,
+    result: TagResult
+}
+
+impl Level<'_> {
+    fn triangle_positions(&self, triangle: usize) -> [Vector3D; 3] {
+        self.jms.triangles[triangle].vertices.map(|v| self.positions[v])
+    }
+
+    fn kind(&self, triangle: usize) -> &MaterialKind {
+        &self.materials[self.jms.triangles[triangle].material].0
+    }
+
+    /// Get valid triangles of a kind, grouped into connected sets of triangles.
+    ///
+    /// If `coplanar` is set, triangles must also be on the same plane.
+    fn connected_triangles(&self, triangles: &[usize], coplanar: bool) -> Vec<Vec<usize>> {
+        let plane = |t: usize| {
+            let [a, b, c] = self.triangle_positions(t);
+            let normal = cross(b - a, c - a).normalize();
+            Plane3D { vector: normal, d: normal.dot(&a) }
+        };
+
+        let mut sets = UnionFind::new(triangles.len());
+        for i in 0..triangles.len() {
+            let a = self.triangle_positions(triangles[i]);
+            for j in (i + 1)..triangles.len() {
+                let b = self.triangle_positions(triangles[j]);
+                let touching = a.iter().any(|p| b.contains(p));
+                if touching && (!coplanar || planes_match(&plane(triangles[i]), &plane(triangles[j]))) {
+                    sets.join(i, j);
+                }
+            }
+        }
+
+        let mut groups: Vec<Vec<usize>> = Vec::new();
+        let mut group_indices: HashMap<usize, usize> = HashMap::new();
+        for (i, triangle) in triangles.iter().enumerate() {
+            let group = *group_indices.entry(sets.find(i)).or_insert_with(|| {
+                groups.push(Vec::new());
+                groups.len() - 1
+            });
+            groups[group].push(*triangle);
+        }
+        groups
+    }
+
+    fn group_portals(&self, portal_triangles: &[usize], compiled: &mut CompiledCollisionBSP) -> Vec<PortalGroup> {
+        self.connected_triangles(portal_triangles, true)
+            .into_iter()
+            .map(|triangles| {
+                let [a, b, c] = self.triangle_positions(triangles[0]);
+                let normal = cross(b - a, c - a).normalize();
+                PortalGroup {
+                    plane: find_or_add_plane(&mut compiled.bsp, Plane3D { vector: normal, d: normal.dot(&a) }),
+                    ai_deafening: triangles.iter().any(|t| self.materials[self.jms.triangles[*t].material].1.ai_deafening),
+                    triangles: triangles.iter().map(|t| self.triangle_positions(*t)).collect()
+                }
+            })
+            .collect()
+    }
+
+    /// Join leaves into clusters, which are divided by portals.
+    ///
+    /// Returns the cluster of each leaf, the number of clusters, and the cluster portals.
+    fn find_clusters(&mut self, compiled: &CompiledCollisionBSP, connections: &[LeafConnection], portals: &[PortalGroup]) -> (Vec<u16>, usize, Vec<ScenarioStructureBSPClusterPortal>) {
+        let mut leaves = UnionFind::new(compiled.bsp.leaves.items.len());
+        let mut portal_connections: Vec<Vec<[usize; 2]>> = vec![Vec::new(); portals.len()];
+
+        for connection in connections {
+            let [Some(front), Some(back)] = connection.leaves else {
+                continue
+            };
+
+            let center = centroid(&connection.points);
+            let covering_portal = portals.iter().position(|portal| {
+                let plane = compiled.bsp.planes.items[portal.plane as usize].plane;
+                let flipped = Plane3D { vector: -plane.vector, d: -plane.d };
+                (planes_match(&plane, &connection.plane) || planes_match(&flipped, &connection.plane))
+                    && portal.triangles.iter().any(|t| point_in_triangle(center, t, plane.vector))
+            });
+
+            match covering_portal {
+                Some(portal) => {
+                    let plane = compiled.bsp.planes.items[portals[portal].plane as usize].plane;
+                    let sides = if plane.vector.dot(&connection.plane.vector) > 0.0 { [front, back] } else { [back, front] };
+                    portal_connections[portal].push(sides);
+                },
+                None => leaves.join(front, back)
+            }
+        }
+
+        let mut cluster_indices: HashMap<usize, u16> = HashMap::new();
+        let leaf_clusters: Vec<u16> = (0..compiled.bsp.leaves.items.len())
+            .map(|leaf| {
+                let next = cluster_indices.len() as u16;
+                *cluster_indices.entry(leaves.find(leaf)).or_insert(next)
+            })
+            .collect();
+
+        let mut cluster_portals = Vec::new();
+        for (portal, sides) in portals.iter().zip(portal_connections) {
+            let mut points: Vec<Vector3D> = Vec::new();
+            for point in portal.triangles.iter().flatten() {
+                if !points.contains(point) {
+                    points.push(*point);
+                }
+            }
+            let center = centroid(&points);
+            let Some([front, back]) = sides.first().map(|s| s.map(|leaf| leaf_clusters[leaf])) else {
+                self.result.warnings.push(Diagnostic::new("BSP008", format!("Portal near {} does not touch any open space", jms_position(center))));
+                continue
+            };
+
+            let divides = sides.iter().all(|s| leaf_clusters[s[0]] == front && leaf_clusters[s[1]] == back);
+            if front == back || !divides {
+                self.result.errors.push(Diagnostic::new("BSP009", format!("Portal near {} does not divide space; the clusters on both sides of it are connected", jms_position(center))));
+                continue
+            }
+
+            let plane = compiled.bsp.planes.items[portal.plane as usize].plane;
+            let mut portal_data = ScenarioStructureBSPClusterPortal {
+                front_cluster: front,
+                back_cluster: back,
+                plane_index: portal.plane,
+                centroid: center,
+                bounding_radius: points.iter().map(|p| p.distance_squared(&center)).fold(0.0f32, f32::max).sqrt(),
+                vertices: Reflexive {
+                    items: polygon_around(&points, plane.vector).into_iter().map(|point| ScenarioStructureBSPClusterPortalVertex { point }).collect()
+                },
+                ..Default::default()
+            };
+            portal_data.flags.ai_cant_hear_through_this = portal.ai_deafening;
+            cluster_portals.push(portal_data);
+        }
+
+        let cluster_count = cluster_indices.len();
+        (leaf_clusters, cluster_count, cluster_portals)
+    }
+
+    /// Find the cluster of a triangle by checking in front of and behind it.
+    fn find_cluster(&self, compiled: &CompiledCollisionBSP, leaf_clusters: &[u16], vertices: &[Vector3D; 3]) -> Option<u16> {
+        let [a, b, c] = *vertices;
+        let normal = cross(b - a, c - a).normalize();
+        let center = centroid(vertices);
+        [center + normal.scale(LEAF_SEARCH_OFFSET), center - normal.scale(LEAF_SEARCH_OFFSET)]
+            .into_iter()
+            .find_map(|p| find_leaf(&compiled.bsp, p))
+            .map(|leaf| leaf_clusters[leaf])
+    }
+
+    /// Get rendered triangles grouped by shader.
+    fn rendered_triangles(&self) -> Vec<(TagReference, Vec<usize>)> {
+        let mut shaders: Vec<(TagReference, Vec<usize>)> = Vec::new();
+        for (index, triangle) in self.jms.triangles.iter().enumerate() {
+            let (MaterialKind::Shader(shader), properties) = &self.materials[triangle.material] else {
+                continue
+            };
+            if !self.valid[index] || properties.collision_only {
+                continue
+            }
+            match shaders.iter_mut().find(|s| s.0 == *shader) {
+                Some(s) => s.1.push(index),
+                None => shaders.push((shader.clone(), vec![index]))
+            }
+        }
+        shaders
+    }
+
+    /// Compile rendered triangles that share a shader into a material, adding their surfaces.
+    fn compile_material(&self, shader: TagReference, triangles: &[usize], surfaces: &mut Vec<ScenarioStructureBSPSurface>) -> RinghopperResult<ScenarioStructureBSPMaterial> {
+        let mut vertices: Vec<ModelVertexUncompressed> = Vec::new();
+        let mut vertex_indices: HashMap<[u32; 7], u16> = HashMap::new();
+        let mut material_triangles: Vec<[u16; 3]> = Vec::with_capacity(triangles.len());
+
+        for triangle in triangles {
+            let mut indices = [0u16; 3];
+            for (index, jms_vertex) in indices.iter_mut().zip(self.jms.triangles[*triangle].vertices) {
+                let vertex = &self.jms.vertices[jms_vertex];
+                let vertex = ModelVertexUncompressed {
+                    position: self.positions[jms_vertex],
+                    normal: vertex.normal.normalize(),
+                    texture_coords: Vector2D { x: vertex.texture_coordinates.x, y: 1.0 - vertex.texture_coordinates.y },
+                    ..Default::default()
+                };
+                let key = [
+                    vertex.position.x.to_bits(),
+                    vertex.position.y.to_bits(),
+                    vertex.position.z.to_bits(),
+                    vertex.normal.x.to_bits(),
+                    vertex.normal.y.to_bits(),
+                    vertex.normal.z.to_bits(),
+                    vertex.texture_coords.x.to_bits() ^ vertex.texture_coords.y.to_bits().rotate_left(16)
+                ];
+                *index = match vertex_indices.get(&key) {
+                    Some(n) => *n,
+                    None => {
+                        // 0xFFFF is a null index
+                        if vertices.len() >= u16::MAX as usize {
+                            return Err(Error::Other(format!("vertex count of {shader} is too high (> 0x{:X})", u16::MAX)))
+                        }
+                        let new_index = vertices.len() as u16;
+                        vertices.push(vertex);
+                        vertex_indices.insert(key, new_index);
+                        new_index
+                    }
+                };
+            }
+            material_triangles.push(indices);
+        }
+
+        calculate_tangents(&mut vertices, &material_triangles);
+
+        let first_surface = surfaces.len();
+        surfaces.extend(material_triangles.iter().map(|[a, b, c]| ScenarioStructureBSPSurface {
+            vertex0_index: Some(*a),
+            vertex1_index: Some(*b),
+            vertex2_index: Some(*c)
+        }));
+
+        let mut uncompressed_vertices = Vec::with_capacity(vertices.len() * ScenarioStructureBSPMaterialUncompressedRenderedVertex::simple_size());
+        for vertex in &vertices {
+            let vertex = ScenarioStructureBSPMaterialUncompressedRenderedVertex {
+                position: vertex.position,
+                normal: vertex.normal,
+                binormal: vertex.binormal,
+                tangent: vertex.tangent,
+                texture_coords: vertex.texture_coords
+            };
+            uncompressed_vertices.extend_from_slice(vertex.as_bytes::<LittleEndian>().unwrap().bytes());
+        }
+
+        let points: Vec<Vector3D> = vertices.iter().map(|v| v.position).collect();
+        let mut material = ScenarioStructureBSPMaterial {
+            shader,
+            surfaces: first_surface as u32,
+            surface_count: material_triangles.len() as u32,
+            centroid: centroid(&points),
+            uncompressed_vertices: BSPVertexData { bytes: uncompressed_vertices },
+            ..Default::default()
+        };
+        material.rendered_vertices.vertex_count = vertices.len() as u32;
+        Ok(material)
+    }
+
+    /// Get the position of a rendered vertex of a surface.
+    fn surface_vertex_position(&self, materials: &[ScenarioStructureBSPMaterial], surface: usize, vertex: u16) -> Vector3D {
+        let Some(material) = materials.iter().find(|m| (m.surfaces as usize..(m.surfaces + m.surface_count) as usize).contains(&surface)) else {
+            return Vector3D::zero()
+        };
+        let size = ScenarioStructureBSPMaterialUncompressedRenderedVertex::simple_size();
+        let offset = vertex as usize * size;
+        ScenarioStructureBSPMaterialUncompressedRenderedVertex::read::<LittleEndian>(&material.uncompressed_vertices.bytes, offset, offset + size)
+            .map(|v| v.position)
+            .unwrap_or_default()
+    }
+
+    /// Make fog planes from triangles with fog materials.
+    ///
+    /// Each fog plane gets its own fog region, and `front_region` is set to the fog palette index for now.
+    fn compile_fog(&self) -> RinghopperResult<(Vec<ScenarioStructureBSPFogPalette>, Vec<ScenarioStructureBSPFogPlane>)> {
+        let mut palette: Vec<ScenarioStructureBSPFogPalette> = Vec::new();
+        let mut planes: Vec<(usize, Plane3D, Vec<Vector3D>)> = Vec::new();
+
+        for (index, triangle) in self.jms.triangles.iter().enumerate() {
+            let MaterialKind::Fog(name, fog) = &self.materials[triangle.material].0 else {
+                continue
+            };
+            if !self.valid[index] {
+                continue
+            }
+
+            let palette_index = match palette.iter().position(|p| p.fog == *fog) {
+                Some(n) => n,
+                None => {
+                    palette.push(ScenarioStructureBSPFogPalette {
+                        name: String32::from_str(name).map_err(|_| Error::Other(format!("fog material name `{name}` is longer than 31 characters")))?,
+                        fog: fog.clone(),
+                        ..Default::default()
+                    });
+                    palette.len() - 1
+                }
+            };
+
+            let vertices = self.triangle_positions(index);
+            let [a, b, c] = vertices;
+            let normal = cross(b - a, c - a).normalize();
+            let plane = Plane3D { vector: normal, d: normal.dot(&a) };
+
+            let points = match planes.iter_mut().find(|(p, existing, _)| *p == palette_index && planes_match(existing, &plane)) {
+                Some(existing) => &mut existing.2,
+                None => {
+                    planes.push((palette_index, plane, Vec::new()));
+                    &mut planes.last_mut().unwrap().2
+                }
+            };
+            for vertex in vertices {
+                if !points.contains(&vertex) {
+                    points.push(vertex);
+                }
+            }
+        }
+
+        let planes = planes
+            .into_iter()
+            .map(|(palette_index, plane, points)| ScenarioStructureBSPFogPlane {
+                front_region: Some(palette_index as u16),
+                plane,
+                vertices: Reflexive {
+                    items: polygon_around(&points, plane.vector).into_iter().map(|point| ScenarioStructureBSPFogPlaneVertex { point }).collect()
+                },
+                ..Default::default()
+            })
+            .collect();
+
+        Ok((palette, planes))
+    }
+
+    /// Make weather polyhedra from connected sets of `+weatherpoly` triangles.
+    fn compile_weather_polyhedra(&self) -> Vec<ScenarioStructureBSPWeatherPolyhedron> {
+        let triangles: Vec<usize> = (0..self.jms.triangles.len())
+            .filter(|t| self.valid[*t] && *self.kind(*t) == MaterialKind::WeatherPolyhedron)
+            .collect();
+
+        self.connected_triangles(&triangles, false)
+            .into_iter()
+            .map(|triangles| {
+                let mut planes: Vec<Plane3D> = Vec::new();
+                let mut points: Vec<Vector3D> = Vec::new();
+                for triangle in triangles {
+                    let vertices = self.triangle_positions(triangle);
+                    let [a, b, c] = vertices;
+                    let normal = cross(b - a, c - a).normalize();
+                    let plane = Plane3D { vector: normal, d: normal.dot(&a) };
+                    if !planes.iter().any(|p| planes_match(p, &plane)) {
+                        planes.push(plane);
+                    }
+                    for vertex in vertices {
+                        if !points.contains(&vertex) {
+                            points.push(vertex);
+                        }
+                    }
+                }
+
+                let center = centroid(&points);
+                ScenarioStructureBSPWeatherPolyhedron {
+                    bounding_sphere_center: center,
+                    bounding_sphere_radius: points.iter().map(|p| p.distance_squared(&center)).fold(0.0f32, f32::max).sqrt(),
+                    planes: Reflexive { items: planes.into_iter().map(|plane| ScenarioStructureBSPWeatherPolyhedronPlane { plane }).collect() }
+                }
+            })
+            .collect()
+    }
+}
+
+fn centroid(points: &[Vector3D]) -> Vector3D {
+    if points.is_empty() {
+        return Vector3D::zero()
+    }
+    let mut sum = Vector3D::zero();
+    for point in points {
+        sum += *point;
+    }
+    sum.scale(1.0 / points.len() as f32)
+}
+
+fn bounds(points: &[Vector3D]) -> [Bounds<f32>; 3] {
+    let mut result = [Bounds { lower: f32::MAX, upper: f32::MIN }; 3];
+    for point in points {
+        for (bounds, value) in result.iter_mut().zip([point.x, point.y, point.z]) {
+            bounds.lower = bounds.lower.min(value);
+            bounds.upper = bounds.upper.max(value);
+        }
+    }
+    if points.is_empty() {
+        result = [Bounds { lower: 0.0, upper: 0.0 }; 3];
+    }
+    result
+}
+
+fn point_in_triangle(point: Vector3D, [a, b, c]: &[Vector3D; 3], normal: Vector3D) -> bool {
+    [(*a, *b), (*b, *c), (*c, *a)]
+        .into_iter()
+        .all(|(start, end)| cross(end - start, point - start).dot(&normal) >= -0.001)
+}
+
+/// Order points on a plane counterclockwise around their centroid.
+fn polygon_around(points: &[Vector3D], normal: Vector3D) -> Vec<Vector3D> {
+    let center = centroid(points);
+    let axis = if normal.x.abs() < 0.6 { Vector3D { x: 1.0, y: 0.0, z: 0.0 } } else { Vector3D { x: 0.0, y: 1.0, z: 0.0 } };
+    let u = cross(normal, axis).normalize();
+    let v = cross(normal, u);
+
+    let mut points = points.to_vec();
+    let angle = |p: &Vector3D| {
+        let offset = *p - center;
+        offset.dot(&v).atan2(offset.dot(&u))
+    };
+    points.sort_by(|a, b| angle(a).total_cmp(&angle(b)));
+    points
+}
+
+struct UnionFind {
+    parents: Vec<usize>
+}
+
+impl UnionFind {
+    fn new(count: usize) -> Self {
+        Self { parents: (0..count).collect() }
+    }
+
+    fn find(&mut self, mut index: usize) -> usize {
+        while self.parents[index] != index {
+            self.parents[index] = self.parents[self.parents[index]];
+            index = self.parents[index];
+        }
+        index
+    }
+
+    fn join(&mut self, a: usize, b: usize) {
+        let (a, b) = (self.find(a), self.find(b));
+        self.parents[a.max(b)] = a.min(b);
+    }
+}
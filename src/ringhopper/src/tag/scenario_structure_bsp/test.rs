@@ -0,0 +1,139 @@
+use definitions::ScenarioStructureBSP;
+use primitives::primitive::{TagGroup, TagPath, TagReference, Vector, Vector2D, Vector3D};
+use crate::data::jms::*;
+use crate::tag::collision_bsp::find_leaf;
+use crate::tag::model::cross;
+use super::*;
+
+const SHADER: usize = 0;
+const SKY: usize = 1;
+const PORTAL: usize = 2;
+const FOG: usize = 3;
+
+/// Make a room from (-200,-100,-100) to (200,100,100) with walls facing inward and a sky on top, split in half by a
+/// portal, with a fog plane at the bottom.
+fn test_room() -> JMS {
+    let mut jms = JMS {
+        nodes: vec![JMSNode { name: "frame".to_owned(), first_child: None, sibling: None, rotation: Default::default(), translation: Default::default() }],
+        materials: ["floor", "+sky", "+portal", "water"]
+            .into_iter()
+            .map(|name| JMSMaterial { name: name.to_owned(), tiff_path: "<none>".to_owned() })
+            .collect(),
+        regions: vec!["unnamed".to_owned()],
+        ..Default::default()
+    };
+
+    let corner = |i: usize| Vector3D {
+        x: if i & 1 != 0 { 200.0 } else { -200.0 },
+        y: if i & 2 != 0 { 100.0 } else { -100.0 },
+        z: if i & 4 != 0 { 100.0 } else { -100.0 }
+    };
+
+    // Counterclockwise when viewed from inside
+    let walls = [
+        ([0, 1, 3, 2], SHADER), // -z
+        ([4, 6, 7, 5], SKY),    // +z
+        ([0, 4, 5, 1], SHADER), // -y
+        ([2, 3, 7, 6], SHADER), // +y
+        ([0, 2, 6, 4], SHADER), // -x
+        ([1, 5, 7, 3], SHADER)  // +x
+    ];
+    for ([a, b, c, d], material) in walls {
+        add_quad(&mut jms, [a, b, c, d].map(corner), material);
+    }
+
+    let point = |x: f32, y: f32, z: f32| Vector3D { x, y, z };
+    add_quad(&mut jms, [point(0.0, -100.0, -100.0), point(0.0, 100.0, -100.0), point(0.0, 100.0, 100.0), point(0.0, -100.0, 100.0)], PORTAL);
+    add_quad(&mut jms, [point(-200.0, -100.0, -50.0), point(0.0, -100.0, -50.0), point(0.0, 100.0, -50.0), point(-200.0, 100.0, -50.0)], FOG);
+
+    jms
+}
+
+fn add_quad(jms: &mut JMS, [a, b, c, d]: [Vector3D; 4], material: usize) {
+    for triangle in [[a, b, c], [a, c, d]] {
+        add_triangle(jms, triangle, material);
+    }
+}
+
+fn add_triangle(jms: &mut JMS, points: [Vector3D; 3], material: usize) {
+    let [a, b, c] = points;
+    let normal = cross(b - a, c - a).normalize();
+    let first = jms.vertices.len();
+    for position in points {
+        jms.vertices.push(JMSVertex {
+            node0: 0,
+            position,
+            normal,
+            node1: None,
+            node1_weight: 0.0,
+            texture_coordinates: Vector2D { x: position.x / 100.0, y: position.y / 100.0 }
+        });
+    }
+    jms.triangles.push(JMSTriangle { region: 0, material, vertices: [first, first + 1, first + 2] });
+}
+
+fn find_shader(name: &str) -> RinghopperResult<TagReference> {
+    let group = if name == "water" { TagGroup::Fog } else { TagGroup::ShaderEnvironment };
+    Ok(TagReference::Set(TagPath::new(&format!("levels\\test\\shaders\\{name}"), group).unwrap()))
+}
+
+#[test]
+fn compile_room_with_portal() {
+    let mut tag = ScenarioStructureBSP::default();
+    let result = compile_scenario_structure_bsp(&mut tag, &test_room(), find_shader).unwrap();
+    assert!(result.is_ok(), "{:?}", result.errors);
+
+    // Sky, portal, and fog triangles are not rendered.
+    assert_eq!(10, tag.surfaces.items.len());
+    assert_eq!(1, tag.collision_materials.items.len());
+    assert_eq!(1, tag.lightmaps.items.len());
+    let material = &tag.lightmaps.items[0].materials.items[0];
+    assert_eq!(10, material.surface_count);
+    assert_eq!(20, material.rendered_vertices.vertex_count);
+    assert_eq!(tag.collision_bsp.items[0].leaves.items.len(), tag.leaves.items.len());
+
+    // The portal splits the room into two clusters which both see the sky.
+    assert_eq!(2, tag.clusters.items.len());
+    assert_eq!(1, tag.cluster_portals.items.len());
+    let portal = &tag.cluster_portals.items[0];
+    assert_ne!(portal.front_cluster, portal.back_cluster);
+    assert_eq!(4, portal.vertices.items.len());
+    assert_eq!(Vector3D::zero(), portal.centroid);
+    for cluster in &tag.clusters.items {
+        assert_eq!(Some(0), cluster.sky);
+        assert_eq!(1, cluster.portals.items.len());
+        assert_eq!(1, cluster.subclusters.items.len());
+    }
+    let surface_count: usize = tag.clusters.items.iter().map(|c| c.subclusters.items[0].surface_indices.items.len()).sum();
+    assert_eq!(10, surface_count);
+
+    // The fog plane is only in the -x half of the room.
+    assert_eq!(1, tag.fog_palette.items.len());
+    assert_eq!(1, tag.fog_planes.items.len());
+    assert_eq!(Some(0), tag.fog_planes.items[0].front_region);
+    assert_eq!(1.0, tag.fog_planes.items[0].plane.vector.z);
+    let fogged: Vec<usize> = (0..2).filter(|c| tag.clusters.items[*c].fog.is_some()).collect();
+    assert_eq!(1, fogged.len());
+    let left_leaf = find_leaf(&tag.collision_bsp.items[0], Vector3D { x: -1.0, y: 0.0, z: 0.0 }).unwrap();
+    assert_eq!(fogged[0], tag.leaves.items[left_leaf].cluster as usize);
+
+    assert_eq!(-2.0, tag.world_bounds_x.lower);
+    assert_eq!(1.0, tag.world_bounds_z.upper);
+}
+
+#[test]
+fn report_leaks_and_degenerate_triangles() {
+    // Remove the sky
+    let mut jms = test_room();
+    jms.triangles.remove(2);
+    jms.triangles.remove(2);
+    let result = compile_scenario_structure_bsp(&mut ScenarioStructureBSP::default(), &jms, find_shader).unwrap();
+    assert!(result.errors.iter().any(|e| e.contains("not sealed")), "{:?}", result.errors);
+
+    let mut jms = test_room();
+    let point = |x: f32| Vector3D { x, y: 0.0, z: -100.0 };
+    add_triangle(&mut jms, [point(0.0), point(10.0), point(20.0)], SHADER);
+    let result = compile_scenario_structure_bsp(&mut ScenarioStructureBSP::default(), &jms, find_shader).unwrap();
+    assert_eq!(1, result.errors.len(), "{:?}", result.errors);
+    assert!(result.errors[0].starts_with("Triangle #16 is degenerate"));
+}
@@ -0,0 +1,206 @@
+use primitives::error::{Error, RinghopperResult};
+
+/// Number of samples per channel in an Xbox ADPCM block.
+///
+/// This is the sample in the header plus all but the last nibble.
+pub const XBOX_ADPCM_SAMPLES_PER_BLOCK: usize = 64;
+
+/// Number of samples per channel in an IMA ADPCM block.
+///
+/// This is the sample in the header plus every nibble.
+pub const IMA_ADPCM_SAMPLES_PER_BLOCK: usize = 65;
+
+/// Number of bytes per channel in an ADPCM block.
+pub const XBOX_ADPCM_BYTES_PER_BLOCK: usize = 36;
+
+/// Number of nibbles per channel in an ADPCM block.
+const ADPCM_NIBBLES_PER_BLOCK: usize = (XBOX_ADPCM_BYTES_PER_BLOCK - 4) * 2;
+
+const STEP_TABLE: [i32; 89] = [
+    7, 8, 9, 10, 11, 12, 13, 14, 16, 17, 19, 21, 23, 25, 28, 31, 34, 37, 41, 45, 50, 55, 60, 66, 73, 80, 88, 97, 107, 118,
+    130, 143, 157, 173, 190, 209, 230, 253, 279, 307, 337, 371, 408, 449, 494, 544, 598, 658, 724, 796, 876, 963, 1060,
+    1166, 1282, 1411, 1552, 1707, 1878, 2066, 2272, 2499, 2749, 3024, 3327, 3660, 4026, 4428, 4871, 5358, 5894, 6484,
+    7132, 7845, 8630, 9493, 10442, 11487, 12635, 13899, 15289, 16818, 18500, 20350, 22385, 24623, 27086, 29794, 32767
+];
+
+const INDEX_TABLE: [i32; 16] = [-1, -1, -1, -1, 2, 4, 6, 8, -1, -1, -1, -1, 2, 4, 6, 8];
+
+/// IMA ADPCM predictor state for a single channel.
+#[derive(Copy, Clone, Default)]
+struct ChannelState {
+    predictor: i32,
+    step_index: i32
+}
+
+impl ChannelState {
+    fn encode(&mut self, sample: i16) -> u8 {
+        let step = STEP_TABLE[self.step_index as usize];
+        let mut difference = sample as i32 - self.predictor;
+        let mut nibble = 0u8;
+        if difference < 0 {
+            nibble = 8;
+            difference = -difference;
+        }
+
+        let mut delta = step >> 3;
+        let mut bit_step = step;
+        for bit in [4, 2, 1] {
+            if difference >= bit_step {
+                nibble |= bit;
+                difference -= bit_step;
+                delta += bit_step;
+            }
+            bit_step >>= 1;
+        }
+
+        self.apply(nibble, delta);
+        nibble
+    }
+
+    fn decode(&mut self, nibble: u8) -> i16 {
+        let step = STEP_TABLE[self.step_index as usize];
+        let mut delta = step >> 3;
+        if nibble & 4 != 0 {
+            delta += step;
+        }
+        if nibble & 2 != 0 {
+            delta += step >> 1;
+        }
+        if nibble & 1 != 0 {
+            delta += step >> 2;
+        }
+
+        self.apply(nibble, delta);
+        self.predictor as i16
+    }
+
+    fn apply(&mut self, nibble: u8, delta: i32) {
+        if nibble & 8 != 0 {
+            self.predictor -= delta;
+        }
+        else {
+            self.predictor += delta;
+        }
+        self.predictor = self.predictor.clamp(i16::MIN as i32, i16::MAX as i32);
+        self.step_index = (self.step_index + INDEX_TABLE[nibble as usize]).clamp(0, STEP_TABLE.len() as i32 - 1);
+    }
+}
+
+/// Encode interleaved 16-bit PCM into Xbox ADPCM.
+///
+/// The final block is padded with silence if the sample count is not a multiple of
+/// [`XBOX_ADPCM_SAMPLES_PER_BLOCK`].
+pub fn encode_xbox_adpcm(samples: &[i16], channel_count: usize) -> Vec<u8> {
+    assert!(channel_count > 0, "channel count must be non-zero");
+
+    let frame_count = samples.len() / channel_count;
+    let block_count = frame_count.div_ceil(XBOX_ADPCM_SAMPLES_PER_BLOCK);
+    let mut output = Vec::with_capacity(block_count * XBOX_ADPCM_BYTES_PER_BLOCK * channel_count);
+    let mut states = vec![ChannelState::default(); channel_count];
+
+    let sample_at = |frame: usize, channel: usize| -> i16 {
+        samples.get(frame * channel_count + channel).copied().unwrap_or(0)
+    };
+
+    for block in 0..block_count {
+        let first_frame = block * XBOX_ADPCM_SAMPLES_PER_BLOCK;
+
+        // Each channel starts with the first sample of the block, which resynchronizes the predictor.
+        for (channel, state) in states.iter_mut().enumerate() {
+            let first = sample_at(first_frame, channel);
+            state.predictor = first as i32;
+
+            // There is no step index to carry over at the start, so use whichever one fits the first block best.
+            if block == 0 {
+                state.step_index = (0..STEP_TABLE.len() as i32).min_by_key(|step_index| {
+                    let mut trial = ChannelState { predictor: state.predictor, step_index: *step_index };
+                    (1..XBOX_ADPCM_SAMPLES_PER_BLOCK).map(|frame| {
+                        let sample = sample_at(frame, channel);
+                        trial.encode(sample);
+                        (sample as i64 - trial.predictor as i64).pow(2)
+                    }).sum::<i64>()
+                }).unwrap();
+            }
+
+            output.extend_from_slice(&first.to_le_bytes());
+            output.push(state.step_index as u8);
+            output.push(0);
+        }
+
+        // Channels are interleaved every 8 samples (4 bytes).
+        //
+        // The last nibble is not played back, so it is used to encode the first sample of the next block, which
+        // keeps the step index in step with the signal.
+        for group in 0..ADPCM_NIBBLES_PER_BLOCK / 8 {
+            for (channel, state) in states.iter_mut().enumerate() {
+                for pair in 0..4 {
+                    let frame = first_frame + 1 + group * 8 + pair * 2;
+                    let low = state.encode(sample_at(frame, channel));
+                    let high = state.encode(sample_at(frame + 1, channel));
+                    output.push(low | (high << 4));
+                }
+            }
+        }
+    }
+
+    output
+}
+
+/// Decode Xbox ADPCM into interleaved 16-bit PCM.
+///
+/// Returns `Err` if the data is not a whole number of blocks.
+pub fn decode_xbox_adpcm(data: &[u8], channel_count: usize) -> RinghopperResult<Vec<i16>> {
+    decode_adpcm(data, channel_count, XBOX_ADPCM_SAMPLES_PER_BLOCK)
+}
+
+/// Decode IMA ADPCM into interleaved 16-bit PCM.
+///
+/// This uses the same block layout as Xbox ADPCM, except that every nibble is played back, so each block has
+/// [`IMA_ADPCM_SAMPLES_PER_BLOCK`] samples per channel.
+///
+/// Returns `Err` if the data is not a whole number of blocks.
+pub fn decode_ima_adpcm(data: &[u8], channel_count: usize) -> RinghopperResult<Vec<i16>> {
+    decode_adpcm(data, channel_count, IMA_ADPCM_SAMPLES_PER_BLOCK)
+}
+
+fn decode_adpcm(data: &[u8], channel_count: usize, samples_per_block: usize) -> RinghopperResult<Vec<i16>> {
+    if channel_count == 0 {
+        return Err(Error::InvalidTagData("cannot decode ADPCM with no channels".to_owned()))
+    }
+
+    let block_size = XBOX_ADPCM_BYTES_PER_BLOCK * channel_count;
+    if data.len() % block_size != 0 {
+        return Err(Error::InvalidTagData(format!("ADPCM data is {} bytes, which is not divisible by the block size {block_size}", data.len())))
+    }
+
+    let block_count = data.len() / block_size;
+    let mut output = vec![0i16; block_count * samples_per_block * channel_count];
+
+    for (block, block_data) in data.chunks_exact(block_size).enumerate() {
+        let first_sample = block * samples_per_block * channel_count;
+        let output = &mut output[first_sample..first_sample + samples_per_block * channel_count];
+
+        let (headers, nibbles) = block_data.split_at(4 * channel_count);
+        for (channel, header) in headers.chunks_exact(4).enumerate() {
+            let mut state = ChannelState {
+                predictor: i16::from_le_bytes([header[0], header[1]]) as i32,
+                step_index: (header[2] as i32).clamp(0, STEP_TABLE.len() as i32 - 1)
+            };
+            output[channel] = state.predictor as i16;
+
+            // Channels are interleaved every 8 samples (4 bytes).
+            let channel_nibbles = nibbles
+                .chunks_exact(4)
+                .skip(channel)
+                .step_by(channel_count)
+                .flatten()
+                .flat_map(|b| [b & 0xF, b >> 4]);
+
+            for (frame, nibble) in (1..samples_per_block).zip(channel_nibbles) {
+                output[frame * channel_count + channel] = state.decode(nibble);
+            }
+        }
+    }
+
+    Ok(output)
+}
@@ -0,0 +1,198 @@
+use definitions::{Sound, SoundChannelCount, SoundFormat, SoundPermutation, SoundSampleRate};
+use primitives::error::{Error, RinghopperResult};
+use primitives::primitive::{Reflexive, String32};
+use crate::data::sound::{Audio, SoundPitchRangeSource};
+use super::{channel_count_from_u32, channel_count_to_u32, encode_ogg_vorbis, encode_xbox_adpcm, sample_rate_to_u32, SoundPermutationMetadata};
+
+/// Maximum size of a permutation's 16-bit PCM data before it is split into subpermutations.
+///
+/// This is a multiple of the size of an Xbox ADPCM block in both mono and stereo.
+pub const MAX_SUBPERMUTATION_PCM_SIZE: usize = 0x38E00;
+
+/// Parameters for compiling a sound tag.
+#[derive(Copy, Clone, Debug)]
+pub struct SoundCompileOptions {
+    /// Format to encode permutations in.
+    ///
+    /// If `None`, the sound tag's format is used.
+    pub format: Option<SoundFormat>,
+
+    /// Sample rate to resample permutations to.
+    ///
+    /// If `None`, 44100 Hz is used if any source exceeds 22050 Hz, and 22050 Hz is used otherwise.
+    pub sample_rate: Option<SoundSampleRate>,
+
+    /// Channel count to convert permutations to.
+    ///
+    /// If `None`, the highest channel count of the sources is used.
+    pub channel_count: Option<SoundChannelCount>,
+
+    /// Split permutations longer than [`MAX_SUBPERMUTATION_PCM_SIZE`] into subpermutations.
+    ///
+    /// If `None`, the sound tag's `split_long_sound_into_permutations` flag is used.
+    pub split_long_permutations: Option<bool>,
+
+    /// Quality to use for Ogg Vorbis, from -0.1 to 1.0.
+    pub ogg_vorbis_quality: f32
+}
+
+impl Default for SoundCompileOptions {
+    fn default() -> Self {
+        Self {
+            format: None,
+            sample_rate: None,
+            channel_count: None,
+            split_long_permutations: None,
+            ogg_vorbis_quality: 1.0
+        }
+    }
+}
+
+/// Compile audio into a sound tag, replacing its pitch ranges.
+///
+/// Settings of existing pitch ranges and permutations with matching names, such as natural pitch, gain, and skip
+/// fraction, are kept.
+///
+/// Returns `Err` if the audio cannot be encoded or the sound tag would exceed its limits.
+pub fn compile_sound(tag: &mut Sound, pitch_ranges: &[SoundPitchRangeSource], options: &SoundCompileOptions) -> RinghopperResult<()> {
+    let format = options.format.unwrap_or(tag.format);
+    if format == SoundFormat::ImaADPCM {
+        return Err(Error::Other("encoding IMA ADPCM is not supported".to_owned()))
+    }
+
+    let all_audio = || pitch_ranges.iter().flat_map(|p| p.permutations.iter()).map(|p| &p.audio);
+
+    let sample_rate = match options.sample_rate {
+        Some(n) => n,
+        None if all_audio().any(|a| a.sample_rate > 22050) => SoundSampleRate::_44100Hz,
+        None => SoundSampleRate::_22050Hz
+    };
+
+    let channel_count = match options.channel_count {
+        Some(n) => n,
+        None => {
+            let highest = all_audio().map(|a| a.channel_count).max().unwrap_or(1);
+            channel_count_from_u32(highest)
+                .ok_or_else(|| Error::Other(format!("{highest} channel audio is not supported; only mono and stereo are supported")))?
+        }
+    };
+
+    let split = options.split_long_permutations.unwrap_or(tag.flags.split_long_sound_into_permutations);
+
+    tag.format = format;
+    tag.sample_rate = sample_rate;
+    tag.channel_count = channel_count;
+    tag.flags.split_long_sound_into_permutations = split;
+
+    let encoder = PermutationEncoder {
+        format,
+        sample_rate: sample_rate_to_u32(sample_rate),
+        channel_count: channel_count_to_u32(channel_count),
+        split,
+        ogg_vorbis_quality: options.ogg_vorbis_quality
+    };
+
+    let mut new_pitch_ranges = Vec::with_capacity(pitch_ranges.len());
+    for source in pitch_ranges {
+        let existing = tag.pitch_ranges.items.iter().find(|p| p.name.as_str() == source.name);
+        let mut pitch_range = existing.cloned().unwrap_or_default();
+        pitch_range.name = String32::from_str(&source.name)?;
+
+        let actual_permutation_count = source.permutations.len();
+        let mut permutations: Vec<SoundPermutation> = Vec::with_capacity(actual_permutation_count);
+        let mut subpermutations: Vec<SoundPermutation> = Vec::new();
+
+        for permutation_source in &source.permutations {
+            // Everything but the audio itself is kept from the existing permutation (e.g. gain and skip fraction)
+            let template = existing
+                .and_then(|p| p.permutations.items[..(p.actual_permutation_count as usize).min(p.permutations.items.len())]
+                    .iter()
+                    .find(|p| p.name.as_str() == permutation_source.name))
+                .cloned()
+                .unwrap_or_default();
+
+            let name = String32::from_str(&permutation_source.name)?;
+            let chunks = encoder.encode(&permutation_source.audio)?;
+
+            let mut chain = Vec::with_capacity(chunks.len());
+            for samples in chunks {
+                let mut permutation = SoundPermutation {
+                    name,
+                    format,
+                    next_permutation_index: None,
+                    ..template.clone()
+                };
+                permutation.samples.bytes = samples;
+                permutation.buffer_size = SoundPermutationMetadata::read_from_sound_permutation(tag, &permutation)?.buffer_size;
+                chain.push(permutation);
+            }
+
+            // The first chunk is the actual permutation, and the rest are appended after all actual permutations.
+            let mut chain = chain.into_iter();
+            let mut previous = permutations.len();
+            permutations.push(chain.next().expect("at least one chunk should be encoded"));
+            for subpermutation in chain {
+                let index = actual_permutation_count + subpermutations.len();
+                let next = Some(u16::try_from(index).map_err(|_| Error::IndexLimitExceeded)?);
+                if previous < actual_permutation_count {
+                    permutations[previous].next_permutation_index = next;
+                }
+                else {
+                    subpermutations[previous - actual_permutation_count].next_permutation_index = next;
+                }
+                previous = index;
+                subpermutations.push(subpermutation);
+            }
+        }
+
+        pitch_range.actual_permutation_count = u16::try_from(actual_permutation_count).map_err(|_| Error::ArrayLimitExceeded)?;
+        permutations.append(&mut subpermutations);
+        if permutations.len() > u16::MAX as usize {
+            return Err(Error::ArrayLimitExceeded)
+        }
+        pitch_range.permutations = Reflexive { items: permutations };
+        new_pitch_ranges.push(pitch_range);
+    }
+
+    tag.pitch_ranges = Reflexive { items: new_pitch_ranges };
+
+    Ok(())
+}
+
+struct PermutationEncoder {
+    format: SoundFormat,
+    sample_rate: u32,
+    channel_count: u32,
+    split: bool,
+    ogg_vorbis_quality: f32
+}
+
+impl PermutationEncoder {
+    /// Encode the audio into one or more chunks, splitting it if needed.
+    fn encode(&self, audio: &Audio) -> RinghopperResult<Vec<Vec<u8>>> {
+        let audio = audio.remix(self.channel_count)?.resample(self.sample_rate);
+        let channel_count = self.channel_count as usize;
+
+        let samples_per_chunk = if self.split {
+            MAX_SUBPERMUTATION_PCM_SIZE / std::mem::size_of::<i16>()
+        }
+        else {
+            audio.samples.len().max(1)
+        };
+
+        let mut chunks: Vec<&[f32]> = audio.samples.chunks(samples_per_chunk).collect();
+        if chunks.is_empty() {
+            chunks.push(&[]);
+        }
+
+        chunks.into_iter().map(|chunk| {
+            let pcm = || Audio { sample_rate: self.sample_rate, channel_count: self.channel_count, samples: chunk.to_vec() }.to_pcm16();
+            Ok(match self.format {
+                SoundFormat::PCM => pcm().into_iter().flat_map(i16::to_be_bytes).collect(),
+                SoundFormat::XboxADPCM => encode_xbox_adpcm(&pcm(), channel_count),
+                SoundFormat::OggVorbis => encode_ogg_vorbis(chunk, channel_count, self.sample_rate, self.ogg_vorbis_quality)?,
+                SoundFormat::ImaADPCM => unreachable!()
+            })
+        }).collect()
+    }
+}
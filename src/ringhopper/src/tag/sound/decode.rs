@@ -0,0 +1,115 @@
+use definitions::{Sound, SoundFormat, SoundPermutation, SoundPitchRange};
+use primitives::error::{Error, RinghopperResult};
+use crate::data::sound::Audio;
+use super::{channel_count_to_u32, decode_ima_adpcm, decode_ogg_vorbis, decode_xbox_adpcm, sample_rate_to_u32};
+
+/// Decode a single permutation into interleaved 16-bit PCM.
+///
+/// This does not follow `next_permutation_index`; use [`decode_permutation_chain`] for that.
+///
+/// Returns `Err` if the permutation's samples are malformed or do not match the sound tag.
+pub fn decode_permutation_samples(sound: &Sound, permutation: &SoundPermutation) -> RinghopperResult<Vec<i16>> {
+    let channel_count = channel_count_to_u32(sound.channel_count);
+    let data = &permutation.samples.bytes;
+
+    match permutation.format {
+        SoundFormat::PCM => {
+            if data.len() % 2 != 0 {
+                return Err(Error::InvalidTagData("Sound data is 16-bit PCM, but the permutation has an odd number of bytes".to_owned()))
+            }
+            Ok(data.chunks_exact(2).map(|b| i16::from_be_bytes([b[0], b[1]])).collect())
+        },
+        SoundFormat::XboxADPCM => decode_xbox_adpcm(data, channel_count as usize),
+        SoundFormat::ImaADPCM => decode_ima_adpcm(data, channel_count as usize),
+        SoundFormat::OggVorbis => {
+            let decoded = decode_ogg_vorbis(data)?;
+            if decoded.channel_count != channel_count {
+                return Err(Error::InvalidTagData(format!("Ogg has {} channel(s), but the sound tag has {channel_count}", decoded.channel_count)))
+            }
+            let sample_rate = sample_rate_to_u32(sound.sample_rate);
+            if decoded.sample_rate != sample_rate {
+                return Err(Error::InvalidTagData(format!("Ogg has a sample rate of {} Hz, but the sound tag has {sample_rate} Hz", decoded.sample_rate)))
+            }
+            Ok(decoded.samples)
+        }
+    }
+}
+
+/// Decode a permutation and all of its subpermutations into audio.
+///
+/// Returns `Err` if the permutation does not exist, the chain is out-of-bounds or loops, or decoding fails.
+pub fn decode_permutation_chain(sound: &Sound, pitch_range: &SoundPitchRange, permutation_index: usize) -> RinghopperResult<Audio> {
+    let permutations = &pitch_range.permutations.items;
+    let mut samples = Vec::new();
+    let mut visited = vec![false; permutations.len()];
+    let mut index = permutation_index;
+
+    loop {
+        let permutation = permutations
+            .get(index)
+            .ok_or_else(|| Error::InvalidTagData(format!("Permutation #{index} is out-of-bounds (there are {} permutations)", permutations.len())))?;
+
+        if std::mem::replace(&mut visited[index], true) {
+            return Err(Error::InvalidTagData(format!("Permutation #{permutation_index} infinitely loops (#{index} is referenced more than once)")))
+        }
+
+        samples.extend(decode_permutation_samples(sound, permutation)?);
+
+        // Only a null index ends the chain; 0 is a valid permutation to continue to.
+        index = match permutation.next_permutation_index {
+            None => break,
+            Some(n) => n as usize
+        };
+    }
+
+    Ok(Audio::from_pcm16(&samples, channel_count_to_u32(sound.channel_count), sample_rate_to_u32(sound.sample_rate)))
+}
+
+/// Find the number of actual permutations in a pitch range, excluding subpermutations.
+///
+/// Subpermutations come after all actual permutations, so this is the lowest subpermutation index referenced.
+pub(crate) fn find_actual_permutation_count(sound: &Sound, pitch_range: &SoundPitchRange) -> u16 {
+    let permutation_count_16_bit = u16::try_from(pitch_range.permutations.items.len()).expect("should be < 65535");
+    let split_permutations = sound_tag_actually_contains_split_permutations(sound);
+
+    if split_permutations {
+        // Go through every index and find the lowest next_permutation_index value.
+        let mut lowest_next_permutation_index = permutation_count_16_bit;
+
+        for i in &pitch_range.permutations {
+            if let Some(n) = next_subpermutation_index(i) {
+                lowest_next_permutation_index = lowest_next_permutation_index.min(n);
+            }
+        }
+
+        lowest_next_permutation_index
+    }
+    else {
+        permutation_count_16_bit
+    }
+}
+
+/// Return `true` if the sound tag has split permutations, even if the flag for it is not set.
+pub(crate) fn sound_tag_actually_contains_split_permutations(sound: &Sound) -> bool {
+    if sound.flags.split_long_sound_into_permutations {
+        return true;
+    }
+
+    for pitch_range in &sound.pitch_ranges {
+        for permutation in &pitch_range.permutations {
+            if next_subpermutation_index(&permutation).is_some() {
+                return true;
+            }
+        }
+    }
+
+    false
+}
+
+/// Get the index of the next subpermutation, treating 0 as none since the first permutation can never be a subpermutation.
+pub(crate) fn next_subpermutation_index(permutation: &SoundPermutation) -> Option<u16> {
+    match permutation.next_permutation_index {
+        Some(0) | None => None,
+        Some(n) => Some(n)
+    }
+}
@@ -0,0 +1,210 @@
+use ringhopper_structs::{Sound, SoundChannelCount, SoundFormat, SoundSampleRate};
+use crate::data::sound::{Audio, SoundPermutationSource, SoundPitchRangeSource};
+use crate::tag::verify::sound_is_playable;
+use super::*;
+
+fn make_sources(frames: usize) -> Vec<SoundPitchRangeSource> {
+    let samples = (0..frames * 2).map(|i| ((i / 2) as f32 * 0.05).sin() * 0.5).collect();
+    vec![SoundPitchRangeSource {
+        name: "default".to_owned(),
+        permutations: vec![
+            SoundPermutationSource { name: "long".to_owned(), audio: Audio { sample_rate: 44100, channel_count: 2, samples } },
+            SoundPermutationSource { name: "short".to_owned(), audio: Audio { sample_rate: 22050, channel_count: 1, samples: vec![0.25; 1000] } }
+        ]
+    }]
+}
+
+#[test]
+fn compiles_split_permutations() {
+    let frames = MAX_SUBPERMUTATION_PCM_SIZE * 2 / 4 + 100;
+    let sources = make_sources(frames);
+
+    for format in [SoundFormat::PCM, SoundFormat::XboxADPCM] {
+        let mut sound = Sound::default();
+        let options = SoundCompileOptions { format: Some(format), split_long_permutations: Some(true), ..Default::default() };
+        compile_sound(&mut sound, &sources, &options).unwrap();
+
+        assert_eq!(SoundSampleRate::_44100Hz, sound.sample_rate);
+        assert_eq!(SoundChannelCount::Stereo, sound.channel_count);
+        assert!(sound.flags.split_long_sound_into_permutations);
+
+        let pitch_range = &sound.pitch_ranges.items[0];
+        assert_eq!(2, pitch_range.actual_permutation_count);
+        assert_eq!(4, pitch_range.permutations.items.len());
+
+        // long -> 2 -> 3, short has no subpermutations
+        let permutations = &pitch_range.permutations.items;
+        assert_eq!(Some(2), permutations[0].next_permutation_index);
+        assert_eq!(Some(3), permutations[2].next_permutation_index);
+        assert_eq!(None, permutations[3].next_permutation_index);
+        assert_eq!(None, permutations[1].next_permutation_index);
+        assert!(permutations.iter().all(|p| p.format == format));
+
+        if format == SoundFormat::PCM {
+            assert_eq!(MAX_SUBPERMUTATION_PCM_SIZE as u32, permutations[0].buffer_size);
+            assert_eq!((frames * 4) as u32, permutations.iter().filter(|p| p.name.as_str() == "long").map(|p| p.buffer_size).sum::<u32>());
+        }
+
+        assert!(sound_is_playable(&sound));
+    }
+}
+
+#[test]
+fn compiles_without_splitting() {
+    let frames = MAX_SUBPERMUTATION_PCM_SIZE + 100;
+    let sources = make_sources(frames);
+
+    let mut sound = Sound::default();
+    let options = SoundCompileOptions {
+        format: Some(SoundFormat::PCM),
+        channel_count: Some(SoundChannelCount::Mono),
+        sample_rate: Some(SoundSampleRate::_22050Hz),
+        split_long_permutations: Some(false),
+        ..Default::default()
+    };
+    compile_sound(&mut sound, &sources, &options).unwrap();
+
+    let permutations = &sound.pitch_ranges.items[0].permutations.items;
+    assert_eq!(2, permutations.len());
+    assert_eq!((frames.div_ceil(2) * 2) as u32, permutations[0].buffer_size);
+    assert_eq!(2000, permutations[1].buffer_size);
+    assert!(sound_is_playable(&sound));
+}
+
+#[test]
+fn keeps_existing_permutation_settings() {
+    let frames = MAX_SUBPERMUTATION_PCM_SIZE * 2 / 4 + 100;
+    let sources = make_sources(frames);
+    let options = SoundCompileOptions { format: Some(SoundFormat::PCM), split_long_permutations: Some(true), ..Default::default() };
+
+    let mut sound = Sound::default();
+    compile_sound(&mut sound, &sources, &options).unwrap();
+    for permutation in sound.pitch_ranges.items[0].permutations.items.iter_mut() {
+        if permutation.name.as_str() == "long" {
+            permutation.gain = 0.5;
+            permutation.skip_fraction = 0.25;
+        }
+    }
+
+    compile_sound(&mut sound, &sources, &options).unwrap();
+
+    // Every subpermutation of "long" should get its settings, but nothing should leak into "short"
+    let permutations = &sound.pitch_ranges.items[0].permutations.items;
+    assert_eq!(4, permutations.len());
+    for permutation in permutations {
+        let expected = if permutation.name.as_str() == "long" { (0.5, 0.25) } else { (0.0, 0.0) };
+        assert_eq!(expected, (permutation.gain, permutation.skip_fraction));
+    }
+    assert_eq!(None, permutations[3].next_permutation_index);
+    assert!(sound_is_playable(&sound));
+}
+
+#[test]
+fn encodes_xbox_adpcm_blocks() {
+    let samples: Vec<i16> = (0..130).map(|i| (i * 100) as i16).collect();
+    let encoded = encode_xbox_adpcm(&samples, 2);
+    assert_eq!(XBOX_ADPCM_BYTES_PER_BLOCK * 2 * 2, encoded.len());
+
+    // Each block starts with its first sample.
+    assert_eq!(&samples[0].to_le_bytes(), &encoded[0..2]);
+    assert_eq!(&samples[1].to_le_bytes(), &encoded[4..6]);
+    assert_eq!(&samples[128].to_le_bytes(), &encoded[72..74]);
+}
+
+#[test]
+fn reads_wav() {
+    let mut wav = Vec::new();
+    wav.extend_from_slice(b"RIFF");
+    wav.extend_from_slice(&(36u32 + 8).to_le_bytes());
+    wav.extend_from_slice(b"WAVEfmt ");
+    wav.extend_from_slice(&16u32.to_le_bytes());
+    wav.extend_from_slice(&1u16.to_le_bytes()); // PCM
+    wav.extend_from_slice(&2u16.to_le_bytes()); // stereo
+    wav.extend_from_slice(&22050u32.to_le_bytes());
+    wav.extend_from_slice(&(22050u32 * 4).to_le_bytes());
+    wav.extend_from_slice(&4u16.to_le_bytes());
+    wav.extend_from_slice(&16u16.to_le_bytes());
+    wav.extend_from_slice(b"data");
+    wav.extend_from_slice(&8u32.to_le_bytes());
+    for sample in [16384i16, -16384, 0, i16::MIN] {
+        wav.extend_from_slice(&sample.to_le_bytes());
+    }
+
+    let audio = Audio::from_wav(&wav).unwrap();
+    assert_eq!(22050, audio.sample_rate);
+    assert_eq!(2, audio.channel_count);
+    assert_eq!(vec![0.5, -0.5, 0.0, -1.0], audio.samples);
+    assert_eq!(vec![16384, -16384, 0, -32768], audio.to_pcm16());
+    assert_eq!(wav, audio.to_wav());
+}
+
+#[test]
+fn decodes_permutation_chains() {
+    let frames = MAX_SUBPERMUTATION_PCM_SIZE * 2 / 4 + 100;
+    let sources = make_sources(frames);
+    let expected = sources[0].permutations[0].audio.to_pcm16();
+
+    for format in [SoundFormat::PCM, SoundFormat::XboxADPCM] {
+        let mut sound = Sound::default();
+        let options = SoundCompileOptions { format: Some(format), split_long_permutations: Some(true), ..Default::default() };
+        compile_sound(&mut sound, &sources, &options).unwrap();
+
+        let pitch_range = &sound.pitch_ranges.items[0];
+        let audio = decode_permutation_chain(&sound, pitch_range, 0).unwrap();
+        assert_eq!(44100, audio.sample_rate);
+        assert_eq!(2, audio.channel_count);
+
+        let decoded = audio.to_pcm16();
+        if format == SoundFormat::PCM {
+            assert_eq!(expected, decoded);
+        }
+        else {
+            // ADPCM is lossy and pads to whole blocks.
+            assert_eq!(frames.div_ceil(XBOX_ADPCM_SAMPLES_PER_BLOCK) * XBOX_ADPCM_SAMPLES_PER_BLOCK * 2, decoded.len());
+            let max_error = expected.iter().zip(decoded.iter()).map(|(a, b)| (*a as i32 - *b as i32).abs()).max().unwrap();
+            assert!(max_error < 1024, "max error {max_error} is too high");
+        }
+    }
+}
+
+#[test]
+fn follows_permutation_chains_through_zero() {
+    let mut sound = Sound::default();
+    sound.pitch_ranges.items.push(Default::default());
+    let pitch_range = &mut sound.pitch_ranges.items[0];
+    for (samples, next) in [(vec![1i16, 2], None), (vec![3], Some(0)), (vec![4], Some(1))] {
+        pitch_range.permutations.items.push(Default::default());
+        let permutation = pitch_range.permutations.items.last_mut().unwrap();
+        permutation.format = SoundFormat::PCM;
+        permutation.samples.bytes = samples.iter().flat_map(|s| s.to_be_bytes()).collect();
+        permutation.next_permutation_index = next;
+    }
+
+    let pitch_range = &sound.pitch_ranges.items[0];
+    assert_eq!(vec![4, 3, 1, 2], decode_permutation_chain(&sound, pitch_range, 2).unwrap().to_pcm16());
+    assert_eq!(vec![1, 2], decode_permutation_chain(&sound, pitch_range, 0).unwrap().to_pcm16());
+
+    // Loops and out-of-bounds indices
+    let mut looping = sound.clone();
+    looping.pitch_ranges.items[0].permutations.items[0].next_permutation_index = Some(2);
+    assert!(decode_permutation_chain(&looping, &looping.pitch_ranges.items[0], 2).is_err());
+    assert!(decode_permutation_chain(&looping, &looping.pitch_ranges.items[0], 0).is_err());
+    looping.pitch_ranges.items[0].permutations.items[0].next_permutation_index = Some(3);
+    assert!(decode_permutation_chain(&looping, &looping.pitch_ranges.items[0], 1).is_err());
+}
+
+#[test]
+fn decodes_xbox_adpcm() {
+    let samples: Vec<i16> = (0..256).map(|i| ((i as f32 * 0.1).sin() * 8000.0) as i16).collect();
+    let encoded = encode_xbox_adpcm(&samples, 1);
+    let decoded = decode_xbox_adpcm(&encoded, 1).unwrap();
+    assert_eq!(samples.len(), decoded.len());
+
+    // The first sample of each block is stored verbatim.
+    for block in 0..4 {
+        assert_eq!(samples[block * 64], decoded[block * 64]);
+    }
+
+    assert_eq!(4 * IMA_ADPCM_SAMPLES_PER_BLOCK, decode_ima_adpcm(&encoded, 1).unwrap().len());
+    assert!(decode_xbox_adpcm(&encoded[1..], 1).is_err());
+}
@@ -0,0 +1,224 @@
+use std::io::{Cursor, Read, Seek, SeekFrom};
+use std::mem::zeroed;
+use std::os::raw::c_void;
+
+use aotuv_lancer_vorbis_sys::{
+    ov_callbacks, ov_clear, ov_info, ov_open_callbacks, ov_read, OggVorbis_File, vorbis_analysis, vorbis_analysis_blockout,
+    vorbis_analysis_buffer, vorbis_analysis_headerout, vorbis_analysis_init, vorbis_analysis_wrote, vorbis_bitrate_addblock,
+    vorbis_bitrate_flushpacket, vorbis_block, vorbis_block_clear, vorbis_block_init, vorbis_comment, vorbis_comment_clear,
+    vorbis_comment_init, vorbis_dsp_clear, vorbis_dsp_state, vorbis_encode_init_vbr, vorbis_info, vorbis_info_clear,
+    vorbis_info_init
+};
+use ogg_next_sys::{ogg_packet, ogg_page, ogg_stream_clear, ogg_stream_flush, ogg_stream_init, ogg_stream_packetin, ogg_stream_pageout, ogg_stream_state};
+use primitives::error::{Error, RinghopperResult};
+
+/// Number of samples per channel to submit to the encoder at a time.
+const ANALYSIS_BUFFER_SIZE: usize = 1024;
+
+/// Encode interleaved samples, normalized to `[-1.0, 1.0]`, into an Ogg Vorbis stream.
+///
+/// `quality` ranges from -0.1 (lowest) to 1.0 (highest).
+///
+/// Returns `Err` if the encoder does not support the given parameters.
+pub fn encode_ogg_vorbis(samples: &[f32], channel_count: usize, sample_rate: u32, quality: f32) -> RinghopperResult<Vec<u8>> {
+    if channel_count == 0 {
+        return Err(Error::Other("cannot encode Ogg Vorbis with no channels".to_owned()))
+    }
+    if !(-0.1..=1.0).contains(&quality) {
+        return Err(Error::Other(format!("Ogg Vorbis quality {quality} is out of range (-0.1 to 1.0)")))
+    }
+
+    let mut output = Vec::new();
+
+    unsafe {
+        let mut vi: vorbis_info = zeroed();
+        let mut vc: vorbis_comment = zeroed();
+        let mut vd: vorbis_dsp_state = zeroed();
+        let mut vb: vorbis_block = zeroed();
+        let mut os: ogg_stream_state = zeroed();
+        let mut og: ogg_page = zeroed();
+        let mut op: ogg_packet = zeroed();
+
+        vorbis_info_init(&mut vi);
+        if vorbis_encode_init_vbr(&mut vi, channel_count as _, sample_rate as _, quality) != 0 {
+            vorbis_info_clear(&mut vi);
+            return Err(Error::Other(format!("Ogg Vorbis does not support {channel_count} channel(s) at {sample_rate} Hz with quality {quality}")))
+        }
+
+        vorbis_comment_init(&mut vc);
+        vorbis_analysis_init(&mut vd, &mut vi);
+        vorbis_block_init(&mut vd, &mut vb);
+        ogg_stream_init(&mut os, 0);
+
+        let mut write_page = |og: &ogg_page| {
+            output.extend_from_slice(std::slice::from_raw_parts(og.header, og.header_len as usize));
+            output.extend_from_slice(std::slice::from_raw_parts(og.body, og.body_len as usize));
+        };
+
+        // Headers each go on their own page at the start of the stream.
+        let mut header: ogg_packet = zeroed();
+        let mut header_comment: ogg_packet = zeroed();
+        let mut header_code: ogg_packet = zeroed();
+        vorbis_analysis_headerout(&mut vd, &mut vc, &mut header, &mut header_comment, &mut header_code);
+        ogg_stream_packetin(&mut os, &mut header);
+        ogg_stream_packetin(&mut os, &mut header_comment);
+        ogg_stream_packetin(&mut os, &mut header_code);
+        while ogg_stream_flush(&mut os, &mut og) != 0 {
+            write_page(&og);
+        }
+
+        // Submit an empty buffer at the end to signal the end of the stream.
+        let chunks = samples.chunks(ANALYSIS_BUFFER_SIZE * channel_count).chain(std::iter::once(&[][..]));
+        for chunk in chunks {
+            let frames = chunk.len() / channel_count;
+            if frames > 0 {
+                let buffer = vorbis_analysis_buffer(&mut vd, frames as _);
+                for channel in 0..channel_count {
+                    let channel_buffer = std::slice::from_raw_parts_mut(*buffer.add(channel), frames);
+                    for (frame, sample) in channel_buffer.iter_mut().enumerate() {
+                        *sample = chunk[frame * channel_count + channel];
+                    }
+                }
+            }
+            vorbis_analysis_wrote(&mut vd, frames as _);
+
+            while vorbis_analysis_blockout(&mut vd, &mut vb) == 1 {
+                vorbis_analysis(&mut vb, std::ptr::null_mut());
+                vorbis_bitrate_addblock(&mut vb);
+
+                while vorbis_bitrate_flushpacket(&mut vd, &mut op) == 1 {
+                    ogg_stream_packetin(&mut os, &mut op);
+                    while ogg_stream_pageout(&mut os, &mut og) != 0 {
+                        write_page(&og);
+                    }
+                }
+            }
+        }
+
+        while ogg_stream_flush(&mut os, &mut og) != 0 {
+            write_page(&og);
+        }
+
+        ogg_stream_clear(&mut os);
+        vorbis_block_clear(&mut vb);
+        vorbis_dsp_clear(&mut vd);
+        vorbis_comment_clear(&mut vc);
+        vorbis_info_clear(&mut vi);
+    }
+
+    Ok(output)
+}
+
+/// Decoded Ogg Vorbis stream.
+pub struct DecodedOggVorbis {
+    /// Interleaved 16-bit PCM samples.
+    pub samples: Vec<i16>,
+
+    /// Number of channels.
+    pub channel_count: u32,
+
+    /// Number of samples per second for each channel.
+    pub sample_rate: u32
+}
+
+/// Decode an Ogg Vorbis stream into 16-bit PCM.
+///
+/// Returns `Err` if the stream is invalid.
+pub fn decode_ogg_vorbis(data: &[u8]) -> RinghopperResult<DecodedOggVorbis> {
+    with_ogg_vorbis_file(data, |vf| {
+        let info = unsafe { &*ov_info(vf, -1) };
+        let channel_count = u32::try_from(info.channels).map_err(|_| Error::InvalidTagData("Ogg has an invalid channel count".to_owned()))?;
+        let sample_rate = u32::try_from(info.rate).map_err(|_| Error::InvalidTagData("Ogg has an invalid sample rate".to_owned()))?;
+
+        let mut bytes = Vec::new();
+        let mut buffer = [0u8; 4096];
+        let mut bitstream = 0;
+        loop {
+            let read = unsafe { ov_read(vf, buffer.as_mut_ptr() as *mut _, buffer.len() as _, 0, 2, 1, &mut bitstream) };
+            match read {
+                0 => break,
+                n if n < 0 => return Err(Error::InvalidTagData(format!("Ogg data could not be decoded (error {n})"))),
+                n => bytes.extend_from_slice(&buffer[..n as usize])
+            }
+        }
+
+        Ok(DecodedOggVorbis {
+            samples: bytes.chunks_exact(2).map(|b| i16::from_le_bytes([b[0], b[1]])).collect(),
+            channel_count,
+            sample_rate
+        })
+    })
+}
+
+/// Open an Ogg Vorbis stream in memory and pass it to `f`.
+///
+/// Returns `Err` if the stream could not be opened or `f` fails.
+pub(crate) fn with_ogg_vorbis_file<T, F: FnOnce(&mut OggVorbis_File) -> RinghopperResult<T>>(data: &[u8], f: F) -> RinghopperResult<T> {
+    let mut vf: OggVorbis_File = unsafe { zeroed() };
+    type SliceCursor<'a> = Cursor<&'a [u8]>;
+    let mut data_source: SliceCursor = Cursor::new(data);
+
+    unsafe extern "C" fn read_data(to: *mut c_void, length: usize, nmem: usize, data_source: *mut c_void) -> usize {
+        let data_source = data_source as *mut _ as *mut SliceCursor;
+        let to_buffer = std::slice::from_raw_parts_mut(to as *mut u8, length * nmem);
+        let mut read = 0;
+
+        for chunk in to_buffer.chunks_mut(length) {
+            match (*data_source).read(chunk) {
+                Ok(n) if n == length => read += 1,
+                _ => break
+            }
+        }
+
+        read
+    }
+
+    unsafe extern "C" fn seek_data(data_source: *mut c_void, offset: i64, whence: std::ffi::c_int) -> std::ffi::c_int {
+        let data_source = &mut *(data_source as *mut _ as *mut SliceCursor);
+        let seek_style = match whence {
+            libc::SEEK_SET => SeekFrom::Start(offset as u64),
+            libc::SEEK_END => SeekFrom::End(offset),
+            libc::SEEK_CUR => SeekFrom::Current(offset),
+            _ => unreachable!("invalid whence passed to seek_data: {whence}")
+        };
+
+        if data_source.seek(seek_style).is_ok() {
+            0
+        }
+        else {
+            -1
+        }
+    }
+
+    unsafe extern "C" fn tell_data(data_source: *mut c_void) -> std::ffi::c_long {
+        let data_source = data_source as *mut _ as *mut SliceCursor;
+        (*data_source).position() as std::ffi::c_long
+    }
+
+    let callbacks = ov_callbacks {
+        read_func: Some(read_data),
+        seek_func: Some(seek_data),
+        close_func: None,
+        tell_func: Some(tell_data)
+    };
+
+    let result = unsafe {
+        ov_open_callbacks(
+            &mut data_source as *mut _ as *mut c_void,
+            &mut vf,
+            std::ptr::null_mut(),
+            0,
+            callbacks
+        )
+    };
+
+    if result < 0 {
+        return Err(Error::InvalidTagData(format!("Ogg data is invalid!")));
+    }
+
+    let result = f(&mut vf);
+
+    unsafe { ov_clear(&mut vf); }
+
+    result
+}
@@ -419,6 +419,71 @@ impl<'a> TagTreeItem<'a> {
         }
         Some(TagPath::new(&self.path, self.tag_group.unwrap()).unwrap())
     }
+
+    /// Copy the item so it refers to `tag_tree` instead, such as for a tree that wraps another tree.
+    fn with_tag_tree<'b>(&self, tag_tree: &'b dyn TagTree) -> TagTreeItem<'b> {
+        TagTreeItem::new(self.item_type, Cow::Owned(self.path.to_string()), self.tag_group, tag_tree)
+    }
+}
+
+/// Index of virtual directories built from a list of tag paths.
+///
+/// This is used to browse tag trees that do not store tags in directories, such as cache files.
+#[derive(Clone, Default)]
+pub struct TagDirectoryIndex {
+    // path of each directory (the root is empty) -> path and group (if a tag) of each item in it
+    directories: HashMap<String, Vec<(String, Option<TagGroup>)>>
+}
+
+impl TagDirectoryIndex {
+    /// Build an index of all directories containing the given tags.
+    pub fn new<'a, I: IntoIterator<Item = &'a TagPath>>(tags: I) -> Self {
+        let mut directories: HashMap<String, Vec<(String, Option<TagGroup>)>> = HashMap::new();
+        directories.insert(String::new(), Vec::new());
+
+        for tag in tags {
+            let path = tag.path();
+            let mut parent = "";
+            for (index, _) in path.match_indices(HALO_PATH_SEPARATOR) {
+                let directory = &path[..index];
+                if !directories.contains_key(directory) {
+                    directories.insert(directory.to_owned(), Vec::new());
+                    directories.get_mut(parent).unwrap().push((directory.to_owned(), None));
+                }
+                parent = directory;
+            }
+            directories.get_mut(parent).unwrap().push((path.to_owned(), Some(tag.group())));
+        }
+
+        // Directories go first, then everything is sorted by path.
+        for items in directories.values_mut() {
+            items.sort_by(|(path_a, group_a), (path_b, group_b)| {
+                (group_a.is_some(), path_a, group_a.as_ref().map(TagGroup::as_str)).cmp(&(group_b.is_some(), path_b, group_b.as_ref().map(TagGroup::as_str)))
+            });
+            items.dedup();
+        }
+
+        Self { directories }
+    }
+
+    /// Get all files in the path for the given tag tree.
+    ///
+    /// Returns `None` if the path does not exist.
+    pub fn files_in_path<'a>(&self, path: &str, tag_tree: &'a dyn TagTree) -> Option<Vec<TagTreeItem<'a>>> {
+        let path: String = path
+            .chars()
+            .map(|c| if std::path::is_separator(c) { HALO_PATH_SEPARATOR } else { c })
+            .collect();
+
+        let items = self.directories.get(path.trim_matches(HALO_PATH_SEPARATOR))?;
+        Some(items
+            .iter()
+            .map(|(path, group)| {
+                let item_type = if group.is_some() { TagTreeItemType::Tag } else { TagTreeItemType::Directory };
+                TagTreeItem::new(item_type, Cow::Owned(path.clone()), *group, tag_tree)
+            })
+            .collect())
+    }
 }
 
 #[derive(PartialEq)]
@@ -756,9 +821,8 @@ impl TagTree for VirtualTagsDirectory {
 ///
 /// This internally uses an `Arc`, so cloning this tag tree actually clones a reference.
 ///
-/// The `files_in_path` function is unavailable, as is `iterate_through_all_tags`.
-///
-/// Use `get_all_tags_with_filter` to get all tags in the tree.
+/// Items returned by `files_in_path` and `root` refer to this tree, so they lock the inner tree whenever they are
+/// traversed.
 pub struct AtomicTagTree<T: TagTree + Send> {
     inner: Arc<Mutex<T>>
 }
@@ -772,8 +836,9 @@ impl<T: TagTree + Send> TagTree for AtomicTagTree<T> {
         self.inner.lock().unwrap().open_tag_shared(path)
     }
 
-    fn files_in_path(&self, _path: &str) -> Option<Vec<TagTreeItem>> {
-        unimplemented!("files_in_path not implemented for AtomicTagTree")
+    fn files_in_path(&self, path: &str) -> Option<Vec<TagTreeItem>> {
+        let files = self.inner.lock().unwrap().files_in_path(path)?.iter().map(|f| f.with_tag_tree(self)).collect();
+        Some(files)
     }
 
     fn write_tag(&mut self, path: &TagPath, tag: &dyn PrimaryTagStructDyn) -> RinghopperResult<bool> {
@@ -789,7 +854,7 @@ impl<T: TagTree + Send> TagTree for AtomicTagTree<T> {
     }
 
     fn root(&self) -> TagTreeItem {
-        unimplemented!("root not implemented for AtomicTagTree")
+        self.inner.lock().unwrap().root().with_tag_tree(self)
     }
 
     fn get_all_tags_with_filter(&self, filter: Option<&TagFilter>) -> Vec<TagPath> {
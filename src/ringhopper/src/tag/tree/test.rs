@@ -6,7 +6,7 @@ use definitions::{Model, ModelAnimations, Weapon};
 use primitives::error::RinghopperResult;
 use primitives::primitive::{TagGroup, TagPath};
 use primitives::tag::PrimaryTagStructDyn;
-use crate::tag::tree::{CachingTagTree, CachingTagTreeWriteStrategy, TagFilter, TagTree, TagTreeItem, TagTreeItemType, TreeType, VirtualTagsDirectory};
+use crate::tag::tree::{iterate_through_all_tags, AtomicTagTree, CachingTagTree, CachingTagTreeWriteStrategy, TagDirectoryIndex, TagFilter, TagTree, TagTreeItem, TagTreeItemType, TreeType, VirtualTagsDirectory};
 
 
 #[derive(Default)]
@@ -140,3 +140,89 @@ fn caching_tag_tree() {
     manual.get(&dummy_model).unwrap().lock().unwrap().get_mut::<Model>().unwrap().flags.blend_shared_normals = false;
     assert!(!manual.open_tag_copy(&dummy_model).unwrap().get_ref::<Model>().unwrap().flags.blend_shared_normals);
 }
+
+#[test]
+fn tag_directory_index() {
+    let tags = [
+        "weapons\\dummy\\dummy.weapon",
+        "weapons\\dummy\\dummy.model",
+        "weapons\\dummy\\fp\\fp.model",
+        "weapons\\dummy\\fp\\fp.model_animations",
+        "weapons\\dummy.weapon",
+        "root.scenario"
+    ].map(|p| TagPath::from_path(p).unwrap());
+    let index = TagDirectoryIndex::new(&tags);
+    let tag_tree = MockTagTree::default();
+
+    let listing = |path: &str| -> Vec<(String, Option<TagGroup>)> {
+        index.files_in_path(path, &tag_tree).unwrap().iter().map(|f| (f.path_str().to_owned(), f.tag_group())).collect()
+    };
+
+    assert_eq!(listing(""), [("weapons".to_owned(), None), ("root".to_owned(), Some(TagGroup::Scenario))]);
+    assert_eq!(listing("weapons"), [("weapons\\dummy".to_owned(), None), ("weapons\\dummy".to_owned(), Some(TagGroup::Weapon))]);
+    assert_eq!(listing("weapons/dummy/"), [
+        ("weapons\\dummy\\fp".to_owned(), None),
+        ("weapons\\dummy\\dummy".to_owned(), Some(TagGroup::Model)),
+        ("weapons\\dummy\\dummy".to_owned(), Some(TagGroup::Weapon))
+    ]);
+    assert_eq!(listing("weapons\\dummy\\fp").len(), 2);
+    assert!(index.files_in_path("weapons\\dummy\\fp\\fp", &tag_tree).is_none());
+    assert!(index.files_in_path("sounds", &tag_tree).is_none());
+
+    let mut found: Vec<TagPath> = iterate_through_all_tags(&IndexedTagTree { index, tags: tags.to_vec() }, None).collect();
+    let mut expected = tags.to_vec();
+    found.sort();
+    expected.sort();
+    assert_eq!(found, expected);
+}
+
+struct IndexedTagTree {
+    index: TagDirectoryIndex,
+    tags: Vec<TagPath>
+}
+
+impl TagTree for IndexedTagTree {
+    fn open_tag_copy(&self, _path: &TagPath) -> RinghopperResult<Box<dyn PrimaryTagStructDyn>> {
+        unimplemented!()
+    }
+    fn files_in_path(&self, path: &str) -> Option<Vec<TagTreeItem>> {
+        self.index.files_in_path(path, self)
+    }
+    fn write_tag(&mut self, _path: &TagPath, _tag: &dyn PrimaryTagStructDyn) -> RinghopperResult<bool> {
+        unimplemented!()
+    }
+    fn is_read_only(&self) -> bool {
+        true
+    }
+    fn contains(&self, path: &TagPath) -> bool {
+        self.tags.contains(path)
+    }
+    fn root(&self) -> TagTreeItem {
+        TagTreeItem::new(TagTreeItemType::Directory, Cow::Borrowed(""), None, self)
+    }
+    fn tree_type(&self) -> TreeType {
+        TreeType::CacheFile
+    }
+    fn get_all_tags_with_filter(&self, _filter: Option<&TagFilter>) -> Vec<TagPath> {
+        unimplemented!()
+    }
+}
+
+#[test]
+fn atomic_tag_tree_traversal() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("src")
+        .join("tag")
+        .join("tree")
+        .join("tags");
+
+    let tag_directory = AtomicTagTree::new(VirtualTagsDirectory::new(&[path], None).unwrap());
+    let mut found: Vec<TagPath> = iterate_through_all_tags(&tag_directory, None).collect();
+    found.sort();
+    assert_eq!(found, [
+        "weapons\\dummy\\dummy.model",
+        "weapons\\dummy\\dummy.weapon",
+        "weapons\\dummy\\fp\\fp.model",
+        "weapons\\dummy\\fp\\fp.model_animations"
+    ].map(|p| TagPath::from_path(p).unwrap()));
+}
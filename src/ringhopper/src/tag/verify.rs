@@ -5,6 +5,7 @@ mod globals;
 mod hud_interface;
 mod model;
 mod dependencies;
+mod engine;
 pub(crate) mod scenario;
 mod unicode_string_list;
 pub(crate) mod sound;
@@ -12,6 +13,8 @@ mod particle_system;
 mod particle;
 pub(crate) mod scenario_structure_bsp;
 mod floats;
+pub(crate) mod indices;
+pub(crate) mod ranges;
 
 use std::collections::HashMap;
 use std::num::NonZeroUsize;
@@ -34,6 +37,7 @@ use self::{
     hud_interface::*,
     particle::*,
     dependencies::*,
+    engine::*,
     unicode_string_list::*,
     sound::*,
     particle_system::*,
@@ -159,8 +163,10 @@ impl<T: TagTree + Send + Sync + 'static> ScenarioContext<T> {
                 let group = path.group();
 
                 floats::check_bad_floats(tag, &mut result);
+                indices::check_bad_indices(tag, &mut result);
+                ranges::check_out_of_range_values(tag, &mut result);
 
-                // TODO: Verify indices
+                verify_engine_support(tag, path, self, &mut result);
                 verify_dependencies(tag, path, self, &mut result);
 
                 // Verify supergroups
@@ -192,7 +198,7 @@ impl<T: TagTree + Send + Sync + 'static> ScenarioContext<T> {
                 if self.reserve_tag_to_verify(path) != VerifyStatus::Unverified {
                     return Some(false);
                 }
-                result.errors.push(format!("Failed to open tag: {e}"))
+                result.errors.push(Diagnostic::new("TAG001", format!("Failed to open tag: {e}")))
             }
         }
 
@@ -203,12 +209,12 @@ impl<T: TagTree + Send + Sync + 'static> ScenarioContext<T> {
         Some(is_ok)
     }
 
-    fn open_tag_reference_maybe(&self, tag_reference: &TagReference, result: &mut TagResult, must_be_set_error: Option<&'static str>) -> Option<Arc<Mutex<Box<dyn PrimaryTagStructDyn>>>> {
+    fn open_tag_reference_maybe(&self, tag_reference: &TagReference, result: &mut TagResult, must_be_set_error: Option<Diagnostic>) -> Option<Arc<Mutex<Box<dyn PrimaryTagStructDyn>>>> {
         match tag_reference {
             TagReference::Set(tp) => self.open_tag_maybe(tp, result),
             TagReference::Null(_) => {
                 if let Some(n) = must_be_set_error {
-                    result.errors.push(n.to_string());
+                    result.errors.push(n);
                 }
                 None
             }
@@ -221,7 +227,7 @@ impl<T: TagTree + Send + Sync + 'static> ScenarioContext<T> {
                 Some(n)
             }
             else {
-                result.errors.push(format!("{tag_path} has errors and could not be opened"));
+                result.errors.push(Diagnostic::new("TAG002", format!("{tag_path} has errors and could not be opened")));
                 None
             }
         })
@@ -231,7 +237,7 @@ impl<T: TagTree + Send + Sync + 'static> ScenarioContext<T> {
         match self.tag_tree.open_tag_shared(tag_path) {
             Ok(n) => Some(n),
             Err(e) => {
-                result.errors.push(format!("Unable to open {tag_path}: {e}"));
+                result.errors.push(Diagnostic::new("TAG003", format!("Unable to open {tag_path}: {e}")));
                 None
             }
         }
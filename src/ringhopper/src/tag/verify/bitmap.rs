@@ -1,9 +1,9 @@
 use definitions::{BitmapDataFormat, BitmapFormat, BitmapUsage};
 use primitives::{error::OverflowCheck, primitive::TagPath, tag::PrimaryTagStructDyn};
 use ringhopper_structs::{Bitmap, BitmapType};
-use crate::{primitives::dynamic::DynamicEnumImpl, tag::{bitmap::{bytes_per_block, MipmapFaceIterator}, tree::TagTree}};
+use crate::{primitives::dynamic::DynamicEnumImpl, tag::{bitmap::{bytes_per_block, MipmapFaceIterator, pixels_per_block_length}, tree::TagTree}};
 
-use super::{ScenarioContext, TagResult};
+use super::{Diagnostic, ScenarioContext, TagResult};
 
 pub enum SequenceType {
     Any,
@@ -11,7 +11,7 @@ pub enum SequenceType {
     Bitmap
 }
 
-pub fn verify_bitmap<T: TagTree + Send + Sync>(tag: &dyn PrimaryTagStructDyn, _path: &TagPath, _context: &ScenarioContext<T>, result: &mut TagResult) {
+pub fn verify_bitmap<T: TagTree + Send + Sync>(tag: &dyn PrimaryTagStructDyn, _path: &TagPath, context: &ScenarioContext<T>, result: &mut TagResult) {
     let bitmap = tag.as_any().downcast_ref::<Bitmap>().unwrap();
 
     let error_count = result.errors.len();
@@ -20,13 +20,13 @@ pub fn verify_bitmap<T: TagTree + Send + Sync>(tag: &dyn PrimaryTagStructDyn, _p
     for (i, data) in ziperator!(bitmap.bitmap_data) {
         if data.format == BitmapDataFormat::P8 {
             if bitmap.flags.disable_height_map_compression {
-                result.errors.push(format!("Bitmap #{i} is {}, but the bitmap tag has height compression disabled", data.format));
+                result.errors.push(Diagnostic::new("BMP001", format!("Bitmap #{i} is {}, but the bitmap tag has height compression disabled", data.format)).with_field(format!("bitmap_data[{i}]")));
             }
             continue;
         }
         else if data.format != BitmapDataFormat::P8 && !bitmap.flags.disable_height_map_compression {
             if bitmap.usage == BitmapUsage::HeightMap || bitmap.usage == BitmapUsage::VectorMap {
-                result.errors.push(format!("Bitmap #{i} is {}, but the bitmap tag has height compression enabled", data.format));
+                result.errors.push(Diagnostic::new("BMP002", format!("Bitmap #{i} is {}, but the bitmap tag has height compression enabled", data.format)).with_field(format!("bitmap_data[{i}]")));
                 continue;
             }
         }
@@ -42,16 +42,16 @@ pub fn verify_bitmap<T: TagTree + Send + Sync>(tag: &dyn PrimaryTagStructDyn, _p
         };
 
         if !allowed_formats.contains(&data.format) {
-            result.errors.push(format!("Bitmap #{i} is {}, but the bitmap tag is set to {} which doesn't match", data.format, bitmap.encoding_format));
+            result.errors.push(Diagnostic::new("BMP003", format!("Bitmap #{i} is {}, but the bitmap tag is set to {} which doesn't match", data.format, bitmap.encoding_format)).with_field(format!("bitmap_data[{i}]")));
         }
     }
 
     if error_count != result.errors.len() {
         if bitmap.color_plate.compressed_data.bytes.is_empty() {
-            result.errors.push("If the bitmap(s) are the correct format, you can change the encoding format of the bitmap. No source data is present in the tag, so it cannot be regenerated.".to_owned());
+            result.errors.push(Diagnostic::new("BMP004", "If the bitmap(s) are the correct format, you can change the encoding format of the bitmap. No source data is present in the tag, so it cannot be regenerated.").with_field("encoding_format"));
         }
         else {
-            result.errors.push("This tag has source data; you can regenerate the bitmap(s). If they are already the correct format, you can instead change the encoding format of the bitmap tag to match.".to_owned());
+            result.errors.push(Diagnostic::new("BMP005", "This tag has source data; you can regenerate the bitmap(s). If they are already the correct format, you can instead change the encoding format of the bitmap tag to match.").with_field("encoding_format"));
         }
     }
 
@@ -60,7 +60,7 @@ pub fn verify_bitmap<T: TagTree + Send + Sync>(tag: &dyn PrimaryTagStructDyn, _p
         let iterator = match MipmapFaceIterator::new_from_bitmap_data(data) {
             Ok(n) => n,
             Err(e) => {
-                result.errors.push(format!("Unable to check mipmaps for bitmap #{i}: {e}"));
+                result.errors.push(Diagnostic::new("BMP006", format!("Unable to check mipmaps for bitmap #{i}: {e}")).with_field(format!("bitmap_data[{i}]")));
                 continue;
             }
         };
@@ -78,7 +78,49 @@ pub fn verify_bitmap<T: TagTree + Send + Sync>(tag: &dyn PrimaryTagStructDyn, _p
             .and_then(|end| bitmap.processed_pixel_data.bytes.get(offset..end));
 
         if range.is_none() {
-            result.errors.push(format!("Bitmap data #{i} has out-of-bounds pixel data ({total_block_length} {block_size}-byte block(s) at offset {offset} out of {} total bytes)", bitmap.processed_pixel_data.bytes.len()));
+            result.errors.push(Diagnostic::new("BMP007", format!("Bitmap data #{i} has out-of-bounds pixel data ({total_block_length} {block_size}-byte block(s) at offset {offset} out of {} total bytes)", bitmap.processed_pixel_data.bytes.len())).with_field(format!("bitmap_data[{i}]")));
+        }
+    }
+
+    // Verify that each bitmap data can be stored the way the target engine needs it
+    let engine = context.engine;
+    let engine_name = engine.name;
+    for (i, data) in ziperator!(bitmap.bitmap_data) {
+        let width = data.width as usize;
+        let height = data.height as usize;
+        let depth = data.depth as usize;
+
+        if data.flags.swizzled {
+            if !engine.bitmap_options.swizzled {
+                result.errors.push(Diagnostic::new("BMP008", format!("Bitmap data #{i} is swizzled, but this is not allowed for engine `{engine_name}`")).with_field(format!("bitmap_data[{i}]")));
+            }
+            if data.flags.compressed {
+                result.errors.push(Diagnostic::new("BMP009", format!("Bitmap data #{i} is swizzled and compressed, which is not allowed")).with_field(format!("bitmap_data[{i}]")));
+            }
+        }
+        // The swizzled flag tells the engine how the data is laid out, and the bitmap compiler does not swizzle yet, so
+        // this is only a warning.
+        else if engine.bitmap_options.swizzled && !data.flags.compressed && width.is_power_of_two() && height.is_power_of_two() && depth.is_power_of_two() {
+            result.warnings.push(Diagnostic::new("BMP010", format!("Bitmap data #{i} is not swizzled, but uncompressed power-of-two bitmaps should be swizzled for engine `{engine_name}`")).with_field(format!("bitmap_data[{i}]")));
+        }
+
+        let block_size = pixels_per_block_length(data.format).get();
+        if !engine.bitmap_options.texture_dimension_must_modulo_block_size || block_size == 1 {
+            continue
+        }
+
+        if width % block_size != 0 || height % block_size != 0 {
+            result.errors.push(Diagnostic::new("BMP011", format!("Bitmap data #{i} is {width}x{height}, which is not divisible by {block_size} as required for engine `{engine_name}`")).with_field(format!("bitmap_data[{i}]")));
+            continue
+        }
+
+        let mipmap_count = data.mipmap_count as usize;
+        let usable_mipmap_count = (1..=mipmap_count)
+            .map(|m| (width.checked_shr(m as u32).unwrap_or(0).max(1), height.checked_shr(m as u32).unwrap_or(0).max(1)))
+            .take_while(|(w, h)| w % block_size == 0 && h % block_size == 0)
+            .count();
+        if usable_mipmap_count != mipmap_count {
+            result.warnings.push(Diagnostic::new("BMP012", format!("Bitmap data #{i} has {mipmap_count} mipmap(s), but only {usable_mipmap_count} are divisible by {block_size} and will be used on engine `{engine_name}`")).with_field(format!("bitmap_data[{i}]")));
         }
     }
 }
@@ -3,7 +3,7 @@ use std::borrow::Cow;
 use primitives::{dynamic::DynamicTagData, primitive::{TagPath, TagReference}, tag::PrimaryTagStructDyn};
 use ringhopper_structs::group_supported_on_engine;
 use crate::tag::tree::TagTree;
-use super::{ScenarioContext, TagResult};
+use super::{Diagnostic, ScenarioContext, TagResult};
 
 pub fn verify_dependencies<T: TagTree + Send + Sync>(tag: &dyn PrimaryTagStructDyn, _path: &TagPath, context: &ScenarioContext<T>, result: &mut TagResult) {
     fn iterate_dependencies_recursively<D: DynamicTagData + ?Sized, T: TagTree + Send + Sync>(
@@ -43,13 +43,13 @@ pub fn verify_dependencies<T: TagTree + Send + Sync>(tag: &dyn PrimaryTagStructD
                         allowed_list += i.as_str();
                     }
 
-                    result.errors.push(format!("{stack_path}{field_name} references `{group}`, which is not allowed for this reference (allowed references are: {allowed_list})"));
+                    result.errors.push(Diagnostic::new("DEP001", format!("{stack_path}{field_name} references `{group}`, which is not allowed for this reference (allowed references are: {allowed_list})")).with_field(format!("{stack_path}{field_name}")));
                     continue
                 }
 
                 if !group_supported_on_engine(group, context.engine) {
                     let stack_path = make_stack_path();
-                    result.errors.push(format!("{stack_path}{field_name} references `{group}`, which is not allowed for engine `{}`", context.engine.name));
+                    result.errors.push(Diagnostic::new("DEP002", format!("{stack_path}{field_name} references `{group}`, which is not allowed for engine `{}`", context.engine.name)).with_field(format!("{stack_path}{field_name}")));
                     continue
                 }
             }
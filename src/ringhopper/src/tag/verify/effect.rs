@@ -1,7 +1,7 @@
 use primitives::{primitive::{TagGroup, TagPath, TagReference}, tag::PrimaryTagStructDyn};
 use ringhopper_structs::Effect;
 use crate::tag::tree::TagTree;
-use super::{ScenarioContext, TagResult};
+use super::{Diagnostic, ScenarioContext, TagResult};
 
 pub fn verify_effect<T: TagTree + Send + Sync>(tag: &dyn PrimaryTagStructDyn, _path: &TagPath, _context: &ScenarioContext<T>, result: &mut TagResult) {
     let effect: &Effect = tag.as_any().downcast_ref().unwrap();
@@ -12,15 +12,15 @@ pub fn verify_effect<T: TagTree + Send + Sync>(tag: &dyn PrimaryTagStructDyn, _p
                 TagReference::Set(_) => (),
 
                 // Null damage_effect references crash the game
-                TagReference::Null(TagGroup::DamageEffect) => result.errors.push(format!("Part #{p} of event #{e} contains a null damage_effect reference. This is invalid, and thus you should remove this or set something.")),
+                TagReference::Null(TagGroup::DamageEffect) => result.errors.push(Diagnostic::new("EFF001", format!("Part #{p} of event #{e} contains a null damage_effect reference. This is invalid, and thus you should remove this or set something.")).with_field(format!("events[{e}].parts[{p}].type"))),
 
                 // This will just do nothing
-                TagReference::Null(g) => result.pedantic_warnings.push(format!("Part #{p} of event #{e} contains a null {g} reference, which is a no-op. You can safely remove this."))
+                TagReference::Null(g) => result.pedantic_warnings.push(Diagnostic::new("EFF002", format!("Part #{p} of event #{e} contains a null {g} reference, which is a no-op. You can safely remove this.")).with_field(format!("events[{e}].parts[{p}].type")))
             }
         }
         for (p, particle) in (0..event.particles.items.len()).zip(event.particles.items.iter()) {
             if particle.particle_type.is_null() {
-                result.pedantic_warnings.push(format!("Particle #{p} of event #{e} contains a null particle reference, which is a no-op. You can safely remove this."));
+                result.pedantic_warnings.push(Diagnostic::new("EFF003", format!("Particle #{p} of event #{e} contains a null particle reference, which is a no-op. You can safely remove this.")).with_field(format!("events[{e}].particles[{p}].particle_type")));
             }
         }
     }
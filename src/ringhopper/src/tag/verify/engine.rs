@@ -0,0 +1,65 @@
+use primitives::dynamic::{DynamicTagData, DynamicTagDataType, SimplePrimitiveType};
+use primitives::primitive::{Data, TagPath, TagReference};
+use primitives::tag::PrimaryTagStructDyn;
+use ringhopper_structs::group_supported_on_engine;
+use crate::tag::tree::TagTree;
+use super::ranges::get_ranged_value;
+use super::{Diagnostic, ScenarioContext, TagResult};
+
+pub fn verify_engine_support<T: TagTree + Send + Sync>(tag: &dyn PrimaryTagStructDyn, path: &TagPath, context: &ScenarioContext<T>, result: &mut TagResult) {
+    let engine = context.engine.name;
+    let group = path.group();
+    if !group_supported_on_engine(group, context.engine) {
+        result.errors.push(Diagnostic::new("ENG001", format!("`{group}` tags are not supported on engine `{engine}`")));
+        return
+    }
+
+    fn check_fields(data: &dyn DynamicTagData, engine: &str, result: &mut TagResult, stack_path: &mut String) {
+        for field_name in data.fields() {
+            let field = data.get_field(field_name).unwrap();
+            let length_before = stack_path.len();
+            if !stack_path.is_empty() {
+                stack_path.push('.');
+            }
+            *stack_path += field_name;
+
+            let supported_engines = data.get_metadata_for_field(field_name).and_then(|m| m.supported_engines);
+            if supported_engines.is_some_and(|e| !e.contains(&engine)) {
+                if field_is_set(field) {
+                    result.warnings.push(Diagnostic::new("ENG002", format!("{stack_path} is set, but it is not used on engine `{engine}`")).with_field(stack_path.as_str()));
+                }
+            }
+            else if let Some(array) = field.as_array() {
+                for i in 0..array.len() {
+                    let length_before = stack_path.len();
+                    *stack_path += &format!("[{i}]");
+                    check_fields(array.get_at_index(i).unwrap(), engine, result, stack_path);
+                    stack_path.truncate(length_before);
+                }
+            }
+            else {
+                check_fields(field, engine, result, stack_path);
+            }
+
+            stack_path.truncate(length_before);
+        }
+    }
+
+    check_fields(tag.as_dynamic(), engine, result, &mut String::new());
+}
+
+/// Return `true` if the field is set to anything other than zero, null, or empty.
+fn field_is_set(field: &dyn DynamicTagData) -> bool {
+    match field.data_type() {
+        DynamicTagDataType::SimplePrimitive(SimplePrimitiveType::Bool) => *field.as_any().downcast_ref::<bool>().unwrap(),
+        DynamicTagDataType::TagReference => field.as_any().downcast_ref::<TagReference>().unwrap().is_set(),
+        DynamicTagDataType::Data => !field.as_any().downcast_ref::<Data>().unwrap().bytes.is_empty(),
+        DynamicTagDataType::Reflexive => field.as_array().unwrap().len() != 0,
+        DynamicTagDataType::Array => {
+            let array = field.as_array().unwrap();
+            (0..array.len()).any(|i| field_is_set(array.get_at_index(i).unwrap()))
+        },
+        DynamicTagDataType::Block => field.fields().iter().any(|f| field_is_set(field.get_field(f).unwrap())),
+        _ => get_ranged_value(field).is_some_and(|v| v != 0.0)
+    }
+}
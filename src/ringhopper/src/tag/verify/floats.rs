@@ -1,10 +1,10 @@
 use primitives::dynamic::{DynamicTagData, DynamicTagDataType, SimplePrimitiveType};
 use primitives::primitive::{Angle, ColorARGB, ColorRGB, Euler2D, Euler3D, Matrix3x3, Plane2D, Plane3D, Quaternion, Vector2D, Vector3D};
-use primitives::tag::{for_each_field, PrimaryTagStructDyn};
-use crate::tag::result::TagResult;
+use primitives::tag::{for_each_field_with_matcher, PrimaryTagStructDyn};
+use crate::tag::result::{Diagnostic, TagResult};
 
 pub fn check_bad_floats(tag: &dyn PrimaryTagStructDyn, result: &mut TagResult) {
-    fn check_field(field: &dyn DynamicTagData, result: &mut TagResult) {
+    fn check_field(field: &dyn DynamicTagData, matcher: &str, result: &mut TagResult) {
         let zero_to_one = 0.0f32..=1.0f32;
 
         match field.data_type() {
@@ -12,79 +12,79 @@ pub fn check_bad_floats(tag: &dyn PrimaryTagStructDyn, result: &mut TagResult) {
                 SimplePrimitiveType::Float => {
                     let f: &f32 = field.as_any().downcast_ref().unwrap();
                     if f.is_nan() {
-                        result.errors.push("NaN float(s) detected. This can be automatically fixed.".to_string());
+                        result.errors.push(Diagnostic::new("FLT001", "NaN float(s) detected. This can be automatically fixed.").with_field(matcher));
                     }
                 }
                 SimplePrimitiveType::Angle => {
                     let f: &Angle = field.as_any().downcast_ref().unwrap();
-                    check_field(&f.angle, result);
+                    check_field(&f.angle, matcher, result);
                 }
                 SimplePrimitiveType::Euler2D => {
                     let f: &Euler2D = field.as_any().downcast_ref().unwrap();
-                    check_field(&f.yaw, result);
-                    check_field(&f.pitch, result);
+                    check_field(&f.yaw, matcher, result);
+                    check_field(&f.pitch, matcher, result);
                 }
                 SimplePrimitiveType::Euler3D => {
                     let f: &Euler3D = field.as_any().downcast_ref().unwrap();
-                    check_field(&f.yaw, result);
-                    check_field(&f.pitch, result);
-                    check_field(&f.roll, result);
+                    check_field(&f.yaw, matcher, result);
+                    check_field(&f.pitch, matcher, result);
+                    check_field(&f.roll, matcher, result);
                 }
                 SimplePrimitiveType::Matrix3x3 => {
                     let f: &Matrix3x3 = field.as_any().downcast_ref().unwrap();
                     for i in f.vectors {
-                        check_field(&i.x, result);
-                        check_field(&i.y, result);
-                        check_field(&i.z, result);
+                        check_field(&i.x, matcher, result);
+                        check_field(&i.y, matcher, result);
+                        check_field(&i.z, matcher, result);
                     }
                 }
                 SimplePrimitiveType::Plane2D => {
                     let f: &Plane2D = field.as_any().downcast_ref().unwrap();
-                    check_field(&f.d, result);
-                    check_field(&f.vector, result);
+                    check_field(&f.d, matcher, result);
+                    check_field(&f.vector, matcher, result);
                 }
                 SimplePrimitiveType::Plane3D => {
                     let f: &Plane3D = field.as_any().downcast_ref().unwrap();
-                    check_field(&f.d, result);
-                    check_field(&f.vector, result);
+                    check_field(&f.d, matcher, result);
+                    check_field(&f.vector, matcher, result);
                 }
                 SimplePrimitiveType::Quaternion => {
                     let f: &Quaternion = field.as_any().downcast_ref().unwrap();
-                    check_field(&f.w, result);
-                    check_field(&f.x, result);
-                    check_field(&f.y, result);
-                    check_field(&f.z, result);
+                    check_field(&f.w, matcher, result);
+                    check_field(&f.x, matcher, result);
+                    check_field(&f.y, matcher, result);
+                    check_field(&f.z, matcher, result);
                 }
                 SimplePrimitiveType::Vector2D => {
                     let f: &Vector2D = field.as_any().downcast_ref().unwrap();
-                    check_field(&f.x, result);
-                    check_field(&f.y, result);
+                    check_field(&f.x, matcher, result);
+                    check_field(&f.y, matcher, result);
                 }
                 SimplePrimitiveType::Vector3D => {
                     let f: &Vector3D = field.as_any().downcast_ref().unwrap();
-                    check_field(&f.x, result);
-                    check_field(&f.y, result);
-                    check_field(&f.z, result);
+                    check_field(&f.x, matcher, result);
+                    check_field(&f.y, matcher, result);
+                    check_field(&f.z, matcher, result);
                 }
                 SimplePrimitiveType::ColorARGB => {
                     let f: &ColorARGB = field.as_any().downcast_ref().unwrap();
-                    check_field(&f.alpha, result);
-                    check_field(&f.red, result);
-                    check_field(&f.green, result);
-                    check_field(&f.blue, result);
+                    check_field(&f.alpha, matcher, result);
+                    check_field(&f.red, matcher, result);
+                    check_field(&f.green, matcher, result);
+                    check_field(&f.blue, matcher, result);
 
                     if !zero_to_one.contains(&f.alpha) || !zero_to_one.contains(&f.red) || !zero_to_one.contains(&f.green) || !zero_to_one.contains(&f.blue) {
-                        result.errors.push(format!("Color components ({f}) detect outside of 0-1 range. This can be automatically fixed."));
+                        result.errors.push(Diagnostic::new("FLT002", format!("Color components ({f}) detect outside of 0-1 range. This can be automatically fixed.")).with_field(matcher));
                     }
                 }
                 SimplePrimitiveType::ColorRGB => {
                     let f: &ColorRGB = field.as_any().downcast_ref().unwrap();
-                    check_field(&f.red, result);
-                    check_field(&f.green, result);
-                    check_field(&f.blue, result);
+                    check_field(&f.red, matcher, result);
+                    check_field(&f.green, matcher, result);
+                    check_field(&f.blue, matcher, result);
 
                     if !zero_to_one.contains(&f.red) || !zero_to_one.contains(&f.green) || !zero_to_one.contains(&f.blue) {
-                        result.errors.push(format!("Color components ({f}) detect outside of 0-1 range. This can be automatically fixed."));
+                        result.errors.push(Diagnostic::new("FLT002", format!("Color components ({f}) detect outside of 0-1 range. This can be automatically fixed.")).with_field(matcher));
                     }
                 }
                 _ => return
@@ -93,7 +93,7 @@ pub fn check_bad_floats(tag: &dyn PrimaryTagStructDyn, result: &mut TagResult) {
         }
     }
 
-    for_each_field(tag.as_dynamic(), |_, field| {
-        check_field(field, result);
+    for_each_field_with_matcher(tag.as_dynamic(), |matcher, _, field| {
+        check_field(field, matcher, result);
     });
 }
@@ -2,7 +2,7 @@ use primitives::{dynamic::DynamicTagDataArray, primitive::TagPath};
 use primitives::tag::PrimaryTagStructDyn;
 use ringhopper_structs::{Globals, ScenarioType};
 use crate::tag::tree::TagTree;
-use super::{ScenarioContext, TagResult};
+use super::{Diagnostic, ScenarioContext, TagResult};
 
 pub fn verify_globals<T: TagTree + Send + Sync>(tag: &dyn PrimaryTagStructDyn, _path: &TagPath, context: &ScenarioContext<T>, result: &mut TagResult) {
     let globals: &Globals = tag.as_any().downcast_ref().unwrap();
@@ -12,26 +12,26 @@ pub fn verify_globals<T: TagTree + Send + Sync>(tag: &dyn PrimaryTagStructDyn, _
 
     if scenario_type == ScenarioType::Multiplayer {
         if globals.multiplayer_information.items.is_empty() {
-            result.errors.push(format!("Multiplayer information reflexive is empty (must have at least 1 for {scenario_type} scenarios)"));
+            result.errors.push(Diagnostic::new("GLB001", format!("Multiplayer information reflexive is empty (must have at least 1 for {scenario_type} scenarios)")).with_field("multiplayer_information"));
         }
         let required_weapons = 16; // TODO: TO BE FILLED BY O.E.M.
         if globals.weapon_list.items.len() < required_weapons {
-            result.errors.push(format!("Weapons list is empty (must have at least {required_weapons} for {scenario_type} scenarios on {engine_name} engines)"));
+            result.errors.push(Diagnostic::new("GLB002", format!("Weapons list is empty (must have at least {required_weapons} for {scenario_type} scenarios on {engine_name} engines)")).with_field("weapon_list"));
         }
         for (i, mp) in ziperator!(globals.multiplayer_information) {
             let required_sounds = 43; // TODO: TO BE FILLED BY O.E.M.
             if mp.sounds.len() < required_sounds {
-                result.warnings.push(format!("Sounds reflexive for MP information #{i} has only {} / {required_sounds} sounds for {engine_name} engines", mp.sounds.len()));
+                result.warnings.push(Diagnostic::new("GLB003", format!("Sounds reflexive for MP information #{i} has only {} / {required_sounds} sounds for {engine_name} engines", mp.sounds.len())).with_field(format!("multiplayer_information[{i}].sounds")));
             }
         }
     }
 
     if scenario_type != ScenarioType::UserInterface {
         if globals.falling_damage.items.is_empty() {
-            result.errors.push(format!("Falling damage reflexive is empty (must have at least 1 for {scenario_type} scenarios)"));
+            result.errors.push(Diagnostic::new("GLB004", format!("Falling damage reflexive is empty (must have at least 1 for {scenario_type} scenarios)")).with_field("falling_damage"));
         }
         if globals.materials.items.len() < 32 {
-            result.errors.push(format!("Materials reflexive is empty (must have at least 32 for {scenario_type} scenarios)"));
+            result.errors.push(Diagnostic::new("GLB005", format!("Materials reflexive is empty (must have at least 32 for {scenario_type} scenarios)")).with_field("materials"));
         }
     }
 
@@ -44,15 +44,15 @@ pub fn verify_globals<T: TagTree + Send + Sync>(tag: &dyn PrimaryTagStructDyn, _
 
         for (i, grenade) in ziperator!(globals.grenades) {
             if grenade.maximum_count > MAX_GRENADE_COUNT_MP {
-                result.errors.push(format!("Grenade #{i}'s maximum count ({}) exceeds the maximum number of grenades ({MAX_GRENADE_COUNT_MP}) supported by the netcode.", grenade.maximum_count));
+                result.errors.push(Diagnostic::new("GLB006", format!("Grenade #{i}'s maximum count ({}) exceeds the maximum number of grenades ({MAX_GRENADE_COUNT_MP}) supported by the netcode.", grenade.maximum_count)).with_field(format!("grenades[{i}].maximum_count")));
             }
             if grenade.mp_spawn_default > MAX_GRENADE_COUNT_MP {
-                result.errors.push(format!("Grenade #{i}'s MP spawn count ({}) exceeds the maximum number of grenades ({MAX_GRENADE_COUNT_MP}) supported by the netcode.", grenade.mp_spawn_default));
+                result.errors.push(Diagnostic::new("GLB007", format!("Grenade #{i}'s MP spawn count ({}) exceeds the maximum number of grenades ({MAX_GRENADE_COUNT_MP}) supported by the netcode.", grenade.mp_spawn_default)).with_field(format!("grenades[{i}].mp_spawn_default")));
             }
         }
 
         if globals.grenades.items.len() > MAX_GRENADE_TYPE_MP {
-            result.errors.push(format!("Grenade reflexive size ({}) exceeds the maximum number of grenades types ({MAX_GRENADE_TYPE_MP}) supported by the netcode.", globals.grenades.items.len()));
+            result.errors.push(Diagnostic::new("GLB008", format!("Grenade reflexive size ({}) exceeds the maximum number of grenades types ({MAX_GRENADE_TYPE_MP}) supported by the netcode.", globals.grenades.items.len())).with_field("grenades"));
         }
     }
 }
@@ -6,7 +6,7 @@ use primitives::primitive::{TagPath, TagReference};
 use primitives::tag::PrimaryTagStructDyn;
 use ringhopper_structs::{Bitmap, GrenadeHUDInterface, HUDGlobals, HUDInterfaceMeterElement, HUDInterfaceStaticElement, UnicodeStringList, UnitHUDInterface};
 use crate::tag::tree::TagTree;
-use crate::tag::verify::TagResult;
+use crate::tag::verify::{Diagnostic, TagResult};
 
 use super::bitmap::{verify_bitmap_sequence_index, SequenceType};
 use super::ScenarioContext;
@@ -28,7 +28,8 @@ pub fn verify_weapon_hud_interface<T: TagTree + Send + Sync + 'static>(tag: &dyn
                 for (ii, inner) in ziperator!(outer.$inner_reflexive) {
                     match verify_bitmap_sequence_index(bitmap, inner.sequence_index, 1, SequenceType::Any) {
                         Ok(_) => (),
-                        Err(e) => result.errors.push(format!("Overlay #{ii} of {} {oi} has an error: {e}", $name))
+                        Err(e) => result.errors.push(Diagnostic::new("HUD001", format!("Overlay #{ii} of {} {oi} has an error: {e}", $name))
+                            .with_field(format!("{}[{oi}].{}[{ii}].sequence_index", stringify!($outer_reflexive), stringify!($inner_reflexive))))
                     }
                 }
             }
@@ -39,11 +40,11 @@ pub fn verify_weapon_hud_interface<T: TagTree + Send + Sync + 'static>(tag: &dyn
     check_sequence_index_for_2d_thing!(overlay_elements, overlay_bitmap, overlays, "overlay element");
 
     for (i, element) in ziperator!(weapon_hud_interface.meter_elements) {
-        check_meter_element(&element.properties, context, result, || format!("Meter element #{i}"))
+        check_meter_element(&element.properties, &format!("meter_elements[{i}].properties"), context, result, || format!("Meter element #{i}"))
     }
 
     for (i, element) in ziperator!(weapon_hud_interface.static_elements) {
-        check_static_element(&element.properties, context, result, || format!("Static element #{i}"))
+        check_static_element(&element.properties, &format!("static_elements[{i}].properties"), context, result, || format!("Static element #{i}"))
     }
 
     verify_no_infinite_loop(weapon_hud_interface, path, context, result);
@@ -52,29 +53,29 @@ pub fn verify_weapon_hud_interface<T: TagTree + Send + Sync + 'static>(tag: &dyn
 pub fn verify_unit_hud_interface<T: TagTree + Send + Sync + 'static>(tag: &dyn PrimaryTagStructDyn, _path: &TagPath, context: &ScenarioContext<T>, result: &mut TagResult) {
     let unit_hud_interface: &UnitHUDInterface = tag.as_any().downcast_ref().unwrap();
 
-    check_static_element(&unit_hud_interface.hud_background, context, result, || "HUD background".to_string());
-    check_static_element(&unit_hud_interface.shield_panel_background, context, result, || "Shield panel background".to_string());
-    check_static_element(&unit_hud_interface.health_panel_background, context, result, || "Health panel background".to_string());
-    check_meter_element(&unit_hud_interface.shield_panel_meter.hudinterface_meter_element, context, result, || "Shield panel meter".to_string());
-    check_meter_element(&unit_hud_interface.health_panel_meter.hudinterface_meter_element, context, result, || "Health panel meter".to_string());
-    check_static_element(&unit_hud_interface.motion_sensor_background, context, result, || "Motion sensor background".to_string());
-    check_static_element(&unit_hud_interface.motion_sensor_foreground, context, result, || "Motion sensor foreground".to_string());
+    check_static_element(&unit_hud_interface.hud_background, "hud_background", context, result, || "HUD background".to_string());
+    check_static_element(&unit_hud_interface.shield_panel_background, "shield_panel_background", context, result, || "Shield panel background".to_string());
+    check_static_element(&unit_hud_interface.health_panel_background, "health_panel_background", context, result, || "Health panel background".to_string());
+    check_meter_element(&unit_hud_interface.shield_panel_meter.hudinterface_meter_element, "shield_panel_meter.hudinterface_meter_element", context, result, || "Shield panel meter".to_string());
+    check_meter_element(&unit_hud_interface.health_panel_meter.hudinterface_meter_element, "health_panel_meter.hudinterface_meter_element", context, result, || "Health panel meter".to_string());
+    check_static_element(&unit_hud_interface.motion_sensor_background, "motion_sensor_background", context, result, || "Motion sensor background".to_string());
+    check_static_element(&unit_hud_interface.motion_sensor_foreground, "motion_sensor_foreground", context, result, || "Motion sensor foreground".to_string());
 
     for (i, element) in ziperator!(unit_hud_interface.auxiliary_elements.overlays) {
-        check_static_element(&element.properties, context, result, || format!("Auxiliary overlay #{i}"));
+        check_static_element(&element.properties, &format!("auxiliary_elements.overlays[{i}].properties"), context, result, || format!("Auxiliary overlay #{i}"));
     }
 
     for (i, element) in ziperator!(unit_hud_interface.auxiliary_elements.meters) {
-        check_static_element(&element.background, context, result, || format!("Auxiliary meter #{i}'s background"));
-        check_meter_element(&element.meter, context, result, || format!("Auxiliary meter #{i}'s meter"));
+        check_static_element(&element.background, &format!("auxiliary_elements.meters[{i}].background"), context, result, || format!("Auxiliary meter #{i}'s background"));
+        check_meter_element(&element.meter, &format!("auxiliary_elements.meters[{i}].meter"), context, result, || format!("Auxiliary meter #{i}'s meter"));
     }
 }
 
 pub fn verify_grenade_hud_interface<T: TagTree + Send + Sync + 'static>(tag: &dyn PrimaryTagStructDyn, _path: &TagPath, context: &ScenarioContext<T>, result: &mut TagResult) {
     let grenade_hud_interface: &GrenadeHUDInterface = tag.as_any().downcast_ref().unwrap();
 
-    check_static_element(&grenade_hud_interface.background, context, result, || "Background".to_string());
-    check_static_element(&grenade_hud_interface.total_grenades_background, context, result, || "Total grenades background".to_string());
+    check_static_element(&grenade_hud_interface.background, "background", context, result, || "Background".to_string());
+    check_static_element(&grenade_hud_interface.total_grenades_background, "total_grenades_background", context, result, || "Total grenades background".to_string());
 
     match context.open_tag_reference_maybe(&grenade_hud_interface.total_grenades_overlays.bitmap, result, None) {
         Some(bitmap) => {
@@ -84,7 +85,8 @@ pub fn verify_grenade_hud_interface<T: TagTree + Send + Sync + 'static>(tag: &dy
             for (i, overlay) in ziperator!(grenade_hud_interface.total_grenades_overlays.overlays) {
                 match verify_bitmap_sequence_index(bitmap, overlay.sequence_index, 1, SequenceType::Any) {
                     Ok(_) => (),
-                    Err(e) => result.errors.push(format!("Total grenade overlay #{i} has an error: {e}"))
+                    Err(e) => result.errors.push(Diagnostic::new("HUD002", format!("Total grenade overlay #{i} has an error: {e}"))
+                        .with_field(format!("total_grenades_overlays.overlays[{i}].sequence_index")))
                 }
             }
         },
@@ -105,23 +107,23 @@ pub fn verify_hud_globals<T: TagTree + Send + Sync + 'static>(tag: &dyn PrimaryT
             });
 
             if out_of_bounds(hud_globals.more_hud_crap.checkpoint_begin_text) {
-                result.errors.push("Checkpoint begin text index is out-of-bounds".to_owned());
+                result.errors.push(Diagnostic::new("HUD003", "Checkpoint begin text index is out-of-bounds").with_field("more_hud_crap.checkpoint_begin_text"));
             }
             if out_of_bounds(hud_globals.more_hud_crap.checkpoint_end_text) {
-                result.errors.push("Checkpoint end text index is out-of-bounds".to_owned());
+                result.errors.push(Diagnostic::new("HUD004", "Checkpoint end text index is out-of-bounds").with_field("more_hud_crap.checkpoint_end_text"));
             }
             if out_of_bounds(hud_globals.more_hud_crap.loading_begin_text) {
-                result.errors.push("Loading begin text index is out-of-bounds".to_owned());
+                result.errors.push(Diagnostic::new("HUD005", "Loading begin text index is out-of-bounds").with_field("more_hud_crap.loading_begin_text"));
             }
             if out_of_bounds(hud_globals.more_hud_crap.loading_end_text) {
-                result.errors.push("Loading end text index is out-of-bounds".to_owned());
+                result.errors.push(Diagnostic::new("HUD006", "Loading end text index is out-of-bounds").with_field("more_hud_crap.loading_end_text"));
             }
         },
         None => ()
     };
 }
 
-fn check_static_element<T: TagTree + Send + Sync + 'static, N: FnOnce() -> String>(element: &HUDInterfaceStaticElement, context: &ScenarioContext<T>, result: &mut TagResult, name: N) {
+fn check_static_element<T: TagTree + Send + Sync + 'static, N: FnOnce() -> String>(element: &HUDInterfaceStaticElement, field: &str, context: &ScenarioContext<T>, result: &mut TagResult, name: N) {
     let bitmap = match context.open_tag_reference_maybe(&element.interface_bitmap, result, None) {
         Some(n) => n,
         None => return
@@ -130,11 +132,11 @@ fn check_static_element<T: TagTree + Send + Sync + 'static, N: FnOnce() -> Strin
     let bitmap = bitmap.as_any_mut().downcast_mut::<Bitmap>().unwrap();
     match verify_bitmap_sequence_index(bitmap, element.sequence_index, 1, SequenceType::Any) {
         Ok(_) => (),
-        Err(e) => result.errors.push(format!("{} has an error: {e}", name()))
+        Err(e) => result.errors.push(Diagnostic::new("HUD007", format!("{} has an error: {e}", name())).with_field(format!("{field}.sequence_index")))
     }
 }
 
-fn check_meter_element<T: TagTree + Send + Sync + 'static, N: FnOnce() -> String>(element: &HUDInterfaceMeterElement, context: &ScenarioContext<T>, result: &mut TagResult, name: N) {
+fn check_meter_element<T: TagTree + Send + Sync + 'static, N: FnOnce() -> String>(element: &HUDInterfaceMeterElement, field: &str, context: &ScenarioContext<T>, result: &mut TagResult, name: N) {
     let bitmap = match context.open_tag_reference_maybe(&element.meter_bitmap, result, None) {
         Some(n) => n,
         None => return
@@ -143,7 +145,7 @@ fn check_meter_element<T: TagTree + Send + Sync + 'static, N: FnOnce() -> String
     let bitmap = bitmap.as_any_mut().downcast_mut::<Bitmap>().unwrap();
     match verify_bitmap_sequence_index(bitmap, element.sequence_index, 1, SequenceType::Any) {
         Ok(_) => (),
-        Err(e) => result.errors.push(format!("{} has an error: {e}", name()))
+        Err(e) => result.errors.push(Diagnostic::new("HUD007", format!("{} has an error: {e}", name())).with_field(format!("{field}.sequence_index")))
     }
 }
 
@@ -153,7 +155,7 @@ fn verify_no_infinite_loop<T: TagTree + Send + Sync + 'static>(base: &WeaponHUDI
     let mut child_ref = base.child_hud.to_owned();
     while let TagReference::Set(n) = &child_ref {
         if all_references.contains(n) {
-            result.errors.push(format!("Infinite loop detected (loop start found at {n})"));
+            result.errors.push(Diagnostic::new("HUD008", format!("Infinite loop detected (loop start found at {n})")).with_field("child_hud"));
             break
         }
 
@@ -0,0 +1,125 @@
+use primitives::dynamic::{DynamicTagData, DynamicTagDataType, SimplePrimitiveType};
+use primitives::primitive::Index;
+use primitives::tag::PrimaryTagStructDyn;
+use crate::tag::result::{Diagnostic, TagResult};
+
+#[cfg(test)]
+mod test;
+
+/// Index that is out of bounds of the reflexive it refers to.
+pub(crate) struct BadIndex {
+    /// Matcher for the index field, such as `nodes[2].parent_node_index`.
+    pub matcher: String,
+    pub index: u16,
+    pub target: &'static str,
+    pub target_length: usize
+}
+
+/// Find all indices that name a reflexive in the definitions but are out of bounds of it.
+///
+/// Indices whose reflexive cannot be found are not checked.
+pub(crate) fn find_bad_indices(tag: &dyn DynamicTagData) -> Vec<BadIndex> {
+    /// Field names and element indices from the root of the tag to the block being checked.
+    type Location<'a> = Vec<(&'a str, Option<usize>)>;
+
+    fn check_block<'a>(root: &dyn DynamicTagData, block: &'a dyn DynamicTagData, location: &mut Location<'a>, matcher: &mut String, bad_indices: &mut Vec<BadIndex>) {
+        for field_name in block.fields() {
+            let field = block.get_field(field_name).unwrap();
+            let length_before = matcher.len();
+            if !matcher.is_empty() {
+                matcher.push('.');
+            }
+            *matcher += field_name;
+
+            if let Some(target) = block.get_metadata_for_field(field_name).and_then(|m| m.index_target) {
+                if let Some(target_length) = resolve_index_target(root, target, location).and_then(|t| t.as_array()).map(|a| a.len()) {
+                    check_index(field, target, target_length, matcher, bad_indices);
+                }
+            }
+            else if let Some(array) = field.as_array() {
+                for i in 0..array.len() {
+                    let item = array.get_at_index(i).unwrap();
+                    if item.fields().is_empty() {
+                        break
+                    }
+                    let length_before = matcher.len();
+                    *matcher += &format!("[{i}]");
+                    location.push((field_name, Some(i)));
+                    check_block(root, item, location, matcher, bad_indices);
+                    location.pop();
+                    matcher.truncate(length_before);
+                }
+            }
+            else if !field.fields().is_empty() {
+                location.push((field_name, None));
+                check_block(root, field, location, matcher, bad_indices);
+                location.pop();
+            }
+
+            matcher.truncate(length_before);
+        }
+    }
+
+    fn check_index(field: &dyn DynamicTagData, target: &'static str, target_length: usize, matcher: &mut String, bad_indices: &mut Vec<BadIndex>) {
+        match field.data_type() {
+            DynamicTagDataType::SimplePrimitive(SimplePrimitiveType::Index) => {
+                if let Some(index) = *field.as_any().downcast_ref::<Index>().unwrap() {
+                    if index as usize >= target_length {
+                        bad_indices.push(BadIndex { matcher: matcher.clone(), index, target, target_length });
+                    }
+                }
+            },
+            DynamicTagDataType::Array => {
+                let array = field.as_array().unwrap();
+                for i in 0..array.len() {
+                    let length_before = matcher.len();
+                    *matcher += &format!("[{i}]");
+                    check_index(array.get_at_index(i).unwrap(), target, target_length, matcher, bad_indices);
+                    matcher.truncate(length_before);
+                }
+            },
+            _ => ()
+        }
+    }
+
+    let mut bad_indices = Vec::new();
+    check_block(tag, tag, &mut Vec::new(), &mut String::new(), &mut bad_indices);
+    bad_indices
+}
+
+/// Find the reflexive an index refers to.
+///
+/// `target` is the path of the reflexive from the root of the tag, such as `regions.permutations.markers`. Every
+/// reflexive along the way must contain the index, and the element it is in is used.
+fn resolve_index_target<'a>(root: &'a dyn DynamicTagData, target: &str, location: &[(&str, Option<usize>)]) -> Option<&'a dyn DynamicTagData> {
+    let mut data = root;
+    let mut location = location.iter();
+    let mut segments = target.split('.').peekable();
+    while let Some(segment) = segments.next() {
+        data = data.get_field(segment)?;
+        if segments.peek().is_none() {
+            break
+        }
+
+        let (field, element) = location.next()?;
+        if *field != segment {
+            return None
+        }
+        if let Some(element) = element {
+            data = data.as_array()?.get_at_index(*element)?;
+        }
+    }
+    Some(data)
+}
+
+pub fn check_bad_indices(tag: &dyn PrimaryTagStructDyn, result: &mut TagResult) {
+    for bad in find_bad_indices(tag.as_dynamic()) {
+        result.errors.push(Diagnostic::new("IDX001", format!(
+            "{} is set to {}, but {} only has {} element(s). This can be automatically fixed.",
+            bad.matcher,
+            bad.index,
+            bad.target,
+            bad.target_length
+        )).with_field(bad.matcher));
+    }
+}
@@ -0,0 +1,122 @@
+use std::any::Any;
+use primitives::dynamic::{DynamicTagDataType, TagFieldMetadata};
+use primitives::error::RinghopperResult;
+use primitives::map::{DomainType, Map, MapWriteContext};
+use primitives::parse::{TagData, TagDataDefaults};
+use primitives::primitive::Reflexive;
+use super::*;
+
+fn index_of(target: &'static str) -> TagFieldMetadata {
+    TagFieldMetadata { index_target: Some(target), ..Default::default() }
+}
+
+/// Define a block that can only be accessed dynamically.
+macro_rules! block {
+    ($name:ident { $($field:ident: $field_type:ty = $metadata:expr),* }) => {
+        #[derive(Clone, Default)]
+        struct $name {
+            $($field: $field_type),*
+        }
+
+        impl TagData for $name {
+            fn size() -> usize {
+                0 $(+ <$field_type>::size())*
+            }
+            fn read_from_tag_file(_data: &[u8], _at: usize, _struct_end: usize, _extra_data_cursor: &mut usize) -> RinghopperResult<Self> {
+                unimplemented!()
+            }
+            fn write_to_tag_file(&self, _data: &mut Vec<u8>, _at: usize, _struct_end: usize) -> RinghopperResult<()> {
+                unimplemented!()
+            }
+            fn read_from_map<M: Map>(_map: &M, _address: usize, _domain_type: &DomainType) -> RinghopperResult<Self> {
+                unimplemented!()
+            }
+            fn write_to_map(&self, _data: &mut Vec<u8>, _at: usize, _struct_end: usize, _context: &mut MapWriteContext) -> RinghopperResult<()> {
+                unimplemented!()
+            }
+        }
+
+        impl TagDataDefaults for $name {}
+
+        impl DynamicTagData for $name {
+            fn get_field(&self, field: &str) -> Option<&dyn DynamicTagData> {
+                match field {
+                    $(stringify!($field) => Some(&self.$field),)*
+                    _ => None
+                }
+            }
+            fn get_field_mut(&mut self, field: &str) -> Option<&mut dyn DynamicTagData> {
+                match field {
+                    $(stringify!($field) => Some(&mut self.$field),)*
+                    _ => None
+                }
+            }
+            fn get_metadata_for_field(&self, field: &str) -> Option<TagFieldMetadata> {
+                match field {
+                    $(stringify!($field) => Some($metadata),)*
+                    _ => None
+                }
+            }
+            fn fields(&self) -> &'static [&'static str] {
+                &[$(stringify!($field)),*]
+            }
+            fn as_any(&self) -> &dyn Any {
+                self
+            }
+            fn as_any_mut(&mut self) -> &mut dyn Any {
+                self
+            }
+            fn data_type(&self) -> DynamicTagDataType {
+                DynamicTagDataType::Block
+            }
+        }
+    };
+}
+
+// `items` is both a reflexive of the root and of each group, and the leaves refer to both.
+block!(Leaf {
+    root_item: Index = index_of("items"),
+    group_item: Index = index_of("items.items"),
+    sibling: Index = index_of("items.leaves")
+});
+block!(Item { unused: u16 = TagFieldMetadata::default() });
+block!(Group {
+    items: Reflexive<Item> = TagFieldMetadata::default(),
+    leaves: Reflexive<Leaf> = TagFieldMetadata::default()
+});
+block!(Root {
+    items: Reflexive<Group> = TagFieldMetadata::default(),
+    orphan: Index = index_of("missing")
+});
+
+fn leaf(root_item: u16, group_item: u16, sibling: u16) -> Leaf {
+    Leaf { root_item: Some(root_item), group_item: Some(group_item), sibling: Some(sibling) }
+}
+
+fn group(item_count: usize, leaves: Vec<Leaf>) -> Group {
+    Group { items: Reflexive::new(vec![Item::default(); item_count]), leaves: Reflexive::new(leaves) }
+}
+
+#[test]
+fn index_target_is_resolved_from_the_root() {
+    let root = Root {
+        items: Reflexive::new(vec![
+            group(4, vec![leaf(1, 3, 0), leaf(2, 4, 2)]),
+            group(1, vec![leaf(3, 0, 1)])
+        ]),
+        orphan: Some(100)
+    };
+
+    let bad: Vec<(String, u16, &str, usize)> = find_bad_indices(&root)
+        .into_iter()
+        .map(|b| (b.matcher, b.index, b.target, b.target_length))
+        .collect();
+
+    assert_eq!(bad, [
+        ("items[0].leaves[1].root_item".to_owned(), 2, "items", 2),
+        ("items[0].leaves[1].group_item".to_owned(), 4, "items.items", 4),
+        ("items[0].leaves[1].sibling".to_owned(), 2, "items.leaves", 2),
+        ("items[1].leaves[0].root_item".to_owned(), 3, "items", 2),
+        ("items[1].leaves[0].sibling".to_owned(), 1, "items.leaves", 1)
+    ]);
+}
@@ -3,7 +3,7 @@ use ringhopper_structs::{GBXModel, Model};
 use crate::tag::tree::TagTree;
 use crate::tag::model::ModelFunctions;
 use crate::primitives::primitive::Vector;
-use super::{ScenarioContext, TagResult};
+use super::{Diagnostic, ScenarioContext, TagResult};
 
 macro_rules! write_verify_model_fn {
     ($name:tt, $group:tt) => {
@@ -11,33 +11,35 @@ macro_rules! write_verify_model_fn {
             let model: &$group = tag.as_any().downcast_ref().unwrap();
 
             if let Err(e) = model.check_indices() {
-                result.errors.push(format!("Error occurred while checking vertex indices: {e}"));
+                result.errors.push(Diagnostic::new("MOD001", format!("Error occurred while checking vertex indices: {e}")));
             }
 
             if !model.runtime_markers.items.is_empty() {
-                result.errors.push("Runtime markers are non-empty. This tag is invalid and needs fixed! This can be automatically repaired with the bludgeon command.".to_string());
+                result.errors.push(Diagnostic::new("MOD002", "Runtime markers are non-empty. This tag is invalid and needs fixed! This can be automatically repaired with the bludgeon command.").with_field("runtime_markers"));
             }
 
-            let mut contains_non_normal_vectors = false;
-            for node in model.nodes() {
-                if !node.default_rotation.is_unit_vector() {
-                    contains_non_normal_vectors = true;
-                }
-            }
+            // Report the first non-normal vector found
+            let mut non_normal_vector = model
+                .nodes()
+                .iter()
+                .position(|node| !node.default_rotation.is_unit_vector())
+                .map(|n| format!("nodes[{n}].default_rotation"));
 
-            'outer: for region in model.regions() {
-                for permutation in &region.permutations {
-                    for marker in &permutation.markers {
-                        if !marker.rotation.is_unit_vector() {
-                            contains_non_normal_vectors = true;
-                            break 'outer;
+            if non_normal_vector.is_none() {
+                'outer: for (r, region) in model.regions().iter().enumerate() {
+                    for (p, permutation) in region.permutations.items.iter().enumerate() {
+                        for (m, marker) in permutation.markers.items.iter().enumerate() {
+                            if !marker.rotation.is_unit_vector() {
+                                non_normal_vector = Some(format!("regions[{r}].permutations[{p}].markers[{m}].rotation"));
+                                break 'outer;
+                            }
                         }
                     }
                 }
             }
 
-            if contains_non_normal_vectors {
-                result.errors.push("Non-normal rotation vectors detected. This can be automatically repaired with the bludgeon command.".to_owned());
+            if let Some(field) = non_normal_vector {
+                result.errors.push(Diagnostic::new("MOD003", "Non-normal rotation vectors detected. This can be automatically repaired with the bludgeon command.").with_field(field));
             }
         }
     };
@@ -1,16 +1,28 @@
 use definitions::{Bitmap, Weapon, WeaponHUDInterface};
 use primitives::dynamic::DynamicTagDataArray;
-use primitives::primitive::TagPath;
+use primitives::primitive::{TagGroup, TagPath};
 use primitives::tag::PrimaryTagStructDyn;
 use ringhopper_structs::{GBXModel, HUDGlobals, Model, ModelAnimations, UnicodeStringList, WeaponHUDInterfaceCrosshairType};
 use crate::tag::object::downcast_base_object;
 use crate::tag::tree::TagTree;
-use crate::tag::verify::TagResult;
+use crate::tag::verify::{Diagnostic, TagResult};
 use super::bitmap::{verify_bitmap_sequence_index, SequenceType};
 use super::ScenarioContext;
 
 const IGNORED_MODEL_NODE_LIST_CHECKSUM: i32 = 0;
 
+/// Get the path of a field in the base object struct of a tag of the given group.
+fn object_field(group: TagGroup, field: &str) -> String {
+    let base = match group {
+        TagGroup::Object => "",
+        TagGroup::Biped | TagGroup::Vehicle => "unit.object.",
+        TagGroup::Weapon | TagGroup::Garbage | TagGroup::Equipment => "item.object.",
+        TagGroup::DeviceMachine | TagGroup::DeviceControl | TagGroup::DeviceLightFixture => "device.object.",
+        _ => "object."
+    };
+    format!("{base}{field}")
+}
+
 pub fn verify_object<T: TagTree + Send + Sync + 'static>(tag: &dyn PrimaryTagStructDyn, _path: &TagPath, context: &ScenarioContext<T>, result: &mut TagResult) {
     let object = downcast_base_object(tag).unwrap();
 
@@ -18,10 +30,10 @@ pub fn verify_object<T: TagTree + Send + Sync + 'static>(tag: &dyn PrimaryTagStr
 
     if object.model.is_null() {
         if !object.animation_graph.is_null() {
-            result.errors.push("Object has no model but has an animation graph.".to_string());
+            result.errors.push(Diagnostic::new("OBJ001", "Object has no model but has an animation graph.").with_field(object_field(tag.group(), "animation_graph")));
         }
         if !object.collision_model.is_null() {
-            result.errors.push("Object has no model but has a collision model.".to_string());
+            result.errors.push(Diagnostic::new("OBJ002", "Object has no model but has a collision model.").with_field(object_field(tag.group(), "collision_model")));
         }
     }
 
@@ -39,7 +51,7 @@ pub fn verify_object<T: TagTree + Send + Sync + 'static>(tag: &dyn PrimaryTagStr
 
                             for i in &anim.animations {
                                 if i.node_list_checksum != IGNORED_MODEL_NODE_LIST_CHECKSUM && i.node_list_checksum != model.node_list_checksum {
-                                    result.errors.push("Object has mismatched model and animations (node list checksum is nonzero and does not match for one or more animations).".to_string());
+                                    result.errors.push(Diagnostic::new("OBJ003", "Object has mismatched model and animations (node list checksum is nonzero and does not match for one or more animations).").with_field(object_field(tag.group(), "animation_graph")));
                                     break
                                 }
                             }
@@ -64,7 +76,7 @@ pub fn verify_object<T: TagTree + Send + Sync + 'static>(tag: &dyn PrimaryTagStr
         let index = object.hud_text_message_index;
         let max = item_message_text.as_any().downcast_ref::<UnicodeStringList>().unwrap().strings.len();
         if max <= index as usize {
-            result.errors.push(format!("HUD text message index ({index}) is out-of-bounds (only {max} strings in {})", hud_globals.messaging_parameters.item_message_text.path().unwrap()));
+            result.errors.push(Diagnostic::new("OBJ004", format!("HUD text message index ({index}) is out-of-bounds (only {max} strings in {})", hud_globals.messaging_parameters.item_message_text.path().unwrap())).with_field(object_field(tag.group(), "hud_text_message_index")));
         }
     }
 }
@@ -96,7 +108,7 @@ pub fn verify_weapon<T: TagTree + Send + Sync + 'static>(tag: &dyn PrimaryTagStr
                         overlay.sequence_index,
                         zoom_levels,
                         match overlay.flags.not_a_sprite { true => SequenceType::Bitmap, false => SequenceType::Sprite }) {
-                        result.errors.push(format!("HUD overlay #{overlay_index} of crosshair #{crosshair_index} has an error: {e}"));
+                        result.errors.push(Diagnostic::new("OBJ005", format!("HUD overlay #{overlay_index} of crosshair #{crosshair_index} has an error: {e}")));
                     }
                 }
             }
@@ -3,7 +3,7 @@ use ringhopper_structs::Particle;
 
 use crate::tag::tree::TagTree;
 
-use super::{ScenarioContext, TagResult};
+use super::{Diagnostic, ScenarioContext, TagResult};
 
 pub fn verify_particle<T: TagTree + Send + Sync + 'static>(tag: &dyn PrimaryTagStructDyn, _path: &TagPath, _context: &ScenarioContext<T>, result: &mut TagResult) {
     let particle: &Particle = tag.as_any().downcast_ref().unwrap();
@@ -16,6 +16,6 @@ pub fn verify_particle<T: TagTree + Send + Sync + 'static>(tag: &dyn PrimaryTagS
             ("end", "start", particle.fade_start_size)
         };
 
-        result.warnings.push(format!("fade {zero} size is zero, thus fade {defaulted} size's value ({value}) will be overwritten"))
+        result.warnings.push(Diagnostic::new("PRT001", format!("fade {zero} size is zero, thus fade {defaulted} size's value ({value}) will be overwritten")))
     }
 }
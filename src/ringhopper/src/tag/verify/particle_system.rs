@@ -3,7 +3,7 @@ use ringhopper_structs::{Bitmap, ParticleSystem, ParticleSystemComplexSpriteRend
 
 use crate::tag::tree::TagTree;
 
-use super::{bitmap::{verify_bitmap_sequence_index, SequenceType}, ScenarioContext, TagResult};
+use super::{bitmap::{verify_bitmap_sequence_index, SequenceType}, Diagnostic, ScenarioContext, TagResult};
 
 pub fn verify_particle_system<T: TagTree + Send + Sync + 'static>(tag: &dyn PrimaryTagStructDyn, _path: &TagPath, context: &ScenarioContext<T>, result: &mut TagResult) {
     let particle_system: &ParticleSystem = tag.as_any().downcast_ref().unwrap();
@@ -29,10 +29,10 @@ pub fn verify_particle_system<T: TagTree + Send + Sync + 'static>(tag: &dyn Prim
 
             for required in 0..required {
                 if let Err(e) = verify_bitmap_sequence_index(bitmap, Some(sequence_index + required), 1, SequenceType::Sprite) {
-                    result.errors.push(format!("Particle state #{s} of particle type #{t} has an error with its sequence index: {e}"));
+                    result.errors.push(Diagnostic::new("PSY001", format!("Particle state #{s} of particle type #{t} has an error with its sequence index: {e}")).with_field(format!("particle_types[{t}].particle_states[{s}].sequence_index")));
 
                     if required == 1 {
-                        result.errors.push(format!("The referenced bitmap must have an additional, valid sequence after its first sequence for render mode `{}`", ptype.complex_sprite_render_modes))
+                        result.errors.push(Diagnostic::new("PSY002", format!("The referenced bitmap must have an additional, valid sequence after its first sequence for render mode `{}`", ptype.complex_sprite_render_modes)).with_field(format!("particle_types[{t}].complex_sprite_render_modes")))
                     }
                 }
             }
@@ -0,0 +1,91 @@
+use primitives::dynamic::{DynamicTagData, DynamicTagDataType, SimplePrimitiveType, TagFieldMetadata};
+use primitives::primitive::Angle;
+use primitives::tag::{for_each_field_with_matcher, PrimaryTagStructDyn};
+use crate::tag::result::{Diagnostic, TagResult};
+
+/// Angles are stored in radians but limited in degrees, so allow for rounding error when converting.
+const ANGLE_EPSILON: f64 = 0.0001;
+
+/// Get the value of a field that can be limited by a minimum and maximum.
+///
+/// Angles are returned in degrees, and enums are returned as their integer value. Returns `None` if the field cannot
+/// be limited.
+pub(crate) fn get_ranged_value(field: &dyn DynamicTagData) -> Option<f64> {
+    let any = field.as_any();
+    match field.data_type() {
+        DynamicTagDataType::SimplePrimitive(t) => match t {
+            SimplePrimitiveType::I8 => any.downcast_ref::<i8>().map(|v| *v as f64),
+            SimplePrimitiveType::U8 => any.downcast_ref::<u8>().map(|v| *v as f64),
+            SimplePrimitiveType::I16 => any.downcast_ref::<i16>().map(|v| *v as f64),
+            SimplePrimitiveType::U16 => any.downcast_ref::<u16>().map(|v| *v as f64),
+            SimplePrimitiveType::I32 => any.downcast_ref::<i32>().map(|v| *v as f64),
+            SimplePrimitiveType::U32 => any.downcast_ref::<u32>().map(|v| *v as f64),
+            SimplePrimitiveType::Float => any.downcast_ref::<f32>().map(|v| *v as f64),
+            SimplePrimitiveType::Angle => any.downcast_ref::<Angle>().map(|v| v.to_degrees() as f64),
+            _ => None
+        },
+        DynamicTagDataType::Enum => field.as_enum().map(|e| e.get_enum_integer_value() as f64),
+        _ => None
+    }
+}
+
+/// Get the value `value` should be clamped to if it is outside of the field's limits.
+///
+/// Returns `None` if the value is in range.
+pub(crate) fn clamp_ranged_value(field: &dyn DynamicTagData, value: f64, metadata: &TagFieldMetadata) -> Option<f64> {
+    let epsilon = if field.data_type() == DynamicTagDataType::SimplePrimitive(SimplePrimitiveType::Angle) {
+        ANGLE_EPSILON
+    }
+    else {
+        0.0
+    };
+
+    match (metadata.minimum, metadata.maximum) {
+        (Some(minimum), _) if value < minimum - epsilon => Some(minimum),
+        (_, Some(maximum)) if value > maximum + epsilon => Some(maximum),
+        _ => None
+    }
+}
+
+/// Call `predicate` on the field if it can be limited, or on each of its elements if it is an array, such as bounds.
+///
+/// `matcher` is the matcher for the field, and elements are appended to it.
+fn for_each_ranged_value<P: FnMut(&str, &dyn DynamicTagData, f64)>(field: &dyn DynamicTagData, matcher: &mut String, predicate: &mut P) {
+    if let Some(array) = field.as_array() {
+        for i in 0..array.len() {
+            let length_before = matcher.len();
+            *matcher += &format!("[{i}]");
+            for_each_ranged_value(array.get_at_index(i).unwrap(), matcher, predicate);
+            matcher.truncate(length_before);
+        }
+    }
+    else if let Some(value) = get_ranged_value(field) {
+        predicate(matcher, field, value);
+    }
+}
+
+pub fn check_out_of_range_values(tag: &dyn PrimaryTagStructDyn, result: &mut TagResult) {
+    for_each_field_with_matcher(tag.as_dynamic(), |matcher, metadata, field| {
+        let Some(metadata) = metadata.filter(|m| m.minimum.is_some() || m.maximum.is_some()) else {
+            return
+        };
+
+        let range = format!(
+            "{} to {}",
+            metadata.minimum.map(|m| m.to_string()).unwrap_or_else(|| "-inf".to_owned()),
+            metadata.maximum.map(|m| m.to_string()).unwrap_or_else(|| "inf".to_owned())
+        );
+
+        for_each_ranged_value(field, &mut matcher.to_owned(), &mut |matcher, field, value| {
+            if clamp_ranged_value(field, value, &metadata).is_none() {
+                return
+            }
+            if let Some(e) = field.as_enum() {
+                result.errors.push(Diagnostic::new("RNG001", format!("{matcher} is set to {} ({value}), which is outside of the allowed range ({range}). This can be automatically fixed.", e.get_enum_string_value())).with_field(matcher));
+            }
+            else {
+                result.errors.push(Diagnostic::new("RNG002", format!("{matcher} is set to {value}, which is outside of the allowed range ({range}). This can be automatically fixed.")).with_field(matcher));
+            }
+        });
+    });
+}
@@ -1,17 +1,42 @@
+use primitives::byteorder::BigEndian;
+use primitives::parse::SimpleTagData;
 use primitives::{primitive::TagPath, tag::PrimaryTagStructDyn};
-use ringhopper_structs::Scenario;
+use ringhopper_structs::{Scenario, ScenarioScriptNodeTable};
 
 use crate::tag::tree::TagTree;
 
-use super::{ScenarioContext, TagResult};
+use super::{Diagnostic, ScenarioContext, TagResult};
 
-pub fn verify_scenario<T: TagTree + Send + Sync + 'static>(tag: &dyn PrimaryTagStructDyn, _path: &TagPath, _context: &ScenarioContext<T>, result: &mut TagResult) {
+pub fn verify_scenario<T: TagTree + Send + Sync + 'static>(tag: &dyn PrimaryTagStructDyn, _path: &TagPath, context: &ScenarioContext<T>, result: &mut TagResult) {
     let scenario: &Scenario = tag.as_any().downcast_ref().unwrap();
     if scenario_missing_source_data(scenario) {
-        result.errors.push("No source data, but scripts/globals detected".to_owned())
+        result.errors.push(Diagnostic::new("SCN001", "No source data, but scripts/globals detected").with_field("source_files"))
     }
+
+    verify_script_node_count(scenario, context, result);
 }
 
 pub fn scenario_missing_source_data(tag: &Scenario) -> bool {
     tag.source_files.items.is_empty() && !(tag.scripts.items.is_empty() && tag.globals.items.is_empty())
 }
+
+fn verify_script_node_count<T: TagTree + Send + Sync + 'static>(scenario: &Scenario, context: &ScenarioContext<T>, result: &mut TagResult) {
+    let syntax_data = &scenario.script_syntax_data.bytes;
+    if syntax_data.is_empty() {
+        return
+    }
+
+    let Ok(table) = ScenarioScriptNodeTable::read::<BigEndian>(syntax_data, 0, syntax_data.len()) else {
+        result.errors.push(Diagnostic::new("SCN002", "Can't read script node table from scenario; scripts need recompiled!").with_field("script_syntax_data"));
+        return
+    };
+
+    // This matches the limit used when compiling scripts.
+    let engine = context.engine;
+    let max_nodes = engine.max_script_nodes.min(u16::MAX as u64 - 1);
+    let node_count = table.size as u64;
+
+    if node_count > max_nodes {
+        result.errors.push(Diagnostic::new("SCN003", format!("Scripts use {node_count} node(s), but engine `{}` only supports {max_nodes}", engine.name)).with_field("script_syntax_data"));
+    }
+}
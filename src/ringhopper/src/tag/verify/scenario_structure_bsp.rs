@@ -3,35 +3,35 @@ use ringhopper_structs::*;
 
 use crate::tag::tree::TagTree;
 
-use super::{ScenarioContext, TagResult};
+use super::{Diagnostic, ScenarioContext, TagResult};
 
 pub fn verify_scenario_structure_bsp<T: TagTree + Send + Sync + 'static>(tag: &dyn PrimaryTagStructDyn, _path: &TagPath, context: &ScenarioContext<T>, result: &mut TagResult) {
     let scenario_structure_bsp: &ScenarioStructureBSP = tag.as_any().downcast_ref().unwrap();
 
     // Check this before we can proceed.
     if !check_scenario_structure_bsp_vertex_data_size_correct(scenario_structure_bsp) {
-        result.errors.push("BSP material(s) contain bad lightmap/render size(s). This tag needs remade.".to_owned());
+        result.errors.push(Diagnostic::new("SBS001", "BSP material(s) contain bad lightmap/render size(s). This tag needs remade."));
         return;
     }
 
     let mut complained_bitfield: u32 = 0;
 
-    for obj in &scenario_structure_bsp.detail_objects {
+    for (o, obj) in ziperator!(scenario_structure_bsp.detail_objects) {
         'next_cell: for (cell_i, cell) in ziperator!(obj.cells) {
             let start = cell.count_index as usize;
             let count = cell.valid_layers_flags.count_ones() as usize;
             let Ok(end) = count.add_overflow_checked(start) else {
-                result.errors.push(format!("BSP detail object cell #{cell_i} has an invalid count index and range (out-of-bounds)"));
+                result.errors.push(Diagnostic::new("SBS002", format!("BSP detail object cell #{cell_i} has an invalid count index and range (out-of-bounds)")).with_field(format!("detail_objects[{o}].cells[{cell_i}]")));
                 continue 'next_cell;
             };
 
             let reference_vector_range = start..end;
             let Some(counts) = obj.counts.items.get(reference_vector_range.clone()) else {
-                result.errors.push(format!("BSP detail object cell #{cell_i} has an invalid count index and range (can't get counts {reference_vector_range:?})"));
+                result.errors.push(Diagnostic::new("SBS003", format!("BSP detail object cell #{cell_i} has an invalid count index and range (can't get counts {reference_vector_range:?})")).with_field(format!("detail_objects[{o}].cells[{cell_i}]")));
                 continue 'next_cell;
             };
             let Some(_) = obj.z_reference_vectors.items.get(reference_vector_range.clone()) else {
-                result.errors.push(format!("BSP detail object cell #{cell_i} has an invalid count index and range (can't get z references {reference_vector_range:?})"));
+                result.errors.push(Diagnostic::new("SBS004", format!("BSP detail object cell #{cell_i} has an invalid count index and range (can't get z references {reference_vector_range:?})")).with_field(format!("detail_objects[{o}].cells[{cell_i}]")));
                 continue 'next_cell;
             };
 
@@ -40,12 +40,12 @@ pub fn verify_scenario_structure_bsp<T: TagTree + Send + Sync + 'static>(tag: &d
             for c in counts {
                 let count = c.count as usize;
                 let Ok(end) = start_index.add_overflow_checked(count) else {
-                    result.errors.push(format!("BSP detail object cell #{cell_i} has an invalid count (out-of-bounds)"));
+                    result.errors.push(Diagnostic::new("SBS005", format!("BSP detail object cell #{cell_i} has an invalid count (out-of-bounds)")).with_field(format!("detail_objects[{o}].cells[{cell_i}]")));
                     continue 'next_cell;
                 };
                 let range = start_index..end;
                 if let None = obj.instances.items.get(range.clone()) {
-                    result.errors.push(format!("BSP detail object cell #{cell_i} has an invalid count (can't get instances {range:?})"));
+                    result.errors.push(Diagnostic::new("SBS006", format!("BSP detail object cell #{cell_i} has an invalid count (can't get instances {range:?})")).with_field(format!("detail_objects[{o}].cells[{cell_i}]")));
                     continue 'next_cell;
                 }
                 start_index = end;
@@ -71,7 +71,7 @@ pub fn verify_scenario_structure_bsp<T: TagTree + Send + Sync + 'static>(tag: &d
                     let bit = 1 << bit_offset;
                     if (complained_bitfield & bit) != 0 {
                         complained_bitfield |= bit;
-                        result.errors.push(format!("Missing detail object collection palette #{bit_offset} in scenario tag"));
+                        result.errors.push(Diagnostic::new("SBS007", format!("Missing detail object collection palette #{bit_offset} in scenario tag")));
                     }
                     continue;
                 };
@@ -98,7 +98,7 @@ pub fn verify_scenario_structure_bsp<T: TagTree + Send + Sync + 'static>(tag: &d
         }
     }
     if non_normal_vectors_found {
-        result.errors.push("Non-normal vectors detected! This can be automatically repaired with the bludgeon command.".to_owned());
+        result.errors.push(Diagnostic::new("SBS008", "Non-normal vectors detected! This can be automatically repaired with the bludgeon command."));
     }
 }
 
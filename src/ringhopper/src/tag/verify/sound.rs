@@ -1,9 +1,9 @@
 use primitives::{dynamic::DynamicTagDataArray, primitive::TagPath, tag::PrimaryTagStructDyn};
-use ringhopper_structs::{Sound, SoundChannelCount, SoundFormat, SoundPermutation, SoundPitchRange};
+use ringhopper_structs::{Sound, SoundChannelCount, SoundFormat};
 
-use crate::tag::{sound::{sample_rate_to_u32, SoundPermutationMetadata}, tree::TagTree};
+use crate::tag::{sound::{find_actual_permutation_count, next_subpermutation_index, sample_rate_to_u32, sound_tag_actually_contains_split_permutations, SoundPermutationMetadata}, tree::TagTree};
 
-use super::{ScenarioContext, TagResult};
+use super::{Diagnostic, ScenarioContext, TagResult};
 
 pub fn verify_sound<T: TagTree + Send + Sync>(tag: &dyn PrimaryTagStructDyn, _path: &TagPath, _context: &ScenarioContext<T>, result: &mut TagResult) {
     let sound: &Sound = tag.as_any().downcast_ref().unwrap();
@@ -12,10 +12,10 @@ pub fn verify_sound<T: TagTree + Send + Sync>(tag: &dyn PrimaryTagStructDyn, _pa
     check_errors_with_sound(sound, result);
     if result.errors.len() != error_count_start {
         if sound_tag_is_fubar(sound) {
-            result.errors.push("These errors can NOT be automatically repaired.".to_owned());
+            result.errors.push(Diagnostic::new("SND001", "These errors can NOT be automatically repaired."));
         }
         else {
-            result.errors.push("These errors can be automatically repaired with the bludgeon command.".to_owned());
+            result.errors.push(Diagnostic::new("SND002", "These errors can be automatically repaired with the bludgeon command."));
         }
     }
 
@@ -33,7 +33,7 @@ pub fn verify_sound<T: TagTree + Send + Sync>(tag: &dyn PrimaryTagStructDyn, _pa
         };
 
         if !pitch_range_ok {
-            result.warnings.push(format!("Pitch range #{p}'s bend bounds does not fit the natural pitch value ({actual_natural_pitch}) and will be adjusted."));
+            result.warnings.push(Diagnostic::new("SND003", format!("Pitch range #{p}'s bend bounds does not fit the natural pitch value ({actual_natural_pitch}) and will be adjusted.")).with_field(format!("pitch_ranges[{p}].bend_bounds")));
         }
     }
 }
@@ -53,25 +53,9 @@ fn check_errors_with_sound(sound: &Sound, verify_result: &mut TagResult) {
     verify_actual_permutation_count_is_correctly_set(sound, verify_result);
 }
 
-pub(crate) fn sound_tag_actually_contains_split_permutations(sound: &Sound) -> bool {
-    if sound.flags.split_long_sound_into_permutations {
-        return true;
-    }
-
-    for pitch_range in &sound.pitch_ranges {
-        for permutation in &pitch_range.permutations {
-            if next_subpermutation_index(&permutation).is_some() {
-                return true;
-            }
-        }
-    }
-
-    false
-}
-
 fn verify_split_permutation_flag_should_be_set_but_is_not(sound: &Sound, verify_result: &mut TagResult) {
     if sound.flags.split_long_sound_into_permutations != sound_tag_actually_contains_split_permutations(sound) {
-        verify_result.errors.push("Detected split permutations, but the split permutations flag isn't set".to_owned());
+        verify_result.errors.push(Diagnostic::new("SND004", "Detected split permutations, but the split permutations flag isn't set").with_field("flags.split_long_sound_into_permutations"));
     }
 }
 
@@ -105,7 +89,7 @@ fn verify_actual_permutation_count_is_correctly_set(sound: &Sound, result: &mut
         let actual = find_actual_permutation_count(sound, pitch_range);
         let expected = pitch_range.actual_permutation_count;
         if expected != actual {
-            result.errors.push(format!("Actual permutation count of pitch range #{pr} is incorrect (expected {expected} but calculated {actual})"));
+            result.errors.push(Diagnostic::new("SND005", format!("Actual permutation count of pitch range #{pr} is incorrect (expected {expected} but calculated {actual})")).with_field(format!("pitch_ranges[{pr}].actual_permutation_count")));
             issues_found = true;
         }
     }
@@ -113,35 +97,6 @@ fn verify_actual_permutation_count_is_correctly_set(sound: &Sound, result: &mut
     !issues_found
 }
 
-fn next_subpermutation_index(permutation: &SoundPermutation) -> Option<u16> {
-    match permutation.next_permutation_index {
-        Some(0) | None => None,
-        Some(n) => Some(n)
-    }
-}
-
-/// Return `None` if the permutation count cannot be calculated.
-pub(crate) fn find_actual_permutation_count(sound: &Sound, pitch_range: &SoundPitchRange) -> u16 {
-    let permutation_count_16_bit = u16::try_from(pitch_range.permutations.items.len()).expect("should be < 65535");
-    let split_permutations = sound_tag_actually_contains_split_permutations(sound);
-
-    if split_permutations {
-        // Go through every index and find the lowest next_permutation_index value.
-        let mut lowest_next_permutation_index = permutation_count_16_bit;
-
-        for i in &pitch_range.permutations {
-            if let Some(n) = next_subpermutation_index(i) {
-                lowest_next_permutation_index = lowest_next_permutation_index.min(n);
-            }
-        }
-
-        lowest_next_permutation_index
-    }
-    else {
-        permutation_count_16_bit
-    }
-}
-
 fn verify_sound_block_size(sound: &Sound, result: &mut TagResult) {
     let channel_count = match sound.channel_count {
         SoundChannelCount::Mono => 1,
@@ -155,7 +110,7 @@ fn verify_sound_block_size(sound: &Sound, result: &mut TagResult) {
                     let data = permutation.samples.bytes.len();
                     let sample_size = 2 * channel_count;
                     if (data % sample_size) != 0 {
-                        result.errors.push(format!("Permutation #{pe} (`{}`) of pitch range #{pr} has an incorrect size (not divisible by {sample_size})", permutation.name.as_str()));
+                        result.errors.push(Diagnostic::new("SND006", format!("Permutation #{pe} (`{}`) of pitch range #{pr} has an incorrect size (not divisible by {sample_size})", permutation.name.as_str())).with_field(format!("pitch_ranges[{pr}].permutations[{pe}].samples")));
                         continue;
                     }
                 },
@@ -163,7 +118,7 @@ fn verify_sound_block_size(sound: &Sound, result: &mut TagResult) {
                     let block_size = 36 * channel_count;
                     let data = permutation.samples.bytes.len();
                     if (data % block_size) != 0 {
-                        result.errors.push(format!("Permutation #{pe} (`{}`) of pitch range #{pr} has an incorrect size (not divisible by {block_size})", permutation.name.as_str()));
+                        result.errors.push(Diagnostic::new("SND007", format!("Permutation #{pe} (`{}`) of pitch range #{pr} has an incorrect size (not divisible by {block_size})", permutation.name.as_str())).with_field(format!("pitch_ranges[{pr}].permutations[{pe}].samples")));
                         continue;
                     }
                 }
@@ -190,7 +145,7 @@ fn verify_sound_metadata(sound: &Sound, result: &mut TagResult) -> Option<bool>
             let metadata = match SoundPermutationMetadata::read_from_sound_permutation(sound, permutation) {
                 Ok(n) => n,
                 Err(e) => {
-                    result.errors.push(format!("Permutation #{pe} (`{}`) of pitch range #{pr} had an error while querying sound metadata: {e}", permutation.name.as_str()));
+                    result.errors.push(Diagnostic::new("SND008", format!("Permutation #{pe} (`{}`) of pitch range #{pr} had an error while querying sound metadata: {e}", permutation.name.as_str())).with_field(format!("pitch_ranges[{pr}].permutations[{pe}]")));
                     all_metadata_successfully_queried = false;
                     continue;
                 }
@@ -213,15 +168,15 @@ fn verify_sound_metadata(sound: &Sound, result: &mut TagResult) -> Option<bool>
             }
 
             if sound.channel_count != metadata.channel_count {
-                result.errors.push(format!("Permutation #{pe} (`{}`) of pitch range #{pr} has a mismatched channel count (permutation is {}, where sound tag is {})", permutation.name.as_str(), metadata.channel_count, sound.channel_count));
+                result.errors.push(Diagnostic::new("SND009", format!("Permutation #{pe} (`{}`) of pitch range #{pr} has a mismatched channel count (permutation is {}, where sound tag is {})", permutation.name.as_str(), metadata.channel_count, sound.channel_count)).with_field(format!("pitch_ranges[{pr}].permutations[{pe}]")));
             }
 
             if sound.sample_rate != metadata.sample_rate {
-                result.errors.push(format!("Permutation #{pe} (`{}`) of pitch range #{pr} has a mismatched sample rate (permutation is {} Hz, where sound tag is {} Hz)", permutation.name.as_str(), sample_rate_to_u32(metadata.sample_rate), sample_rate_to_u32(sound.sample_rate)));
+                result.errors.push(Diagnostic::new("SND010", format!("Permutation #{pe} (`{}`) of pitch range #{pr} has a mismatched sample rate (permutation is {} Hz, where sound tag is {} Hz)", permutation.name.as_str(), sample_rate_to_u32(metadata.sample_rate), sample_rate_to_u32(sound.sample_rate))).with_field(format!("pitch_ranges[{pr}].permutations[{pe}]")));
             }
 
             if permutation.buffer_size != metadata.buffer_size {
-                result.errors.push(format!("Permutation #{pe} (`{}`) of pitch range #{pr} has a mismatched buffer size (permutation is {}, where sound tag is {})", permutation.name.as_str(), metadata.buffer_size, permutation.buffer_size));
+                result.errors.push(Diagnostic::new("SND011", format!("Permutation #{pe} (`{}`) of pitch range #{pr} has a mismatched buffer size (permutation is {}, where sound tag is {})", permutation.name.as_str(), metadata.buffer_size, permutation.buffer_size)).with_field(format!("pitch_ranges[{pr}].permutations[{pe}].buffer_size")));
             }
         }
     }
@@ -256,7 +211,7 @@ pub(crate) fn verify_sound_formats(sound: &Sound, result: &mut TagResult) -> Opt
             }
 
             if expected != actual {
-                result.errors.push(format!("Permutation #{pe} (`{}`) of pitch range #{pr} has a mismatched sound format (expected {expected}, got {actual} instead)", permutation.name.as_str()));
+                result.errors.push(Diagnostic::new("SND012", format!("Permutation #{pe} (`{}`) of pitch range #{pr} has a mismatched sound format (expected {expected}, got {actual} instead)", permutation.name.as_str())).with_field(format!("pitch_ranges[{pr}].permutations[{pe}].format")));
                 issues_found = true;
             }
         }
@@ -273,14 +228,14 @@ pub(crate) fn verify_sound_formats(sound: &Sound, result: &mut TagResult) -> Opt
 fn verify_sound_permutation_indices(sound: &Sound, result: &mut TagResult) {
     let split_permutations = sound_tag_actually_contains_split_permutations(sound);
 
-    'l: for pitch_range in &sound.pitch_ranges {
+    'l: for (pr, pitch_range) in ziperator!(sound.pitch_ranges) {
         if pitch_range.actual_permutation_count == 0 && pitch_range.permutations.items.is_empty() {
-            result.errors.push("Pitch range has unset actual_permutation_count.".to_owned());
+            result.errors.push(Diagnostic::new("SND013", "Pitch range has unset actual_permutation_count.").with_field(format!("pitch_ranges[{pr}].actual_permutation_count")));
             break 'l;
         }
-        for permutation in &pitch_range.permutations {
+        for (pe, permutation) in ziperator!(pitch_range.permutations) {
             if permutation.next_permutation_index == Some(0) {
-                result.errors.push("Sound tag contains permutations with next_permutation_index set to 0. This is invalid.".to_owned());
+                result.errors.push(Diagnostic::new("SND014", "Sound tag contains permutations with next_permutation_index set to 0. This is invalid.").with_field(format!("pitch_ranges[{pr}].permutations[{pe}].next_permutation_index")));
                 break 'l;
             }
         }
@@ -300,13 +255,13 @@ fn verify_sound_permutation_indices(sound: &Sound, result: &mut TagResult) {
                 let mut permutation_index = ap;
                 loop {
                     if permutation_index >= subpermutation_count {
-                        result.errors.push(format!("Permutation #{ap} (`{}`) of pitch range #{pr} has out-of-bounds permutations", permutation.name.as_str()));
+                        result.errors.push(Diagnostic::new("SND015", format!("Permutation #{ap} (`{}`) of pitch range #{pr} has out-of-bounds permutations", permutation.name.as_str())).with_field(format!("pitch_ranges[{pr}].permutations[{ap}].next_permutation_index")));
                         continue 'permutation_loop;
                     }
 
                     traversed += 1;
                     if traversed > subpermutation_count {
-                        result.errors.push(format!("Permutation #{ap} (`{}`) of pitch range #{pr} infinitely loops", permutation.name.as_str()));
+                        result.errors.push(Diagnostic::new("SND016", format!("Permutation #{ap} (`{}`) of pitch range #{pr} infinitely loops", permutation.name.as_str())).with_field(format!("pitch_ranges[{pr}].permutations[{ap}].next_permutation_index")));
                         continue 'permutation_loop;
                     }
 
@@ -320,7 +275,7 @@ fn verify_sound_permutation_indices(sound: &Sound, result: &mut TagResult) {
 
             let unused_permutations_total = used.into_iter().filter(|&p| p == false).count();
             if unused_permutations_total > 0 {
-                result.warnings.push(format!("Pitch range #{pr} contains {unused_permutations_total} unused subpermutation(s)"));
+                result.warnings.push(Diagnostic::new("SND017", format!("Pitch range #{pr} contains {unused_permutations_total} unused subpermutation(s)")).with_field(format!("pitch_ranges[{pr}].permutations")));
             }
         }
     }
@@ -1,14 +1,14 @@
 use primitives::{primitive::TagPath, tag::PrimaryTagStructDyn};
 use ringhopper_structs::UnicodeStringList;
 use crate::tag::tree::TagTree;
-use super::{ScenarioContext, TagResult};
+use super::{Diagnostic, ScenarioContext, TagResult};
 
 pub fn verify_unicode_string_list<T: TagTree + Send + Sync>(tag: &dyn PrimaryTagStructDyn, _path: &TagPath, _context: &ScenarioContext<T>, result: &mut TagResult) {
     let list: &UnicodeStringList = tag.as_any().downcast_ref().unwrap();
 
     for (i, string) in ziperator!(list.strings) {
         if string.string.get_string_lossy().is_err() {
-            result.errors.push(format!("String #{i} is corrupted"));
+            result.errors.push(Diagnostic::new("USL001", format!("String #{i} is corrupted")).with_field(format!("strings[{i}].string")));
         }
     }
 }